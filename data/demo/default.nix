@@ -0,0 +1,78 @@
+# Demo configuration used by `samba-share --demo`.
+# Changes made in demo mode are written to a temporary copy of this file.
+{ config, pkgs, ... }:
+
+{
+  services.samba = {
+    enable = true;
+    openFirewall = true;
+    settings = {
+      global = {
+        "server min protocol" = "SMB2_10";
+      };
+      "Family Photos" = {
+        path = "/srv/samba/photos";
+        browseable = "yes";
+        "read only" = "no";
+        "guest ok" = "no";
+        "force user" = "alice";
+        "force group" = "users";
+      };
+      "Public" = {
+        path = "/srv/samba/public";
+        browseable = "yes";
+        "read only" = "yes";
+        "guest ok" = "yes";
+        "force user" = "nobody";
+        "force group" = "nogroup";
+      };
+      "Scans" = {
+        path = "/srv/samba/scans";
+        browseable = "yes";
+        "read only" = "no";
+        "guest ok" = "yes";
+        "force user" = "nobody";
+        "force group" = "nogroup";
+        "create mask" = "0666";
+        "directory mask" = "0777";
+      };
+    };
+  };
+
+fileSystems."/mnt/nas/media" = {
+  device = "//nas.local/media";
+  fsType = "cifs";
+  options = [
+    "credentials=/etc/nixos/smb-secrets"
+    "x-systemd.automount"
+    "noauto"
+    "uid=1000"
+    "gid=100"
+  ];
+};
+
+fileSystems."/mnt/nas/backup" = {
+  device = "//nas.local/backup";
+  fsType = "cifs";
+  options = [
+    "credentials=/etc/nixos/smb-secrets"
+    "x-systemd.automount"
+    "noauto"
+    "uid=1000"
+    "gid=100"
+  ];
+};
+
+fileSystems."/mnt/office/projects" = {
+  device = "//fileserver.office/projects";
+  fsType = "cifs";
+  options = [
+    "credentials=/etc/nixos/office-secrets"
+    "x-systemd.automount"
+    "noauto"
+    "uid=1000"
+    "gid=100"
+  ];
+};
+
+}
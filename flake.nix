@@ -30,6 +30,7 @@
               libadwaita
               adwaita-icon-theme
               gtk4
+              gtksourceview5
               librsvg
               pkg-config
               glib
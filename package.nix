@@ -15,6 +15,7 @@
   appstream-glib,
   libadwaita,
   gtk4,
+  gtksourceview5,
   polkit,
   gobject-introspection,
   gsettings-desktop-schemas,
@@ -54,6 +55,7 @@ stdenv.mkDerivation rec {
   buildInputs = [
     gtk4
     libadwaita
+    gtksourceview5
     glib
     librsvg
     polkit
@@ -0,0 +1,125 @@
+use crate::samba::diagnostics::build_report;
+use crate::samba::paths::{CONFIG_ENV, DEFAULT_CONFIG_PATH};
+use crate::samba::report::build_html_report;
+use crate::samba::{list_all_shares, SambaShareConfig};
+use serde::Serialize;
+use std::fs;
+
+/// Option printing the shares as JSON instead of tab separated lines
+const JSON_FLAG: &str = "--json";
+
+/// Commands of the command line frontend. A first argument among them selects it.
+pub const COMMANDS: [&str; 5] = ["list", "remote", "report", "diagnostics", "help"];
+
+/// Run a command and return the exit code of the process
+pub fn run(args: &[String]) -> i32 {
+    let json = args.iter().any(|arg| arg == JSON_FLAG);
+    let args: Vec<String> = args
+        .iter()
+        .filter(|arg| *arg != JSON_FLAG)
+        .cloned()
+        .collect();
+    let result = match args.first().map(String::as_str).unwrap_or("help") {
+        "list" => list_local(json),
+        "remote" => list_remote(json),
+        "report" => report(args.get(1)),
+        "diagnostics" => {
+            print!("{}", build_report());
+            Ok(())
+        }
+        "help" => {
+            print!("{}", usage());
+            Ok(())
+        }
+        other => Err(format!("Unknown command '{}'\n\n{}", other, usage())),
+    };
+
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+fn usage() -> String {
+    format!(
+        "samba-share {}
+
+Usage: samba-share [COMMAND] [--json] [--config FILE]
+
+Without a command, the graphical interface starts when a display is available.
+
+Shares are read from and saved to FILE, or the file set in the {env}
+environment variable, the preferences, then {default}.
+
+Commands:
+  list           List the local shares, with every setting when --json is given
+  remote         List the remote shares and whether they are mounted, as JSON with --json
+  report [FILE]  Write an HTML report of all shares, to FILE or the standard output
+  diagnostics    Print the diagnostics report
+  help           Show this help
+",
+        env!("CARGO_PKG_VERSION"),
+        env = CONFIG_ENV,
+        default = DEFAULT_CONFIG_PATH,
+    )
+}
+
+/// Print `value` as indented JSON
+fn print_json(value: &impl Serialize) -> Result<(), String> {
+    let json =
+        serde_json::to_string_pretty(value).map_err(|e| format!("Failed to encode JSON: {}", e))?;
+    println!("{}", json);
+    Ok(())
+}
+
+fn list_local(json: bool) -> Result<(), String> {
+    let shares = SambaShareConfig::load_all()?;
+    if json {
+        return print_json(&shares);
+    }
+    for share in shares {
+        let mut flags = vec![if share.read_only {
+            "read-only"
+        } else {
+            "writable"
+        }];
+        if share.guest_ok {
+            flags.push("guest");
+        }
+        if !share.browsable {
+            flags.push("hidden");
+        }
+        println!("{}\t{}\t{}", share.name, share.path, flags.join(","));
+    }
+    Ok(())
+}
+
+fn list_remote(json: bool) -> Result<(), String> {
+    let shares = list_all_shares()?;
+    if json {
+        return print_json(&shares);
+    }
+    for share in shares {
+        let status = if share.is_mounted {
+            "mounted"
+        } else {
+            "not-mounted"
+        };
+        println!("{}\t{}\t{}", share.source, share.target, status);
+    }
+    Ok(())
+}
+
+fn report(path: Option<&String>) -> Result<(), String> {
+    let html = build_html_report();
+    match path {
+        Some(path) => fs::write(path, html).map_err(|e| format!("Failed to write {}: {}", path, e)),
+        None => {
+            print!("{}", html);
+            Ok(())
+        }
+    }
+}
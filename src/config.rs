@@ -1,14 +1,74 @@
+use crate::samba::credentials_file;
+use crate::samba::idle_timeout::parse_timespan;
+use crate::samba::login_mounts::{desktop_entry, smb_bookmarks, AUTOSTART_FILE};
+use crate::samba::mount_defaults::{parse_option_list, MountDefaults, SystemdMount};
+use crate::samba::mount_operations::RetryPolicy;
+use crate::samba::neighborhood::DiscoveryScan;
+use crate::samba::root::invoking_user;
+use crate::samba::server_template::ServerTemplate;
+use crate::samba::share_backend::BackendKind;
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::PathBuf;
+use std::os::unix::fs::chown;
+use std::path::{Path, PathBuf};
+
+/// Separator between the favorites stored on one preferences line
+const FAVORITES_SEPARATOR: char = '|';
+
+/// Separator between the share appearances stored on one preferences line
+const APPEARANCES_SEPARATOR: char = '|';
+
+/// Separator between the managed files stored on one preferences line
+const MANAGED_FILES_SEPARATOR: char = '|';
+
+/// A share starred to appear on the main page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Favorite {
+    /// Local share, by name
+    Local(String),
+    /// Remote share, by mount point
+    Remote(String),
+}
+
+impl Favorite {
+    fn to_key(&self) -> String {
+        match self {
+            Self::Local(name) => format!("local:{}", name),
+            Self::Remote(target) => format!("remote:{}", target),
+        }
+    }
+
+    fn from_key(key: &str) -> Option<Self> {
+        let (kind, value) = key.split_once(':')?;
+        match kind {
+            "local" => Some(Self::Local(value.to_string())),
+            "remote" => Some(Self::Remote(value.to_string())),
+            _ => None,
+        }
+    }
+}
+
+/// Icon and color telling a share apart from similar ones, None keeping the defaults
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ShareAppearance {
+    pub icon: Option<String>,
+    pub color: Option<String>,
+}
 
 pub struct AppConfig {
     config_dir: PathBuf,
     config_file: PathBuf,
+    /// Uid and gid to give the files to, when running as root for another user
+    owner: Option<(u32, u32)>,
 }
 
 impl AppConfig {
     pub fn new() -> Self {
-        let config_dir = if let Ok(home) = std::env::var("HOME") {
+        // Under sudo or pkexec, keep the preferences of the user who started the app
+        let user = invoking_user();
+        let config_dir = if let Some(user) = &user {
+            user.home.join(".config/samba-share")
+        } else if let Ok(home) = std::env::var("HOME") {
             PathBuf::from(home).join(".config/samba-share")
         } else {
             PathBuf::from("/tmp/samba-share")
@@ -19,47 +79,446 @@ impl AppConfig {
         Self {
             config_dir,
             config_file,
+            owner: user.map(|user| (user.uid, user.gid)),
         }
     }
 
     pub fn ensure_config_dir(&self) -> std::io::Result<()> {
         if !self.config_dir.exists() {
+            // Directories about to be created, so none of them stays owned by root
+            let missing: Vec<PathBuf> = self
+                .config_dir
+                .ancestors()
+                .take_while(|dir| !dir.exists())
+                .map(Path::to_path_buf)
+                .collect();
             fs::create_dir_all(&self.config_dir)?;
+            for dir in &missing {
+                self.give_to_owner(dir);
+            }
         }
         Ok(())
     }
 
+    /// Hand a created file over to the invoking user when running as root
+    fn give_to_owner(&self, path: &Path) {
+        if let Some((uid, gid)) = self.owner {
+            if let Err(e) = chown(path, Some(uid), Some(gid)) {
+                eprintln!("Failed to change owner of {}: {}", path.display(), e);
+            }
+        }
+    }
+
     pub fn should_show_welcome(&self) -> bool {
-        // If file doesn't exist or can't be read, show welcome (default)
-        if !self.config_file.exists() {
-            return true;
+        // If the key is missing or the file can't be read, show welcome (default)
+        self.get("hide_welcome").as_deref() != Some("true")
+    }
+
+    pub fn set_hide_welcome(&self, hide: bool) {
+        self.set("hide_welcome", if hide { "true" } else { "false" });
+    }
+
+    /// Whether the summary of the existing configuration hasn't been shown yet
+    pub fn should_show_config_audit(&self) -> bool {
+        self.get("config_audit_shown").as_deref() != Some("true")
+    }
+
+    pub fn set_config_audit_shown(&self) {
+        self.set("config_audit_shown", "true");
+    }
+
+    /// Command template used to open a terminal for rebuilds, `None` to detect one.
+    /// `{script}` is replaced by the script to run.
+    pub fn terminal_command(&self) -> Option<String> {
+        self.get("terminal").filter(|command| !command.is_empty())
+    }
+
+    pub fn set_terminal_command(&self, command: Option<&str>) {
+        self.set("terminal", command.unwrap_or(""));
+    }
+
+    /// Whether the app keeps running in the background once its window is closed
+    pub fn run_in_background(&self) -> bool {
+        self.get("background").as_deref() == Some("true")
+    }
+
+    pub fn set_run_in_background(&self, enabled: bool) {
+        self.set("background", if enabled { "true" } else { "false" });
+    }
+
+    /// Folder the credentials files created by the app are written to
+    pub fn credentials_folder(&self) -> String {
+        self.get("credentials_folder")
+            .filter(|folder| !folder.is_empty())
+            .unwrap_or_else(|| credentials_file::DEFAULT_FOLDER.to_string())
+    }
+
+    pub fn set_credentials_folder(&self, folder: &str) {
+        self.set("credentials_folder", folder);
+    }
+
+    /// NixOS configuration file chosen in the preferences, None for the default one.
+    /// The command line option and the environment variable take precedence.
+    pub fn nixos_config_path(&self) -> Option<String> {
+        self.get("nixos_config").filter(|path| !path.is_empty())
+    }
+
+    pub fn set_nixos_config_path(&self, path: Option<&str>) {
+        self.set("nixos_config", path.unwrap_or(""));
+    }
+
+    /// Files imported by the NixOS configuration whose shares the app may rewrite,
+    /// besides the main file
+    pub fn managed_files(&self) -> Vec<String> {
+        self.get("managed_files")
+            .unwrap_or_default()
+            .split(MANAGED_FILES_SEPARATOR)
+            .filter(|file| !file.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    pub fn set_managed_files(&self, files: &[String]) {
+        self.set("managed_files", &files.join(&MANAGED_FILES_SEPARATOR.to_string()));
+    }
+
+    /// Where local shares are saved, None to detect it from the system
+    pub fn share_backend(&self) -> Option<BackendKind> {
+        self.get("share_backend").and_then(|key| BackendKind::from_key(&key))
+    }
+
+    pub fn set_share_backend(&self, kind: Option<BackendKind>) {
+        self.set("share_backend", kind.map(BackendKind::to_key).unwrap_or(""));
+    }
+
+    /// Mount settings new remote shares start from.
+    /// Timeouts are stored in seconds, 0 standing for none.
+    pub fn mount_defaults(&self) -> MountDefaults {
+        let defaults = MountDefaults::default();
+        let flag = |key: &str, default: bool| {
+            self.get(key).map(|value| value == "true").unwrap_or(default)
+        };
+        let seconds = |key: &str, default: Option<u32>| {
+            self.get(key).map(|value| parse_timespan(&value)).unwrap_or(default)
+        };
+
+        MountDefaults {
+            systemd: SystemdMount {
+                automount: flag("mount_automount", defaults.systemd.automount),
+                noauto: flag("mount_noauto", defaults.systemd.noauto),
+                device_timeout: seconds("mount_device_timeout", defaults.systemd.device_timeout),
+                mount_timeout: seconds("mount_mount_timeout", defaults.systemd.mount_timeout),
+            },
+            idle_timeout: seconds("mount_idle_timeout", defaults.idle_timeout),
+            extra_options: self
+                .get("mount_options")
+                .map(|value| parse_option_list(&value))
+                .unwrap_or(defaults.extra_options),
         }
+    }
+
+    pub fn set_mount_defaults(&self, defaults: &MountDefaults) {
+        let flag = |value: bool| if value { "true" } else { "false" };
+        let seconds = |value: Option<u32>| value.unwrap_or(0).to_string();
+
+        self.set("mount_automount", flag(defaults.systemd.automount));
+        self.set("mount_noauto", flag(defaults.systemd.noauto));
+        self.set("mount_device_timeout", &seconds(defaults.systemd.device_timeout));
+        self.set("mount_mount_timeout", &seconds(defaults.systemd.mount_timeout));
+        self.set("mount_idle_timeout", &seconds(defaults.idle_timeout));
+        self.set("mount_options", &defaults.extra_options.join(","));
+    }
+
+    /// How mounts from the app retry a server that doesn't answer, delays in seconds
+    pub fn mount_retry(&self) -> RetryPolicy {
+        let defaults = RetryPolicy::default();
+        let number = |key: &str, default: u32| {
+            self.get(key).and_then(|value| value.parse().ok()).unwrap_or(default)
+        };
 
-        match fs::read_to_string(&self.config_file) {
-            Ok(content) => {
-                // Look for "hide_welcome=true" line
-                !content
-                    .lines()
-                    .any(|line| line.trim() == "hide_welcome=true")
+        RetryPolicy {
+            attempts: number("mount_attempts", defaults.attempts).max(1),
+            initial_delay: number("mount_retry_delay", defaults.initial_delay),
+            timeout: number("mount_attempt_timeout", defaults.timeout),
+        }
+    }
+
+    pub fn set_mount_retry(&self, retry: &RetryPolicy) {
+        self.set("mount_attempts", &retry.attempts.to_string());
+        self.set("mount_retry_delay", &retry.initial_delay.to_string());
+        self.set("mount_attempt_timeout", &retry.timeout.to_string());
+    }
+
+    /// Scans looking for servers on the network besides mDNS
+    pub fn discovery_scan(&self) -> DiscoveryScan {
+        let defaults = DiscoveryScan::default();
+        let flag = |key: &str, default: bool| {
+            self.get(key).map(|value| value == "true").unwrap_or(default)
+        };
+
+        DiscoveryScan {
+            netbios: flag("discovery_netbios", defaults.netbios),
+            ws_discovery: flag("discovery_ws_discovery", defaults.ws_discovery),
+        }
+    }
+
+    pub fn set_discovery_scan(&self, scan: &DiscoveryScan) {
+        let flag = |value: bool| if value { "true" } else { "false" };
+        self.set("discovery_netbios", flag(scan.netbios));
+        self.set("discovery_ws_discovery", flag(scan.ws_discovery));
+    }
+
+    /// Server settings written when the first share creates the Samba configuration,
+    /// detected from this computer until they are changed in the preferences
+    pub fn server_template(&self) -> ServerTemplate {
+        let Some(workgroup) = self.get("server_workgroup") else {
+            return ServerTemplate::detect();
+        };
+        ServerTemplate {
+            workgroup,
+            server_name: self.get("server_name").unwrap_or_default(),
+            hosts_allow: self
+                .get("server_hosts_allow")
+                .map(|value| value.split_whitespace().map(|host| host.to_string()).collect())
+                .unwrap_or_default(),
+            map_to_guest: self.get("server_map_to_guest").map(|value| value == "true").unwrap_or(true),
+        }
+    }
+
+    pub fn set_server_template(&self, template: &ServerTemplate) {
+        self.set("server_workgroup", &template.workgroup);
+        self.set("server_name", &template.server_name);
+        self.set("server_hosts_allow", &template.hosts_allow.join(" "));
+        self.set("server_map_to_guest", if template.map_to_guest { "true" } else { "false" });
+    }
+
+    /// GVfs locations mounted at login by the autostart entry
+    pub fn login_mounts(&self) -> Vec<String> {
+        self.get("login_mounts")
+            .unwrap_or_default()
+            .split_whitespace()
+            .map(|uri| uri.to_string())
+            .collect()
+    }
+
+    /// Remember the locations to mount at login, then install the autostart entry
+    /// mounting them, or remove it when there are none
+    pub fn set_login_mounts(&self, uris: &[String]) -> std::io::Result<()> {
+        self.set("login_mounts", &uris.join(" "));
+
+        let autostart_dir = self.user_config_dir().join("autostart");
+        let autostart_file = autostart_dir.join(AUTOSTART_FILE);
+        if uris.is_empty() {
+            if autostart_file.exists() {
+                fs::remove_file(&autostart_file)?;
             }
-            Err(_) => true, // Default to showing welcome on error
+            return Ok(());
         }
+
+        if !autostart_dir.exists() {
+            fs::create_dir_all(&autostart_dir)?;
+            self.give_to_owner(&autostart_dir);
+        }
+        fs::write(&autostart_file, desktop_entry(uris))?;
+        self.give_to_owner(&autostart_file);
+        Ok(())
     }
 
-    pub fn set_hide_welcome(&self, hide: bool) {
+    /// SMB locations bookmarked in the file manager, candidates for mounting at login
+    pub fn smb_bookmarks(&self) -> Vec<String> {
+        let bookmarks = self.user_config_dir().join("gtk-3.0/bookmarks");
+        smb_bookmarks(&fs::read_to_string(bookmarks).unwrap_or_default())
+    }
+
+    /// ~/.config, holding the app's own directory
+    fn user_config_dir(&self) -> PathBuf {
+        self.config_dir
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| self.config_dir.clone())
+    }
+
+    /// Starred shares, in the order they were added
+    pub fn favorites(&self) -> Vec<Favorite> {
+        self.get("favorites")
+            .unwrap_or_default()
+            .split(FAVORITES_SEPARATOR)
+            .filter_map(Favorite::from_key)
+            .collect()
+    }
+
+    pub fn set_favorite(&self, favorite: &Favorite, starred: bool) {
+        let mut favorites = self.favorites();
+        favorites.retain(|existing| existing != favorite);
+        if starred {
+            favorites.push(favorite.clone());
+        }
+
+        let keys: Vec<String> = favorites.iter().map(Favorite::to_key).collect();
+        self.set("favorites", &keys.join(&FAVORITES_SEPARATOR.to_string()));
+    }
+
+    /// Icon and color chosen for a share, keyed like the favorites
+    pub fn share_appearance(&self, share: &Favorite) -> ShareAppearance {
+        let key = share.to_key();
+        self.share_appearances()
+            .into_iter()
+            .find(|(known, _)| *known == key)
+            .map(|(_, appearance)| appearance)
+            .unwrap_or_default()
+    }
+
+    pub fn set_share_appearance(&self, share: &Favorite, appearance: &ShareAppearance) {
+        let key = share.to_key();
+        let mut appearances = self.share_appearances();
+        appearances.retain(|(known, _)| *known != key);
+        if *appearance != ShareAppearance::default() {
+            appearances.push((key, appearance.clone()));
+        }
+
+        let entries: Vec<String> = appearances
+            .iter()
+            .map(|(key, appearance)| {
+                format!(
+                    "{}={},{}",
+                    key,
+                    appearance.icon.as_deref().unwrap_or_default(),
+                    appearance.color.as_deref().unwrap_or_default()
+                )
+            })
+            .collect();
+        self.set("share_appearances", &entries.join(&APPEARANCES_SEPARATOR.to_string()));
+    }
+
+    /// Shares with their appearance, stored as `local:docs=icon,color` entries.
+    /// Icons and colors never hold `=`, unlike the mount points of remote shares.
+    fn share_appearances(&self) -> Vec<(String, ShareAppearance)> {
+        let optional = |value: &str| Some(value.to_string()).filter(|value| !value.is_empty());
+        self.get("share_appearances")
+            .unwrap_or_default()
+            .split(APPEARANCES_SEPARATOR)
+            .filter_map(|entry| entry.rsplit_once('='))
+            .filter_map(|(key, value)| {
+                let (icon, color) = value.split_once(',')?;
+                let appearance = ShareAppearance {
+                    icon: optional(icon),
+                    color: optional(color),
+                };
+                Some((key.to_string(), appearance))
+            })
+            .collect()
+    }
+
+    /// Note written about a share, empty when there is none
+    pub fn share_note(&self, share: &Favorite) -> String {
+        self.share_notes().remove(&share.to_key()).unwrap_or_default()
+    }
+
+    pub fn set_share_note(&self, share: &Favorite, note: &str) {
+        let mut notes = self.share_notes();
+        let note = note.trim();
+        if note.is_empty() {
+            notes.remove(&share.to_key());
+        } else {
+            notes.insert(share.to_key(), note.to_string());
+        }
+
         if let Err(e) = self.ensure_config_dir() {
             eprintln!("Failed to create config directory: {}", e);
             return;
         }
-
-        let content = if hide {
-            "hide_welcome=true\n"
-        } else {
-            "hide_welcome=false\n"
+        let notes_file = self.notes_file();
+        let content = match serde_json::to_string_pretty(&notes) {
+            Ok(content) => content,
+            Err(e) => {
+                eprintln!("Failed to encode share notes: {}", e);
+                return;
+            }
         };
+        if let Err(e) = fs::write(&notes_file, content + "\n") {
+            eprintln!("Failed to write share notes: {}", e);
+            return;
+        }
+        self.give_to_owner(&notes_file);
+    }
+
+    /// Notes by share, keyed like the favorites. They hold any text, separators
+    /// included, so they live in their own JSON file rather than the preferences.
+    fn share_notes(&self) -> BTreeMap<String, String> {
+        fs::read_to_string(self.notes_file())
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    fn notes_file(&self) -> PathBuf {
+        self.config_dir.join("notes.json")
+    }
+
+    /// Hardware address learned for a server while it was online, to wake it up later
+    pub fn server_mac_address(&self, server: &str) -> Option<String> {
+        self.server_mac_addresses()
+            .into_iter()
+            .find(|(known, _)| known.eq_ignore_ascii_case(server))
+            .map(|(_, mac)| mac)
+    }
 
-        if let Err(e) = fs::write(&self.config_file, content) {
+    pub fn set_server_mac_address(&self, server: &str, mac_address: &str) {
+        let mut addresses = self.server_mac_addresses();
+        if addresses
+            .iter()
+            .any(|(known, mac)| known.eq_ignore_ascii_case(server) && mac == mac_address)
+        {
+            return;
+        }
+        addresses.retain(|(known, _)| !known.eq_ignore_ascii_case(server));
+        addresses.push((server.to_string(), mac_address.to_string()));
+
+        let entries: Vec<String> = addresses
+            .iter()
+            .map(|(server, mac)| format!("{} {}", server, mac))
+            .collect();
+        self.set("server_mac_addresses", &entries.join(","));
+    }
+
+    /// Servers with their hardware address, stored as comma separated `server mac` pairs
+    fn server_mac_addresses(&self) -> Vec<(String, String)> {
+        self.get("server_mac_addresses")
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|entry| entry.split_once(' '))
+            .map(|(server, mac)| (server.to_string(), mac.to_string()))
+            .collect()
+    }
+
+    /// Value of a `key=value` line of the preferences file
+    fn get(&self, key: &str) -> Option<String> {
+        let content = fs::read_to_string(&self.config_file).ok()?;
+        content.lines().find_map(|line| {
+            let (line_key, value) = line.split_once('=')?;
+            (line_key.trim() == key).then(|| value.trim().to_string())
+        })
+    }
+
+    /// Set one `key=value` line, keeping the other preferences
+    fn set(&self, key: &str, value: &str) {
+        if let Err(e) = self.ensure_config_dir() {
+            eprintln!("Failed to create config directory: {}", e);
+            return;
+        }
+
+        let mut lines: Vec<String> = fs::read_to_string(&self.config_file)
+            .unwrap_or_default()
+            .lines()
+            .filter(|line| line.split_once('=').map(|(k, _)| k.trim()) != Some(key))
+            .map(|line| line.to_string())
+            .collect();
+        lines.push(format!("{}={}", key, value));
+
+        if let Err(e) = fs::write(&self.config_file, lines.join("\n") + "\n") {
             eprintln!("Failed to write config file: {}", e);
+            return;
         }
+        self.give_to_owner(&self.config_file);
     }
 }
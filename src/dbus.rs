@@ -0,0 +1,116 @@
+use crate::samba::report::build_html_report;
+use crate::samba::{list_all_shares, SambaShareConfig};
+use gio::prelude::*;
+use serde::Serialize;
+
+/// Argument starting the D-Bus service instead of another frontend
+pub const DBUS_FLAG: &str = "--dbus";
+
+const BUS_NAME: &str = "org.dupot.sambasharemanager.Service";
+const OBJECT_PATH: &str = "/org/dupot/sambasharemanager";
+const INTERFACE: &str = "org.dupot.sambasharemanager.Shares";
+const ERROR_FAILED: &str = "org.dupot.sambasharemanager.Error.Failed";
+
+const INTROSPECTION: &str = r#"<node>
+  <interface name="org.dupot.sambasharemanager.Shares">
+    <method name="ListLocalShares">
+      <arg type="a(ssb)" name="shares" direction="out"/>
+    </method>
+    <method name="ListRemoteShares">
+      <arg type="a(ssb)" name="shares" direction="out"/>
+    </method>
+    <method name="LocalSharesJson">
+      <arg type="s" name="json" direction="out"/>
+    </method>
+    <method name="RemoteSharesJson">
+      <arg type="s" name="json" direction="out"/>
+    </method>
+    <method name="Report">
+      <arg type="s" name="html" direction="out"/>
+    </method>
+  </interface>
+</node>"#;
+
+/// Serve the shares on the session bus, to the processes of the same user only. The
+/// system bus would need a bus policy and an authorization check first, as any local
+/// user could call the methods there. Returns the exit code once the bus name is lost.
+pub fn run() -> i32 {
+    let interface = match gio::DBusNodeInfo::for_xml(INTROSPECTION) {
+        Ok(node) => node
+            .lookup_interface(INTERFACE)
+            .expect("interface in introspection data"),
+        Err(e) => {
+            eprintln!("Invalid introspection data: {}", e);
+            return 1;
+        }
+    };
+    let main_loop = glib::MainLoop::new(None, false);
+    let main_loop_clone = main_loop.clone();
+    let owner = gio::bus_own_name(
+        gio::BusType::Session,
+        BUS_NAME,
+        gio::BusNameOwnerFlags::NONE,
+        move |connection, _| {
+            let registered = connection
+                .register_object(OBJECT_PATH, &interface)
+                .method_call(handle_method_call)
+                .build();
+            if let Err(e) = registered {
+                eprintln!("Failed to register {}: {}", OBJECT_PATH, e);
+            }
+        },
+        |_, name| eprintln!("D-Bus service running as {}", name),
+        move |_, name| {
+            eprintln!("Lost the D-Bus name {}", name);
+            main_loop_clone.quit();
+        },
+    );
+
+    main_loop.run();
+    gio::bus_unown_name(owner);
+    1
+}
+
+/// Answer a method call of the Shares interface
+fn handle_method_call(
+    _connection: gio::DBusConnection,
+    _sender: Option<&str>,
+    _object_path: &str,
+    _interface: Option<&str>,
+    method: &str,
+    _parameters: glib::Variant,
+    invocation: gio::DBusMethodInvocation,
+) {
+    let result = match method {
+        "ListLocalShares" => SambaShareConfig::load_all().map(|shares| {
+            let shares: Vec<(String, String, bool)> = shares
+                .into_iter()
+                .map(|share| (share.name, share.path, share.read_only))
+                .collect();
+            (shares,).to_variant()
+        }),
+        "ListRemoteShares" => list_all_shares().map(|shares| {
+            let shares: Vec<(String, String, bool)> = shares
+                .into_iter()
+                .map(|share| (share.source, share.target, share.is_mounted))
+                .collect();
+            (shares,).to_variant()
+        }),
+        "LocalSharesJson" => SambaShareConfig::load_all().and_then(|shares| json_variant(&shares)),
+        "RemoteSharesJson" => list_all_shares().and_then(|shares| json_variant(&shares)),
+        "Report" => Ok((build_html_report(),).to_variant()),
+        other => Err(format!("Unknown method {}", other)),
+    };
+
+    match result {
+        Ok(value) => invocation.return_value(Some(&value)),
+        Err(e) => invocation.return_dbus_error(ERROR_FAILED, &e),
+    }
+}
+
+/// Reply holding `value` as JSON, encoded like the command line's --json output
+fn json_variant(value: &impl Serialize) -> Result<glib::Variant, String> {
+    serde_json::to_string(value)
+        .map(|json| (json,).to_variant())
+        .map_err(|e| format!("Failed to encode JSON: {}", e))
+}
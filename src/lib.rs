@@ -0,0 +1,6 @@
+// Core logic of Samba Share Manager: reading and editing the NixOS configuration,
+// mounting remote shares and checking the system. It doesn't depend on GTK, so
+// other frontends and the tests can use it; the GTK interface lives in main.rs.
+pub mod config;
+pub mod samba;
+pub mod utils;
@@ -1,36 +1,192 @@
-mod config;
+#[cfg(feature = "cli")]
+mod cli;
+#[cfg(feature = "dbus")]
+mod dbus;
+#[cfg(feature = "gui")]
 mod models;
-mod samba;
+#[cfg(feature = "gui")]
 mod ui;
-mod utils;
 
+// The frontends refer to the core modules as crate::samba, crate::config...
+#[allow(unused_imports)]
+use samba_share::{config, samba, utils};
 
 use anyhow::Result;
-use gettextrs::{bind_textdomain_codeset, bindtextdomain, setlocale, textdomain, LocaleCategory};
+use gettextrs::{bind_textdomain_codeset, bindtextdomain, textdomain};
+#[cfg(feature = "gui")]
 use libadwaita as adw;
 use std::env;
 
+#[cfg(feature = "gui")]
 use ui::app::SambaShareManagerApp;
 
+/// Interface the process runs, depending on the enabled features and its arguments
+enum Frontend {
+    #[cfg(feature = "gui")]
+    Gui,
+    #[cfg(feature = "cli")]
+    Cli(Vec<String>),
+    #[cfg(feature = "dbus")]
+    Dbus,
+}
+
 fn main() -> Result<()> {
-    // Initialize GTK
-    gtk4::init()?;
-    adw::init()?;
+    // Started by pkexec to mount or unmount, before anything reads the user's environment
+    if env::args().nth(1).as_deref() == Some(samba::libmount::HELPER_FLAG) {
+        std::process::exit(samba::libmount::run_helper());
+    }
 
     // Setup internationalization
     setup_i18n()?;
 
-    // Get locale from system
-    let locale = get_system_locale();
-    setlocale(LocaleCategory::LcAll, locale);
+    let frontend = select_frontend()?;
+
+    #[cfg(feature = "gui")]
+    if matches!(frontend, Frontend::Gui) {
+        // File and folder pickers go through xdg-desktop-portal even outside a sandbox,
+        // GTK falls back to its own dialog when no portal is reachable (e.g. under pkexec)
+        prefer_portals();
+
+        // Initialize GTK
+        gtk4::init()?;
+        adw::init()?;
+        sourceview5::init();
+    }
 
-    // Create and run the application
-    let app = SambaShareManagerApp::new();
-    let exit_code = app.run();
+    // Apply the locale from LC_ALL, LC_MESSAGES or LANG
+    let locale = utils::locale::setup_locale();
+    eprintln!("Locale: {}", locale);
+
+    // Demo mode works on a temporary copy of a bundled configuration
+    if env::args().any(|arg| arg == samba::demo::DEMO_FLAG) {
+        match samba::demo::enable() {
+            Ok(path) => eprintln!("Demo mode: configuration copied to {}", path.display()),
+            Err(e) => {
+                eprintln!("Failed to start demo mode: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // The demo configuration is a NixOS one, whatever the system
+    if !samba::demo::is_enabled() {
+        select_config_path();
+        let backend = config::AppConfig::new()
+            .share_backend()
+            .unwrap_or_else(samba::share_backend::BackendKind::detect);
+        eprintln!("Share backend: {}", backend.to_key());
+        samba::share_backend::set_backend(backend);
+    }
+
+    // GTK running as root misses the session's settings, theme and portals
+    #[cfg(feature = "gui")]
+    if samba::root::is_root() && matches!(frontend, Frontend::Gui) {
+        eprintln!("Running as root: privileged commands run directly, but the desktop theme and settings may not apply");
+    }
+
+    // Inside Flatpak, system commands have to be spawned on the host
+    if !samba::demo::is_enabled() && samba::sandbox::is_sandboxed() {
+        eprintln!("Running inside a Flatpak sandbox, commands are spawned on the host");
+        samba::command_runner::set_runner(Box::new(samba::command_runner::FlatpakRunner));
+    }
+
+    let exit_code = match frontend {
+        // Create and run the application
+        #[cfg(feature = "gui")]
+        Frontend::Gui => SambaShareManagerApp::new().run(),
+        #[cfg(feature = "cli")]
+        Frontend::Cli(args) => cli::run(&args),
+        #[cfg(feature = "dbus")]
+        Frontend::Dbus => dbus::run(),
+    };
 
     std::process::exit(exit_code);
 }
 
+/// Pick the frontend: `--dbus` starts the service, a known command runs the
+/// command line, otherwise the interface opens when a display is available
+fn select_frontend() -> Result<Frontend> {
+    let args: Vec<String> = env::args()
+        .skip(1)
+        .filter(|arg| arg != samba::demo::DEMO_FLAG)
+        .collect();
+    let (_, args) = samba::paths::split_config_flag(&args);
+
+    #[cfg(feature = "dbus")]
+    if args.iter().any(|arg| arg == dbus::DBUS_FLAG) {
+        return Ok(Frontend::Dbus);
+    }
+
+    #[cfg(feature = "cli")]
+    if args.first().is_some_and(|arg| cli::COMMANDS.contains(&arg.as_str())) {
+        return Ok(Frontend::Cli(args));
+    }
+
+    #[cfg(feature = "gui")]
+    if env::var_os("WAYLAND_DISPLAY").is_some() || env::var_os("DISPLAY").is_some() {
+        return Ok(Frontend::Gui);
+    }
+
+    fallback_frontend()
+}
+
+/// Edit the configuration file given on the command line, in the environment or in
+/// the preferences, in that order, the default one being used otherwise
+fn select_config_path() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let (flag, _) = samba::paths::split_config_flag(&args);
+    let chosen = flag
+        .or_else(|| env::var(samba::paths::CONFIG_ENV).ok().filter(|path| !path.is_empty()))
+        .or_else(|| config::AppConfig::new().nixos_config_path());
+    let Some(path) = chosen else {
+        return;
+    };
+
+    let current_dir = env::current_dir().unwrap_or_else(|_| "/".into());
+    let path = samba::paths::absolute_path(&path, &current_dir);
+    if !std::path::Path::new(&path).is_file() {
+        eprintln!("Configuration file {} not found", path);
+    }
+    eprintln!("Configuration file: {}", path);
+    samba::paths::set_config_path(&path);
+}
+
+/// Without a display, the command line shows its help
+#[cfg(feature = "cli")]
+fn fallback_frontend() -> Result<Frontend> {
+    Ok(Frontend::Cli(vec!["help".to_string()]))
+}
+
+/// Without the command line, GTK reports the missing display itself
+#[cfg(all(feature = "gui", not(feature = "cli")))]
+fn fallback_frontend() -> Result<Frontend> {
+    Ok(Frontend::Gui)
+}
+
+/// With the D-Bus service alone, it has to be asked for
+#[cfg(all(feature = "dbus", not(any(feature = "gui", feature = "cli"))))]
+fn fallback_frontend() -> Result<Frontend> {
+    Err(anyhow::anyhow!("Start the D-Bus service with {}", dbus::DBUS_FLAG))
+}
+
+#[cfg(not(any(feature = "gui", feature = "cli", feature = "dbus")))]
+fn fallback_frontend() -> Result<Frontend> {
+    Err(anyhow::anyhow!("Built without any frontend, enable the gui, cli or dbus feature"))
+}
+
+#[cfg(feature = "gui")]
+fn prefer_portals() {
+    let debug = env::var("GDK_DEBUG").unwrap_or_default();
+    if !debug.split(',').any(|flag| flag == "portals") {
+        let flags = if debug.is_empty() {
+            "portals".to_string()
+        } else {
+            format!("{},portals", debug)
+        };
+        env::set_var("GDK_DEBUG", flags);
+    }
+}
+
 fn setup_i18n() -> Result<()> {
     // These paths will be set by the build system
     let locale_dir = option_env!("LOCALE_DIR").unwrap_or("/usr/share/locale");
@@ -41,14 +197,3 @@ fn setup_i18n() -> Result<()> {
 
     Ok(())
 }
-
-fn get_system_locale() -> &'static str {
-    // Try to get locale from environment variables
-    if let Ok(locale) = env::var("LANG") {
-        Box::leak(locale.into_boxed_str())
-    } else if let Ok(locale) = env::var("LC_ALL") {
-        Box::leak(locale.into_boxed_str())
-    } else {
-        "C.UTF-8"
-    }
-}
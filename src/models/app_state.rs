@@ -0,0 +1,316 @@
+use crate::config::{AppConfig, Favorite, ShareAppearance};
+use crate::samba::nix_parse::{with_progress, LoadProgress};
+use crate::samba::paths::config_path;
+use crate::samba::sudo_write::{can_write, run_privileged};
+use crate::samba::{list_all_shares, ConfigChange, MountedShare, SambaShareConfig};
+use glib::prelude::*;
+use glib::subclass::prelude::*;
+use gtk4::prelude::*;
+use libadwaita as adw;
+use libadwaita::prelude::*;
+use std::cell::Cell;
+use std::sync::Arc;
+
+/// Result of loading local shares, shared by every page showing them
+pub type LocalShares = Result<Vec<SambaShareConfig>, String>;
+
+/// Result of loading remote shares with their mount status
+pub type RemoteShares = Result<Vec<MountedShare>, String>;
+
+mod imp {
+    use super::*;
+    use glib::subclass::Signal;
+    use std::cell::RefCell;
+    use std::sync::OnceLock;
+
+    #[derive(Default)]
+    pub struct AppState {
+        pub local_shares: RefCell<Option<LocalShares>>,
+        pub remote_shares: RefCell<Option<RemoteShares>>,
+        pub pending_changes: RefCell<Vec<ConfigChange>>,
+        /// Mounted shares whose options change with the next rebuild
+        pub pending_remounts: RefCell<Vec<String>>,
+        pub loading_local: Cell<bool>,
+        pub loading_remote: Cell<bool>,
+        /// Progress of the running loads, kept once they finish
+        pub local_progress: RefCell<Arc<LoadProgress>>,
+        pub remote_progress: RefCell<Arc<LoadProgress>>,
+        pub read_only: Cell<bool>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for AppState {
+        const NAME: &'static str = "SambaShareAppState";
+        type Type = super::AppState;
+    }
+
+    impl ObjectImpl for AppState {
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: OnceLock<Vec<Signal>> = OnceLock::new();
+            SIGNALS.get_or_init(|| {
+                vec![
+                    Signal::builder("local-shares-changed").build(),
+                    Signal::builder("remote-shares-changed").build(),
+                    Signal::builder("pending-changes-changed").build(),
+                    Signal::builder("read-only-changed").build(),
+                    Signal::builder("favorites-changed").build(),
+                    Signal::builder("appearances-changed").build(),
+                ]
+            })
+        }
+    }
+}
+
+glib::wrapper! {
+    /// Shares, mounts and configuration changes known to the app.
+    /// Pages read from here and listen to the `*-changed` signals instead of
+    /// loading on their own, so an edit anywhere shows up everywhere.
+    pub struct AppState(ObjectSubclass<imp::AppState>);
+}
+
+thread_local! {
+    static STATE: AppState = glib::Object::new();
+}
+
+impl AppState {
+    /// The state shared by the whole application
+    pub fn get() -> Self {
+        STATE.with(|state| state.clone())
+    }
+
+    /// Last loaded local shares, `None` until the first load finished
+    pub fn local_shares(&self) -> Option<LocalShares> {
+        self.imp().local_shares.borrow().clone()
+    }
+
+    /// Last loaded remote shares, `None` until the first load finished
+    pub fn remote_shares(&self) -> Option<RemoteShares> {
+        self.imp().remote_shares.borrow().clone()
+    }
+
+    /// Changes written to the configuration since the last rebuild
+    pub fn pending_changes(&self) -> Vec<ConfigChange> {
+        self.imp().pending_changes.borrow().clone()
+    }
+
+    /// Whether a load started by `reload*` is still running
+    pub fn is_loading(&self) -> bool {
+        self.imp().loading_local.get() || self.imp().loading_remote.get()
+    }
+
+    /// Files parsed by the last local load, which `LoadProgress::cancel` stops
+    pub fn local_progress(&self) -> Arc<LoadProgress> {
+        self.imp().local_progress.borrow().clone()
+    }
+
+    /// Files parsed by the last remote load, which `LoadProgress::cancel` stops
+    pub fn remote_progress(&self) -> Arc<LoadProgress> {
+        self.imp().remote_progress.borrow().clone()
+    }
+
+    /// Whether the configuration can only be inspected, because no way to
+    /// write it with elevated privileges was found
+    pub fn is_read_only(&self) -> bool {
+        self.imp().read_only.get()
+    }
+
+    fn set_read_only(&self, read_only: bool) {
+        if self.imp().read_only.replace(read_only) != read_only {
+            self.emit_by_name::<()>("read-only-changed", &[]);
+        }
+    }
+
+    /// Check in the background whether the configuration can be written,
+    /// then switch in or out of read-only mode
+    pub fn check_write_access(&self) {
+        let state = self.clone();
+        glib::spawn_future_local(async move {
+            let writable = gio::spawn_blocking(|| can_write(config_path()))
+                .await
+                .unwrap_or(false);
+            state.set_read_only(!writable);
+        });
+    }
+
+    /// Ask for authentication once, leaving read-only mode when it works.
+    /// `on_done` gets the error when it didn't.
+    pub fn retry_write_access<F: Fn(Result<(), String>) + 'static>(&self, on_done: F) {
+        let state = self.clone();
+        glib::spawn_future_local(async move {
+            let result = gio::spawn_blocking(|| run_privileged("true", &[], None).map(|_| ()))
+                .await
+                .unwrap_or_else(|e| Err(format!("{:?}", e)));
+            if result.is_ok() {
+                state.set_read_only(false);
+            }
+            on_done(result);
+        });
+    }
+
+    /// Star or unstar a share, then emit `favorites-changed`
+    pub fn set_favorite(&self, favorite: &Favorite, starred: bool) {
+        AppConfig::new().set_favorite(favorite, starred);
+        self.emit_by_name::<()>("favorites-changed", &[]);
+    }
+
+    /// Give a share its own icon and color, then emit `appearances-changed`
+    pub fn set_share_appearance(&self, share: &Favorite, appearance: &ShareAppearance) {
+        AppConfig::new().set_share_appearance(share, appearance);
+        self.emit_by_name::<()>("appearances-changed", &[]);
+    }
+
+    /// Reload everything in the background
+    pub fn reload(&self) {
+        self.reload_local();
+        self.reload_remote();
+    }
+
+    /// Parse the local shares again in the background, then emit `local-shares-changed`.
+    /// Ignored while a load is already running.
+    pub fn reload_local(&self) {
+        if self.imp().loading_local.replace(true) {
+            return;
+        }
+
+        let progress = Arc::new(LoadProgress::default());
+        self.imp().local_progress.replace(progress.clone());
+
+        let state = self.clone();
+        glib::spawn_future_local(async move {
+            let result = gio::spawn_blocking(move || with_progress(progress, SambaShareConfig::load_all))
+                .await
+                .unwrap_or_else(|e| Err(format!("{:?}", e)));
+
+            state.imp().local_shares.replace(Some(result));
+            state.imp().loading_local.set(false);
+            state.emit_by_name::<()>("local-shares-changed", &[]);
+        });
+    }
+
+    /// List the remote shares and mounts again in the background, then emit
+    /// `remote-shares-changed`. Ignored while a load is already running.
+    pub fn reload_remote(&self) {
+        if self.imp().loading_remote.replace(true) {
+            return;
+        }
+
+        let progress = Arc::new(LoadProgress::default());
+        self.imp().remote_progress.replace(progress.clone());
+
+        let state = self.clone();
+        glib::spawn_future_local(async move {
+            let result = gio::spawn_blocking(move || with_progress(progress, list_all_shares))
+                .await
+                .unwrap_or_else(|e| Err(format!("{:?}", e)));
+
+            state.imp().remote_shares.replace(Some(result));
+            state.imp().loading_remote.set(false);
+            state.emit_by_name::<()>("remote-shares-changed", &[]);
+        });
+    }
+
+    /// Remember a change waiting for a rebuild
+    pub fn add_pending_change(&self, change: ConfigChange) {
+        self.imp().pending_changes.borrow_mut().push(change);
+        self.emit_by_name::<()>("pending-changes-changed", &[]);
+    }
+
+    /// Forget a change that was reverted before any rebuild
+    pub fn remove_pending_change(&self, reverted: &ConfigChange) {
+        self.imp()
+            .pending_changes
+            .borrow_mut()
+            .retain(|change| !(change.path == reverted.path && change.after == reverted.after));
+        self.emit_by_name::<()>("pending-changes-changed", &[]);
+    }
+
+    /// Forget the pending changes once they were applied
+    pub fn clear_pending_changes(&self) {
+        self.imp().pending_changes.borrow_mut().clear();
+        self.emit_by_name::<()>("pending-changes-changed", &[]);
+    }
+
+    /// Remember a mounted share to remount once its new options are applied
+    pub fn add_pending_remount(&self, mount_point: &str) {
+        let mut remounts = self.imp().pending_remounts.borrow_mut();
+        if !remounts.iter().any(|pending| pending == mount_point) {
+            remounts.push(mount_point.to_string());
+        }
+    }
+
+    /// Shares to remount after the rebuild that just finished, forgotten from here
+    pub fn take_pending_remounts(&self) -> Vec<String> {
+        self.imp().pending_remounts.take()
+    }
+
+    pub fn connect_local_shares_changed<F: Fn(&Self) + 'static>(&self, f: F) -> glib::SignalHandlerId {
+        self.connect_local("local-shares-changed", false, move |values| {
+            let state = values[0].get::<Self>().expect("AppState as first argument");
+            f(&state);
+            None
+        })
+    }
+
+    pub fn connect_remote_shares_changed<F: Fn(&Self) + 'static>(&self, f: F) -> glib::SignalHandlerId {
+        self.connect_local("remote-shares-changed", false, move |values| {
+            let state = values[0].get::<Self>().expect("AppState as first argument");
+            f(&state);
+            None
+        })
+    }
+
+    pub fn connect_pending_changes_changed<F: Fn(&Self) + 'static>(&self, f: F) -> glib::SignalHandlerId {
+        self.connect_local("pending-changes-changed", false, move |values| {
+            let state = values[0].get::<Self>().expect("AppState as first argument");
+            f(&state);
+            None
+        })
+    }
+
+    pub fn connect_read_only_changed<F: Fn(&Self) + 'static>(&self, f: F) -> glib::SignalHandlerId {
+        self.connect_local("read-only-changed", false, move |values| {
+            let state = values[0].get::<Self>().expect("AppState as first argument");
+            f(&state);
+            None
+        })
+    }
+
+    pub fn connect_favorites_changed<F: Fn(&Self) + 'static>(&self, f: F) -> glib::SignalHandlerId {
+        self.connect_local("favorites-changed", false, move |values| {
+            let state = values[0].get::<Self>().expect("AppState as first argument");
+            f(&state);
+            None
+        })
+    }
+
+    pub fn connect_appearances_changed<F: Fn(&Self) + 'static>(&self, f: F) -> glib::SignalHandlerId {
+        self.connect_local("appearances-changed", false, move |values| {
+            let state = values[0].get::<Self>().expect("AppState as first argument");
+            f(&state);
+            None
+        })
+    }
+
+    /// Disconnect `handler` when `widget` is destroyed, so closed dialogs stop listening
+    pub fn disconnect_on_destroy(&self, widget: &impl IsA<gtk4::Widget>, handler: glib::SignalHandlerId) {
+        let state = self.clone();
+        let handler = Cell::new(Some(handler));
+        widget.connect_destroy(move |_| {
+            if let Some(handler) = handler.take() {
+                state.disconnect(handler);
+            }
+        });
+    }
+
+    /// Disconnect `handler` when `dialog` is closed. Closed dialogs aren't destroyed
+    /// while the handler still holds a reference to them.
+    pub fn disconnect_on_close(&self, dialog: &adw::Dialog, handler: glib::SignalHandlerId) {
+        let state = self.clone();
+        let handler = Cell::new(Some(handler));
+        dialog.connect_closed(move |_| {
+            if let Some(handler) = handler.take() {
+                state.disconnect(handler);
+            }
+        });
+    }
+}
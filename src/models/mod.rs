@@ -1 +1,4 @@
-// Models module - for Samba share management
\ No newline at end of file
+// Models module - for Samba share management
+pub mod app_state;
+
+pub use app_state::AppState;
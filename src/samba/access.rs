@@ -0,0 +1,330 @@
+use crate::samba::sandbox::host_path;
+use crate::samba::share_config::SambaShareConfig;
+use std::fs;
+use std::path::Path;
+
+/// Characters marking a group in Samba user lists, possibly combined like `+&staff`
+const GROUP_PREFIXES: [char; 3] = ['@', '+', '&'];
+
+/// Local accounts and group memberships, read from /etc/passwd and /etc/group
+#[derive(Debug, Default)]
+pub struct Accounts {
+    /// User names with their primary group id
+    users: Vec<(String, String)>,
+    /// Group names with their id and supplementary members
+    groups: Vec<(String, String, Vec<String>)>,
+}
+
+/// A user able to connect to a share
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserAccess {
+    pub name: String,
+    pub writable: bool,
+}
+
+/// Who can reach a share once `valid users`, `invalid users`, `read list` and `write list` are applied
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShareAccess {
+    /// Whether `valid users` limits the share to `users`.
+    /// Otherwise any Samba user can connect and `users` only lists the exceptions.
+    pub restricted: bool,
+    pub users: Vec<UserAccess>,
+    /// Users refused by `invalid users`, left out of `users`
+    pub denied: Vec<String>,
+    /// Whether users not listed can write, when the share isn't restricted
+    pub writable_by_default: bool,
+    /// Guest access and the account guests are mapped to, empty for the server default
+    pub guest: Option<String>,
+}
+
+impl Accounts {
+    /// Read the accounts of the host, empty when the files can't be read
+    pub fn load() -> Self {
+        let read = |path: &str| fs::read_to_string(host_path(Path::new(path))).unwrap_or_default();
+        Self::parse(&read("/etc/passwd"), &read("/etc/group"))
+    }
+
+    /// Parse the content of /etc/passwd and /etc/group
+    pub fn parse(passwd: &str, group: &str) -> Self {
+        let users = passwd
+            .lines()
+            .filter_map(|line| {
+                let fields: Vec<&str> = line.split(':').collect();
+                Some((fields.first()?.to_string(), fields.get(3)?.to_string()))
+            })
+            .collect();
+        let groups = group
+            .lines()
+            .filter_map(|line| {
+                let fields: Vec<&str> = line.split(':').collect();
+                let members = fields
+                    .get(3)
+                    .map(|members| {
+                        members
+                            .split(',')
+                            .filter(|member| !member.is_empty())
+                            .map(|member| member.to_string())
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                Some((fields.first()?.to_string(), fields.get(2)?.to_string(), members))
+            })
+            .collect();
+        Self { users, groups }
+    }
+
+    /// Users in `group`, as supplementary members or through their primary group
+    pub fn group_members(&self, group: &str) -> Vec<String> {
+        let Some((_, gid, members)) = self.groups.iter().find(|(name, _, _)| name == group) else {
+            return Vec::new();
+        };
+
+        let mut result = members.clone();
+        for (user, primary_gid) in &self.users {
+            if primary_gid == gid && !result.contains(user) {
+                result.push(user.clone());
+            }
+        }
+        result
+    }
+
+    /// Resolve a Samba user list, where `@group`, `+group` and `&group` name groups
+    fn expand(&self, entries: &[String]) -> Vec<String> {
+        let mut result: Vec<String> = Vec::new();
+        for entry in entries {
+            let names = if entry.starts_with(GROUP_PREFIXES) {
+                self.group_members(entry.trim_start_matches(GROUP_PREFIXES))
+            } else {
+                vec![entry.clone()]
+            };
+            for name in names {
+                if !result.contains(&name) {
+                    result.push(name);
+                }
+            }
+        }
+        result
+    }
+}
+
+/// Compute the effective access to `share`. `invalid users` wins over everything,
+/// then `write list` wins over `read list`, which wins over the share's Read Only setting.
+pub fn share_access(share: &SambaShareConfig, accounts: &Accounts) -> ShareAccess {
+    let denied = accounts.expand(&share.invalid_users);
+    let read_list = accounts.expand(&share.read_list);
+    let write_list = accounts.expand(&share.write_list);
+    let writable = |name: &str| {
+        write_list.iter().any(|user| user == name)
+            || (!share.read_only && !read_list.iter().any(|user| user == name))
+    };
+
+    let restricted = !share.valid_users.is_empty();
+    let names = if restricted {
+        accounts.expand(&share.valid_users)
+    } else {
+        // Only the users for whom the lists change something
+        let mut names = read_list.clone();
+        names.extend(write_list.iter().filter(|user| !read_list.contains(user)).cloned());
+        names
+    };
+
+    ShareAccess {
+        restricted,
+        users: names
+            .into_iter()
+            .filter(|name| !denied.contains(name))
+            .map(|name| UserAccess {
+                writable: writable(&name),
+                name,
+            })
+            .collect(),
+        denied,
+        writable_by_default: !share.read_only,
+        guest: share.guest_ok.then(|| share.guest_account.clone()),
+    }
+}
+
+/// What a single user can do on a share
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessLevel {
+    None,
+    Read,
+    Write,
+}
+
+/// Access of `user` to `share`, through the lists or the share's defaults
+pub fn user_level(share: &SambaShareConfig, accounts: &Accounts, user: &str) -> AccessLevel {
+    let access = share_access(share, accounts);
+    if access.denied.iter().any(|denied| denied == user) {
+        return AccessLevel::None;
+    }
+    let writable = match access.users.iter().find(|listed| listed.name == user) {
+        Some(listed) => listed.writable,
+        None if access.restricted => return AccessLevel::None,
+        None => access.writable_by_default,
+    };
+    if writable {
+        AccessLevel::Write
+    } else {
+        AccessLevel::Read
+    }
+}
+
+/// Copy of `share` giving `user` the access `level` by naming them in the lists.
+/// A user still let in once removed from `valid users`, by an unrestricted share or
+/// a group, is denied through `invalid users`, so the others keep their access.
+/// Groups are left alone, so access a group grants can't be given back here.
+pub fn set_user_level(
+    share: &SambaShareConfig,
+    accounts: &Accounts,
+    user: &str,
+    level: AccessLevel,
+) -> Result<SambaShareConfig, String> {
+    let mut updated = share.clone();
+    updated.read_list.retain(|entry| entry != user);
+    updated.write_list.retain(|entry| entry != user);
+    updated.invalid_users.retain(|entry| entry != user);
+
+    match level {
+        AccessLevel::None => {
+            updated.valid_users.retain(|entry| entry != user);
+            if user_level(&updated, accounts, user) != AccessLevel::None {
+                updated.invalid_users.push(user.to_string());
+            }
+        }
+        AccessLevel::Read | AccessLevel::Write => {
+            if user_level(&updated, accounts, user) == AccessLevel::None {
+                updated.valid_users.push(user.to_string());
+            }
+            let writable = user_level(&updated, accounts, user) == AccessLevel::Write;
+            if level == AccessLevel::Read && writable {
+                updated.read_list.push(user.to_string());
+            }
+            if level == AccessLevel::Write && !writable {
+                updated.write_list.push(user.to_string());
+            }
+        }
+    }
+
+    // A group in the lists, or an empty valid users list, can still decide
+    if user_level(&updated, accounts, user) != level {
+        return Err(format!(
+            "The access of '{}' to '{}' comes from a group or the share's defaults",
+            user, share.name
+        ));
+    }
+    Ok(updated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_share_access() {
+        let accounts = Accounts::parse(
+            "alice:x:1000:100::/home/alice:/bin/sh\nbob:x:1001:1001::/home/bob:/bin/sh\ncarol:x:1002:100::/home/carol:/bin/sh\n",
+            "users:x:100:\nteam:x:1001:alice\n",
+        );
+        assert_eq!(accounts.group_members("team"), vec!["alice", "bob"]);
+
+        let mut share = SambaShareConfig::new(
+            "docs".to_string(),
+            "/srv/docs".to_string(),
+            true,
+            true,
+            false,
+            String::new(),
+            String::new(),
+        );
+        share.valid_users = vec!["@users".to_string(), "bob".to_string()];
+        share.write_list = vec!["@team".to_string()];
+
+        let access = share_access(&share, &accounts);
+        assert!(access.restricted);
+        assert_eq!(
+            access.users,
+            vec![
+                UserAccess { name: "alice".to_string(), writable: true },
+                UserAccess { name: "carol".to_string(), writable: false },
+                UserAccess { name: "bob".to_string(), writable: true },
+            ]
+        );
+        assert_eq!(access.guest, None);
+    }
+
+    #[test]
+    fn test_set_user_level() {
+        let accounts = Accounts::parse(
+            "alice:x:1000:100::/home/alice:/bin/sh\nbob:x:1001:100::/home/bob:/bin/sh\ncarol:x:1002:1002::/home/carol:/bin/sh\n",
+            "users:x:100:\n",
+        );
+        let share = SambaShareConfig::new(
+            "docs".to_string(),
+            "/srv/docs".to_string(),
+            true,
+            false,
+            false,
+            String::new(),
+            String::new(),
+        );
+        assert_eq!(user_level(&share, &accounts, "alice"), AccessLevel::Write);
+
+        let read = set_user_level(&share, &accounts, "alice", AccessLevel::Read).unwrap();
+        assert_eq!(read.read_list, vec!["alice"]);
+        assert_eq!(user_level(&read, &accounts, "bob"), AccessLevel::Write);
+
+        let denied = set_user_level(&read, &accounts, "alice", AccessLevel::None).unwrap();
+        assert!(denied.valid_users.is_empty());
+        assert_eq!(denied.invalid_users, vec!["alice"]);
+        assert!(denied.read_list.is_empty());
+        assert_eq!(user_level(&denied, &accounts, "alice"), AccessLevel::None);
+        assert_eq!(user_level(&denied, &accounts, "bob"), AccessLevel::Write);
+
+        let allowed = set_user_level(&denied, &accounts, "alice", AccessLevel::Write).unwrap();
+        assert_eq!(user_level(&allowed, &accounts, "alice"), AccessLevel::Write);
+        assert!(allowed.invalid_users.is_empty());
+
+        // bob only gets in through @users
+        let mut grouped = share.clone();
+        grouped.valid_users = vec!["@users".to_string()];
+        let denied = set_user_level(&grouped, &accounts, "bob", AccessLevel::None).unwrap();
+        assert_eq!(denied.valid_users, vec!["@users"]);
+        assert_eq!(denied.invalid_users, vec!["bob"]);
+        assert_eq!(user_level(&denied, &accounts, "alice"), AccessLevel::Write);
+        let mut group_denied = grouped.clone();
+        group_denied.invalid_users = vec!["@users".to_string()];
+        assert!(set_user_level(&group_denied, &accounts, "bob", AccessLevel::Read).is_err());
+        assert_eq!(
+            set_user_level(&grouped, &accounts, "carol", AccessLevel::Read)
+                .unwrap()
+                .valid_users,
+            vec!["@users", "carol"]
+        );
+    }
+
+    #[test]
+    fn test_deny_user_on_guest_share() {
+        let accounts = Accounts::parse("alice:x:1000:100::/home/alice:/bin/sh\n", "users:x:100:\n");
+        let share = SambaShareConfig::new(
+            "public".to_string(),
+            "/srv/public".to_string(),
+            true,
+            true,
+            true,
+            String::new(),
+            String::new(),
+        );
+
+        let denied = set_user_level(&share, &accounts, "alice", AccessLevel::None).unwrap();
+        assert!(denied.valid_users.is_empty());
+        assert!(denied.guest_ok);
+        let access = share_access(&denied, &accounts);
+        assert!(!access.restricted);
+        assert_eq!(access.denied, vec!["alice"]);
+        assert_eq!(access.guest, Some(String::new()));
+        assert_eq!(user_level(&denied, &accounts, "alice"), AccessLevel::None);
+        // Samba users created later still get in
+        assert_eq!(user_level(&denied, &accounts, "dave"), AccessLevel::Read);
+    }
+}
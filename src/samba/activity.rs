@@ -0,0 +1,122 @@
+use crate::samba::command_runner::run_output;
+use crate::samba::logs::AUDIT_TAG;
+use std::collections::{BTreeSet, HashMap};
+
+/// Usage of a share, from the audit log for audited shares, from the current connections otherwise
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ShareActivity {
+    /// Time of the last audited operation, "2024-03-13 19:01", or when a current connection was opened
+    pub last_access: Option<String>,
+    /// Addresses of the clients seen this week, or connected now
+    pub clients: BTreeSet<String>,
+    /// Whether the figures cover the past week rather than the current connections
+    pub from_audit_log: bool,
+}
+
+/// Activity of every share, keyed by share name. Audited shares are measured over
+/// the last seven days of the journal; the others from the connections `smbstatus` lists now.
+pub fn share_activity(audited_shares: &[String]) -> Result<HashMap<String, ShareActivity>, String> {
+    let mut activity = match run_output("smbstatus", &["-S"]).map_err(|e| format!("smbstatus failed: {}", e)) {
+        Ok(output) => parse_connections(&output),
+        // smbstatus reads Samba's databases, which usually needs root; the journal may still answer
+        Err(e) if !audited_shares.is_empty() => {
+            eprintln!("Failed to list Samba connections: {}", e);
+            HashMap::new()
+        }
+        Err(e) => return Err(e),
+    };
+
+    if !audited_shares.is_empty() {
+        let journal = run_output(
+            "journalctl",
+            &["-t", AUDIT_TAG, "--since", "-7d", "--no-pager", "-o", "short-iso"],
+        )
+        .map_err(|e| format!("journalctl failed: {}", e))?;
+        let audited = parse_audit_log(&journal);
+        for share in audited_shares {
+            let mut share_activity = audited.get(share).cloned().unwrap_or_default();
+            share_activity.from_audit_log = true;
+            activity.insert(share.clone(), share_activity);
+        }
+    }
+    Ok(activity)
+}
+
+/// Journal lines look like
+/// `2024-03-13T19:01:02+01:00 host smbd_audit[42]: alice|192.168.1.20|media|openat|ok|file`
+fn parse_audit_log(output: &str) -> HashMap<String, ShareActivity> {
+    let mut activity: HashMap<String, ShareActivity> = HashMap::new();
+    for line in output.lines().filter(|line| !line.starts_with("-- ")) {
+        let fields: Vec<&str> = line.split('|').collect();
+        if fields.len() < 3 {
+            continue;
+        }
+        let share = activity.entry(fields[2].trim().to_string()).or_default();
+        share.clients.insert(fields[1].trim().to_string());
+        // The journal lists entries oldest first
+        share.last_access = line
+            .split_whitespace()
+            .next()
+            .map(|time| time.replacen('T', " ", 1).chars().take(16).collect());
+    }
+    activity
+}
+
+/// `smbstatus -S` lists one connection per line after a dashed separator:
+/// `media  12345  192.168.1.20  Wed Mar 13 19:01:02 2024 CET  -  -`
+fn parse_connections(output: &str) -> HashMap<String, ShareActivity> {
+    let mut activity: HashMap<String, ShareActivity> = HashMap::new();
+    let lines = output
+        .lines()
+        .skip_while(|line| !line.starts_with("---"))
+        .skip(1);
+    for line in lines {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        // Service, pid, machine, five date fields and the time zone, encryption, signing
+        if fields.len() < 10 {
+            continue;
+        }
+        let share = activity.entry(fields[0].to_string()).or_default();
+        share.clients.insert(fields[2].to_string());
+        share
+            .last_access
+            .get_or_insert_with(|| fields[3..fields.len() - 2].join(" "));
+    }
+    activity
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_audit_log() {
+        let output = "-- Journal begins at Mon 2024-03-11 --
+2024-03-12T08:00:00+01:00 nas smbd_audit[42]: alice|192.168.1.20|media|openat|ok|films
+2024-03-13T19:01:02+01:00 nas smbd_audit[42]: bob|192.168.1.21|media|mkdirat|ok|series
+2024-03-13T19:05:00+01:00 nas smbd_audit[43]: alice|192.168.1.20|backup|connect|fail|backup
+";
+        let activity = parse_audit_log(output);
+        let media = &activity["media"];
+        assert_eq!(media.last_access.as_deref(), Some("2024-03-13 19:01"));
+        assert_eq!(media.clients.len(), 2);
+        assert_eq!(activity["backup"].clients.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_connections() {
+        let output = "
+Service      pid     Machine       Connected at                     Encryption   Signing
+---------------------------------------------------------------------------------------------
+media        12345   192.168.1.20  Wed Mar 13 19:01:02 2024 CET     -            -
+media        12346   192.168.1.21  Wed Mar 13 19:30:00 2024 CET     -            -
+IPC$         12345   192.168.1.20  Wed Mar 13 19:01:02 2024 CET     -            -
+";
+        let activity = parse_connections(output);
+        let media = &activity["media"];
+        assert_eq!(media.clients.len(), 2);
+        assert_eq!(media.last_access.as_deref(), Some("Wed Mar 13 19:01:02 2024 CET"));
+        assert!(!media.from_audit_log);
+        assert!(!activity.contains_key("backup"));
+    }
+}
@@ -0,0 +1,332 @@
+use crate::samba::nix_edit::{entry_value, insert_before_closing, module_attrset, remove_node, set_attr};
+use crate::samba::share_config::get_attrpath_name;
+use rnix::{Root, SyntaxKind, SyntaxNode};
+
+/// Comment opening the block the app writes
+const BLOCK_COMMENT: &str = "# Remote shares mounted on access by autofs, managed by samba-share";
+
+/// Direct map file, relative to /etc
+pub const MAP_FILE: &str = "auto.samba-share";
+
+/// Seconds a share stays mounted without being used
+const TIMEOUT: u32 = 300;
+
+/// One line of the map
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MapEntry {
+    pub mount_point: String,
+    /// Mount options, `fstype=` included
+    pub options: Vec<String>,
+    /// Share in `//server/share` form
+    pub remote_path: String,
+}
+
+/// Option turning autofs on
+const ENABLE_ATTR: &str = "services.autofs.enable";
+
+/// Option holding the master map, one line per map
+const MASTER_ATTR: &str = "services.autofs.autoMaster";
+
+/// Name `get_attrpath_name` gives to the entry holding the map text
+fn map_attr() -> String {
+    format!("environment.etc.{}.text", MAP_FILE)
+}
+
+/// Line of the master map pointing autofs at the map
+fn master_line() -> String {
+    format!("/- /etc/{} --timeout={}", MAP_FILE, TIMEOUT)
+}
+
+/// Entry named `name` directly inside the module attrset
+fn module_entry(root: &SyntaxNode, name: &str) -> Option<SyntaxNode> {
+    module_attrset(root)?
+        .children()
+        .filter(|child| child.kind() == SyntaxKind::NODE_ATTRPATH_VALUE)
+        .find(|child| get_attrpath_name(child).as_deref() == Some(name))
+}
+
+/// Text of the map, without its `''` delimiters
+fn map_content(root: &SyntaxNode) -> Option<String> {
+    let entry = module_entry(root, &map_attr())?;
+    let text = entry_value(&entry)?.text().to_string();
+    Some(text.strip_prefix("''")?.strip_suffix("''")?.to_string())
+}
+
+/// Whether a configuration file contains the map
+pub fn has_map(content: &str) -> bool {
+    map_content(&Root::parse(content).syntax()).is_some()
+}
+
+/// Entries of the map found in a configuration file, with the reason they can't
+/// be rewritten when the map text uses Nix interpolation
+pub fn parse_map(content: &str) -> (Vec<MapEntry>, Option<String>) {
+    let Some(text) = map_content(&Root::parse(content).syntax()) else {
+        return (Vec::new(), None);
+    };
+
+    let locked_reason = text
+        .contains("${")
+        .then(|| "the autofs map uses Nix interpolation".to_string());
+    let entries = text.lines().filter_map(parse_line).collect();
+    (entries, locked_reason)
+}
+
+/// Map line for a share
+pub fn map_line(mount_point: &str, options: &[String], remote_path: &str) -> String {
+    format!("{} -{} :{}", mount_point, options.join(","), remote_path)
+}
+
+/// Add, replace or, with `line` None, remove the entry of `mount_point` in a
+/// configuration file. The block is created with the first entry and removed with the last.
+pub fn set_entry(content: &str, mount_point: &str, line: Option<&str>) -> Result<String, String> {
+    let Some(text) = map_content(&Root::parse(content).syntax()) else {
+        let Some(line) = line else {
+            return Err(format!("Could not find autofs entry for '{}'", mount_point));
+        };
+        return add_block(content, line);
+    };
+
+    let mut lines: Vec<String> = Vec::new();
+    let mut found = false;
+    for existing in text.lines().filter(|existing| !existing.trim().is_empty()) {
+        let is_entry = parse_line(existing).is_some_and(|entry| entry.mount_point == mount_point);
+        if !is_entry {
+            lines.push(existing.trim().to_string());
+        } else if !found {
+            found = true;
+            lines.extend(line.map(|line| line.to_string()));
+        }
+    }
+    match (found, line) {
+        (false, None) => return Err(format!("Could not find autofs entry for '{}'", mount_point)),
+        (false, Some(line)) => lines.push(line.to_string()),
+        (true, _) => {}
+    }
+
+    if lines.is_empty() {
+        return remove_block(content);
+    }
+    set_attr(content, module_attrset, &map_attr(), Some(&map_value(&lines)))
+}
+
+/// Parse a map line, skipping comments and lines that aren't CIFS mounts
+fn parse_line(line: &str) -> Option<MapEntry> {
+    let line = line.trim();
+    if line.starts_with('#') {
+        return None;
+    }
+
+    let mut fields = line.split_whitespace();
+    let mount_point = fields.next()?;
+    let options = fields.next()?.strip_prefix('-')?;
+    let location = fields.next()?.strip_prefix(':')?;
+
+    let options: Vec<String> = options.split(',').map(|option| option.to_string()).collect();
+    if !options.iter().any(|option| option == "fstype=cifs") {
+        return None;
+    }
+    Some(MapEntry {
+        mount_point: mount_point.to_string(),
+        options,
+        remote_path: location.to_string(),
+    })
+}
+
+/// Indented string holding the map lines
+fn map_value(lines: &[String]) -> String {
+    let lines: Vec<String> = lines.iter().map(|line| format!("    {}\n", line)).collect();
+    format!("''\n{}  ''", lines.concat())
+}
+
+/// Add the block enabling autofs with the map at the end of the module:
+///
+/// ```nix
+/// # Remote shares mounted on access by autofs, managed by samba-share
+/// services.autofs.enable = true;
+/// services.autofs.autoMaster = "/- /etc/auto.samba-share --timeout=300";
+/// environment.etc."auto.samba-share".text = ''
+///   /media/nas -fstype=cifs,credentials=/etc/nas-secrets,uid=1000,gid=100 ://nas/share
+/// '';
+/// ```
+///
+/// Options the module already sets are changed where they are instead of being defined twice.
+fn add_block(content: &str, line: &str) -> Result<String, String> {
+    let root = Root::parse(content).syntax();
+    let mut content = content.to_string();
+    let mut block = vec![String::new(), BLOCK_COMMENT.to_string()];
+
+    if module_entry(&root, ENABLE_ATTR).is_some() {
+        content = set_attr(&content, module_attrset, ENABLE_ATTR, Some("true"))?;
+    } else {
+        block.push(format!("{} = true;", ENABLE_ATTR));
+    }
+
+    match module_entry(&root, MASTER_ATTR) {
+        Some(entry) => {
+            let current = entry_value(&entry).map(|value| value.text().to_string()).unwrap_or_default();
+            let master = add_master_line(&current).ok_or_else(|| {
+                format!("{} is not a plain string, add \"{}\" to it by hand", MASTER_ATTR, master_line())
+            })?;
+            content = set_attr(&content, module_attrset, MASTER_ATTR, Some(&master))?;
+        }
+        None => block.push(format!("{} = \"{}\";", MASTER_ATTR, master_line())),
+    }
+
+    block.push(format!(
+        "environment.etc.\"{}\".text = {};",
+        MAP_FILE,
+        map_value(&[line.to_string()])
+    ));
+    let block: Vec<String> = block
+        .iter()
+        .map(|text| if text.is_empty() { String::new() } else { format!("  {}", text) })
+        .collect();
+
+    let root = Root::parse(&content).syntax();
+    let attrset = module_attrset(&root).ok_or("Could not find the module attrset in configuration")?;
+    insert_before_closing(&content, &attrset, &block.join("\n"))
+}
+
+/// Remove the block written by `add_block`. Options set before it or changed by hand
+/// since stay as the user wrote them: only the line of the map is taken out of the
+/// master map, and when even that is not possible the map is just emptied.
+fn remove_block(content: &str) -> Result<String, String> {
+    let root = Root::parse(content).syntax();
+    let block_start = content.find(BLOCK_COMMENT);
+    // Entries written after the comment come from the block
+    let in_block = |entry: &SyntaxNode| block_start.is_some_and(|start| usize::from(entry.text_range().start()) > start);
+    let value = |entry: &SyntaxNode| entry_value(entry).map(|value| value.text().to_string()).unwrap_or_default();
+
+    let mut content = content.to_string();
+    let mut removed = vec![map_attr()];
+    if let Some(entry) = module_entry(&root, MASTER_ATTR) {
+        if in_block(&entry) && value(&entry) == format!("\"{}\"", master_line()) {
+            removed.push(MASTER_ATTR.to_string());
+        } else if let Some(master) = remove_master_line(&value(&entry)) {
+            content = set_attr(&content, module_attrset, MASTER_ATTR, Some(&master))?;
+        } else {
+            // Removing the map would leave autofs pointing at a missing file
+            return set_attr(&content, module_attrset, &map_attr(), Some(&map_value(&[])));
+        }
+    }
+    if module_entry(&root, ENABLE_ATTR).is_some_and(|entry| in_block(&entry) && value(&entry) == "true") {
+        removed.push(ENABLE_ATTR.to_string());
+    }
+
+    for name in removed {
+        let root = Root::parse(&content).syntax();
+        if let Some(entry) = module_entry(&root, &name) {
+            content = remove_node(&content, &entry);
+        }
+    }
+
+    // The comment goes with its line and the blank line written before it
+    if let Some(start) = content.find(BLOCK_COMMENT) {
+        let mut first = content[..start].rfind('\n').map(|i| i + 1).unwrap_or(0);
+        if content[..first].ends_with("\n\n") {
+            first -= 1;
+        }
+        let last = content[start..]
+            .find('\n')
+            .map(|i| start + i + 1)
+            .unwrap_or(content.len());
+        content.replace_range(first..last, "");
+    }
+    Ok(content)
+}
+
+/// Lines of a plain string literal, with the delimiters needed to write it back.
+/// None for any other expression, or when it uses interpolation.
+fn master_lines(value: &str) -> Option<(Vec<String>, bool)> {
+    if value.contains("${") {
+        return None;
+    }
+    if let Some(text) = value.strip_prefix("''").and_then(|text| text.strip_suffix("''")) {
+        let lines = text.lines().map(|line| line.trim().to_string()).filter(|line| !line.is_empty());
+        return Some((lines.collect(), true));
+    }
+    let text = value.strip_prefix('"')?.strip_suffix('"')?;
+    let lines = text.split("\\n").map(|line| line.trim().to_string()).filter(|line| !line.is_empty());
+    Some((lines.collect(), false))
+}
+
+/// Write lines back in the form `master_lines` read them from
+fn master_value(lines: &[String], indented: bool) -> String {
+    if indented {
+        let lines: Vec<String> = lines.iter().map(|line| format!("    {}\n", line)).collect();
+        format!("''\n{}  ''", lines.concat())
+    } else {
+        format!("\"{}\"", lines.join("\\n"))
+    }
+}
+
+/// Master map `value` with the line of the map added, None when it can't be edited
+fn add_master_line(value: &str) -> Option<String> {
+    let (mut lines, indented) = master_lines(value)?;
+    if lines.contains(&master_line()) {
+        return Some(value.to_string());
+    }
+    lines.push(master_line());
+    Some(master_value(&lines, indented))
+}
+
+/// Master map `value` without the line of the map, None when it can't be edited or doesn't have it
+fn remove_master_line(value: &str) -> Option<String> {
+    let (lines, indented) = master_lines(value)?;
+    let kept: Vec<String> = lines.iter().filter(|line| **line != master_line()).cloned().collect();
+    (kept.len() < lines.len()).then(|| master_value(&kept, indented))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_entry() {
+        let config = "{ config, pkgs, ... }:\n{\n  networking.hostName = \"box\";\n}\n";
+        let options = vec!["fstype=cifs".to_string(), "uid=1000".to_string()];
+        let nas = map_line("/media/nas", &options, "//nas/share");
+        let backup = map_line("/media/backup", &options, "//nas/backup");
+        assert_eq!(nas, "/media/nas -fstype=cifs,uid=1000 ://nas/share");
+
+        let added = set_entry(config, "/media/nas", Some(&nas)).unwrap();
+        let added = set_entry(&added, "/media/backup", Some(&backup)).unwrap();
+        let (entries, locked) = parse_map(&added);
+        assert_eq!(locked, None);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            entries[0],
+            MapEntry {
+                mount_point: "/media/nas".to_string(),
+                options: options.clone(),
+                remote_path: "//nas/share".to_string(),
+            }
+        );
+
+        let replaced = map_line("/media/nas", &options, "//nas/other");
+        let updated = set_entry(&added, "/media/nas", Some(&replaced)).unwrap();
+        assert_eq!(parse_map(&updated).0[0].remote_path, "//nas/other");
+
+        let removed = set_entry(&updated, "/media/nas", None).unwrap();
+        assert_eq!(parse_map(&removed).0.len(), 1);
+        assert_eq!(set_entry(&removed, "/media/backup", None).unwrap(), config);
+        assert!(set_entry(config, "/media/nas", None).is_err());
+    }
+
+    #[test]
+    fn test_set_entry_existing_master() {
+        let config = "{ config, pkgs, ... }:\n{\n  services.autofs.autoMaster = \"/net -hosts\";\n}\n";
+        let options = vec!["fstype=cifs".to_string()];
+        let nas = map_line("/media/nas", &options, "//nas/share");
+
+        let added = set_entry(config, "/media/nas", Some(&nas)).unwrap();
+        assert_eq!(added.matches("services.autofs.autoMaster").count(), 1);
+        assert!(added.contains("services.autofs.autoMaster = \"/net -hosts\\n/- /etc/auto.samba-share --timeout=300\";"));
+        assert!(added.contains("services.autofs.enable = true;"));
+        assert_eq!(parse_map(&added).0.len(), 1);
+        assert_eq!(set_entry(&added, "/media/nas", None).unwrap(), config);
+
+        let computed = "{\n  services.autofs.autoMaster = builtins.readFile ./master;\n}\n";
+        assert!(set_entry(computed, "/media/nas", Some(&nas)).is_err());
+    }
+}
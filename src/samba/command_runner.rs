@@ -0,0 +1,91 @@
+use once_cell::sync::OnceCell;
+use std::process::Command;
+
+/// Builds the commands used to reach the system (mount, journalctl, pkexec...),
+/// so demo and sandboxed modes can redirect or stub them in one place
+pub trait CommandRunner: Send + Sync {
+    /// Command running `program` for this mode; arguments are added by the caller
+    fn command(&self, program: &str) -> Command;
+}
+
+/// Runs programs directly on this system
+pub struct HostRunner;
+
+impl CommandRunner for HostRunner {
+    fn command(&self, program: &str) -> Command {
+        Command::new(program)
+    }
+}
+
+/// Programs that change the system, replaced by `true` in demo mode
+const DEMO_STUBBED: [&str; 9] = [
+    "mount",
+    "umount",
+    "/run/wrappers/bin/pkexec",
+    "pkexec",
+    "run0",
+    "sudo",
+    "nixos-rebuild",
+    "smbpasswd",
+    "secret-tool",
+];
+
+/// Runs read-only programs normally and pretends the others succeeded
+pub struct DemoRunner;
+
+impl CommandRunner for DemoRunner {
+    fn command(&self, program: &str) -> Command {
+        if DEMO_STUBBED.contains(&program) {
+            eprintln!("[demo] skipping {}", program);
+            Command::new("true")
+        } else {
+            Command::new(program)
+        }
+    }
+}
+
+/// Runs programs on the host from inside a Flatpak sandbox.
+/// Needs the `--talk-name=org.freedesktop.Flatpak` permission.
+pub struct FlatpakRunner;
+
+impl CommandRunner for FlatpakRunner {
+    fn command(&self, program: &str) -> Command {
+        let mut command = Command::new("flatpak-spawn");
+        command.arg("--host").arg(program);
+        command
+    }
+}
+
+static RUNNER: OnceCell<Box<dyn CommandRunner>> = OnceCell::new();
+
+/// Select the runner for the whole process. Only the first call has an effect.
+pub fn set_runner(runner: Box<dyn CommandRunner>) {
+    if RUNNER.set(runner).is_err() {
+        eprintln!("Command runner already set");
+    }
+}
+
+/// Command running `program` through the selected runner, directly on the host by default
+pub fn command(program: &str) -> Command {
+    match RUNNER.get() {
+        Some(runner) => runner.command(program),
+        None => HostRunner.command(program),
+    }
+}
+
+/// Run `program` through the selected runner and return its trimmed standard output.
+/// On failure the error is its standard error, or its standard output when that is empty.
+pub fn run_output(program: &str, args: &[&str]) -> Result<String, String> {
+    let output = command(program)
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if output.status.success() {
+        Ok(stdout)
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        Err(if stderr.is_empty() { stdout } else { stderr })
+    }
+}
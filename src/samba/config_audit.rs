@@ -0,0 +1,375 @@
+use crate::samba::global_config::MANAGED_KEYS;
+use crate::samba::homes::HOMES;
+use crate::samba::nix_imports::config_files;
+use crate::samba::nix_parse::parse_file;
+use crate::samba::paths::config_path;
+use crate::samba::remote_share_config::RemoteSambaShareConfig;
+use crate::samba::sandbox::host_path;
+use crate::samba::share_config::{
+    find_samba_settings, get_attrpath_name, parse_attrset_entry, unsupported_expression,
+    SambaShareConfig, MANAGED_KEYS as MANAGED_SHARE_KEYS,
+};
+use rnix::{Root, SyntaxKind, SyntaxNode};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Options of `services.samba` from NixOS releases before 24.05, which the app doesn't read
+const LEGACY_OPTIONS: [&str; 4] = ["shares", "extraConfig", "configText", "syncPasswordsByPam"];
+
+/// A key of the `global` section
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditedSetting {
+    pub key: String,
+    pub value: String,
+    /// Whether the server settings edit it, other keys are left as they are
+    pub managed: bool,
+}
+
+/// A share of `services.samba.settings` and what editing it would change
+#[derive(Debug, Clone)]
+pub struct AuditedShare {
+    pub share: SambaShareConfig,
+    /// Settings the share editor doesn't know, lost when the share is saved from the app
+    pub dropped_settings: Vec<String>,
+}
+
+/// How the app treats an option of `services.samba` outside the settings it reads
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionHandling {
+    /// Left as it is, such as `openFirewall` or `nmbd`
+    Kept,
+    /// Not read at all: legacy options, or settings written in a form the app doesn't recognize
+    Ignored,
+}
+
+/// An option of `services.samba` other than `enable` and the settings the app reads
+#[derive(Debug, Clone, PartialEq)]
+pub struct OtherOption {
+    /// Full option path, such as `services.samba.openFirewall`
+    pub path: String,
+    pub source_file: String,
+    pub handling: OptionHandling,
+}
+
+/// Summary of the Samba configuration already present, written by hand or by another tool
+#[derive(Debug, Clone, Default)]
+pub struct ConfigAudit {
+    /// Configuration files read, the main one first
+    pub files: Vec<String>,
+    pub global: Vec<AuditedSetting>,
+    /// Whether a `homes` section shares the home folders
+    pub homes: bool,
+    pub shares: Vec<AuditedShare>,
+    /// CIFS mounts from `fileSystems` and the autofs map
+    pub mounts: Vec<RemoteSambaShareConfig>,
+    pub other_options: Vec<OtherOption>,
+}
+
+impl ConfigAudit {
+    /// Read the main configuration file and its imports, without changing anything
+    pub fn load() -> Result<Self, String> {
+        let mut audit = Self::default();
+
+        for file in config_files(config_path()) {
+            let source_file = file.to_string_lossy().to_string();
+            let content = match fs::read_to_string(host_path(&file)) {
+                Ok(content) => content,
+                Err(e) if source_file == config_path() => {
+                    return Err(format!("Failed to read {}: {}", source_file, e));
+                }
+                Err(e) => {
+                    eprintln!("Skipping imported file {}: {}", source_file, e);
+                    continue;
+                }
+            };
+            audit.add_file(&content, &source_file);
+        }
+        audit.mounts = RemoteSambaShareConfig::load_all()?;
+
+        Ok(audit)
+    }
+
+    /// Whether the app can edit everything it found without losing anything
+    pub fn fully_managed(&self) -> bool {
+        self.shares
+            .iter()
+            .all(|audited| audited.share.locked_reason.is_none() && audited.dropped_settings.is_empty())
+            && self.mounts.iter().all(|mount| mount.locked_reason.is_none())
+            && self
+                .other_options
+                .iter()
+                .all(|option| option.handling == OptionHandling::Kept)
+    }
+
+    /// Add the Samba settings and options of one configuration file
+    fn add_file(&mut self, content: &str, source_file: &str) {
+        self.files.push(source_file.to_string());
+
+        let root = parse_file(Path::new(source_file), content).syntax();
+        if let Some(settings) = find_samba_settings(&root) {
+            for entry in settings.children() {
+                if entry.kind() != SyntaxKind::NODE_ATTRPATH_VALUE {
+                    continue;
+                }
+                let Some((name, props)) = parse_attrset_entry(&entry) else {
+                    continue;
+                };
+                match name.as_str() {
+                    "global" => self.global.extend(global_settings(&props)),
+                    HOMES => self.homes = true,
+                    _ => {
+                        let mut share = SambaShareConfig::from_props(name, &props);
+                        share.source_file = source_file.to_string();
+                        share.locked_reason = unsupported_expression(&entry);
+                        let dropped_settings = dropped_settings(&share, &props);
+                        self.shares.push(AuditedShare {
+                            share,
+                            dropped_settings,
+                        });
+                    }
+                }
+            }
+        }
+
+        let mut options = Vec::new();
+        find_samba_options(&root, "", &mut options);
+        self.other_options
+            .extend(options.into_iter().map(|(path, handling)| OtherOption {
+                path,
+                source_file: source_file.to_string(),
+                handling,
+            }));
+    }
+}
+
+/// Keys of the global section sorted by name, marking the ones the server settings edit
+fn global_settings(props: &HashMap<String, String>) -> Vec<AuditedSetting> {
+    let mut settings: Vec<AuditedSetting> = props
+        .iter()
+        .map(|(key, value)| AuditedSetting {
+            key: key.clone(),
+            value: value.clone(),
+            managed: MANAGED_KEYS.contains(&key.as_str()),
+        })
+        .collect();
+    settings.sort_by(|a, b| a.key.cmp(&b.key));
+    settings
+}
+
+/// Keys of a share entry that regenerating it from the parsed share would not write back.
+/// Keys the share editor doesn't manage are kept by updates, so only managed ones count.
+fn dropped_settings(share: &SambaShareConfig, props: &HashMap<String, String>) -> Vec<String> {
+    let regenerated = Root::parse(&format!("{{\n{}\n}}", share.to_nix())).syntax();
+    let written: Vec<String> = regenerated
+        .descendants()
+        .find(|node| node.kind() == SyntaxKind::NODE_ATTRPATH_VALUE)
+        .and_then(|entry| parse_attrset_entry(&entry))
+        .map(|(_, written)| written.into_keys().collect())
+        .unwrap_or_default();
+
+    let mut dropped: Vec<String> = props
+        .keys()
+        .filter(|key| MANAGED_SHARE_KEYS.contains(&key.as_str()) && !written.contains(key))
+        .cloned()
+        .collect();
+    dropped.sort();
+    dropped
+}
+
+/// Collect the options set under `services.samba`, following nested attrsets such as
+/// `services = { samba = { ... }; }`. `prefix` is the path of the attrset `node` is in.
+fn find_samba_options(node: &SyntaxNode, prefix: &str, options: &mut Vec<(String, OptionHandling)>) {
+    for child in node.children() {
+        if child.kind() != SyntaxKind::NODE_ATTRPATH_VALUE {
+            find_samba_options(&child, prefix, options);
+            continue;
+        }
+        let Some(name) = get_attrpath_name(&child) else {
+            continue;
+        };
+        let path = if prefix.is_empty() {
+            name
+        } else {
+            format!("{}.{}", prefix, name)
+        };
+
+        if path == "services.samba" {
+            let value = child
+                .children()
+                .find(|value| value.kind() == SyntaxKind::NODE_ATTR_SET);
+            // Settings are only read under a `services.samba` attrpath written in one piece
+            match value {
+                Some(value) => samba_attrset_options(&value, prefix.is_empty(), options),
+                None => options.push((path, OptionHandling::Ignored)),
+            }
+        } else if let Some(option) = path.strip_prefix("services.samba.") {
+            if option != "enable" {
+                options.push((path.clone(), option_handling(option)));
+            }
+        } else {
+            // Other attrsets may still hold `services.samba` further down
+            let inside = if path == "services" { path.as_str() } else { "" };
+            for value in child.children().filter(|value| value.kind() != SyntaxKind::NODE_ATTRPATH) {
+                find_samba_options(&value, inside, options);
+            }
+        }
+    }
+}
+
+/// Options of a `services.samba = { ... };` attrset, `readable` when the app reads its settings
+fn samba_attrset_options(samba: &SyntaxNode, readable: bool, options: &mut Vec<(String, OptionHandling)>) {
+    for entry in samba.children() {
+        if entry.kind() != SyntaxKind::NODE_ATTRPATH_VALUE {
+            continue;
+        }
+        let Some(name) = get_attrpath_name(&entry) else {
+            continue;
+        };
+        // The settings attrset is what the app reads
+        let settings_attrset = readable
+            && name == "settings"
+            && entry
+                .children()
+                .any(|value| value.kind() == SyntaxKind::NODE_ATTR_SET);
+        if name == "enable" || settings_attrset {
+            continue;
+        }
+        options.push((format!("services.samba.{}", name), option_handling(&name)));
+    }
+}
+
+/// How the app treats `services.samba.<option>`. Settings are only read from a
+/// `settings = { ... };` attrset inside `services.samba = { ... };`.
+fn option_handling(option: &str) -> OptionHandling {
+    let first = option.split('.').next().unwrap_or_default();
+    if LEGACY_OPTIONS.contains(&first) || first == "settings" {
+        OptionHandling::Ignored
+    } else {
+        OptionHandling::Kept
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"{ config, pkgs, ... }:
+{
+  services.samba = {
+    enable = true;
+    openFirewall = true;
+    extraConfig = ''
+      [legacy]
+      path = /srv/legacy
+    '';
+    settings = {
+      global = {
+        "workgroup" = "WORKGROUP";
+        "smb encrypt" = "required";
+      };
+      homes = {
+        browseable = "no";
+      };
+      docs = {
+        path = "/srv/docs";
+        "read only" = "no";
+        "guest account" = "nobody";
+        comment = "Team documents";
+        "veto files" = "/*.tmp/";
+      };
+      media = mediaShare;
+    };
+  };
+  services.samba.nmbd.enable = false;
+  fileSystems."/mnt/nas" = {
+    device = "//nas/share";
+    fsType = "cifs";
+  };
+}
+"#;
+
+    fn audit() -> ConfigAudit {
+        let mut audit = ConfigAudit::default();
+        audit.add_file(CONFIG, "/etc/nixos/customConfig/default.nix");
+        audit
+    }
+
+    #[test]
+    fn test_global_settings() {
+        let audit = audit();
+        assert_eq!(
+            audit.global,
+            vec![
+                AuditedSetting {
+                    key: "smb encrypt".to_string(),
+                    value: "required".to_string(),
+                    managed: true,
+                },
+                AuditedSetting {
+                    key: "workgroup".to_string(),
+                    value: "WORKGROUP".to_string(),
+                    managed: false,
+                },
+            ]
+        );
+        assert!(audit.homes);
+    }
+
+    #[test]
+    fn test_shares() {
+        let audit = audit();
+        assert_eq!(audit.shares.len(), 2);
+
+        let docs = &audit.shares[0];
+        assert_eq!(docs.share.name, "docs");
+        assert_eq!(docs.share.locked_reason, None);
+        // Only written for guest shares, the comment and vetoed files are kept
+        assert_eq!(docs.dropped_settings, vec!["guest account"]);
+
+        let media = &audit.shares[1];
+        assert_eq!(media.share.name, "media");
+        assert!(media.share.locked_reason.is_some());
+        assert!(!audit.fully_managed());
+    }
+
+    #[test]
+    fn test_other_options() {
+        let options: Vec<(String, OptionHandling)> = audit()
+            .other_options
+            .into_iter()
+            .map(|option| (option.path, option.handling))
+            .collect();
+        assert_eq!(
+            options,
+            vec![
+                ("services.samba.openFirewall".to_string(), OptionHandling::Kept),
+                ("services.samba.extraConfig".to_string(), OptionHandling::Ignored),
+                ("services.samba.nmbd.enable".to_string(), OptionHandling::Kept),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_nested_and_dotted_settings() {
+        let mut audit = ConfigAudit::default();
+        audit.add_file(
+            r#"{
+  services = {
+    samba.settings.global.workgroup = "HOME";
+  };
+}
+"#,
+            "/etc/nixos/samba.nix",
+        );
+        assert!(audit.global.is_empty());
+        assert_eq!(
+            audit.other_options,
+            vec![OtherOption {
+                path: "services.samba.settings.global.workgroup".to_string(),
+                source_file: "/etc/nixos/samba.nix".to_string(),
+                handling: OptionHandling::Ignored,
+            }]
+        );
+    }
+}
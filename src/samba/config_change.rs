@@ -0,0 +1,59 @@
+use crate::samba::sudo_write::{read_file, write_with_sudo};
+use similar::TextDiff;
+
+/// A rewrite of one configuration file, computed before anything is written
+/// so it can be previewed first
+#[derive(Debug, Clone)]
+pub struct ConfigChange {
+    pub path: String,
+    pub before: String,
+    pub after: String,
+}
+
+impl ConfigChange {
+    pub fn new(path: String, before: String, after: String) -> Self {
+        Self {
+            path,
+            before,
+            after,
+        }
+    }
+
+    /// Unified diff of the change, with three lines of context
+    pub fn diff(&self) -> String {
+        TextDiff::from_lines(&self.before, &self.after)
+            .unified_diff()
+            .context_radius(3)
+            .header(&self.path, &self.path)
+            .to_string()
+    }
+
+    /// Write the new content to disk
+    pub fn apply(&self) -> Result<(), String> {
+        write_with_sudo(&self.path, &self.after)
+    }
+
+    /// Write several changes as one: when one fails, those already written are reverted
+    pub fn apply_all(changes: &[ConfigChange]) -> Result<(), String> {
+        for (index, change) in changes.iter().enumerate() {
+            if let Err(e) = change.apply() {
+                for applied in changes[..index].iter().rev() {
+                    if let Err(revert_error) = applied.revert() {
+                        eprintln!("Failed to revert {}: {}", applied.path, revert_error);
+                    }
+                }
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Restore the previous content, as long as the file wasn't modified since `apply`
+    pub fn revert(&self) -> Result<(), String> {
+        let current = read_file(&self.path)?;
+        if current != self.after {
+            return Err(format!("{} was modified in the meantime", self.path));
+        }
+        write_with_sudo(&self.path, &self.before)
+    }
+}
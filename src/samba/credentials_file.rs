@@ -0,0 +1,111 @@
+use crate::samba::sudo_write::run_privileged;
+
+/// Folder new credentials files go to, until another one is chosen in the preferences
+pub const DEFAULT_FOLDER: &str = "/etc/nixos";
+
+/// Script run as root writing stdin to `$1`, readable by root only from the start.
+/// mount.cifs runs as root, so nobody else needs to read the password. A missing
+/// folder is created for root only, existing ones such as /etc/nixos keep their mode.
+const WRITE_SCRIPT: &str = r#"set -e
+export PATH=/run/current-system/sw/bin:/run/wrappers/bin:$PATH
+umask 077
+folder="$(dirname "$1")"
+if [ ! -d "$folder" ]; then
+  install -d -m 0700 -o root -g root "$folder"
+fi
+cat > "$1"
+chown root:root "$1"
+chmod 0600 "$1"
+"#;
+
+/// What a credentials file holds, in the `username=` `password=` `domain=` lines mount.cifs reads
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Credentials {
+    pub username: String,
+    pub password: String,
+    /// Workgroup or Windows domain, left out of the file when empty
+    pub domain: String,
+}
+
+impl Credentials {
+    /// Content of the file. Values are read up to the end of their line, so they can
+    /// hold spaces but no line breaks.
+    pub fn to_file_content(&self) -> Result<String, String> {
+        let username = self.username.trim();
+        if username.is_empty() {
+            return Err("Username is required".to_string());
+        }
+        let fields = [("username", username), ("password", &self.password), ("domain", self.domain.trim())];
+        if fields.iter().any(|(_, value)| value.contains(['\n', '\r'])) {
+            return Err("Credentials can't contain line breaks".to_string());
+        }
+
+        Ok(fields
+            .iter()
+            .filter(|(key, value)| *key != "domain" || !value.is_empty())
+            .map(|(key, value)| format!("{}={}\n", key, value))
+            .collect())
+    }
+}
+
+/// Path offered for the credentials of `server` in `folder`, one file per server
+pub fn suggested_path(folder: &str, server: &str) -> String {
+    let server: String = server
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'))
+        .collect::<String>()
+        .to_lowercase();
+    let folder = folder.trim_end_matches('/');
+    if server.is_empty() {
+        format!("{}/smb-secrets", folder)
+    } else {
+        format!("{}/smb-secrets-{}", folder, server)
+    }
+}
+
+/// Write `credentials` to `path`, owned by root with mode 0600, replacing the file
+/// if there is one. The password goes through stdin, never on a command line.
+pub fn create(path: &str, credentials: &Credentials) -> Result<(), String> {
+    if !path.starts_with('/') {
+        return Err("The credentials file needs an absolute path".to_string());
+    }
+    if path.ends_with('/') {
+        return Err("The credentials file needs a file name".to_string());
+    }
+    let content = credentials.to_file_content()?;
+    run_privileged("/bin/sh", &["-c", WRITE_SCRIPT, "sh", path], Some(&content)).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn credentials(username: &str, password: &str, domain: &str) -> Credentials {
+        Credentials {
+            username: username.to_string(),
+            password: password.to_string(),
+            domain: domain.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_to_file_content() {
+        assert_eq!(
+            credentials(" alice ", "pass word", "").to_file_content().unwrap(),
+            "username=alice\npassword=pass word\n"
+        );
+        assert_eq!(
+            credentials("alice", "secret", "WORKGROUP").to_file_content().unwrap(),
+            "username=alice\npassword=secret\ndomain=WORKGROUP\n"
+        );
+        assert!(credentials("", "secret", "").to_file_content().is_err());
+        assert!(credentials("alice", "a\nb", "").to_file_content().is_err());
+    }
+
+    #[test]
+    fn test_suggested_path() {
+        assert_eq!(suggested_path("/etc/nixos/", "NAS.local"), "/etc/nixos/smb-secrets-nas.local");
+        assert_eq!(suggested_path("/etc/nixos", ""), "/etc/nixos/smb-secrets");
+        assert_eq!(suggested_path("/etc/nixos", "a/../b"), "/etc/nixos/smb-secrets-a..b");
+    }
+}
@@ -0,0 +1,186 @@
+use crate::samba::config_change::ConfigChange;
+use crate::samba::remote_share_config::RemoteSambaShareConfig;
+use crate::samba::sudo_write::{read_file, run_privileged};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Script run as root moving credentials files, arguments being the folder then pairs of
+/// source and destination. Nothing moves when one destination is taken. A link is left at
+/// each old path, which the mounts of the running system use until the next rebuild.
+const MOVE_SCRIPT: &str = r#"set -e
+export PATH=/run/current-system/sw/bin:/run/wrappers/bin:$PATH
+folder="$1"
+shift
+if [ ! -d "$folder" ]; then
+  install -d -m 0700 -o root -g root "$folder"
+fi
+i=0
+for file in "$@"; do
+  i=$((i + 1))
+  if [ $((i % 2)) -eq 0 ] && [ -e "$file" ]; then
+    echo "$file already exists" >&2
+    exit 1
+  fi
+done
+while [ "$#" -ge 2 ]; do
+  mv "$1" "$2"
+  chown root:root "$2"
+  chmod 0600 "$2"
+  ln -s "$2" "$1"
+  shift 2
+done
+"#;
+
+/// Script run as root undoing `MOVE_SCRIPT`, with pairs of new and old paths
+const MOVE_BACK_SCRIPT: &str = r#"export PATH=/run/current-system/sw/bin:/run/wrappers/bin:$PATH
+while [ "$#" -ge 2 ]; do
+  rm -f "$2"
+  mv "$1" "$2"
+  shift 2
+done
+"#;
+
+/// A credentials file to move into the credentials folder, with the shares using it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CredentialsMove {
+    pub from: String,
+    pub to: String,
+    /// Mount points of the shares whose entries are updated
+    pub shares: Vec<String>,
+}
+
+/// Credentials files of `shares` outside `folder`, each going to the same name in it.
+/// Files shared by several entries move once, names taken twice get a number.
+pub fn plan(shares: &[RemoteSambaShareConfig], folder: &str) -> Vec<CredentialsMove> {
+    let folder = folder.trim_end_matches('/');
+    let mut moves: Vec<CredentialsMove> = Vec::new();
+    for share in shares {
+        let from = share.option_credentials.trim();
+        let in_folder = Path::new(from).parent().is_some_and(|parent| parent == Path::new(folder));
+        // Entries the app can't rewrite keep their file where it is
+        if from.is_empty() || !from.starts_with('/') || in_folder || share.locked_reason.is_some() {
+            continue;
+        }
+        if let Some(planned) = moves.iter_mut().find(|planned| planned.from == from) {
+            planned.shares.push(share.name.clone());
+            continue;
+        }
+
+        let file_name = Path::new(from)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| "smb-secrets".to_string());
+        let mut to = format!("{}/{}", folder, file_name);
+        let mut number = 2;
+        while moves.iter().any(|planned| planned.to == to) {
+            to = format!("{}/{}-{}", folder, file_name, number);
+            number += 1;
+        }
+        moves.push(CredentialsMove {
+            from: from.to_string(),
+            to,
+            shares: vec![share.name.clone()],
+        });
+    }
+    moves
+}
+
+/// Changes pointing the entries of `shares` to the new paths of `moves`, one per file,
+/// none written yet
+pub fn config_changes(
+    shares: &[RemoteSambaShareConfig],
+    moves: &[CredentialsMove],
+) -> Result<Vec<ConfigChange>, String> {
+    let mut files: BTreeMap<String, (String, String)> = BTreeMap::new();
+    for share in shares {
+        let Some(planned) = moves.iter().find(|planned| planned.from == share.option_credentials.trim()) else {
+            continue;
+        };
+        if !files.contains_key(&share.source_file) {
+            let original = read_file(&share.source_file)?;
+            files.insert(share.source_file.clone(), (original.clone(), original));
+        }
+        let updated = RemoteSambaShareConfig {
+            option_credentials: planned.to.clone(),
+            ..share.clone()
+        };
+        let (_, content) = files.get_mut(&share.source_file).expect("file read above");
+        *content = updated.replace_in(content, &share.name)?;
+    }
+
+    Ok(files
+        .into_iter()
+        .filter(|(_, (original, content))| original != content)
+        .map(|(path, (original, content))| ConfigChange::new(path, original, content))
+        .collect())
+}
+
+/// Move the files into `folder`, created for root only when missing, then write `changes`.
+/// The files are moved back when the configuration can't be written.
+pub fn migrate(folder: &str, moves: &[CredentialsMove], changes: &[ConfigChange]) -> Result<(), String> {
+    if !folder.starts_with('/') {
+        return Err("The credentials folder needs an absolute path".to_string());
+    }
+    let mut args = vec!["-c", MOVE_SCRIPT, "sh", folder];
+    for planned in moves {
+        args.extend([planned.from.as_str(), planned.to.as_str()]);
+    }
+    run_privileged("/bin/sh", &args, None)?;
+
+    if let Err(e) = ConfigChange::apply_all(changes) {
+        let mut back = vec!["-c", MOVE_BACK_SCRIPT, "sh"];
+        for planned in moves {
+            back.extend([planned.to.as_str(), planned.from.as_str()]);
+        }
+        if let Err(restore_error) = run_privileged("/bin/sh", &back, None) {
+            eprintln!("Failed to move the credentials files back: {}", restore_error);
+        }
+        return Err(e);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn share(name: &str, credentials: &str) -> RemoteSambaShareConfig {
+        RemoteSambaShareConfig::new(
+            name.to_string(),
+            "//nas/share".to_string(),
+            "cifs".to_string(),
+            credentials.to_string(),
+            String::new(),
+            String::new(),
+        )
+    }
+
+    #[test]
+    fn test_plan() {
+        let mut locked = share("/mnt/locked", "/root/locked-secrets");
+        locked.locked_reason = Some("uses a variable".to_string());
+        let shares = vec![
+            share("/mnt/media", "/etc/nixos/smb-secrets"),
+            share("/mnt/music", "/etc/nixos/smb-secrets"),
+            share("/mnt/backup", "/root/smb-secrets"),
+            share("/mnt/done", "/etc/samba/credentials/nas"),
+            share("/mnt/guest", ""),
+            locked,
+        ];
+        assert_eq!(
+            plan(&shares, "/etc/samba/credentials/"),
+            vec![
+                CredentialsMove {
+                    from: "/etc/nixos/smb-secrets".to_string(),
+                    to: "/etc/samba/credentials/smb-secrets".to_string(),
+                    shares: vec!["/mnt/media".to_string(), "/mnt/music".to_string()],
+                },
+                CredentialsMove {
+                    from: "/root/smb-secrets".to_string(),
+                    to: "/etc/samba/credentials/smb-secrets-2".to_string(),
+                    shares: vec!["/mnt/backup".to_string()],
+                },
+            ]
+        );
+    }
+}
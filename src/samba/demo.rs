@@ -0,0 +1,37 @@
+use crate::samba::command_runner::{set_runner, DemoRunner};
+use crate::samba::paths::set_config_path;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Command line flag enabling demo mode
+pub const DEMO_FLAG: &str = "--demo";
+
+/// Fake configuration with a few local and remote shares
+const DEMO_CONFIG: &str = include_str!("../../data/demo/default.nix");
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Switch the whole app to demo mode: the configuration is a temporary copy of
+/// the bundled demo file and commands changing the system are skipped.
+/// Returns the path of the temporary configuration.
+pub fn enable() -> Result<PathBuf, String> {
+    let dir = std::env::temp_dir().join(format!("samba-share-demo-{}", std::process::id()));
+    fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+    let config_file = dir.join("default.nix");
+    fs::write(&config_file, DEMO_CONFIG)
+        .map_err(|e| format!("Failed to write {}: {}", config_file.display(), e))?;
+
+    set_config_path(&config_file.to_string_lossy());
+    set_runner(Box::new(DemoRunner));
+    ENABLED.store(true, Ordering::Relaxed);
+
+    Ok(config_file)
+}
+
+/// Whether `enable` was called
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
@@ -0,0 +1,271 @@
+use crate::samba::command_runner::run_output;
+use crate::samba::mount_operations::list_all_shares;
+use crate::samba::nix_imports::config_files;
+use crate::samba::nix_parse::parse_file;
+use crate::samba::paths::config_path;
+use crate::samba::sandbox::host_path;
+use crate::samba::security_modules::{active_modules, samba_denials};
+use crate::samba::share_backend::backend;
+use crate::samba::share_config::{find_samba_settings, SambaShareConfig};
+use crate::utils::locale::{active_locale, system_locale};
+use std::fs;
+
+/// Lines of each journal excerpt included in the report
+const LOG_LINES: usize = 200;
+
+/// Placeholder written instead of secrets
+const REDACTED: &str = "***";
+
+/// One check of the environment, with a short explanation of the result
+pub struct CheckResult {
+    pub name: String,
+    pub ok: bool,
+    pub detail: String,
+}
+
+/// Run the checks included at the top of the diagnostics report
+pub fn run_checks() -> Vec<CheckResult> {
+    let mut checks = Vec::new();
+
+    let files = config_files(config_path());
+    for file in &files {
+        let result = fs::read_to_string(host_path(file))
+            .map_err(|e| e.to_string())
+            .and_then(|content| match parse_file(file, &content).errors().len() {
+                0 => Ok("parses without errors".to_string()),
+                errors => Err(format!("{} syntax error(s)", errors)),
+            });
+        checks.push(check(&format!("Configuration file {}", file.display()), result));
+    }
+
+    checks.push(check(
+        "Local shares",
+        SambaShareConfig::load_all()
+            .map(|shares| format!("{} share(s) in {}", shares.len(), backend().description())),
+    ));
+    checks.push(check(
+        "Remote shares",
+        list_all_shares().map(|shares| {
+            let mounted = shares.iter().filter(|share| share.is_mounted).count();
+            format!("{} share(s), {} mounted", shares.len(), mounted)
+        }),
+    ));
+    checks.push(check(
+        "Samba service",
+        run_output("systemctl", &["is-active", "samba-smbd.service"]),
+    ));
+    checks.push(check(
+        "Samba configuration (testparm)",
+        run_output("testparm", &["-s", "--suppress-prompt"]).map(|_| "valid".to_string()),
+    ));
+    checks.push(check("Access control (SELinux/AppArmor)", security_modules_check()));
+
+    checks
+}
+
+/// Build a plain text report to attach to bug reports.
+/// Secrets in the configuration excerpt are replaced by `***`.
+pub fn build_report() -> String {
+    let mut report = String::new();
+
+    report.push_str(&format!(
+        "samba-share {} diagnostics report\n",
+        env!("CARGO_PKG_VERSION")
+    ));
+    if let Ok(system) = run_output("uname", &["-srm"]) {
+        report.push_str(&format!("System: {}\n", system));
+    }
+    report.push_str(&format!(
+        "Locale: {} (requested: {})\n",
+        active_locale().unwrap_or("not set"),
+        system_locale().unwrap_or_else(|| "none".to_string())
+    ));
+
+    section(&mut report, "Checks");
+    for result in run_checks() {
+        report.push_str(&format!(
+            "[{}] {}: {}\n",
+            if result.ok { "OK" } else { "FAIL" },
+            result.name,
+            result.detail
+        ));
+    }
+
+    section(&mut report, "Configuration excerpt");
+    report.push_str(&config_excerpt());
+
+    section(&mut report, "Access control denials");
+    report.push_str(&denials_excerpt());
+
+    section(&mut report, "Samba log");
+    report.push_str(&journal(&["-u", "samba-smbd.service"]));
+
+    section(&mut report, "Mount log");
+    report.push_str(&journal(&["-k", "-g", "CIFS"]));
+
+    section(&mut report, "Application log");
+    report.push_str(&journal(&["--user", "-t", "samba-share"]));
+
+    report
+}
+
+/// Replace passwords and credentials in configuration or mount option text
+pub fn sanitize(text: &str) -> String {
+    // "smb passwd file" = "/path"; password = "..."; credentials = "..."
+    let quoted = regex::Regex::new(
+        r#"(?i)((?:password|passwd|secret|credentials)[^=\n"]*"?\s*=\s*)"[^"\n]*""#,
+    )
+    .expect("valid regex");
+    let text = quoted.replace_all(text, format!(r#"${{1}}"{}""#, REDACTED));
+
+    // password=... inside mount options
+    let option = regex::Regex::new(r#"(?i)\b(password|pass|credentials)=[^,"\s]*"#)
+        .expect("valid regex");
+    option
+        .replace_all(&text, format!("${{1}}={}", REDACTED))
+        .to_string()
+}
+
+/// Samba settings and CIFS fileSystems entries from every configuration file
+fn config_excerpt() -> String {
+    let filesystem = regex::Regex::new(r#"(?s)fileSystems\."[^"]*"\s*=\s*\{.*?\};"#)
+        .expect("valid regex");
+
+    let mut excerpt = String::new();
+    for file in config_files(config_path()) {
+        let Ok(content) = fs::read_to_string(host_path(&file)) else {
+            continue;
+        };
+
+        let mut parts = Vec::new();
+        if let Some(settings) = find_samba_settings(&parse_file(&file, &content).syntax()) {
+            parts.push(format!("services.samba.settings = {};", settings.text()));
+        }
+        parts.extend(
+            filesystem
+                .find_iter(&content)
+                .map(|m| m.as_str())
+                .filter(|entry| entry.contains("cifs"))
+                .map(|entry| entry.to_string()),
+        );
+
+        if !parts.is_empty() {
+            excerpt.push_str(&format!("# {}\n{}\n\n", file.display(), sanitize(&parts.join("\n"))));
+        }
+    }
+
+    if excerpt.is_empty() {
+        excerpt.push_str("(no Samba or CIFS configuration found)\n");
+    }
+    excerpt
+}
+
+/// Security modules enabled and whether they refused anything to Samba.
+/// A denial explains permission errors on shares with correct file permissions.
+fn security_modules_check() -> Result<String, String> {
+    let modules = active_modules();
+    if modules.is_empty() {
+        return Ok("neither SELinux nor AppArmor is enabled".to_string());
+    }
+    let names: Vec<String> = modules
+        .iter()
+        .map(|status| {
+            if status.enforcing {
+                status.module.name().to_string()
+            } else {
+                format!("{} (permissive)", status.module.name())
+            }
+        })
+        .collect();
+    let names = names.join(", ");
+
+    match samba_denials() {
+        Ok(denials) if denials.is_empty() => Ok(format!("{}, no denials for Samba", names)),
+        Ok(denials) => Err(format!(
+            "{}, {} denial(s) for Samba, see Access control denials",
+            names,
+            denials.len()
+        )),
+        Err(e) => Err(format!("{}, journal unavailable: {}", names, e)),
+    }
+}
+
+/// Recent denials of Samba processes, each with the share it concerns and a likely fix
+fn denials_excerpt() -> String {
+    if active_modules().is_empty() {
+        return "(no security module enabled)\n".to_string();
+    }
+    let denials = match samba_denials() {
+        Ok(denials) if denials.is_empty() => return "(no denials)\n".to_string(),
+        Ok(denials) => denials,
+        Err(e) => return format!("(unavailable: {})\n", e),
+    };
+    let shares = SambaShareConfig::load_all().unwrap_or_default();
+
+    let mut excerpt = String::new();
+    for denial in denials {
+        let share = denial.share(&shares);
+        excerpt.push_str(&format!(
+            "{} denied {} {} to {} ({})\n",
+            denial.module.name(),
+            denial.operation,
+            denial.path,
+            denial.process,
+            denial.context
+        ));
+        if let Some(share) = share {
+            excerpt.push_str(&format!("  Share: {}\n", share.name));
+        }
+        excerpt.push_str(&format!(
+            "  Suggested fix: {}\n",
+            denial.suggestion(share.map(|share| share.path.as_str()))
+        ));
+    }
+    excerpt
+}
+
+/// Last journal lines matching `filter`, or the reason they couldn't be read
+fn journal(filter: &[&str]) -> String {
+    let lines = LOG_LINES.to_string();
+    let mut args = vec!["--no-pager", "-o", "short-iso", "-n", lines.as_str()];
+    args.extend_from_slice(filter);
+
+    match run_output("journalctl", &args) {
+        Ok(output) if output.is_empty() => "(no entries)\n".to_string(),
+        Ok(output) => format!("{}\n", sanitize(&output)),
+        Err(e) => format!("(unavailable: {})\n", e),
+    }
+}
+
+/// Turn the outcome of a check into a report line
+fn check(name: &str, result: Result<String, String>) -> CheckResult {
+    let (ok, detail) = match result {
+        Ok(detail) => (true, detail),
+        Err(detail) => (false, detail),
+    };
+    CheckResult {
+        name: name.to_string(),
+        ok,
+        detail,
+    }
+}
+
+fn section(report: &mut String, title: &str) {
+    report.push_str(&format!("\n===== {} =====\n", title));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize() {
+        let text = r#"options = [ "credentials=/etc/smb-secrets" "password=hunter2" "uid=1000" ];
+"smb passwd file" = "/var/lib/samba/private/smbpasswd";"#;
+        let sanitized = sanitize(text);
+        assert!(!sanitized.contains("hunter2"));
+        assert!(!sanitized.contains("/etc/smb-secrets"));
+        assert!(!sanitized.contains("smbpasswd\""));
+        assert!(sanitized.contains("uid=1000"));
+    }
+}
@@ -0,0 +1,90 @@
+use crate::samba::command_runner::command;
+
+/// Free space under which a share is reported as running out, in percent of its filesystem
+const LOW_SPACE_PERCENT: u64 = 5;
+
+/// Free space under which a share is reported as running out whatever the filesystem size
+const LOW_SPACE_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+
+/// Filesystem holding a folder, with its size and the space left
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiskSpace {
+    pub fs_type: String,
+    pub total: u64,
+    /// Bytes unprivileged users can still write, reserved blocks excluded
+    pub available: u64,
+}
+
+impl DiskSpace {
+    /// Whether clients may soon fail to copy files to the share
+    pub fn is_low(&self) -> bool {
+        self.available < LOW_SPACE_BYTES || self.available * 100 < self.total * LOW_SPACE_PERCENT
+    }
+}
+
+/// Filesystem type and space of the filesystem holding `path`
+pub fn disk_space(path: &str) -> Result<DiskSpace, String> {
+    let output = command("df")
+        .args(["--output=fstype,size,avail", "-B1", path])
+        .output()
+        .map_err(|e| format!("Failed to run df: {}", e))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    parse_df(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Read the line after the header of `df --output=fstype,size,avail -B1`
+fn parse_df(output: &str) -> Result<DiskSpace, String> {
+    let fields: Vec<&str> = output
+        .lines()
+        .nth(1)
+        .map(|line| line.split_whitespace().collect())
+        .unwrap_or_default();
+    let [fs_type, total, available] = fields[..] else {
+        return Err(format!("Unexpected df output: {}", output.trim()));
+    };
+    let number = |value: &str| {
+        value
+            .parse::<u64>()
+            .map_err(|_| format!("Unexpected df size: {}", value))
+    };
+    Ok(DiskSpace {
+        fs_type: fs_type.to_string(),
+        total: number(total)?,
+        available: number(available)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_df() {
+        let output = "Type     1B-blocks        Avail\next4  502392610816 120000000000\n";
+        assert_eq!(
+            parse_df(output).unwrap(),
+            DiskSpace {
+                fs_type: "ext4".to_string(),
+                total: 502392610816,
+                available: 120000000000,
+            }
+        );
+        assert!(parse_df("Type 1B-blocks Avail\n").is_err());
+    }
+
+    #[test]
+    fn test_is_low() {
+        let gib = 1024 * 1024 * 1024;
+        let space = |total, available| DiskSpace {
+            fs_type: "ext4".to_string(),
+            total,
+            available,
+        };
+        assert!(!space(500 * gib, 100 * gib).is_low());
+        assert!(space(500 * gib, 20 * gib).is_low());
+        assert!(space(8 * gib, gib).is_low());
+        assert!(!space(8 * gib, 4 * gib).is_low());
+    }
+}
@@ -0,0 +1,129 @@
+use crate::samba::share_config::SambaShareConfig;
+use crate::samba::sudo_write::read_file;
+use std::collections::HashMap;
+
+/// Configuration generated by the last rebuild, the one Samba runs with
+const SMB_CONF: &str = "/etc/samba/smb.conf";
+
+/// How a share of the NixOS configuration differs from the running one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Drift {
+    /// The share isn't in smb.conf, it was added since the last rebuild
+    NotApplied,
+    /// Settings whose running value differs, named as in smb.conf
+    Differs(Vec<&'static str>),
+}
+
+/// Settings of every share in the running Samba configuration
+#[derive(Debug, Default)]
+pub struct ActiveConfig {
+    /// Settings by share name, names and keys lowercase
+    shares: HashMap<String, HashMap<String, String>>,
+}
+
+impl ActiveConfig {
+    /// Read the running smb.conf, None when Samba hasn't been configured by a rebuild yet
+    pub fn load() -> Option<Self> {
+        read_file(SMB_CONF).ok().map(|content| Self::parse(&content))
+    }
+
+    fn parse(content: &str) -> Self {
+        let mut shares: HashMap<String, HashMap<String, String>> = HashMap::new();
+        let mut current: Option<String> = None;
+
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            if let Some(section) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+                let name = section.trim().to_lowercase();
+                current = (name != "global").then_some(name);
+                if let Some(name) = &current {
+                    shares.entry(name.clone()).or_default();
+                }
+                continue;
+            }
+            let (Some(share), Some((key, value))) = (&current, line.split_once('=')) else {
+                continue;
+            };
+            let key = key.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+            if let Some(settings) = shares.get_mut(share) {
+                settings.insert(key, value.trim().to_string());
+            }
+        }
+        Self { shares }
+    }
+
+    /// How `share` differs from what Samba runs, None when the last rebuild applied it as it is
+    pub fn drift(&self, share: &SambaShareConfig) -> Option<Drift> {
+        let Some(settings) = self.shares.get(&share.name.to_lowercase()) else {
+            return Some(Drift::NotApplied);
+        };
+        let get = |keys: &[&str]| keys.iter().find_map(|key| settings.get(*key)).map(String::as_str);
+
+        let mut differs = Vec::new();
+        if get(&["path"]).is_some_and(|path| path.trim_end_matches('/') != share.path.trim_end_matches('/')) {
+            differs.push("path");
+        }
+        // Samba accepts synonyms, and "writable" is the opposite of "read only"
+        let writable = get(&["writable", "writeable", "write ok"]).and_then(parse_bool);
+        let read_only = get(&["read only"])
+            .and_then(parse_bool)
+            .or_else(|| writable.map(|writable| !writable));
+        let checks = [
+            ("browseable", get(&["browseable", "browsable"]).and_then(parse_bool), true, share.browsable),
+            ("read only", read_only, true, share.read_only),
+            ("guest ok", get(&["guest ok", "public"]).and_then(parse_bool), false, share.guest_ok),
+        ];
+        for (key, running, default, configured) in checks {
+            if running.unwrap_or(default) != configured {
+                differs.push(key);
+            }
+        }
+
+        (!differs.is_empty()).then_some(Drift::Differs(differs))
+    }
+}
+
+/// Samba boolean, None when the value isn't one
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_lowercase().as_str() {
+        "yes" | "true" | "1" => Some(true),
+        "no" | "false" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn share(name: &str, path: &str, read_only: bool, guest_ok: bool) -> SambaShareConfig {
+        SambaShareConfig::new(
+            name.to_string(),
+            path.to_string(),
+            true,
+            read_only,
+            guest_ok,
+            String::new(),
+            String::new(),
+        )
+    }
+
+    #[test]
+    fn test_drift() {
+        let active = ActiveConfig::parse(
+            "[global]\n\tworkgroup = WORKGROUP\n\n[Media]\n\tpath = /srv/media/\n\tread only = yes\n\n\
+             [public]\n\tpath = /srv/public\n\twriteable = yes\n\tpublic = yes\n; comment\n",
+        );
+
+        assert_eq!(active.drift(&share("media", "/srv/media", true, false)), None);
+        assert_eq!(active.drift(&share("public", "/srv/public", false, true)), None);
+        assert_eq!(
+            active.drift(&share("Media", "/srv/films", false, false)),
+            Some(Drift::Differs(vec!["path", "read only"]))
+        );
+        assert_eq!(active.drift(&share("backup", "/srv/backup", true, false)), Some(Drift::NotApplied));
+        assert_eq!(active.drift(&share("global", "/", true, false)), Some(Drift::NotApplied));
+    }
+}
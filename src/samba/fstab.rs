@@ -0,0 +1,138 @@
+use crate::samba::fstab_import::unescape;
+use crate::samba::mount_option_set::MountOptionSet;
+use crate::samba::remote_share_config::{MountBackend, RemoteSambaShareConfig};
+
+/// Option making the boot wait for the network before mounting, added to every line written
+const NETWORK_OPTION: &str = "_netdev";
+
+/// Options of existing lines the entries don't keep, the first ones meaning nothing
+/// and the last one being added back on write
+const IGNORED_OPTIONS: [&str; 3] = ["defaults", "auto", NETWORK_OPTION];
+
+/// Remote shares of the cifs lines of an fstab file
+pub fn parse_shares(content: &str, source_file: &str) -> Vec<RemoteSambaShareConfig> {
+    content
+        .lines()
+        .filter_map(cifs_fields)
+        .map(|fields| {
+            let options: Vec<String> = fields
+                .get(3)
+                .map(|options| options.split(',').map(str::to_string).collect())
+                .unwrap_or_default();
+            let parsed = MountOptionSet::parse(&options);
+            RemoteSambaShareConfig {
+                name: fields[1].clone(),
+                remote_path: fields[0].clone(),
+                fs_type: fields[2].clone(),
+                option_credentials: parsed.credentials.unwrap_or_default(),
+                force_user: parsed.uid.unwrap_or_default(),
+                force_group: parsed.gid.unwrap_or_default(),
+                extra_options: parsed
+                    .extras
+                    .into_iter()
+                    .filter(|option| !IGNORED_OPTIONS.contains(&option.as_str()))
+                    .collect(),
+                idle_timeout: parsed.idle_timeout,
+                systemd: parsed.systemd,
+                backend: MountBackend::Fstab,
+                source_file: source_file.to_string(),
+                locked_reason: None,
+            }
+        })
+        .collect()
+}
+
+/// fstab line of a share, mounted by systemd once the network is up
+pub fn entry_line(share: &RemoteSambaShareConfig) -> String {
+    let mut options = share.mount_options().to_options();
+    options.push(NETWORK_OPTION.to_string());
+    format!(
+        "{} {} {} {} 0 0",
+        escape(&share.remote_path),
+        escape(&share.name),
+        share.fs_type,
+        options.join(",")
+    )
+}
+
+/// `content` with the cifs line of `mount_point` replaced by `line`, or removed when
+/// it is None. A new line goes at the end of the file.
+pub fn set_entry(content: &str, mount_point: &str, line: Option<&str>) -> Result<String, String> {
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    let existing = lines
+        .iter()
+        .position(|current| cifs_fields(current).is_some_and(|fields| fields[1] == mount_point));
+
+    match (existing, line) {
+        (Some(index), Some(line)) => lines[index] = line.to_string(),
+        (Some(index), None) => {
+            lines.remove(index);
+        }
+        (None, Some(line)) => lines.push(line.to_string()),
+        (None, None) => return Err(format!("Could not find the fstab entry of '{}'", mount_point)),
+    }
+    Ok(lines.join("\n") + "\n")
+}
+
+/// Fields of a cifs line, `device mount-point type options dump pass`, unescaped
+fn cifs_fields(line: &str) -> Option<Vec<String>> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let fields: Vec<String> = line.split_whitespace().map(unescape).collect();
+    (fields.len() >= 3 && matches!(fields[2].as_str(), "cifs" | "smb3")).then_some(fields)
+}
+
+/// Octal escapes fstab needs for whitespace and backslashes in a field
+fn escape(field: &str) -> String {
+    field
+        .chars()
+        .map(|c| match c {
+            ' ' => "\\040".to_string(),
+            '\t' => "\\011".to_string(),
+            '\n' => "\\012".to_string(),
+            '\\' => "\\134".to_string(),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FSTAB: &str = "# /etc/fstab
+UUID=1234 / ext4 defaults 0 1
+//nas/my\\040files /mnt/my\\040files cifs credentials=/etc/samba/credentials/nas,uid=1000,x-systemd.automount,_netdev,vers=3.0 0 0
+";
+
+    #[test]
+    fn test_parse_shares() {
+        let shares = parse_shares(FSTAB, "/etc/fstab");
+        assert_eq!(shares.len(), 1);
+        let share = &shares[0];
+        assert_eq!(share.name, "/mnt/my files");
+        assert_eq!(share.remote_path, "//nas/my files");
+        assert_eq!(share.option_credentials, "/etc/samba/credentials/nas");
+        assert_eq!(share.force_user, "1000");
+        assert!(share.systemd.automount);
+        assert_eq!(share.extra_options, vec!["vers=3.0"]);
+        assert_eq!(share.backend, MountBackend::Fstab);
+    }
+
+    #[test]
+    fn test_set_entry() {
+        let share = &parse_shares(FSTAB, "/etc/fstab")[0];
+        let line = entry_line(share);
+        assert!(line.starts_with("//nas/my\\040files /mnt/my\\040files cifs credentials=/etc/samba/credentials/nas,"));
+        assert!(line.ends_with(",_netdev 0 0"));
+        assert_eq!(parse_shares(&line, "/etc/fstab")[0].extra_options, vec!["vers=3.0"]);
+
+        let removed = set_entry(FSTAB, "/mnt/my files", None).unwrap();
+        assert_eq!(removed, "# /etc/fstab\nUUID=1234 / ext4 defaults 0 1\n");
+        let added = set_entry(&removed, "/mnt/music", Some("//nas/music /mnt/music cifs guest 0 0")).unwrap();
+        assert!(added.ends_with("defaults 0 1\n//nas/music /mnt/music cifs guest 0 0\n"));
+        assert!(set_entry(&removed, "/mnt/music", None).is_err());
+    }
+}
@@ -0,0 +1,180 @@
+use crate::samba::config_change::ConfigChange;
+use crate::samba::mount_option_set::MountOptionSet;
+use crate::samba::remote_share_config::RemoteSambaShareConfig;
+use crate::samba::sandbox::host_path;
+use crate::samba::sudo_write::read_file;
+use std::path::Path;
+
+/// Mount table of the system, the one a setup from another distribution left behind
+pub const FSTAB: &str = "/etc/fstab";
+
+/// Comment written above the lines moved to the NixOS configuration
+const MOVED_COMMENT: &str = "# Moved to the NixOS configuration by Samba Share Manager:";
+
+/// Options left out of imported entries, the NixOS entry mounts on first access instead
+const DROPPED_OPTIONS: [&str; 5] = ["defaults", "auto", "noauto", "x-systemd.automount", "_netdev"];
+
+/// A cifs line of an fstab file, as a share of the NixOS configuration
+#[derive(Debug, Clone)]
+pub struct FstabEntry {
+    /// Index of the line in the file
+    pub line: usize,
+    pub share: RemoteSambaShareConfig,
+    /// The line had a `password=` option, which isn't imported since the Nix store is world-readable
+    pub had_password: bool,
+}
+
+/// Read the cifs entries of an fstab file
+pub fn load(path: &str) -> Result<Vec<FstabEntry>, String> {
+    Ok(parse(&read_file(path)?))
+}
+
+/// Whether NixOS generates the file, in which case editing it is pointless.
+/// The generated /etc/fstab is a link into the Nix store.
+pub fn is_generated(path: &str) -> bool {
+    host_path(Path::new(path))
+        .symlink_metadata()
+        .map(|metadata| metadata.file_type().is_symlink())
+        .unwrap_or(false)
+}
+
+/// Change commenting out `lines` of the fstab file, so the shares aren't mounted twice.
+/// Nothing is written yet, the change goes with the ones adding the shares.
+pub fn comment_out(path: &str, lines: &[usize]) -> Result<ConfigChange, String> {
+    if is_generated(path) {
+        return Err(format!("{} is generated by NixOS and can't be edited", path));
+    }
+    let original = read_file(path)?;
+    let content = comment_out_lines(&original, lines);
+    Ok(ConfigChange::new(path.to_string(), original, content))
+}
+
+fn comment_out_lines(content: &str, lines: &[usize]) -> String {
+    let mut result = String::new();
+    for (index, line) in content.lines().enumerate() {
+        if lines.contains(&index) {
+            result.push_str(MOVED_COMMENT);
+            result.push('\n');
+            result.push_str("# ");
+        }
+        result.push_str(line);
+        result.push('\n');
+    }
+    result
+}
+
+/// fstab lines are `device mount-point type options dump pass`, spaces in fields written `\040`
+fn parse(content: &str) -> Vec<FstabEntry> {
+    let mut entries = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<String> = line.split_whitespace().map(unescape).collect();
+        if fields.len() < 3 || !matches!(fields[2].as_str(), "cifs" | "smb3") {
+            continue;
+        }
+        let options: Vec<String> = fields
+            .get(3)
+            .map(|options| options.split(',').map(str::to_string).collect())
+            .unwrap_or_default();
+
+        let parsed = MountOptionSet::parse(&options);
+        let mut share = RemoteSambaShareConfig::new(
+            fields[1].clone(),
+            fields[0].clone(),
+            fields[2].clone(),
+            parsed.credentials.unwrap_or_default(),
+            parsed.uid.unwrap_or_default(),
+            parsed.gid.unwrap_or_default(),
+        );
+        // Without a timeout of its own the imported share gets the usual one
+        if parsed.idle_timeout.is_some() {
+            share.idle_timeout = parsed.idle_timeout;
+        }
+        let is_password = |option: &str| option.starts_with("password=") || option.starts_with("pass=");
+        share.extra_options = parsed
+            .extras
+            .into_iter()
+            .filter(|option| {
+                !DROPPED_OPTIONS.contains(&option.as_str())
+                    && !is_password(option)
+                    && !option.starts_with("x-systemd.")
+            })
+            .collect();
+
+        entries.push(FstabEntry {
+            line: index,
+            share,
+            had_password: options.iter().any(|option| is_password(option)),
+        });
+    }
+    entries
+}
+
+/// Decode the octal escapes fstab uses for spaces, tabs and backslashes
+pub(crate) fn unescape(field: &str) -> String {
+    let mut result = String::new();
+    let mut rest = field;
+    while let Some(pos) = rest.find('\\') {
+        result.push_str(&rest[..pos]);
+        let code = rest.get(pos + 1..pos + 4).and_then(|code| u8::from_str_radix(code, 8).ok());
+        match code {
+            Some(code) => {
+                result.push(code as char);
+                rest = &rest[pos + 4..];
+            }
+            None => {
+                result.push('\\');
+                rest = &rest[pos + 1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::samba::idle_timeout;
+
+    const FSTAB_CONTENT: &str = "# /etc/fstab
+UUID=1234 / ext4 defaults 0 1
+//nas/media /mnt/media cifs credentials=/etc/nas.cred,uid=1000,gid=100,_netdev,vers=3.0 0 0
+//nas/my\\040files /mnt/my\\040files cifs username=alice,password=secret,x-systemd.idle-timeout=30min 0 0
+# //nas/old /mnt/old cifs defaults 0 0
+";
+
+    #[test]
+    fn test_parse() {
+        let entries = parse(FSTAB_CONTENT);
+        assert_eq!(entries.len(), 2);
+
+        let media = &entries[0];
+        assert_eq!(media.line, 2);
+        assert_eq!(media.share.name, "/mnt/media");
+        assert_eq!(media.share.remote_path, "//nas/media");
+        assert_eq!(media.share.option_credentials, "/etc/nas.cred");
+        assert_eq!(media.share.force_user, "1000");
+        assert_eq!(media.share.extra_options, vec!["vers=3.0"]);
+        assert_eq!(media.share.idle_timeout, Some(idle_timeout::DEFAULT));
+        assert!(!media.had_password);
+
+        let files = &entries[1];
+        assert_eq!(files.share.name, "/mnt/my files");
+        assert_eq!(files.share.remote_path, "//nas/my files");
+        assert_eq!(files.share.extra_options, vec!["username=alice"]);
+        assert_eq!(files.share.idle_timeout, Some(1800));
+        assert!(files.had_password);
+    }
+
+    #[test]
+    fn test_comment_out_lines() {
+        let content = comment_out_lines(FSTAB_CONTENT, &[2]);
+        assert!(content.contains(&format!("{}\n# //nas/media /mnt/media cifs", MOVED_COMMENT)));
+        assert!(content.contains("\n//nas/my\\040files"));
+        assert_eq!(content.lines().count(), FSTAB_CONTENT.lines().count() + 1);
+    }
+}
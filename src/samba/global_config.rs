@@ -0,0 +1,187 @@
+use crate::samba::network::invalid_host_entries;
+use crate::samba::nix_edit::set_attr;
+use crate::samba::nix_parse::parse_file;
+use crate::samba::security::SecurityLevel;
+use crate::samba::share_config::{
+    find_direct_attrset, find_samba_settings, get_attrpath_name, get_attrvalue, parse_name_list,
+    SambaShareConfig,
+};
+use crate::samba::sudo_write::{read_file, write_with_sudo};
+use rnix::{Root, SyntaxKind, SyntaxNode};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Protocol names accepted by `server min protocol` / `server max protocol`, oldest first
+pub const SMB_PROTOCOLS: [&str; 6] = ["NT1", "SMB2_02", "SMB2_10", "SMB3_00", "SMB3_02", "SMB3_11"];
+
+/// Keys of the global section edited by the server settings, in the order they are written.
+/// Other keys are left as they are.
+pub(crate) const MANAGED_KEYS: [&str; 8] = [
+    "smb encrypt",
+    "server signing",
+    "server min protocol",
+    "server max protocol",
+    "allow insecure wide links",
+    "interfaces",
+    "bind interfaces only",
+    "hosts allow",
+];
+
+/// Server-wide options stored in `services.samba.settings.global`
+#[derive(Debug, Clone, Default)]
+pub struct SambaGlobalConfig {
+    pub smb_encrypt: SecurityLevel,
+    pub server_signing: SecurityLevel,
+    /// `server min protocol`, None to keep Samba's default
+    pub server_min_protocol: Option<String>,
+    /// `server max protocol`, None to keep Samba's default
+    pub server_max_protocol: Option<String>,
+    /// `allow insecure wide links`, required for shares using `wide links`
+    pub allow_insecure_wide_links: bool,
+    /// `interfaces`, network interfaces or addresses Samba serves, empty for all
+    pub interfaces: Vec<String>,
+    /// `bind interfaces only`, stop listening on interfaces not in `interfaces`
+    pub bind_interfaces_only: bool,
+    /// `hosts allow`, addresses and networks allowed to connect, empty for all
+    pub hosts_allow: Vec<String>,
+}
+
+impl SambaGlobalConfig {
+    /// Load the global section, falling back to defaults when it doesn't exist yet
+    pub fn load() -> Result<Self, String> {
+        // The global section sits next to the shares, possibly in an imported file
+        let config_path = SambaShareConfig::settings_file();
+        let content = read_file(&config_path)?;
+
+        let parsed = parse_file(Path::new(&config_path), &content);
+        let root = parsed.syntax();
+
+        let props = find_samba_global(&root)
+            .map(|global| section_props(&global))
+            .unwrap_or_default();
+
+        Ok(Self {
+            smb_encrypt: props
+                .get("smb encrypt")
+                .map(|v| SecurityLevel::parse(v))
+                .unwrap_or_default(),
+            server_signing: props
+                .get("server signing")
+                .map(|v| SecurityLevel::parse(v))
+                .unwrap_or_default(),
+            server_min_protocol: props.get("server min protocol").cloned(),
+            server_max_protocol: props.get("server max protocol").cloned(),
+            allow_insecure_wide_links: props
+                .get("allow insecure wide links")
+                .map(|v| v == "yes")
+                .unwrap_or(false),
+            interfaces: props
+                .get("interfaces")
+                .map(|v| parse_name_list(v))
+                .unwrap_or_default(),
+            bind_interfaces_only: props
+                .get("bind interfaces only")
+                .map(|v| v == "yes")
+                .unwrap_or(false),
+            hosts_allow: props
+                .get("hosts allow")
+                .map(|v| parse_name_list(v))
+                .unwrap_or_default(),
+        })
+    }
+
+    /// Whether the legacy SMB1 protocol is allowed by the minimum protocol setting
+    pub fn smb1_enabled(&self) -> bool {
+        self.server_min_protocol
+            .as_deref()
+            .and_then(protocol_rank)
+            .map(|rank| rank == 0)
+            .unwrap_or(false)
+    }
+
+    /// Check that the options are consistent before writing them
+    pub fn validate(&self) -> Result<(), String> {
+        let min = self.server_min_protocol.as_deref().and_then(protocol_rank);
+        let max = self.server_max_protocol.as_deref().and_then(protocol_rank);
+        if let (Some(min), Some(max)) = (min, max) {
+            if min > max {
+                return Err("Minimum protocol is newer than maximum protocol".to_string());
+            }
+        }
+        if self.bind_interfaces_only && self.interfaces.is_empty() {
+            return Err("Select at least one interface to listen on".to_string());
+        }
+        let invalid_hosts = invalid_host_entries(&self.hosts_allow);
+        if !invalid_hosts.is_empty() {
+            return Err(format!("Invalid allowed hosts: {}", invalid_hosts.join(" ")));
+        }
+        Ok(())
+    }
+
+    /// Write the global options back, only touching the keys managed here
+    pub fn save(&self) -> Result<(), String> {
+        self.validate()?;
+
+        let config_path = SambaShareConfig::settings_file();
+        let mut content = read_file(&config_path)?;
+
+        let parsed = Root::parse(&content);
+        if !parsed.errors().is_empty() {
+            return Err("Configuration file has syntax errors".to_string());
+        }
+        if find_samba_global(&parsed.syntax()).is_none() {
+            return Err(
+                "No services.samba.settings.global section found. Add a share first.".to_string(),
+            );
+        }
+
+        let interfaces = self.interfaces.join(" ");
+        let hosts_allow = self.hosts_allow.join(" ");
+        let values = [
+            self.smb_encrypt.encrypt_value(),
+            self.server_signing.signing_value(),
+            self.server_min_protocol.as_deref(),
+            self.server_max_protocol.as_deref(),
+            self.allow_insecure_wide_links.then_some("yes"),
+            (!self.interfaces.is_empty()).then_some(interfaces.as_str()),
+            self.bind_interfaces_only.then_some("yes"),
+            (!self.hosts_allow.is_empty()).then_some(hosts_allow.as_str()),
+        ];
+
+        for (key, value) in MANAGED_KEYS.into_iter().zip(values) {
+            let value = value.map(|v| format!("\"{}\"", v));
+            content = set_attr(&content, find_samba_global, key, value.as_deref())?;
+        }
+
+        write_with_sudo(&config_path, &content)
+    }
+}
+
+/// Find the services.samba.settings.global attrset node
+pub(crate) fn find_samba_global(root: &SyntaxNode) -> Option<SyntaxNode> {
+    find_samba_settings(root).and_then(|settings| find_direct_attrset(&settings, "global"))
+}
+
+/// Position of a protocol in `SMB_PROTOCOLS`, mapping Samba's aliases and SMB1 dialects
+pub fn protocol_rank(protocol: &str) -> Option<usize> {
+    let protocol = protocol.trim().to_uppercase();
+    match protocol.as_str() {
+        "CORE" | "COREPLUS" | "LANMAN1" | "LANMAN2" | "NT1" => Some(0),
+        "SMB2" => Some(2),
+        "SMB3" => Some(5),
+        other => SMB_PROTOCOLS.iter().position(|p| *p == other),
+    }
+}
+
+/// Collect the key/value pairs of a settings section such as `global`
+pub(crate) fn section_props(section: &SyntaxNode) -> HashMap<String, String> {
+    let mut props = HashMap::new();
+    for entry in section.children() {
+        if entry.kind() == SyntaxKind::NODE_ATTRPATH_VALUE {
+            if let (Some(key), Some(value)) = (get_attrpath_name(&entry), get_attrvalue(&entry)) {
+                props.insert(key, value);
+            }
+        }
+    }
+    props
+}
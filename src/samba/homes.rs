@@ -0,0 +1,193 @@
+use crate::samba::global_config::section_props;
+use crate::samba::nix_edit::{remove_node, set_attr};
+use crate::samba::share_config::{
+    find_samba_settings, get_attrpath_name, unsupported_expression, yes_no, SambaShareConfig,
+};
+use crate::samba::sudo_write::{read_file, write_with_sudo};
+use rnix::{Root, SyntaxKind, SyntaxNode};
+
+/// Name of the special section Samba turns into one share per user
+pub const HOMES: &str = "homes";
+
+/// `valid users` value restricting each home folder to the person it belongs to
+const OWNER_ONLY: &str = "%S";
+
+/// The `homes` section of `services.samba.settings`. When it exists, someone
+/// connecting to a share named after their user gets their home folder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HomesConfig {
+    pub enabled: bool,
+    /// `read only = no`, people can change the files of their home folder
+    pub writable: bool,
+    /// `browseable`, also list a share called "homes". Each person's own folder is listed anyway.
+    pub browseable: bool,
+    /// `valid users = %S`, nobody but the owner can open a home folder
+    pub owner_only: bool,
+}
+
+impl Default for HomesConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            writable: true,
+            browseable: false,
+            owner_only: true,
+        }
+    }
+}
+
+impl HomesConfig {
+    /// Load the homes section, disabled when it doesn't exist
+    pub fn load() -> Result<Self, String> {
+        let content = read_file(SambaShareConfig::settings_file())?;
+        Ok(Self::parse(&content))
+    }
+
+    fn parse(content: &str) -> Self {
+        let root = Root::parse(content).syntax();
+        let Some(section) = find_samba_homes(&root) else {
+            return Self::default();
+        };
+        let props = section_props(&section);
+        let flag = |key: &str| props.get(key).map(|value| is_yes(value));
+
+        Self {
+            enabled: true,
+            // Samba's defaults, which apply to an existing section
+            writable: flag("read only")
+                .map(|read_only| !read_only)
+                .or_else(|| flag("writable").or_else(|| flag("writeable")))
+                .unwrap_or(false),
+            browseable: flag("browseable").unwrap_or(true),
+            owner_only: props.get("valid users").map(String::as_str) == Some(OWNER_ONLY),
+        }
+    }
+
+    /// Add, update or remove the homes section, only touching the keys managed here
+    pub fn save(&self) -> Result<(), String> {
+        let config_path = SambaShareConfig::settings_file();
+        let content = read_file(&config_path)?;
+        let new_content = self.apply(&content)?;
+        write_with_sudo(&config_path, &new_content)
+    }
+
+    fn apply(&self, content: &str) -> Result<String, String> {
+        let parsed = Root::parse(content);
+        if !parsed.errors().is_empty() {
+            return Err("Configuration file has syntax errors".to_string());
+        }
+        let root = parsed.syntax();
+        let settings = find_samba_settings(&root)
+            .ok_or("No services.samba.settings section found. Add a share first.")?;
+
+        let entry = homes_entry(&settings);
+        if let Some(reason) = entry.as_ref().and_then(unsupported_expression) {
+            return Err(format!("The {} section can't be edited here: {}", HOMES, reason));
+        }
+
+        let mut content = match (entry, self.enabled) {
+            (Some(entry), false) => return Ok(remove_node(content, &entry)),
+            (None, false) => return Ok(content.to_string()),
+            (Some(_), true) => content.to_string(),
+            (None, true) => set_attr(content, find_samba_settings, HOMES, Some("{ }"))?,
+        };
+
+        // Another list of valid users is left alone
+        let current_valid_users = find_samba_homes(&Root::parse(&content).syntax())
+            .and_then(|section| section_props(&section).remove("valid users"));
+        let valid_users = if self.owner_only {
+            Some(OWNER_ONLY)
+        } else if current_valid_users.as_deref() == Some(OWNER_ONLY) {
+            None
+        } else {
+            current_valid_users.as_deref()
+        };
+
+        let values = [
+            ("browseable", Some(yes_no(self.browseable))),
+            ("read only", Some(yes_no(!self.writable))),
+            // Synonyms of "read only" that would contradict it
+            ("writable", None),
+            ("writeable", None),
+            ("valid users", valid_users),
+        ];
+        for (key, value) in values {
+            let value = value.map(|v| format!("\"{}\"", v));
+            content = set_attr(&content, find_samba_homes, key, value.as_deref())?;
+        }
+        Ok(content)
+    }
+}
+
+/// Find the services.samba.settings.homes attrset node
+fn find_samba_homes(root: &SyntaxNode) -> Option<SyntaxNode> {
+    find_samba_settings(root)
+        .and_then(|settings| homes_entry(&settings))
+        .and_then(|entry| entry.children().find(|child| child.kind() == SyntaxKind::NODE_ATTR_SET))
+}
+
+/// The `homes = { ... };` entry of the settings, whether its name is quoted or not
+fn homes_entry(settings: &SyntaxNode) -> Option<SyntaxNode> {
+    settings.children().find(|child| {
+        child.kind() == SyntaxKind::NODE_ATTRPATH_VALUE
+            && get_attrpath_name(child).as_deref() == Some(HOMES)
+    })
+}
+
+fn is_yes(value: &str) -> bool {
+    matches!(value.to_lowercase().as_str(), "yes" | "true" | "1")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"{
+  services.samba = {
+    enable = true;
+    settings = {
+      global = {
+        "workgroup" = "WORKGROUP";
+      };
+    };
+  };
+}"#;
+
+    #[test]
+    fn test_enable_and_disable() {
+        let homes = HomesConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        let enabled = homes.apply(CONFIG).unwrap();
+        assert!(enabled.contains(
+            r#""homes" = { "browseable" = "no"; "read only" = "no"; "valid users" = "%S"; };"#
+        ));
+        assert_eq!(HomesConfig::parse(&enabled), homes);
+
+        let disabled = HomesConfig::default().apply(&enabled).unwrap();
+        assert_eq!(disabled, CONFIG);
+        assert!(!HomesConfig::parse(&disabled).enabled);
+    }
+
+    #[test]
+    fn test_existing_section_keeps_other_options() {
+        let config = CONFIG.replace(
+            "    };\n  };",
+            "      homes = {\n        writable = \"yes\";\n        \"valid users\" = \"%S admin\";\n        \"create mask\" = \"0700\";\n      };\n    };\n  };",
+        );
+        let homes = HomesConfig::parse(&config);
+        assert!(homes.enabled && homes.writable && homes.browseable && !homes.owner_only);
+
+        let updated = HomesConfig {
+            writable: false,
+            ..homes
+        }
+        .apply(&config)
+        .unwrap();
+        assert!(updated.contains("\"create mask\" = \"0700\";"));
+        assert!(updated.contains("\"valid users\" = \"%S admin\";"));
+        assert!(updated.contains("\"read only\" = \"yes\";"));
+        assert!(!updated.contains("writable"));
+    }
+}
@@ -0,0 +1,99 @@
+use crate::samba::command_runner::command;
+
+/// Mount option making systemd unmount a share left unused
+pub const OPTION_PREFIX: &str = "x-systemd.idle-timeout=";
+
+/// Seconds written for new shares
+pub const DEFAULT: u32 = 300;
+
+/// Timeouts offered in the UI, None standing for never
+pub const CHOICES: [Option<u32>; 4] = [Some(300), Some(1800), Some(7200), None];
+
+/// Seconds in a systemd time span like `300`, `90s`, `30min` or `2h`.
+/// None for `0` and `infinity`, which disable the timeout, or a span that can't be read.
+pub fn parse_timespan(value: &str) -> Option<u32> {
+    let value = value.trim();
+    if value == "infinity" {
+        return None;
+    }
+
+    let mut seconds = 0;
+    // systemd accepts several parts, "1h 30min"
+    for part in value.split_whitespace() {
+        let split = part.find(|c: char| !c.is_ascii_digit()).unwrap_or(part.len());
+        let (number, unit) = part.split_at(split);
+        let number: u32 = number.parse().ok()?;
+        let factor = match unit {
+            "" | "s" | "sec" | "second" | "seconds" => 1,
+            "m" | "min" | "minute" | "minutes" => 60,
+            "h" | "hr" | "hour" | "hours" => 3600,
+            "d" | "day" | "days" => 86400,
+            _ => return None,
+        };
+        seconds += number.checked_mul(factor)?;
+    }
+    (seconds > 0).then_some(seconds)
+}
+
+/// Timeout set by mount options. Without the option systemd never unmounts the share.
+pub fn from_options(options: &[String]) -> Option<u32> {
+    options
+        .iter()
+        .find_map(|option| option.strip_prefix(OPTION_PREFIX))
+        .and_then(parse_timespan)
+}
+
+/// Mount option for a timeout, None when the share should stay mounted
+pub fn option(timeout: Option<u32>) -> Option<String> {
+    timeout.map(|seconds| format!("{}{}", OPTION_PREFIX, seconds))
+}
+
+/// Timeout the running automount unit of `mount_point` applies, which differs from the
+/// configuration until the next rebuild. None when no such unit is loaded.
+pub fn effective(mount_point: &str) -> Option<Option<u32>> {
+    let unit = command("systemd-escape")
+        .args(["--path", "--suffix=automount", mount_point])
+        .output()
+        .ok()?;
+    let unit = String::from_utf8_lossy(&unit.stdout).trim().to_string();
+
+    let output = command("systemctl")
+        .args(["show", "--property=LoadState,TimeoutIdleUSec", &unit])
+        .output()
+        .ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let property = |key: &str| {
+        stdout
+            .lines()
+            .find_map(|line| line.strip_prefix(key)?.strip_prefix('='))
+            .map(str::to_string)
+    };
+    if property("LoadState")? != "loaded" {
+        return None;
+    }
+    Some(property("TimeoutIdleUSec").and_then(|value| parse_timespan(&value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_timespan() {
+        assert_eq!(parse_timespan("300"), Some(300));
+        assert_eq!(parse_timespan("30min"), Some(1800));
+        assert_eq!(parse_timespan("1h 30min"), Some(5400));
+        assert_eq!(parse_timespan("0"), None);
+        assert_eq!(parse_timespan("infinity"), None);
+        assert_eq!(parse_timespan("soon"), None);
+    }
+
+    #[test]
+    fn test_from_options() {
+        let options = vec!["noauto".to_string(), "x-systemd.idle-timeout=2h".to_string()];
+        assert_eq!(from_options(&options), Some(7200));
+        assert_eq!(from_options(&["noauto".to_string()]), None);
+        assert_eq!(option(Some(300)).as_deref(), Some("x-systemd.idle-timeout=300"));
+        assert_eq!(option(None), None);
+    }
+}
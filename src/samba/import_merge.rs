@@ -0,0 +1,220 @@
+use crate::samba::config_change::ConfigChange;
+use crate::samba::fstab_import::FSTAB;
+use crate::samba::mount_option_set::MountOptionSet;
+use crate::samba::paths::config_path;
+use crate::samba::remote_share_config::{MountBackend, RemoteSambaShareConfig};
+use crate::samba::sudo_write::read_file;
+use std::collections::{BTreeMap, HashSet};
+
+/// Setting of a remote share compared when merging an import
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    RemotePath,
+    FsType,
+    Credentials,
+    UserId,
+    GroupId,
+    IdleTimeout,
+    Options,
+}
+
+/// How an imported share fits in the configuration
+#[derive(Debug, Clone)]
+pub enum MergeItem {
+    /// The mount point isn't configured yet
+    Addition(RemoteSambaShareConfig),
+    /// The mount point is configured with other settings
+    Conflict {
+        existing: Box<RemoteSambaShareConfig>,
+        incoming: RemoteSambaShareConfig,
+        differences: Vec<Field>,
+    },
+    /// The mount point is configured with the same settings, nothing to write
+    Unchanged(RemoteSambaShareConfig),
+}
+
+impl MergeItem {
+    /// Mount point the item is about
+    pub fn name(&self) -> &str {
+        match self {
+            MergeItem::Addition(share) | MergeItem::Unchanged(share) => &share.name,
+            MergeItem::Conflict { incoming, .. } => &incoming.name,
+        }
+    }
+}
+
+/// Compare imported shares with the configured ones, keeping the order of the import.
+/// A mount point imported twice keeps its first entry.
+pub fn plan(incoming: Vec<RemoteSambaShareConfig>, configured: &[RemoteSambaShareConfig]) -> Vec<MergeItem> {
+    let mut seen = HashSet::new();
+    incoming
+        .into_iter()
+        .filter(|incoming| seen.insert(incoming.name.clone()))
+        .map(|incoming| {
+            let Some(existing) = configured.iter().find(|share| share.name == incoming.name) else {
+                return MergeItem::Addition(incoming);
+            };
+            let differences = differences(existing, &incoming);
+            if differences.is_empty() {
+                MergeItem::Unchanged(incoming)
+            } else {
+                MergeItem::Conflict {
+                    existing: Box::new(existing.clone()),
+                    incoming,
+                    differences,
+                }
+            }
+        })
+        .collect()
+}
+
+/// Settings of `incoming` that differ from `existing`. The idle timeout only counts
+/// for fileSystems entries, autofs has one for the whole map.
+pub fn differences(existing: &RemoteSambaShareConfig, incoming: &RemoteSambaShareConfig) -> Vec<Field> {
+    // Only the options without a field of their own, their order doesn't count
+    let options = |a: &[String], b: &[String]| {
+        let extras = |options: &[String]| MountOptionSet {
+            extras: options.to_vec(),
+            ..MountOptionSet::default()
+        };
+        !extras(a).diff(&extras(b)).is_empty()
+    };
+    [
+        (Field::RemotePath, existing.remote_path != incoming.remote_path),
+        (Field::FsType, existing.fs_type != incoming.fs_type),
+        (Field::Credentials, existing.option_credentials != incoming.option_credentials),
+        (Field::UserId, existing.force_user != incoming.force_user),
+        (Field::GroupId, existing.force_group != incoming.force_group),
+        (
+            Field::IdleTimeout,
+            existing.backend.uses_systemd() && existing.idle_timeout != incoming.idle_timeout,
+        ),
+        (Field::Options, options(&existing.extra_options, &incoming.extra_options)),
+    ]
+    .into_iter()
+    .filter_map(|(field, differs)| differs.then_some(field))
+    .collect()
+}
+
+/// Changes writing the items to keep: additions go to the main configuration file or /etc/fstab,
+/// imported settings replace the conflicting entries where they are defined.
+/// One change per file, none written yet, so they can go through `ConfigChange::apply_all`.
+pub fn changes(items: &[(MergeItem, bool)]) -> Result<Vec<ConfigChange>, String> {
+    // Original and edited content of every file touched
+    let mut files: BTreeMap<String, (String, String)> = BTreeMap::new();
+    let mut edit = |path: &str, apply: &dyn Fn(&str) -> Result<String, String>| -> Result<(), String> {
+        if !files.contains_key(path) {
+            let original = read_file(path)?;
+            files.insert(path.to_string(), (original.clone(), original));
+        }
+        let (_, content) = files.get_mut(path).expect("file read above");
+        *content = apply(content)?;
+        Ok(())
+    };
+
+    for (item, _) in items.iter().filter(|(_, keep)| *keep) {
+        match item {
+            MergeItem::Addition(share) => {
+                // Outside NixOS the imported shares become fstab lines
+                let backend = MountBackend::available()[0];
+                let share = RemoteSambaShareConfig {
+                    backend,
+                    ..share.clone()
+                };
+                let path = if backend == MountBackend::Fstab { FSTAB } else { config_path() };
+                edit(path, &|content| share.add_to(content))?
+            }
+            MergeItem::Conflict { existing, incoming, .. } => {
+                if let Some(reason) = &existing.locked_reason {
+                    return Err(format!("'{}' can't be edited here: {}", existing.name, reason));
+                }
+                let replacement = replacement(existing, incoming);
+                edit(&existing.source_file, &|content| replacement.replace_in(content, &existing.name))?;
+            }
+            MergeItem::Unchanged(_) => {}
+        }
+    }
+
+    Ok(files
+        .into_iter()
+        .filter(|(_, (original, content))| original != content)
+        .map(|(path, (original, content))| ConfigChange::new(path, original, content))
+        .collect())
+}
+
+/// Imported settings written where the existing entry is, with its backend
+fn replacement(existing: &RemoteSambaShareConfig, incoming: &RemoteSambaShareConfig) -> RemoteSambaShareConfig {
+    RemoteSambaShareConfig {
+        idle_timeout: if existing.backend.uses_systemd() {
+            incoming.idle_timeout
+        } else {
+            None
+        },
+        backend: existing.backend,
+        source_file: existing.source_file.clone(),
+        locked_reason: None,
+        ..incoming.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn share(name: &str, remote_path: &str) -> RemoteSambaShareConfig {
+        RemoteSambaShareConfig::new(
+            name.to_string(),
+            remote_path.to_string(),
+            "cifs".to_string(),
+            String::new(),
+            String::new(),
+            String::new(),
+        )
+    }
+
+    #[test]
+    fn test_plan() {
+        let mut media = share("/mnt/media", "//nas/media");
+        media.extra_options = vec!["vers=3.0".to_string(), "ro".to_string()];
+        let configured = vec![media.clone(), share("/mnt/backup", "//nas/backup")];
+
+        let mut same_media = media.clone();
+        same_media.extra_options.reverse();
+        let mut other_backup = share("/mnt/backup", "//nas2/backup");
+        other_backup.force_user = "1000".to_string();
+        let items = plan(
+            vec![
+                same_media,
+                other_backup,
+                share("/mnt/films", "//nas/films"),
+                share("/mnt/films", "//nas/old-films"),
+            ],
+            &configured,
+        );
+
+        assert!(matches!(items[0], MergeItem::Unchanged(_)));
+        match &items[1] {
+            MergeItem::Conflict { differences, .. } => {
+                assert_eq!(differences, &vec![Field::RemotePath, Field::UserId])
+            }
+            other => panic!("expected a conflict, got {:?}", other),
+        }
+        assert!(matches!(items[2], MergeItem::Addition(_)));
+        assert_eq!(items[2].name(), "/mnt/films");
+        assert_eq!(items.len(), 3);
+    }
+
+    #[test]
+    fn test_replacement_keeps_backend() {
+        let mut existing = share("/mnt/media", "//nas/media");
+        existing.backend = MountBackend::Autofs;
+        existing.source_file = "/etc/nixos/mounts.nix".to_string();
+        let incoming = share("/mnt/media", "//nas2/media");
+
+        let replaced = replacement(&existing, &incoming);
+        assert_eq!(replaced.remote_path, "//nas2/media");
+        assert_eq!(replaced.backend, MountBackend::Autofs);
+        assert_eq!(replaced.source_file, "/etc/nixos/mounts.nix");
+        assert_eq!(replaced.idle_timeout, None);
+    }
+}
@@ -0,0 +1,499 @@
+use crate::samba::demo;
+use crate::samba::mount_operations::{validate_mount_point, validate_remote_url};
+use crate::samba::root::skip_escalation;
+use crate::samba::sandbox::is_sandboxed;
+use crate::samba::sudo_write::{run_privileged, run_privileged_output};
+use serde::{Deserialize, Serialize};
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::fmt;
+use std::io::Read;
+use std::path::Path;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+/// Argument starting the process as the privileged helper of `run`, reading a
+/// `MountRequest` on stdin and printing the outcome on stdout
+pub const HELPER_FLAG: &str = "--mount-helper";
+
+/// libmount exit code of a successful mount or unmount
+const MNT_EX_SUCCESS: i32 = 0;
+/// libmount exit code of a failed mount or unmount
+const MNT_EX_FAIL: i32 = 32;
+
+/// `struct libmnt_context`, only handled through pointers
+#[repr(C)]
+struct LibmntContext {
+    _private: [u8; 0],
+}
+
+#[link(name = "mount")]
+extern "C" {
+    fn mnt_new_context() -> *mut LibmntContext;
+    fn mnt_free_context(cxt: *mut LibmntContext);
+    fn mnt_context_set_source(cxt: *mut LibmntContext, source: *const c_char) -> c_int;
+    fn mnt_context_set_target(cxt: *mut LibmntContext, target: *const c_char) -> c_int;
+    fn mnt_context_set_fstype(cxt: *mut LibmntContext, fstype: *const c_char) -> c_int;
+    fn mnt_context_set_options(cxt: *mut LibmntContext, optstr: *const c_char) -> c_int;
+    fn mnt_context_mount(cxt: *mut LibmntContext) -> c_int;
+    fn mnt_context_umount(cxt: *mut LibmntContext) -> c_int;
+    fn mnt_context_get_excode(cxt: *mut LibmntContext, rc: c_int, buf: *mut c_char, bufsz: usize) -> c_int;
+    fn mnt_context_syscall_called(cxt: *mut LibmntContext) -> c_int;
+    fn mnt_context_get_syscall_errno(cxt: *mut LibmntContext) -> c_int;
+}
+
+/// Whether a failure happened while mounting or unmounting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Operation {
+    Mount,
+    Unmount,
+}
+
+/// Why libmount, or the mount.cifs helper it runs, failed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MountError {
+    pub operation: Operation,
+    /// `MNT_EX_*` code of libmount, or the exit status of the helper
+    pub code: i32,
+    /// errno of the failed system call, None when it wasn't reached or is unknown
+    pub errno: Option<i32>,
+    /// Explanation of libmount or the helper, possibly empty
+    pub message: String,
+}
+
+impl MountError {
+    pub fn new(operation: Operation, message: impl Into<String>) -> Self {
+        Self {
+            operation,
+            code: MNT_EX_FAIL,
+            errno: None,
+            message: message.into(),
+        }
+    }
+
+    /// Complete the error with what the helper printed. mount.cifs reports the
+    /// errno of the kernel as `mount error(13): Permission denied`.
+    pub fn with_helper_output(mut self, output: &str) -> Self {
+        let output = output.trim();
+        if self.errno.is_none() {
+            self.errno = output.lines().find_map(|line| {
+                let (_, rest) = line.split_once("mount error(")?;
+                rest.split_once(')')?.0.parse().ok()
+            });
+        }
+        if self.message.is_empty() {
+            self.message = output.to_string();
+        }
+        self
+    }
+
+    /// Whether the attempt gave up waiting, so the mount may still have gone through
+    pub fn is_timeout(&self) -> bool {
+        self.errno == Some(libc::ETIMEDOUT)
+    }
+
+    /// Whether the server didn't answer rather than refused, which may go away by itself
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self.errno,
+            Some(
+                libc::ETIMEDOUT
+                    | libc::EHOSTDOWN
+                    | libc::EHOSTUNREACH
+                    | libc::ENETUNREACH
+                    | libc::ECONNREFUSED
+                    | libc::EAGAIN
+            )
+        )
+    }
+}
+
+impl fmt::Display for MountError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let explanation = match (self.operation, self.errno) {
+            (Operation::Mount, Some(libc::EACCES | libc::EPERM)) => {
+                "Permission denied. Check your credentials or run with sudo."
+            }
+            (Operation::Mount, Some(libc::ECONNREFUSED)) => "Connection refused. Server may be offline or unreachable.",
+            (Operation::Mount, Some(libc::EBUSY)) => "Mount point is already in use or mounted.",
+            (Operation::Mount, Some(libc::ENOENT | libc::ENXIO)) => "Server or share not found. Check the remote URL.",
+            (Operation::Mount, Some(libc::EINVAL)) => "Invalid mount options. Check your configuration.",
+            (Operation::Mount, Some(libc::EHOSTDOWN | libc::EHOSTUNREACH | libc::ENETUNREACH)) => {
+                "Host is unreachable. Check network connectivity."
+            }
+            (Operation::Mount, Some(libc::ETIMEDOUT)) => "The server didn't answer in time. Check that it is online.",
+            (Operation::Unmount, Some(libc::EINVAL)) => "The specified path is not currently mounted.",
+            (Operation::Unmount, Some(libc::EBUSY)) => {
+                "Mount point is busy. Close any programs using files from this share."
+            }
+            (Operation::Unmount, Some(libc::EPERM | libc::EACCES)) => "Permission denied. You may need to run with sudo.",
+            _ => {
+                let action = match self.operation {
+                    Operation::Mount => "Mount failed",
+                    Operation::Unmount => "Unmount failed",
+                };
+                return match self.message.is_empty() {
+                    true => write!(f, "{} (code {})", action, self.code),
+                    false => write!(f, "{}: {}", action, self.message),
+                };
+            }
+        };
+        f.write_str(explanation)
+    }
+}
+
+/// A libmount context, freed on drop. Mounting through it needs root.
+struct Context {
+    cxt: *mut LibmntContext,
+    operation: Operation,
+}
+
+impl Context {
+    fn new(operation: Operation) -> Result<Self, MountError> {
+        let cxt = unsafe { mnt_new_context() };
+        if cxt.is_null() {
+            return Err(MountError::new(operation, "Failed to create a libmount context"));
+        }
+        Ok(Self { cxt, operation })
+    }
+
+    fn set(
+        &mut self,
+        setter: unsafe extern "C" fn(*mut LibmntContext, *const c_char) -> c_int,
+        value: &str,
+    ) -> Result<(), MountError> {
+        let value = CString::new(value)
+            .map_err(|_| MountError::new(self.operation, format!("'{}' contains a null byte", value)))?;
+        if unsafe { setter(self.cxt, value.as_ptr()) } != 0 {
+            return Err(MountError::new(
+                self.operation,
+                format!("libmount refused '{}'", value.to_string_lossy()),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Outcome of `mnt_context_mount` or `mnt_context_umount`, which return 0 even
+    /// when the system call or the helper failed
+    fn result(&self, rc: c_int) -> Result<(), MountError> {
+        let mut buf = [0 as c_char; 1024];
+        let code = unsafe { mnt_context_get_excode(self.cxt, rc, buf.as_mut_ptr(), buf.len()) };
+        if code == MNT_EX_SUCCESS {
+            return Ok(());
+        }
+
+        let errno = (unsafe { mnt_context_syscall_called(self.cxt) } == 1)
+            .then(|| unsafe { mnt_context_get_syscall_errno(self.cxt) })
+            .filter(|errno| *errno != 0);
+        let message = unsafe { CStr::from_ptr(buf.as_ptr()) }.to_string_lossy().to_string();
+        Err(MountError {
+            operation: self.operation,
+            code,
+            errno,
+            message,
+        })
+    }
+}
+
+impl Drop for Context {
+    fn drop(&mut self) {
+        unsafe { mnt_free_context(self.cxt) };
+    }
+}
+
+/// Mount `source` on `target`. CIFS goes through the mount.cifs helper, which reads
+/// credentials files and resolves the server.
+pub fn mount(source: &str, target: &str, fstype: &str, options: &str) -> Result<(), MountError> {
+    let mut context = Context::new(Operation::Mount)?;
+    context.set(mnt_context_set_source, source)?;
+    context.set(mnt_context_set_target, target)?;
+    context.set(mnt_context_set_fstype, fstype)?;
+    context.set(mnt_context_set_options, options)?;
+    let rc = unsafe { mnt_context_mount(context.cxt) };
+    context.result(rc)
+}
+
+/// Mount `target` with the source and options of its fstab entry
+pub fn mount_configured(target: &str) -> Result<(), MountError> {
+    let mut context = Context::new(Operation::Mount)?;
+    context.set(mnt_context_set_target, target)?;
+    let rc = unsafe { mnt_context_mount(context.cxt) };
+    context.result(rc)
+}
+
+/// Unmount whatever is mounted on `target`
+pub fn umount(target: &str) -> Result<(), MountError> {
+    let mut context = Context::new(Operation::Unmount)?;
+    context.set(mnt_context_set_target, target)?;
+    let rc = unsafe { mnt_context_umount(context.cxt) };
+    context.result(rc)
+}
+
+/// A mount or unmount, sent to the privileged helper as JSON
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MountRequest {
+    /// Mount `source`, giving up after `timeout` seconds (0 for no limit)
+    Mount {
+        source: String,
+        target: String,
+        fstype: String,
+        options: String,
+        timeout: u32,
+    },
+    /// Mount the fstab entry of `target`
+    MountConfigured { target: String },
+    Unmount { target: String },
+}
+
+/// Script mounting or unmounting on the host from a Flatpak sandbox, where this
+/// binary can't run. Arguments: seconds (0 for none), the program, then its arguments.
+/// Reports a timeout like mount.cifs, as `timeout` itself stays silent.
+const HOST_MOUNT_SCRIPT: &str = r#"export PATH=/run/current-system/sw/bin:/run/wrappers/bin:$PATH
+seconds="$1"
+shift
+timeout --kill-after=5 "$seconds" "$@"
+status=$?
+if [ "$status" -eq 124 ]; then
+  echo "mount error(110): timed out after ${seconds}s" >&2
+fi
+exit "$status"
+"#;
+
+impl MountRequest {
+    fn operation(&self) -> Operation {
+        match self {
+            Self::Mount { .. } | Self::MountConfigured { .. } => Operation::Mount,
+            Self::Unmount { .. } => Operation::Unmount,
+        }
+    }
+
+    /// Check a request read by the helper, which runs as root whoever sent it: only
+    /// CIFS shares, on mount points passing the checks of `mount_share`
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            Self::Mount {
+                source, target, fstype, ..
+            } => {
+                if fstype != "cifs" {
+                    return Err(format!("Only CIFS shares can be mounted, not '{}'", fstype));
+                }
+                validate_remote_url(source)?;
+                validate_mount_point(Path::new(target))
+            }
+            Self::MountConfigured { target } | Self::Unmount { target } => validate_mount_point(Path::new(target)),
+        }
+    }
+
+    /// Carry out the request in this process, which needs root
+    fn perform(&self) -> Result<(), MountError> {
+        match self {
+            Self::Mount {
+                source,
+                target,
+                fstype,
+                options,
+                timeout: 0,
+            } => mount(source, target, fstype, options),
+            Self::Mount {
+                source,
+                target,
+                fstype,
+                options,
+                timeout,
+            } => {
+                // A server that doesn't answer can block the mount for minutes
+                let (sender, receiver) = mpsc::channel();
+                let (source, target, fstype, options) =
+                    (source.clone(), target.clone(), fstype.clone(), options.clone());
+                thread::spawn(move || {
+                    let _ = sender.send(mount(&source, &target, &fstype, &options));
+                });
+                receiver
+                    .recv_timeout(Duration::from_secs(u64::from(*timeout)))
+                    .unwrap_or_else(|_| {
+                        Err(MountError {
+                            errno: Some(libc::ETIMEDOUT),
+                            ..MountError::new(Operation::Mount, format!("timed out after {}s", timeout))
+                        })
+                    })
+            }
+            Self::MountConfigured { target } => mount_configured(target),
+            Self::Unmount { target } => umount(target),
+        }
+    }
+
+    /// Carry out the request with the host's mount and umount, from a Flatpak sandbox
+    fn perform_on_host(&self) -> Result<(), MountError> {
+        let seconds = match self {
+            Self::Mount { timeout, .. } => timeout.to_string(),
+            _ => "0".to_string(),
+        };
+        let mut args = vec!["-c", HOST_MOUNT_SCRIPT, "sh", &seconds];
+        match self {
+            Self::Mount {
+                source,
+                target,
+                fstype,
+                options,
+                ..
+            } => args.extend(["mount", "-t", fstype, source, target, "-o", options]),
+            Self::MountConfigured { target } => args.extend(["mount", target]),
+            Self::Unmount { target } => args.extend(["umount", target]),
+        }
+        run_privileged("/bin/sh", &args, None)
+            .map(|_| ())
+            .map_err(|e| MountError::new(self.operation(), "").with_helper_output(&e))
+    }
+}
+
+/// Mount or unmount with root privileges: in this process when it has them, otherwise
+/// through this binary started as `HELPER_FLAG` by the launchers of `run_privileged`
+pub fn run(request: &MountRequest) -> Result<(), MountError> {
+    if demo::is_enabled() {
+        eprintln!("[demo] skipping {:?}", request);
+        return Ok(());
+    }
+    if is_sandboxed() {
+        return request.perform_on_host();
+    }
+    if skip_escalation() {
+        return request.perform();
+    }
+
+    let operation = request.operation();
+    let exe = std::env::current_exe()
+        .map_err(|e| MountError::new(operation, format!("Failed to locate the mount helper: {}", e)))?;
+    let input = serde_json::to_string(request).map_err(|e| MountError::new(operation, e.to_string()))?;
+    let output = run_privileged_output(&exe.to_string_lossy(), &[HELPER_FLAG], Some(&input))
+        .map_err(|e| MountError::new(operation, e))?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    match serde_json::from_str::<Result<(), MountError>>(stdout.trim()) {
+        Ok(result) => result.map_err(|e| e.with_helper_output(&stderr)),
+        Err(_) => Err(MountError::new(operation, "").with_helper_output(&format!("{}{}", stdout, stderr))),
+    }
+}
+
+/// Like `run`, trying first without privileges, as fstab entries with the `user`
+/// option allow, so no password is asked for them
+pub fn run_as_user_first(request: &MountRequest) -> Result<(), MountError> {
+    if !demo::is_enabled() && !is_sandboxed() && !skip_escalation() && request.perform().is_ok() {
+        return Ok(());
+    }
+    run(request)
+}
+
+/// Entry point of the process started with `HELPER_FLAG`, returning its exit code
+pub fn run_helper() -> i32 {
+    let mut input = String::new();
+    if let Err(e) = std::io::stdin().read_to_string(&mut input) {
+        eprintln!("Failed to read the mount request: {}", e);
+        return 1;
+    }
+    let request: MountRequest = match serde_json::from_str(&input) {
+        Ok(request) => request,
+        Err(e) => {
+            eprintln!("Invalid mount request: {}", e);
+            return 1;
+        }
+    };
+
+    let result = request
+        .validate()
+        .map_err(|e| MountError::new(request.operation(), e))
+        .and_then(|()| request.perform());
+    match serde_json::to_string(&result) {
+        Ok(reply) => {
+            println!("{}", reply);
+            0
+        }
+        Err(e) => {
+            eprintln!("Failed to encode the mount result: {}", e);
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_helper_output() {
+        let error = MountError::new(Operation::Mount, "")
+            .with_helper_output("mount error(112): Host is down\nRefer to the mount.cifs(8) manual page\n");
+        assert_eq!(error.errno, Some(libc::EHOSTDOWN));
+        assert!(error.is_transient());
+        assert_eq!(error.to_string(), "Host is unreachable. Check network connectivity.");
+
+        let error = MountError::new(Operation::Mount, "").with_helper_output("mount error(13): Permission denied");
+        assert!(!error.is_transient());
+        assert_eq!(error.to_string(), "Permission denied. Check your credentials or run with sudo.");
+
+        let error = MountError::new(Operation::Unmount, "").with_helper_output("umount: /mnt/nas: not mounted.");
+        assert_eq!(error.errno, None);
+        assert_eq!(error.to_string(), "Unmount failed: umount: /mnt/nas: not mounted.");
+    }
+
+    #[test]
+    fn test_unmount_errors() {
+        // The errno of the system call wins over what the helper printed
+        let error = MountError {
+            errno: Some(libc::EBUSY),
+            ..MountError::new(Operation::Unmount, "")
+        }
+        .with_helper_output("mount error(13): Permission denied");
+        assert_eq!(error.errno, Some(libc::EBUSY));
+        assert_eq!(
+            error.to_string(),
+            "Mount point is busy. Close any programs using files from this share."
+        );
+
+        let error = MountError {
+            errno: Some(libc::EINVAL),
+            ..MountError::new(Operation::Unmount, "").with_helper_output("umount: /mnt/nas: not mounted.")
+        };
+        assert_eq!(error.to_string(), "The specified path is not currently mounted.");
+        assert_eq!(error.message, "umount: /mnt/nas: not mounted.");
+    }
+
+    #[test]
+    fn test_validate_request() {
+        let mount = |source: &str, target: &str, fstype: &str| MountRequest::Mount {
+            source: source.to_string(),
+            target: target.to_string(),
+            fstype: fstype.to_string(),
+            options: String::new(),
+            timeout: 0,
+        };
+        assert!(mount("//nas/media", "/mnt/media", "cifs").validate().is_ok());
+        assert!(mount("//nas/media", "/mnt/media", "ext4").validate().is_err());
+        assert!(mount("/dev/sda1", "/mnt/media", "cifs").validate().is_err());
+        assert!(mount("//nas/media", "/", "cifs").validate().is_err());
+        let unmount = MountRequest::Unmount {
+            target: "/mnt/../etc".to_string(),
+        };
+        assert!(unmount.validate().is_err());
+    }
+
+    #[test]
+    fn test_request_json() {
+        let request = MountRequest::Mount {
+            source: "//nas/media".to_string(),
+            target: "/mnt/media".to_string(),
+            fstype: "cifs".to_string(),
+            options: "guest,uid=1000".to_string(),
+            timeout: 30,
+        };
+        let json = serde_json::to_string(&request).unwrap();
+        assert_eq!(serde_json::from_str::<MountRequest>(&json).unwrap(), request);
+
+        let reply: Result<(), MountError> = Err(MountError {
+            errno: Some(libc::EACCES),
+            ..MountError::new(Operation::Mount, "")
+        });
+        let json = serde_json::to_string(&reply).unwrap();
+        assert_eq!(serde_json::from_str::<Result<(), MountError>>(&json).unwrap(), reply);
+    }
+}
@@ -0,0 +1,62 @@
+/// Autostart entry mounting the selected locations, in ~/.config/autostart
+pub const AUTOSTART_FILE: &str = "samba-share-mounts.desktop";
+
+/// SMB locations among the file manager bookmarks, one `uri [label]` per line
+pub fn smb_bookmarks(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .filter(|uri| uri.starts_with("smb://"))
+        .map(|uri| uri.to_string())
+        .collect()
+}
+
+/// Desktop entry running `gio mount` on every location at login.
+/// GVfs takes the passwords from the keyring, where the file manager saved them.
+pub fn desktop_entry(uris: &[String]) -> String {
+    let args: Vec<String> = uris.iter().map(|uri| quote_exec_arg(uri)).collect();
+    format!(
+        "[Desktop Entry]\n\
+         Type=Application\n\
+         Name=Samba Share Mounts\n\
+         Comment=Mount the network shares selected in Samba Share Manager\n\
+         Exec=gio mount {}\n\
+         NoDisplay=true\n\
+         X-GNOME-Autostart-enabled=true\n",
+        args.join(" ")
+    )
+}
+
+/// Quote an argument of an `Exec` key, where `%` introduces field codes
+fn quote_exec_arg(arg: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in arg.chars() {
+        match c {
+            '"' | '`' | '$' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '%' => quoted.push_str("%%"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_smb_bookmarks() {
+        let content = "file:///home/alice/Projects Projects\nsmb://nas/media Media\nsmb://nas/backup\n";
+        assert_eq!(smb_bookmarks(content), vec!["smb://nas/media", "smb://nas/backup"]);
+    }
+
+    #[test]
+    fn test_desktop_entry() {
+        let entry = desktop_entry(&["smb://nas/my%20files".to_string(), "smb://nas/media".to_string()]);
+        assert!(entry.contains("Exec=gio mount \"smb://nas/my%%20files\" \"smb://nas/media\"\n"));
+    }
+}
@@ -0,0 +1,39 @@
+use crate::samba::command_runner::command;
+
+/// Syslog tag used by the full_audit VFS module
+pub const AUDIT_TAG: &str = "smbd_audit";
+
+/// Read the most recent full_audit entries from the journal.
+/// When `share` is given, only entries for that share are returned.
+pub fn read_audit_log(share: Option<&str>, max_lines: usize) -> Result<Vec<String>, String> {
+    let output = command("journalctl")
+        .args(["-t", AUDIT_TAG, "--no-pager", "-o", "short-iso", "-n"])
+        .arg(max_lines.to_string())
+        .output()
+        .map_err(|e| format!("Failed to run journalctl: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("journalctl failed: {}", stderr.trim()));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(filter_audit_lines(&stdout, share))
+}
+
+/// Keep journal lines belonging to `share`, matching the `user|ip|share|...` prefix
+fn filter_audit_lines(output: &str, share: Option<&str>) -> Vec<String> {
+    output
+        .lines()
+        .filter(|line| !line.starts_with("-- "))
+        .filter(|line| match share {
+            Some(name) => line
+                .split('|')
+                .nth(2)
+                .map(|field| field.trim() == name)
+                .unwrap_or(false),
+            None => true,
+        })
+        .map(|line| line.to_string())
+        .collect()
+}
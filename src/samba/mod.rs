@@ -1,12 +1,71 @@
+pub mod access;
+pub mod activity;
+pub mod autofs;
+pub mod command_runner;
+pub mod config_audit;
+pub mod config_change;
+pub mod credentials_file;
+pub mod credentials_migration;
+pub mod demo;
+pub mod diagnostics;
+pub mod disk_space;
+pub mod drift;
+pub mod fstab;
+pub mod fstab_import;
+pub mod global_config;
+pub mod homes;
+pub mod idle_timeout;
+pub mod import_merge;
+pub mod libmount;
+pub mod login_mounts;
+pub mod logs;
+pub mod mount_defaults;
 pub mod mount_operations;
+pub mod mount_option_set;
+pub mod mount_tuning;
+pub mod neighborhood;
+pub mod network;
+pub mod nix_edit;
+pub mod nix_imports;
+pub mod nix_parse;
+pub mod open_files;
+pub mod overview;
+pub mod paths;
+pub mod presets;
+pub mod recycle;
+pub mod quota;
+pub mod rebuild;
 pub mod remote_share_config;
+pub mod report;
+pub mod root;
+pub mod sandbox;
+pub mod secrets;
+pub mod security;
+pub mod security_modules;
+pub mod server_template;
+pub mod share_backend;
+pub mod share_browser;
 pub mod share_config;
+pub mod share_test;
+pub mod smb_conf;
+pub mod spotlight;
 pub mod sudo_write;
+pub mod terminal;
+pub mod users;
+pub mod wake_on_lan;
+pub mod warnings;
+pub mod ws_discovery;
 
+pub use config_change::ConfigChange;
+pub use global_config::SambaGlobalConfig;
 pub use mount_operations::{
-    is_mounted, list_all_shares, list_cifs_mounts, mount_share, unmount_share, MountOptions,
-    MountedShare,
+    is_leftover_mount_point, is_mounted, list_all_shares, list_cifs_mounts, mount_share,
+    move_mount_point, reapply_mount, release_mount_point, remount_share,
+    remount_with_configured_options, remove_mount_point, unmount_share, MountCredentials, MountOptions,
+    MountedShare, ShareState,
 };
+pub use overview::Overview;
 pub use remote_share_config::RemoteSambaShareConfig;
+pub use security::SecurityLevel;
 pub use share_config::{get_system_groups, get_system_users, SambaShareConfig};
 pub use sudo_write::write_with_sudo;
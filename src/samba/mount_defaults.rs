@@ -0,0 +1,178 @@
+use crate::samba::idle_timeout::{self, parse_timespan};
+use serde::{Deserialize, Serialize};
+
+/// Option mounting the share on first access through a systemd automount unit
+const AUTOMOUNT: &str = "x-systemd.automount";
+
+/// Option keeping the share from being mounted while the computer starts
+const NOAUTO: &str = "noauto";
+
+/// Option limiting how long systemd waits for the server to answer
+const DEVICE_TIMEOUT_PREFIX: &str = "x-systemd.device-timeout=";
+
+/// Option limiting how long the mount command may take
+const MOUNT_TIMEOUT_PREFIX: &str = "x-systemd.mount-timeout=";
+
+/// Seconds written for both timeouts of new shares, so an offline server doesn't hold up boot
+pub const DEFAULT_TIMEOUT: u32 = 10;
+
+/// How systemd mounts a `fileSystems` entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SystemdMount {
+    /// Mount on first access (`x-systemd.automount`)
+    pub automount: bool,
+    /// Don't mount while the computer starts (`noauto`)
+    pub noauto: bool,
+    /// `x-systemd.device-timeout` in seconds, None for systemd's default
+    pub device_timeout: Option<u32>,
+    /// `x-systemd.mount-timeout` in seconds, None for systemd's default
+    pub mount_timeout: Option<u32>,
+}
+
+impl Default for SystemdMount {
+    fn default() -> Self {
+        Self {
+            automount: true,
+            noauto: true,
+            device_timeout: Some(DEFAULT_TIMEOUT),
+            mount_timeout: Some(DEFAULT_TIMEOUT),
+        }
+    }
+}
+
+impl SystemdMount {
+    /// None of the settings, as for entries that aren't mounted by systemd
+    pub const NONE: Self = Self {
+        automount: false,
+        noauto: false,
+        device_timeout: None,
+        mount_timeout: None,
+    };
+
+    /// Settings found in the options of an entry, none of them set when they are missing
+    pub fn from_options(options: &[String]) -> Self {
+        let timeout = |prefix: &str| {
+            options
+                .iter()
+                .find_map(|option| option.strip_prefix(prefix))
+                .and_then(parse_timespan)
+        };
+        Self {
+            automount: options.iter().any(|option| option == AUTOMOUNT),
+            noauto: options.iter().any(|option| option == NOAUTO),
+            device_timeout: timeout(DEVICE_TIMEOUT_PREFIX),
+            mount_timeout: timeout(MOUNT_TIMEOUT_PREFIX),
+        }
+    }
+
+    /// Whether a mount option is one of the settings held here
+    pub fn is_option(option: &str) -> bool {
+        option == AUTOMOUNT
+            || option == NOAUTO
+            || option.starts_with(DEVICE_TIMEOUT_PREFIX)
+            || option.starts_with(MOUNT_TIMEOUT_PREFIX)
+    }
+
+    /// Mount options of the settings, in the order they are written
+    pub fn options(&self) -> Vec<String> {
+        let mut options = Vec::new();
+        if self.automount {
+            options.push(AUTOMOUNT.to_string());
+        }
+        if self.noauto {
+            options.push(NOAUTO.to_string());
+        }
+        if let Some(seconds) = self.device_timeout {
+            options.push(format!("{}{}s", DEVICE_TIMEOUT_PREFIX, seconds));
+        }
+        if let Some(seconds) = self.mount_timeout {
+            options.push(format!("{}{}s", MOUNT_TIMEOUT_PREFIX, seconds));
+        }
+        options
+    }
+}
+
+/// Settings every new remote share starts from, chosen in the preferences
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MountDefaults {
+    pub systemd: SystemdMount,
+    /// Seconds without use before the share is unmounted, None to keep it mounted
+    pub idle_timeout: Option<u32>,
+    /// Other mount options added to new shares, such as `vers=3.1.1`
+    pub extra_options: Vec<String>,
+}
+
+impl Default for MountDefaults {
+    fn default() -> Self {
+        Self {
+            systemd: SystemdMount::default(),
+            idle_timeout: Some(idle_timeout::DEFAULT),
+            extra_options: Vec::new(),
+        }
+    }
+}
+
+/// Split mount options typed as in fstab, separated by commas or spaces
+pub fn parse_option_list(value: &str) -> Vec<String> {
+    value
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|option| !option.is_empty())
+        .map(|option| option.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(list: &[&str]) -> Vec<String> {
+        list.iter().map(|option| option.to_string()).collect()
+    }
+
+    #[test]
+    fn test_default_options() {
+        assert_eq!(
+            SystemdMount::default().options(),
+            options(&[
+                "x-systemd.automount",
+                "noauto",
+                "x-systemd.device-timeout=10s",
+                "x-systemd.mount-timeout=10s",
+            ])
+        );
+    }
+
+    #[test]
+    fn test_from_options() {
+        let systemd = SystemdMount::from_options(&options(&[
+            "credentials=/etc/nixos/smb-secrets",
+            "x-systemd.automount",
+            "x-systemd.mount-timeout=1min",
+            "uid=1000",
+        ]));
+        assert_eq!(
+            systemd,
+            SystemdMount {
+                automount: true,
+                noauto: false,
+                device_timeout: None,
+                mount_timeout: Some(60),
+            }
+        );
+        assert_eq!(
+            SystemdMount::from_options(&SystemdMount::default().options()),
+            SystemdMount::default()
+        );
+        assert!(SystemdMount::is_option("x-systemd.device-timeout=5s"));
+        assert!(!SystemdMount::is_option("x-systemd.idle-timeout=300"));
+    }
+
+    #[test]
+    fn test_parse_option_list() {
+        assert_eq!(
+            parse_option_list("vers=3.1.1, nofail  cache=loose"),
+            options(&["vers=3.1.1", "nofail", "cache=loose"])
+        );
+        assert!(parse_option_list(" ").is_empty());
+    }
+}
@@ -1,39 +1,149 @@
-use serde::Deserialize;
+use crate::samba::command_runner::command;
+use crate::samba::credentials_file::Credentials;
+use crate::samba::idle_timeout;
+use crate::samba::libmount::{self, MountRequest};
+use crate::samba::mount_defaults::{MountDefaults, SystemdMount};
+use crate::samba::mount_option_set::MountOptionSet;
+use crate::samba::nix_parse::CANCELLED;
+use crate::samba::open_files::{blocking_processes, describe};
+use crate::samba::remote_share_config::RemoteSambaShareConfig;
+use crate::samba::root::desktop_user_ids;
+use crate::samba::sandbox::{host_path, shared_temp_dir};
+use crate::samba::sudo_write::run_privileged;
+use serde::{Deserialize, Serialize};
 use std::fs;
-use std::os::unix::fs::PermissionsExt;
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
 use std::path::{Path, PathBuf};
-use std::process::Command;
-use users::{get_current_gid, get_current_uid};
+use std::thread;
+use std::time::Duration;
+
+/// Folders whose mount points belong to root, removed with escalation
+const ROOT_MOUNT_DIRS: [&str; 2] = ["/media", "/mnt"];
 
 /// Represents a mounted CIFS/SMB share
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MountedShare {
     pub source: String,      // //server/share
     pub target: String,      // /media/blender
     pub fstype: String,      // cifs
-    pub options: String,     // rw,credentials=...,uid=1000
+    pub options: MountOptionSet, // rw,credentials=...,uid=1000
     #[serde(default)]
     pub is_mounted: bool,
+    /// NixOS configuration entry, None for mounts not declared in the configuration
+    #[serde(default)]
+    pub config: Option<RemoteSambaShareConfig>,
+}
+
+/// Whether a listed share is declared in the configuration and mounted, in the
+/// order shares are shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ShareState {
+    /// Declared in the configuration and mounted
+    Mounted,
+    /// Declared in the configuration, not mounted now
+    NotMounted,
+    /// Mounted without an entry in the configuration, by hand or by another tool
+    Unmanaged,
+}
+
+impl MountedShare {
+    /// Host part of the source, "server" for "//server/share"
+    pub fn server(&self) -> &str {
+        self.source
+            .trim_start_matches('/')
+            .split('/')
+            .next()
+            .unwrap_or_default()
+    }
+
+    pub fn state(&self) -> ShareState {
+        match (&self.config, self.is_mounted) {
+            (None, _) => ShareState::Unmanaged,
+            (Some(_), true) => ShareState::Mounted,
+            (Some(_), false) => ShareState::NotMounted,
+        }
+    }
+
+    /// Configuration entry mounting this share as it is mounted now, for mounts made
+    /// outside the configuration. The mount table has no credentials file, and the
+    /// systemd settings come from `defaults` as for a new share.
+    pub fn adoption_config(&self, defaults: &MountDefaults) -> RemoteSambaShareConfig {
+        let live = self.options.adoptable();
+        let mut config = RemoteSambaShareConfig::new(
+            self.target.clone(),
+            self.source.clone(),
+            self.fstype.clone(),
+            String::new(),
+            live.uid.clone().unwrap_or_else(|| "1000".to_string()),
+            live.gid.clone().unwrap_or_else(|| "100".to_string()),
+        );
+        let defaults_set = MountOptionSet {
+            extras: defaults.extra_options.clone(),
+            ..MountOptionSet::default()
+        };
+        config.extra_options = defaults_set.merge(&live).extras;
+        config.systemd = defaults.systemd;
+        config.idle_timeout = defaults.idle_timeout;
+        config
+    }
 }
 
 /// Options for mounting a CIFS share
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MountOptions {
     pub uid: Option<u32>,
     pub gid: Option<u32>,
     pub additional_opts: Vec<String>,
+    #[serde(default)]
+    pub retry: RetryPolicy,
+}
+
+/// Longest wait between two mount attempts, however many failed before
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// How long a mount may take, and how often it is tried again when the server
+/// doesn't answer, as when it is still waking up
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    /// Attempts in all, 1 to never try again
+    pub attempts: u32,
+    /// Seconds before the second attempt, doubled after each failure
+    pub initial_delay: u32,
+    /// Seconds an attempt may take before it is stopped, 0 for no limit
+    pub timeout: u32,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            initial_delay: 2,
+            timeout: 30,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Wait after the failed attempt number `attempt`, counting from 1
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 1u64 << attempt.saturating_sub(1).min(16);
+        Duration::from_secs(u64::from(self.initial_delay) * factor).min(MAX_RETRY_DELAY)
+    }
 }
 
 impl Default for MountOptions {
     fn default() -> Self {
+        let (uid, gid) = desktop_user_ids();
         Self {
-            uid: Some(get_current_uid()),
-            gid: Some(get_current_gid()),
+            uid: Some(uid),
+            gid: Some(gid),
             additional_opts: vec![
                 "x-systemd.automount".to_string(),
                 "noauto".to_string(),
-                "x-systemd.idle-timeout=300".to_string(),
+                format!("{}{}", idle_timeout::OPTION_PREFIX, idle_timeout::DEFAULT),
             ],
+            retry: RetryPolicy::default(),
         }
     }
 }
@@ -45,25 +155,35 @@ struct CredentialsFile {
 }
 
 impl CredentialsFile {
-    /// Create a new credentials file with secure permissions
+    /// Create a new credentials file, readable by the owner only from the start.
+    /// An existing file or symlink at the path is an error, never followed.
     fn new(username: &str, password: &str) -> Result<Self, String> {
+        // Checked before creating the file, a line break would add credential lines
+        let content = Credentials {
+            username: username.to_string(),
+            password: password.to_string(),
+            domain: String::new(),
+        }
+        .to_file_content()?;
+
         // Create unique filename using process ID and timestamp
         let timestamp = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
-            .as_secs();
-        let path = PathBuf::from(format!("/tmp/smb_creds_{}_{}", std::process::id(), timestamp));
-
-        // Write credentials
-        let content = format!("username={}\npassword={}\n", username, password);
-        fs::write(&path, content)
+            .as_nanos();
+        let path = shared_temp_dir().join(format!("smb_creds_{}_{}", std::process::id(), timestamp));
+
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&path)
             .map_err(|e| format!("Failed to create credentials file: {}", e))?;
+        let creds_file = Self { path };
+        file.write_all(content.as_bytes())
+            .map_err(|e| format!("Failed to write credentials file: {}", e))?;
 
-        // Set permissions to 0600 (owner read/write only)
-        fs::set_permissions(&path, fs::Permissions::from_mode(0o600))
-            .map_err(|e| format!("Failed to set credentials file permissions: {}", e))?;
-
-        Ok(Self { path })
+        Ok(creds_file)
     }
 
     fn path(&self) -> &Path {
@@ -81,11 +201,14 @@ impl Drop for CredentialsFile {
 /// List all CIFS shares (both configured and currently mounted)
 /// Combines NixOS configuration with actual mount status
 pub fn list_all_shares() -> Result<Vec<MountedShare>, String> {
-    use super::remote_share_config::RemoteSambaShareConfig;
     use std::collections::HashMap;
 
-    // Get configured shares from NixOS config
-    let configured = RemoteSambaShareConfig::load_all().unwrap_or_default();
+    // Get configured shares from NixOS config. A cancelled load fails as a whole,
+    // the mounts alone would all look unmanaged.
+    let configured = match RemoteSambaShareConfig::load_all() {
+        Err(e) if e == CANCELLED => return Err(e),
+        result => result.unwrap_or_default(),
+    };
 
     // Get currently mounted shares from system
     let mounted = list_cifs_mounts().unwrap_or_default();
@@ -108,18 +231,12 @@ pub fn list_all_shares() -> Result<Vec<MountedShare>, String> {
             source: config.remote_path.clone(),
             target: config.name.clone(),
             fstype: config.fs_type.clone(),
-            options: if let Some(m) = mounted_share {
-                m.options.clone()
-            } else {
-                // Build options string from config
-                let mut opts = vec![
-                    format!("credentials={}", config.option_credentials),
-                    format!("uid={}", config.force_user),
-                    format!("gid={}", config.force_group),
-                ];
-                opts.join(",")
+            options: match mounted_share {
+                Some(m) => m.options.clone(),
+                None => config.mount_options(),
             },
             is_mounted,
+            config: Some(config),
         });
     }
 
@@ -146,7 +263,7 @@ pub fn list_cifs_mounts() -> Result<Vec<MountedShare>, String> {
 
 /// List CIFS mounts using findmnt command (preferred method)
 fn list_cifs_mounts_findmnt() -> Result<Vec<MountedShare>, String> {
-    let output = Command::new("findmnt")
+    let output = command("findmnt")
         .args(&["-t", "cifs", "--json", "-o", "SOURCE,TARGET,FSTYPE,OPTIONS"])
         .output()
         .map_err(|e| format!("Failed to run findmnt: {}", e))?;
@@ -180,8 +297,9 @@ fn list_cifs_mounts_findmnt() -> Result<Vec<MountedShare>, String> {
             source: fs.source,
             target: fs.target,
             fstype: fs.fstype,
-            options: fs.options,
+            options: MountOptionSet::from_list(&fs.options),
             is_mounted: true,
+            config: None,
         })
         .collect())
 }
@@ -200,8 +318,9 @@ fn list_cifs_mounts_proc() -> Result<Vec<MountedShare>, String> {
                 source: parts[0].to_string(),
                 target: parts[1].to_string(),
                 fstype: parts[2].to_string(),
-                options: parts[3].to_string(),
+                options: MountOptionSet::from_list(parts[3]),
                 is_mounted: true,
+                config: None,
             });
         }
     }
@@ -218,29 +337,53 @@ pub fn is_mounted(mount_point: &Path) -> bool {
     }
 }
 
+/// Whether `source` is the share mounted on `mount_point`
+fn is_mounted_from(mount_point: &Path, source: &str) -> bool {
+    list_cifs_mounts().is_ok_and(|shares| {
+        shares.iter().any(|s| {
+            Path::new(&s.target) == mount_point && s.source.trim_end_matches('/') == source.trim_end_matches('/')
+        })
+    })
+}
+
+/// How the server is logged in to when mounting a share by hand
+#[derive(Debug, Clone)]
+pub enum MountCredentials {
+    /// Typed in, written to a temporary file for the time of the mount
+    Password { username: String, password: String },
+    /// An existing file holding `username=` and `password=` lines
+    File(PathBuf),
+    /// No account, for shares open to guests
+    Guest,
+}
+
 /// Mount a CIFS/SMB share
 ///
+/// Goes through libmount, see `libmount::run`: the source and target are passed as
+/// they are, only the option list needs care, see `encode_mount_options`.
+///
 /// # Arguments
 /// * `remote_url` - The SMB share path (e.g., "//server/share")
 /// * `mount_point` - Local directory to mount to
-/// * `username` - SMB username
-/// * `password` - SMB password
+/// * `credentials` - Password typed in or credentials file to log in with
 /// * `options` - Additional mount options
 ///
 /// # Security
-/// - Credentials are written to a temporary file with 0600 permissions
-/// - The credentials file is automatically deleted after mounting
+/// - Typed credentials are written to a temporary file with 0600 permissions
+/// - The temporary file is automatically deleted after mounting
 /// - Never passes passwords via command line arguments
 pub fn mount_share(
     remote_url: &str,
     mount_point: &Path,
-    username: &str,
-    password: &str,
+    credentials: &MountCredentials,
     options: MountOptions,
 ) -> Result<(), String> {
     // Validate inputs
     validate_remote_url(remote_url)?;
     validate_mount_point(mount_point)?;
+    let target = mount_point
+        .to_str()
+        .ok_or_else(|| "Mount point is not valid UTF-8".to_string())?;
 
     // Check if already mounted
     if is_mounted(mount_point) {
@@ -250,41 +393,68 @@ pub fn mount_share(
         ));
     }
 
-    // Create mount point directory if it doesn't exist
-    if !mount_point.exists() {
-        fs::create_dir_all(mount_point)
-            .map_err(|e| format!("Failed to create mount point directory: {}", e))?;
+    // Create mount point directory if it doesn't exist, with escalation under /media and /mnt
+    match fs::create_dir_all(mount_point) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied && in_root_mount_dir(mount_point) => {
+            run_privileged("mkdir", &["-p", target], None)?;
+        }
+        Err(e) => return Err(format!("Failed to create mount point directory: {}", e)),
     }
 
-    // Create temporary credentials file (auto-deleted on drop)
-    let creds_file = CredentialsFile::new(username, password)?;
+    // Temporary credentials file (auto-deleted on drop) kept until mount returns
+    let (_creds_file, creds_path) = match credentials {
+        MountCredentials::Password { username, password } => {
+            let creds_file = CredentialsFile::new(username, password)?;
+            let path = creds_file.path().to_path_buf();
+            (Some(creds_file), Some(path))
+        }
+        MountCredentials::File(path) => (None, Some(path.clone())),
+        MountCredentials::Guest => (None, None),
+    };
 
     // Build mount options
-    let mut mount_opts = vec![
-        format!("credentials={}", creds_file.path().display()),
-        format!("uid={}", options.uid.unwrap_or_else(get_current_uid)),
-        format!("gid={}", options.gid.unwrap_or_else(get_current_gid)),
-    ];
-    mount_opts.extend(options.additional_opts);
-
-    // Execute mount command
-    let output = Command::new("mount")
-        .arg("-t")
-        .arg("cifs")
-        .arg(remote_url)
-        .arg(mount_point)
-        .arg("-o")
-        .arg(mount_opts.join(","))
-        .output()
-        .map_err(|e| format!("Failed to execute mount command: {}", e))?;
-
-    // Check if mount succeeded
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(parse_mount_error(&stderr));
+    let (uid, gid) = desktop_user_ids();
+    let mut mount_opts = MountOptionSet {
+        credentials: creds_path.map(|path| path.display().to_string()),
+        uid: Some(options.uid.unwrap_or(uid).to_string()),
+        gid: Some(options.gid.unwrap_or(gid).to_string()),
+        ..MountOptionSet::parse(&options.additional_opts)
+    };
+    if matches!(credentials, MountCredentials::Guest) && !mount_opts.extras.iter().any(|option| option == "guest") {
+        mount_opts.extras.push("guest".to_string());
+    }
+    let mount_opts = encode_mount_options(&mount_opts.to_options())?;
+
+    // Mounting CIFS needs root, libmount runs in this process when the app already has it.
+    // Servers that are offline or busy for a moment get a few more chances.
+    let retry = options.retry;
+    let request = MountRequest::Mount {
+        source: remote_url.to_string(),
+        target: target.to_string(),
+        fstype: "cifs".to_string(),
+        options: mount_opts,
+        timeout: retry.timeout,
+    };
+    let mut attempt = 1;
+    loop {
+        let mounted_before = is_mounted(mount_point);
+        match libmount::run(&request) {
+            Ok(()) => return Ok(()),
+            // An attempt stopped at its time limit may still have gone through,
+            // unless what is mounted there now was already there before it
+            Err(e) if e.is_timeout() && !mounted_before && is_mounted_from(mount_point, remote_url) => {
+                return Ok(())
+            }
+            Err(e) if attempt < retry.attempts && e.is_transient() => {
+                let delay = retry.delay(attempt);
+                eprintln!("Mount attempt {} failed, retrying in {}s: {}", attempt, delay.as_secs(), e);
+                thread::sleep(delay);
+                attempt += 1;
+            }
+            Err(e) => return Err(e.to_string()),
+        }
     }
-
-    Ok(())
 }
 
 /// Unmount a CIFS/SMB share
@@ -300,98 +470,278 @@ pub fn unmount_share(mount_point: &Path) -> Result<(), String> {
         ));
     }
 
-    // Execute umount command
-    let output = Command::new("umount")
-        .arg(mount_point)
-        .output()
-        .map_err(|e| format!("Failed to execute umount command: {}", e))?;
+    // Name the applications up front rather than let umount fail with "target is busy"
+    let blocking = blocking_processes(mount_point);
+    if !blocking.is_empty() {
+        return Err(format!(
+            "Mount point is busy, used by {}. Close them and retry.",
+            describe(&blocking)
+        ));
+    }
 
-    // Check if unmount succeeded
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(parse_umount_error(&stderr));
+    let target = mount_point
+        .to_str()
+        .ok_or_else(|| "Mount point is not valid UTF-8".to_string())?;
+    libmount::run_as_user_first(&MountRequest::Unmount {
+        target: target.to_string(),
+    })
+    .map_err(|e| e.to_string())
+}
+
+/// Mount a share again from its system configuration entry
+///
+/// Used to undo an unmount: the options and credentials come from the
+/// `fileSystems` entry or the autofs map, so no password is needed here.
+///
+/// # Arguments
+/// * `mount_point` - The configured mount point to mount
+pub fn remount_share(mount_point: &Path) -> Result<(), String> {
+    if is_mounted(mount_point) {
+        return Ok(());
     }
 
-    Ok(())
+    let target = mount_point
+        .to_str()
+        .ok_or_else(|| "Mount point is not valid UTF-8".to_string())?;
+
+    // Shares of the autofs map mount as soon as their folder is listed
+    if is_autofs_trigger(target) {
+        fs::read_dir(host_path(mount_point))
+            .map_err(|e| format!("Failed to access {}: {}", target, e))?;
+        if is_mounted(mount_point) {
+            return Ok(());
+        }
+        return Err(format!("autofs didn't mount {}", target));
+    }
+
+    // Entries with the `user` option can be mounted without privileges
+    libmount::run_as_user_first(&MountRequest::MountConfigured {
+        target: target.to_string(),
+    })
+    .map_err(|e| e.to_string())
 }
 
-/// Validate remote URL format
-fn validate_remote_url(url: &str) -> Result<(), String> {
-    if !url.starts_with("//") {
-        return Err("Remote URL must start with '//' (e.g., //server/share)".to_string());
+/// Unmount a share then mount it again from its system entry, so options changed by
+/// the last rebuild apply without restarting. A share no longer mounted is left as is.
+pub fn reapply_mount(mount_point: &Path) -> Result<(), String> {
+    if !is_mounted(mount_point) {
+        return Ok(());
     }
+    unmount_share(mount_point)?;
+    remount_share(mount_point)
+}
 
-    if url.matches('/').count() < 3 {
-        return Err("Remote URL must include server and share name (e.g., //server/share)".to_string());
+/// Mount a share again with the options of its configuration entry, as they are
+/// in the file rather than as the last rebuild applied them. For a share mounted by
+/// hand or before its options were changed; it is unmounted first.
+pub fn remount_with_configured_options(config: &RemoteSambaShareConfig) -> Result<(), String> {
+    let mount_point = Path::new(&config.name);
+    // Mounted once by hand, the systemd settings of the entry don't apply
+    let options = MountOptionSet {
+        systemd: SystemdMount::NONE,
+        idle_timeout: None,
+        ..config.mount_options()
+    };
+    let options = encode_mount_options(&options.to_options())?;
+
+    if is_mounted(mount_point) {
+        unmount_share(mount_point)?;
+    }
+    libmount::run(&MountRequest::Mount {
+        source: config.remote_path.clone(),
+        target: config.name.clone(),
+        fstype: config.fs_type.clone(),
+        options,
+        timeout: 0,
+    })
+    .map_err(|e| e.to_string())
+}
+
+/// Whether `mount_point` is an empty folder with nothing mounted on it, as left
+/// behind by a share that was removed or unmounted for good
+pub fn is_leftover_mount_point(mount_point: &Path) -> bool {
+    let Some(target) = mount_point.to_str() else {
+        return false;
+    };
+    !is_mount_point(target)
+        && fs::read_dir(host_path(mount_point)).is_ok_and(|mut entries| entries.next().is_none())
+}
+
+/// Delete the empty folder of a removed or unmounted share. Refuses when something
+/// is mounted there or it holds files. Under /media and /mnt the folder belongs
+/// to root, so removing it is escalated; `rmdir` still refuses non-empty folders.
+pub fn remove_mount_point(mount_point: &Path) -> Result<(), String> {
+    validate_mount_point(mount_point)?;
+    let target = mount_point
+        .to_str()
+        .ok_or_else(|| "Mount point is not valid UTF-8".to_string())?;
+
+    if is_mount_point(target) {
+        return Err(format!("{} is still a mount point", target));
+    }
+    let mut entries = fs::read_dir(host_path(mount_point))
+        .map_err(|e| format!("Failed to read {}: {}", target, e))?;
+    if entries.next().is_some() {
+        return Err(format!("{} is not empty, it was left in place", target));
+    }
+
+    match fs::remove_dir(mount_point) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied && in_root_mount_dir(mount_point) => {
+            run_privileged("rmdir", &[target], None).map(|_| ())
+        }
+        Err(e) => Err(format!("Failed to remove {}: {}", target, e)),
     }
+}
 
-    // Check for potential command injection
-    if url.contains(';') || url.contains('&') || url.contains('|') || url.contains('`') {
-        return Err("Remote URL contains invalid characters".to_string());
+/// Stop using `old` before its share moves to another mount point: unmount the
+/// share and stop the systemd automount unit watching the folder
+pub fn release_mount_point(old: &Path) -> Result<(), String> {
+    if is_mounted(old) {
+        unmount_share(old)?;
     }
 
+    let target = old
+        .to_str()
+        .ok_or_else(|| "Mount point is not valid UTF-8".to_string())?;
+    if !is_mount_point(target) {
+        return Ok(());
+    }
+    let unit = command("systemd-escape")
+        .args(["--path", "--suffix=automount", target])
+        .output()
+        .map_err(|e| format!("Failed to run systemd-escape: {}", e))?;
+    let unit = String::from_utf8_lossy(&unit.stdout).trim().to_string();
+    let active = command("systemctl")
+        .args(["is-active", "--quiet", &unit])
+        .status()
+        .is_ok_and(|status| status.success());
+    // autofs map entries keep their trigger until the next rebuild
+    if active {
+        run_privileged("systemctl", &["stop", &unit], None)?;
+    }
     Ok(())
 }
 
-/// Validate mount point path
-fn validate_mount_point(path: &Path) -> Result<(), String> {
-    // Must be absolute path
-    if !path.is_absolute() {
-        return Err("Mount point must be an absolute path".to_string());
+/// Create the folder a share moved to and, with `link_old`, replace the emptied
+/// old folder with a link to it so paths keep working during the transition.
+/// Like mount points, folders under /media and /mnt are created with escalation.
+pub fn move_mount_point(old: &Path, new: &Path, link_old: bool) -> Result<(), String> {
+    validate_mount_point(new)?;
+    let new_target = new
+        .to_str()
+        .ok_or_else(|| "Mount point is not valid UTF-8".to_string())?;
+
+    match fs::create_dir_all(new) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied && in_root_mount_dir(new) => {
+            run_privileged("mkdir", &["-p", new_target], None)?;
+        }
+        Err(e) => return Err(format!("Failed to create {}: {}", new_target, e)),
     }
 
-    // Check for potential command injection in path
-    let path_str = path.to_string_lossy();
-    if path_str.contains(';')
-        || path_str.contains('&')
-        || path_str.contains('|')
-        || path_str.contains('`')
-    {
-        return Err("Mount point path contains invalid characters".to_string());
+    if !link_old || !old.exists() {
+        return Ok(());
     }
+    let old_target = old
+        .to_str()
+        .ok_or_else(|| "Mount point is not valid UTF-8".to_string())?;
+    if !is_leftover_mount_point(old) {
+        return Err(format!(
+            "{} can't be replaced by a link, it is still mounted or not empty",
+            old_target
+        ));
+    }
+    remove_mount_point(old)?;
 
-    Ok(())
+    match std::os::unix::fs::symlink(new, old) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied && in_root_mount_dir(old) => {
+            run_privileged("ln", &["-s", new_target, old_target], None).map(|_| ())
+        }
+        Err(e) => Err(format!("Failed to link {} to {}: {}", old_target, new_target, e)),
+    }
 }
 
-/// Parse mount command error messages into user-friendly errors
-fn parse_mount_error(stderr: &str) -> String {
-    let lower = stderr.to_lowercase();
-
-    if lower.contains("permission denied") || lower.contains("access denied") {
-        "Permission denied. Check your credentials or run with sudo.".to_string()
-    } else if lower.contains("connection refused") || lower.contains("could not resolve") {
-        "Connection refused. Server may be offline or unreachable.".to_string()
-    } else if lower.contains("already mounted") || lower.contains("busy") {
-        "Mount point is already in use or mounted.".to_string()
-    } else if lower.contains("no such file or directory") {
-        "Server or share not found. Check the remote URL.".to_string()
-    } else if lower.contains("invalid argument") {
-        "Invalid mount options. Check your configuration.".to_string()
-    } else if lower.contains("host is down") {
-        "Host is unreachable. Check network connectivity.".to_string()
-    } else {
-        format!("Mount failed: {}", stderr.trim())
+/// Whether `path` is under a folder belonging to root, see `ROOT_MOUNT_DIRS`
+fn in_root_mount_dir(path: &Path) -> bool {
+    ROOT_MOUNT_DIRS.iter().any(|dir| path.starts_with(dir))
+}
+
+/// Whether any filesystem, an automount trigger included, is mounted on `target`
+fn is_mount_point(target: &str) -> bool {
+    command("findmnt")
+        .args(["-n", "--mountpoint", target])
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+/// Whether autofs watches `target`, mounting the share there on access
+fn is_autofs_trigger(target: &str) -> bool {
+    command("findmnt")
+        .args(["-t", "autofs", "-n", target])
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+/// Join mount options into the comma separated list given to libmount.
+/// mount.cifs has no escaping, so a comma inside a value would split it in two.
+pub fn encode_mount_options(options: &[String]) -> Result<String, String> {
+    for option in options {
+        if option.is_empty() {
+            return Err("Mount options can't be empty".to_string());
+        }
+        if option.contains(',') {
+            return Err(format!("Mount option '{}' can't contain a comma", option));
+        }
+        if option.chars().any(char::is_control) {
+            return Err(format!("Mount option '{}' contains a control character", option));
+        }
     }
+    Ok(options.join(","))
 }
 
-/// Parse unmount command error messages into user-friendly errors
-fn parse_umount_error(stderr: &str) -> String {
-    let lower = stderr.to_lowercase();
+/// Validate remote URL format
+pub(crate) fn validate_remote_url(url: &str) -> Result<(), String> {
+    let Some(path) = url.strip_prefix("//") else {
+        return Err("Remote URL must start with '//' (e.g., //server/share)".to_string());
+    };
 
-    if lower.contains("not mounted") {
-        "The specified path is not currently mounted.".to_string()
-    } else if lower.contains("busy") || lower.contains("target is busy") {
-        "Mount point is busy. Close any programs using files from this share.".to_string()
-    } else if lower.contains("permission denied") {
-        "Permission denied. You may need to run with sudo.".to_string()
-    } else {
-        format!("Unmount failed: {}", stderr.trim())
+    let mut parts = path.split('/');
+    let server = parts.next().unwrap_or_default();
+    let share = parts.next().unwrap_or_default();
+    if server.is_empty() || share.is_empty() {
+        return Err("Remote URL must include server and share name (e.g., //server/share)".to_string());
+    }
+
+    Ok(())
+}
+
+/// Validate mount point path
+pub(crate) fn validate_mount_point(path: &Path) -> Result<(), String> {
+    // Must be absolute path
+    if !path.is_absolute() {
+        return Err("Mount point must be an absolute path".to_string());
+    }
+
+    // Mounting over the root or a parent of the given folder would hide the system
+    if path.parent().is_none() {
+        return Err("Mount point can't be the root directory".to_string());
     }
+    if path
+        .components()
+        .any(|component| component == std::path::Component::ParentDir)
+    {
+        return Err("Mount point can't contain '..'".to_string());
+    }
+
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::os::unix::fs::PermissionsExt;
 
     #[test]
     fn test_validate_remote_url() {
@@ -399,13 +749,171 @@ mod tests {
         assert!(validate_remote_url("//192.168.1.100/data").is_ok());
         assert!(validate_remote_url("server/share").is_err());
         assert!(validate_remote_url("//server").is_err());
-        assert!(validate_remote_url("//server/share;rm -rf").is_err());
+        assert!(validate_remote_url("//server/My Files/2024").is_ok());
     }
 
     #[test]
     fn test_validate_mount_point() {
         assert!(validate_mount_point(Path::new("/mnt/share")).is_ok());
         assert!(validate_mount_point(Path::new("relative/path")).is_err());
-        assert!(validate_mount_point(Path::new("/mnt/share;whoami")).is_err());
+        // Arguments never go through a shell, any folder name works
+        assert!(validate_mount_point(Path::new("/mnt/Films & Séries")).is_ok());
+        assert!(validate_mount_point(Path::new("/mnt/../etc")).is_err());
+        assert!(validate_mount_point(Path::new("/")).is_err());
+    }
+
+    #[test]
+    fn test_credentials_file() {
+        let creds_file = CredentialsFile::new("alice", "pass word").unwrap();
+        let path = creds_file.path().to_path_buf();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "username=alice\npassword=pass word\n");
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        drop(creds_file);
+        assert!(!path.exists());
+
+        assert!(CredentialsFile::new("alice", "secret\nusername=root").is_err());
+        assert!(CredentialsFile::new("alice\nroot", "secret").is_err());
+    }
+
+    #[test]
+    fn test_remove_mount_point() {
+        let dir = std::env::temp_dir().join(format!("samba-share-mount-point-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("file.txt"), "kept").unwrap();
+        assert!(!is_leftover_mount_point(&dir));
+        assert!(remove_mount_point(&dir).is_err());
+        assert!(dir.join("file.txt").exists());
+
+        fs::remove_file(dir.join("file.txt")).unwrap();
+        assert!(is_leftover_mount_point(&dir));
+        remove_mount_point(&dir).unwrap();
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn test_move_mount_point() {
+        let dir = std::env::temp_dir().join(format!("samba-share-move-{}", std::process::id()));
+        let old = dir.join("old");
+        let new = dir.join("new");
+        fs::create_dir_all(&old).unwrap();
+
+        move_mount_point(&old, &new, true).unwrap();
+        assert!(new.is_dir());
+        assert_eq!(fs::read_link(&old).unwrap(), new);
+
+        // A folder still holding files is left alone
+        let kept = dir.join("kept");
+        fs::create_dir_all(&kept).unwrap();
+        fs::write(kept.join("file.txt"), "kept").unwrap();
+        assert!(move_mount_point(&kept, &dir.join("other"), true).is_err());
+        assert!(kept.join("file.txt").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_encode_mount_options() {
+        let options = vec!["credentials=/etc/nas secrets".to_string(), "uid=1000".to_string()];
+        assert_eq!(
+            encode_mount_options(&options).unwrap(),
+            "credentials=/etc/nas secrets,uid=1000"
+        );
+        assert!(encode_mount_options(&["credentials=/etc/a,b".to_string()]).is_err());
+        assert!(encode_mount_options(&["uid=1000\n".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_share_state() {
+        let config = RemoteSambaShareConfig::new(
+            "/media/nas".to_string(),
+            "//nas/media".to_string(),
+            "cifs".to_string(),
+            String::new(),
+            "1000".to_string(),
+            "100".to_string(),
+        );
+        let share = |is_mounted: bool, config: Option<RemoteSambaShareConfig>| MountedShare {
+            source: "//nas/media".to_string(),
+            target: "/media/nas".to_string(),
+            fstype: "cifs".to_string(),
+            options: MountOptionSet::default(),
+            is_mounted,
+            config,
+        };
+        assert_eq!(share(true, Some(config.clone())).state(), ShareState::Mounted);
+        assert_eq!(share(false, Some(config)).state(), ShareState::NotMounted);
+        assert_eq!(share(true, None).state(), ShareState::Unmanaged);
+        assert!(ShareState::Mounted < ShareState::NotMounted && ShareState::NotMounted < ShareState::Unmanaged);
+    }
+
+    #[test]
+    fn test_adoption_config() {
+        let share = MountedShare {
+            source: "//nas/media".to_string(),
+            target: "/media/nas".to_string(),
+            fstype: "cifs".to_string(),
+            options: MountOptionSet::from_list("rw,relatime,vers=3.0,username=alice,uid=1001,gid=100,addr=10.0.0.2"),
+            is_mounted: true,
+            config: None,
+        };
+        let defaults = MountDefaults {
+            extra_options: vec!["nofail".to_string(), "vers=3.1.1".to_string()],
+            ..MountDefaults::default()
+        };
+        let config = share.adoption_config(&defaults);
+        assert_eq!(config.name, "/media/nas");
+        assert_eq!(config.remote_path, "//nas/media");
+        assert_eq!(config.option_credentials, "");
+        assert_eq!(config.force_user, "1001");
+        assert_eq!(config.extra_options, vec!["nofail".to_string(), "vers=3.0".to_string()]);
+        assert_eq!(config.systemd, defaults.systemd);
+    }
+
+    #[test]
+    fn test_retry_policy() {
+        let retry = RetryPolicy::default();
+        assert_eq!(retry.delay(1), Duration::from_secs(2));
+        assert_eq!(retry.delay(3), Duration::from_secs(8));
+        assert_eq!(retry.delay(40), MAX_RETRY_DELAY);
+    }
+
+    #[test]
+    fn test_server() {
+        let share = MountedShare {
+            source: "//nas.local/media".to_string(),
+            target: "/mnt/media".to_string(),
+            fstype: "cifs".to_string(),
+            options: MountOptionSet::default(),
+            is_mounted: false,
+            config: None,
+        };
+        assert_eq!(share.server(), "nas.local");
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let mut config = RemoteSambaShareConfig::new(
+            "/mnt/media".to_string(),
+            "//nas.local/media".to_string(),
+            "cifs".to_string(),
+            "/etc/nixos/smb-secrets".to_string(),
+            String::new(),
+            String::new(),
+        );
+        config.extra_options = vec!["cache=loose".to_string()];
+        let share = MountedShare {
+            source: "//nas.local/media".to_string(),
+            target: "/mnt/media".to_string(),
+            fstype: "cifs".to_string(),
+            options: MountOptionSet::from_list("rw,uid=1000"),
+            is_mounted: true,
+            config: Some(config),
+        };
+
+        let json = serde_json::to_string(&share).unwrap();
+        assert!(json.contains(r#""backend":"file_systems""#));
+        let parsed: MountedShare = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&parsed).unwrap(), json);
+        assert_eq!(parsed.config.unwrap().extra_options, vec!["cache=loose"]);
     }
 }
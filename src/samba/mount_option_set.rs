@@ -0,0 +1,339 @@
+use crate::samba::idle_timeout;
+use crate::samba::mount_defaults::SystemdMount;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+const CREDENTIALS_PREFIX: &str = "credentials=";
+const UID_PREFIX: &str = "uid=";
+const GID_PREFIX: &str = "gid=";
+
+/// Options the kernel doesn't list in the mount table: the ones only mount(8) and
+/// systemd read, and the password it hides
+const UNLISTED_OPTIONS: [&str; 11] = [
+    "_netdev", "nofail", "auto", "noauto", "defaults", "user", "users", "nouser", "comment", "password", "pass",
+];
+
+/// Options of the mount table that describe the connection rather than set it up: the
+/// defaults, the resolved address and the account, which comes from the credentials file
+const CONNECTION_OPTIONS: [&str; 8] = ["rw", "relatime", "addr", "username", "user", "domain", "unc", "prefixpath"];
+
+/// Option of the configuration that a live mount has with another value, or doesn't have
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OptionDifference {
+    pub key: String,
+    /// Option as configured, `cache=loose`
+    pub configured: String,
+    /// Option of the same name in the mount table, None when it has none
+    pub live: Option<String>,
+}
+
+/// Mount options of a CIFS share: the ones the app edits on their own, the others kept
+/// as they are. Shared by the configuration reader and writers, the mount commands and
+/// the share lists, so option lists are only parsed here.
+/// Serialized as the comma separated list `mount` and `findmnt` use.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "String", from = "String")]
+pub struct MountOptionSet {
+    /// File holding the username and password, `credentials=`
+    pub credentials: Option<String>,
+    /// Owner of the mounted files, `uid=`
+    pub uid: Option<String>,
+    /// Group of the mounted files, `gid=`
+    pub gid: Option<String>,
+    /// Automount, noauto and timeouts of systemd
+    pub systemd: SystemdMount,
+    /// `x-systemd.idle-timeout` in seconds, None to keep the share mounted
+    pub idle_timeout: Option<u32>,
+    /// Every other option, such as `cache=` or `vers=`, in its original order
+    pub extras: Vec<String>,
+}
+
+/// No option at all, unlike `SystemdMount::default()` which has those of new shares
+impl Default for MountOptionSet {
+    fn default() -> Self {
+        Self {
+            credentials: None,
+            uid: None,
+            gid: None,
+            systemd: SystemdMount::NONE,
+            idle_timeout: None,
+            extras: Vec::new(),
+        }
+    }
+}
+
+impl MountOptionSet {
+    /// Sort options as found in a `fileSystems` entry, an autofs map or fstab
+    pub fn parse(options: &[String]) -> Self {
+        let value = |prefix: &str| {
+            options
+                .iter()
+                .find_map(|option| option.strip_prefix(prefix))
+                .map(|value| value.to_string())
+        };
+        Self {
+            credentials: value(CREDENTIALS_PREFIX),
+            uid: value(UID_PREFIX),
+            gid: value(GID_PREFIX),
+            systemd: SystemdMount::from_options(options),
+            idle_timeout: idle_timeout::from_options(options),
+            extras: options
+                .iter()
+                .filter(|option| !option.is_empty() && !Self::is_known(option))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Sort a comma separated list, as `findmnt` and /proc/mounts show it
+    pub fn from_list(list: &str) -> Self {
+        let options: Vec<String> = list.split(',').map(|option| option.trim().to_string()).collect();
+        Self::parse(&options)
+    }
+
+    /// Whether an option has a field of its own rather than being kept in `extras`
+    pub fn is_known(option: &str) -> bool {
+        [CREDENTIALS_PREFIX, UID_PREFIX, GID_PREFIX, idle_timeout::OPTION_PREFIX]
+            .iter()
+            .any(|prefix| option.starts_with(prefix))
+            || SystemdMount::is_option(option)
+    }
+
+    /// Options in the order they are written: the credentials, the systemd settings,
+    /// the ids, then the other options
+    pub fn to_options(&self) -> Vec<String> {
+        let mut options = Vec::new();
+        if let Some(credentials) = &self.credentials {
+            options.push(format!("{}{}", CREDENTIALS_PREFIX, credentials));
+        }
+        options.extend(self.systemd.options());
+        options.extend(idle_timeout::option(self.idle_timeout));
+        if let Some(uid) = &self.uid {
+            options.push(format!("{}{}", UID_PREFIX, uid));
+        }
+        if let Some(gid) = &self.gid {
+            options.push(format!("{}{}", GID_PREFIX, gid));
+        }
+        options.extend(self.extras.iter().cloned());
+        options
+    }
+
+    /// These options with the ones of `other` on top: the values `other` sets win,
+    /// and its extra options replace the ones with the same key
+    pub fn merge(&self, other: &Self) -> Self {
+        let mut extras: Vec<String> = self
+            .extras
+            .iter()
+            .filter(|option| !other.extras.iter().any(|new| option_key(new) == option_key(option)))
+            .cloned()
+            .collect();
+        extras.extend(other.extras.iter().cloned());
+
+        Self {
+            credentials: other.credentials.clone().or_else(|| self.credentials.clone()),
+            uid: other.uid.clone().or_else(|| self.uid.clone()),
+            gid: other.gid.clone().or_else(|| self.gid.clone()),
+            systemd: SystemdMount {
+                automount: self.systemd.automount || other.systemd.automount,
+                noauto: self.systemd.noauto || other.systemd.noauto,
+                device_timeout: other.systemd.device_timeout.or(self.systemd.device_timeout),
+                mount_timeout: other.systemd.mount_timeout.or(self.systemd.mount_timeout),
+            },
+            idle_timeout: other.idle_timeout.or(self.idle_timeout),
+            extras,
+        }
+    }
+
+    /// Keys of the options set differently in `other`, added or removed ones included,
+    /// in the order they are written
+    pub fn diff(&self, other: &Self) -> Vec<String> {
+        let ours = self.to_options();
+        let theirs = other.to_options();
+        let mut keys: Vec<String> = Vec::new();
+        for option in ours.iter().chain(&theirs) {
+            let key = option_key(option);
+            if values(&ours, key) != values(&theirs, key) && !keys.iter().any(|known| known == key) {
+                keys.push(key.to_string());
+            }
+        }
+        keys
+    }
+
+    /// Options of this live set, read from the mount table, to configure a share the
+    /// same way, without the ones describing the connection
+    pub fn adoptable(&self) -> Self {
+        Self {
+            extras: self
+                .extras
+                .iter()
+                .filter(|option| !CONNECTION_OPTIONS.contains(&option_key(option)))
+                .cloned()
+                .collect(),
+            ..self.clone()
+        }
+    }
+
+    /// Options of this configured set that `live`, read from the mount table, doesn't
+    /// apply as configured, as after a manual mount or before a rebuild. The options the
+    /// kernel doesn't list, and owners given by name rather than id, aren't compared.
+    pub fn live_differences(&self, live: &Self) -> Vec<OptionDifference> {
+        let mut comparable: Vec<String> = Vec::new();
+        for (prefix, id) in [(UID_PREFIX, &self.uid), (GID_PREFIX, &self.gid)] {
+            if let Some(id) = id.as_ref().filter(|id| id.parse::<u32>().is_ok()) {
+                comparable.push(format!("{}{}", prefix, id));
+            }
+        }
+        comparable.extend(
+            self.extras
+                .iter()
+                .filter(|option| {
+                    let key = option_key(option);
+                    !UNLISTED_OPTIONS.contains(&key) && !key.starts_with("x-")
+                })
+                .cloned(),
+        );
+
+        let live = live.to_options();
+        comparable
+            .into_iter()
+            .filter(|option| !live.contains(option))
+            .map(|option| {
+                // "ro" and "rw" are the two values of the same setting
+                let key = option_key(&option);
+                let keys = match key {
+                    "ro" | "rw" => vec!["ro", "rw"],
+                    _ => vec![key],
+                };
+                OptionDifference {
+                    key: key.to_string(),
+                    live: live.iter().find(|live| keys.contains(&option_key(live))).cloned(),
+                    configured: option,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Options named `key`, sorted so their order doesn't count
+fn values<'a>(options: &'a [String], key: &str) -> Vec<&'a String> {
+    let mut values: Vec<&String> = options.iter().filter(|option| option_key(option) == key).collect();
+    values.sort();
+    values
+}
+
+impl fmt::Display for MountOptionSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_options().join(","))
+    }
+}
+
+impl From<String> for MountOptionSet {
+    fn from(list: String) -> Self {
+        Self::from_list(&list)
+    }
+}
+
+impl From<MountOptionSet> for String {
+    fn from(options: MountOptionSet) -> Self {
+        options.to_string()
+    }
+}
+
+/// Name of an option, `cache` for `cache=strict`
+pub fn option_key(option: &str) -> &str {
+    option.split('=').next().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(list: &[&str]) -> Vec<String> {
+        list.iter().map(|option| option.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        let set = MountOptionSet::parse(&options(&[
+            "credentials=/etc/nixos/smb-secrets",
+            "x-systemd.automount",
+            "x-systemd.idle-timeout=300",
+            "uid=1000",
+            "cache=loose",
+            "",
+            "vers=3.1.1",
+        ]));
+        assert_eq!(set.credentials.as_deref(), Some("/etc/nixos/smb-secrets"));
+        assert_eq!(set.uid.as_deref(), Some("1000"));
+        assert_eq!(set.gid, None);
+        assert!(set.systemd.automount);
+        assert_eq!(set.idle_timeout, Some(300));
+        assert_eq!(set.extras, options(&["cache=loose", "vers=3.1.1"]));
+        assert_eq!(
+            set.to_string(),
+            "credentials=/etc/nixos/smb-secrets,x-systemd.automount,x-systemd.idle-timeout=300,uid=1000,cache=loose,vers=3.1.1"
+        );
+        assert_eq!(MountOptionSet::from_list(&set.to_string()), set);
+    }
+
+    #[test]
+    fn test_merge() {
+        let base = MountOptionSet::from_list("credentials=/etc/a,uid=1000,cache=strict,rsize=65536");
+        let preset = MountOptionSet::from_list("uid=1001,cache=loose,vers=3.0");
+        assert_eq!(
+            base.merge(&preset).to_options(),
+            options(&["credentials=/etc/a", "uid=1001", "rsize=65536", "cache=loose", "vers=3.0"])
+        );
+    }
+
+    #[test]
+    fn test_diff() {
+        let configured = MountOptionSet::from_list("credentials=/etc/a,uid=1000,cache=strict,ro");
+        let mounted = MountOptionSet::from_list("uid=1000,cache=loose,ro,vers=3.1.1");
+        assert_eq!(configured.diff(&mounted), options(&["credentials", "cache", "vers"]));
+        assert!(configured.diff(&configured).is_empty());
+    }
+
+    #[test]
+    fn test_live_differences() {
+        let configured = MountOptionSet::from_list(
+            "credentials=/etc/a,x-systemd.automount,noauto,uid=1000,gid=users,ro,cache=loose,nofail,x-gvfs-show,vers=3.1.1",
+        );
+        let live = MountOptionSet::from_list("rw,relatime,vers=3.1.1,cache=strict,username=alice,uid=1000,gid=100");
+        assert_eq!(
+            configured.live_differences(&live),
+            vec![
+                OptionDifference {
+                    key: "ro".to_string(),
+                    configured: "ro".to_string(),
+                    live: Some("rw".to_string()),
+                },
+                OptionDifference {
+                    key: "cache".to_string(),
+                    configured: "cache=loose".to_string(),
+                    live: Some("cache=strict".to_string()),
+                },
+            ]
+        );
+        let manual = MountOptionSet::from_list("rw,uid=1001,vers=3.1.1");
+        assert_eq!(configured.live_differences(&manual)[0].live.as_deref(), Some("uid=1001"));
+        assert_eq!(configured.live_differences(&manual)[2].live, None);
+    }
+
+    #[test]
+    fn test_adoptable() {
+        let live = MountOptionSet::from_list(
+            "rw,relatime,vers=3.1.1,cache=strict,username=alice,domain=HOME,uid=1000,gid=100,addr=192.168.1.10,file_mode=0644",
+        );
+        assert_eq!(live.adoptable().to_string(), "uid=1000,gid=100,vers=3.1.1,cache=strict,file_mode=0644");
+        assert_eq!(MountOptionSet::from_list("ro,relatime").adoptable().to_string(), "ro");
+    }
+
+    #[test]
+    fn test_serde() {
+        let set = MountOptionSet::from_list("rw,uid=1000");
+        let json = serde_json::to_string(&set).unwrap();
+        assert_eq!(json, r#""uid=1000,rw""#);
+        assert_eq!(serde_json::from_str::<MountOptionSet>(&json).unwrap(), set);
+    }
+}
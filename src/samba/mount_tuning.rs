@@ -0,0 +1,155 @@
+use crate::samba::libmount::{self, MountRequest};
+use crate::samba::mount_defaults::SystemdMount;
+use crate::samba::mount_operations::encode_mount_options;
+use crate::samba::mount_option_set::{option_key, MountOptionSet};
+use crate::samba::remote_share_config::RemoteSambaShareConfig;
+use crate::samba::sandbox::shared_temp_dir;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::time::Instant;
+
+/// Options a preset sets, replaced in the share's options when a preset is applied
+const TUNED_OPTIONS: [&str; 3] = ["cache", "rsize", "wsize"];
+
+/// Size of the file written then read during a benchmark
+const BENCHMARK_SIZE: usize = 16 * 1024 * 1024;
+
+/// A set of mount options to compare
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TuningPreset {
+    pub name: &'static str,
+    pub options: &'static [&'static str],
+}
+
+/// Presets benchmarked by the tuning dialog, the kernel defaults first
+pub const PRESETS: [TuningPreset; 5] = [
+    TuningPreset {
+        name: "Default",
+        options: &["cache=strict"],
+    },
+    TuningPreset {
+        name: "No cache",
+        options: &["cache=none"],
+    },
+    TuningPreset {
+        name: "Loose cache",
+        options: &["cache=loose"],
+    },
+    TuningPreset {
+        name: "Small blocks",
+        options: &["cache=strict", "rsize=65536", "wsize=65536"],
+    },
+    TuningPreset {
+        name: "Large blocks",
+        options: &["cache=strict", "rsize=4194304", "wsize=4194304"],
+    },
+];
+
+/// Throughput measured with one preset, in megabytes per second
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BenchmarkResult {
+    pub write_mbps: f64,
+    pub read_mbps: f64,
+}
+
+impl BenchmarkResult {
+    /// Single figure to rank presets, writes and reads weighing the same
+    pub fn score(&self) -> f64 {
+        self.write_mbps + self.read_mbps
+    }
+}
+
+/// Extra options of a share with the preset's options instead of the tuned ones
+pub fn apply_preset(extra_options: &[String], preset: &TuningPreset) -> Vec<String> {
+    let mut options: Vec<String> = extra_options
+        .iter()
+        .filter(|option| !TUNED_OPTIONS.contains(&option_key(option)))
+        .cloned()
+        .collect();
+    options.extend(preset.options.iter().map(|option| option.to_string()));
+    options
+}
+
+/// Mount the share on a temporary mount point with the preset's options,
+/// time writing and reading a file there, then unmount it
+pub fn benchmark(share: &RemoteSambaShareConfig, preset: &TuningPreset) -> Result<BenchmarkResult, String> {
+    // Mounted once by hand, the systemd settings of the entry don't apply
+    let options = MountOptionSet {
+        systemd: SystemdMount::NONE,
+        idle_timeout: None,
+        extras: apply_preset(&share.extra_options, preset),
+        ..share.mount_options()
+    };
+    let options = encode_mount_options(&options.to_options())?;
+
+    let mount_point = shared_temp_dir().join(format!("samba-share-tuning-{}", std::process::id()));
+    fs::create_dir_all(&mount_point).map_err(|e| format!("Failed to create a temporary mount point: {}", e))?;
+    let mount_point_str = mount_point.to_string_lossy().to_string();
+
+    let mounted = libmount::run(&MountRequest::Mount {
+        source: share.remote_path.clone(),
+        target: mount_point_str.clone(),
+        fstype: "cifs".to_string(),
+        options,
+        timeout: 0,
+    });
+    if let Err(e) = mounted {
+        let _ = fs::remove_dir(&mount_point);
+        return Err(e.to_string());
+    }
+
+    let result = measure(&mount_point);
+    if let Err(e) = libmount::run(&MountRequest::Unmount {
+        target: mount_point_str.clone(),
+    }) {
+        eprintln!("Failed to unmount {}: {}", mount_point_str, e);
+    }
+    let _ = fs::remove_dir(&mount_point);
+    result
+}
+
+/// Write then read a file in `mount_point`, removing it afterwards
+fn measure(mount_point: &Path) -> Result<BenchmarkResult, String> {
+    let path = mount_point.join(".samba-share-benchmark");
+    let content = vec![0x5au8; BENCHMARK_SIZE];
+
+    let result = (|| -> Result<BenchmarkResult, String> {
+        let start = Instant::now();
+        let mut file = fs::File::create(&path).map_err(|e| format!("Failed to create the benchmark file: {}", e))?;
+        file.write_all(&content)
+            .and_then(|_| file.sync_all())
+            .map_err(|e| format!("Failed to write the benchmark file: {}", e))?;
+        let write_seconds = start.elapsed().as_secs_f64();
+
+        let start = Instant::now();
+        let read = fs::read(&path).map_err(|e| format!("Failed to read the benchmark file: {}", e))?;
+        let read_seconds = start.elapsed().as_secs_f64();
+        if read.len() != content.len() {
+            return Err("The benchmark file read back is incomplete".to_string());
+        }
+
+        let megabytes = BENCHMARK_SIZE as f64 / 1_000_000.0;
+        Ok(BenchmarkResult {
+            write_mbps: megabytes / write_seconds.max(f64::EPSILON),
+            read_mbps: megabytes / read_seconds.max(f64::EPSILON),
+        })
+    })();
+
+    let _ = fs::remove_file(&path);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_preset() {
+        let extra = vec!["vers=3.0".to_string(), "cache=none".to_string(), "rsize=65536".to_string()];
+        assert_eq!(
+            apply_preset(&extra, &PRESETS[4]),
+            vec!["vers=3.0", "cache=strict", "rsize=4194304", "wsize=4194304"]
+        );
+    }
+}
@@ -0,0 +1,374 @@
+use crate::samba::command_runner::command;
+use crate::samba::sandbox::host_path;
+use crate::samba::sudo_write::run_privileged;
+use crate::samba::ws_discovery;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Script run as root opening a share with smbclient, arguments passed on. Its whole
+/// output goes to stderr, where the errors of privileged commands are reported.
+const OPEN_SHARE_SCRIPT: &str = r#"export PATH=/run/current-system/sw/bin:/run/wrappers/bin:$PATH
+smbclient "$@" 1>&2
+"#;
+
+/// A computer answering on the local network, found by NetBIOS or mDNS
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetworkHost {
+    pub name: String,
+    pub address: String,
+    /// Workgroup or domain the host announces, None for hosts only found by mDNS
+    pub workgroup: Option<String>,
+    /// Hardware address of the host's network card, when NetBIOS reports it
+    pub mac_address: Option<String>,
+}
+
+/// A disk share offered by a host
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostShare {
+    pub name: String,
+    pub comment: String,
+}
+
+/// Broadcast scans run besides mDNS, which Samba and macOS servers answer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiscoveryScan {
+    /// NetBIOS name status queries, which also tell the workgroups
+    pub netbios: bool,
+    /// WS-Discovery probe, the only way recent Windows versions announce themselves
+    pub ws_discovery: bool,
+}
+
+impl Default for DiscoveryScan {
+    fn default() -> Self {
+        Self {
+            netbios: true,
+            ws_discovery: false,
+        }
+    }
+}
+
+/// Look for Samba and Windows hosts with mDNS and the scans of `scan`, sorted by name.
+/// Fails only when none of them could run.
+pub fn discover_hosts(scan: DiscoveryScan) -> Result<Vec<NetworkHost>, String> {
+    let mdns = command("avahi-browse")
+        .args(["--terminate", "--parsable", "--resolve", "_smb._tcp"])
+        .output()
+        .map(|output| parse_avahi_services(&String::from_utf8_lossy(&output.stdout)))
+        .map_err(|e| format!("Failed to run avahi-browse: {}", e));
+    let netbios = scan.netbios.then(|| {
+        command("nmblookup")
+            .args(["-S", "*"])
+            .output()
+            .map(|output| parse_name_status(&String::from_utf8_lossy(&output.stdout)))
+            .map_err(|e| format!("Failed to run nmblookup: {}", e))
+    });
+    let ws_discovery = scan.ws_discovery.then(ws_discovery::probe_hosts);
+
+    // NetBIOS knows the workgroup and WS-Discovery no name, each only adds the hosts
+    // the previous ones didn't find
+    let results: Vec<Result<Vec<NetworkHost>, String>> = [netbios, Some(mdns), ws_discovery]
+        .into_iter()
+        .flatten()
+        .collect();
+    if results.iter().all(|result| result.is_err()) {
+        let errors: Vec<String> = results.into_iter().filter_map(|result| result.err()).collect();
+        return Err(errors.join(", "));
+    }
+
+    let mut hosts: Vec<NetworkHost> = Vec::new();
+    for host in results.into_iter().flat_map(|result| result.unwrap_or_default()) {
+        if !hosts.iter().any(|known| known.address == host.address) {
+            hosts.push(host);
+        }
+    }
+
+    hosts.sort_by_key(|host| host.name.to_lowercase());
+    Ok(hosts)
+}
+
+/// Hosts by workgroup, workgroups sorted by name and hosts without one last
+pub fn group_by_workgroup(hosts: &[NetworkHost]) -> Vec<(Option<String>, Vec<NetworkHost>)> {
+    let mut groups: BTreeMap<(bool, Option<String>), Vec<NetworkHost>> = BTreeMap::new();
+    for host in hosts {
+        groups
+            .entry((host.workgroup.is_none(), host.workgroup.clone()))
+            .or_default()
+            .push(host.clone());
+    }
+    groups
+        .into_iter()
+        .map(|((_, workgroup), hosts)| (workgroup, hosts))
+        .collect()
+}
+
+/// Disk shares of a host, listed with smbclient.
+/// Connects as guest when no credentials file is given.
+pub fn list_host_shares(host: &str, credentials: &str) -> Result<Vec<HostShare>, String> {
+    let mut smbclient = command("smbclient");
+    smbclient.args(["-L", &format!("//{}", host), "-g"]);
+    if credentials.is_empty() {
+        smbclient.arg("-N");
+    } else {
+        smbclient.args(["-A", credentials]);
+    }
+    let output = smbclient
+        .output()
+        .map_err(|e| format!("Failed to run smbclient: {}", e))?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if !output.status.success() {
+        return Err(smbclient_error(&stdout, &String::from_utf8_lossy(&output.stderr)));
+    }
+    Ok(parse_share_list(&stdout))
+}
+
+/// Open `share`, as `//server/share`, and list its top folder, as guest when no
+/// credentials file is given. Files only root can read are used through escalation.
+pub fn open_share(share: &str, credentials: &str) -> Result<(), String> {
+    let mut args = vec![share, "-c", "ls"];
+    if credentials.is_empty() {
+        args.push("-N");
+    } else {
+        args.extend(["-A", credentials]);
+    }
+
+    if !credentials.is_empty() && fs::File::open(host_path(Path::new(credentials))).is_err() {
+        let mut script_args = vec!["-c", OPEN_SHARE_SCRIPT, "sh"];
+        script_args.extend(&args);
+        return run_privileged("/bin/sh", &script_args, None)
+            .map(|_| ())
+            .map_err(|e| smbclient_error("", &e));
+    }
+
+    let output = command("smbclient")
+        .args(&args)
+        .output()
+        .map_err(|e| format!("Failed to run smbclient: {}", e))?;
+    if !output.status.success() {
+        return Err(smbclient_error(
+            &String::from_utf8_lossy(&output.stdout),
+            &String::from_utf8_lossy(&output.stderr),
+        ));
+    }
+    Ok(())
+}
+
+/// Line of a failed smbclient run telling why, such as NT_STATUS_LOGON_FAILURE
+fn smbclient_error(stdout: &str, stderr: &str) -> String {
+    // smbclient reports most errors like NT_STATUS_ACCESS_DENIED on stdout
+    stdout
+        .lines()
+        .chain(stderr.lines())
+        .find(|line| line.contains("NT_STATUS_"))
+        .unwrap_or(stderr.trim())
+        .trim()
+        .to_string()
+}
+
+/// Whether `share`, as `//server/share`, lets guests in without an account.
+/// False as well when the server can't be reached.
+pub fn allows_guest(share: &str) -> bool {
+    open_share(share, "").is_ok()
+}
+
+/// Hosts in the output of `nmblookup -S '*'`, which queries the name status of every answer
+fn parse_name_status(output: &str) -> Vec<NetworkHost> {
+    let mut hosts: Vec<NetworkHost> = Vec::new();
+    for line in output.lines() {
+        if let Some(address) = line.strip_prefix("Looking up status of ") {
+            hosts.push(NetworkHost {
+                name: String::new(),
+                address: address.trim().to_string(),
+                workgroup: None,
+                mac_address: None,
+            });
+            continue;
+        }
+        let Some(host) = hosts.last_mut() else {
+            continue;
+        };
+
+        // "\tNAS             <00> -         B <ACTIVE> "
+        // "\tWORKGROUP       <00> - <GROUP> B <ACTIVE> "
+        let line = line.trim();
+        if let Some(mac) = line.strip_prefix("MAC Address = ") {
+            let mac = mac.trim().replace('-', ":").to_lowercase();
+            host.mac_address = Some(mac).filter(|mac| mac != "00:00:00:00:00:00");
+        } else if let Some((name, flags)) = line.split_once("<00>") {
+            let name = name.trim().to_string();
+            if flags.contains("<GROUP>") {
+                host.workgroup.get_or_insert(name);
+            } else if host.name.is_empty() {
+                host.name = name;
+            }
+        }
+    }
+    // Hosts that didn't answer the status query
+    hosts.retain(|host| !host.name.is_empty());
+    hosts
+}
+
+/// Hosts in the output of `avahi-browse --parsable --resolve _smb._tcp`
+fn parse_avahi_services(output: &str) -> Vec<NetworkHost> {
+    let mut hosts: Vec<NetworkHost> = Vec::new();
+    // "=;eth0;IPv4;NAS;Microsoft Windows Network;local;nas.local;192.168.1.10;445;"
+    for line in output.lines() {
+        let fields: Vec<&str> = line.split(';').collect();
+        if fields.len() < 8 || fields[0] != "=" || fields[2] != "IPv4" {
+            continue;
+        }
+        let address = fields[7].to_string();
+        if hosts.iter().any(|host| host.address == address) {
+            continue;
+        }
+        let name = fields[6].strip_suffix(".local").unwrap_or(fields[6]);
+        hosts.push(NetworkHost {
+            name: name.to_string(),
+            address,
+            workgroup: None,
+            mac_address: None,
+        });
+    }
+    hosts
+}
+
+/// Disk shares in the output of `smbclient -L -g`, hidden and administrative ones left out
+fn parse_share_list(output: &str) -> Vec<HostShare> {
+    // "Disk|media|Films and music"
+    let mut shares: Vec<HostShare> = output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '|');
+            let kind = fields.next()?;
+            let name = fields.next()?;
+            (kind == "Disk" && !name.ends_with('$')).then(|| HostShare {
+                name: name.to_string(),
+                comment: fields.next().unwrap_or_default().to_string(),
+            })
+        })
+        .collect();
+    shares.sort_by_key(|share| share.name.to_lowercase());
+    shares
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn host(name: &str, address: &str, workgroup: Option<&str>) -> NetworkHost {
+        NetworkHost {
+            name: name.to_string(),
+            address: address.to_string(),
+            workgroup: workgroup.map(|workgroup| workgroup.to_string()),
+            mac_address: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_name_status() {
+        let output = "querying * on 192.168.1.255
+192.168.1.10 *<00>
+192.168.1.20 *<00>
+192.168.1.30 *<00>
+Looking up status of 192.168.1.10
+\tNAS             <00> -         B <ACTIVE>
+\tNAS             <03> -         B <ACTIVE>
+\tNAS             <20> -         B <ACTIVE>
+\tHOME            <00> - <GROUP> B <ACTIVE>
+\tHOME            <1e> - <GROUP> B <ACTIVE>
+
+\tMAC Address = 00-11-22-AA-BB-CC
+
+Looking up status of 192.168.1.20
+\tDESK            <00> -         B <ACTIVE>
+\tWORKGROUP       <00> - <GROUP> B <ACTIVE>
+
+\tMAC Address = 00-00-00-00-00-00
+
+Looking up status of 192.168.1.30
+No reply from 192.168.1.30
+";
+        assert_eq!(
+            parse_name_status(output),
+            vec![
+                NetworkHost {
+                    mac_address: Some("00:11:22:aa:bb:cc".to_string()),
+                    ..host("NAS", "192.168.1.10", Some("HOME"))
+                },
+                host("DESK", "192.168.1.20", Some("WORKGROUP")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_avahi_services() {
+        let output = "+;eth0;IPv4;nas;Microsoft Windows Network;local
+=;eth0;IPv4;nas;Microsoft Windows Network;local;nas.local;192.168.1.10;445;
+=;eth0;IPv6;nas;Microsoft Windows Network;local;nas.local;fe80::1;445;
+=;wlan0;IPv4;nas;Microsoft Windows Network;local;nas.local;192.168.1.10;445;
+=;eth0;IPv4;printer;Microsoft Windows Network;local;printer.local;192.168.1.40;445;
+";
+        assert_eq!(
+            parse_avahi_services(output),
+            vec![
+                host("nas", "192.168.1.10", None),
+                host("printer", "192.168.1.40", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_group_by_workgroup() {
+        let hosts = vec![
+            host("printer", "192.168.1.40", None),
+            host("NAS", "192.168.1.10", Some("WORKGROUP")),
+            host("DESK", "192.168.1.20", Some("HOME")),
+        ];
+        let groups: Vec<(Option<String>, usize)> = group_by_workgroup(&hosts)
+            .into_iter()
+            .map(|(workgroup, hosts)| (workgroup, hosts.len()))
+            .collect();
+        assert_eq!(
+            groups,
+            vec![
+                (Some("HOME".to_string()), 1),
+                (Some("WORKGROUP".to_string()), 1),
+                (None, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_smbclient_error() {
+        assert_eq!(
+            smbclient_error("tree connect failed: NT_STATUS_BAD_NETWORK_NAME\n", ""),
+            "tree connect failed: NT_STATUS_BAD_NETWORK_NAME"
+        );
+        assert_eq!(smbclient_error("", " Connection to nas failed \n"), "Connection to nas failed");
+    }
+
+    #[test]
+    fn test_parse_share_list() {
+        let output = "Disk|media|Films and music
+IPC|IPC$|IPC Service (Samba 4.20)
+Disk|print$|Printer Drivers
+Disk|Backups|
+Printer|laser|Office printer
+Workgroup|HOME|NAS
+";
+        assert_eq!(
+            parse_share_list(output),
+            vec![
+                HostShare {
+                    name: "Backups".to_string(),
+                    comment: String::new(),
+                },
+                HostShare {
+                    name: "media".to_string(),
+                    comment: "Films and music".to_string(),
+                },
+            ]
+        );
+    }
+}
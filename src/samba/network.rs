@@ -0,0 +1,158 @@
+use crate::samba::command_runner::command;
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::Path;
+
+/// Directory holding one entry per network interface
+const NET_CLASS_DIR: &str = "/sys/class/net";
+
+/// Loopback interface, which local tools like smbclient connect through
+pub const LOOPBACK: &str = "lo";
+
+/// A network interface of this computer
+#[derive(Debug, Clone)]
+pub struct NetworkInterface {
+    pub name: String,
+    /// Backed by a network card, as opposed to VPN, container or bridge interfaces
+    pub physical: bool,
+}
+
+/// List the network interfaces, loopback first and the others by name
+pub fn network_interfaces() -> Vec<NetworkInterface> {
+    let Ok(entries) = fs::read_dir(NET_CLASS_DIR) else {
+        return Vec::new();
+    };
+
+    let mut interfaces: Vec<NetworkInterface> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            NetworkInterface {
+                physical: Path::new(NET_CLASS_DIR).join(&name).join("device").exists(),
+                name,
+            }
+        })
+        .collect();
+    interfaces.sort_by(|a, b| (a.name != LOOPBACK, &a.name).cmp(&(b.name != LOOPBACK, &b.name)));
+    interfaces
+}
+
+/// IPv4 subnets of the local networks this computer is connected to, like `192.168.1.0/24`
+pub fn lan_subnets() -> Vec<String> {
+    let output = command("ip")
+        .args(["-o", "-4", "addr", "show", "scope", "global"])
+        .output();
+
+    let Ok(output) = output else {
+        return Vec::new();
+    };
+
+    // Lines look like "2: eth0    inet 192.168.1.23/24 brd 192.168.1.255 scope global eth0"
+    let mut subnets = Vec::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let mut fields = line.split_whitespace();
+        let address = fields.find(|field| *field == "inet").and_then(|_| fields.next());
+        if let Some(subnet) = address.and_then(subnet_of) {
+            if !subnets.contains(&subnet) {
+                subnets.push(subnet);
+            }
+        }
+    }
+    subnets
+}
+
+/// Network of an `address/prefix` pair, e.g. `192.168.1.23/24` gives `192.168.1.0/24`
+fn subnet_of(address: &str) -> Option<String> {
+    let (ip, prefix) = address.split_once('/')?;
+    let ip: Ipv4Addr = ip.parse().ok()?;
+    let prefix: u32 = prefix.parse().ok().filter(|prefix| *prefix <= 32)?;
+    let mask = u32::MAX.checked_shl(32 - prefix).unwrap_or(0);
+    Some(format!("{}/{}", Ipv4Addr::from(u32::from(ip) & mask), prefix))
+}
+
+/// Check one entry of a `hosts allow` / `hosts deny` list: an address, an address
+/// prefix like `192.168.1.`, a network in `addr/prefix` or `addr/mask` form,
+/// a host name, `@netgroup`, or the `ALL` / `EXCEPT` keywords
+pub fn is_valid_host_entry(entry: &str) -> bool {
+    if entry == "ALL" || entry == "EXCEPT" {
+        return true;
+    }
+    if let Some(netgroup) = entry.strip_prefix('@') {
+        return is_host_name(netgroup);
+    }
+
+    let (address, mask) = match entry.split_once('/') {
+        Some((address, mask)) => (address, Some(mask)),
+        None => (entry, None),
+    };
+
+    if let Ok(ip) = address.parse::<IpAddr>() {
+        let max_prefix = if ip.is_ipv4() { 32 } else { 128 };
+        return match mask {
+            None => true,
+            Some(mask) => {
+                mask.parse::<u32>().is_ok_and(|prefix| prefix <= max_prefix)
+                    || (ip.is_ipv4() && mask.parse::<Ipv4Addr>().is_ok())
+            }
+        };
+    }
+
+    // Address prefix such as "192.168.1." matching a whole range
+    if mask.is_none() && address.ends_with('.') {
+        let octets: Vec<&str> = address.trim_end_matches('.').split('.').collect();
+        return octets.len() < 4 && octets.iter().all(|octet| octet.parse::<u8>().is_ok());
+    }
+
+    mask.is_none() && is_host_name(address)
+}
+
+/// Entries of a `hosts allow` value that Samba wouldn't understand
+pub fn invalid_host_entries(entries: &[String]) -> Vec<String> {
+    entries
+        .iter()
+        .filter(|entry| !is_valid_host_entry(entry))
+        .cloned()
+        .collect()
+}
+
+fn is_host_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(['-', '.'])
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+        && name.chars().any(|c| c.is_ascii_alphabetic())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subnet_of() {
+        assert_eq!(subnet_of("192.168.1.23/24"), Some("192.168.1.0/24".to_string()));
+        assert_eq!(subnet_of("10.1.2.3/8"), Some("10.0.0.0/8".to_string()));
+        assert_eq!(subnet_of("10.1.2.3/0"), Some("0.0.0.0/0".to_string()));
+        assert_eq!(subnet_of("10.1.2.3"), None);
+    }
+
+    #[test]
+    fn test_is_valid_host_entry() {
+        for entry in [
+            "127.0.0.1",
+            "192.168.1.",
+            "10.",
+            "192.168.1.0/24",
+            "192.168.1.0/255.255.255.0",
+            "::1",
+            "fe80::/10",
+            "localhost",
+            "nas.home.lan",
+            "@trusted",
+            "EXCEPT",
+        ] {
+            assert!(is_valid_host_entry(entry), "{}", entry);
+        }
+        for entry in ["192.168.1.0/33", "192.168.300.", "1.2.3.4.", "-host", "nas/24", "a,b"] {
+            assert!(!is_valid_host_entry(entry), "{}", entry);
+        }
+    }
+}
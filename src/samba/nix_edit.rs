@@ -0,0 +1,344 @@
+use crate::samba::share_config::get_attrpath_name;
+use rnix::{Root, SyntaxKind, SyntaxNode};
+
+/// Set, replace or remove (`value = None`) a single `"key" = value;` entry inside
+/// the attrset located by `find`, leaving the rest of the file untouched.
+/// An existing entry only gets its value replaced, its key stays as written.
+///
+/// `value` must already be a valid Nix expression (e.g. `"\"required\""`).
+pub fn set_attr(
+    content: &str,
+    find: impl Fn(&SyntaxNode) -> Option<SyntaxNode>,
+    key: &str,
+    value: Option<&str>,
+) -> Result<String, String> {
+    let parsed = Root::parse(content);
+    let root = parsed.syntax();
+
+    let attrset = find(&root).ok_or("Could not find the section to edit in configuration")?;
+
+    let existing = attrset
+        .children()
+        .filter(|child| child.kind() == SyntaxKind::NODE_ATTRPATH_VALUE)
+        .find(|child| get_attrpath_name(child).as_deref() == Some(key));
+
+    match (existing, value) {
+        (Some(entry), Some(value)) => {
+            let current = entry_value(&entry).ok_or("Could not find the value of the entry")?;
+            if current.text() == value {
+                return Ok(content.to_string());
+            }
+            // Replace the value in place
+            let range = current.text_range();
+            let start: usize = range.start().into();
+            let end: usize = range.end().into();
+            Ok(format!("{}{}{}", &content[..start], value, &content[end..]))
+        }
+        (Some(entry), None) => {
+            // Remove the entry, including its line if it stands alone
+            Ok(remove_node(content, &entry))
+        }
+        (None, Some(value)) => {
+            // Insert a new entry just before the closing brace
+            let closing = attrset
+                .last_token()
+                .filter(|token| token.kind() == SyntaxKind::TOKEN_R_BRACE)
+                .ok_or("Could not find closing brace of section")?;
+            let closing_pos: usize = closing.text_range().start().into();
+            let closing_line = line_start(content, closing_pos);
+            let entry = format!("\"{}\" = {};", key, value);
+
+            if content[closing_line..closing_pos].trim().is_empty() {
+                let indent = entry_indent(content, &attrset, closing_line, closing_pos);
+                Ok(format!(
+                    "{}{}{}\n{}",
+                    &content[..closing_line],
+                    indent,
+                    entry,
+                    &content[closing_line..]
+                ))
+            } else {
+                // One-line attrset like `{ a = 1; }`
+                Ok(format!(
+                    "{}{} {}",
+                    &content[..closing_pos],
+                    entry,
+                    &content[closing_pos..]
+                ))
+            }
+        }
+        (None, None) => Ok(content.to_string()),
+    }
+}
+
+/// Turn the `old_name = { ... };` entry of the attrset located by `find` into `new_entry`,
+/// a whole entry like `"docs" = { path = "/srv/docs"; };`. Only the name and the
+/// settings that differ are rewritten, so comments and formatting around them survive.
+/// Settings missing from `new_entry` are removed when listed in `managed`, the others are
+/// left to whoever wrote them.
+pub fn update_entry(
+    content: &str,
+    find: impl Fn(&SyntaxNode) -> Option<SyntaxNode>,
+    old_name: &str,
+    new_entry: &str,
+    managed: &[&str],
+) -> Result<String, String> {
+    let new_root = Root::parse(&format!("{{\n{}\n}}", new_entry)).syntax();
+    let new = new_root
+        .descendants()
+        .find(|node| node.kind() == SyntaxKind::NODE_ATTRPATH_VALUE)
+        .ok_or("Invalid entry to write")?;
+    let new_name = get_attrpath_name(&new).ok_or("Invalid entry to write")?;
+    let new_settings: Vec<(String, String)> = entry_attrset(&new)
+        .ok_or("Invalid entry to write")?
+        .children()
+        .filter(|child| child.kind() == SyntaxKind::NODE_ATTRPATH_VALUE)
+        .filter_map(|child| Some((get_attrpath_name(&child)?, entry_value(&child)?.text().to_string())))
+        .collect();
+
+    let root = Root::parse(content).syntax();
+    let entry = find(&root)
+        .and_then(|parent| named_entry(&parent, old_name))
+        .ok_or_else(|| format!("'{}' not found in configuration", old_name))?;
+    let old_keys: Vec<String> = entry_attrset(&entry)
+        .ok_or_else(|| format!("'{}' isn't an attribute set", old_name))?
+        .children()
+        .filter(|child| child.kind() == SyntaxKind::NODE_ATTRPATH_VALUE)
+        .filter_map(|child| get_attrpath_name(&child))
+        .collect();
+
+    // Rename by replacing the attribute path alone
+    let mut content = content.to_string();
+    if new_name != old_name {
+        let old_path = attrpath(&entry).ok_or("Could not find the name of the entry")?;
+        let new_path = attrpath(&new).ok_or("Invalid entry to write")?;
+        let range = old_path.text_range();
+        let start: usize = range.start().into();
+        let end: usize = range.end().into();
+        content = format!("{}{}{}", &content[..start], new_path.text(), &content[end..]);
+    }
+
+    let find_entry = |root: &SyntaxNode| {
+        find(root)
+            .and_then(|parent| named_entry(&parent, &new_name))
+            .and_then(|entry| entry_attrset(&entry))
+    };
+    let removed = old_keys
+        .iter()
+        .filter(|key| managed.contains(&key.as_str()))
+        .filter(|key| !new_settings.iter().any(|(new_key, _)| new_key == *key));
+    for key in removed {
+        content = set_attr(&content, find_entry, key, None)?;
+    }
+    for (key, value) in &new_settings {
+        content = set_attr(&content, find_entry, key, Some(value))?;
+    }
+    Ok(content)
+}
+
+/// Entry named `name` directly inside `attrset`
+fn named_entry(attrset: &SyntaxNode, name: &str) -> Option<SyntaxNode> {
+    attrset
+        .children()
+        .filter(|child| child.kind() == SyntaxKind::NODE_ATTRPATH_VALUE)
+        .find(|child| get_attrpath_name(child).as_deref() == Some(name))
+}
+
+fn attrpath(entry: &SyntaxNode) -> Option<SyntaxNode> {
+    entry.children().find(|child| child.kind() == SyntaxKind::NODE_ATTRPATH)
+}
+
+/// Value of an entry, whatever expression it is
+pub(crate) fn entry_value(entry: &SyntaxNode) -> Option<SyntaxNode> {
+    entry.children().find(|child| child.kind() != SyntaxKind::NODE_ATTRPATH)
+}
+
+/// Value of an entry when it is an attrset
+fn entry_attrset(entry: &SyntaxNode) -> Option<SyntaxNode> {
+    entry_value(entry).filter(|value| value.kind() == SyntaxKind::NODE_ATTR_SET)
+}
+
+/// Insert `text`, made of whole lines, just before the closing brace of `attrset`.
+/// The brace keeps its line and indentation, and a one-line attrset like `{ a = 1; }`
+/// is split so the text gets lines of its own.
+pub fn insert_before_closing(content: &str, attrset: &SyntaxNode, text: &str) -> Result<String, String> {
+    let closing = attrset
+        .last_token()
+        .filter(|token| token.kind() == SyntaxKind::TOKEN_R_BRACE)
+        .ok_or("Could not find closing brace of section")?;
+    let closing_pos: usize = closing.text_range().start().into();
+    let closing_line = line_start(content, closing_pos);
+
+    if content[closing_line..closing_pos].trim().is_empty() {
+        Ok(format!(
+            "{}{}\n{}",
+            &content[..closing_line],
+            text,
+            &content[closing_line..]
+        ))
+    } else {
+        Ok(format!(
+            "{}\n{}\n{}",
+            content[..closing_pos].trim_end(),
+            text,
+            &content[closing_pos..]
+        ))
+    }
+}
+
+/// Top-level attrset of a NixOS module, inside its `{ config, pkgs, ... }:` function if any
+pub fn module_attrset(root: &SyntaxNode) -> Option<SyntaxNode> {
+    let mut node = root.first_child()?;
+    loop {
+        match node.kind() {
+            SyntaxKind::NODE_ATTR_SET => return Some(node),
+            // The body comes after the parameters
+            SyntaxKind::NODE_LAMBDA => node = node.last_child()?,
+            SyntaxKind::NODE_PAREN => node = node.first_child()?,
+            _ => return None,
+        }
+    }
+}
+
+/// Remove `node` from `content`, including its lines if nothing else shares them
+pub fn remove_node(content: &str, node: &SyntaxNode) -> String {
+    let range = node.text_range();
+    let (start, end) = expand_to_line(content, range.start().into(), range.end().into());
+    format!("{}{}", &content[..start], &content[end..])
+}
+
+/// Byte offset of the start of the line containing `pos`
+fn line_start(content: &str, pos: usize) -> usize {
+    content[..pos].rfind('\n').map(|i| i + 1).unwrap_or(0)
+}
+
+/// Widen `start..end` to whole lines when nothing else shares those lines
+fn expand_to_line(content: &str, start: usize, end: usize) -> (usize, usize) {
+    let first = line_start(content, start);
+    let last = content[end..]
+        .find('\n')
+        .map(|i| end + i + 1)
+        .unwrap_or(content.len());
+
+    if content[first..start].trim().is_empty() && content[end..last].trim().is_empty() {
+        (first, last)
+    } else {
+        (start, end)
+    }
+}
+
+/// Indentation to use for a new entry: copy an existing entry, or indent past the closing brace
+fn entry_indent(content: &str, attrset: &SyntaxNode, closing_line: usize, closing_pos: usize) -> String {
+    if let Some(first) = attrset
+        .children()
+        .find(|child| child.kind() == SyntaxKind::NODE_ATTRPATH_VALUE)
+    {
+        let start: usize = first.text_range().start().into();
+        let prefix = &content[line_start(content, start)..start];
+        if prefix.trim().is_empty() {
+            return prefix.to_string();
+        }
+    }
+    format!("{}  ", &content[closing_line..closing_pos])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find_global(root: &SyntaxNode) -> Option<SyntaxNode> {
+        root.descendants()
+            .filter(|node| node.kind() == SyntaxKind::NODE_ATTRPATH_VALUE)
+            .find(|node| get_attrpath_name(node).as_deref() == Some("global"))
+            .and_then(|node| node.children().find(|c| c.kind() == SyntaxKind::NODE_ATTR_SET))
+    }
+
+    const CONFIG: &str = r#"{
+  global = {
+    # keep me
+    "workgroup" = "WORKGROUP";
+  };
+}"#;
+
+    #[test]
+    fn test_set_attr_inserts_and_replaces() {
+        let inserted = set_attr(CONFIG, find_global, "smb encrypt", Some("\"required\"")).unwrap();
+        assert!(inserted.contains("    \"smb encrypt\" = \"required\";\n  };"));
+        assert!(inserted.contains("# keep me"), "Comments must survive");
+
+        let replaced = set_attr(&inserted, find_global, "smb encrypt", Some("\"off\"")).unwrap();
+        assert!(replaced.contains("\"smb encrypt\" = \"off\";"));
+        assert!(!replaced.contains("required"));
+    }
+
+    #[test]
+    fn test_set_attr_removes_whole_line() {
+        let removed = set_attr(CONFIG, find_global, "workgroup", None).unwrap();
+        assert_eq!(removed, "{\n  global = {\n    # keep me\n  };\n}");
+    }
+
+    #[test]
+    fn test_update_entry_keeps_comments() {
+        let config = r#"{
+  settings = {
+    # Team documents, backed up nightly
+    docs = {
+      path = "/srv/docs"; # on the RAID
+      "read only" = "yes";
+      "guest account" = "nobody";
+      comment = "old";
+      "veto files" = "/*.tmp/";
+    };
+  };
+}"#;
+        fn find_settings(root: &SyntaxNode) -> Option<SyntaxNode> {
+            root.descendants()
+                .filter(|node| node.kind() == SyntaxKind::NODE_ATTRPATH_VALUE)
+                .find(|node| get_attrpath_name(node).as_deref() == Some("settings"))
+                .and_then(|node| entry_attrset(&node))
+        }
+
+        // `guest account` is no longer written, `comment` and `veto files` were added by hand
+        let updated = update_entry(
+            config,
+            find_settings,
+            "docs",
+            "    \"team-docs\" = {\n      path = \"/srv/docs\";\n      \"read only\" = \"no\";\n      \"guest ok\" = \"no\";\n    };",
+            &["path", "read only", "guest ok", "guest account"],
+        )
+        .unwrap();
+        assert_eq!(
+            updated,
+            r#"{
+  settings = {
+    # Team documents, backed up nightly
+    "team-docs" = {
+      path = "/srv/docs"; # on the RAID
+      "read only" = "no";
+      comment = "old";
+      "veto files" = "/*.tmp/";
+      "guest ok" = "no";
+    };
+  };
+}"#
+        );
+    }
+
+    #[test]
+    fn test_insert_before_closing() {
+        let root = Root::parse(CONFIG).syntax();
+        let global = find_global(&root).unwrap();
+        let inserted = insert_before_closing(CONFIG, &global, "    \"smb encrypt\" = \"required\";").unwrap();
+        assert!(inserted.contains("\"WORKGROUP\";\n    \"smb encrypt\" = \"required\";\n  };\n}"));
+
+        // One-line module, the closing brace used to be looked for on a line of its own
+        let config = "{ config, ... }: { imports = [ ./hardware.nix ]; } # end\n";
+        let root = Root::parse(config).syntax();
+        let module = module_attrset(&root).unwrap();
+        let inserted = insert_before_closing(config, &module, "  services.samba.enable = true;").unwrap();
+        assert_eq!(
+            inserted,
+            "{ config, ... }: { imports = [ ./hardware.nix ];\n  services.samba.enable = true;\n} # end\n"
+        );
+    }
+}
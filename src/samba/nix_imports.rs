@@ -0,0 +1,115 @@
+use crate::samba::nix_parse::parse_file;
+use crate::samba::sandbox::host_path;
+use crate::samba::share_config::get_attrpath_name;
+use rnix::{SyntaxKind, SyntaxNode};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The main configuration file followed by the files it imports, recursively.
+/// Only literal relative or absolute paths are followed; anything computed
+/// (`<nixpkgs/...>`, function calls, interpolations) is ignored.
+pub fn config_files(main: &str) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut seen = HashSet::new();
+    collect(PathBuf::from(main), &mut files, &mut seen);
+    files
+}
+
+fn collect(path: PathBuf, files: &mut Vec<PathBuf>, seen: &mut HashSet<PathBuf>) {
+    // Importing a directory loads its default.nix
+    let path = if host_path(&path).is_dir() {
+        path.join("default.nix")
+    } else {
+        path
+    };
+
+    let key = fs::canonicalize(host_path(&path)).unwrap_or_else(|_| path.clone());
+    if !seen.insert(key) {
+        return;
+    }
+
+    let content = fs::read_to_string(host_path(&path)).ok();
+    files.push(path.clone());
+
+    let Some(content) = content else {
+        return;
+    };
+    let base_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+    for import in import_paths(&parse_file(&path, &content).syntax(), &base_dir) {
+        collect(import, files, seen);
+    }
+}
+
+/// Paths listed in the `imports = [ ... ];` entries of a file, resolved against `base_dir`
+fn import_paths(root: &SyntaxNode, base_dir: &Path) -> Vec<PathBuf> {
+    let mut lists = Vec::new();
+    find_import_lists(root, &mut lists);
+
+    lists
+        .iter()
+        .flat_map(|list| list.children())
+        .filter_map(|item| {
+            let text = item.text().to_string();
+            let text = text.trim();
+            if text.contains("${") {
+                return None;
+            }
+            if text.starts_with("./") || text.starts_with("../") {
+                Some(base_dir.join(text))
+            } else if text.starts_with('/') {
+                Some(PathBuf::from(text))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Find the list values of every `imports` entry
+fn find_import_lists(node: &SyntaxNode, lists: &mut Vec<SyntaxNode>) {
+    for child in node.children() {
+        if child.kind() == SyntaxKind::NODE_ATTRPATH_VALUE
+            && get_attrpath_name(&child).as_deref() == Some("imports")
+        {
+            lists.extend(
+                child
+                    .children()
+                    .filter(|value| value.kind() == SyntaxKind::NODE_LIST),
+            );
+            continue;
+        }
+        find_import_lists(&child, lists);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rnix::Root;
+
+    #[test]
+    fn test_import_paths_resolves_literal_paths() {
+        let content = r#"{ config, pkgs, ... }:
+{
+  imports = [
+    ./samba.nix
+    ../common
+    /etc/nixos/extra.nix
+    <nixpkgs/nixos/modules/profiles/minimal.nix>
+    (import ./generated.nix { })
+  ];
+}
+"#;
+        let paths = import_paths(&Root::parse(content).syntax(), Path::new("/etc/nixos/customConfig"));
+
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("/etc/nixos/customConfig/./samba.nix"),
+                PathBuf::from("/etc/nixos/customConfig/../common"),
+                PathBuf::from("/etc/nixos/extra.nix"),
+            ]
+        );
+    }
+}
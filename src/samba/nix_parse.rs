@@ -0,0 +1,134 @@
+use once_cell::sync::Lazy;
+use rnix::{Parse, Root};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Error of a load stopped with `LoadProgress::cancel`
+pub const CANCELLED: &str = "Loading cancelled";
+
+/// Content of a file with its parse
+type CachedParse = (String, Parse<Root>);
+
+/// Files parsed so far, with the content they were parsed from. Generated configurations
+/// can have thousands of lines, and a single load reads each file several times.
+static CACHE: Lazy<Mutex<HashMap<PathBuf, CachedParse>>> = Lazy::new(Default::default);
+
+thread_local! {
+    /// Progress of the load running on this thread, if it is followed
+    static PROGRESS: RefCell<Option<Arc<LoadProgress>>> = const { RefCell::new(None) };
+}
+
+/// Parse of `content`, read from `path`, reused until the file changes.
+/// The lock is held while parsing, so loads running together parse a file once.
+pub fn parse_file(path: &Path, content: &str) -> Parse<Root> {
+    let mut cache = CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((_, parsed)) = cache.get(path).filter(|(cached, _)| cached == content) {
+        return parsed.clone();
+    }
+
+    with_current(|progress| progress.start_file(path));
+    let parsed = Root::parse(content);
+    with_current(LoadProgress::finish_file);
+    cache.insert(path.to_path_buf(), (content.to_string(), parsed.clone()));
+    parsed
+}
+
+/// A load running on a worker thread, followed by the UI which can also stop it.
+/// Parsing a file can't be interrupted, the load stops before the next one.
+#[derive(Debug, Default)]
+pub struct LoadProgress {
+    files_parsed: AtomicUsize,
+    current_file: Mutex<Option<String>>,
+    cancelled: AtomicBool,
+}
+
+impl LoadProgress {
+    /// Files parsed since the load started, the cached ones left out
+    pub fn files_parsed(&self) -> usize {
+        self.files_parsed.load(Ordering::Relaxed)
+    }
+
+    /// File being parsed, None between two files
+    pub fn current_file(&self) -> Option<String> {
+        self.current_file.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Ask the load to stop, it then fails with `CANCELLED`
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    fn start_file(&self, path: &Path) {
+        *self.current_file.lock().unwrap_or_else(|e| e.into_inner()) = Some(path.to_string_lossy().to_string());
+    }
+
+    fn finish_file(&self) {
+        self.files_parsed.fetch_add(1, Ordering::Relaxed);
+        *self.current_file.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+}
+
+/// Run `load` on this thread, reporting the files it parses to `progress`
+pub fn with_progress<T>(progress: Arc<LoadProgress>, load: impl FnOnce() -> T) -> T {
+    PROGRESS.with(|current| current.replace(Some(progress)));
+    let result = load();
+    PROGRESS.with(|current| current.replace(None));
+    result
+}
+
+/// Fail with `CANCELLED` when the load running on this thread was cancelled
+pub fn check_cancelled() -> Result<(), String> {
+    let cancelled = PROGRESS.with(|current| current.borrow().as_ref().is_some_and(|progress| progress.is_cancelled()));
+    if cancelled {
+        return Err(CANCELLED.to_string());
+    }
+    Ok(())
+}
+
+fn with_current(report: impl FnOnce(&LoadProgress)) {
+    PROGRESS.with(|current| {
+        if let Some(progress) = current.borrow().as_ref() {
+            report(progress);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_file_cache() {
+        let path = Path::new("/etc/nixos/test-parse-cache.nix");
+        let progress = Arc::new(LoadProgress::default());
+        with_progress(progress.clone(), || {
+            parse_file(path, "{ a = 1; }");
+            parse_file(path, "{ a = 1; }");
+            assert_eq!(progress.files_parsed(), 1);
+
+            let parsed = parse_file(path, "{ a = 2; }");
+            assert_eq!(progress.files_parsed(), 2);
+            assert_eq!(parsed.syntax().to_string(), "{ a = 2; }");
+            assert_eq!(progress.current_file(), None);
+        });
+    }
+
+    #[test]
+    fn test_check_cancelled() {
+        let progress = Arc::new(LoadProgress::default());
+        assert_eq!(check_cancelled(), Ok(()));
+        with_progress(progress.clone(), || {
+            assert_eq!(check_cancelled(), Ok(()));
+            progress.cancel();
+            assert_eq!(check_cancelled(), Err(CANCELLED.to_string()));
+        });
+        assert_eq!(check_cancelled(), Ok(()));
+    }
+}
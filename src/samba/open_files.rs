@@ -0,0 +1,91 @@
+use crate::samba::sandbox::is_sandboxed;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Process keeping a mount point busy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockingProcess {
+    pub pid: u32,
+    /// Command name, from /proc/<pid>/comm
+    pub name: String,
+    /// Files open under the mount point, the working directory included
+    pub open_files: usize,
+}
+
+/// Processes with open files, or their working directory, under `mount_point`,
+/// found by scanning /proc. Only processes the app may inspect are seen: those of
+/// its user, or all of them when running as root. Inside Flatpak the host's
+/// processes aren't visible, so nothing is returned.
+pub fn blocking_processes(mount_point: &Path) -> Vec<BlockingProcess> {
+    if is_sandboxed() {
+        return Vec::new();
+    }
+    let Ok(entries) = fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+
+    let mut processes = Vec::new();
+    for entry in entries.flatten() {
+        let Some(pid) = entry.file_name().to_str().and_then(|name| name.parse::<u32>().ok()) else {
+            continue;
+        };
+        let dir = entry.path();
+
+        let mut paths: Vec<PathBuf> = fs::read_link(dir.join("cwd")).into_iter().collect();
+        if let Ok(fds) = fs::read_dir(dir.join("fd")) {
+            paths.extend(fds.flatten().filter_map(|fd| fs::read_link(fd.path()).ok()));
+        }
+
+        let open_files = paths.iter().filter(|path| path.starts_with(mount_point)).count();
+        if open_files > 0 {
+            let name = fs::read_to_string(dir.join("comm"))
+                .map(|name| name.trim().to_string())
+                .unwrap_or_else(|_| pid.to_string());
+            processes.push(BlockingProcess { pid, name, open_files });
+        }
+    }
+    processes.sort_by(|a, b| a.name.cmp(&b.name).then(a.pid.cmp(&b.pid)));
+    processes
+}
+
+/// "firefox (1234), nautilus (5678)"
+pub fn describe(processes: &[BlockingProcess]) -> String {
+    processes
+        .iter()
+        .map(|process| format!("{} ({})", process.name, process.pid))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocking_processes() {
+        let dir = std::env::temp_dir().join(format!("samba-share-open-files-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        assert!(blocking_processes(&dir).is_empty());
+
+        let file = fs::File::create(dir.join("open.txt")).unwrap();
+        let processes = blocking_processes(&dir);
+        drop(file);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let own = processes
+            .iter()
+            .find(|process| process.pid == std::process::id())
+            .expect("the test process holds a file open");
+        assert_eq!(own.open_files, 1);
+    }
+
+    #[test]
+    fn test_describe() {
+        let processes = vec![
+            BlockingProcess { pid: 1234, name: "firefox".to_string(), open_files: 2 },
+            BlockingProcess { pid: 5678, name: "nautilus".to_string(), open_files: 1 },
+        ];
+        assert_eq!(describe(&processes), "firefox (1234), nautilus (5678)");
+        assert_eq!(describe(&[]), "");
+    }
+}
@@ -0,0 +1,61 @@
+use crate::samba::logs::read_audit_log;
+use crate::samba::mount_operations::MountedShare;
+use crate::samba::share_config::SambaShareConfig;
+
+/// Counters shown on the main page
+#[derive(Debug, Clone, Default)]
+pub struct Overview {
+    pub local_shares: usize,
+    pub remote_shares: usize,
+    pub mounted: usize,
+    /// Errors met while reading the local shares
+    pub local_error: Option<String>,
+    /// Errors met while reading the remote shares or mount status
+    pub remote_error: Option<String>,
+    /// Most recent audit log line, if any share is audited
+    pub last_activity: Option<String>,
+}
+
+impl Overview {
+    /// Counters for already loaded local and remote shares
+    pub fn new(
+        local: &Result<Vec<SambaShareConfig>, String>,
+        remote: &Result<Vec<MountedShare>, String>,
+        last_activity: Option<String>,
+    ) -> Self {
+        let mut overview = Self {
+            last_activity,
+            ..Default::default()
+        };
+
+        match local {
+            Ok(shares) => overview.local_shares = shares.len(),
+            Err(e) => overview.local_error = Some(e.clone()),
+        }
+
+        match remote {
+            Ok(shares) => {
+                overview.remote_shares = shares.len();
+                overview.mounted = shares.iter().filter(|share| share.is_mounted).count();
+            }
+            Err(e) => overview.remote_error = Some(e.clone()),
+        }
+
+        overview
+    }
+
+    /// Most recent audit log line. Reads the journal, so call it off the main thread.
+    pub fn load_last_activity() -> Option<String> {
+        read_audit_log(None, 1)
+            .ok()
+            .and_then(|lines| lines.last().cloned())
+    }
+
+    /// Number of sources that couldn't be read
+    pub fn failures(&self) -> usize {
+        [&self.local_error, &self.remote_error]
+            .iter()
+            .filter(|error| error.is_some())
+            .count()
+    }
+}
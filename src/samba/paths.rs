@@ -0,0 +1,86 @@
+use once_cell::sync::OnceCell;
+use std::path::Path;
+
+/// NixOS configuration file edited by the app
+pub const DEFAULT_CONFIG_PATH: &str = "/etc/nixos/customConfig/default.nix";
+
+/// Command line option choosing the configuration file, as `--config FILE` or `--config=FILE`
+pub const CONFIG_FLAG: &str = "--config";
+
+/// Environment variable choosing the configuration file when the option isn't given
+pub const CONFIG_ENV: &str = "SAMBA_SHARE_CONFIG";
+
+static CONFIG_PATH: OnceCell<String> = OnceCell::new();
+
+/// Use another main configuration file, e.g. the demo copy.
+/// Must be called before anything is loaded; only the first call has an effect.
+pub fn set_config_path(path: &str) {
+    if CONFIG_PATH.set(path.to_string()).is_err() {
+        eprintln!("Configuration path already set");
+    }
+}
+
+/// Main configuration file, imports are followed from there
+pub fn config_path() -> &'static str {
+    CONFIG_PATH
+        .get()
+        .map(|path| path.as_str())
+        .unwrap_or(DEFAULT_CONFIG_PATH)
+}
+
+/// Configuration file given with `CONFIG_FLAG`, and the other arguments
+pub fn split_config_flag(args: &[String]) -> (Option<String>, Vec<String>) {
+    let mut path = None;
+    let mut rest = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == CONFIG_FLAG {
+            path = args.next().cloned();
+        } else if let Some(value) = arg.strip_prefix(CONFIG_FLAG).and_then(|value| value.strip_prefix('=')) {
+            path = Some(value.to_string());
+        } else {
+            rest.push(arg.clone());
+        }
+    }
+    (path.filter(|path| !path.is_empty()), rest)
+}
+
+/// `path` relative to `current_dir` when it isn't absolute, as privileged commands
+/// may run from another folder
+pub fn absolute_path(path: &str, current_dir: &Path) -> String {
+    if path.starts_with('/') {
+        path.to_string()
+    } else {
+        current_dir.join(path).to_string_lossy().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_split_config_flag() {
+        assert_eq!(
+            split_config_flag(&args(&["list", "--config", "/etc/nixos/configuration.nix", "--json"])),
+            (Some("/etc/nixos/configuration.nix".to_string()), args(&["list", "--json"]))
+        );
+        assert_eq!(
+            split_config_flag(&args(&["--config=flake/hosts/nas.nix"])),
+            (Some("flake/hosts/nas.nix".to_string()), Vec::new())
+        );
+        assert_eq!(split_config_flag(&args(&["remote", "--config"])), (None, args(&["remote"])));
+        assert_eq!(split_config_flag(&args(&["--configure"])), (None, args(&["--configure"])));
+    }
+
+    #[test]
+    fn test_absolute_path() {
+        let current_dir = Path::new("/home/alice/nixos-config");
+        assert_eq!(absolute_path("/etc/nixos/configuration.nix", current_dir), "/etc/nixos/configuration.nix");
+        assert_eq!(absolute_path("hosts/nas.nix", current_dir), "/home/alice/nixos-config/hosts/nas.nix");
+    }
+}
@@ -0,0 +1,37 @@
+use crate::samba::sudo_write::run_privileged;
+use crate::samba::SambaShareConfig;
+
+/// Mode for a drop box folder: guests can add files but not list or remove others' files
+const DROP_BOX_FOLDER_MODE: &str = "1733";
+
+/// Configure `share` as a public drop box for scan-to-folder printers and guests:
+/// guest writable, files created world-readable/writable by the guest account
+pub fn apply_drop_box(share: &mut SambaShareConfig) {
+    share.browsable = true;
+    share.read_only = false;
+    share.guest_ok = true;
+    share.force_user = "nobody".to_string();
+    share.force_group = "nogroup".to_string();
+    share.create_mask = "0666".to_string();
+    share.directory_mask = "0777".to_string();
+}
+
+/// Create the drop box folder and make it write-only for everyone but its owner
+pub fn prepare_drop_box_folder(path: &str) -> Result<(), String> {
+    if !path.starts_with('/') {
+        return Err("Folder must be an absolute path".to_string());
+    }
+
+    run_privileged(
+        "/bin/sh",
+        &[
+            "-c",
+            r#"mkdir -p "$1" && chmod "$2" "$1""#,
+            "sh",
+            path,
+            DROP_BOX_FOLDER_MODE,
+        ],
+        None,
+    )
+    .map(|_| ())
+}
@@ -0,0 +1,196 @@
+use crate::samba::command_runner::run_output;
+use crate::samba::disk_space::disk_space;
+use crate::samba::sudo_write::run_privileged;
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+
+/// Script run as root to set or remove (`none`) the size limit of a folder.
+/// Arguments: kind ("btrfs"/"xfs"), folder, limit in bytes or "none", XFS project ID.
+const QUOTA_SCRIPT: &str = r#"set -e
+export PATH=/run/current-system/sw/bin:/run/wrappers/bin:$PATH
+path="$2"
+limit="$3"
+mount=$(findmnt -n -o TARGET -T "$path")
+case "$1" in
+btrfs)
+  if ! btrfs subvolume show "$path" >/dev/null 2>&1; then
+    echo "$path is not a Btrfs subvolume" >&2
+    exit 1
+  fi
+  if [ "$limit" != none ] && ! btrfs qgroup show "$path" >/dev/null 2>&1; then
+    btrfs quota enable "$mount"
+  fi
+  btrfs qgroup limit "$limit" "$path"
+  ;;
+xfs)
+  if [ "$limit" = none ]; then
+    xfs_quota -x -c "limit -p bhard=0 $4" "$mount"
+    xfs_quota -x -c "project -C -p $path $4" "$mount"
+  else
+    xfs_quota -x -c "project -s -p $path $4" "$mount"
+    xfs_quota -x -c "limit -p bhard=$limit $4" "$mount"
+  fi
+  ;;
+esac
+"#;
+
+/// Inode number of the root directory of every Btrfs subvolume
+const BTRFS_SUBVOLUME_INODE: u64 = 256;
+
+/// How a folder gets a size limit of its own
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuotaKind {
+    /// Qgroup of the Btrfs subvolume the folder is the root of
+    Btrfs,
+    /// XFS project quota, needs the filesystem mounted with `prjquota`
+    Xfs,
+}
+
+impl QuotaKind {
+    /// Quota available on a filesystem type as reported by df
+    pub fn for_fs_type(fs_type: &str) -> Option<Self> {
+        match fs_type {
+            "btrfs" => Some(QuotaKind::Btrfs),
+            "xfs" => Some(QuotaKind::Xfs),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            QuotaKind::Btrfs => "btrfs",
+            QuotaKind::Xfs => "xfs",
+        }
+    }
+}
+
+/// Space used by a folder with a size limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuotaUsage {
+    pub used: u64,
+    pub limit: u64,
+}
+
+impl QuotaUsage {
+    /// Share of the limit in use, from 0 to 1
+    pub fn fraction(&self) -> f64 {
+        if self.limit == 0 {
+            return 0.0;
+        }
+        (self.used as f64 / self.limit as f64).min(1.0)
+    }
+}
+
+/// Usage of the size limit of `path`, None when it has none.
+/// Read without privileges: Btrfs exposes qgroups in sysfs, and df reports
+/// the project quota of an XFS folder instead of the filesystem.
+pub fn quota_usage(path: &str, kind: QuotaKind) -> Result<Option<QuotaUsage>, String> {
+    match kind {
+        QuotaKind::Btrfs => {
+            let metadata = fs::metadata(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+            if metadata.ino() != BTRFS_SUBVOLUME_INODE {
+                return Ok(None);
+            }
+            let uuid = run_output("findmnt", &["-n", "-o", "UUID", "-T", path])?;
+            let id = run_output("btrfs", &["inspect-internal", "rootid", path])?;
+            let qgroup = format!("/sys/fs/btrfs/{}/qgroups/0_{}", uuid, id);
+            // No qgroup directory while quotas are disabled
+            let read = |name: &str| {
+                fs::read_to_string(format!("{}/{}", qgroup, name))
+                    .ok()
+                    .and_then(|value| value.trim().parse::<u64>().ok())
+            };
+            Ok(match (read("referenced"), read("max_referenced")) {
+                (Some(used), Some(limit)) if limit > 0 => Some(QuotaUsage { used, limit }),
+                _ => None,
+            })
+        }
+        QuotaKind::Xfs => {
+            if project(path)? == 0 {
+                return Ok(None);
+            }
+            let space = disk_space(path)?;
+            Ok(Some(QuotaUsage {
+                used: space.total.saturating_sub(space.available),
+                limit: space.total,
+            }))
+        }
+    }
+}
+
+/// Set the size limit of `path` in bytes, or remove it with None.
+/// The limit lives in the filesystem, it is kept across rebuilds without any configuration.
+pub fn set_quota(path: &str, kind: QuotaKind, limit: Option<u64>) -> Result<(), String> {
+    if !path.starts_with('/') {
+        return Err("Folder must be an absolute path".to_string());
+    }
+    // xfs_quota splits its commands on spaces
+    if kind == QuotaKind::Xfs && path.contains(char::is_whitespace) {
+        return Err("XFS size limits can't be set on folders with spaces in their path".to_string());
+    }
+    if limit == Some(0) {
+        return Err("The size limit must be above zero".to_string());
+    }
+
+    // An XFS folder keeps the project it already has
+    let project_id = match kind {
+        QuotaKind::Xfs => match project(path)? {
+            0 => project_id(path),
+            current => current,
+        },
+        QuotaKind::Btrfs => 0,
+    }
+    .to_string();
+    let limit = limit.map(|limit| limit.to_string()).unwrap_or_else(|| "none".to_string());
+    run_privileged(
+        "/bin/sh",
+        &["-c", QUOTA_SCRIPT, "sh", kind.name(), path, &limit, &project_id],
+        None,
+    )
+    .map(|_| ())
+}
+
+/// XFS project of a folder, 0 for none
+fn project(path: &str) -> Result<u32, String> {
+    parse_project(&run_output("lsattr", &["-p", "-d", path])?)
+        .ok_or_else(|| format!("Failed to read the project of {}", path))
+}
+
+/// `lsattr -p -d` prints the project before the flags: `  42 --------------P-- /srv/share`
+fn parse_project(output: &str) -> Option<u32> {
+    output.split_whitespace().next()?.parse().ok()
+}
+
+/// Project ID derived from the folder, so a share keeps the same one.
+/// FNV-1a, kept in 31 bits and never 0, the project of files without one.
+fn project_id(path: &str) -> u32 {
+    let hash = path
+        .bytes()
+        .fold(0x811c9dc5u32, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x01000193));
+    (hash & 0x7fff_ffff).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_project() {
+        assert_eq!(parse_project("   42 --------------P--- /srv/share\n"), Some(42));
+        assert_eq!(parse_project("    0 ------------------ /srv/share\n"), Some(0));
+        assert_eq!(parse_project("lsattr: Operation not supported"), None);
+    }
+
+    #[test]
+    fn test_project_id() {
+        assert_eq!(project_id("/srv/share"), project_id("/srv/share"));
+        assert_ne!(project_id("/srv/share"), project_id("/srv/other"));
+        assert!(project_id("/srv/share") > 0 && project_id("/srv/share") <= 0x7fff_ffff);
+    }
+
+    #[test]
+    fn test_fraction() {
+        assert_eq!(QuotaUsage { used: 25, limit: 100 }.fraction(), 0.25);
+        assert_eq!(QuotaUsage { used: 150, limit: 100 }.fraction(), 1.0);
+    }
+}
@@ -0,0 +1,172 @@
+use crate::samba::command_runner::command;
+use crate::samba::root::skip_escalation;
+use crate::samba::sandbox::is_sandboxed;
+use crate::samba::share_backend::backend;
+use crate::samba::sudo_write::run_privileged;
+use gio::prelude::*;
+use std::ffi::OsStr;
+use std::path::Path;
+
+/// NixOS setuid wrapper for pkexec, preferred over the one in PATH
+const PKEXEC_WRAPPER: &str = "/run/wrappers/bin/pkexec";
+
+/// Stage of a running rebuild, guessed from its output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RebuildPhase {
+    /// Evaluating the Nix expressions, nothing printed for a while
+    Evaluating,
+    /// Fetching store paths from a binary cache
+    Downloading,
+    /// Building derivations locally
+    Building,
+    /// Switching to the new configuration and restarting services
+    Activating,
+}
+
+impl RebuildPhase {
+    /// Phase starting with this output line, if any
+    pub fn from_line(line: &str) -> Option<Self> {
+        let line = line.trim_start();
+        if line.starts_with("building the system configuration") || line.starts_with("building Nix") {
+            Some(Self::Evaluating)
+        } else if line.starts_with("copying path") || (line.starts_with("these ") && line.contains("will be fetched")) {
+            Some(Self::Downloading)
+        } else if line.starts_with("building '") || (line.starts_with("these ") && line.contains("will be built")) {
+            Some(Self::Building)
+        } else if line.starts_with("activating the configuration")
+            || line.starts_with("stopping the following units")
+            || line.starts_with("setting up /etc")
+        {
+            Some(Self::Activating)
+        } else {
+            None
+        }
+    }
+}
+
+/// Command applying the saved configuration, `nixos-rebuild switch` on NixOS, running
+/// with elevated privileges, output piped to the app
+pub struct Rebuild {
+    subprocess: gio::Subprocess,
+}
+
+impl Rebuild {
+    /// Start the rebuild. Authentication goes through the polkit agent of the session,
+    /// unless the app already runs as root.
+    pub fn start() -> Result<Self, String> {
+        let launcher = if !is_sandboxed() && Path::new(PKEXEC_WRAPPER).exists() {
+            PKEXEC_WRAPPER
+        } else {
+            "pkexec"
+        };
+
+        // Build the command through the runner so demo and Flatpak modes apply.
+        // setsid puts the rebuild and everything it starts in a process group
+        // led by this process, so `terminate` can stop all of it.
+        let mut rebuild = if skip_escalation() {
+            command("setsid")
+        } else {
+            let mut escalated = command(launcher);
+            escalated.arg("setsid");
+            escalated
+        };
+        let apply_command = backend().apply_command();
+        rebuild.args(apply_command);
+        let argv: Vec<&OsStr> = std::iter::once(rebuild.get_program())
+            .chain(rebuild.get_args())
+            .collect();
+
+        // nixos-rebuild reports progress on stderr, read both as one stream
+        let subprocess = gio::Subprocess::newv(
+            &argv,
+            gio::SubprocessFlags::STDOUT_PIPE | gio::SubprocessFlags::STDERR_MERGE,
+        )
+        .map_err(|e| format!("Failed to start {}: {}", apply_command[0], e))?;
+
+        Ok(Self { subprocess })
+    }
+
+    /// Call `on_line` for every output line until the rebuild exits, then report how it ended
+    pub async fn supervise(&self, on_line: impl Fn(&str)) -> Result<(), String> {
+        if let Some(stdout) = self.subprocess.stdout_pipe() {
+            let lines = gio::DataInputStream::new(&stdout);
+            loop {
+                match lines.read_line_utf8_future(glib::Priority::DEFAULT).await {
+                    Ok(Some(line)) => on_line(&line),
+                    Ok(None) => break,
+                    Err(e) => {
+                        eprintln!("Failed to read rebuild output: {}", e);
+                        break;
+                    }
+                }
+            }
+        }
+
+        self.subprocess
+            .wait_future()
+            .await
+            .map_err(|e| format!("Failed to wait for nixos-rebuild: {}", e))?;
+
+        if self.subprocess.is_successful() {
+            return Ok(());
+        }
+        if !self.subprocess.has_exited() {
+            return Err(format!(
+                "nixos-rebuild was stopped by signal {}",
+                self.subprocess.term_sig()
+            ));
+        }
+        match self.subprocess.exit_status() {
+            126 => Err("Authorization cancelled by user".to_string()),
+            127 => Err("Authorization unavailable".to_string()),
+            code => Err(format!("nixos-rebuild exited with status {}", code)),
+        }
+    }
+
+    /// Process id of the rebuild, which is also its process group id.
+    /// `None` inside Flatpak, where only the local flatpak-spawn process is known.
+    pub fn pid(&self) -> Option<String> {
+        if is_sandboxed() {
+            return None;
+        }
+        self.subprocess.identifier().map(|pid| pid.to_string())
+    }
+
+    /// Whether pkexec couldn't ask for a password, e.g. no polkit agent is running.
+    /// Only meaningful once `supervise` returned.
+    pub fn authorization_unavailable(&self) -> bool {
+        self.subprocess.has_exited() && self.subprocess.exit_status() == 127
+    }
+}
+
+/// Stop the rebuild process group started by `Rebuild::start`.
+/// The rebuild runs as root, so the signal is sent with elevated privileges.
+pub fn terminate(pid: &str) -> Result<(), String> {
+    run_privileged("kill", &["-TERM", "--", &format!("-{}", pid)], None).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_phase_from_line() {
+        assert_eq!(
+            RebuildPhase::from_line("building the system configuration..."),
+            Some(RebuildPhase::Evaluating)
+        );
+        assert_eq!(
+            RebuildPhase::from_line("these 12 paths will be fetched (4.20 MiB download, 20.1 MiB unpacked):"),
+            Some(RebuildPhase::Downloading)
+        );
+        assert_eq!(
+            RebuildPhase::from_line("building '/nix/store/abc-etc.drv'..."),
+            Some(RebuildPhase::Building)
+        );
+        assert_eq!(
+            RebuildPhase::from_line("activating the configuration..."),
+            Some(RebuildPhase::Activating)
+        );
+        assert_eq!(RebuildPhase::from_line("  /nix/store/abc-samba-4.20"), None);
+    }
+}
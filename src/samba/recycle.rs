@@ -0,0 +1,237 @@
+use crate::samba::share_config::SambaShareConfig;
+use crate::samba::sudo_write::run_privileged;
+use std::path::{Component, Path, PathBuf};
+
+/// Repository of the recycle module when `recycle:repository` isn't set
+const RECYCLE_DIR: &str = ".recycle";
+
+/// Script run as root listing the files of a recycle bin, since bins are often private.
+/// Argument: bin folder. Prints `size<TAB>mtime<TAB>relative path` separated by NUL characters.
+const LIST_SCRIPT: &str = r#"set -e
+export PATH=/run/current-system/sw/bin:/run/wrappers/bin:$PATH
+[ -d "$1" ] || exit 0
+find "$1" -mindepth 1 -type f -printf '%s\t%T@\t%P\0'
+"#;
+
+/// Script run as root moving recycled files back. Arguments: pairs of recycled file and destination.
+/// Existing files are never overwritten, they are reported once the others are restored.
+const RESTORE_SCRIPT: &str = r#"set -e
+export PATH=/run/current-system/sw/bin:/run/wrappers/bin:$PATH
+existing=""
+while [ $# -ge 2 ]; do
+  src="$1"
+  dest="$2"
+  shift 2
+  if [ -e "$dest" ]; then
+    existing="$existing $dest"
+    continue
+  fi
+  parent=$(dirname "$dest")
+  if [ ! -d "$parent" ]; then
+    mkdir -p "$parent"
+    chown --reference="$(dirname "$src")" "$parent"
+  fi
+  mv -- "$src" "$dest"
+done
+if [ -n "$existing" ]; then
+  echo "Already exists:$existing" >&2
+  exit 1
+fi
+"#;
+
+/// Script run as root deleting recycled files for good. Arguments: bin folder, then the files.
+/// Folders left empty are removed too, the module recreates them.
+const PURGE_SCRIPT: &str = r#"set -e
+export PATH=/run/current-system/sw/bin:/run/wrappers/bin:$PATH
+root="$1"
+shift
+rm -f -- "$@"
+find "$root" -mindepth 1 -depth -type d -empty -delete
+"#;
+
+/// A file waiting in a recycle bin
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecycledFile {
+    /// Path inside the bin folder
+    pub path: String,
+    pub size: u64,
+    /// When the file was recycled, as a Unix timestamp
+    pub deleted: i64,
+}
+
+/// Where the recycle module of a share moves deleted files
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecycleBin {
+    /// Folder holding the bin, or the per-user bins
+    pub root: PathBuf,
+    /// Leading components of a file's path naming its bin, for repositories
+    /// with variables such as `.recycle/%U`
+    bin_levels: usize,
+}
+
+impl RecycleBin {
+    /// Bin of a share, following `recycle:repository`. The share's name and path
+    /// are filled in; other variables like `%U` make one bin per user, the folder
+    /// before them holds them all.
+    pub fn of(share: &SambaShareConfig) -> Self {
+        let repository = match share.recycle_repository.as_str() {
+            "" => RECYCLE_DIR.to_string(),
+            repository => repository.replace("%S", &share.name).replace("%P", &share.path),
+        };
+        let components: Vec<&str> = repository.split('/').filter(|part| !part.is_empty()).collect();
+        let fixed = components
+            .iter()
+            .position(|part| part.contains('%'))
+            .unwrap_or(components.len());
+
+        let base = if repository.starts_with('/') {
+            PathBuf::from("/")
+        } else {
+            PathBuf::from(&share.path)
+        };
+        Self {
+            root: components[..fixed].iter().fold(base, |path, part| path.join(part)),
+            bin_levels: components.len() - fixed,
+        }
+    }
+
+    /// Files in the bin, most recently deleted first
+    pub fn list(&self) -> Result<Vec<RecycledFile>, String> {
+        let root = self.root.to_string_lossy();
+        let output = run_privileged("/bin/sh", &["-c", LIST_SCRIPT, "sh", &root], None)?;
+        let mut files = parse_listing(&output);
+        files.sort_by_key(|file| std::cmp::Reverse(file.deleted));
+        Ok(files)
+    }
+
+    /// Where a recycled file was before it was deleted, None for paths leaving the bin
+    pub fn original_path(&self, share_path: &str, file: &RecycledFile) -> Option<PathBuf> {
+        let relative = Path::new(&file.path);
+        if relative.components().any(|part| !matches!(part, Component::Normal(_))) {
+            return None;
+        }
+        let inside: PathBuf = relative.components().skip(self.bin_levels).collect();
+        (inside.components().count() > 0).then(|| Path::new(share_path).join(inside))
+    }
+
+    /// Move files back where they were deleted from
+    pub fn restore(&self, share_path: &str, files: &[RecycledFile]) -> Result<(), String> {
+        let mut args = vec!["-c".to_string(), RESTORE_SCRIPT.to_string(), "sh".to_string()];
+        for file in files {
+            let destination = self
+                .original_path(share_path, file)
+                .ok_or_else(|| format!("'{}' can't be restored", file.path))?;
+            args.push(self.root.join(&file.path).to_string_lossy().to_string());
+            args.push(destination.to_string_lossy().to_string());
+        }
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        run_privileged("/bin/sh", &args, None).map(|_| ())
+    }
+
+    /// Delete files from the bin for good
+    pub fn purge(&self, files: &[RecycledFile]) -> Result<(), String> {
+        let mut args = vec![
+            "-c".to_string(),
+            PURGE_SCRIPT.to_string(),
+            "sh".to_string(),
+            self.root.to_string_lossy().to_string(),
+        ];
+        for file in files {
+            let relative = Path::new(&file.path);
+            if relative.components().any(|part| !matches!(part, Component::Normal(_))) {
+                return Err(format!("'{}' is outside the recycle bin", file.path));
+            }
+            args.push(self.root.join(relative).to_string_lossy().to_string());
+        }
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        run_privileged("/bin/sh", &args, None).map(|_| ())
+    }
+}
+
+/// Read the NUL separated `size<TAB>mtime<TAB>path` records of the list script
+fn parse_listing(output: &str) -> Vec<RecycledFile> {
+    output
+        .split('\0')
+        .filter_map(|record| {
+            let mut fields = record.trim_start_matches('\n').splitn(3, '\t');
+            let size = fields.next()?.parse().ok()?;
+            // find prints the time with a fractional part
+            let deleted = fields.next()?.split('.').next()?.parse().ok()?;
+            let path = fields.next().filter(|path| !path.is_empty())?;
+            Some(RecycledFile {
+                path: path.to_string(),
+                size,
+                deleted,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn share(repository: &str) -> SambaShareConfig {
+        let mut share = SambaShareConfig::new(
+            "docs".to_string(),
+            "/srv/docs".to_string(),
+            true,
+            false,
+            false,
+            String::new(),
+            String::new(),
+        );
+        share.recycle_repository = repository.to_string();
+        share
+    }
+
+    #[test]
+    fn test_bin_of_share() {
+        assert_eq!(RecycleBin::of(&share("")).root, PathBuf::from("/srv/docs/.recycle"));
+
+        let per_user = RecycleBin::of(&share(".recycle/%U"));
+        assert_eq!(per_user.root, PathBuf::from("/srv/docs/.recycle"));
+        assert_eq!(per_user.bin_levels, 1);
+
+        let absolute = RecycleBin::of(&share("/var/recycle/%S/%U/%m"));
+        assert_eq!(absolute.root, PathBuf::from("/var/recycle/docs"));
+        assert_eq!(absolute.bin_levels, 2);
+    }
+
+    #[test]
+    fn test_parse_listing() {
+        let output = "1024\t1700000000.5\treport.pdf\0\
+                      20\t1700000100.0\tphotos/cat 1.jpg\0";
+        assert_eq!(
+            parse_listing(output),
+            vec![
+                RecycledFile {
+                    path: "report.pdf".to_string(),
+                    size: 1024,
+                    deleted: 1700000000,
+                },
+                RecycledFile {
+                    path: "photos/cat 1.jpg".to_string(),
+                    size: 20,
+                    deleted: 1700000100,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_original_path() {
+        let file = |path: &str| RecycledFile {
+            path: path.to_string(),
+            size: 0,
+            deleted: 0,
+        };
+        let bin = RecycleBin::of(&share(".recycle/%U"));
+        assert_eq!(
+            bin.original_path("/srv/docs", &file("alice/photos/cat.jpg")),
+            Some(PathBuf::from("/srv/docs/photos/cat.jpg"))
+        );
+        assert_eq!(bin.original_path("/srv/docs", &file("alice")), None);
+        assert_eq!(bin.original_path("/srv/docs", &file("alice/../../etc/passwd")), None);
+    }
+}
@@ -1,9 +1,52 @@
-use crate::samba::sudo_write::write_with_sudo;
-use rnix::{Root, SyntaxKind, SyntaxNode};
+use crate::samba::autofs;
+use crate::samba::config_change::ConfigChange;
+use crate::samba::fstab;
+use crate::samba::fstab_import::FSTAB;
+use crate::samba::idle_timeout;
+use crate::samba::mount_defaults::SystemdMount;
+use crate::samba::mount_option_set::MountOptionSet;
+use crate::samba::nix_imports::config_files;
+use crate::samba::nix_parse::{check_cancelled, parse_file};
+use crate::samba::paths::config_path;
+use crate::samba::sandbox::host_path;
+use crate::samba::share_backend::{backend, BackendKind};
+use crate::samba::share_config::unsupported_expression;
+use crate::samba::sudo_write::read_file;
+use rnix::{SyntaxKind, SyntaxNode};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
+use std::path::Path;
+
+/// How a remote share is declared, in the NixOS configuration or in /etc/fstab
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MountBackend {
+    /// `fileSystems` entry, mounted by a systemd automount unit
+    #[default]
+    FileSystems,
+    /// Line of the autofs direct map managed by the app
+    Autofs,
+    /// Line of /etc/fstab, mounted by systemd on systems without NixOS
+    Fstab,
+}
+
+impl MountBackend {
+    /// Backends of the selected share backend, in the order they are shown in the UI
+    pub fn available() -> Vec<MountBackend> {
+        match backend().kind() {
+            BackendKind::Nixos => vec![MountBackend::FileSystems, MountBackend::Autofs],
+            BackendKind::SmbConf => vec![MountBackend::Fstab],
+        }
+    }
+
+    /// Whether systemd mounts the share, with its own automount and idle timeout
+    pub fn uses_systemd(self) -> bool {
+        matches!(self, MountBackend::FileSystems | MountBackend::Fstab)
+    }
+}
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RemoteSambaShareConfig {
     pub name: String,
     pub remote_path: String,
@@ -11,12 +54,21 @@ pub struct RemoteSambaShareConfig {
     pub option_credentials: String,
     pub force_user: String,
     pub force_group: String,
+    /// Other mount options, such as `cache=` or `rsize=`, kept as they are
+    pub extra_options: Vec<String>,
+    /// Seconds without use before systemd unmounts the share, None to keep it mounted.
+    /// Only systemd mounts have one, the autofs map has a single timeout.
+    pub idle_timeout: Option<u32>,
+    /// Automount, noauto and timeouts of fileSystems and fstab entries
+    pub systemd: SystemdMount,
+    pub backend: MountBackend,
+    /// Configuration file the entry is defined in, the main file or one of its imports
+    pub source_file: String,
+    /// Why the entry can't be rewritten safely, None when it only uses plain values
+    pub locked_reason: Option<String>,
 }
 
 impl RemoteSambaShareConfig {
-    /// Path to the NixOS configuration file
-    const CONFIG_PATH: &'static str = "/etc/nixos/customConfig/default.nix";
-
     pub fn new(
         name: String,
         remote_path: String,
@@ -25,6 +77,11 @@ impl RemoteSambaShareConfig {
         force_user: String,
         force_group: String,
     ) -> Self {
+        let backend = MountBackend::available()[0];
+        let source_file = match backend {
+            MountBackend::Fstab => FSTAB,
+            _ => config_path(),
+        };
         Self {
             name,
             remote_path,
@@ -32,171 +89,332 @@ impl RemoteSambaShareConfig {
             option_credentials,
             force_user,
             force_group,
+            extra_options: Vec::new(),
+            idle_timeout: Some(idle_timeout::DEFAULT),
+            systemd: SystemdMount::default(),
+            backend,
+            source_file: source_file.to_string(),
+            locked_reason: None,
         }
     }
 
-    /// Load all Samba shares from NixOS configuration using rnix parser
+    /// Load all remote shares from the NixOS configuration and the files it imports,
+    /// or from /etc/fstab outside NixOS
     pub fn load_all() -> Result<Vec<Self>, String> {
-        let content = fs::read_to_string(Self::CONFIG_PATH)
-            .map_err(|e| format!("Failed to read {}: {}", Self::CONFIG_PATH, e))?;
-
-        let parsed = Root::parse(&content);
-        let root = parsed.syntax();
+        if backend().kind() != BackendKind::Nixos {
+            return load_fstab();
+        }
 
         let mut shares = Vec::new();
 
-        // Search recursively for fileSystems."/mount/point" entries
-        find_filesystem_entries(&root, &mut shares);
+        for file in config_files(config_path()) {
+            check_cancelled()?;
+            let source_file = file.to_string_lossy().to_string();
+            let content = match fs::read_to_string(host_path(&file)) {
+                Ok(content) => content,
+                Err(e) if source_file == config_path() => {
+                    return Err(format!("Failed to read {}: {}", source_file, e));
+                }
+                Err(e) => {
+                    eprintln!("Skipping imported file {}: {}", source_file, e);
+                    continue;
+                }
+            };
+
+            let parsed = parse_file(&file, &content);
+            let root = parsed.syntax();
+
+            // Search recursively for fileSystems."/mount/point" entries
+            let mut file_shares = Vec::new();
+            find_filesystem_entries(&root, &mut file_shares);
+            file_shares.extend(autofs_entries(&content));
+            for mut share in file_shares {
+                share.source_file = source_file.clone();
+                shares.push(share);
+            }
+        }
 
         Ok(shares)
     }
 
-    /// Write a new remote filesystem configuration to NixOS
-    pub fn write(&self) -> Result<(), String> {
-        let mut content = fs::read_to_string(Self::CONFIG_PATH)
-            .map_err(|e| format!("Failed to read {}: {}", Self::CONFIG_PATH, e))?;
-
-        // Build the options list
-        let mut options = Vec::new();
-        if !self.option_credentials.is_empty() {
-            options.push(format!("\"credentials={}\"", self.option_credentials));
+    /// Write a new remote filesystem configuration to NixOS.
+    /// Returns the applied change so it can be tracked until the next rebuild.
+    pub fn write(&self) -> Result<ConfigChange, String> {
+        // A mount point may already be defined in an imported file
+        if Self::load_all()?.iter().any(|share| share.name == self.name) {
+            return Err(format!("Mount point '{}' is already configured", self.name));
         }
-        options.push("\"x-systemd.automount\"".to_string());
-        options.push("\"noauto\"".to_string());
-        options.push("\"x-systemd.idle-timeout=300\"".to_string());
-        options.push("\"x-systemd.device-timeout=10s\"".to_string());
-        options.push("\"x-systemd.mount-timeout=10s\"".to_string());
-        if !self.force_user.is_empty() {
-            options.push(format!("\"uid={}\"", self.force_user));
-        }
-        if !self.force_group.is_empty() {
-            options.push(format!("\"gid={}\"", self.force_group));
+
+        match self.backend {
+            MountBackend::Autofs => return self.write_autofs(),
+            MountBackend::Fstab => return self.write_fstab(),
+            MountBackend::FileSystems => {}
         }
 
-        // Build the new entry
-        let new_entry = format!(
-            r#"fileSystems."{}" = {{
-  device = "{}";
-  fsType = "{}";
-  options = [
-    {}
-  ];
-}};
+        let original = read_file(config_path())?;
+        let content = self.add_to(&original)?;
 
-"#,
-            self.name,
-            self.remote_path,
-            self.fs_type,
-            options.join("\n    ")
-        );
+        // Write back to file with sudo
+        let change = ConfigChange::new(config_path().to_string(), original, content);
+        change.apply()?;
 
-        // Find where to insert (before the closing brace of the module)
-        // Look for the last closing brace
-        if let Some(last_brace_pos) = content.rfind('}') {
-            content.insert_str(last_brace_pos, &new_entry);
-        } else {
-            return Err("Could not find insertion point in config file".to_string());
+        Ok(change)
+    }
+
+    /// Update an existing remote filesystem configuration.
+    /// Returns the applied change so it can be tracked until the next rebuild.
+    pub fn update(&self, old_name: &str) -> Result<ConfigChange, String> {
+        self.ensure_editable(old_name)?;
+        let old_backend = self
+            .existing(old_name)?
+            .map(|share| share.backend)
+            .unwrap_or(self.backend);
+
+        // Name or backend changed - delete old and add new
+        if old_name != self.name || old_backend != self.backend {
+            let previous = Self {
+                backend: old_backend,
+                ..self.clone()
+            };
+            let removed = previous.delete(old_name)?;
+            let added = self.write()?;
+
+            // Both edits hit the same file unless the entry came from an import
+            if removed.path == added.path {
+                return Ok(ConfigChange::new(added.path, removed.before, added.after));
+            }
+            return Ok(added);
         }
 
+        let original = read_file(&self.source_file)?;
+        let content = self.replace_in(&original, old_name)?;
+
         // Write back to file with sudo
-        write_with_sudo(Self::CONFIG_PATH, &content)?;
+        let change = ConfigChange::new(self.source_file.clone(), original, content);
+        change.apply()?;
 
-        Ok(())
+        Ok(change)
     }
 
-    /// Update an existing remote filesystem configuration
-    pub fn update(&self, old_name: &str) -> Result<(), String> {
-        let mut content = fs::read_to_string(Self::CONFIG_PATH)
-            .map_err(|e| format!("Failed to read {}: {}", Self::CONFIG_PATH, e))?;
+    /// Delete a remote filesystem configuration.
+    /// Returns the applied change so it can be reverted.
+    pub fn delete(&self, name: &str) -> Result<ConfigChange, String> {
+        self.ensure_editable(name)?;
+
+        let content = read_file(&self.source_file)?;
 
-        // If name hasn't changed, update in place
-        // Otherwise, delete old entry and add new one
-        if old_name == self.name {
-            // Update in place using regex with multiline flag
-            // This pattern matches the entire fileSystems entry including nested braces
+        let new_content = if self.backend == MountBackend::Autofs {
+            autofs::set_entry(&content, name, None)?
+        } else if self.backend == MountBackend::Fstab {
+            fstab::set_entry(&content, name, None)?
+        } else {
+            // Delete the entry using regex with multiline flag
+            // (?s) enables dotall mode where . matches newlines
             let pattern = format!(
-                r#"(?s)fileSystems\."{}"\s*=\s*\{{.*?\}};"#,
-                regex::escape(old_name)
+                r#"(?s)fileSystems\."{}"\s*=\s*\{{.*?\}};[\n\r]*"#,
+                regex::escape(name)
             );
 
             let re = regex::Regex::new(&pattern)
                 .map_err(|e| format!("Failed to create regex: {}", e))?;
 
             if !re.is_match(&content) {
-                return Err(format!("Could not find filesystem entry for '{}'", old_name));
+                return Err(format!("Could not find filesystem entry for '{}'", name));
             }
 
-            // Build the options list
-            let mut options = Vec::new();
-            if !self.option_credentials.is_empty() {
-                options.push(format!("\"credentials={}\"", self.option_credentials));
-            }
-            options.push("\"x-systemd.automount\"".to_string());
-            options.push("\"noauto\"".to_string());
-            options.push("\"x-systemd.idle-timeout=300\"".to_string());
-            options.push("\"x-systemd.device-timeout=10s\"".to_string());
-            options.push("\"x-systemd.mount-timeout=10s\"".to_string());
-            if !self.force_user.is_empty() {
-                options.push(format!("\"uid={}\"", self.force_user));
-            }
-            if !self.force_group.is_empty() {
-                options.push(format!("\"gid={}\"", self.force_group));
-            }
+            re.replace(&content, "").to_string()
+        };
+
+        // Write back to file with sudo
+        let change = ConfigChange::new(self.source_file.clone(), content, new_content);
+        change.apply()?;
 
-            // Build the replacement entry
-            let replacement = format!(
-                r#"fileSystems."{}" = {{
+        Ok(change)
+    }
+
+    /// `content` with the share's `fileSystems` entry added before the closing brace of the module,
+    /// or its line added to the fstab file
+    pub(crate) fn add_to(&self, content: &str) -> Result<String, String> {
+        if self.backend == MountBackend::Fstab {
+            return fstab::set_entry(content, &self.name, Some(&fstab::entry_line(self)));
+        }
+        let Some(last_brace_pos) = content.rfind('}') else {
+            return Err("Could not find insertion point in config file".to_string());
+        };
+        let mut content = content.to_string();
+        content.insert_str(last_brace_pos, &format!("{}\n\n", self.filesystem_entry()));
+        Ok(content)
+    }
+
+    /// `content` with the entry of `old_name` rewritten from this share, in the same backend
+    pub(crate) fn replace_in(&self, content: &str, old_name: &str) -> Result<String, String> {
+        match self.backend {
+            MountBackend::Autofs => return autofs::set_entry(content, old_name, Some(&self.autofs_line())),
+            MountBackend::Fstab => return fstab::set_entry(content, old_name, Some(&fstab::entry_line(self))),
+            MountBackend::FileSystems => {}
+        }
+
+        // Update in place using regex with multiline flag
+        // This pattern matches the entire fileSystems entry including nested braces
+        let pattern = format!(
+            r#"(?s)fileSystems\."{}"\s*=\s*\{{.*?\}};"#,
+            regex::escape(old_name)
+        );
+
+        let re = regex::Regex::new(&pattern)
+            .map_err(|e| format!("Failed to create regex: {}", e))?;
+
+        if !re.is_match(content) {
+            return Err(format!("Could not find filesystem entry for '{}'", old_name));
+        }
+
+        let entry = self.filesystem_entry();
+        Ok(re.replace(content, regex::NoExpand(&entry)).to_string())
+    }
+
+    /// `fileSystems` entry of the share, as written to the configuration
+    fn filesystem_entry(&self) -> String {
+        format!(
+            r#"fileSystems."{}" = {{
   device = "{}";
   fsType = "{}";
   options = [
     {}
   ];
 }};"#,
-                self.name,
-                self.remote_path,
-                self.fs_type,
-                options.join("\n    ")
-            );
+            self.name,
+            self.remote_path,
+            self.fs_type,
+            self.nix_options().join("\n    ")
+        )
+    }
 
-            content = re.replace(&content, replacement.as_str()).to_string();
-        } else {
-            // Name changed - delete old and add new
-            self.delete(old_name)?;
-            return self.write();
-        }
+    /// Add the share to the autofs map, in the file that already holds it
+    /// or in the main configuration file
+    fn write_autofs(&self) -> Result<ConfigChange, String> {
+        let path = config_files(config_path())
+            .into_iter()
+            .map(|file| file.to_string_lossy().to_string())
+            .find(|file| read_file(file).is_ok_and(|content| autofs::has_map(&content)))
+            .unwrap_or_else(|| config_path().to_string());
 
-        // Write back to file with sudo
-        write_with_sudo(Self::CONFIG_PATH, &content)?;
+        let original = read_file(&path)?;
+        let content = autofs::set_entry(&original, &self.name, Some(&self.autofs_line()))?;
+
+        let change = ConfigChange::new(path, original, content);
+        change.apply()?;
 
-        Ok(())
+        Ok(change)
     }
 
-    /// Delete a remote filesystem configuration
-    fn delete(&self, name: &str) -> Result<(), String> {
-        let mut content = fs::read_to_string(Self::CONFIG_PATH)
-            .map_err(|e| format!("Failed to read {}: {}", Self::CONFIG_PATH, e))?;
+    /// Add the share's line to /etc/fstab
+    fn write_fstab(&self) -> Result<ConfigChange, String> {
+        let original = if host_path(Path::new(FSTAB)).exists() {
+            read_file(FSTAB)?
+        } else {
+            String::new()
+        };
+        let content = self.add_to(&original)?;
 
-        // Delete the entry using regex with multiline flag
-        // (?s) enables dotall mode where . matches newlines
-        let pattern = format!(
-            r#"(?s)fileSystems\."{}"\s*=\s*\{{.*?\}};[\n\r]*"#,
-            regex::escape(name)
-        );
+        let change = ConfigChange::new(FSTAB.to_string(), original, content);
+        change.apply()?;
 
-        let re = regex::Regex::new(&pattern)
-            .map_err(|e| format!("Failed to create regex: {}", e))?;
+        Ok(change)
+    }
 
-        if !re.is_match(&content) {
-            return Err(format!("Could not find filesystem entry for '{}'", name));
+    /// Mount options the entry is written with, empty fields left out
+    pub fn mount_options(&self) -> MountOptionSet {
+        let value = |field: &String| Some(field.clone()).filter(|value| !value.is_empty());
+        MountOptionSet {
+            credentials: value(&self.option_credentials),
+            uid: value(&self.force_user),
+            gid: value(&self.force_group),
+            systemd: self.systemd,
+            idle_timeout: self.idle_timeout,
+            extras: self.extra_options.clone(),
         }
+    }
 
-        content = re.replace(&content, "").to_string();
+    /// Line of the autofs map: the credentials and ids, then the other options.
+    /// Unmounting when idle is set for the whole map.
+    fn autofs_line(&self) -> String {
+        let mount_options = MountOptionSet {
+            systemd: SystemdMount::NONE,
+            idle_timeout: None,
+            ..self.mount_options()
+        };
+        let mut options = vec![format!("fstype={}", self.fs_type)];
+        options.extend(mount_options.to_options());
+
+        autofs::map_line(&self.name, &options, &self.remote_path)
+    }
 
-        // Write back to file with sudo
-        write_with_sudo(Self::CONFIG_PATH, &content)?;
+    /// Mount options as quoted Nix strings
+    fn nix_options(&self) -> Vec<String> {
+        self.mount_options()
+            .to_options()
+            .iter()
+            .map(|option| format!("\"{}\"", option))
+            .collect()
+    }
+
+    /// Refuse to rewrite an entry defined with expressions the parser can't reproduce
+    fn ensure_editable(&self, name: &str) -> Result<(), String> {
+        let locked = self.existing(name)?.and_then(|share| share.locked_reason);
 
-        Ok(())
+        match locked {
+            Some(reason) => Err(format!("'{}' can't be edited here: {}", name, reason)),
+            None => Ok(()),
+        }
+    }
+
+    /// Entry named `name` in this share's configuration file, as it is on disk
+    fn existing(&self, name: &str) -> Result<Option<Self>, String> {
+        Ok(Self::load_all()?
+            .into_iter()
+            .find(|share| share.name == name && share.source_file == self.source_file))
+    }
+}
+
+/// Shares of the cifs lines of /etc/fstab, none when the file is missing
+fn load_fstab() -> Result<Vec<RemoteSambaShareConfig>, String> {
+    if !host_path(Path::new(FSTAB)).exists() {
+        return Ok(Vec::new());
     }
+    Ok(fstab::parse_shares(&read_file(FSTAB)?, FSTAB))
+}
+
+/// Shares of the autofs map in a configuration file
+fn autofs_entries(content: &str) -> Vec<RemoteSambaShareConfig> {
+    let (entries, locked_reason) = autofs::parse_map(content);
+    entries
+        .into_iter()
+        .map(|entry| {
+            let mut options = MountOptionSet::parse(&entry.options);
+            let fs_type = options
+                .extras
+                .iter()
+                .find_map(|option| option.strip_prefix("fstype="))
+                .unwrap_or_default()
+                .to_string();
+            options.extras.retain(|option| !option.starts_with("fstype="));
+
+            RemoteSambaShareConfig {
+                name: entry.mount_point.clone(),
+                remote_path: entry.remote_path.clone(),
+                fs_type,
+                option_credentials: options.credentials.unwrap_or_default(),
+                force_user: options.uid.unwrap_or_default(),
+                force_group: options.gid.unwrap_or_default(),
+                extra_options: options.extras,
+                idle_timeout: None,
+                systemd: SystemdMount::NONE,
+                backend: MountBackend::Autofs,
+                source_file: String::new(),
+                locked_reason: locked_reason.clone(),
+            }
+        })
+        .collect()
 }
 
 /// Recursively find all fileSystems entries in the AST
@@ -226,6 +444,29 @@ fn find_filesystem_entries(node: &SyntaxNode, shares: &mut Vec<RemoteSambaShareC
 
                 // If this is a fileSystems entry, parse its value
                 if is_filesystems && !mount_point.is_empty() {
+                    let locked_reason = unsupported_expression(node);
+
+                    // Computed entries are only listed when they visibly mount a CIFS share
+                    let has_attrset = node
+                        .children()
+                        .any(|value_child| value_child.kind() == SyntaxKind::NODE_ATTR_SET);
+                    if !has_attrset && node.text().to_string().contains("cifs") {
+                        shares.push(RemoteSambaShareConfig {
+                            name: mount_point.clone(),
+                            remote_path: String::new(),
+                            fs_type: "cifs".to_string(),
+                            option_credentials: String::new(),
+                            force_user: String::new(),
+                            force_group: String::new(),
+                            extra_options: Vec::new(),
+                            idle_timeout: None,
+                            systemd: SystemdMount::NONE,
+                            backend: MountBackend::FileSystems,
+                            source_file: String::new(),
+                            locked_reason: locked_reason.clone(),
+                        });
+                    }
+
                     // Find the attr set value
                     for value_child in node.children() {
                         if value_child.kind() == SyntaxKind::NODE_ATTR_SET {
@@ -253,37 +494,30 @@ fn find_filesystem_entries(node: &SyntaxNode, shares: &mut Vec<RemoteSambaShareC
                                 }
                             }
 
-                            // Only process CIFS/SMB shares
-                            if fs_type == "cifs" {
-                                // Extract credentials from options
-                                let credentials = options_list
-                                    .iter()
-                                    .find(|opt| opt.starts_with("credentials="))
-                                    .map(|opt| {
-                                        opt.strip_prefix("credentials=").unwrap_or("").to_string()
-                                    })
-                                    .unwrap_or_default();
-
-                                // Extract uid and gid from options
-                                let uid = options_list
-                                    .iter()
-                                    .find(|opt| opt.starts_with("uid="))
-                                    .and_then(|opt| opt.strip_prefix("uid="))
-                                    .unwrap_or("1000");
-
-                                let gid = options_list
-                                    .iter()
-                                    .find(|opt| opt.starts_with("gid="))
-                                    .and_then(|opt| opt.strip_prefix("gid="))
-                                    .unwrap_or("100");
+                            // Only process CIFS/SMB shares, including ones with a computed fsType
+                            let computed_cifs = fs_type.is_empty()
+                                && value_child.text().to_string().contains("cifs");
+                            if fs_type == "cifs" || computed_cifs {
+                                // Everything the fields don't cover is kept as is
+                                let options = MountOptionSet::parse(&options_list);
 
                                 shares.push(RemoteSambaShareConfig {
                                     name: mount_point.clone(),
                                     remote_path: device,
-                                    fs_type,
-                                    option_credentials: credentials,
-                                    force_user: uid.to_string(),
-                                    force_group: gid.to_string(),
+                                    fs_type: if computed_cifs {
+                                        "cifs".to_string()
+                                    } else {
+                                        fs_type
+                                    },
+                                    option_credentials: options.credentials.unwrap_or_default(),
+                                    force_user: options.uid.unwrap_or_else(|| "1000".to_string()),
+                                    force_group: options.gid.unwrap_or_else(|| "100".to_string()),
+                                    extra_options: options.extras,
+                                    idle_timeout: options.idle_timeout,
+                                    systemd: options.systemd,
+                                    backend: MountBackend::FileSystems,
+                                    source_file: String::new(),
+                                    locked_reason: locked_reason.clone(),
                                 });
                             }
                         }
@@ -0,0 +1,188 @@
+use crate::samba::access::{share_access, Accounts};
+use crate::samba::diagnostics::sanitize;
+use crate::samba::mount_operations::{list_all_shares, MountedShare};
+use crate::samba::share_config::SambaShareConfig;
+
+/// Style embedded in the report, so the file stands alone and prints cleanly
+const STYLE: &str = "body { font-family: sans-serif; margin: 2em; color: #222; }
+h1 { font-size: 1.6em; }
+h2 { font-size: 1.3em; margin-top: 2em; border-bottom: 1px solid #ccc; }
+table { border-collapse: collapse; width: 100%; margin-bottom: 1em; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.6em; text-align: left; vertical-align: top; }
+th { background: #f0f0f0; }
+code { font-size: 0.9em; word-break: break-all; }
+.muted { color: #777; }
+@media print { body { margin: 0; } h2 { break-after: avoid; } tr { break-inside: avoid; } }";
+
+/// Build an HTML summary of the local shares and remote mounts of this machine
+pub fn build_html_report() -> String {
+    let accounts = Accounts::load();
+    html_report(&SambaShareConfig::load_all(), &list_all_shares(), &accounts)
+}
+
+/// HTML summary of `local` shares, with who can reach them, and `remote` mounts.
+/// Credentials in mount options are replaced by `***`.
+pub fn html_report(
+    local: &Result<Vec<SambaShareConfig>, String>,
+    remote: &Result<Vec<MountedShare>, String>,
+    accounts: &Accounts,
+) -> String {
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str("<title>Samba shares report</title>\n");
+    html.push_str(&format!("<style>\n{}\n</style>\n</head>\n<body>\n", STYLE));
+    html.push_str("<h1>Samba shares report</h1>\n");
+    html.push_str(&format!(
+        "<p class=\"muted\">Generated by samba-share {}</p>\n",
+        env!("CARGO_PKG_VERSION")
+    ));
+
+    html.push_str("<h2>Local shares</h2>\n");
+    match local {
+        Ok(shares) if shares.is_empty() => html.push_str("<p class=\"muted\">No local shares.</p>\n"),
+        Ok(shares) => {
+            for share in shares {
+                html.push_str(&local_share(share, accounts));
+            }
+        }
+        Err(e) => html.push_str(&format!("<p>Failed to load local shares: {}</p>\n", escape(e))),
+    }
+
+    html.push_str("<h2>Remote shares</h2>\n");
+    match remote {
+        Ok(shares) if shares.is_empty() => html.push_str("<p class=\"muted\">No remote shares.</p>\n"),
+        Ok(shares) => {
+            html.push_str("<table>\n<tr><th>Server</th><th>Remote path</th><th>Mount point</th>");
+            html.push_str("<th>Status</th><th>Options</th></tr>\n");
+            for share in shares {
+                let status = match (share.is_mounted, share.config.is_some()) {
+                    (true, _) => "Mounted",
+                    (false, true) => "Not mounted",
+                    (false, false) => "Not in the configuration",
+                };
+                html.push_str(&format!(
+                    "<tr><td>{}</td><td><code>{}</code></td><td><code>{}</code></td><td>{}</td><td><code>{}</code></td></tr>\n",
+                    escape(share.server()),
+                    escape(&share.source),
+                    escape(&share.target),
+                    status,
+                    escape(&sanitize(&share.options.to_string()))
+                ));
+            }
+            html.push_str("</table>\n");
+        }
+        Err(e) => html.push_str(&format!("<p>Failed to load remote shares: {}</p>\n", escape(e))),
+    }
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+/// Table describing one local share and the users able to reach it
+fn local_share(share: &SambaShareConfig, accounts: &Accounts) -> String {
+    let access = share_access(share, accounts);
+
+    let mut rows = vec![
+        ("Path", format!("<code>{}</code>", escape(&share.path))),
+        ("Browsable", yes_no(share.browsable).to_string()),
+        (
+            "Default access",
+            if access.restricted {
+                "Listed users only".to_string()
+            } else if access.writable_by_default {
+                "Read and write".to_string()
+            } else {
+                "Read only".to_string()
+            },
+        ),
+    ];
+
+    if !access.users.is_empty() {
+        let users: Vec<String> = access
+            .users
+            .iter()
+            .map(|user| {
+                let mode = if user.writable { "read and write" } else { "read only" };
+                format!("{} ({})", escape(&user.name), mode)
+            })
+            .collect();
+        rows.push(("Users", users.join("<br>")));
+    }
+
+    rows.push((
+        "Guests",
+        match &access.guest {
+            None => "Not allowed".to_string(),
+            Some(account) if account.is_empty() => "Allowed".to_string(),
+            Some(account) => format!("Allowed, as {}", escape(account)),
+        },
+    ));
+    if let Some(value) = share.smb_encrypt.encrypt_value() {
+        rows.push(("Encryption", value.to_string()));
+    }
+    if !share.force_user.is_empty() {
+        rows.push(("Force user", escape(&share.force_user)));
+    }
+    if !share.force_group.is_empty() {
+        rows.push(("Force group", escape(&share.force_group)));
+    }
+    rows.push(("Configuration file", format!("<code>{}</code>", escape(&share.source_file))));
+
+    let mut html = format!("<h3>{}</h3>\n<table>\n", escape(&share.name));
+    for (name, value) in rows {
+        html.push_str(&format!("<tr><th>{}</th><td>{}</td></tr>\n", name, value));
+    }
+    html.push_str("</table>\n");
+    html
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value {
+        "Yes"
+    } else {
+        "No"
+    }
+}
+
+/// Escape text for HTML content and attribute values
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::samba::mount_option_set::MountOptionSet;
+
+    #[test]
+    fn test_html_report() {
+        let accounts = Accounts::parse("alice:x:1000:100::/home/alice:/bin/sh\n", "users:x:100:\n");
+        let mut share = SambaShareConfig::new(
+            "docs <old>".to_string(),
+            "/srv/docs".to_string(),
+            true,
+            false,
+            false,
+            String::new(),
+            String::new(),
+        );
+        share.valid_users = vec!["alice".to_string()];
+        let mount = MountedShare {
+            source: "//nas/media".to_string(),
+            target: "/media/nas".to_string(),
+            fstype: "cifs".to_string(),
+            options: MountOptionSet::from_list("rw,credentials=/etc/nas-secrets,uid=1000"),
+            is_mounted: true,
+            config: None,
+        };
+
+        let html = html_report(&Ok(vec![share]), &Ok(vec![mount]), &accounts);
+        assert!(html.contains("<h3>docs &lt;old&gt;</h3>"));
+        assert!(html.contains("alice (read and write)"));
+        assert!(html.contains("//nas/media"));
+        assert!(!html.contains("/etc/nas-secrets"));
+    }
+}
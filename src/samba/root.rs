@@ -0,0 +1,60 @@
+use crate::samba::demo;
+use std::env;
+use std::path::PathBuf;
+use users::os::unix::UserExt;
+use users::{get_current_gid, get_current_uid, get_user_by_name, get_user_by_uid};
+
+/// User who started the app through sudo or pkexec
+#[derive(Debug, Clone)]
+pub struct InvokingUser {
+    pub name: String,
+    pub uid: u32,
+    pub gid: u32,
+    pub home: PathBuf,
+}
+
+/// Whether the app itself runs as root
+pub fn is_root() -> bool {
+    get_current_uid() == 0
+}
+
+/// Whether privileged commands can run directly instead of through pkexec, run0 or sudo.
+/// Not in demo mode, where only the launchers are stubbed.
+pub fn skip_escalation() -> bool {
+    is_root() && !demo::is_enabled()
+}
+
+/// The user behind sudo (`SUDO_USER`) or pkexec (`PKEXEC_UID`) when running as root
+pub fn invoking_user() -> Option<InvokingUser> {
+    if !is_root() {
+        return None;
+    }
+
+    let user = if let Ok(name) = env::var("SUDO_USER") {
+        get_user_by_name(&name)
+    } else {
+        env::var("PKEXEC_UID")
+            .ok()
+            .and_then(|uid| uid.parse().ok())
+            .and_then(get_user_by_uid)
+    }?;
+    if user.uid() == 0 {
+        return None;
+    }
+
+    Some(InvokingUser {
+        name: user.name().to_string_lossy().to_string(),
+        uid: user.uid(),
+        gid: user.primary_group_id(),
+        home: user.home_dir().to_path_buf(),
+    })
+}
+
+/// Uid and gid of the desktop user, the invoking one when running as root,
+/// so mounted files don't end up owned by root
+pub fn desktop_user_ids() -> (u32, u32) {
+    match invoking_user() {
+        Some(user) => (user.uid, user.gid),
+        None => (get_current_uid(), get_current_gid()),
+    }
+}
@@ -0,0 +1,37 @@
+use std::env;
+use std::path::{Path, PathBuf};
+
+/// File present in every Flatpak sandbox
+const FLATPAK_INFO: &str = "/.flatpak-info";
+
+/// Where the host's /etc is visible with the `host-etc` filesystem permission
+const HOST_ETC: &str = "/run/host/etc";
+
+/// Whether the app runs inside a Flatpak sandbox
+pub fn is_sandboxed() -> bool {
+    Path::new(FLATPAK_INFO).exists()
+}
+
+/// Path to read a host file from this process.
+/// Inside the sandbox the host's /etc is only reachable under /run/host/etc;
+/// paths passed to host commands must stay unmapped.
+pub fn host_path(path: &Path) -> PathBuf {
+    if !is_sandboxed() {
+        return path.to_path_buf();
+    }
+    match path.strip_prefix("/etc") {
+        Ok(rest) => Path::new(HOST_ETC).join(rest),
+        Err(_) => path.to_path_buf(),
+    }
+}
+
+/// Directory for temporary files that host commands must read.
+/// The sandbox has a private /tmp, but shares `$XDG_RUNTIME_DIR/app/$FLATPAK_ID` with the host.
+pub fn shared_temp_dir() -> PathBuf {
+    if is_sandboxed() {
+        if let (Ok(runtime_dir), Ok(app_id)) = (env::var("XDG_RUNTIME_DIR"), env::var("FLATPAK_ID")) {
+            return PathBuf::from(runtime_dir).join("app").join(app_id);
+        }
+    }
+    env::temp_dir()
+}
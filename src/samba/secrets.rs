@@ -0,0 +1,118 @@
+use crate::samba::command_runner::command;
+use crate::samba::sudo_write::run_with_input;
+use std::process::Command;
+
+/// Attribute marking the passwords the app saved in the keyring
+const APPLICATION: &str = "samba-share";
+
+/// Username and password saved in the keyring for a server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredCredentials {
+    pub username: String,
+    pub password: String,
+}
+
+/// Credentials saved for `server`, None when there are none or the keyring can't be reached.
+/// Unlocking the keyring may show a password prompt of the desktop.
+pub fn lookup(server: &str) -> Option<StoredCredentials> {
+    let output = command("secret-tool")
+        .args(["search", "--unlock", "application", APPLICATION, "server", &server_key(server)])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    // Depending on the version the attributes are printed on stderr, the secret on stdout
+    let text = format!(
+        "{}\n{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    parse_search(&text)
+}
+
+/// Save the credentials of `server` in the keyring of the session, through the
+/// Secret Service of GNOME Keyring or KWallet, replacing the ones saved before.
+/// The password only goes through stdin, never on a command line.
+pub fn store(server: &str, username: &str, password: &str) -> Result<(), String> {
+    forget(server)?;
+
+    let mut store = command("secret-tool");
+    store.args([
+        "store",
+        "--label",
+        &format!("Samba password for {}", server),
+        "application",
+        APPLICATION,
+        "server",
+        &server_key(server),
+        "user",
+        username,
+    ]);
+    run(store, Some(password)).map_err(|e| format!("Failed to save the password: {}", e))
+}
+
+/// Remove the credentials saved for `server`, if any
+pub fn forget(server: &str) -> Result<(), String> {
+    let mut clear = command("secret-tool");
+    clear.args(["clear", "application", APPLICATION, "server", &server_key(server)]);
+    run(clear, None).map_err(|e| format!("Failed to remove the saved password: {}", e))
+}
+
+/// Run secret-tool, the error being its message
+fn run(secret_tool: Command, input: Option<&str>) -> Result<(), String> {
+    let output = run_with_input(secret_tool, input)
+        .map_err(|e| format!("secret-tool is not available ({}), install libsecret", e))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+/// Host names are saved in lower case, "NAS" and "nas" being the same server
+fn server_key(server: &str) -> String {
+    server.trim().to_lowercase()
+}
+
+/// First item of `secret-tool search`: `secret = ...` and `attribute.user = ...` lines
+fn parse_search(output: &str) -> Option<StoredCredentials> {
+    let value = |key: &str| {
+        output
+            .lines()
+            .find_map(|line| line.strip_prefix(key)?.trim_start().strip_prefix('='))
+            .map(|value| value.strip_prefix(' ').unwrap_or(value).to_string())
+    };
+    Some(StoredCredentials {
+        username: value("attribute.user").filter(|username| !username.is_empty())?,
+        password: value("secret")?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_search() {
+        let output = "[/org/freedesktop/secrets/collection/login/4]
+label = Samba password for nas.local
+secret = pass word=1
+created = 2026-03-02 10:12:44
+modified = 2026-03-02 10:12:44
+schema = org.freedesktop.Secret.Generic
+attribute.application = samba-share
+attribute.server = nas.local
+attribute.user = alice
+";
+        assert_eq!(
+            parse_search(output),
+            Some(StoredCredentials {
+                username: "alice".to_string(),
+                password: "pass word=1".to_string(),
+            })
+        );
+        assert_eq!(parse_search(""), None);
+        assert_eq!(server_key(" NAS.local"), "nas.local");
+    }
+}
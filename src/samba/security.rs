@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+
+/// Requirement level for the `smb encrypt` and `server signing` options
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SecurityLevel {
+    /// Option not written, Samba's built-in default applies
+    #[default]
+    Default,
+    Off,
+    Desired,
+    Required,
+}
+
+impl SecurityLevel {
+    /// All levels, in the order they are shown in the UI
+    pub const ALL: [SecurityLevel; 4] = [
+        SecurityLevel::Default,
+        SecurityLevel::Off,
+        SecurityLevel::Desired,
+        SecurityLevel::Required,
+    ];
+
+    /// Value written for `smb encrypt`, or None to leave the option out
+    pub fn encrypt_value(&self) -> Option<&'static str> {
+        match self {
+            SecurityLevel::Default => None,
+            SecurityLevel::Off => Some("off"),
+            SecurityLevel::Desired => Some("desired"),
+            SecurityLevel::Required => Some("required"),
+        }
+    }
+
+    /// Value written for `server signing`, or None to leave the option out
+    pub fn signing_value(&self) -> Option<&'static str> {
+        match self {
+            SecurityLevel::Default => None,
+            SecurityLevel::Off => Some("disabled"),
+            SecurityLevel::Desired => Some("auto"),
+            SecurityLevel::Required => Some("mandatory"),
+        }
+    }
+
+    /// Parse a value read from the configuration.
+    /// Accepts both the `smb encrypt` and `server signing` vocabularies, including synonyms.
+    pub fn parse(value: &str) -> Self {
+        match value.trim().to_lowercase().as_str() {
+            "off" | "no" | "false" | "disabled" => SecurityLevel::Off,
+            "desired" | "if_required" | "auto" | "yes" | "true" | "enabled" => {
+                SecurityLevel::Desired
+            }
+            "required" | "mandatory" | "forced" => SecurityLevel::Required,
+            _ => SecurityLevel::Default,
+        }
+    }
+}
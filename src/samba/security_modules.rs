@@ -0,0 +1,227 @@
+use crate::samba::command_runner::command;
+use crate::samba::share_config::SambaShareConfig;
+use std::fs;
+use std::path::Path;
+
+/// Last matching journal lines searched for denials
+const DENIAL_LINES: usize = 500;
+
+/// Journal pattern matching AppArmor and SELinux denials
+const DENIAL_PATTERN: &str = r#"apparmor="DENIED"|avc: +denied"#;
+
+/// Processes of the Samba server
+const SAMBA_PROCESSES: [&str; 5] = ["smbd", "nmbd", "samba", "rpcd_", "winbindd"];
+
+/// Linux security module able to refuse access on top of the file permissions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecurityModule {
+    SELinux,
+    AppArmor,
+}
+
+impl SecurityModule {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SecurityModule::SELinux => "SELinux",
+            SecurityModule::AppArmor => "AppArmor",
+        }
+    }
+}
+
+/// A security module loaded in the kernel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModuleStatus {
+    pub module: SecurityModule,
+    /// False when denials are only logged (SELinux permissive mode)
+    pub enforcing: bool,
+}
+
+/// Access refused to a Samba process by a security module
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Denial {
+    pub module: SecurityModule,
+    /// Process refused, such as smbd
+    pub process: String,
+    /// Operation or permissions refused, such as "open" or "write"
+    pub operation: String,
+    /// File or folder, only its name when SELinux doesn't log the full path
+    pub path: String,
+    /// AppArmor profile, or SELinux type of the file
+    pub context: String,
+}
+
+impl Denial {
+    /// Share whose folder holds the denied path, the most specific one first
+    pub fn share<'a>(&self, shares: &'a [SambaShareConfig]) -> Option<&'a SambaShareConfig> {
+        shares
+            .iter()
+            .filter(|share| !share.path.is_empty() && Path::new(&self.path).starts_with(&share.path))
+            .max_by_key(|share| share.path.len())
+    }
+
+    /// Likely fix, for `folder` the shared folder when known
+    pub fn suggestion(&self, folder: Option<&str>) -> String {
+        let folder = folder.map(str::to_string).unwrap_or_else(|| {
+            Path::new(&self.path)
+                .parent()
+                .filter(|parent| parent.is_absolute())
+                .map(|parent| parent.display().to_string())
+                .unwrap_or_else(|| "/path/to/share".to_string())
+        });
+
+        match self.module {
+            SecurityModule::AppArmor => format!(
+                "Allow the folder in the '{profile}' profile, e.g. a \"{folder}/** rwk,\" rule in security.apparmor.policies.\"{profile}\". \
+                 Setting its state to \"complain\" confirms AppArmor is the cause.",
+                profile = self.context,
+                folder = folder
+            ),
+            SecurityModule::SELinux => match self.context.as_str() {
+                "user_home_t" | "user_home_dir_t" => "setsebool -P samba_enable_home_dirs on".to_string(),
+                "nfs_t" => "setsebool -P samba_share_nfs on".to_string(),
+                "fusefs_t" => "setsebool -P samba_share_fusefs on".to_string(),
+                _ => format!(
+                    "semanage fcontext -a -t samba_share_t \"{folder}(/.*)?\" && restorecon -R \"{folder}\"",
+                    folder = folder
+                ),
+            },
+        }
+    }
+}
+
+/// Security modules enabled on this computer
+pub fn active_modules() -> Vec<ModuleStatus> {
+    let mut modules = Vec::new();
+    if let Ok(enforce) = fs::read_to_string("/sys/fs/selinux/enforce") {
+        modules.push(ModuleStatus {
+            module: SecurityModule::SELinux,
+            enforcing: enforce.trim() == "1",
+        });
+    }
+    if fs::read_to_string("/sys/module/apparmor/parameters/enabled").is_ok_and(|enabled| enabled.trim() == "Y") {
+        modules.push(ModuleStatus {
+            module: SecurityModule::AppArmor,
+            enforcing: true,
+        });
+    }
+    modules
+}
+
+/// Recent denials of Samba processes found in the journal, oldest first
+pub fn samba_denials() -> Result<Vec<Denial>, String> {
+    let lines = DENIAL_LINES.to_string();
+    let output = command("journalctl")
+        .args(["--no-pager", "-o", "cat", "-n", &lines, "-g", DENIAL_PATTERN])
+        .output()
+        .map_err(|e| format!("Failed to run journalctl: {}", e))?;
+
+    // journalctl fails when nothing matches
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() && !stderr.trim().is_empty() {
+        return Err(stderr.trim().to_string());
+    }
+    Ok(parse_denials(&stdout))
+}
+
+/// Denials of Samba processes, each distinct one once
+fn parse_denials(log: &str) -> Vec<Denial> {
+    let fields = regex::Regex::new(r#"([a-z_]+)=("[^"]*"|\S+)"#).expect("valid regex");
+    let permissions = regex::Regex::new(r"avc:\s+denied\s+\{\s*([^}]*?)\s*\}").expect("valid regex");
+
+    let mut denials: Vec<Denial> = Vec::new();
+    for denial in log.lines().filter_map(|line| parse_denial(line, &fields, &permissions)) {
+        let is_samba = SAMBA_PROCESSES.iter().any(|process| {
+            denial.process.starts_with(process)
+                || (denial.module == SecurityModule::AppArmor && denial.context.contains(process))
+        });
+        if is_samba && !denials.contains(&denial) {
+            denials.push(denial);
+        }
+    }
+    denials
+}
+
+/// Read an audit line such as
+/// `apparmor="DENIED" operation="open" profile="smbd" name="/srv/a" comm="smbd"` or
+/// `avc:  denied  { write } for comm="smbd" name="a" tcontext=system_u:object_r:var_t:s0`,
+/// with `fields` matching `key=value` pairs and `permissions` the braces of SELinux lines
+fn parse_denial(line: &str, fields: &regex::Regex, permissions: &regex::Regex) -> Option<Denial> {
+    let field = |key: &str| {
+        fields
+            .captures_iter(line)
+            .find(|captures| &captures[1] == key)
+            .map(|captures| captures[2].trim_matches('"').to_string())
+    };
+
+    if line.contains("apparmor=\"DENIED\"") {
+        return Some(Denial {
+            module: SecurityModule::AppArmor,
+            process: field("comm")?,
+            operation: field("operation").unwrap_or_default(),
+            path: field("name").unwrap_or_default(),
+            context: field("profile").unwrap_or_default(),
+        });
+    }
+
+    let operation = permissions.captures(line)?[1].to_string();
+    // tcontext is user:role:type:level
+    let context = field("tcontext")
+        .and_then(|tcontext| tcontext.split(':').nth(2).map(str::to_string))
+        .unwrap_or_default();
+    Some(Denial {
+        module: SecurityModule::SELinux,
+        process: field("comm")?,
+        operation,
+        path: field("path").or_else(|| field("name")).unwrap_or_default(),
+        context,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOG: &str = r#"audit: type=1400 audit(1700000000.123:45): apparmor="DENIED" operation="open" class="file" profile="samba-smbd" name="/srv/media/film.mkv" pid=1234 comm="smbd" requested_mask="r" denied_mask="r" fsuid=1000 ouid=1000
+audit: type=1400 audit(1700000001.123:46): apparmor="DENIED" operation="open" class="file" profile="samba-smbd" name="/srv/media/film.mkv" pid=1234 comm="smbd" requested_mask="r" denied_mask="r" fsuid=1000 ouid=1000
+audit: type=1400 audit(1700000002.123:47): apparmor="DENIED" operation="open" profile="firefox" name="/home/alice/.ssh/id_ed25519" pid=99 comm="firefox"
+type=AVC msg=audit(1700000003.123:48): avc:  denied  { write } for  pid=1234 comm="smbd" name="docs" dev="sda1" ino=42 scontext=system_u:system_r:smbd_t:s0 tcontext=unconfined_u:object_r:user_home_t:s0 tclass=dir permissive=0
+"#;
+
+    #[test]
+    fn test_parse_denials() {
+        let denials = parse_denials(LOG);
+        assert_eq!(denials.len(), 2);
+
+        assert_eq!(
+            denials[0],
+            Denial {
+                module: SecurityModule::AppArmor,
+                process: "smbd".to_string(),
+                operation: "open".to_string(),
+                path: "/srv/media/film.mkv".to_string(),
+                context: "samba-smbd".to_string(),
+            }
+        );
+        assert_eq!(denials[1].module, SecurityModule::SELinux);
+        assert_eq!(denials[1].operation, "write");
+        assert_eq!(denials[1].path, "docs");
+        assert_eq!(denials[1].context, "user_home_t");
+    }
+
+    #[test]
+    fn test_suggestion() {
+        let denials = parse_denials(LOG);
+        assert!(denials[0]
+            .suggestion(Some("/srv/media"))
+            .contains("\"/srv/media/** rwk,\" rule in security.apparmor.policies.\"samba-smbd\""));
+        assert_eq!(denials[1].suggestion(None), "setsebool -P samba_enable_home_dirs on");
+
+        let mut other = denials[1].clone();
+        other.context = "var_t".to_string();
+        assert_eq!(
+            other.suggestion(None),
+            "semanage fcontext -a -t samba_share_t \"/path/to/share(/.*)?\" && restorecon -R \"/path/to/share\""
+        );
+    }
+}
@@ -0,0 +1,180 @@
+use crate::samba::network::{invalid_host_entries, lan_subnets};
+use std::fs;
+
+/// Workgroup of Windows computers out of the box
+pub const DEFAULT_WORKGROUP: &str = "WORKGROUP";
+
+/// Server name used when the host name can't be read
+const FALLBACK_SERVER_NAME: &str = "smbnix";
+
+/// Longest NetBIOS name Samba accepts
+const MAX_NETBIOS_NAME: usize = 15;
+
+/// Hosts always allowed next to the chosen networks, localhost being the IPv6 localhost ::1
+const LOOPBACK_HOSTS: [&str; 2] = ["127.0.0.1", "localhost"];
+
+/// Global settings written when adding the first share creates the `services.samba` section.
+/// Existing sections are never rewritten from it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerTemplate {
+    pub workgroup: String,
+    /// `netbios name` and `server string`
+    pub server_name: String,
+    /// Networks allowed to connect besides this computer, every other host being denied.
+    /// Empty to allow every host.
+    pub hosts_allow: Vec<String>,
+    /// Let clients logging in with an unknown user in as guest (`map to guest = bad user`)
+    pub map_to_guest: bool,
+}
+
+impl ServerTemplate {
+    /// Template matching this computer: its host name and the networks it is connected to
+    pub fn detect() -> Self {
+        let host_name = fs::read_to_string("/proc/sys/kernel/hostname").unwrap_or_default();
+        Self {
+            workgroup: DEFAULT_WORKGROUP.to_string(),
+            server_name: netbios_name(&host_name).unwrap_or_else(|| FALLBACK_SERVER_NAME.to_string()),
+            hosts_allow: lan_subnets(),
+            map_to_guest: true,
+        }
+    }
+
+    /// Check the settings before they are written
+    pub fn validate(&self) -> Result<(), String> {
+        if !is_netbios_name(&self.workgroup) {
+            return Err(format!("Invalid workgroup '{}'", self.workgroup));
+        }
+        if !is_netbios_name(&self.server_name) {
+            return Err(format!(
+                "Invalid server name '{}', use up to {} letters, digits and dashes",
+                self.server_name, MAX_NETBIOS_NAME
+            ));
+        }
+        let invalid_hosts = invalid_host_entries(&self.hosts_allow);
+        if !invalid_hosts.is_empty() {
+            return Err(format!("Invalid allowed hosts: {}", invalid_hosts.join(" ")));
+        }
+        Ok(())
+    }
+
+    /// Settings of the `global` section for this server
+    pub fn global_settings(&self) -> Vec<(&'static str, String)> {
+        let mut global = vec![
+            ("workgroup", self.workgroup.clone()),
+            ("server string", self.server_name.clone()),
+            ("netbios name", self.server_name.clone()),
+            ("security", "user".to_string()),
+        ];
+        if !self.hosts_allow.is_empty() {
+            let mut hosts = self.hosts_allow.clone();
+            hosts.extend(LOOPBACK_HOSTS.iter().map(|host| host.to_string()));
+            global.push(("hosts allow", hosts.join(" ")));
+            global.push(("hosts deny", "0.0.0.0/0".to_string()));
+        }
+        global.push(("guest account", "nobody".to_string()));
+        if self.map_to_guest {
+            global.push(("map to guest", "bad user".to_string()));
+        }
+        global
+    }
+
+    /// `services.samba` section holding the global settings and `shares`, to insert
+    /// before the closing brace of the configuration
+    pub fn to_nix(&self, shares: &str) -> String {
+        let mut lines = vec![
+            String::new(),
+            "  services.samba = {".to_string(),
+            "    enable = true;".to_string(),
+            "    securityType = \"user\";".to_string(),
+            "    openFirewall = true;".to_string(),
+            "    settings = {".to_string(),
+            "        global = {".to_string(),
+        ];
+        for (key, value) in self.global_settings() {
+            lines.push(format!("          \"{}\" = \"{}\";", key, value));
+        }
+        lines.push("        };".to_string());
+        lines.push(shares.to_string());
+        lines.push("    };".to_string());
+        lines.push("  };".to_string());
+        lines.join("\n")
+    }
+}
+
+/// NetBIOS name derived from a host name: its first label, cut to the allowed length
+fn netbios_name(host_name: &str) -> Option<String> {
+    let name: String = host_name
+        .trim()
+        .split('.')
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .take(MAX_NETBIOS_NAME)
+        .collect();
+    let name = name.trim_end_matches('-').to_string();
+    is_netbios_name(&name).then_some(name)
+}
+
+/// Letters, digits and dashes, not starting with a dash, as Samba and Windows accept them
+pub fn is_netbios_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_NETBIOS_NAME
+        && !name.starts_with('-')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template() -> ServerTemplate {
+        ServerTemplate {
+            workgroup: "HOME".to_string(),
+            server_name: "desk".to_string(),
+            hosts_allow: vec!["192.168.1.0/24".to_string()],
+            map_to_guest: false,
+        }
+    }
+
+    #[test]
+    fn test_netbios_name() {
+        assert_eq!(netbios_name("desk.example.org\n"), Some("desk".to_string()));
+        assert_eq!(
+            netbios_name("a-very-long-host-name"),
+            Some("a-very-long-hos".to_string())
+        );
+        assert_eq!(netbios_name("workstation-12345"), Some("workstation-123".to_string()));
+        assert_eq!(netbios_name(""), None);
+        assert_eq!(netbios_name("ünï"), None);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(template().validate().is_ok());
+
+        let mut template = template();
+        template.hosts_allow.push("192.168.300.".to_string());
+        assert!(template.validate().is_err());
+
+        let mut template = self::template();
+        template.server_name = "name with spaces".to_string();
+        assert!(template.validate().is_err());
+    }
+
+    #[test]
+    fn test_to_nix() {
+        let nix = template().to_nix("    \"docs\" = {\n    };");
+        assert!(nix.contains("\"workgroup\" = \"HOME\";"));
+        assert!(nix.contains("\"netbios name\" = \"desk\";"));
+        assert!(nix.contains("\"hosts allow\" = \"192.168.1.0/24 127.0.0.1 localhost\";"));
+        assert!(nix.contains("\"hosts deny\" = \"0.0.0.0/0\";"));
+        assert!(!nix.contains("map to guest"));
+        assert!(nix.contains("    \"docs\" = {\n    };\n    };\n  };"));
+
+        let open = ServerTemplate {
+            hosts_allow: Vec::new(),
+            ..template()
+        };
+        assert!(!open.to_nix("").contains("hosts"));
+    }
+}
@@ -0,0 +1,113 @@
+use crate::samba::config_change::ConfigChange;
+use crate::samba::paths::config_path;
+use crate::samba::sandbox::host_path;
+use crate::samba::server_template::ServerTemplate;
+use crate::samba::share_config::{NixosBackend, SambaShareConfig};
+use crate::samba::smb_conf::SmbConfBackend;
+use once_cell::sync::OnceCell;
+use std::path::Path;
+
+/// File present on every NixOS system
+const NIXOS_MARKER: &str = "/etc/NIXOS";
+
+/// Where local shares are stored, so the same dialogs work on NixOS and on
+/// distributions configuring Samba through smb.conf
+pub trait ShareBackend: Send + Sync {
+    /// Kind of backend, deciding where remote shares are saved too
+    fn kind(&self) -> BackendKind;
+
+    /// File or files the shares are saved to, shown to the user
+    fn description(&self) -> String;
+
+    /// Every share of the configuration
+    fn load_all(&self) -> Result<Vec<SambaShareConfig>, String>;
+
+    /// File change adding `share`, without writing it. `template` sets up the
+    /// server when it isn't configured yet.
+    fn prepare_write(&self, share: &SambaShareConfig, template: &ServerTemplate) -> Result<ConfigChange, String>;
+
+    /// File change replacing the share `old_name` with `share`, without writing it
+    fn prepare_update(&self, share: &SambaShareConfig, old_name: &str) -> Result<ConfigChange, String>;
+
+    /// File change removing `share`, without writing it
+    fn prepare_delete(&self, share: &SambaShareConfig) -> Result<ConfigChange, String>;
+
+    /// Command making Samba use the saved configuration, run as root
+    fn apply_command(&self) -> &'static [&'static str];
+}
+
+/// Backend chosen in the preferences, or detected from the running system
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackendKind {
+    Nixos,
+    SmbConf,
+}
+
+impl BackendKind {
+    pub fn to_key(self) -> &'static str {
+        match self {
+            Self::Nixos => "nixos",
+            Self::SmbConf => "smb.conf",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        match key {
+            "nixos" => Some(Self::Nixos),
+            "smb.conf" => Some(Self::SmbConf),
+            _ => None,
+        }
+    }
+
+    /// NixOS when the system is one, smb.conf otherwise
+    pub fn detect() -> Self {
+        if host_path(Path::new(NIXOS_MARKER)).exists() {
+            Self::Nixos
+        } else {
+            Self::SmbConf
+        }
+    }
+
+    fn backend(self) -> Box<dyn ShareBackend> {
+        match self {
+            Self::Nixos => Box::new(NixosBackend),
+            Self::SmbConf => Box::new(SmbConfBackend::default()),
+        }
+    }
+}
+
+static BACKEND: OnceCell<Box<dyn ShareBackend>> = OnceCell::new();
+
+/// Select the backend for the whole process. Only the first call has an effect.
+pub fn set_backend(kind: BackendKind) {
+    if BACKEND.set(kind.backend()).is_err() {
+        eprintln!("Share backend already set");
+    }
+}
+
+/// Selected backend, the NixOS configuration by default
+pub fn backend() -> &'static dyn ShareBackend {
+    BACKEND.get_or_init(|| BackendKind::Nixos.backend()).as_ref()
+}
+
+/// Whether the app may rewrite the entries of `file`: the main NixOS configuration file,
+/// or an imported one listed in `managed_files`. The other backends only read their own file.
+pub fn is_managed_file(file: &str, managed_files: &[String]) -> bool {
+    match backend().kind() {
+        BackendKind::Nixos => file == config_path() || managed_files.iter().any(|managed| managed == file),
+        BackendKind::SmbConf => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_managed_file() {
+        let managed_files = vec!["/etc/nixos/samba.nix".to_string()];
+        assert!(is_managed_file(config_path(), &[]));
+        assert!(is_managed_file("/etc/nixos/samba.nix", &managed_files));
+        assert!(!is_managed_file("/etc/nixos/hardware.nix", &managed_files));
+    }
+}
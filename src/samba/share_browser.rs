@@ -0,0 +1,128 @@
+use crate::samba::command_runner::command;
+
+/// Split `//server/share/sub/dir` into the share, `//server/share`,
+/// and the folder inside it, `sub/dir`. None when no share is named.
+pub fn split_remote_path(remote_path: &str) -> Option<(String, String)> {
+    let path = remote_path.strip_prefix("//")?;
+    let mut parts = path.split('/').filter(|part| !part.is_empty());
+    let server = parts.next()?;
+    let share = parts.next()?;
+    let subpath: Vec<&str> = parts.collect();
+    Some((format!("//{}/{}", server, share), subpath.join("/")))
+}
+
+/// Device for a folder of a share, without doubled or trailing slashes
+pub fn join_remote_path(share: &str, subpath: &str) -> String {
+    let subpath: Vec<&str> = subpath.split('/').filter(|part| !part.is_empty()).collect();
+    if subpath.is_empty() {
+        share.trim_end_matches('/').to_string()
+    } else {
+        format!("{}/{}", share.trim_end_matches('/'), subpath.join("/"))
+    }
+}
+
+/// Convert a Windows path like `\\server\share\sub dir` to `//server/share/sub dir`.
+/// None when `text` isn't a UNC path, or is already written with slashes.
+pub fn from_unc_path(text: &str) -> Option<String> {
+    let path = text.trim().trim_matches('"');
+    if !path.contains('\\') {
+        return None;
+    }
+    // Pasted after the "//" the entry starts with, the path has extra slashes
+    let converted = path.replace('\\', "/");
+    converted
+        .starts_with("//")
+        .then(|| format!("//{}", converted.trim_start_matches('/')))
+}
+
+/// Folders directly inside `subpath` of `share`, listed with smbclient.
+/// Connects as guest when no credentials file is given.
+pub fn list_folders(share: &str, subpath: &str, credentials: &str) -> Result<Vec<String>, String> {
+    if subpath.contains('"') {
+        return Err("Folder names with quotes can't be browsed".to_string());
+    }
+    let pattern = if subpath.is_empty() {
+        "*".to_string()
+    } else {
+        format!("{}\\*", subpath.replace('/', "\\"))
+    };
+
+    let mut smbclient = command("smbclient");
+    smbclient.arg(share);
+    if credentials.is_empty() {
+        smbclient.arg("-N");
+    } else {
+        smbclient.args(["-A", credentials]);
+    }
+    let output = smbclient
+        .args(["-c", &format!("ls \"{}\"", pattern)])
+        .output()
+        .map_err(|e| format!("Failed to run smbclient: {}", e))?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if !output.status.success() {
+        // smbclient reports most errors like NT_STATUS_ACCESS_DENIED on stdout
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let message = stdout
+            .lines()
+            .chain(stderr.lines())
+            .find(|line| line.contains("NT_STATUS_"))
+            .unwrap_or(stderr.trim());
+        return Err(message.trim().to_string());
+    }
+    Ok(parse_folders(&stdout))
+}
+
+/// Folder names in the output of smbclient's `ls`, sorted
+fn parse_folders(output: &str) -> Vec<String> {
+    // "  name   D   0  Mon Jan  1 10:00:00 2024"
+    let entry = regex::Regex::new(r"^  (.+?)\s+([A-Z]*)\s+\d+\s+\w{3} \w{3}\s+\d+ [\d:]+ \d{4}$")
+        .expect("valid regex");
+
+    let mut folders: Vec<String> = output
+        .lines()
+        .filter_map(|line| entry.captures(line))
+        .filter(|captures| captures[2].contains('D'))
+        .map(|captures| captures[1].to_string())
+        .filter(|name| name != "." && name != "..")
+        .collect();
+    folders.sort_by_key(|name| name.to_lowercase());
+    folders
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remote_paths() {
+        assert_eq!(
+            split_remote_path("//nas/media/Films/2024/"),
+            Some(("//nas/media".to_string(), "Films/2024".to_string()))
+        );
+        assert_eq!(split_remote_path("//nas"), None);
+        assert_eq!(join_remote_path("//nas/media", "Films//2024/"), "//nas/media/Films/2024");
+        assert_eq!(join_remote_path("//nas/media", ""), "//nas/media");
+
+        assert_eq!(
+            from_unc_path(r#" "\\nas\media\Films 2024\" "#),
+            Some("//nas/media/Films 2024/".to_string())
+        );
+        assert_eq!(from_unc_path(r"//\\nas\media"), Some("//nas/media".to_string()));
+        assert_eq!(from_unc_path("//nas/media"), None);
+        assert_eq!(from_unc_path(r"C:\Users"), None);
+    }
+
+    #[test]
+    fn test_parse_folders() {
+        let output = "  .                                   D        0  Mon Jan  1 10:00:00 2024
+  ..                                  D        0  Mon Jan  1 10:00:00 2024
+  photos                              D        0  Tue Feb  6 08:12:40 2024
+  notes.txt                           A     1234  Tue Feb  6 08:12:40 2024
+  My Music                           DH        0  Wed Mar 13 19:01:02 2024
+
+\t\t122847 blocks of size 1024. 4573 blocks available
+";
+        assert_eq!(parse_folders(output), vec!["My Music", "photos"]);
+    }
+}
@@ -1,10 +1,21 @@
-use crate::samba::sudo_write::write_with_sudo;
+use crate::samba::command_runner::command;
+use crate::samba::config_change::ConfigChange;
+use crate::samba::homes::HOMES;
+use crate::samba::nix_edit::{insert_before_closing, module_attrset, remove_node, update_entry};
+use crate::samba::nix_imports::config_files;
+use crate::samba::nix_parse::{check_cancelled, parse_file};
+use crate::samba::paths::config_path;
+use crate::samba::sandbox::host_path;
+use crate::samba::security::SecurityLevel;
+use crate::samba::server_template::ServerTemplate;
+use crate::samba::share_backend::{backend, BackendKind, ShareBackend};
+use crate::samba::sudo_write::read_file;
 use rnix::{Root, SyntaxKind, SyntaxNode};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
-use std::process::Command;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SambaShareConfig {
     pub name: String,
     pub path: String,
@@ -13,12 +24,107 @@ pub struct SambaShareConfig {
     pub guest_ok: bool,
     pub force_user: String,
     pub force_group: String,
+    pub smb_encrypt: SecurityLevel,
+    /// Log file operations through the full_audit VFS module
+    pub audit: bool,
+    /// Other `vfs objects` modules present in the configuration, kept as-is
+    pub other_vfs_objects: Vec<String>,
+    /// `recycle:repository` of shares using the recycle module, empty for Samba's `.recycle`
+    pub recycle_repository: String,
+    /// Users allowed to connect (`valid users`), empty for no restriction
+    pub valid_users: Vec<String>,
+    /// Users and `@groups` refused (`invalid users`), whatever the other lists say
+    pub invalid_users: Vec<String>,
+    /// Users and `@groups` limited to reading (`read list`), whatever `read_only` says
+    pub read_list: Vec<String>,
+    /// Users and `@groups` allowed to write (`write list`), even on a read-only share
+    pub write_list: Vec<String>,
+    /// `guest account` override for guest-enabled shares, empty for the server default
+    pub guest_account: String,
+    /// Octal permission settings, empty when not set
+    pub create_mask: String,
+    pub directory_mask: String,
+    pub force_create_mode: String,
+    pub force_directory_mode: String,
+    /// New files and folders take their permissions from the parent directory
+    pub inherit_permissions: bool,
+    /// New files and folders take their ACLs from the parent directory
+    pub inherit_acls: bool,
+    /// Allow clients to follow symbolic links inside the share
+    pub follow_symlinks: bool,
+    /// Allow symbolic links pointing outside the share path
+    pub wide_links: bool,
+    /// Keep Windows attributes in extended attributes (`store dos attributes`)
+    pub store_dos_attributes: bool,
+    /// Map Windows attributes onto Unix execute bits, used when they are not stored
+    pub map_archive: bool,
+    pub map_hidden: bool,
+    pub map_system: bool,
+    /// Opportunistic locking, disable for databases shared between clients
+    pub oplocks: bool,
+    pub level2_oplocks: bool,
+    /// Let the kernel break oplocks when local processes access the files
+    pub kernel_oplocks: bool,
+    /// Server-side Spotlight search for macOS clients, backed by Elasticsearch
+    pub spotlight: bool,
+    /// Configuration file the share is defined in, the main file or one of its imports
+    pub source_file: String,
+    /// Why the entry can't be rewritten safely, None when it only uses plain values
+    pub locked_reason: Option<String>,
 }
 
-impl SambaShareConfig {
-    /// Path to the NixOS configuration file
-    const CONFIG_PATH: &'static str = "/etc/nixos/customConfig/default.nix";
+/// Keys of a share entry the share editor writes. When a share is updated, those it no
+/// longer writes are removed, the others were added by hand and stay as they are.
+pub(crate) const MANAGED_KEYS: [&str; 36] = [
+    "path",
+    "browseable",
+    "read only",
+    "guest ok",
+    "force user",
+    "force group",
+    "smb encrypt",
+    "valid users",
+    "invalid users",
+    "read list",
+    "write list",
+    "guest account",
+    "create mask",
+    "directory mask",
+    "force create mode",
+    "force directory mode",
+    "inherit permissions",
+    "inherit acls",
+    "follow symlinks",
+    "wide links",
+    "store dos attributes",
+    "map archive",
+    "map hidden",
+    "map system",
+    "oplocks",
+    "level2 oplocks",
+    "kernel oplocks",
+    "spotlight",
+    "spotlight backend",
+    "vfs objects",
+    "recycle:repository",
+    "full_audit:prefix",
+    "full_audit:success",
+    "full_audit:failure",
+    "full_audit:facility",
+    "full_audit:priority",
+];
+
+/// Settings written alongside `vfs objects = full_audit`.
+/// Entries go to syslog as `user|ip|share|operation|result|path` under the `smbd_audit` tag.
+const FULL_AUDIT_SETTINGS: [(&str, &str); 5] = [
+    ("full_audit:prefix", "%u|%I|%S"),
+    ("full_audit:success", "mkdirat renameat unlinkat openat pwrite"),
+    ("full_audit:failure", "connect"),
+    ("full_audit:facility", "local5"),
+    ("full_audit:priority", "notice"),
+];
 
+impl SambaShareConfig {
     pub fn new(
         name: String,
         path: String,
@@ -36,45 +142,394 @@ impl SambaShareConfig {
             guest_ok,
             force_user,
             force_group,
+            smb_encrypt: SecurityLevel::Default,
+            audit: false,
+            other_vfs_objects: Vec::new(),
+            recycle_repository: String::new(),
+            valid_users: Vec::new(),
+            invalid_users: Vec::new(),
+            read_list: Vec::new(),
+            write_list: Vec::new(),
+            guest_account: String::new(),
+            create_mask: String::new(),
+            directory_mask: String::new(),
+            force_create_mode: String::new(),
+            force_directory_mode: String::new(),
+            inherit_permissions: false,
+            inherit_acls: false,
+            follow_symlinks: true,
+            wide_links: false,
+            store_dos_attributes: true,
+            map_archive: true,
+            map_hidden: false,
+            map_system: false,
+            oplocks: true,
+            level2_oplocks: true,
+            kernel_oplocks: false,
+            spotlight: false,
+            source_file: config_path().to_string(),
+            locked_reason: None,
         }
     }
 
-    /// Load all Samba shares from NixOS configuration using rnix parser
+    /// Load all Samba shares from the configuration of the selected backend
     pub fn load_all() -> Result<Vec<Self>, String> {
-        let content = fs::read_to_string(Self::CONFIG_PATH)
-            .map_err(|e| format!("Failed to read {}: {}", Self::CONFIG_PATH, e))?;
+        backend().load_all()
+    }
 
-        let parsed = Root::parse(&content);
-        let root = parsed.syntax();
+    /// File holding `services.samba.settings`, falling back to the main configuration file
+    pub(crate) fn settings_file() -> String {
+        config_files(config_path())
+            .into_iter()
+            .find(|file| {
+                fs::read_to_string(host_path(file))
+                    .map(|content| find_samba_settings(&parse_file(file, &content).syntax()).is_some())
+                    .unwrap_or(false)
+            })
+            .map(|file| file.to_string_lossy().to_string())
+            .unwrap_or_else(|| config_path().to_string())
+    }
+
+    /// Build a share from the properties of its settings entry
+    pub(crate) fn from_props(name: String, props: &HashMap<String, String>) -> Self {
+        let vfs_objects: Vec<String> = props
+            .get("vfs objects")
+            .map(|v| v.split_whitespace().map(|s| s.to_string()).collect())
+            .unwrap_or_default();
+
+        SambaShareConfig {
+            name,
+            path: props.get("path").cloned().unwrap_or_default(),
+            browsable: props
+                .get("browseable")
+                .map(|v| v == "yes")
+                .unwrap_or(true),
+            read_only: props
+                .get("read only")
+                .map(|v| v == "yes")
+                .unwrap_or(false),
+            guest_ok: props
+                .get("guest ok")
+                .map(|v| v == "yes")
+                .unwrap_or(false),
+            force_user: props.get("force user").cloned().unwrap_or_default(),
+            force_group: props.get("force group").cloned().unwrap_or_default(),
+            smb_encrypt: props
+                .get("smb encrypt")
+                .map(|v| SecurityLevel::parse(v))
+                .unwrap_or_default(),
+            audit: vfs_objects.iter().any(|m| m == "full_audit"),
+            other_vfs_objects: vfs_objects
+                .into_iter()
+                .filter(|m| m != "full_audit")
+                .collect(),
+            recycle_repository: props.get("recycle:repository").cloned().unwrap_or_default(),
+            valid_users: props
+                .get("valid users")
+                .map(|v| parse_name_list(v))
+                .unwrap_or_default(),
+            invalid_users: props
+                .get("invalid users")
+                .map(|v| parse_name_list(v))
+                .unwrap_or_default(),
+            read_list: props
+                .get("read list")
+                .map(|v| parse_name_list(v))
+                .unwrap_or_default(),
+            write_list: props
+                .get("write list")
+                .map(|v| parse_name_list(v))
+                .unwrap_or_default(),
+            guest_account: props.get("guest account").cloned().unwrap_or_default(),
+            create_mask: props.get("create mask").cloned().unwrap_or_default(),
+            directory_mask: props.get("directory mask").cloned().unwrap_or_default(),
+            force_create_mode: props.get("force create mode").cloned().unwrap_or_default(),
+            force_directory_mode: props
+                .get("force directory mode")
+                .cloned()
+                .unwrap_or_default(),
+            inherit_permissions: props
+                .get("inherit permissions")
+                .map(|v| v == "yes")
+                .unwrap_or(false),
+            inherit_acls: props
+                .get("inherit acls")
+                .map(|v| v == "yes")
+                .unwrap_or(false),
+            follow_symlinks: props
+                .get("follow symlinks")
+                .map(|v| v == "yes")
+                .unwrap_or(true),
+            wide_links: props
+                .get("wide links")
+                .map(|v| v == "yes")
+                .unwrap_or(false),
+            store_dos_attributes: props
+                .get("store dos attributes")
+                .map(|v| v == "yes")
+                .unwrap_or(true),
+            map_archive: props
+                .get("map archive")
+                .map(|v| v == "yes")
+                .unwrap_or(true),
+            map_hidden: props
+                .get("map hidden")
+                .map(|v| v == "yes")
+                .unwrap_or(false),
+            map_system: props
+                .get("map system")
+                .map(|v| v == "yes")
+                .unwrap_or(false),
+            oplocks: props
+                .get("oplocks")
+                .map(|v| v == "yes")
+                .unwrap_or(true),
+            level2_oplocks: props
+                .get("level2 oplocks")
+                .map(|v| v == "yes")
+                .unwrap_or(true),
+            kernel_oplocks: props
+                .get("kernel oplocks")
+                .map(|v| v == "yes")
+                .unwrap_or(false),
+            spotlight: props
+                .get("spotlight")
+                .map(|v| v == "yes")
+                .unwrap_or(false),
+            source_file: config_path().to_string(),
+            locked_reason: None,
+        }
+    }
+
+    /// Octal permission options with their Samba names
+    fn mode_options(&self) -> [(&'static str, &str); 4] {
+        [
+            ("create mask", &self.create_mask),
+            ("directory mask", &self.directory_mask),
+            ("force create mode", &self.force_create_mode),
+            ("force directory mode", &self.force_directory_mode),
+        ]
+    }
+
+    /// Check option values before they are written to the configuration
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name == "global" || self.name == HOMES {
+            return Err(format!("'{}' is a reserved section name", self.name));
+        }
+        for (key, value) in self.mode_options() {
+            if !value.is_empty() && !is_octal_mode(value) {
+                return Err(format!(
+                    "'{}' must be an octal mode such as 0644, got '{}'",
+                    key, value
+                ));
+            }
+        }
+        if self.wide_links && !self.follow_symlinks {
+            return Err("'wide links' requires 'follow symlinks' to be enabled".to_string());
+        }
+        Ok(())
+    }
+
+    /// Whether deleted files go to a recycle bin instead of being removed
+    pub fn recycle_enabled(&self) -> bool {
+        self.other_vfs_objects.iter().any(|module| module == "recycle")
+    }
+
+    /// Modules for the `vfs objects` option
+    fn vfs_objects(&self) -> Vec<String> {
+        let mut modules = self.other_vfs_objects.clone();
+        if self.audit {
+            modules.push("full_audit".to_string());
+        }
+        modules
+    }
+
+    /// Generate the Nix settings entry for this share
+    pub fn to_nix(&self) -> String {
+        let mut lines = vec![
+            format!("    \"{}\" = {{", self.name),
+            format!("      path = \"{}\";", self.path),
+            format!("      browseable = {};", yes_no(self.browsable)),
+            format!("      \"read only\" = {};", yes_no(self.read_only)),
+            format!("      \"guest ok\" = {};", yes_no(self.guest_ok)),
+            format!("      \"force user\" = \"{}\";", self.force_user),
+            format!("      \"force group\" = \"{}\";", self.force_group),
+        ];
+
+        for (key, value) in self.other_settings() {
+            lines.push(format!("      \"{}\" = \"{}\";", key, value));
+        }
+        lines.push("    };".to_string());
+        lines.join("\n")
+    }
+
+    /// Settings written after the basic ones, only when they differ from Samba's defaults
+    pub(crate) fn other_settings(&self) -> Vec<(&'static str, String)> {
+        let mut options: Vec<(&'static str, String)> = Vec::new();
+        if let Some(value) = self.smb_encrypt.encrypt_value() {
+            options.push(("smb encrypt", value.to_string()));
+        }
+        if !self.valid_users.is_empty() {
+            options.push(("valid users", self.valid_users.join(" ")));
+        }
+        if !self.invalid_users.is_empty() {
+            options.push(("invalid users", self.invalid_users.join(" ")));
+        }
+        if !self.read_list.is_empty() {
+            options.push(("read list", self.read_list.join(" ")));
+        }
+        if !self.write_list.is_empty() {
+            options.push(("write list", self.write_list.join(" ")));
+        }
+        if self.guest_ok && !self.guest_account.is_empty() {
+            options.push(("guest account", self.guest_account.clone()));
+        }
+        for (key, value) in self.mode_options() {
+            if !value.is_empty() {
+                options.push((key, value.to_string()));
+            }
+        }
+        if self.inherit_permissions {
+            options.push(("inherit permissions", "yes".to_string()));
+        }
+        if self.inherit_acls {
+            options.push(("inherit acls", "yes".to_string()));
+        }
+        if !self.follow_symlinks {
+            options.push(("follow symlinks", "no".to_string()));
+        }
+        if self.wide_links {
+            options.push(("wide links", "yes".to_string()));
+        }
+        if !self.store_dos_attributes {
+            options.push(("store dos attributes", "no".to_string()));
+        }
+        if !self.map_archive {
+            options.push(("map archive", "no".to_string()));
+        }
+        if self.map_hidden {
+            options.push(("map hidden", "yes".to_string()));
+        }
+        if self.map_system {
+            options.push(("map system", "yes".to_string()));
+        }
+        if !self.oplocks {
+            options.push(("oplocks", "no".to_string()));
+        }
+        if !self.level2_oplocks {
+            options.push(("level2 oplocks", "no".to_string()));
+        }
+        if self.kernel_oplocks {
+            options.push(("kernel oplocks", "yes".to_string()));
+        }
+        if self.spotlight {
+            options.push(("spotlight", "yes".to_string()));
+            options.push(("spotlight backend", "elasticsearch".to_string()));
+        }
+
+        let vfs_objects = self.vfs_objects();
+        if !vfs_objects.is_empty() {
+            options.push(("vfs objects", vfs_objects.join(" ")));
+        }
+        if self.recycle_enabled() && !self.recycle_repository.is_empty() {
+            options.push(("recycle:repository", self.recycle_repository.clone()));
+        }
+        if self.audit {
+            for (key, value) in FULL_AUDIT_SETTINGS {
+                options.push((key, value.to_string()));
+            }
+        }
+        options
+    }
+
+    /// Write a new Samba share to the configuration.
+    /// Returns the applied change so it can be tracked until the next rebuild.
+    pub fn write(&self, template: &ServerTemplate) -> Result<ConfigChange, String> {
+        let change = self.prepare_write(template)?;
+        change.apply()?;
+        Ok(change)
+    }
+
+    /// Compute the file change adding this share, without writing it.
+    /// `template` sets up the server when it isn't configured yet.
+    pub fn prepare_write(&self, template: &ServerTemplate) -> Result<ConfigChange, String> {
+        self.validate()?;
+        backend().prepare_write(self, template)
+    }
+
+    /// Update an existing Samba share configuration.
+    /// Returns the applied change so it can be tracked until the next rebuild.
+    pub fn update(&self, old_name: &str) -> Result<ConfigChange, String> {
+        let change = self.prepare_update(old_name)?;
+        change.apply()?;
+        Ok(change)
+    }
+
+    /// Compute the file change replacing the share `old_name` with this one, without writing it
+    pub fn prepare_update(&self, old_name: &str) -> Result<ConfigChange, String> {
+        self.validate()?;
+        backend().prepare_update(self, old_name)
+    }
+
+    /// Remove this share from the file it is defined in.
+    /// Returns the applied change so it can be reverted.
+    pub fn delete(&self) -> Result<ConfigChange, String> {
+        let change = self.prepare_delete()?;
+        change.apply()?;
+        Ok(change)
+    }
+
+    /// Compute the file change removing this share, without writing it
+    pub fn prepare_delete(&self) -> Result<ConfigChange, String> {
+        backend().prepare_delete(self)
+    }
+}
+
+/// Shares in `services.samba.settings` of the NixOS configuration and the files it imports
+pub struct NixosBackend;
+
+impl ShareBackend for NixosBackend {
+    fn kind(&self) -> BackendKind {
+        BackendKind::Nixos
+    }
+
+    fn description(&self) -> String {
+        config_path().to_string()
+    }
 
+    fn load_all(&self) -> Result<Vec<SambaShareConfig>, String> {
         let mut shares = Vec::new();
 
-        // Find services.samba.settings attrset
-        if let Some(settings_attrset) = find_samba_settings(&root) {
-            // Iterate through all entries in the settings attrset
-            for child in settings_attrset.children() {
-                if child.kind() == SyntaxKind::NODE_ATTRPATH_VALUE {
-                    if let Some((name, props)) = parse_attrset_entry(&child) {
-                        // Skip the "global" section
-                        if name != "global" {
-                            shares.push(SambaShareConfig {
-                                name,
-                                path: props.get("path").cloned().unwrap_or_default(),
-                                browsable: props
-                                    .get("browseable")
-                                    .map(|v| v == "yes")
-                                    .unwrap_or(true),
-                                read_only: props
-                                    .get("read only")
-                                    .map(|v| v == "yes")
-                                    .unwrap_or(false),
-                                guest_ok: props
-                                    .get("guest ok")
-                                    .map(|v| v == "yes")
-                                    .unwrap_or(false),
-                                force_user: props.get("force user").cloned().unwrap_or_default(),
-                                force_group: props.get("force group").cloned().unwrap_or_default(),
-                            });
+        for file in config_files(config_path()) {
+            check_cancelled()?;
+            let source_file = file.to_string_lossy().to_string();
+            let content = match fs::read_to_string(host_path(&file)) {
+                Ok(content) => content,
+                Err(e) if source_file == config_path() => {
+                    return Err(format!("Failed to read {}: {}", source_file, e));
+                }
+                Err(e) => {
+                    eprintln!("Skipping imported file {}: {}", source_file, e);
+                    continue;
+                }
+            };
+
+            let parsed = parse_file(&file, &content);
+            let root = parsed.syntax();
+
+            // Find services.samba.settings attrset
+            if let Some(settings_attrset) = find_samba_settings(&root) {
+                // Iterate through all entries in the settings attrset
+                for child in settings_attrset.children() {
+                    if child.kind() == SyntaxKind::NODE_ATTRPATH_VALUE {
+                        if let Some((name, props)) = parse_attrset_entry(&child) {
+                            // Skip the special sections, edited in the server settings
+                            if name != "global" && name != HOMES {
+                                let mut share = SambaShareConfig::from_props(name, &props);
+                                share.source_file = source_file.clone();
+                                share.locked_reason = unsupported_expression(&child);
+                                shares.push(share);
+                            }
                         }
                     }
                 }
@@ -84,10 +539,15 @@ impl SambaShareConfig {
         Ok(shares)
     }
 
-    /// Write a new Samba share configuration to NixOS
-    pub fn write(&self) -> Result<(), String> {
-        let content = fs::read_to_string(Self::CONFIG_PATH)
-            .map_err(|e| format!("Failed to read {}: {}", Self::CONFIG_PATH, e))?;
+    fn prepare_write(&self, share: &SambaShareConfig, template: &ServerTemplate) -> Result<ConfigChange, String> {
+        // A share of the same name may already live in an imported file
+        if self.load_all()?.iter().any(|existing| existing.name == share.name) {
+            return Err(format!("Share '{}' already exists", share.name));
+        }
+
+        // Add the share next to the existing ones, wherever they are defined
+        let config_path = SambaShareConfig::settings_file();
+        let content = read_file(&config_path)?;
 
         // Parse to validate syntax
         let parsed = Root::parse(&content);
@@ -96,156 +556,143 @@ impl SambaShareConfig {
         }
 
         // Generate the share configuration
-        let share_config = format!(
-            r#"    "{}" = {{
-      path = "{}";
-      browseable = {};
-      "read only" = {};
-      "guest ok" = {};
-      "force user" = "{}";
-      "force group" = "{}";
-    }};"#,
-            self.name,
-            self.path,
-            if self.browsable { "yes" } else { "no" },
-            if self.read_only { "yes" } else { "no" },
-            if self.guest_ok { "yes" } else { "no" },
-            self.force_user,
-            self.force_group
-        );
+        let share_config = share.to_nix();
 
         let root = parsed.syntax();
 
-        // Find the settings attrset to determine insertion point
-        if let Some(settings_attrset) = find_samba_settings(&root) {
-            // Get the text range of the settings attrset
-            let range = settings_attrset.text_range();
-            let settings_end: usize = range.end().into();
+        // Add the share after the existing ones, or create the whole samba section
+        let new_content = if let Some(settings_attrset) = find_samba_settings(&root) {
+            insert_before_closing(&content, &settings_attrset, &share_config)?
+        } else {
+            template.validate()?;
+            let module = module_attrset(&root)
+                .ok_or("Could not find suitable location to add services.samba section")?;
+            insert_before_closing(&content, &module, &template.to_nix(&share_config))?
+        };
 
-            // Find the position just before the closing brace
-            // We need to insert before the last }
-            let before_closing = content[..settings_end]
-                .rfind('}')
-                .ok_or("Could not find closing brace of settings section")?;
+        Ok(ConfigChange::new(config_path, content, new_content))
+    }
 
-            let before = &content[..before_closing];
-            let after = &content[before_closing..];
-            let new_content = format!("{}\n{}\n{}", before, share_config, after);
+    /// Only the settings that changed are rewritten, comments and formatting stay as they are
+    fn prepare_update(&self, share: &SambaShareConfig, old_name: &str) -> Result<ConfigChange, String> {
+        let content = read_file(&share.source_file)?;
 
-            write_with_sudo(Self::CONFIG_PATH, &new_content)?;
-        } else {
-            // No settings section exists, create entire samba section
-            let lines: Vec<&str> = content.lines().collect();
-            let mut insert_idx = None;
-
-            for (i, line) in lines.iter().enumerate().rev() {
-                if line.trim() == "}" {
-                    insert_idx = Some(i);
-                    break;
-                }
-            }
+        let parsed = Root::parse(&content);
+        let root = parsed.syntax();
 
-            if let Some(idx) = insert_idx {
-                let samba_section = format!(
-                    r#"
-  services.samba = {{
-    enable = true;
-    securityType = "user";
-    openFirewall = true;
-    settings = {{
-        global = {{
-          "workgroup" = "WORKGROUP";
-          "server string" = "smbnix";
-          "netbios name" = "smbnix";
-          "security" = "user";
-          #"use sendfile" = "yes";
-          #"max protocol" = "smb2";
-          # note: localhost is the ipv6 localhost ::1
-          "hosts allow" = "192.168.0. 127.0.0.1 localhost";
-          "hosts deny" = "0.0.0.0/0";
-          "guest account" = "nobody";
-          "map to guest" = "bad user";
-        }};
-{}
-    }};
-  }};"#,
-                    share_config
-                );
-
-                let mut new_lines: Vec<String> = lines.iter().map(|s| s.to_string()).collect();
-                new_lines.insert(idx, samba_section);
-                let new_content = new_lines.join("\n");
-
-                write_with_sudo(Self::CONFIG_PATH, &new_content)?;
-            } else {
-                return Err(
-                    "Could not find suitable location to add services.samba section".to_string(),
-                );
-            }
+        // Find the specific share entry
+        let entry = find_samba_settings(&root)
+            .and_then(|settings| {
+                settings.children().find(|child| {
+                    child.kind() == SyntaxKind::NODE_ATTRPATH_VALUE
+                        && get_attrpath_name(child).as_deref() == Some(old_name)
+                })
+            })
+            .ok_or_else(|| format!("Share '{}' not found in {}", old_name, share.source_file))?;
+
+        // Rewriting a computed entry would lose the expression
+        if let Some(reason) = unsupported_expression(&entry) {
+            return Err(format!("Share '{}' can't be edited here: {}", old_name, reason));
         }
 
-        Ok(())
+        let new_content = update_entry(&content, find_samba_settings, old_name, &share.to_nix(), &MANAGED_KEYS)?;
+        Ok(ConfigChange::new(share.source_file.clone(), content, new_content))
     }
 
-    /// Update an existing Samba share configuration
-    pub fn update(&self, old_name: &str) -> Result<(), String> {
-        let content = fs::read_to_string(Self::CONFIG_PATH)
-            .map_err(|e| format!("Failed to read {}: {}", Self::CONFIG_PATH, e))?;
+    fn prepare_delete(&self, share: &SambaShareConfig) -> Result<ConfigChange, String> {
+        let content = read_file(&share.source_file)?;
 
         let parsed = Root::parse(&content);
         let root = parsed.syntax();
 
-        // Find the settings attrset
-        if let Some(settings_attrset) = find_samba_settings(&root) {
-            // Find the specific share entry
-            for child in settings_attrset.children() {
-                if child.kind() == SyntaxKind::NODE_ATTRPATH_VALUE {
-                    if let Some(name) = get_attrpath_name(&child) {
-                        if name == old_name {
-                            // Found the share to update
-                            let range = child.text_range();
-                            let start: usize = range.start().into();
-                            let end: usize = range.end().into();
-
-                            // Generate the new share configuration
-                            let share_config = format!(
-                                r#"    "{}" = {{
-      path = "{}";
-      browseable = {};
-      "read only" = {};
-      "guest ok" = {};
-      "force user" = "{}";
-      "force group" = "{}";
-    }};"#,
-                                self.name,
-                                self.path,
-                                if self.browsable { "yes" } else { "no" },
-                                if self.read_only { "yes" } else { "no" },
-                                if self.guest_ok { "yes" } else { "no" },
-                                self.force_user,
-                                self.force_group
-                            );
-
-                            // Replace the old share with the new one
-                            let before = &content[..start];
-                            let after = &content[end..];
-                            let new_content = format!("{}{}{}", before, share_config, after);
-
-                            write_with_sudo(Self::CONFIG_PATH, &new_content)?;
-
-                            return Ok(());
-                        }
-                    }
+        let entry = find_samba_settings(&root)
+            .and_then(|settings| {
+                settings.children().find(|child| {
+                    child.kind() == SyntaxKind::NODE_ATTRPATH_VALUE
+                        && get_attrpath_name(child).as_deref() == Some(share.name.as_str())
+                })
+            })
+            .ok_or_else(|| format!("Share '{}' not found in {}", share.name, share.source_file))?;
+
+        let new_content = remove_node(&content, &entry);
+        Ok(ConfigChange::new(share.source_file.clone(), content, new_content))
+    }
+
+    fn apply_command(&self) -> &'static [&'static str] {
+        &["nixos-rebuild", "switch"]
+    }
+}
+
+/// Format a boolean the way Samba settings expect it
+pub(crate) fn yes_no(value: bool) -> &'static str {
+    if value {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
+/// Split a Samba list of users and `@groups`, separated by commas or spaces
+pub fn parse_name_list(value: &str) -> Vec<String> {
+    value
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|name| !name.is_empty())
+        .map(|name| name.to_string())
+        .collect()
+}
+
+/// Whether a value is an octal permission mode like `0644` or `775`
+fn is_octal_mode(value: &str) -> bool {
+    (3..=4).contains(&value.len()) && value.chars().all(|c| ('0'..='7').contains(&c))
+}
+
+/// Explain why an ATTRPATH_VALUE entry can't be regenerated from its parsed values.
+/// Entries using variables, `let` bindings, function calls, `inherit` or
+/// string interpolation are listed read-only instead of being rewritten.
+pub(crate) fn unsupported_expression(entry: &SyntaxNode) -> Option<String> {
+    let value = entry
+        .children()
+        .find(|child| child.kind() != SyntaxKind::NODE_ATTRPATH)?;
+
+    if value.kind() != SyntaxKind::NODE_ATTR_SET {
+        return Some("defined by a Nix expression (variable, let binding or function call)".to_string());
+    }
+
+    for child in value.children() {
+        match child.kind() {
+            SyntaxKind::NODE_INHERIT => {
+                return Some("uses inherit".to_string());
+            }
+            SyntaxKind::NODE_ATTRPATH_VALUE => {
+                let literal = child
+                    .children()
+                    .find(|c| c.kind() != SyntaxKind::NODE_ATTRPATH)
+                    .map(|v| is_plain_value(&v))
+                    .unwrap_or(false);
+                if !literal {
+                    let key = get_attrpath_name(&child).unwrap_or_default();
+                    return Some(format!("'{}' is computed by a Nix expression", key));
                 }
             }
+            _ => {}
         }
+    }
+
+    None
+}
 
-        Err(format!("Share '{}' not found in configuration", old_name))
+/// Whether a value node is a literal the parser reads back verbatim
+fn is_plain_value(value: &SyntaxNode) -> bool {
+    match value.kind() {
+        SyntaxKind::NODE_STRING => !value.text().to_string().contains("${"),
+        SyntaxKind::NODE_IDENT | SyntaxKind::NODE_LITERAL => true,
+        SyntaxKind::NODE_LIST => value.children().all(|item| is_plain_value(&item)),
+        _ => false,
     }
 }
 
 /// Find the services.samba.settings attrset node
-fn find_samba_settings(node: &SyntaxNode) -> Option<SyntaxNode> {
+pub(crate) fn find_samba_settings(node: &SyntaxNode) -> Option<SyntaxNode> {
     // Recursively search for services.samba.settings
     for child in node.children() {
         // Look for ATTRPATH_VALUE nodes
@@ -282,7 +729,7 @@ fn find_samba_settings(node: &SyntaxNode) -> Option<SyntaxNode> {
 }
 
 /// Find a direct child attrset by name (not nested deeper)
-fn find_direct_attrset(parent_attrset: &SyntaxNode, name: &str) -> Option<SyntaxNode> {
+pub(crate) fn find_direct_attrset(parent_attrset: &SyntaxNode, name: &str) -> Option<SyntaxNode> {
     for child in parent_attrset.children() {
         if child.kind() == SyntaxKind::NODE_ATTRPATH_VALUE {
             // Check if this entry has the name we're looking for
@@ -305,7 +752,7 @@ fn find_direct_attrset(parent_attrset: &SyntaxNode, name: &str) -> Option<Syntax
 }
 
 /// Get the name from an ATTRPATH_VALUE node
-fn get_attrpath_name(node: &SyntaxNode) -> Option<String> {
+pub(crate) fn get_attrpath_name(node: &SyntaxNode) -> Option<String> {
     for child in node.children() {
         if child.kind() == SyntaxKind::NODE_ATTRPATH {
             // Get all identifiers/strings in the path
@@ -334,7 +781,7 @@ fn get_attrpath_name(node: &SyntaxNode) -> Option<String> {
 }
 
 /// Parse an ATTRPATH_VALUE entry and extract name and properties
-fn parse_attrset_entry(node: &SyntaxNode) -> Option<(String, HashMap<String, String>)> {
+pub(crate) fn parse_attrset_entry(node: &SyntaxNode) -> Option<(String, HashMap<String, String>)> {
     let name = get_attrpath_name(node)?;
     let mut props = HashMap::new();
 
@@ -358,7 +805,7 @@ fn parse_attrset_entry(node: &SyntaxNode) -> Option<(String, HashMap<String, Str
 }
 
 /// Get the value from an ATTRPATH_VALUE node
-fn get_attrvalue(node: &SyntaxNode) -> Option<String> {
+pub(crate) fn get_attrvalue(node: &SyntaxNode) -> Option<String> {
     for child in node.children() {
         match child.kind() {
             SyntaxKind::NODE_STRING => {
@@ -376,7 +823,7 @@ fn get_attrvalue(node: &SyntaxNode) -> Option<String> {
 
 /// Get list of system users
 pub fn get_system_users() -> Vec<String> {
-    let output = Command::new("sh")
+    let output = command("sh")
         .arg("-c")
         .arg("cut -d: -f1 /etc/passwd | sort")
         .output();
@@ -393,7 +840,7 @@ pub fn get_system_users() -> Vec<String> {
 
 /// Get list of system groups
 pub fn get_system_groups() -> Vec<String> {
-    let output = Command::new("sh")
+    let output = command("sh")
         .arg("-c")
         .arg("cut -d: -f1 /etc/group | sort")
         .output();
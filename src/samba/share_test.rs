@@ -0,0 +1,213 @@
+use crate::samba::neighborhood::open_share;
+use crate::samba::share_browser::split_remote_path;
+use std::fs;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Port of SMB over TCP
+const SMB_PORT: u16 = 445;
+
+/// Time allowed for connecting and for each network read or write
+const NETWORK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// SMB2 dialects offered during negotiation. SMB 3.1.1 is left out,
+/// since it requires negotiate contexts that a plain probe doesn't need.
+const DIALECTS: [u16; 4] = [0x0202, 0x0210, 0x0300, 0x0302];
+
+/// Size of the file written and read back on the mounted share
+const ROUND_TRIP_SIZE: usize = 64 * 1024;
+
+/// Largest negotiate response accepted, far above what servers send
+const MAX_RESPONSE: usize = 64 * 1024;
+
+/// Run `step` and measure how long it took
+pub fn timed<T>(step: impl FnOnce() -> Result<T, String>) -> (Duration, Result<T, String>) {
+    let start = Instant::now();
+    let result = step();
+    (start.elapsed(), result)
+}
+
+/// Resolve the server name to the addresses of its SMB port
+pub fn resolve(server: &str) -> Result<Vec<SocketAddr>, String> {
+    let addresses: Vec<SocketAddr> = (server, SMB_PORT)
+        .to_socket_addrs()
+        .map_err(|e| format!("Cannot resolve {}: {}", server, e))?
+        .collect();
+    if addresses.is_empty() {
+        return Err(format!("{} has no address", server));
+    }
+    Ok(addresses)
+}
+
+/// Open a TCP connection to the first address that answers
+pub fn connect(addresses: &[SocketAddr]) -> Result<TcpStream, String> {
+    let mut last_error = String::from("no address to connect to");
+    for address in addresses {
+        match TcpStream::connect_timeout(address, NETWORK_TIMEOUT) {
+            Ok(stream) => {
+                let _ = stream.set_read_timeout(Some(NETWORK_TIMEOUT));
+                let _ = stream.set_write_timeout(Some(NETWORK_TIMEOUT));
+                return Ok(stream);
+            }
+            Err(e) => last_error = format!("{}: {}", address, e),
+        }
+    }
+    Err(last_error)
+}
+
+/// Send an SMB2 NEGOTIATE request and return the dialect the server picked
+pub fn negotiate(stream: &mut TcpStream) -> Result<u16, String> {
+    stream
+        .write_all(&negotiate_request())
+        .map_err(|e| format!("Failed to send the negotiate request: {}", e))?;
+
+    // Direct TCP transport: one zero byte, then the message length on 3 bytes
+    let mut header = [0u8; 4];
+    stream
+        .read_exact(&mut header)
+        .map_err(|e| format!("No SMB2 answer, the server may only speak SMB1: {}", e))?;
+    let length = u32::from_be_bytes(header) as usize;
+    if length > MAX_RESPONSE {
+        return Err(format!("Unexpected answer of {} bytes", length));
+    }
+
+    let mut response = vec![0u8; length];
+    stream
+        .read_exact(&mut response)
+        .map_err(|e| format!("Incomplete negotiate answer: {}", e))?;
+    parse_negotiate_response(&response)
+}
+
+/// Human name of an SMB2 dialect number
+pub fn dialect_name(dialect: u16) -> String {
+    match dialect {
+        0x0202 => "SMB 2.0.2".to_string(),
+        0x0210 => "SMB 2.1".to_string(),
+        0x0300 => "SMB 3.0".to_string(),
+        0x0302 => "SMB 3.0.2".to_string(),
+        0x0311 => "SMB 3.1.1".to_string(),
+        other => format!("0x{:04x}", other),
+    }
+}
+
+/// Check a share before it is configured: the server answers on the SMB port and
+/// the credentials file opens `remote_path`. Returns the dialect the server picked.
+pub fn test_connection(remote_path: &str, credentials: &str) -> Result<u16, String> {
+    let (share, _) = split_remote_path(remote_path.trim())
+        .ok_or_else(|| "Remote path must be like //server/share".to_string())?;
+    let server = share.trim_start_matches('/').split('/').next().unwrap_or_default();
+
+    let mut stream = resolve(server)
+        .and_then(|addresses| connect(&addresses))
+        .map_err(|e| format!("{} doesn't answer on port {}: {}", server, SMB_PORT, e))?;
+    let dialect = negotiate(&mut stream)?;
+    drop(stream);
+
+    open_share(&share, credentials.trim()).map_err(|e| format!("Cannot open {}: {}", share, e))?;
+    Ok(dialect)
+}
+
+/// Write a small file on the mounted share, read it back and remove it.
+/// Returns the number of bytes checked.
+pub fn read_write_round_trip(mount_point: &Path) -> Result<usize, String> {
+    let path = mount_point.join(format!(".samba-share-test-{}", std::process::id()));
+    let content: Vec<u8> = (0..ROUND_TRIP_SIZE).map(|i| (i % 251) as u8).collect();
+
+    let result = (|| -> Result<usize, String> {
+        let mut file = fs::File::create(&path).map_err(|e| format!("Failed to create a test file: {}", e))?;
+        file.write_all(&content)
+            .and_then(|_| file.sync_all())
+            .map_err(|e| format!("Failed to write the test file: {}", e))?;
+
+        let read = fs::read(&path).map_err(|e| format!("Failed to read the test file back: {}", e))?;
+        if read != content {
+            return Err("The test file read back differs from what was written".to_string());
+        }
+        Ok(content.len())
+    })();
+
+    let _ = fs::remove_file(&path);
+    result
+}
+
+/// NEGOTIATE request with its direct TCP transport header
+fn negotiate_request() -> Vec<u8> {
+    let mut message = Vec::with_capacity(64 + 36 + DIALECTS.len() * 2);
+
+    // SMB2 header
+    message.extend_from_slice(b"\xfeSMB");
+    message.extend_from_slice(&64u16.to_le_bytes()); // structure size
+    message.extend_from_slice(&0u16.to_le_bytes()); // credit charge
+    message.extend_from_slice(&0u32.to_le_bytes()); // status
+    message.extend_from_slice(&0u16.to_le_bytes()); // command: NEGOTIATE
+    message.extend_from_slice(&1u16.to_le_bytes()); // credits requested
+    message.extend_from_slice(&0u32.to_le_bytes()); // flags
+    message.extend_from_slice(&0u32.to_le_bytes()); // next command
+    message.extend_from_slice(&0u64.to_le_bytes()); // message id
+    message.extend_from_slice(&0u32.to_le_bytes()); // process id
+    message.extend_from_slice(&0u32.to_le_bytes()); // tree id
+    message.extend_from_slice(&0u64.to_le_bytes()); // session id
+    message.extend_from_slice(&[0u8; 16]); // signature
+
+    // NEGOTIATE request
+    message.extend_from_slice(&36u16.to_le_bytes()); // structure size
+    message.extend_from_slice(&(DIALECTS.len() as u16).to_le_bytes());
+    message.extend_from_slice(&1u16.to_le_bytes()); // security mode: signing enabled
+    message.extend_from_slice(&0u16.to_le_bytes()); // reserved
+    message.extend_from_slice(&0u32.to_le_bytes()); // capabilities
+    message.extend_from_slice(&[0u8; 16]); // client guid
+    message.extend_from_slice(&0u64.to_le_bytes()); // client start time
+    for dialect in DIALECTS {
+        message.extend_from_slice(&dialect.to_le_bytes());
+    }
+
+    let mut packet = (message.len() as u32).to_be_bytes().to_vec();
+    packet.extend(message);
+    packet
+}
+
+/// Dialect picked in a NEGOTIATE response, without its transport header
+fn parse_negotiate_response(response: &[u8]) -> Result<u16, String> {
+    if response.starts_with(b"\xffSMB") {
+        return Err("The server answered with SMB1 only".to_string());
+    }
+    if !response.starts_with(b"\xfeSMB") || response.len() < 64 + 6 {
+        return Err("The server didn't answer with SMB2".to_string());
+    }
+
+    let status = u32::from_le_bytes([response[8], response[9], response[10], response[11]]);
+    if status != 0 {
+        return Err(format!("Negotiation refused with status 0x{:08x}", status));
+    }
+    Ok(u16::from_le_bytes([response[68], response[69]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negotiate_request() {
+        let packet = negotiate_request();
+        assert_eq!(packet.len(), 4 + 64 + 36 + DIALECTS.len() * 2);
+        assert_eq!(u32::from_be_bytes([packet[0], packet[1], packet[2], packet[3]]) as usize, packet.len() - 4);
+        assert_eq!(&packet[4..8], b"\xfeSMB");
+    }
+
+    #[test]
+    fn test_parse_negotiate_response() {
+        let mut response = b"\xfeSMB".to_vec();
+        response.extend_from_slice(&[0u8; 60]);
+        response.extend_from_slice(&65u16.to_le_bytes());
+        response.extend_from_slice(&1u16.to_le_bytes());
+        response.extend_from_slice(&0x0302u16.to_le_bytes());
+        assert_eq!(parse_negotiate_response(&response), Ok(0x0302));
+        assert_eq!(dialect_name(0x0302), "SMB 3.0.2");
+
+        response[8] = 0x22;
+        assert!(parse_negotiate_response(&response).is_err());
+        assert!(parse_negotiate_response(b"\xffSMBr").is_err());
+    }
+}
@@ -0,0 +1,360 @@
+use crate::samba::config_change::ConfigChange;
+use crate::samba::homes::HOMES;
+use crate::samba::sandbox::host_path;
+use crate::samba::server_template::ServerTemplate;
+use crate::samba::share_backend::{BackendKind, ShareBackend};
+use crate::samba::share_config::{yes_no, SambaShareConfig};
+use crate::samba::sudo_write::read_file;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Samba configuration file of most distributions
+pub const DEFAULT_SMB_CONF: &str = "/etc/samba/smb.conf";
+
+/// Sections configuring the server rather than a share
+const SPECIAL_SECTIONS: [&str; 4] = ["global", HOMES, "printers", "print$"];
+
+/// Other spellings Samba accepts for the settings the app reads, with whether
+/// the boolean means the opposite
+const SYNONYMS: [(&str, &str, bool); 7] = [
+    ("browsable", "browseable", false),
+    ("writeable", "read only", true),
+    ("writable", "read only", true),
+    ("write ok", "read only", true),
+    ("public", "guest ok", false),
+    ("create mode", "create mask", false),
+    ("directory mode", "directory mask", false),
+];
+
+/// Script run as root after saving: systemd reads the mounts of /etc/fstab again, then a
+/// running Samba server reloads its configuration
+const APPLY_SCRIPT: &str = "systemctl daemon-reload && systemctl start remote-fs.target \
+&& { ! pidof smbd >/dev/null || smbcontrol smbd reload-config; }";
+
+/// Indentation of the settings written by the app, the one testparm uses
+const INDENT: &str = "   ";
+
+/// Shares in the sections of smb.conf, for systems configuring Samba without NixOS
+pub struct SmbConfBackend {
+    path: String,
+}
+
+impl Default for SmbConfBackend {
+    fn default() -> Self {
+        Self {
+            path: DEFAULT_SMB_CONF.to_string(),
+        }
+    }
+}
+
+impl SmbConfBackend {
+    /// Content of the file, empty when Samba isn't configured yet
+    fn read(&self) -> Result<String, String> {
+        if !host_path(Path::new(&self.path)).exists() {
+            return Ok(String::new());
+        }
+        read_file(&self.path)
+    }
+}
+
+impl ShareBackend for SmbConfBackend {
+    fn kind(&self) -> BackendKind {
+        BackendKind::SmbConf
+    }
+
+    fn description(&self) -> String {
+        self.path.clone()
+    }
+
+    fn load_all(&self) -> Result<Vec<SambaShareConfig>, String> {
+        Ok(parse_shares(&self.read()?, &self.path))
+    }
+
+    fn prepare_write(&self, share: &SambaShareConfig, template: &ServerTemplate) -> Result<ConfigChange, String> {
+        let content = self.read()?;
+        if parse_shares(&content, &self.path)
+            .iter()
+            .any(|existing| existing.name.eq_ignore_ascii_case(&share.name))
+        {
+            return Err(format!("Share '{}' already exists", share.name));
+        }
+
+        // Set the server up along with its first share
+        let mut new_content = content.clone();
+        if find_section(&content, "global").is_none() {
+            template.validate()?;
+            new_content = add_section(&new_content, &section_lines("global", &template.global_settings()));
+        }
+        new_content = add_section(&new_content, &section_lines(&share.name, &share_settings(share)));
+        Ok(ConfigChange::new(self.path.clone(), content, new_content))
+    }
+
+    /// Settings the app doesn't write are removed, comments stay where they are
+    fn prepare_update(&self, share: &SambaShareConfig, old_name: &str) -> Result<ConfigChange, String> {
+        let content = read_file(&share.source_file)?;
+        let new_content = replace_section(&content, old_name, &share.name, &share_settings(share))
+            .ok_or_else(|| format!("Share '{}' not found in {}", old_name, share.source_file))?;
+        Ok(ConfigChange::new(share.source_file.clone(), content, new_content))
+    }
+
+    fn prepare_delete(&self, share: &SambaShareConfig) -> Result<ConfigChange, String> {
+        let content = read_file(&share.source_file)?;
+        let new_content = remove_section(&content, &share.name)
+            .ok_or_else(|| format!("Share '{}' not found in {}", share.name, share.source_file))?;
+        Ok(ConfigChange::new(share.source_file.clone(), content, new_content))
+    }
+
+    fn apply_command(&self) -> &'static [&'static str] {
+        &["/bin/sh", "-c", APPLY_SCRIPT]
+    }
+}
+
+/// Lines of one `[name]` section, from its header to its last setting. Comments and
+/// blank lines after the last setting belong to the next section.
+struct Section {
+    name: String,
+    header: usize,
+    end: usize,
+}
+
+fn sections(lines: &[&str]) -> Vec<Section> {
+    let mut sections: Vec<Section> = Vec::new();
+    for (index, line) in lines.iter().enumerate() {
+        let line = line.trim();
+        if let Some(name) = line.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+            sections.push(Section {
+                name: name.trim().to_string(),
+                header: index,
+                end: index + 1,
+            });
+        } else if let Some(section) = sections.last_mut().filter(|_| setting(line).is_some()) {
+            section.end = index + 1;
+        }
+    }
+    sections
+}
+
+fn find_section(content: &str, name: &str) -> Option<Section> {
+    let lines: Vec<&str> = content.lines().collect();
+    sections(&lines)
+        .into_iter()
+        .find(|section| section.name.eq_ignore_ascii_case(name))
+}
+
+/// Key and value of a `key = value` line, the key lowercased with single spaces as
+/// Samba compares them, and its synonyms replaced by the name the app reads
+fn setting(line: &str) -> Option<(String, String)> {
+    let line = line.trim();
+    if line.starts_with(['#', ';', '[']) {
+        return None;
+    }
+    let (key, value) = line.split_once('=')?;
+    let key = key.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+    let value = value.trim();
+    let value = match value.to_lowercase().as_str() {
+        "yes" | "true" => "yes".to_string(),
+        "no" | "false" => "no".to_string(),
+        _ => value.to_string(),
+    };
+
+    match SYNONYMS.iter().find(|(synonym, _, _)| *synonym == key) {
+        Some((_, name, true)) => Some((name.to_string(), yes_no(value != "yes").to_string())),
+        Some((_, name, false)) => Some((name.to_string(), value)),
+        None => Some((key, value)),
+    }
+}
+
+/// Shares of an smb.conf file, special sections left out
+fn parse_shares(content: &str, source_file: &str) -> Vec<SambaShareConfig> {
+    let lines: Vec<&str> = content.lines().collect();
+    sections(&lines)
+        .into_iter()
+        .filter(|section| !SPECIAL_SECTIONS.iter().any(|special| section.name.eq_ignore_ascii_case(special)))
+        .map(|section| {
+            let props: HashMap<String, String> = lines[section.header + 1..section.end]
+                .iter()
+                .filter_map(|line| setting(line))
+                .collect();
+            let mut share = SambaShareConfig::from_props(section.name, &props);
+            share.source_file = source_file.to_string();
+            share
+        })
+        .collect()
+}
+
+/// Every setting of a share section, the basic ones first
+fn share_settings(share: &SambaShareConfig) -> Vec<(&'static str, String)> {
+    let mut settings = vec![
+        ("path", share.path.clone()),
+        ("browseable", yes_no(share.browsable).to_string()),
+        ("read only", yes_no(share.read_only).to_string()),
+        ("guest ok", yes_no(share.guest_ok).to_string()),
+    ];
+    if !share.force_user.is_empty() {
+        settings.push(("force user", share.force_user.clone()));
+    }
+    if !share.force_group.is_empty() {
+        settings.push(("force group", share.force_group.clone()));
+    }
+    settings.extend(share.other_settings());
+    settings
+}
+
+fn setting_line(key: &str, value: &str) -> String {
+    format!("{}{} = {}", INDENT, key, value)
+}
+
+fn section_lines(name: &str, settings: &[(&'static str, String)]) -> Vec<String> {
+    std::iter::once(format!("[{}]", name))
+        .chain(settings.iter().map(|(key, value)| setting_line(key, value)))
+        .collect()
+}
+
+/// `content` with a section added at the end, after a blank line
+fn add_section(content: &str, section: &[String]) -> String {
+    let content = content.trim_end();
+    let separator = if content.is_empty() { "" } else { "\n\n" };
+    format!("{}{}{}\n", content, separator, section.join("\n"))
+}
+
+/// `content` with the section `old_name` renamed to `name` and holding `settings`.
+/// Changed values are rewritten on their line, None when there is no such section.
+fn replace_section(content: &str, old_name: &str, name: &str, settings: &[(&'static str, String)]) -> Option<String> {
+    let lines: Vec<&str> = content.lines().collect();
+    let section = sections(&lines)
+        .into_iter()
+        .find(|section| section.name.eq_ignore_ascii_case(old_name))?;
+
+    let mut written: Vec<&str> = Vec::new();
+    let mut body: Vec<String> = Vec::new();
+    for line in &lines[section.header + 1..section.end] {
+        let Some((key, value)) = setting(line) else {
+            body.push(line.to_string());
+            continue;
+        };
+        let new = settings.iter().find(|(new_key, _)| *new_key == key);
+        match new {
+            Some((new_key, new_value)) if !written.contains(new_key) => {
+                written.push(new_key);
+                if *new_value == value {
+                    body.push(line.to_string());
+                } else {
+                    body.push(setting_line(new_key, new_value));
+                }
+            }
+            // Settings the share no longer has, and repeated ones Samba would ignore
+            _ => {}
+        }
+    }
+    for (key, value) in settings.iter().filter(|(key, _)| !written.contains(key)) {
+        body.push(setting_line(key, value));
+    }
+
+    let header = if section.name == name {
+        lines[section.header].to_string()
+    } else {
+        format!("[{}]", name)
+    };
+    let mut new_lines: Vec<String> = lines[..section.header].iter().map(|line| line.to_string()).collect();
+    new_lines.push(header);
+    new_lines.extend(body);
+    new_lines.extend(lines[section.end..].iter().map(|line| line.to_string()));
+    Some(new_lines.join("\n") + "\n")
+}
+
+/// `content` without the section `name` and the blank lines after it,
+/// None when there is no such section
+fn remove_section(content: &str, name: &str) -> Option<String> {
+    let lines: Vec<&str> = content.lines().collect();
+    let section = sections(&lines)
+        .into_iter()
+        .find(|section| section.name.eq_ignore_ascii_case(name))?;
+
+    let end = section.end
+        + lines[section.end..]
+            .iter()
+            .take_while(|line| line.trim().is_empty())
+            .count();
+    let new_lines: Vec<&str> = lines[..section.header]
+        .iter()
+        .chain(&lines[end..])
+        .copied()
+        .collect();
+    Some(new_lines.join("\n") + "\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = "[global]
+   workgroup = WORKGROUP
+
+# Family photos
+[Photos]
+   path = /srv/photos
+   writable = yes
+   public = Yes
+   ; kept from the distribution
+   comment = Pictures
+   create mode = 0644
+
+[printers]
+   path = /var/spool/samba
+";
+
+    #[test]
+    fn test_parse_shares() {
+        let shares = parse_shares(CONFIG, DEFAULT_SMB_CONF);
+        assert_eq!(shares.len(), 1);
+        let photos = &shares[0];
+        assert_eq!(photos.name, "Photos");
+        assert_eq!(photos.path, "/srv/photos");
+        assert!(!photos.read_only);
+        assert!(photos.guest_ok);
+        assert!(photos.browsable);
+        assert_eq!(photos.create_mask, "0644");
+        assert_eq!(photos.source_file, DEFAULT_SMB_CONF);
+    }
+
+    #[test]
+    fn test_replace_section() {
+        let settings = vec![
+            ("path", "/srv/pictures".to_string()),
+            ("read only", "no".to_string()),
+            ("guest ok", "no".to_string()),
+        ];
+        assert_eq!(
+            replace_section(CONFIG, "photos", "Pictures", &settings).as_deref(),
+            Some(
+                "[global]
+   workgroup = WORKGROUP
+
+# Family photos
+[Pictures]
+   path = /srv/pictures
+   writable = yes
+   guest ok = no
+   ; kept from the distribution
+
+[printers]
+   path = /var/spool/samba
+"
+            )
+        );
+        assert_eq!(replace_section(CONFIG, "music", "music", &settings), None);
+    }
+
+    #[test]
+    fn test_add_and_remove_section() {
+        let section = section_lines("music", &[("path", "/srv/music".to_string())]);
+        let added = add_section(CONFIG, &section);
+        assert!(added.ends_with("   path = /var/spool/samba\n\n[music]\n   path = /srv/music\n"));
+        assert_eq!(add_section("", &section), "[music]\n   path = /srv/music\n");
+
+        assert_eq!(
+            remove_section(CONFIG, "Photos").as_deref(),
+            Some("[global]\n   workgroup = WORKGROUP\n\n# Family photos\n[printers]\n   path = /var/spool/samba\n")
+        );
+    }
+}
@@ -0,0 +1,22 @@
+use std::net::{SocketAddr, TcpStream};
+use std::time::Duration;
+
+/// Address Samba queries when `spotlight backend = elasticsearch`
+/// and `elasticsearch:address` / `elasticsearch:port` are left at their defaults
+const ELASTICSEARCH_ADDRESS: &str = "127.0.0.1:9200";
+
+/// Check that the Elasticsearch indexing backend used by Spotlight is reachable
+pub fn check_backend() -> Result<(), String> {
+    let addr: SocketAddr = ELASTICSEARCH_ADDRESS
+        .parse()
+        .map_err(|e| format!("Invalid Elasticsearch address: {}", e))?;
+
+    TcpStream::connect_timeout(&addr, Duration::from_secs(2))
+        .map(|_| ())
+        .map_err(|e| {
+            format!(
+                "Elasticsearch is not reachable on {} ({}). Enable services.elasticsearch and index the shared folders.",
+                ELASTICSEARCH_ADDRESS, e
+            )
+        })
+}
@@ -1,22 +1,36 @@
+use crate::samba::command_runner::command;
+use crate::samba::root::skip_escalation;
+use crate::samba::sandbox::{host_path, shared_temp_dir};
 use std::fs;
-use std::process::Command;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
 
 /// Write content to a file that requires root privileges.
 /// Tries multiple methods for privilege escalation.
 pub fn write_with_sudo(path: &str, content: &str) -> Result<(), String> {
     // First, try to write directly (in case we already have permissions)
-    if fs::write(path, content).is_ok() {
+    let direct = fs::write(path, content);
+    if direct.is_ok() {
         return Ok(());
     }
 
-    // Create a temporary file with the content
-    let temp_path = format!("/tmp/samba_share_config_{}.tmp", std::process::id());
+    // Running as root, there is nobody to escalate to
+    if skip_escalation() {
+        return direct.map_err(|e| format!("Failed to write {}: {}", path, e));
+    }
+
+    // Create a temporary file with the content, where the privileged `cp` can see it
+    let temp_path = shared_temp_dir()
+        .join(format!("samba_share_config_{}.tmp", std::process::id()))
+        .to_string_lossy()
+        .to_string();
 
     fs::write(&temp_path, content)
         .map_err(|e| format!("Failed to write temporary file: {}", e))?;
 
     // Try method 1: NixOS wrapped pkexec (if available)
-    if let Ok(output) = Command::new("/run/wrappers/bin/pkexec")
+    if let Ok(output) = command("/run/wrappers/bin/pkexec")
         .args(["cp", &temp_path, path])
         .output()
     {
@@ -32,7 +46,7 @@ pub fn write_with_sudo(path: &str, content: &str) -> Result<(), String> {
     }
 
     // Try method 2: run0 (systemd's modern privilege escalation, available in systemd 256+)
-    if let Ok(output) = Command::new("run0")
+    if let Ok(output) = command("run0")
         .args(["cp", &temp_path, path])
         .output()
     {
@@ -43,7 +57,7 @@ pub fn write_with_sudo(path: &str, content: &str) -> Result<(), String> {
     }
 
     // Try method 3: Regular pkexec (might work if setuid is configured)
-    if let Ok(output) = Command::new("pkexec")
+    if let Ok(output) = command("pkexec")
         .args(["cp", &temp_path, path])
         .output()
     {
@@ -59,7 +73,7 @@ pub fn write_with_sudo(path: &str, content: &str) -> Result<(), String> {
     }
 
     // Try method 4: sudo (may work if user has NOPASSWD or cached credentials)
-    if let Ok(output) = Command::new("sudo")
+    if let Ok(output) = command("sudo")
         .args(["-n", "cp", &temp_path, path])
         .output()
     {
@@ -83,8 +97,100 @@ pub fn write_with_sudo(path: &str, content: &str) -> Result<(), String> {
     )
 }
 
-/// Read a file (doesn't need sudo, but included for completeness)
-pub fn read_file(path: &str) -> Result<String, String> {
-    fs::read_to_string(path)
-        .map_err(|e| format!("Failed to read {}: {}", path, e))
+/// Run a command with elevated privileges, feeding `input` on stdin when given.
+/// Tries the same escalation methods as `write_with_sudo`, and returns stdout on success.
+pub fn run_privileged(program: &str, args: &[&str], input: Option<&str>) -> Result<String, String> {
+    run_privileged_output(program, args, input).map(|output| String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Like `run_privileged`, returning both outputs of the command on success, as
+/// it may report on stderr even then
+pub fn run_privileged_output(program: &str, args: &[&str], input: Option<&str>) -> Result<Output, String> {
+    // Running as root, the command can run as is
+    if skip_escalation() {
+        let mut direct = command(program);
+        direct.args(args);
+        let output = run_with_input(direct, input).map_err(|e| format!("Failed to run {}: {}", program, e))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+            return Err(format!("{} failed: {}", program, stderr));
+        }
+        return Ok(output);
+    }
+
+    let launchers: [(&str, &[&str]); 4] = [
+        ("/run/wrappers/bin/pkexec", &[]),
+        ("run0", &[]),
+        ("pkexec", &[]),
+        ("sudo", &["-n"]),
+    ];
+
+    let mut last_error = String::new();
+    for (launcher, launcher_args) in launchers {
+        let mut escalated = command(launcher);
+        escalated.args(launcher_args).arg(program).args(args);
+        let Ok(output) = run_with_input(escalated, input) else {
+            continue;
+        };
+        if output.status.success() {
+            return Ok(output);
+        }
+
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        if stderr.contains("dismissed") || stderr.contains("Not authorized") {
+            return Err("Authorization cancelled by user".to_string());
+        }
+
+        // pkexec exits with 126/127 when authorization failed; anything else means
+        // the command itself ran and failed, so don't run it a second time
+        if !matches!(output.status.code(), Some(126) | Some(127)) && launcher != "sudo" {
+            return Err(format!("{} failed: {}", program, stderr));
+        }
+        last_error = stderr;
+    }
+
+    Err(format!(
+        "Failed to run {} with elevated privileges. On NixOS, enable security.polkit.enable = true. {}",
+        program, last_error
+    ))
+}
+
+/// Run `command` to completion, feeding `input` on stdin when given
+pub(crate) fn run_with_input(mut command: Command, input: Option<&str>) -> std::io::Result<Output> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        let _ = stdin.write_all(input.as_bytes());
+    }
+
+    child.wait_with_output()
+}
+
+/// Shell test succeeding when `$1` (or its folder, for a new file) can be written
+/// directly, or when one of the launchers of `write_with_sudo` is available
+const WRITE_ACCESS_CHECK: &str = r#"test -w "$1" || { test ! -e "$1" && test -w "$(dirname "$1")"; } \
+    || test -x /run/wrappers/bin/pkexec || command -v run0 || command -v pkexec || sudo -n true"#;
+
+/// Whether `write_with_sudo` has a chance to write `path`, checked without asking for a password.
+/// A launcher being installed doesn't guarantee the user is allowed to use it.
+pub fn can_write(path: &str) -> bool {
+    command("sh")
+        .args(["-c", WRITE_ACCESS_CHECK, "sh", path])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+/// Read a file (doesn't need sudo, but included for completeness).
+/// Inside a Flatpak sandbox the host copy of the file is read.
+pub fn read_file(path: impl AsRef<Path>) -> Result<String, String> {
+    let path = path.as_ref();
+    fs::read_to_string(host_path(path))
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))
 }
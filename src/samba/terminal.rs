@@ -0,0 +1,94 @@
+use crate::samba::command_runner::command;
+
+/// Placeholder replaced by the script path in terminal command templates
+pub const SCRIPT_PLACEHOLDER: &str = "{script}";
+
+/// Terminals tried in order of preference when none is configured
+pub const KNOWN_TERMINALS: [(&str, &str); 7] = [
+    ("kgx", "kgx -- {script}"), // GNOME Console
+    ("gnome-terminal", "gnome-terminal -- {script}"),
+    ("konsole", "konsole -e {script}"),
+    ("xfce4-terminal", "xfce4-terminal -e {script}"),
+    ("alacritty", "alacritty -e {script}"),
+    ("kitty", "kitty {script}"),
+    ("xterm", "xterm -e {script}"),
+];
+
+/// Known terminals installed on the system, as (name, command template)
+pub fn detect_terminals() -> Vec<(&'static str, &'static str)> {
+    KNOWN_TERMINALS
+        .into_iter()
+        .filter(|(name, _)| is_installed(name))
+        .collect()
+}
+
+/// Open `script` in a terminal, using `template` when set or the known terminals otherwise.
+/// Returns the command that was started, or every command that was tried.
+pub fn open_in_terminal(template: Option<&str>, script: &str) -> Result<String, Vec<String>> {
+    let templates: Vec<&str> = match template {
+        Some(template) => vec![template],
+        None => KNOWN_TERMINALS.iter().map(|(_, template)| *template).collect(),
+    };
+
+    let mut tried = Vec::new();
+    for template in templates {
+        let parts = expand_template(template, script);
+        let Some((program, args)) = parts.split_first() else {
+            continue;
+        };
+
+        eprintln!("Trying {}...", program);
+        let command_line = args_to_string(program, args);
+        match command(program).args(args).spawn() {
+            Ok(_) => return Ok(command_line),
+            Err(e) => tried.push(format!("{} ({})", command_line, e)),
+        }
+    }
+    Err(tried)
+}
+
+/// Split a command template into arguments, replacing the script placeholder.
+/// Without a placeholder the script is appended as the last argument.
+pub fn expand_template(template: &str, script: &str) -> Vec<String> {
+    let mut args: Vec<String> = template
+        .split_whitespace()
+        .map(|arg| arg.replace(SCRIPT_PLACEHOLDER, script))
+        .collect();
+    if !template.contains(SCRIPT_PLACEHOLDER) && !args.is_empty() {
+        args.push(script.to_string());
+    }
+    args
+}
+
+fn args_to_string(program: &str, args: &[String]) -> String {
+    std::iter::once(program)
+        .chain(args.iter().map(String::as_str))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn is_installed(program: &str) -> bool {
+    command("sh")
+        .args(["-c", "command -v \"$1\"", "sh", program])
+        .output()
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_template() {
+        assert_eq!(
+            expand_template("konsole -e {script}", "/tmp/rebuild.sh"),
+            vec!["konsole", "-e", "/tmp/rebuild.sh"]
+        );
+        assert_eq!(
+            expand_template("foot", "/tmp/rebuild.sh"),
+            vec!["foot", "/tmp/rebuild.sh"]
+        );
+        assert!(expand_template("  ", "/tmp/rebuild.sh").is_empty());
+    }
+}
@@ -0,0 +1,241 @@
+use crate::samba::config_change::ConfigChange;
+use crate::samba::paths::config_path;
+use crate::samba::sudo_write::{read_file, run_privileged};
+
+/// Script run as root to prepare a private share. Arguments: user, folder, create ("yes"/"no").
+/// The Samba password is read twice from stdin by `smbpasswd -s`.
+const PROVISION_SCRIPT: &str = r#"set -e
+export PATH=/run/current-system/sw/bin:/run/wrappers/bin:$PATH
+user="$1"
+folder="$2"
+if [ "$3" = yes ]; then
+  useradd --no-create-home --shell /run/current-system/sw/bin/nologin "$user"
+fi
+smbpasswd -s -a "$user"
+mkdir -p "$folder"
+chown "$user": "$folder"
+chmod 0700 "$folder"
+"#;
+
+/// Script run as root to prepare a team folder. Arguments: group, folder,
+/// create ("yes"/"no"), then the users to add to the group.
+/// The setgid bit keeps new files in the group.
+const TEAM_SCRIPT: &str = r#"set -e
+export PATH=/run/current-system/sw/bin:/run/wrappers/bin:$PATH
+group="$1"
+folder="$2"
+create="$3"
+shift 3
+if [ "$create" = yes ]; then
+  groupadd "$group"
+fi
+for member in "$@"; do
+  usermod -a -G "$group" "$member"
+done
+mkdir -p "$folder"
+chgrp "$group" "$folder"
+chmod 2770 "$folder"
+"#;
+
+/// Script run as root listing the Samba users, `name:uid:full name` per line
+const LIST_SCRIPT: &str = r#"export PATH=/run/current-system/sw/bin:/run/wrappers/bin:$PATH
+pdbedit -L
+"#;
+
+/// Script run as root to set the Samba password of `$1`, read twice from stdin.
+/// As root `smbpasswd` doesn't ask for the old password.
+const PASSWORD_SCRIPT: &str = r#"set -e
+export PATH=/run/current-system/sw/bin:/run/wrappers/bin:$PATH
+smbpasswd -s "$1"
+"#;
+
+/// How the group of a team share is obtained
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupCreation {
+    /// The group already exists on the system
+    Existing,
+    /// Created with `groupadd`, kept across rebuilds while `users.mutableUsers` is enabled
+    Imperative,
+    /// Also declared in `users.groups` so the configuration describes it
+    Declarative,
+}
+
+/// Whether `name` is acceptable as a Unix user or group name
+pub fn is_valid_user_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) if first.is_ascii_lowercase() || first == '_' => {}
+        _ => return false,
+    }
+    name.len() <= 32
+        && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-')
+}
+
+/// Users of the Samba password database, sorted by name
+pub fn samba_users() -> Result<Vec<String>, String> {
+    let output = run_privileged("/bin/sh", &["-c", LIST_SCRIPT], None)?;
+    Ok(parse_user_list(&output))
+}
+
+fn parse_user_list(output: &str) -> Vec<String> {
+    let mut users: Vec<String> = output
+        .lines()
+        .filter_map(|line| line.split(':').next())
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect();
+    users.sort();
+    users
+}
+
+/// Set the Samba password of an existing user. The password only goes through
+/// stdin, never on a command line where other processes could read it.
+pub fn change_password(user: &str, password: &str) -> Result<(), String> {
+    if !is_valid_user_name(user) {
+        return Err(format!("'{}' is not a valid user name", user));
+    }
+    if password.is_empty() {
+        return Err("Samba password is required".to_string());
+    }
+    // smbpasswd reads one line per prompt
+    if password.contains(['\n', '\r']) {
+        return Err("The password can't contain line breaks".to_string());
+    }
+
+    let input = format!("{}\n{}\n", password, password);
+    run_privileged("/bin/sh", &["-c", PASSWORD_SCRIPT, "sh", user], Some(&input)).map(|_| ())
+}
+
+/// Prepare a private share for `user` in a single privileged step: create the
+/// system user when `create_user` is set, set their Samba password and create
+/// `folder` owned by them with mode 0700.
+///
+/// The user is created imperatively so the folder can be owned right away;
+/// it only persists across rebuilds when `users.mutableUsers` is enabled (the default).
+pub fn provision_private_share(
+    user: &str,
+    create_user: bool,
+    password: &str,
+    folder: &str,
+) -> Result<(), String> {
+    if !is_valid_user_name(user) {
+        return Err(format!("'{}' is not a valid user name", user));
+    }
+    if password.is_empty() {
+        return Err("Samba password is required".to_string());
+    }
+    if !folder.starts_with('/') {
+        return Err("Folder must be an absolute path".to_string());
+    }
+
+    let create = if create_user { "yes" } else { "no" };
+    let input = format!("{}\n{}\n", password, password);
+    run_privileged(
+        "/bin/sh",
+        &["-c", PROVISION_SCRIPT, "sh", user, folder, create],
+        Some(&input),
+    )
+    .map(|_| ())
+}
+
+/// Prepare a team folder for `group` in a single privileged step: create the
+/// group unless it exists, add `members` to it and create `folder` owned by
+/// the group with mode 2770.
+///
+/// The group is always created imperatively so the folder can be owned right
+/// away. With `GroupCreation::Declarative` it is also added to the configuration,
+/// and the applied change is returned. NixOS keeps the existing GID on rebuild.
+pub fn provision_team_share(
+    group: &str,
+    creation: GroupCreation,
+    members: &[String],
+    folder: &str,
+) -> Result<Option<ConfigChange>, String> {
+    if !is_valid_user_name(group) {
+        return Err(format!("'{}' is not a valid group name", group));
+    }
+    if let Some(member) = members.iter().find(|member| !is_valid_user_name(member)) {
+        return Err(format!("'{}' is not a valid user name", member));
+    }
+    if !folder.starts_with('/') {
+        return Err("Folder must be an absolute path".to_string());
+    }
+
+    // Compute the declaration first so a parse problem stops before anything runs
+    let declaration = match creation {
+        GroupCreation::Declarative => {
+            let content = read_file(config_path())?;
+            let new_content = declare_group(&content, group, members)?;
+            Some(ConfigChange::new(config_path().to_string(), content, new_content))
+        }
+        _ => None,
+    };
+
+    let create = if creation == GroupCreation::Existing { "no" } else { "yes" };
+    let mut args = vec!["-c", TEAM_SCRIPT, "sh", group, folder, create];
+    args.extend(members.iter().map(|member| member.as_str()));
+    run_privileged("/bin/sh", &args, None)?;
+
+    if let Some(change) = &declaration {
+        change.apply()?;
+    }
+    Ok(declaration)
+}
+
+/// Add `users.groups."<group>".members` to a configuration module,
+/// before its closing brace
+fn declare_group(content: &str, group: &str, members: &[String]) -> Result<String, String> {
+    let members: Vec<String> = members.iter().map(|member| format!("\"{}\"", member)).collect();
+    let entry = format!(
+        "users.groups.\"{}\".members = [ {} ];\n\n",
+        group,
+        members.join(" ")
+    );
+
+    let last_brace_pos = content
+        .rfind('}')
+        .ok_or("Could not find insertion point in config file")?;
+    let mut new_content = content.to_string();
+    new_content.insert_str(last_brace_pos, &entry);
+    Ok(new_content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_valid_user_name() {
+        assert!(is_valid_user_name("alice"));
+        assert!(is_valid_user_name("_svc-backup2"));
+        assert!(!is_valid_user_name(""));
+        assert!(!is_valid_user_name("Alice"));
+        assert!(!is_valid_user_name("2alice"));
+        assert!(!is_valid_user_name("alice smith"));
+        assert!(!is_valid_user_name("alice;rm"));
+    }
+
+    #[test]
+    fn test_parse_user_list() {
+        let output = "bob:1001:Bob\nalice:1000:Alice Martin\n\n";
+        assert_eq!(parse_user_list(output), vec!["alice", "bob"]);
+    }
+
+    #[test]
+    fn test_change_password_rejects_line_breaks() {
+        assert!(change_password("alice", "one\ntwo").is_err());
+        assert!(change_password("alice", "").is_err());
+        assert!(change_password("Alice", "secret").is_err());
+    }
+
+    #[test]
+    fn test_declare_group() {
+        let content = "{ config, pkgs, ... }:\n{\n  services.samba.enable = true;\n}\n";
+        let members = vec!["alice".to_string(), "bob".to_string()];
+        assert_eq!(
+            declare_group(content, "team", &members).unwrap(),
+            "{ config, pkgs, ... }:\n{\n  services.samba.enable = true;\nusers.groups.\"team\".members = [ \"alice\" \"bob\" ];\n\n}\n"
+        );
+    }
+}
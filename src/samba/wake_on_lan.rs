@@ -0,0 +1,130 @@
+use crate::samba::command_runner::command;
+use crate::samba::share_test::{connect, resolve};
+use std::net::UdpSocket;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Port magic packets are usually sent to ("discard")
+const WOL_PORT: u16 = 9;
+
+/// Time a server is given to boot and answer on the SMB port after being woken up
+const WAKE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Pause between two connection attempts while waiting for a server
+const RETRY_INTERVAL: Duration = Duration::from_secs(3);
+
+/// Hardware address of a server answering on the SMB port, read from the neighbor table.
+/// None when the server is offline or on another network, which the table doesn't cover.
+pub fn learn_mac_address(server: &str) -> Option<String> {
+    let addresses = resolve(server).ok()?;
+    // The connection fills the neighbor table and tells the server is online
+    let stream = connect(&addresses).ok()?;
+    let address = stream.peer_addr().ok()?.ip();
+    if address.is_loopback() {
+        return None;
+    }
+
+    let output = command("ip")
+        .args(["neigh", "show", &address.to_string()])
+        .output()
+        .ok()?;
+    parse_neighbor(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Send a magic packet to `mac_address` on the local networks
+pub fn wake(mac_address: &str) -> Result<(), String> {
+    let packet = magic_packet(mac_address)?;
+    let socket = UdpSocket::bind("0.0.0.0:0").map_err(|e| format!("Failed to open a socket: {}", e))?;
+    socket
+        .set_broadcast(true)
+        .map_err(|e| format!("Failed to allow broadcasts: {}", e))?;
+    socket
+        .send_to(&packet, ("255.255.255.255", WOL_PORT))
+        .map_err(|e| format!("Failed to send the magic packet: {}", e))?;
+    Ok(())
+}
+
+/// Wait until `server` answers on the SMB port, giving up after `WAKE_TIMEOUT`
+pub fn wait_until_online(server: &str) -> Result<(), String> {
+    let start = Instant::now();
+    loop {
+        let error = match resolve(server).and_then(|addresses| connect(&addresses)) {
+            Ok(_) => return Ok(()),
+            Err(e) => e,
+        };
+        if start.elapsed() >= WAKE_TIMEOUT {
+            return Err(format!(
+                "{} still doesn't answer after {} s: {}",
+                server,
+                WAKE_TIMEOUT.as_secs(),
+                error
+            ));
+        }
+        thread::sleep(RETRY_INTERVAL);
+    }
+}
+
+/// Six bytes of `ff` followed by the hardware address sixteen times
+fn magic_packet(mac_address: &str) -> Result<Vec<u8>, String> {
+    let mac = parse_mac(mac_address).ok_or_else(|| format!("Invalid hardware address '{}'", mac_address))?;
+    let mut packet = vec![0xff; 6];
+    for _ in 0..16 {
+        packet.extend_from_slice(&mac);
+    }
+    Ok(packet)
+}
+
+/// Bytes of a hardware address written `00:11:22:aa:bb:cc` or `00-11-22-AA-BB-CC`
+fn parse_mac(mac_address: &str) -> Option<[u8; 6]> {
+    let parts: Vec<&str> = mac_address.trim().split([':', '-']).collect();
+    if parts.len() != 6 {
+        return None;
+    }
+    let mut mac = [0u8; 6];
+    for (byte, part) in mac.iter_mut().zip(parts) {
+        if part.len() != 2 {
+            return None;
+        }
+        *byte = u8::from_str_radix(part, 16).ok()?;
+    }
+    Some(mac)
+}
+
+/// Hardware address in the output of `ip neigh show <address>`
+fn parse_neighbor(output: &str) -> Option<String> {
+    // "192.168.1.10 dev eth0 lladdr 00:11:22:aa:bb:cc REACHABLE"
+    output.lines().find_map(|line| {
+        let mut fields = line.split_whitespace();
+        fields.find(|field| *field == "lladdr")?;
+        let mac = fields.next()?.to_lowercase();
+        parse_mac(&mac).map(|_| mac)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_magic_packet() {
+        let packet = magic_packet("00-11-22-AA-BB-CC").unwrap();
+        assert_eq!(packet.len(), 102);
+        assert_eq!(&packet[..6], &[0xff; 6]);
+        assert_eq!(&packet[6..12], &[0x00, 0x11, 0x22, 0xaa, 0xbb, 0xcc]);
+        assert_eq!(&packet[96..], &[0x00, 0x11, 0x22, 0xaa, 0xbb, 0xcc]);
+
+        assert!(magic_packet("00:11:22:aa:bb").is_err());
+        assert!(magic_packet("00:11:22:aa:bb:zz").is_err());
+        assert!(magic_packet("0:11:22:aa:bb:cc").is_err());
+    }
+
+    #[test]
+    fn test_parse_neighbor() {
+        assert_eq!(
+            parse_neighbor("192.168.1.10 dev eth0 lladdr 00:11:22:AA:BB:CC REACHABLE\n"),
+            Some("00:11:22:aa:bb:cc".to_string())
+        );
+        assert_eq!(parse_neighbor("192.168.1.10 dev eth0 FAILED\n"), None);
+        assert_eq!(parse_neighbor(""), None);
+    }
+}
@@ -0,0 +1,81 @@
+use crate::samba::global_config::SambaGlobalConfig;
+use crate::samba::share_config::SambaShareConfig;
+
+/// Risky configuration patterns flagged in the UI
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigWarning {
+    /// Guests can connect and the share is writable, for them or anyone
+    GuestWritable,
+    /// Symbolic links may lead outside the shared folder
+    WideLinks,
+    /// `hosts allow` lets every address in
+    HostsAllowAll,
+    /// The obsolete SMB1 protocol is accepted
+    Smb1Enabled,
+}
+
+/// `hosts allow` entries matching every IPv4 or IPv6 address
+const ALL_HOSTS: [&str; 6] = ["ALL", "0.0.0.0", "0.0.0.0/0", "0.0.0.0/0.0.0.0", "::", "::/0"];
+
+/// Warnings raised by the settings of one share
+pub fn share_warnings(share: &SambaShareConfig) -> Vec<ConfigWarning> {
+    let mut warnings = Vec::new();
+    if share.guest_ok && (!share.read_only || !share.write_list.is_empty()) {
+        warnings.push(ConfigWarning::GuestWritable);
+    }
+    if share.follow_symlinks && share.wide_links {
+        warnings.push(ConfigWarning::WideLinks);
+    }
+    warnings
+}
+
+/// Warnings raised by the server-wide settings
+pub fn global_warnings(global: &SambaGlobalConfig) -> Vec<ConfigWarning> {
+    let mut warnings = Vec::new();
+    if global.hosts_allow.iter().any(|host| ALL_HOSTS.contains(&host.as_str())) {
+        warnings.push(ConfigWarning::HostsAllowAll);
+    }
+    if global.smb1_enabled() {
+        warnings.push(ConfigWarning::Smb1Enabled);
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_share_warnings() {
+        let mut share = SambaShareConfig::new(
+            "public".to_string(),
+            "/srv/public".to_string(),
+            true,
+            true,
+            true,
+            String::new(),
+            String::new(),
+        );
+        assert!(share_warnings(&share).is_empty());
+
+        share.read_only = false;
+        share.wide_links = true;
+        assert_eq!(
+            share_warnings(&share),
+            vec![ConfigWarning::GuestWritable, ConfigWarning::WideLinks]
+        );
+    }
+
+    #[test]
+    fn test_global_warnings() {
+        let global = SambaGlobalConfig {
+            server_min_protocol: Some("NT1".to_string()),
+            hosts_allow: vec!["192.168.1.".to_string(), "0.0.0.0/0".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            global_warnings(&global),
+            vec![ConfigWarning::HostsAllowAll, ConfigWarning::Smb1Enabled]
+        );
+    }
+}
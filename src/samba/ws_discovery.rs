@@ -0,0 +1,117 @@
+use crate::samba::neighborhood::NetworkHost;
+use std::net::{Ipv4Addr, UdpSocket};
+use std::process;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Multicast group and port WS-Discovery probes are sent to
+const MULTICAST_ADDRESS: Ipv4Addr = Ipv4Addr::new(239, 255, 255, 250);
+const PORT: u16 = 3702;
+
+/// Time computers are given to answer the probe
+const ANSWER_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Probe asking computers sharing files, Windows ones and Samba servers running wsdd,
+/// to announce themselves. `{message_id}` is replaced for each probe.
+const PROBE: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<soap:Envelope xmlns:soap="http://www.w3.org/2003/05/soap-envelope" xmlns:wsa="http://schemas.xmlsoap.org/ws/2004/08/addressing" xmlns:wsd="http://schemas.xmlsoap.org/ws/2005/04/discovery" xmlns:wsdp="http://schemas.xmlsoap.org/ws/2006/02/devprof" xmlns:pub="http://schemas.microsoft.com/windows/pub/2005/07">
+<soap:Header>
+<wsa:To>urn:schemas-xmlsoap-org:ws:2005:04:discovery</wsa:To>
+<wsa:Action>http://schemas.xmlsoap.org/ws/2005/04/discovery/Probe</wsa:Action>
+<wsa:MessageID>urn:uuid:{message_id}</wsa:MessageID>
+</soap:Header>
+<soap:Body>
+<wsd:Probe><wsd:Types>wsdp:Device pub:Computer</wsd:Types></wsd:Probe>
+</soap:Body>
+</soap:Envelope>"#;
+
+/// Computers answering a WS-Discovery probe, the only announcement recent Windows
+/// versions make. Answers carry no name, so hosts are named after their address.
+pub fn probe_hosts() -> Result<Vec<NetworkHost>, String> {
+    let socket = UdpSocket::bind("0.0.0.0:0").map_err(|e| format!("Failed to open a socket: {}", e))?;
+    socket
+        .set_multicast_ttl_v4(1)
+        .map_err(|e| format!("Failed to limit the probe to the local network: {}", e))?;
+    let probe = PROBE.replace("{message_id}", &message_id());
+    socket
+        .send_to(probe.as_bytes(), (MULTICAST_ADDRESS, PORT))
+        .map_err(|e| format!("Failed to send the WS-Discovery probe: {}", e))?;
+
+    let mut hosts: Vec<NetworkHost> = Vec::new();
+    let mut buffer = [0u8; 65535];
+    let start = Instant::now();
+    while let Some(remaining) = ANSWER_TIMEOUT.checked_sub(start.elapsed()) {
+        if remaining.is_zero() || socket.set_read_timeout(Some(remaining)).is_err() {
+            break;
+        }
+        let Ok((length, sender)) = socket.recv_from(&mut buffer) else {
+            break;
+        };
+        let answer = String::from_utf8_lossy(&buffer[..length]);
+        // Computers without an IPv4 transport address are reached where they answered from
+        let address = match parse_probe_match(&answer) {
+            Some(address) => address,
+            None if is_probe_match(&answer) => sender.ip().to_string(),
+            None => continue,
+        };
+        if !hosts.iter().any(|host| host.address == address) {
+            hosts.push(NetworkHost {
+                name: address.clone(),
+                address,
+                workgroup: None,
+                mac_address: None,
+            });
+        }
+    }
+    Ok(hosts)
+}
+
+/// Unique enough identifier for one probe, in the form of a UUID
+fn message_id() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos())
+        .unwrap_or_default();
+    let hex = format!("{:024x}{:08x}", nanos, process::id());
+    format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32])
+}
+
+fn is_probe_match(answer: &str) -> bool {
+    answer.contains("ProbeMatches")
+}
+
+/// IPv4 address in the transport addresses of a probe answer, such as
+/// `<wsd:XAddrs>http://192.168.1.20:5357/6f3a...</wsd:XAddrs>`
+fn parse_probe_match(answer: &str) -> Option<String> {
+    if !is_probe_match(answer) {
+        return None;
+    }
+    let start = answer.find("XAddrs>")? + "XAddrs>".len();
+    let end = start + answer[start..].find('<')?;
+    answer[start..end].split_whitespace().find_map(|url| {
+        let host = url.split_once("://")?.1.split(['/', ':']).next()?;
+        host.parse::<Ipv4Addr>().ok().map(|address| address.to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_probe_match() {
+        let answer = r#"<soap:Envelope><soap:Body><wsd:ProbeMatches><wsd:ProbeMatch>
+<wsd:Types>wsdp:Device pub:Computer</wsd:Types>
+<wsd:XAddrs>http://[fe80::1]:5357/6f3a http://192.168.1.20:5357/6f3a</wsd:XAddrs>
+</wsd:ProbeMatch></wsd:ProbeMatches></soap:Body></soap:Envelope>"#;
+        assert_eq!(parse_probe_match(answer), Some("192.168.1.20".to_string()));
+        assert_eq!(parse_probe_match("<wsd:Probe><wsd:XAddrs>http://192.168.1.20/</wsd:XAddrs>"), None);
+        assert!(is_probe_match("<wsd:ProbeMatches></wsd:ProbeMatches>"));
+    }
+
+    #[test]
+    fn test_message_id() {
+        let id = message_id();
+        assert_eq!(id.len(), 36);
+        assert_eq!(id.matches('-').count(), 4);
+    }
+}
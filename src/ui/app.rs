@@ -1,10 +1,14 @@
 use crate::config::AppConfig;
+use crate::samba::demo::DEMO_FLAG;
+use crate::samba::paths::{config_path, split_config_flag};
+use crate::samba::sudo_write::read_file;
+use crate::ui::background;
+use crate::ui::widgets::load_share_colors;
 use crate::ui::window::SambaShareManagerWindow;
 use gtk4::prelude::*;
 use gtk4::{glib, gio};
 use libadwaita as adw;
 use std::cell::RefCell;
-use std::fs;
 use std::path::PathBuf;
 use std::rc::Rc;
 
@@ -29,7 +33,7 @@ impl SambaShareManagerApp {
         glib::set_application_name("samba-share");
         glib::set_prgname(Some("samba-share"));
 
-        let hardware_config_file = PathBuf::from("/etc/nixos/customConfig/default.nix");
+        let hardware_config_file = PathBuf::from(config_path());
         let hardware_config = Rc::new(RefCell::new(String::new()));
         let must_save = Rc::new(RefCell::new(false));
         let windows: Rc<RefCell<Vec<adw::ApplicationWindow>>> = Rc::new(RefCell::new(Vec::new()));
@@ -52,6 +56,12 @@ impl SambaShareManagerApp {
         let must_save_clone = must_save.clone();
         let windows_clone = windows.clone();
 
+        // Background mode and the notification actions only live in the primary instance
+        app.connect_startup(|app| {
+            background::setup(app);
+            load_share_colors();
+        });
+
         app.connect_activate(move |app| {
             Self::on_activate(
                 app,
@@ -73,7 +83,7 @@ impl SambaShareManagerApp {
         windows: &Rc<RefCell<Vec<adw::ApplicationWindow>>>,
     ) {
         // Load hardware configuration
-        if let Ok(config) = fs::read_to_string(config_file) {
+        if let Ok(config) = read_file(config_file) {
             *hardware_config.borrow_mut() = config;
         } else {
             eprintln!("Failed to read hardware configuration file");
@@ -99,6 +109,9 @@ impl SambaShareManagerApp {
     }
 
     pub fn run(&self) -> i32 {
-        self.app.run().into()
+        // GApplication rejects options it doesn't know, so drop the ones handled in main
+        let args: Vec<String> = std::env::args().filter(|arg| arg != DEMO_FLAG).collect();
+        let (_, args) = split_config_flag(&args);
+        self.app.run_with_args(&args).into()
     }
 }
\ No newline at end of file
@@ -0,0 +1,176 @@
+use crate::config::{AppConfig, Favorite};
+use crate::models::AppState;
+use crate::samba::{remount_share, unmount_share};
+use gettextrs::gettext;
+use gtk4::prelude::*;
+use gtk4::{gio, glib};
+use libadwaita as adw;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Seconds between two checks of the mounts while no window is open
+const POLL_INTERVAL: u32 = 30;
+
+thread_local! {
+    /// Keeps the application running once its last window is closed
+    static HOLD: RefCell<Option<gio::ApplicationHoldGuard>> = const { RefCell::new(None) };
+    /// Mount state of the favorite remote shares at the last check
+    static MOUNTED: RefCell<HashMap<String, bool>> = RefCell::new(HashMap::new());
+}
+
+/// Register the actions used by notifications and start following the mounts.
+/// Background mode itself only starts if it is enabled in the preferences.
+pub fn setup(app: &adw::Application) {
+    for (name, mount) in [("mount-favorite", true), ("unmount-favorite", false)] {
+        let action = gio::SimpleAction::new(name, Some(glib::VariantTy::STRING));
+        action.connect_activate(move |_, target| {
+            if let Some(target) = target.and_then(|target| target.get::<String>()) {
+                toggle_mount(PathBuf::from(target), mount);
+            }
+        });
+        app.add_action(&action);
+    }
+
+    let app_clone = app.clone();
+    AppState::get().connect_remote_shares_changed(move |state| {
+        notify_mount_changes(&app_clone, state);
+    });
+
+    // Mounts change outside of the app too, e.g. when the network goes away
+    let app_clone = app.clone();
+    glib::timeout_add_seconds_local(POLL_INTERVAL, move || {
+        if is_enabled() && app_clone.active_window().is_none() {
+            AppState::get().reload_remote();
+        }
+        glib::ControlFlow::Continue
+    });
+
+    set_enabled(app, AppConfig::new().run_in_background());
+}
+
+/// Whether the application keeps running without a window
+pub fn is_enabled() -> bool {
+    HOLD.with(|hold| hold.borrow().is_some())
+}
+
+/// Start or stop running in the background
+pub fn set_enabled(app: &adw::Application, enabled: bool) {
+    if enabled == is_enabled() {
+        return;
+    }
+
+    if enabled {
+        HOLD.with(|hold| hold.replace(Some(app.hold())));
+        glib::spawn_future_local(async {
+            if let Err(e) = request_background().await {
+                eprintln!("Failed to request running in the background: {}", e);
+            }
+        });
+    } else {
+        HOLD.with(|hold| hold.replace(None));
+    }
+}
+
+/// Ask the background portal to let the app run without a window, so the desktop
+/// doesn't stop it and lists it among background apps
+async fn request_background() -> Result<(), glib::Error> {
+    let connection = gio::bus_get_future(gio::BusType::Session).await?;
+
+    let options = glib::VariantDict::new(None);
+    options.insert("reason", gettext("Watch favorite shares and notify when they are mounted or unmounted"));
+    options.insert("autostart", false);
+    let parameters = glib::Variant::tuple_from_iter(["".to_variant(), options.end()]);
+
+    connection
+        .call_future(
+            Some("org.freedesktop.portal.Desktop"),
+            "/org/freedesktop/portal/desktop",
+            "org.freedesktop.portal.Background",
+            "RequestBackground",
+            Some(&parameters),
+            None,
+            gio::DBusCallFlags::NONE,
+            -1,
+        )
+        .await
+        .map(|_| ())
+}
+
+/// Notify about favorite remote shares mounted or unmounted since the last check,
+/// only while no window shows it already
+fn notify_mount_changes(app: &adw::Application, state: &AppState) {
+    let Some(Ok(shares)) = state.remote_shares() else {
+        return;
+    };
+    let favorites = AppConfig::new().favorites();
+
+    for share in &shares {
+        if !favorites.contains(&Favorite::Remote(share.target.clone())) {
+            continue;
+        }
+
+        let previous = MOUNTED.with(|mounted| {
+            mounted
+                .borrow_mut()
+                .insert(share.target.clone(), share.is_mounted)
+        });
+        let changed = previous.is_some_and(|was_mounted| was_mounted != share.is_mounted);
+        if !changed || !is_enabled() || app.active_window().is_some() {
+            continue;
+        }
+
+        let notification = if share.is_mounted {
+            let notification = gio::Notification::new(&gettext("Share mounted"));
+            notification.add_button_with_target_value(
+                &gettext("Unmount"),
+                "app.unmount-favorite",
+                Some(&share.target.to_variant()),
+            );
+            notification
+        } else {
+            let notification = gio::Notification::new(&gettext("Share unmounted"));
+            if share.config.is_some() {
+                notification.add_button_with_target_value(
+                    &gettext("Mount"),
+                    "app.mount-favorite",
+                    Some(&share.target.to_variant()),
+                );
+            }
+            notification
+        };
+        notification.set_body(Some(&format!("{} ({})", share.target, share.source)));
+        app.send_notification(Some(&format!("mount-{}", share.target)), &notification);
+    }
+}
+
+/// Mount or unmount a favorite from a notification, reporting failures the same way
+fn toggle_mount(target: PathBuf, mount: bool) {
+    glib::spawn_future_local(async move {
+        let target_clone = target.clone();
+        let result = gio::spawn_blocking(move || {
+            if mount {
+                remount_share(&target_clone)
+            } else {
+                unmount_share(&target_clone)
+            }
+        })
+        .await
+        .unwrap_or_else(|e| Err(format!("{:?}", e)));
+
+        if let Err(e) = result {
+            eprintln!("Failed to change the mount of {}: {}", target.display(), e);
+            let failure = if mount {
+                gettext("Mount failed")
+            } else {
+                gettext("Unmount failed")
+            };
+            let notification = gio::Notification::new(&failure);
+            notification.set_body(Some(&e));
+            if let Some(app) = gio::Application::default() {
+                app.send_notification(None, &notification);
+            }
+        }
+        AppState::get().reload_remote();
+    });
+}
@@ -1,19 +1,37 @@
-use crate::samba::remote_share_config::RemoteSambaShareConfig;
+use crate::config::AppConfig;
+use crate::models::AppState;
+use crate::samba::mount_defaults::SystemdMount;
+use crate::samba::remote_share_config::{MountBackend, RemoteSambaShareConfig};
+use crate::samba::share_browser::split_remote_path;
+use crate::ui::dialogs::{CredentialsFileDialog, ShareBrowserDialog};
+use crate::ui::widgets::{
+    browse_file, connection_test_button, convert_unc_paths, fill_from_clipboard, idle_timeout_combo,
+    mount_backend_combo, selected_idle_timeout, selected_mount_backend, server_picker, share_picker,
+};
 use gettextrs::gettext;
 use gtk4::prelude::*;
 use libadwaita as adw;
 use libadwaita::prelude::*;
 
 pub struct AddRemoteShareDialog {
-    window: adw::Window,
+    dialog: adw::Dialog,
 }
 
 impl AddRemoteShareDialog {
     pub fn new() -> Self {
-        let window = adw::Window::new();
-        window.set_title(Some(&gettext("Add Remote Samba Share")));
-        window.set_default_size(500, 600);
-        window.set_modal(true);
+        Self::build(None)
+    }
+
+    /// Dialog adding `remote_path`, such as a share found on the network
+    pub fn for_remote_path(remote_path: &str) -> Self {
+        Self::build(Some(remote_path))
+    }
+
+    fn build(remote_path: Option<&str>) -> Self {
+        let dialog = adw::Dialog::new();
+        dialog.set_title(&gettext("Add Remote Samba Share"));
+        dialog.set_content_width(500);
+        dialog.set_content_height(600);
 
         // Create toolbar header
         let toolbar_view = adw::ToolbarView::new();
@@ -34,21 +52,39 @@ impl AddRemoteShareDialog {
         mount_point_entry.set_tooltip_text(Some(&gettext("Local directory where the remote share will be mounted (e.g., /media/share)")));
         basic_group.add(&mount_point_entry);
 
+        // Credentials File Path, also used to list the shares of the server
+        let credentials_entry = adw::EntryRow::new();
+        credentials_entry.set_title(&gettext("Credentials File"));
+        credentials_entry.set_tooltip_text(Some(&gettext("Path to file containing username and password")));
+
         // Remote Path (SMB share path)
         let remote_path_entry = adw::EntryRow::new();
         remote_path_entry.set_title(&gettext("Remote Path"));
         remote_path_entry.set_text("//");
         remote_path_entry.set_tooltip_text(Some(&gettext("SMB share path (e.g., //server/share)")));
+        convert_unc_paths(&remote_path_entry);
+        remote_path_entry.add_suffix(&server_picker(&remote_path_entry));
+        remote_path_entry.add_suffix(&share_picker(&remote_path_entry, &credentials_entry));
+
+        let browse_folders_button = gtk4::Button::from_icon_name("folder-remote-symbolic");
+        browse_folders_button.set_valign(gtk4::Align::Center);
+        browse_folders_button.set_tooltip_text(Some(&gettext("Browse Folders")));
+        browse_folders_button.add_css_class("flat");
+        remote_path_entry.add_suffix(&browse_folders_button);
         basic_group.add(&remote_path_entry);
 
-        // Credentials File Path
-        let credentials_entry = adw::EntryRow::new();
-        credentials_entry.set_title(&gettext("Credentials File"));
-        credentials_entry.set_tooltip_text(Some(&gettext("Path to file containing username and password")));
+        // Named after the share, the mount point only needs checking
+        if let Some(remote_path) = remote_path {
+            remote_path_entry.set_text(remote_path);
+            if let Some(name) = remote_path.rsplit('/').find(|part| !part.is_empty()) {
+                mount_point_entry.set_text(&format!("/media/{}", name));
+            }
+        }
 
         let browse_button = gtk4::Button::with_label(&gettext("Browse..."));
         browse_button.set_valign(gtk4::Align::Center);
         credentials_entry.add_suffix(&browse_button);
+        credentials_entry.add_suffix(&CredentialsFileDialog::button(&dialog, &remote_path_entry, &credentials_entry));
         basic_group.add(&credentials_entry);
 
         preferences_page.add(&basic_group);
@@ -80,22 +116,53 @@ impl AddRemoteShareDialog {
             "These options will be automatically included in the configuration"
         )));
 
-        // Auto-mount switch
+        // Backend, the switches below only apply to systemd mounts
+        let backend_combo = mount_backend_combo(MountBackend::available()[0]);
+        advanced_group.add(&backend_combo);
+
+        // The switches start from the defaults chosen in the preferences
+        let defaults = AppConfig::new().mount_defaults();
+
+        // Auto-mount switch, the x-systemd.automount option
         let automount_switch = adw::SwitchRow::new();
-        automount_switch.set_title(&gettext("Auto-mount"));
-        automount_switch.set_subtitle(&gettext("Automatically mount on system startup"));
-        automount_switch.set_active(true); // Default enabled
+        automount_switch.set_title(&gettext("Mount on access"));
+        automount_switch.set_subtitle(&gettext("Mount the share the first time it is opened (x-systemd.automount)"));
+        automount_switch.set_active(defaults.systemd.automount);
         advanced_group.add(&automount_switch);
 
-        // No auto switch (mount on access)
+        // No auto switch, the noauto option
         let noauto_switch = adw::SwitchRow::new();
-        noauto_switch.set_title(&gettext("Mount on access"));
-        noauto_switch.set_subtitle(&gettext("Only mount when accessed (noauto)"));
-        noauto_switch.set_active(true); // Default enabled
+        noauto_switch.set_title(&gettext("Skip at startup"));
+        noauto_switch.set_subtitle(&gettext("Don't mount the share while the computer starts (noauto)"));
+        noauto_switch.set_active(defaults.systemd.noauto);
         advanced_group.add(&noauto_switch);
 
+        // Idle unmount delay, the x-systemd.idle-timeout option
+        let timeout_combo = idle_timeout_combo(defaults.idle_timeout);
+        advanced_group.add(&timeout_combo);
+
+        let update_switches = {
+            let automount_switch = automount_switch.clone();
+            let noauto_switch = noauto_switch.clone();
+            let timeout_combo = timeout_combo.clone();
+            move |combo: &adw::ComboRow| {
+                let systemd = selected_mount_backend(combo).uses_systemd();
+                automount_switch.set_sensitive(systemd);
+                noauto_switch.set_sensitive(systemd);
+                timeout_combo.set_sensitive(systemd);
+            }
+        };
+        update_switches(&backend_combo);
+        backend_combo.connect_selected_notify(update_switches);
+
         preferences_page.add(&advanced_group);
 
+        // Checked before anything is written to the configuration
+        let toast_overlay = adw::ToastOverlay::new();
+        let test_group = adw::PreferencesGroup::new();
+        test_group.add(&connection_test_button(&remote_path_entry, &credentials_entry, &toast_overlay));
+        preferences_page.add(&test_group);
+
         // Information banner
         let info_group = adw::PreferencesGroup::new();
         let info_banner = adw::Banner::new(&gettext(
@@ -113,48 +180,68 @@ impl AddRemoteShareDialog {
         // Add action buttons in header
         let cancel_button = gtk4::Button::with_label(&gettext("Cancel"));
         header_bar.pack_start(&cancel_button);
+        header_bar.set_show_start_title_buttons(false);
+        header_bar.set_show_end_title_buttons(false);
 
         let add_button = gtk4::Button::with_label(&gettext("Add Share"));
         add_button.add_css_class("suggested-action");
         header_bar.pack_end(&add_button);
 
         // Wrap toolbar in toast overlay for error messages
-        let toast_overlay = adw::ToastOverlay::new();
         toast_overlay.set_child(Some(&toolbar_view));
 
-        window.set_content(Some(&toast_overlay));
+        dialog.set_child(Some(&toast_overlay));
+
+        // A path copied from Windows is most likely the share to add
+        if remote_path.is_none() {
+            fill_from_clipboard(&remote_path_entry, &toast_overlay);
+        }
 
         // Handle browse button for credentials file
-        let window_clone_for_browse = window.clone();
+        let dialog_clone_for_browse = dialog.clone();
         let credentials_entry_clone = credentials_entry.clone();
         browse_button.connect_clicked(move |_| {
-            let dialog = gtk4::FileDialog::new();
-            dialog.set_title(&gettext("Select Credentials File"));
-
-            let credentials_entry_clone2 = credentials_entry_clone.clone();
-            dialog.open(Some(&window_clone_for_browse), None::<&gtk4::gio::Cancellable>, move |result| {
-                if let Ok(file) = result {
-                    if let Some(path) = file.path() {
-                        credentials_entry_clone2.set_text(&path.to_string_lossy());
-                    }
-                }
+            browse_file(&dialog_clone_for_browse, &credentials_entry_clone, &gettext("Select Credentials File"));
+        });
+
+        // Handle browse button for a folder inside the share, connecting with the credentials given
+        let dialog_clone_for_folders = dialog.clone();
+        let remote_path_entry_clone = remote_path_entry.clone();
+        let credentials_entry_clone = credentials_entry.clone();
+        let toast_overlay_clone = toast_overlay.clone();
+        browse_folders_button.connect_clicked(move |_| {
+            let Some((share, subpath)) = split_remote_path(&remote_path_entry_clone.text()) else {
+                let toast = adw::Toast::new(&gettext("Enter the server and share first (e.g., //server/share)"));
+                toast_overlay_clone.add_toast(toast);
+                return;
+            };
+
+            let remote_path_entry = remote_path_entry_clone.clone();
+            let credentials = credentials_entry_clone.text();
+            let browser = ShareBrowserDialog::new(&share, &subpath, &credentials, move |device| {
+                remote_path_entry.set_text(&device);
             });
+            browser.present(Some(&dialog_clone_for_folders));
         });
 
         // Handle cancel button
-        let window_clone = window.clone();
+        let dialog_clone = dialog.clone();
         cancel_button.connect_clicked(move |_| {
-            window_clone.close();
+            dialog_clone.close();
         });
 
         // Handle add button
-        let window_clone2 = window.clone();
+        let dialog_clone2 = dialog.clone();
         let mount_point_entry_clone = mount_point_entry.clone();
         let remote_path_entry_clone = remote_path_entry.clone();
         let credentials_entry_clone = credentials_entry.clone();
         let uid_entry_clone = uid_entry.clone();
         let gid_entry_clone = gid_entry.clone();
         let toast_overlay_clone = toast_overlay.clone();
+        let backend_combo_clone = backend_combo.clone();
+        let timeout_combo_clone = timeout_combo.clone();
+        let automount_switch_clone = automount_switch.clone();
+        let noauto_switch_clone = noauto_switch.clone();
 
         add_button.connect_clicked(move |_| {
             let mount_point = mount_point_entry_clone.text();
@@ -205,7 +292,7 @@ impl AddRemoteShareDialog {
             }
 
             // Create new share configuration
-            let new_share = RemoteSambaShareConfig::new(
+            let mut new_share = RemoteSambaShareConfig::new(
                 mount_point.to_string(),
                 remote_path.to_string(),
                 "cifs".to_string(),
@@ -213,16 +300,37 @@ impl AddRemoteShareDialog {
                 uid.to_string(),
                 gid.to_string(),
             );
+            new_share.backend = selected_mount_backend(&backend_combo_clone);
+            new_share.idle_timeout = if new_share.backend.uses_systemd() {
+                selected_idle_timeout(&timeout_combo_clone, defaults.idle_timeout)
+            } else {
+                None
+            };
+            new_share.systemd = if new_share.backend.uses_systemd() {
+                SystemdMount {
+                    automount: automount_switch_clone.is_active(),
+                    noauto: noauto_switch_clone.is_active(),
+                    ..defaults.systemd
+                }
+            } else {
+                SystemdMount::NONE
+            };
+            new_share.extra_options = defaults.extra_options.clone();
 
             match new_share.write() {
-                Ok(_) => {
+                Ok(change) => {
+                    // Open share lists pick the change up from the shared state
+                    let state = AppState::get();
+                    state.add_pending_change(change);
+                    state.reload_remote();
+
                     eprintln!(
                         "Remote share added: mount_point={}, remote_path={}, credentials={}, uid={}, gid={}",
                         mount_point, remote_path, credentials, uid, gid
                     );
                     let toast = adw::Toast::new(&gettext("Share added successfully. Run 'sudo nixos-rebuild switch' to apply changes."));
                     toast_overlay_clone.add_toast(toast);
-                    window_clone2.close();
+                    dialog_clone2.close();
                 }
                 Err(e) => {
                     eprintln!("Failed to add remote share: {}", e);
@@ -233,15 +341,10 @@ impl AddRemoteShareDialog {
             }
         });
 
-        Self { window }
+        Self { dialog }
     }
 
     pub fn present(&self, parent: Option<&impl IsA<gtk4::Widget>>) {
-        if let Some(p) = parent {
-            if let Some(parent_window) = p.dynamic_cast_ref::<gtk4::Window>() {
-                self.window.set_transient_for(Some(parent_window));
-            }
-        }
-        self.window.present();
+        self.dialog.present(parent);
     }
 }
@@ -1,11 +1,19 @@
+use crate::config::AppConfig;
+use crate::models::AppState;
+use crate::samba::presets::{apply_drop_box, prepare_drop_box_folder};
 use crate::samba::share_config::{get_system_groups, get_system_users, SambaShareConfig};
+use crate::ui::dialogs::ChangePreviewDialog;
+use crate::ui::widgets::{
+    browse_folder, load_combo_names, select_string, selected_string, ShareAdvancedOptions,
+};
 use gettextrs::gettext;
 use gtk4::prelude::*;
 use libadwaita as adw;
 use libadwaita::prelude::*;
+use std::rc::Rc;
 
 pub struct AddShareDialog {
-    window: adw::Window,
+    dialog: adw::Dialog,
     name_entry: adw::EntryRow,
     path_entry: adw::EntryRow,
     browse_button: gtk4::Button,
@@ -19,10 +27,10 @@ pub struct AddShareDialog {
 
 impl AddShareDialog {
     pub fn new() -> Self {
-        let window = adw::Window::new();
-        window.set_title(Some(&gettext("Setup New Samba Share")));
-        window.set_default_size(500, 600);
-        window.set_modal(true);
+        let dialog = adw::Dialog::new();
+        dialog.set_title(&gettext("Setup New Samba Share"));
+        dialog.set_content_width(500);
+        dialog.set_content_height(600);
 
         // Create toolbar header
         let toolbar_view = adw::ToolbarView::new();
@@ -32,6 +40,31 @@ impl AddShareDialog {
         // Create preferences page for the form
         let preferences_page: libadwaita::PreferencesPage = adw::PreferencesPage::new();
 
+        // Presets Group
+        let presets_group = adw::PreferencesGroup::new();
+        presets_group.set_title(&gettext("Presets"));
+
+        let drop_box_row = adw::ActionRow::new();
+        drop_box_row.set_title(&gettext("Drop Box"));
+        drop_box_row.set_subtitle(&gettext(
+            "Guests can drop files, e.g. from a scanner, without seeing the folder's content",
+        ));
+        drop_box_row.add_prefix(&gtk4::Image::from_icon_name("mail-send-symbolic"));
+        let drop_box_button = gtk4::Button::with_label(&gettext("Apply"));
+        drop_box_button.set_valign(gtk4::Align::Center);
+        drop_box_row.add_suffix(&drop_box_button);
+        presets_group.add(&drop_box_row);
+
+        let write_only_switch = adw::SwitchRow::new();
+        write_only_switch.set_title(&gettext("Make Folder Write-Only"));
+        write_only_switch.set_subtitle(&gettext(
+            "Create the folder with mode 1733 so guests can add files but not list them",
+        ));
+        write_only_switch.set_visible(false);
+        presets_group.add(&write_only_switch);
+
+        preferences_page.add(&presets_group);
+
         // Basic Information Group
         let basic_group = adw::PreferencesGroup::new();
         basic_group.set_title(&gettext("Basic Information"));
@@ -88,11 +121,8 @@ impl AddShareDialog {
         force_user_combo.set_title(&gettext("Force User"));
         force_user_combo.set_subtitle(&gettext("Force all file operations as this user"));
 
-        // Get system users
-        let users = get_system_users();
-        let user_list = gtk4::StringList::new(&users.iter().map(|s| s.as_str()).collect::<Vec<_>>());
-        force_user_combo.set_model(Some(&user_list));
-        force_user_combo.set_selected(0);
+        // Get system users in the background
+        load_combo_names(&force_user_combo, "", get_system_users);
         user_group_group.add(&force_user_combo);
 
         // Force Group dropdown
@@ -100,56 +130,85 @@ impl AddShareDialog {
         force_group_combo.set_title(&gettext("Force Group"));
         force_group_combo.set_subtitle(&gettext("Force all file operations as this group"));
 
-        // Get system groups
-        let groups = get_system_groups();
-        let group_list = gtk4::StringList::new(&groups.iter().map(|s| s.as_str()).collect::<Vec<_>>());
-        force_group_combo.set_model(Some(&group_list));
-        force_group_combo.set_selected(0);
+        // Get system groups in the background
+        load_combo_names(&force_group_combo, "", get_system_groups);
         user_group_group.add(&force_group_combo);
 
         preferences_page.add(&user_group_group);
 
+        // Advanced options
+        let advanced_options = Rc::new(ShareAdvancedOptions::new());
+        advanced_options.follow_guest_switch(&guest_ok_switch);
+        preferences_page.add(advanced_options.group());
+
+
         toolbar_view.set_content(Some(&preferences_page));
 
         // Add action buttons in header
         let cancel_button = gtk4::Button::with_label(&gettext("Cancel"));
         header_bar.pack_start(&cancel_button);
+        header_bar.set_show_start_title_buttons(false);
+        header_bar.set_show_end_title_buttons(false);
 
         let add_button = gtk4::Button::with_label(&gettext("Add Share"));
         add_button.add_css_class("suggested-action");
         header_bar.pack_end(&add_button);
 
+        let preview_button = gtk4::Button::from_icon_name("document-properties-symbolic");
+        preview_button.set_tooltip_text(Some(&gettext("Preview Changes")));
+        header_bar.pack_end(&preview_button);
+
         // Wrap toolbar in toast overlay for error messages
         let toast_overlay = adw::ToastOverlay::new();
         toast_overlay.set_child(Some(&toolbar_view));
 
-        window.set_content(Some(&toast_overlay));
+        dialog.set_child(Some(&toast_overlay));
 
         // Handle browse button
-        let window_clone_for_browse = window.clone();
+        let dialog_clone_for_browse = dialog.clone();
         let path_entry_clone = path_entry.clone();
         browse_button.connect_clicked(move |_| {
-            let dialog = gtk4::FileDialog::new();
-            dialog.set_title(&gettext("Select Folder"));
-
-            let path_entry_clone2 = path_entry_clone.clone();
-            dialog.select_folder(Some(&window_clone_for_browse), None::<&gtk4::gio::Cancellable>, move |result| {
-                if let Ok(folder) = result {
-                    if let Some(path) = folder.path() {
-                        path_entry_clone2.set_text(&path.to_string_lossy());
-                    }
-                }
-            });
+            browse_folder(&dialog_clone_for_browse, &path_entry_clone, &gettext("Select Folder"));
         });
 
         // Handle cancel button
-        let window_clone = window.clone();
+        let dialog_clone = dialog.clone();
         cancel_button.connect_clicked(move |_| {
-            window_clone.close();
+            dialog_clone.close();
         });
 
-        // Handle add button
-        let window_clone2 = window.clone();
+        // Handle drop box preset
+        let browsable_switch_clone = browsable_switch.clone();
+        let read_only_switch_clone = read_only_switch.clone();
+        let guest_ok_switch_clone = guest_ok_switch.clone();
+        let force_user_combo_clone = force_user_combo.clone();
+        let force_group_combo_clone = force_group_combo.clone();
+        let advanced_options_clone = advanced_options.clone();
+        let write_only_switch_clone = write_only_switch.clone();
+        drop_box_button.connect_clicked(move |_| {
+            let mut preset = SambaShareConfig::new(
+                String::new(),
+                String::new(),
+                true,
+                false,
+                false,
+                String::new(),
+                String::new(),
+            );
+            apply_drop_box(&mut preset);
+
+            browsable_switch_clone.set_active(preset.browsable);
+            read_only_switch_clone.set_active(preset.read_only);
+            guest_ok_switch_clone.set_active(preset.guest_ok);
+            select_string(&force_user_combo_clone, &preset.force_user);
+            select_string(&force_group_combo_clone, &preset.force_group);
+            advanced_options_clone.load(&preset);
+
+            write_only_switch_clone.set_visible(true);
+            write_only_switch_clone.set_active(true);
+        });
+
+        // Build the share described by the form, or the message for a missing field
         let name_entry_clone = name_entry.clone();
         let path_entry_clone2 = path_entry.clone();
         let browsable_switch_clone = browsable_switch.clone();
@@ -157,73 +216,93 @@ impl AddShareDialog {
         let guest_ok_switch_clone = guest_ok_switch.clone();
         let force_user_combo_clone = force_user_combo.clone();
         let force_group_combo_clone = force_group_combo.clone();
-        let toast_overlay_clone = toast_overlay.clone();
-
-        add_button.connect_clicked(move |_| {
+        let advanced_options_clone = advanced_options.clone();
+        let build_share = Rc::new(move || -> Result<SambaShareConfig, String> {
             let name = name_entry_clone.text();
             let path = path_entry_clone2.text();
 
             // Validate required fields
             if name.is_empty() {
-                let toast = adw::Toast::new(&gettext("Share name is required"));
-                toast_overlay_clone.add_toast(toast);
-                return;
+                return Err(gettext("Share name is required"));
             }
 
             if path.is_empty() {
-                let toast = adw::Toast::new(&gettext("Path is required"));
-                toast_overlay_clone.add_toast(toast);
-                return;
+                return Err(gettext("Path is required"));
             }
 
-            let browsable = browsable_switch_clone.is_active();
-            let read_only = read_only_switch_clone.is_active();
-            let guest_ok = guest_ok_switch_clone.is_active();
-
-            let force_user = if let Some(model) = force_user_combo_clone.model() {
-                if let Some(string_list) = model.dynamic_cast_ref::<gtk4::StringList>() {
-                    string_list.string(force_user_combo_clone.selected())
-                        .map(|s| s.to_string())
-                        .unwrap_or_default()
-                } else {
-                    String::new()
+            let mut share_config = SambaShareConfig::new(
+                name.to_string(),
+                path.to_string(),
+                browsable_switch_clone.is_active(),
+                read_only_switch_clone.is_active(),
+                guest_ok_switch_clone.is_active(),
+                selected_string(&force_user_combo_clone),
+                selected_string(&force_group_combo_clone),
+            );
+            advanced_options_clone.apply(&mut share_config);
+            Ok(share_config)
+        });
+
+        // Handle preview button
+        let dialog_clone_for_preview = dialog.clone();
+        let toast_overlay_clone = toast_overlay.clone();
+        let build_share_clone = build_share.clone();
+        preview_button.connect_clicked(move |_| {
+            let share_config = match build_share_clone() {
+                Ok(share_config) => share_config,
+                Err(msg) => {
+                    toast_overlay_clone.add_toast(adw::Toast::new(&msg));
+                    return;
                 }
-            } else {
-                String::new()
             };
 
-            let force_group = if let Some(model) = force_group_combo_clone.model() {
-                if let Some(string_list) = model.dynamic_cast_ref::<gtk4::StringList>() {
-                    string_list.string(force_group_combo_clone.selected())
-                        .map(|s| s.to_string())
-                        .unwrap_or_default()
-                } else {
-                    String::new()
+            match share_config.prepare_write(&AppConfig::new().server_template()) {
+                Ok(change) => {
+                    let preview = ChangePreviewDialog::new(&change, &share_config.to_nix());
+                    preview.present(Some(&dialog_clone_for_preview));
+                }
+                Err(e) => {
+                    let error_msg = format!("{}: {}", gettext("Failed to preview changes"), e);
+                    toast_overlay_clone.add_toast(adw::Toast::new(&error_msg));
+                }
+            }
+        });
+
+        // Handle add button
+        let dialog_clone2 = dialog.clone();
+        let toast_overlay_clone = toast_overlay.clone();
+
+        add_button.connect_clicked(move |_| {
+            let share_config = match build_share() {
+                Ok(share_config) => share_config,
+                Err(msg) => {
+                    toast_overlay_clone.add_toast(adw::Toast::new(&msg));
+                    return;
                 }
-            } else {
-                String::new()
             };
 
             // Write configuration to NixOS
-            let share_config = SambaShareConfig::new(
-                name.to_string(),
-                path.to_string(),
-                browsable,
-                read_only,
-                guest_ok,
-                force_user,
-                force_group,
-            );
+            let result = share_config.write(&AppConfig::new().server_template()).and_then(|change| {
+                if write_only_switch.is_active() {
+                    prepare_drop_box_folder(&share_config.path)?;
+                }
+                Ok(change)
+            });
+
+            match result {
+                Ok(change) => {
+                    // Open share lists pick the new share up from the shared state
+                    let state = AppState::get();
+                    state.add_pending_change(change);
+                    state.reload_local();
 
-            match share_config.write() {
-                Ok(_) => {
                     eprintln!(
                         "Share added: name={}, path={}, browsable={}, read_only={}, guest_ok={}, force_user={}, force_group={}",
-                        name, path, browsable, read_only, guest_ok, share_config.force_user, share_config.force_group
+                        share_config.name, share_config.path, share_config.browsable, share_config.read_only, share_config.guest_ok, share_config.force_user, share_config.force_group
                     );
                     let toast = adw::Toast::new(&gettext("Share added successfully. Please rebuild NixOS to apply changes."));
                     toast_overlay_clone.add_toast(toast);
-                    window_clone2.close();
+                    dialog_clone2.close();
                 }
                 Err(e) => {
                     eprintln!("Failed to add share: {}", e);
@@ -235,7 +314,7 @@ impl AddShareDialog {
         });
 
         Self {
-            window,
+            dialog,
             name_entry,
             path_entry,
             browse_button,
@@ -249,15 +328,11 @@ impl AddShareDialog {
     }
 
     pub fn present(&self, parent: Option<&impl IsA<gtk4::Widget>>) {
-        if let Some(p) = parent {
-            if let Some(parent_window) = p.dynamic_cast_ref::<gtk4::Window>() {
-                self.window.set_transient_for(Some(parent_window));
-            }
-        }
-        self.window.present();
+        self.dialog.present(parent);
     }
 
-    pub fn window(&self) -> &adw::Window {
-        &self.window
+    pub fn dialog(&self) -> &adw::Dialog {
+        &self.dialog
     }
 }
+
@@ -0,0 +1,46 @@
+use crate::samba::open_files::BlockingProcess;
+use gettextrs::gettext;
+use gtk4::prelude::*;
+use libadwaita as adw;
+use libadwaita::prelude::*;
+
+/// Explain that applications keep `mount_point` busy, list them, and offer to
+/// retry once they are closed
+pub fn show_blocking_processes(
+    parent: &impl IsA<gtk4::Widget>,
+    mount_point: &str,
+    processes: &[BlockingProcess],
+    on_retry: impl Fn() + 'static,
+) {
+    let body = format!(
+        "{}\n\n{}",
+        gettext("These applications have files open in {mount_point}. Close them, or leave the folder in them, then retry.")
+            .replace("{mount_point}", mount_point),
+        gettext("Unmounting now could lose their unsaved changes.")
+    );
+    let dialog = adw::AlertDialog::new(Some(&gettext("Share in Use")), Some(&body));
+    dialog.add_responses(&[("cancel", &gettext("Cancel")), ("retry", &gettext("Retry"))]);
+    dialog.set_response_appearance("retry", adw::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("retry"));
+    dialog.set_close_response("cancel");
+
+    let list = gtk4::ListBox::new();
+    list.add_css_class("boxed-list");
+    list.set_selection_mode(gtk4::SelectionMode::None);
+    for process in processes {
+        let row = adw::ActionRow::new();
+        row.set_title(&process.name);
+        row.set_use_markup(false);
+        row.set_subtitle(
+            &gettext("Process {pid}, {count} open files")
+                .replace("{pid}", &process.pid.to_string())
+                .replace("{count}", &process.open_files.to_string()),
+        );
+        row.add_prefix(&gtk4::Image::from_icon_name("application-x-executable-symbolic"));
+        list.append(&row);
+    }
+    dialog.set_extra_child(Some(&list));
+
+    dialog.connect_response(Some("retry"), move |_, _| on_retry());
+    dialog.present(Some(parent));
+}
@@ -0,0 +1,76 @@
+use crate::samba::ConfigChange;
+use crate::ui::widgets::source_view;
+use gettextrs::gettext;
+use gtk4::prelude::*;
+use libadwaita as adw;
+use libadwaita::prelude::*;
+
+/// Shows the diff of a pending configuration change and the raw Nix snippet it adds
+pub struct ChangePreviewDialog {
+    dialog: adw::Dialog,
+}
+
+impl ChangePreviewDialog {
+    pub fn new(change: &ConfigChange, snippet: &str) -> Self {
+        let dialog = adw::Dialog::new();
+        dialog.set_title(&gettext("Preview Changes"));
+        dialog.set_content_width(800);
+        dialog.set_content_height(600);
+
+        let view_stack = adw::ViewStack::new();
+
+        // Diff of the whole file
+        let diff = change.diff();
+        let diff_text = if diff.is_empty() {
+            gettext("No changes")
+        } else {
+            diff
+        };
+        view_stack.add_titled_with_icon(
+            &scrolled(&source_view(&diff_text, "diff")),
+            Some("changes"),
+            &gettext("Changes"),
+            "document-edit-symbolic",
+        );
+
+        // Generated Nix entry
+        view_stack.add_titled_with_icon(
+            &scrolled(&source_view(snippet, "nix")),
+            Some("snippet"),
+            &gettext("Nix Snippet"),
+            "text-x-generic-symbolic",
+        );
+
+        // Create toolbar header with the view switcher
+        let toolbar_view = adw::ToolbarView::new();
+        let header_bar = adw::HeaderBar::new();
+        let switcher = adw::ViewSwitcher::new();
+        switcher.set_stack(Some(&view_stack));
+        switcher.set_policy(adw::ViewSwitcherPolicy::Wide);
+        header_bar.set_title_widget(Some(&switcher));
+        toolbar_view.add_top_bar(&header_bar);
+
+        // File being changed
+        let path_banner = adw::Banner::new(&change.path);
+        path_banner.set_use_markup(false);
+        path_banner.set_revealed(true);
+        toolbar_view.add_top_bar(&path_banner);
+
+        toolbar_view.set_content(Some(&view_stack));
+        dialog.set_child(Some(&toolbar_view));
+
+        Self { dialog }
+    }
+
+    pub fn present(&self, parent: Option<&impl IsA<gtk4::Widget>>) {
+        self.dialog.present(parent);
+    }
+}
+
+fn scrolled(child: &impl IsA<gtk4::Widget>) -> gtk4::ScrolledWindow {
+    gtk4::ScrolledWindow::builder()
+        .hexpand(true)
+        .vexpand(true)
+        .child(child)
+        .build()
+}
@@ -0,0 +1,231 @@
+use crate::samba::config_audit::{AuditedShare, ConfigAudit, OptionHandling};
+use crate::samba::paths::config_path;
+use crate::ui::widgets::{locked_badge, mount_backend_label, skeleton_group};
+use gettextrs::gettext;
+use gtk4::prelude::*;
+use gtk4::{gio, glib};
+use libadwaita as adw;
+use libadwaita::prelude::*;
+
+/// Read-only summary of the Samba configuration already present, telling what
+/// the app will edit, keep as it is, or not read at all
+pub struct ConfigAuditDialog {
+    dialog: adw::Dialog,
+}
+
+impl ConfigAuditDialog {
+    pub fn new() -> Self {
+        let dialog = adw::Dialog::new();
+        dialog.set_title(&gettext("Existing Configuration"));
+        dialog.set_content_width(600);
+        dialog.set_content_height(600);
+
+        // Create toolbar header
+        let toolbar_view = adw::ToolbarView::new();
+        let header_bar = adw::HeaderBar::new();
+        toolbar_view.add_top_bar(&header_bar);
+
+        let scrolled = gtk4::ScrolledWindow::builder()
+            .hexpand(true)
+            .vexpand(true)
+            .build();
+        let preferences_page = adw::PreferencesPage::new();
+        preferences_page.add(&skeleton_group(4));
+        scrolled.set_child(Some(&preferences_page));
+        toolbar_view.set_content(Some(&scrolled));
+        dialog.set_child(Some(&toolbar_view));
+
+        glib::spawn_future_local(async move {
+            let result = gio::spawn_blocking(ConfigAudit::load)
+                .await
+                .unwrap_or_else(|e| Err(format!("{:?}", e)));
+            Self::show_audit(&scrolled, result);
+        });
+
+        Self { dialog }
+    }
+
+    pub fn present(&self, parent: Option<&impl IsA<gtk4::Widget>>) {
+        self.dialog.present(parent);
+    }
+
+    fn show_audit(scrolled: &gtk4::ScrolledWindow, result: Result<ConfigAudit, String>) {
+        let audit = match result {
+            Ok(audit) => audit,
+            Err(e) => {
+                eprintln!("Failed to read the configuration: {}", e);
+                let status = adw::StatusPage::new();
+                status.set_title(&gettext("Cannot Read the Configuration"));
+                status.set_description(Some(&e));
+                status.set_icon_name(Some("dialog-error-symbolic"));
+                scrolled.set_child(Some(&status));
+                return;
+            }
+        };
+
+        let empty = audit.global.is_empty()
+            && !audit.homes
+            && audit.shares.is_empty()
+            && audit.mounts.is_empty()
+            && audit.other_options.is_empty();
+        if empty {
+            let status = adw::StatusPage::new();
+            status.set_title(&gettext("No Samba Configuration Yet"));
+            status.set_description(Some(
+                &gettext("Shares you add are written to {file}").replace("{file}", config_path()),
+            ));
+            status.set_icon_name(Some("folder-publicshare-symbolic"));
+            scrolled.set_child(Some(&status));
+            return;
+        }
+
+        let preferences_page = adw::PreferencesPage::new();
+        scrolled.set_child(Some(&preferences_page));
+
+        preferences_page.add(&summary_group(&audit));
+        if !audit.global.is_empty() || audit.homes {
+            preferences_page.add(&server_group(&audit));
+        }
+        if !audit.shares.is_empty() {
+            let group = adw::PreferencesGroup::new();
+            group.set_title(&gettext("Local Shares"));
+            for audited in &audit.shares {
+                group.add(&share_row(audited));
+            }
+            preferences_page.add(&group);
+        }
+        if !audit.mounts.is_empty() {
+            let group = adw::PreferencesGroup::new();
+            group.set_title(&gettext("Remote Shares"));
+            for mount in &audit.mounts {
+                let row = adw::ActionRow::new();
+                row.set_title(&mount.name);
+                row.set_subtitle(&format!(
+                    "{} • {}\n{}",
+                    mount.remote_path,
+                    mount_backend_label(mount.backend),
+                    mount.source_file
+                ));
+                row.set_use_markup(false);
+                if let Some(reason) = &mount.locked_reason {
+                    row.add_suffix(&locked_badge(reason));
+                }
+                group.add(&row);
+            }
+            preferences_page.add(&group);
+        }
+        if !audit.other_options.is_empty() {
+            let group = adw::PreferencesGroup::new();
+            group.set_title(&gettext("Other Samba Options"));
+            group.set_description(Some(&gettext("Options outside the shares and server settings")));
+            for option in &audit.other_options {
+                let row = adw::ActionRow::new();
+                row.set_title(&option.path);
+                let handling = match option.handling {
+                    OptionHandling::Kept => gettext("Left as it is"),
+                    OptionHandling::Ignored => {
+                        row.add_prefix(&gtk4::Image::from_icon_name("dialog-warning-symbolic"));
+                        gettext("Not read by this app, what it defines doesn't appear here")
+                    }
+                };
+                row.set_subtitle(&format!("{}\n{}", handling, option.source_file));
+                row.set_use_markup(false);
+                group.add(&row);
+            }
+            preferences_page.add(&group);
+        }
+
+        let files_group = adw::PreferencesGroup::new();
+        files_group.set_title(&gettext("Files Read"));
+        files_group.set_description(Some(&gettext(
+            "The main configuration file and the files it imports. Imports computed by Nix expressions aren't followed.",
+        )));
+        for file in &audit.files {
+            let row = adw::ActionRow::new();
+            row.set_title(file);
+            row.set_use_markup(false);
+            row.add_prefix(&gtk4::Image::from_icon_name("text-x-generic-symbolic"));
+            files_group.add(&row);
+        }
+        preferences_page.add(&files_group);
+    }
+}
+
+/// What the app will do with the configuration, at a glance
+fn summary_group(audit: &ConfigAudit) -> adw::PreferencesGroup {
+    let group = adw::PreferencesGroup::new();
+    let row = adw::ActionRow::new();
+    if audit.fully_managed() {
+        row.set_title(&gettext("Everything found can be edited here"));
+        row.set_subtitle(&gettext(
+            "Only the shares and settings you change are rewritten, the rest of the files stays as it is.",
+        ));
+        row.add_prefix(&gtk4::Image::from_icon_name("emblem-ok-symbolic"));
+    } else {
+        row.set_title(&gettext("Some of the configuration is out of reach"));
+        row.set_subtitle(&gettext(
+            "Entries marked below are read-only, lose settings when edited, or aren't read at all. Change them directly in the files.",
+        ));
+        row.add_prefix(&gtk4::Image::from_icon_name("dialog-warning-symbolic"));
+        row.add_css_class("warning");
+    }
+    group.add(&row);
+    group
+}
+
+/// Keys of the global section, telling which ones the server settings edit
+fn server_group(audit: &ConfigAudit) -> adw::PreferencesGroup {
+    let group = adw::PreferencesGroup::new();
+    group.set_title(&gettext("Server Settings"));
+    group.set_description(Some(&gettext(
+        "Keys not edited in Server Settings are kept as they are",
+    )));
+
+    for setting in &audit.global {
+        let row = adw::ActionRow::new();
+        row.set_title(&setting.key);
+        row.set_subtitle(&setting.value);
+        row.set_use_markup(false);
+
+        let label = if setting.managed {
+            gtk4::Label::new(Some(&gettext("Editable")))
+        } else {
+            gtk4::Label::new(Some(&gettext("Kept")))
+        };
+        label.add_css_class("caption");
+        label.add_css_class("dim-label");
+        label.set_valign(gtk4::Align::Center);
+        row.add_suffix(&label);
+        group.add(&row);
+    }
+
+    if audit.homes {
+        let row = adw::ActionRow::new();
+        row.set_title(&gettext("Home Folders"));
+        row.set_subtitle(&gettext("Shared through the homes section, editable in Server Settings"));
+        group.add(&row);
+    }
+    group
+}
+
+/// Share with why it can't be edited, or the settings editing it would drop
+fn share_row(audited: &AuditedShare) -> adw::ActionRow {
+    let share = &audited.share;
+    let row = adw::ActionRow::new();
+    row.set_title(&share.name);
+    row.set_use_markup(false);
+
+    let mut lines = vec![share.path.clone()];
+    if let Some(reason) = &share.locked_reason {
+        row.add_suffix(&locked_badge(reason));
+    } else if !audited.dropped_settings.is_empty() {
+        row.add_prefix(&gtk4::Image::from_icon_name("dialog-warning-symbolic"));
+        lines.push(
+            gettext("Lost when edited here: {settings}")
+                .replace("{settings}", &audited.dropped_settings.join(", ")),
+        );
+    }
+    lines.push(share.source_file.clone());
+    row.set_subtitle(&lines.join("\n"));
+    row
+}
@@ -0,0 +1,40 @@
+use gettextrs::gettext;
+use gtk4::prelude::*;
+use libadwaita as adw;
+use libadwaita::prelude::*;
+
+/// Ask the user to type `name` before running a destructive `on_confirm`
+pub fn confirm_delete(
+    parent: &impl IsA<gtk4::Widget>,
+    name: &str,
+    body: &str,
+    on_confirm: impl Fn() + 'static,
+) {
+    let dialog = adw::AlertDialog::new(Some(&gettext("Delete Share?")), Some(body));
+    dialog.add_responses(&[("cancel", &gettext("Cancel")), ("delete", &gettext("Delete"))]);
+    dialog.set_response_appearance("delete", adw::ResponseAppearance::Destructive);
+    dialog.set_response_enabled("delete", false);
+    dialog.set_default_response(Some("cancel"));
+    dialog.set_close_response("cancel");
+
+    let confirm_box = gtk4::Box::new(gtk4::Orientation::Vertical, 6);
+    let hint = gtk4::Label::new(Some(&format!("{} {}", gettext("Type this name to confirm:"), name)));
+    hint.set_wrap(true);
+    hint.set_xalign(0.0);
+    confirm_box.append(&hint);
+
+    let entry = gtk4::Entry::new();
+    entry.set_placeholder_text(Some(name));
+    confirm_box.append(&entry);
+    dialog.set_extra_child(Some(&confirm_box));
+
+    // Only enable the destructive response once the exact name is typed
+    let dialog_clone = dialog.clone();
+    let expected = name.to_string();
+    entry.connect_changed(move |entry| {
+        dialog_clone.set_response_enabled("delete", entry.text() == expected.as_str());
+    });
+
+    dialog.connect_response(Some("delete"), move |_, _| on_confirm());
+    dialog.present(Some(parent));
+}
@@ -0,0 +1,174 @@
+use crate::config::AppConfig;
+use crate::samba::credentials_file::{create, suggested_path, Credentials};
+use crate::samba::sandbox::host_path;
+use gettextrs::gettext;
+use gtk4::prelude::*;
+use gtk4::{gio, glib};
+use libadwaita as adw;
+use libadwaita::prelude::*;
+use std::path::Path;
+
+/// Creates a credentials file readable by root only from a username, password and
+/// domain, then puts its path in the credentials entry of the share dialog
+pub struct CredentialsFileDialog {
+    dialog: adw::Dialog,
+}
+
+impl CredentialsFileDialog {
+    /// `server` names the file offered, `credentials_entry` receives its path once written
+    pub fn new(server: &str, credentials_entry: &adw::EntryRow) -> Self {
+        let dialog = adw::Dialog::new();
+        dialog.set_title(&gettext("New Credentials File"));
+        dialog.set_content_width(450);
+
+        // Create toolbar header
+        let toolbar_view = adw::ToolbarView::new();
+        let header_bar = adw::HeaderBar::new();
+        toolbar_view.add_top_bar(&header_bar);
+
+        let toast_overlay = adw::ToastOverlay::new();
+        let preferences_page = adw::PreferencesPage::new();
+
+        let group = adw::PreferencesGroup::new();
+        group.set_description(Some(&gettext(
+            "The file is owned by root and only root can read it, as the system mounts the share",
+        )));
+
+        let username_entry = adw::EntryRow::new();
+        username_entry.set_title(&gettext("Username"));
+        group.add(&username_entry);
+
+        let password_entry = adw::PasswordEntryRow::new();
+        password_entry.set_title(&gettext("Password"));
+        group.add(&password_entry);
+
+        let domain_entry = adw::EntryRow::new();
+        domain_entry.set_title(&gettext("Domain (optional)"));
+        domain_entry.set_tooltip_text(Some(&gettext("Windows domain or workgroup of the account, e.g. WORKGROUP")));
+        group.add(&domain_entry);
+
+        let path_entry = adw::EntryRow::new();
+        path_entry.set_title(&gettext("Save As"));
+        path_entry.set_text(&suggested_path(&AppConfig::new().credentials_folder(), server));
+        group.add(&path_entry);
+
+        preferences_page.add(&group);
+
+        // Actions
+        let actions_group = adw::PreferencesGroup::new();
+        let create_button = gtk4::Button::with_label(&gettext("Create"));
+        create_button.set_halign(gtk4::Align::Center);
+        create_button.add_css_class("pill");
+        create_button.add_css_class("suggested-action");
+        actions_group.add(&create_button);
+        preferences_page.add(&actions_group);
+
+        toast_overlay.set_child(Some(&preferences_page));
+        toolbar_view.set_content(Some(&toast_overlay));
+        dialog.set_child(Some(&toolbar_view));
+
+        let dialog_clone = dialog.clone();
+        let credentials_entry = credentials_entry.clone();
+        create_button.connect_clicked(move |button| {
+            let credentials = Credentials {
+                username: username_entry.text().to_string(),
+                password: password_entry.text().to_string(),
+                domain: domain_entry.text().to_string(),
+            };
+            if let Err(e) = credentials.to_file_content() {
+                let error_msg = format!("{}: {}", gettext("Failed to create the credentials file"), e);
+                toast_overlay.add_toast(adw::Toast::new(&error_msg));
+                return;
+            }
+            let path = path_entry.text().trim().to_string();
+            // The password of another share may be in the file already
+            let exists = host_path(Path::new(&path)).exists();
+            let message = gettext("{file} already exists. Shares using it will log in with the new credentials.")
+                .replace("{file}", &path);
+
+            let write = {
+                let button = button.clone();
+                let dialog = dialog_clone.clone();
+                let toast_overlay = toast_overlay.clone();
+                let credentials_entry = credentials_entry.clone();
+                move || {
+                    write_file(&button, &dialog, &toast_overlay, &credentials_entry, path.clone(), credentials.clone())
+                }
+            };
+
+            if !exists {
+                write();
+                return;
+            }
+            let alert = adw::AlertDialog::new(Some(&gettext("Replace the File?")), Some(&message));
+            alert.add_responses(&[("cancel", &gettext("Cancel")), ("replace", &gettext("Replace"))]);
+            alert.set_response_appearance("replace", adw::ResponseAppearance::Destructive);
+            alert.set_close_response("cancel");
+            alert.connect_response(Some("replace"), move |_, _| write());
+            alert.present(Some(&dialog_clone));
+        });
+
+        Self { dialog }
+    }
+
+    /// "Create..." button for the credentials entry of a share dialog, offering a file
+    /// named after the server of `remote_path_entry`
+    pub fn button(
+        parent: &adw::Dialog,
+        remote_path_entry: &adw::EntryRow,
+        credentials_entry: &adw::EntryRow,
+    ) -> gtk4::Button {
+        let button = gtk4::Button::with_label(&gettext("Create..."));
+        button.set_valign(gtk4::Align::Center);
+        button.set_tooltip_text(Some(&gettext("Create a credentials file from a username and password")));
+
+        let parent = parent.clone();
+        let remote_path_entry = remote_path_entry.clone();
+        let credentials_entry = credentials_entry.clone();
+        button.connect_clicked(move |_| {
+            let remote_path = remote_path_entry.text();
+            let server = remote_path.trim().trim_start_matches('/').split('/').next().unwrap_or_default();
+            Self::new(server, &credentials_entry).present(Some(&parent));
+        });
+        button
+    }
+
+    pub fn present(&self, parent: Option<&impl IsA<gtk4::Widget>>) {
+        self.dialog.present(parent);
+    }
+}
+
+/// Write the file with escalation, then fill the entry and close the dialog
+fn write_file(
+    button: &gtk4::Button,
+    dialog: &adw::Dialog,
+    toast_overlay: &adw::ToastOverlay,
+    credentials_entry: &adw::EntryRow,
+    path: String,
+    credentials: Credentials,
+) {
+    button.set_sensitive(false);
+    let button = button.clone();
+    let dialog = dialog.clone();
+    let toast_overlay = toast_overlay.clone();
+    let credentials_entry = credentials_entry.clone();
+    glib::spawn_future_local(async move {
+        let written = path.clone();
+        let result = gio::spawn_blocking(move || create(&written, &credentials))
+            .await
+            .unwrap_or_else(|e| Err(format!("{:?}", e)));
+
+        button.set_sensitive(true);
+        match result {
+            Ok(()) => {
+                credentials_entry.set_text(&path);
+                dialog.close();
+            }
+            Err(e) => {
+                eprintln!("Failed to create the credentials file: {}", e);
+                let error_msg = format!("{}: {}", gettext("Failed to create the credentials file"), e);
+                toast_overlay.add_toast(adw::Toast::new(&error_msg));
+            }
+        }
+    });
+}
@@ -1,20 +1,34 @@
+use crate::config::AppConfig;
+use crate::models::AppState;
+use crate::samba::mount_defaults::SystemdMount;
 use crate::samba::remote_share_config::RemoteSambaShareConfig;
+use crate::samba::{is_mounted, move_mount_point, release_mount_point};
+use crate::ui::dialogs::remote_list_shares::start_delete;
+use crate::ui::dialogs::CredentialsFileDialog;
+use crate::ui::widgets::{
+    browse_file, connection_test_button, convert_unc_paths, idle_timeout_combo, mount_backend_combo,
+    selected_idle_timeout, selected_mount_backend,
+};
 use gettextrs::gettext;
 use gtk4::prelude::*;
+use gtk4::{gio, glib};
 use libadwaita as adw;
 use libadwaita::prelude::*;
+use std::path::{Path, PathBuf};
 
 pub struct EditRemoteShareDialog {
-    window: adw::Window,
+    dialog: adw::Dialog,
     original_name: String,
 }
 
 impl EditRemoteShareDialog {
-    pub fn new(share: &RemoteSambaShareConfig) -> Self {
-        let window = adw::Window::new();
-        window.set_title(Some(&gettext("Edit Remote Samba Share")));
-        window.set_default_size(500, 600);
-        window.set_modal(true);
+    /// `list_toast_overlay` reports the deletion of the share once this dialog is closed,
+    /// None for shares mounted outside the configuration, which can't be deleted
+    pub fn new(share: &RemoteSambaShareConfig, list_toast_overlay: Option<&adw::ToastOverlay>) -> Self {
+        let dialog = adw::Dialog::new();
+        dialog.set_title(&gettext("Edit Remote Samba Share"));
+        dialog.set_content_width(500);
+        dialog.set_content_height(600);
 
         // Create toolbar header
         let toolbar_view = adw::ToolbarView::new();
@@ -40,6 +54,7 @@ impl EditRemoteShareDialog {
         remote_path_entry.set_title(&gettext("Remote Path"));
         remote_path_entry.set_text(&share.remote_path);
         remote_path_entry.set_tooltip_text(Some(&gettext("SMB share path (e.g., //server/share)")));
+        convert_unc_paths(&remote_path_entry);
         basic_group.add(&remote_path_entry);
 
         // Credentials File Path
@@ -51,6 +66,7 @@ impl EditRemoteShareDialog {
         let browse_button = gtk4::Button::with_label(&gettext("Browse..."));
         browse_button.set_valign(gtk4::Align::Center);
         credentials_entry.add_suffix(&browse_button);
+        credentials_entry.add_suffix(&CredentialsFileDialog::button(&dialog, &remote_path_entry, &credentials_entry));
         basic_group.add(&credentials_entry);
 
         preferences_page.add(&basic_group);
@@ -82,22 +98,57 @@ impl EditRemoteShareDialog {
             "These options are automatically included in the configuration"
         )));
 
-        // Auto-mount switch
+        // Backend, the switches below only apply to systemd mounts
+        let backend_combo = mount_backend_combo(share.backend);
+        advanced_group.add(&backend_combo);
+
+        // autofs entries have no systemd settings of their own, offer the defaults when switching
+        let (initial_systemd, initial_idle_timeout) = if share.backend.uses_systemd() {
+            (share.systemd, share.idle_timeout)
+        } else {
+            let defaults = AppConfig::new().mount_defaults();
+            (defaults.systemd, defaults.idle_timeout)
+        };
+
+        // Auto-mount switch, the x-systemd.automount option
         let automount_switch = adw::SwitchRow::new();
-        automount_switch.set_title(&gettext("Auto-mount"));
-        automount_switch.set_subtitle(&gettext("Automatically mount on system startup"));
-        automount_switch.set_active(true); // Default enabled
+        automount_switch.set_title(&gettext("Mount on access"));
+        automount_switch.set_subtitle(&gettext("Mount the share the first time it is opened (x-systemd.automount)"));
+        automount_switch.set_active(initial_systemd.automount);
         advanced_group.add(&automount_switch);
 
-        // No auto switch (mount on access)
+        // No auto switch, the noauto option
         let noauto_switch = adw::SwitchRow::new();
-        noauto_switch.set_title(&gettext("Mount on access"));
-        noauto_switch.set_subtitle(&gettext("Only mount when accessed (noauto)"));
-        noauto_switch.set_active(true); // Default enabled
+        noauto_switch.set_title(&gettext("Skip at startup"));
+        noauto_switch.set_subtitle(&gettext("Don't mount the share while the computer starts (noauto)"));
+        noauto_switch.set_active(initial_systemd.noauto);
         advanced_group.add(&noauto_switch);
+        // Idle unmount delay, the x-systemd.idle-timeout option
+        let timeout_combo = idle_timeout_combo(initial_idle_timeout);
+        advanced_group.add(&timeout_combo);
+
+        let update_switches = {
+            let automount_switch = automount_switch.clone();
+            let noauto_switch = noauto_switch.clone();
+            let timeout_combo = timeout_combo.clone();
+            move |combo: &adw::ComboRow| {
+                let systemd = selected_mount_backend(combo).uses_systemd();
+                automount_switch.set_sensitive(systemd);
+                noauto_switch.set_sensitive(systemd);
+                timeout_combo.set_sensitive(systemd);
+            }
+        };
+        update_switches(&backend_combo);
+        backend_combo.connect_selected_notify(update_switches);
 
         preferences_page.add(&advanced_group);
 
+        // Checked before anything is written to the configuration
+        let toast_overlay = adw::ToastOverlay::new();
+        let test_group = adw::PreferencesGroup::new();
+        test_group.add(&connection_test_button(&remote_path_entry, &credentials_entry, &toast_overlay));
+        preferences_page.add(&test_group);
+
         // Information banner
         let info_group = adw::PreferencesGroup::new();
         let info_banner = adw::Banner::new(&gettext(
@@ -110,57 +161,87 @@ impl EditRemoteShareDialog {
         info_group.add(&banner_box);
         preferences_page.add(&info_group);
 
+        // Deletion, for entries of the configuration
+        let delete_button = gtk4::Button::with_label(&gettext("Delete Share"));
+        delete_button.set_halign(gtk4::Align::Center);
+        delete_button.add_css_class("pill");
+        delete_button.add_css_class("destructive-action");
+        if list_toast_overlay.is_some() {
+            let delete_group = adw::PreferencesGroup::new();
+            delete_group.add(&delete_button);
+            preferences_page.add(&delete_group);
+        }
+
         toolbar_view.set_content(Some(&preferences_page));
 
         // Add action buttons in header
         let cancel_button = gtk4::Button::with_label(&gettext("Cancel"));
         header_bar.pack_start(&cancel_button);
+        header_bar.set_show_start_title_buttons(false);
+        header_bar.set_show_end_title_buttons(false);
 
         let save_button = gtk4::Button::with_label(&gettext("Save Changes"));
         save_button.add_css_class("suggested-action");
         header_bar.pack_end(&save_button);
 
         // Wrap toolbar in toast overlay for error messages
-        let toast_overlay = adw::ToastOverlay::new();
         toast_overlay.set_child(Some(&toolbar_view));
 
-        window.set_content(Some(&toast_overlay));
+        dialog.set_child(Some(&toast_overlay));
 
         // Store original name for updating
         let original_name = share.name.clone();
 
         // Handle browse button for credentials file
-        let window_clone_for_browse = window.clone();
+        let dialog_clone_for_browse = dialog.clone();
         let credentials_entry_clone = credentials_entry.clone();
         browse_button.connect_clicked(move |_| {
-            let dialog = gtk4::FileDialog::new();
-            dialog.set_title(&gettext("Select Credentials File"));
-
-            let credentials_entry_clone2 = credentials_entry_clone.clone();
-            dialog.open(Some(&window_clone_for_browse), None::<&gtk4::gio::Cancellable>, move |result| {
-                if let Ok(file) = result {
-                    if let Some(path) = file.path() {
-                        credentials_entry_clone2.set_text(&path.to_string_lossy());
-                    }
-                }
-            });
+            browse_file(&dialog_clone_for_browse, &credentials_entry_clone, &gettext("Select Credentials File"));
         });
 
+        // Deleting needs the mount point typed, and unmounts the share first when asked
+        if let Some(list_toast_overlay) = list_toast_overlay {
+            let share = share.clone();
+            let list_toast_overlay = list_toast_overlay.clone();
+            let dialog_clone = dialog.clone();
+            delete_button.connect_clicked(move |button| {
+                let dialog = dialog_clone.clone();
+                let is_mounted = is_mounted(Path::new(&share.name));
+                start_delete(
+                    button,
+                    share.clone(),
+                    share.name.clone(),
+                    is_mounted,
+                    list_toast_overlay.clone(),
+                    move || {
+                        dialog.close();
+                    },
+                );
+            });
+        }
+
         // Handle cancel button
-        let window_clone = window.clone();
+        let dialog_clone = dialog.clone();
         cancel_button.connect_clicked(move |_| {
-            window_clone.close();
+            dialog_clone.close();
         });
 
         // Handle save button
-        let window_clone2 = window.clone();
+        let dialog_clone2 = dialog.clone();
         let mount_point_entry_clone = mount_point_entry.clone();
         let remote_path_entry_clone = remote_path_entry.clone();
         let credentials_entry_clone = credentials_entry.clone();
         let uid_entry_clone = uid_entry.clone();
         let gid_entry_clone = gid_entry.clone();
         let toast_overlay_clone = toast_overlay.clone();
+        let backend_combo_clone = backend_combo.clone();
+        let timeout_combo_clone = timeout_combo.clone();
+        let automount_switch_clone = automount_switch.clone();
+        let noauto_switch_clone = noauto_switch.clone();
         let original_name_clone = original_name.clone();
+        let source_file = share.source_file.clone();
+        let extra_options = share.extra_options.clone();
+        let initial_options = share.mount_options();
 
         save_button.connect_clicked(move |_| {
             let mount_point = mount_point_entry_clone.text();
@@ -211,7 +292,7 @@ impl EditRemoteShareDialog {
             }
 
             // Update configuration in NixOS
-            let updated_share = RemoteSambaShareConfig::new(
+            let mut updated_share = RemoteSambaShareConfig::new(
                 mount_point.to_string(),
                 remote_path.to_string(),
                 "cifs".to_string(),
@@ -219,38 +300,138 @@ impl EditRemoteShareDialog {
                 uid.to_string(),
                 gid.to_string(),
             );
-
-            match updated_share.update(&original_name_clone) {
-                Ok(_) => {
-                    eprintln!(
-                        "Remote share updated: mount_point={}, remote_path={}, credentials={}, uid={}, gid={}",
-                        mount_point, remote_path, credentials, uid, gid
-                    );
-                    let toast = adw::Toast::new(&gettext("Share updated successfully. Run 'sudo nixos-rebuild switch' to apply changes."));
-                    toast_overlay_clone.add_toast(toast);
-                    window_clone2.close();
+            updated_share.source_file = source_file.clone();
+            updated_share.extra_options = extra_options.clone();
+            updated_share.backend = selected_mount_backend(&backend_combo_clone);
+            updated_share.idle_timeout = if updated_share.backend.uses_systemd() {
+                selected_idle_timeout(&timeout_combo_clone, initial_idle_timeout)
+            } else {
+                None
+            };
+            updated_share.systemd = if updated_share.backend.uses_systemd() {
+                SystemdMount {
+                    automount: automount_switch_clone.is_active(),
+                    noauto: noauto_switch_clone.is_active(),
+                    ..initial_systemd
                 }
-                Err(e) => {
-                    eprintln!("Failed to update remote share: {}", e);
-                    let error_msg = format!("{}: {}", gettext("Failed to update share"), e);
-                    let toast = adw::Toast::new(&error_msg);
-                    toast_overlay_clone.add_toast(toast);
+            } else {
+                SystemdMount::NONE
+            };
+
+            // A new mount point also moves the share out of the old folder
+            if updated_share.name != original_name_clone {
+                confirm_move(&dialog_clone2, &toast_overlay_clone, updated_share, original_name_clone.clone());
+                return;
+            }
+
+            if save_share(&updated_share, &original_name_clone, &toast_overlay_clone) {
+                // Mounted with the previous options, it can be remounted once they are applied
+                if updated_share.mount_options() != initial_options && is_mounted(Path::new(&updated_share.name)) {
+                    AppState::get().add_pending_remount(&updated_share.name);
                 }
+                dialog_clone2.close();
             }
         });
 
         Self {
-            window,
+            dialog,
             original_name,
         }
     }
 
     pub fn present(&self, parent: Option<&impl IsA<gtk4::Widget>>) {
-        if let Some(p) = parent {
-            if let Some(parent_window) = p.dynamic_cast_ref::<gtk4::Window>() {
-                self.window.set_transient_for(Some(parent_window));
-            }
+        self.dialog.present(parent);
+    }
+}
+
+/// Write the edited entry as a pending change, reporting failures in a toast
+fn save_share(share: &RemoteSambaShareConfig, original_name: &str, toast_overlay: &adw::ToastOverlay) -> bool {
+    match share.update(original_name) {
+        Ok(change) => {
+            // Open share lists pick the change up from the shared state
+            let state = AppState::get();
+            state.add_pending_change(change);
+            state.reload_remote();
+
+            eprintln!(
+                "Remote share updated: mount_point={}, remote_path={}, credentials={}, uid={}, gid={}",
+                share.name, share.remote_path, share.option_credentials, share.force_user, share.force_group
+            );
+            let toast = adw::Toast::new(&gettext("Share updated successfully. Run 'sudo nixos-rebuild switch' to apply changes."));
+            toast_overlay.add_toast(toast);
+            true
+        }
+        Err(e) => {
+            eprintln!("Failed to update remote share: {}", e);
+            let error_msg = format!("{}: {}", gettext("Failed to update share"), e);
+            let toast = adw::Toast::new(&error_msg);
+            toast_overlay.add_toast(toast);
+            false
         }
-        self.window.present();
     }
 }
+
+/// Move the share to a new mount point: unmount the old one, update the entry,
+/// create the new folder and, when asked, leave a link at the old path
+fn confirm_move(
+    dialog: &adw::Dialog,
+    toast_overlay: &adw::ToastOverlay,
+    share: RemoteSambaShareConfig,
+    old_mount_point: String,
+) {
+    let body = gettext("The share is unmounted from {old} and the folder {new} is created. It is mounted there after the next rebuild.")
+        .replace("{old}", &old_mount_point)
+        .replace("{new}", &share.name);
+    let alert = adw::AlertDialog::new(Some(&gettext("Move Mount Point?")), Some(&body));
+    alert.add_responses(&[("cancel", &gettext("Cancel")), ("move", &gettext("Move"))]);
+    alert.set_response_appearance("move", adw::ResponseAppearance::Suggested);
+    alert.set_default_response(Some("move"));
+    alert.set_close_response("cancel");
+
+    let link_check = gtk4::CheckButton::with_label(&gettext("Leave a link at the old path to the new one"));
+    alert.set_extra_child(Some(&link_check));
+
+    let dialog_clone = dialog.clone();
+    let toast_overlay = toast_overlay.clone();
+    alert.connect_response(Some("move"), move |_, _| {
+        let link_old = link_check.is_active();
+        let share = share.clone();
+        let old_mount_point = old_mount_point.clone();
+        let dialog = dialog_clone.clone();
+        let toast_overlay = toast_overlay.clone();
+
+        glib::spawn_future_local(async move {
+            let old_path = PathBuf::from(&old_mount_point);
+            let released = gio::spawn_blocking(move || release_mount_point(&old_path))
+                .await
+                .unwrap_or_else(|e| Err(format!("{:?}", e)));
+            if let Err(e) = released {
+                eprintln!("Failed to release {}: {}", old_mount_point, e);
+                let error_msg = format!("{}: {}", gettext("Failed to unmount the old mount point"), e);
+                toast_overlay.add_toast(adw::Toast::new(&error_msg));
+                return;
+            }
+
+            if !save_share(&share, &old_mount_point, &toast_overlay) {
+                return;
+            }
+
+            let old_path = PathBuf::from(&old_mount_point);
+            let new_path = PathBuf::from(&share.name);
+            let moved = gio::spawn_blocking(move || move_mount_point(&old_path, &new_path, link_old))
+                .await
+                .unwrap_or_else(|e| Err(format!("{:?}", e)));
+            match moved {
+                Ok(()) => {
+                    dialog.close();
+                }
+                Err(e) => {
+                    eprintln!("Failed to move mount point: {}", e);
+                    let error_msg = format!("{}: {}", gettext("Share updated, but the folders could not be moved"), e);
+                    toast_overlay.add_toast(adw::Toast::new(&error_msg));
+                }
+            }
+        });
+    });
+    alert.present(Some(dialog));
+}
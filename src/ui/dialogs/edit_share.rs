@@ -1,20 +1,24 @@
+use crate::models::AppState;
 use crate::samba::share_config::{get_system_groups, get_system_users, SambaShareConfig};
+use crate::ui::dialogs::ChangePreviewDialog;
+use crate::ui::widgets::{browse_folder, load_combo_names, selected_string, ShareAdvancedOptions};
 use gettextrs::gettext;
 use gtk4::prelude::*;
 use libadwaita as adw;
 use libadwaita::prelude::*;
+use std::rc::Rc;
 
 pub struct EditShareDialog {
-    window: adw::Window,
+    dialog: adw::Dialog,
     original_name: String,
 }
 
 impl EditShareDialog {
     pub fn new(share: &SambaShareConfig) -> Self {
-        let window = adw::Window::new();
-        window.set_title(Some(&gettext("Edit Samba Share")));
-        window.set_default_size(500, 600);
-        window.set_modal(true);
+        let dialog = adw::Dialog::new();
+        dialog.set_title(&gettext("Edit Samba Share"));
+        dialog.set_content_width(500);
+        dialog.set_content_height(600);
 
         // Create toolbar header
         let toolbar_view = adw::ToolbarView::new();
@@ -82,17 +86,8 @@ impl EditShareDialog {
         force_user_combo.set_title(&gettext("Force User"));
         force_user_combo.set_subtitle(&gettext("Force all file operations as this user"));
 
-        // Get system users and set selection
-        let users = get_system_users();
-        let user_list = gtk4::StringList::new(&users.iter().map(|s| s.as_str()).collect::<Vec<_>>());
-        force_user_combo.set_model(Some(&user_list));
-
-        // Find and select the current user
-        if let Some(pos) = users.iter().position(|u| u == &share.force_user) {
-            force_user_combo.set_selected(pos as u32);
-        } else {
-            force_user_combo.set_selected(0);
-        }
+        // Get system users in the background, keeping the current user selected
+        load_combo_names(&force_user_combo, &share.force_user, get_system_users);
         user_group_group.add(&force_user_combo);
 
         // Force Group dropdown
@@ -100,65 +95,57 @@ impl EditShareDialog {
         force_group_combo.set_title(&gettext("Force Group"));
         force_group_combo.set_subtitle(&gettext("Force all file operations as this group"));
 
-        // Get system groups and set selection
-        let groups = get_system_groups();
-        let group_list = gtk4::StringList::new(&groups.iter().map(|s| s.as_str()).collect::<Vec<_>>());
-        force_group_combo.set_model(Some(&group_list));
-
-        // Find and select the current group
-        if let Some(pos) = groups.iter().position(|g| g == &share.force_group) {
-            force_group_combo.set_selected(pos as u32);
-        } else {
-            force_group_combo.set_selected(0);
-        }
+        // Get system groups in the background, keeping the current group selected
+        load_combo_names(&force_group_combo, &share.force_group, get_system_groups);
         user_group_group.add(&force_group_combo);
 
         preferences_page.add(&user_group_group);
 
+        // Advanced options
+        let advanced_options = Rc::new(ShareAdvancedOptions::new());
+        advanced_options.follow_guest_switch(&guest_ok_switch);
+        advanced_options.load(share);
+        preferences_page.add(advanced_options.group());
+
         toolbar_view.set_content(Some(&preferences_page));
 
         // Add action buttons in header
         let cancel_button = gtk4::Button::with_label(&gettext("Cancel"));
         header_bar.pack_start(&cancel_button);
+        header_bar.set_show_start_title_buttons(false);
+        header_bar.set_show_end_title_buttons(false);
 
         let save_button = gtk4::Button::with_label(&gettext("Save Changes"));
         save_button.add_css_class("suggested-action");
         header_bar.pack_end(&save_button);
 
+        let preview_button = gtk4::Button::from_icon_name("document-properties-symbolic");
+        preview_button.set_tooltip_text(Some(&gettext("Preview Changes")));
+        header_bar.pack_end(&preview_button);
+
         // Wrap toolbar in toast overlay for error messages
         let toast_overlay = adw::ToastOverlay::new();
         toast_overlay.set_child(Some(&toolbar_view));
 
-        window.set_content(Some(&toast_overlay));
+        dialog.set_child(Some(&toast_overlay));
 
         // Store original name for updating
         let original_name = share.name.clone();
 
         // Handle browse button
-        let window_clone_for_browse = window.clone();
+        let dialog_clone_for_browse = dialog.clone();
         let path_entry_clone = path_entry.clone();
         browse_button.connect_clicked(move |_| {
-            let dialog = gtk4::FileDialog::new();
-            dialog.set_title(&gettext("Select Folder"));
-
-            let path_entry_clone2 = path_entry_clone.clone();
-            dialog.select_folder(Some(&window_clone_for_browse), None::<&gtk4::gio::Cancellable>, move |result| {
-                if let Ok(folder) = result {
-                    if let Some(path) = folder.path() {
-                        path_entry_clone2.set_text(&path.to_string_lossy());
-                    }
-                }
-            });
+            browse_folder(&dialog_clone_for_browse, &path_entry_clone, &gettext("Select Folder"));
         });
 
         // Handle cancel button
-        let window_clone = window.clone();
+        let dialog_clone = dialog.clone();
         cancel_button.connect_clicked(move |_| {
-            window_clone.close();
+            dialog_clone.close();
         });
 
-        // Handle save button
-        let window_clone2 = window.clone();
+        // Build the share described by the form, or the message for a missing field
         let name_entry_clone = name_entry.clone();
         let path_entry_clone2 = path_entry.clone();
         let browsable_switch_clone = browsable_switch.clone();
@@ -166,74 +153,89 @@ impl EditShareDialog {
         let guest_ok_switch_clone = guest_ok_switch.clone();
         let force_user_combo_clone = force_user_combo.clone();
         let force_group_combo_clone = force_group_combo.clone();
-        let toast_overlay_clone = toast_overlay.clone();
-        let original_name_clone = original_name.clone();
-
-        save_button.connect_clicked(move |_| {
+        let advanced_options_clone = advanced_options.clone();
+        let original_share = share.clone();
+        let build_share = Rc::new(move || -> Result<SambaShareConfig, String> {
             let name = name_entry_clone.text();
             let path = path_entry_clone2.text();
 
             // Validate required fields
             if name.is_empty() {
-                let toast = adw::Toast::new(&gettext("Share name is required"));
-                toast_overlay_clone.add_toast(toast);
-                return;
+                return Err(gettext("Share name is required"));
             }
 
             if path.is_empty() {
-                let toast = adw::Toast::new(&gettext("Path is required"));
-                toast_overlay_clone.add_toast(toast);
-                return;
+                return Err(gettext("Path is required"));
             }
 
-            let browsable = browsable_switch_clone.is_active();
-            let read_only = read_only_switch_clone.is_active();
-            let guest_ok = guest_ok_switch_clone.is_active();
-
-            let force_user = if let Some(model) = force_user_combo_clone.model() {
-                if let Some(string_list) = model.dynamic_cast_ref::<gtk4::StringList>() {
-                    string_list.string(force_user_combo_clone.selected())
-                        .map(|s| s.to_string())
-                        .unwrap_or_default()
-                } else {
-                    String::new()
+            // Start from the loaded share so options without a widget are kept
+            let mut updated_share = original_share.clone();
+            updated_share.name = name.to_string();
+            updated_share.path = path.to_string();
+            updated_share.browsable = browsable_switch_clone.is_active();
+            updated_share.read_only = read_only_switch_clone.is_active();
+            updated_share.guest_ok = guest_ok_switch_clone.is_active();
+            updated_share.force_user = selected_string(&force_user_combo_clone);
+            updated_share.force_group = selected_string(&force_group_combo_clone);
+            advanced_options_clone.apply(&mut updated_share);
+            Ok(updated_share)
+        });
+
+        // Handle preview button
+        let dialog_clone_for_preview = dialog.clone();
+        let toast_overlay_clone = toast_overlay.clone();
+        let original_name_clone = original_name.clone();
+        let build_share_clone = build_share.clone();
+        preview_button.connect_clicked(move |_| {
+            let updated_share = match build_share_clone() {
+                Ok(updated_share) => updated_share,
+                Err(msg) => {
+                    toast_overlay_clone.add_toast(adw::Toast::new(&msg));
+                    return;
                 }
-            } else {
-                String::new()
             };
 
-            let force_group = if let Some(model) = force_group_combo_clone.model() {
-                if let Some(string_list) = model.dynamic_cast_ref::<gtk4::StringList>() {
-                    string_list.string(force_group_combo_clone.selected())
-                        .map(|s| s.to_string())
-                        .unwrap_or_default()
-                } else {
-                    String::new()
+            match updated_share.prepare_update(&original_name_clone) {
+                Ok(change) => {
+                    let preview = ChangePreviewDialog::new(&change, &updated_share.to_nix());
+                    preview.present(Some(&dialog_clone_for_preview));
+                }
+                Err(e) => {
+                    let error_msg = format!("{}: {}", gettext("Failed to preview changes"), e);
+                    toast_overlay_clone.add_toast(adw::Toast::new(&error_msg));
+                }
+            }
+        });
+
+        // Handle save button
+        let dialog_clone2 = dialog.clone();
+        let toast_overlay_clone = toast_overlay.clone();
+        let original_name_clone = original_name.clone();
+
+        save_button.connect_clicked(move |_| {
+            let updated_share = match build_share() {
+                Ok(updated_share) => updated_share,
+                Err(msg) => {
+                    toast_overlay_clone.add_toast(adw::Toast::new(&msg));
+                    return;
                 }
-            } else {
-                String::new()
             };
 
             // Update configuration in NixOS
-            let updated_share = SambaShareConfig::new(
-                name.to_string(),
-                path.to_string(),
-                browsable,
-                read_only,
-                guest_ok,
-                force_user,
-                force_group,
-            );
-
             match updated_share.update(&original_name_clone) {
-                Ok(_) => {
+                Ok(change) => {
+                    // Open share lists pick the change up from the shared state
+                    let state = AppState::get();
+                    state.add_pending_change(change);
+                    state.reload_local();
+
                     eprintln!(
                         "Share updated: name={}, path={}, browsable={}, read_only={}, guest_ok={}, force_user={}, force_group={}",
-                        name, path, browsable, read_only, guest_ok, updated_share.force_user, updated_share.force_group
+                        updated_share.name, updated_share.path, updated_share.browsable, updated_share.read_only, updated_share.guest_ok, updated_share.force_user, updated_share.force_group
                     );
                     let toast = adw::Toast::new(&gettext("Share updated successfully. Please rebuild NixOS to apply changes."));
                     toast_overlay_clone.add_toast(toast);
-                    window_clone2.close();
+                    dialog_clone2.close();
                 }
                 Err(e) => {
                     eprintln!("Failed to update share: {}", e);
@@ -245,17 +247,12 @@ impl EditShareDialog {
         });
 
         Self {
-            window,
+            dialog,
             original_name,
         }
     }
 
     pub fn present(&self, parent: Option<&impl IsA<gtk4::Widget>>) {
-        if let Some(p) = parent {
-            if let Some(parent_window) = p.dynamic_cast_ref::<gtk4::Window>() {
-                self.window.set_transient_for(Some(parent_window));
-            }
-        }
-        self.window.present();
+        self.dialog.present(parent);
     }
 }
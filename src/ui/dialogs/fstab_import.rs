@@ -0,0 +1,262 @@
+use crate::models::AppState;
+use crate::samba::fstab_import::{comment_out, is_generated, load, FstabEntry, FSTAB};
+use crate::samba::import_merge::{changes, plan, MergeItem};
+use crate::samba::{ConfigChange, RemoteSambaShareConfig};
+use crate::ui::widgets::{allow_write, browse_file, merge_row, MergeChoice};
+use gettextrs::gettext;
+use gtk4::prelude::*;
+use gtk4::{gio, glib};
+use libadwaita as adw;
+use libadwaita::prelude::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Moves the SMB shares of an fstab file, left by a previous setup, to the NixOS configuration
+pub struct FstabImportDialog {
+    dialog: adw::Dialog,
+}
+
+/// Widgets and entries of the importer, shared by its callbacks
+struct Importer {
+    dialog: adw::Dialog,
+    toast_overlay: adw::ToastOverlay,
+    path_entry: adw::EntryRow,
+    list: gtk4::ListBox,
+    comment_switch: adw::SwitchRow,
+    import_button: gtk4::Button,
+    /// Path the entries were read from, and the shares found with the choice made for them
+    scanned: RefCell<(String, Vec<ImportItem>)>,
+}
+
+/// Share found in the file
+struct ImportItem {
+    item: MergeItem,
+    /// Line to comment out once imported
+    line: usize,
+    choice: MergeChoice,
+}
+
+impl FstabImportDialog {
+    pub fn new() -> Self {
+        let dialog = adw::Dialog::new();
+        dialog.set_title(&gettext("Import from fstab"));
+        dialog.set_content_width(600);
+        dialog.set_content_height(550);
+
+        // Create toolbar header
+        let toolbar_view = adw::ToolbarView::new();
+        let header_bar = adw::HeaderBar::new();
+        let import_button = gtk4::Button::with_label(&gettext("Import"));
+        import_button.add_css_class("suggested-action");
+        import_button.set_sensitive(false);
+        header_bar.pack_end(&import_button);
+        toolbar_view.add_top_bar(&header_bar);
+
+        let toast_overlay = adw::ToastOverlay::new();
+        let preferences_page = adw::PreferencesPage::new();
+
+        // File to read, /etc/fstab or a copy saved from the old system
+        let source_group = adw::PreferencesGroup::new();
+        source_group.set_description(Some(&gettext(
+            "SMB shares mounted by an fstab file are added to the NixOS configuration, where they mount on first access",
+        )));
+        let path_entry = adw::EntryRow::new();
+        path_entry.set_title(&gettext("File"));
+        path_entry.set_text(FSTAB);
+        path_entry.set_show_apply_button(true);
+        let browse_button = gtk4::Button::with_label(&gettext("Browse..."));
+        browse_button.set_valign(gtk4::Align::Center);
+        path_entry.add_suffix(&browse_button);
+        source_group.add(&path_entry);
+        preferences_page.add(&source_group);
+
+        // Shares found in the file
+        let entries_group = adw::PreferencesGroup::new();
+        entries_group.set_title(&gettext("Shares Found"));
+        entries_group.set_description(Some(&gettext(
+            "Mount points already configured with other settings keep them unless the imported version is chosen",
+        )));
+        let list = gtk4::ListBox::new();
+        list.add_css_class("boxed-list");
+        list.set_selection_mode(gtk4::SelectionMode::None);
+        let placeholder = gtk4::Label::new(Some(&gettext("No SMB shares in this file")));
+        placeholder.add_css_class("dim-label");
+        placeholder.set_margin_top(12);
+        placeholder.set_margin_bottom(12);
+        list.set_placeholder(Some(&placeholder));
+        entries_group.add(&list);
+        preferences_page.add(&entries_group);
+
+        let options_group = adw::PreferencesGroup::new();
+        let comment_switch = adw::SwitchRow::new();
+        comment_switch.set_title(&gettext("Comment Out the fstab Lines"));
+        options_group.add(&comment_switch);
+        preferences_page.add(&options_group);
+
+        toast_overlay.set_child(Some(&preferences_page));
+        toolbar_view.set_content(Some(&toast_overlay));
+        dialog.set_child(Some(&toolbar_view));
+
+        let importer = Rc::new(Importer {
+            dialog: dialog.clone(),
+            toast_overlay,
+            path_entry: path_entry.clone(),
+            list,
+            comment_switch,
+            import_button: import_button.clone(),
+            scanned: RefCell::new((String::new(), Vec::new())),
+        });
+
+        let path_entry_clone = path_entry.clone();
+        browse_button.connect_clicked(move |button| {
+            browse_file(button, &path_entry_clone, &gettext("Select an fstab File"));
+        });
+
+        // Typed paths are read on apply, picked ones as soon as the chooser writes them
+        let importer_clone = importer.clone();
+        path_entry.connect_apply(move |_| Importer::scan(&importer_clone));
+        let importer_clone = importer.clone();
+        path_entry.connect_changed(move |entry| {
+            let typing = entry.delegate().is_some_and(|text| text.has_focus());
+            if !typing {
+                Importer::scan(&importer_clone);
+            }
+        });
+
+        let importer_clone = importer.clone();
+        import_button.connect_clicked(move |button| {
+            if !allow_write(button) {
+                return;
+            }
+            Importer::import(&importer_clone);
+        });
+
+        Importer::scan(&importer);
+
+        Self { dialog }
+    }
+
+    pub fn present(&self, parent: Option<&impl IsA<gtk4::Widget>>) {
+        self.dialog.present(parent);
+    }
+}
+
+impl Importer {
+    /// Read the file in the background, then list its SMB shares against the configured ones
+    fn scan(importer: &Rc<Self>) {
+        let path = importer.path_entry.text().trim().to_string();
+        importer.import_button.set_sensitive(false);
+        while let Some(row) = importer.list.first_child() {
+            importer.list.remove(&row);
+        }
+        importer.scanned.replace((path.clone(), Vec::new()));
+
+        let importer = importer.clone();
+        glib::spawn_future_local(async move {
+            let path_clone = path.clone();
+            let result = gio::spawn_blocking(move || {
+                let entries = load(&path_clone)?;
+                let configured = RemoteSambaShareConfig::load_all()?;
+                let items = plan(entries.iter().map(|entry| entry.share.clone()).collect(), &configured);
+                Ok((entries, items, is_generated(&path_clone)))
+            })
+            .await
+            .unwrap_or_else(|e| Err(format!("{:?}", e)));
+
+            // Another file was picked in the meantime
+            if importer.scanned.borrow().0 != path {
+                return;
+            }
+            match result {
+                Ok((entries, items, generated)) => Self::show_items(&importer, &entries, items, generated),
+                Err(e) => {
+                    eprintln!("Failed to read {}: {}", path, e);
+                    let error_msg = format!("{}: {}", gettext("Failed to read the file"), e);
+                    importer.toast_overlay.add_toast(adw::Toast::new(&error_msg));
+                }
+            }
+        });
+    }
+
+    fn show_items(importer: &Rc<Self>, entries: &[FstabEntry], items: Vec<MergeItem>, generated: bool) {
+        let mut rows = Vec::new();
+        for item in items {
+            // The first line of a mount point is the one imported
+            let Some(entry) = entries.iter().find(|entry| entry.share.name == item.name()) else {
+                continue;
+            };
+            let password_note = gettext("The password is left out, add a credentials file after the import");
+            let (row, choice) = merge_row(&item, entry.had_password.then_some(password_note.as_str()));
+
+            let importer_clone = importer.clone();
+            choice.connect_changed(move || importer_clone.update_import_button());
+            importer.list.append(&row);
+            rows.push(ImportItem {
+                item,
+                line: entry.line,
+                choice,
+            });
+        }
+        importer.scanned.borrow_mut().1 = rows;
+
+        // On NixOS /etc/fstab is generated from the configuration itself
+        importer.comment_switch.set_sensitive(!generated);
+        importer.comment_switch.set_active(!generated);
+        importer.comment_switch.set_subtitle(&if generated {
+            gettext("NixOS generates this file, it can't be edited")
+        } else {
+            gettext("Keep the lines as comments so the shares aren't mounted twice")
+        });
+        importer.update_import_button();
+    }
+
+    fn update_import_button(&self) {
+        let any_kept = self.scanned.borrow().1.iter().any(|row| row.choice.keeps());
+        self.import_button.set_sensitive(any_kept);
+    }
+
+    /// Write the chosen additions and replacements, and comment their lines out when asked,
+    /// all at once: when one file can't be written the others are restored
+    fn import(importer: &Rc<Self>) {
+        let (path, items, lines) = {
+            let scanned = importer.scanned.borrow();
+            let items: Vec<(MergeItem, bool)> = scanned
+                .1
+                .iter()
+                .map(|row| (row.item.clone(), row.choice.keeps()))
+                .collect();
+            let lines: Vec<usize> = scanned
+                .1
+                .iter()
+                .filter(|row| row.choice.keeps())
+                .map(|row| row.line)
+                .collect();
+            (scanned.0.clone(), items, lines)
+        };
+
+        let result = changes(&items).and_then(|mut changes| {
+            if importer.comment_switch.is_active() && !lines.is_empty() {
+                changes.push(comment_out(&path, &lines)?);
+            }
+            ConfigChange::apply_all(&changes)?;
+            Ok(changes)
+        });
+
+        match result {
+            Ok(changes) => {
+                eprintln!("Imported {} remote shares from {}", lines.len(), path);
+                let state = AppState::get();
+                for change in changes {
+                    state.add_pending_change(change);
+                }
+                state.reload_remote();
+                importer.dialog.close();
+            }
+            Err(e) => {
+                eprintln!("Failed to import shares from {}: {}", path, e);
+                let error_msg = format!("{}: {}", gettext("Failed to import shares"), e);
+                importer.toast_overlay.add_toast(adw::Toast::new(&error_msg));
+            }
+        }
+    }
+}
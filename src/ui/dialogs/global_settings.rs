@@ -0,0 +1,421 @@
+use crate::samba::global_config::{protocol_rank, SMB_PROTOCOLS};
+use crate::samba::homes::HomesConfig;
+use crate::samba::network::{invalid_host_entries, lan_subnets, network_interfaces, LOOPBACK};
+use crate::samba::share_config::parse_name_list;
+use crate::samba::warnings::{global_warnings, ConfigWarning};
+use crate::samba::SambaGlobalConfig;
+use crate::ui::widgets::{
+    security_level_combo, select_security_level, selected_security_level, warning_row,
+};
+use gettextrs::gettext;
+use gtk4::prelude::*;
+use libadwaita as adw;
+use libadwaita::prelude::*;
+
+pub struct GlobalSettingsDialog {
+    dialog: adw::Dialog,
+}
+
+impl GlobalSettingsDialog {
+    pub fn new() -> Self {
+        let dialog = adw::Dialog::new();
+        dialog.set_title(&gettext("Server Settings"));
+        dialog.set_content_width(500);
+        dialog.set_content_height(500);
+
+        // Create toolbar header
+        let toolbar_view = adw::ToolbarView::new();
+        let header_bar = adw::HeaderBar::new();
+        toolbar_view.add_top_bar(&header_bar);
+
+        // Create preferences page for the form
+        let preferences_page = adw::PreferencesPage::new();
+
+        // Wrap toolbar in toast overlay for error messages
+        let toast_overlay = adw::ToastOverlay::new();
+        toast_overlay.set_child(Some(&toolbar_view));
+
+        // Load current global settings
+        let global = match SambaGlobalConfig::load() {
+            Ok(global) => global,
+            Err(e) => {
+                eprintln!("Failed to load global settings: {}", e);
+                let error_msg = format!("{}: {}", gettext("Failed to load server settings"), e);
+                toast_overlay.add_toast(adw::Toast::new(&error_msg));
+                SambaGlobalConfig::default()
+            }
+        };
+        let homes = HomesConfig::load().unwrap_or_else(|e| {
+            eprintln!("Failed to load the homes section: {}", e);
+            HomesConfig::default()
+        });
+
+        // Security Group
+        let security_group = adw::PreferencesGroup::new();
+        security_group.set_title(&gettext("Security"));
+        security_group.set_description(Some(&gettext(
+            "Defaults applied to every share unless a share overrides them",
+        )));
+
+        let security_expander = adw::ExpanderRow::new();
+        security_expander.set_title(&gettext("Encryption and Signing"));
+        security_expander.set_subtitle(&gettext("Requirements for client connections"));
+        security_expander.set_expanded(true);
+
+        let encrypt_combo = security_level_combo(
+            &gettext("Encryption"),
+            &gettext("Require clients to encrypt traffic (smb encrypt)"),
+        );
+        select_security_level(&encrypt_combo, global.smb_encrypt);
+        security_expander.add_row(&encrypt_combo);
+
+        let signing_combo = security_level_combo(
+            &gettext("Signing"),
+            &gettext("Require clients to sign packets (server signing)"),
+        );
+        select_security_level(&signing_combo, global.server_signing);
+        security_expander.add_row(&signing_combo);
+
+        security_group.add(&security_expander);
+        preferences_page.add(&security_group);
+
+        // Protocols Group
+        let protocol_group = adw::PreferencesGroup::new();
+        protocol_group.set_title(&gettext("Protocols"));
+        protocol_group.set_description(Some(&gettext(
+            "Range of SMB protocol versions accepted from clients",
+        )));
+
+        let min_protocol_combo = protocol_combo(
+            &gettext("Minimum Protocol"),
+            &gettext("Oldest protocol clients may use (server min protocol)"),
+            global.server_min_protocol.as_deref(),
+        );
+        protocol_group.add(&min_protocol_combo);
+
+        let max_protocol_combo = protocol_combo(
+            &gettext("Maximum Protocol"),
+            &gettext("Newest protocol offered to clients (server max protocol)"),
+            global.server_max_protocol.as_deref(),
+        );
+        protocol_group.add(&max_protocol_combo);
+
+        // Warning shown while SMB1 is allowed
+        let smb1_warning_row = warning_row(ConfigWarning::Smb1Enabled);
+        protocol_group.add(&smb1_warning_row);
+
+        let smb1_warning_row_clone = smb1_warning_row.clone();
+        min_protocol_combo.connect_selected_notify(move |combo| {
+            let preview = SambaGlobalConfig {
+                server_min_protocol: selected_protocol(combo),
+                ..Default::default()
+            };
+            smb1_warning_row_clone
+                .set_visible(global_warnings(&preview).contains(&ConfigWarning::Smb1Enabled));
+        });
+        smb1_warning_row.set_visible(global_warnings(&global).contains(&ConfigWarning::Smb1Enabled));
+
+        preferences_page.add(&protocol_group);
+
+        // Network Group
+        let network_group = adw::PreferencesGroup::new();
+        network_group.set_title(&gettext("Network"));
+        network_group.set_description(Some(&gettext(
+            "Keep shares off VPN, container or other networks",
+        )));
+
+        let interfaces_expander = adw::ExpanderRow::new();
+        interfaces_expander.set_title(&gettext("Interfaces"));
+        interfaces_expander.set_subtitle(&gettext("Networks Samba serves (interfaces)"));
+
+        // Detected interfaces, then configured entries such as addresses that weren't detected
+        let mut interface_rows: Vec<(String, String)> = network_interfaces()
+            .into_iter()
+            .map(|interface| {
+                let description = if interface.name == LOOPBACK {
+                    gettext("Loopback, used by tools on this computer")
+                } else if interface.physical {
+                    gettext("Network card")
+                } else {
+                    gettext("Virtual interface, e.g. VPN, container or bridge")
+                };
+                (interface.name, description)
+            })
+            .collect();
+        for configured in &global.interfaces {
+            if !interface_rows.iter().any(|(name, _)| name == configured) {
+                interface_rows.push((configured.clone(), gettext("Configured, not detected")));
+            }
+        }
+
+        let mut interface_checks = Vec::new();
+        for (name, description) in interface_rows {
+            let check = gtk4::CheckButton::new();
+            check.set_active(global.interfaces.contains(&name));
+            let row = adw::ActionRow::new();
+            row.set_title(&name);
+            row.set_subtitle(&description);
+            row.add_prefix(&check);
+            row.set_activatable_widget(Some(&check));
+            interfaces_expander.add_row(&row);
+            interface_checks.push((name, check));
+        }
+        network_group.add(&interfaces_expander);
+
+        let bind_interfaces_switch = adw::SwitchRow::new();
+        bind_interfaces_switch.set_title(&gettext("Listen Only on Selected Interfaces"));
+        bind_interfaces_switch.set_subtitle(&gettext(
+            "Refuse connections arriving through other interfaces (bind interfaces only)",
+        ));
+        bind_interfaces_switch.set_active(global.bind_interfaces_only);
+        network_group.add(&bind_interfaces_switch);
+
+        let hosts_allow_entry = adw::EntryRow::new();
+        hosts_allow_entry.set_title(&gettext("Allowed Hosts"));
+        hosts_allow_entry.set_tooltip_text(Some(&gettext(
+            "Addresses, networks like 192.168.1.0/24 or 192.168.1., and host names, separated by spaces. Leave empty to allow every host.",
+        )));
+        hosts_allow_entry.set_text(&global.hosts_allow.join(" "));
+        network_group.add(&hosts_allow_entry);
+
+        // Warning shown while every host is allowed
+        let hosts_warning_row = warning_row(ConfigWarning::HostsAllowAll);
+        hosts_warning_row
+            .set_visible(global_warnings(&global).contains(&ConfigWarning::HostsAllowAll));
+        network_group.add(&hosts_warning_row);
+
+        // Flag entries Samba wouldn't understand while typing
+        hosts_allow_entry.connect_changed(move |entry| {
+            let hosts = parse_name_list(&entry.text());
+            if invalid_host_entries(&hosts).is_empty() {
+                entry.remove_css_class("error");
+            } else {
+                entry.add_css_class("error");
+            }
+
+            let preview = SambaGlobalConfig {
+                hosts_allow: hosts,
+                ..Default::default()
+            };
+            hosts_warning_row
+                .set_visible(global_warnings(&preview).contains(&ConfigWarning::HostsAllowAll));
+        });
+
+        // One button per local network, adding it to the allowed hosts
+        let subnets_row = adw::ActionRow::new();
+        subnets_row.set_title(&gettext("Detected Networks"));
+        subnets_row.set_subtitle(&gettext("Loading..."));
+        let subnets_box = gtk4::Box::new(gtk4::Orientation::Horizontal, 6);
+        subnets_box.set_valign(gtk4::Align::Center);
+        subnets_row.add_suffix(&subnets_box);
+        network_group.add(&subnets_row);
+
+        let hosts_allow_entry_clone = hosts_allow_entry.clone();
+        glib::spawn_future_local(async move {
+            let subnets = gio::spawn_blocking(lan_subnets).await.unwrap_or_else(|e| {
+                eprintln!("Failed to detect local networks: {:?}", e);
+                Vec::new()
+            });
+
+            if subnets.is_empty() {
+                subnets_row.set_subtitle(&gettext("No local network detected"));
+                return;
+            }
+            subnets_row.set_subtitle(&gettext("Click a network to allow it"));
+            for subnet in subnets {
+                let button = gtk4::Button::with_label(&subnet);
+                button.add_css_class("pill");
+                button.add_css_class("small");
+                let entry = hosts_allow_entry_clone.clone();
+                button.connect_clicked(move |_| {
+                    let mut hosts = parse_name_list(&entry.text());
+                    if !hosts.contains(&subnet) {
+                        hosts.push(subnet.clone());
+                        entry.set_text(&hosts.join(" "));
+                    }
+                });
+                subnets_box.append(&button);
+            }
+        });
+
+        preferences_page.add(&network_group);
+
+        // Home Folders Group
+        let homes_group = adw::PreferencesGroup::new();
+        homes_group.set_title(&gettext("Home Folders"));
+        homes_group.set_description(Some(&gettext(
+            "Each person with a Samba password connects to a share named after their user and gets their home folder. No folder needs to be chosen.",
+        )));
+
+        let homes_switch = adw::SwitchRow::new();
+        homes_switch.set_title(&gettext("Share Home Folders"));
+        homes_switch.set_subtitle(&gettext("Adds the special homes section"));
+        homes_switch.set_active(homes.enabled);
+        homes_group.add(&homes_switch);
+
+        let homes_expander = adw::ExpanderRow::new();
+        homes_expander.set_title(&gettext("Home Folder Options"));
+        homes_expander.set_subtitle(&gettext("Apply to every home folder"));
+        homes_switch
+            .bind_property("active", &homes_expander, "sensitive")
+            .sync_create()
+            .build();
+
+        let homes_writable_switch = adw::SwitchRow::new();
+        homes_writable_switch.set_title(&gettext("Allow Changes"));
+        homes_writable_switch.set_subtitle(&gettext(
+            "People can add, change and delete files in their home folder (read only = no)",
+        ));
+        homes_writable_switch.set_active(homes.writable);
+        homes_expander.add_row(&homes_writable_switch);
+
+        let homes_owner_switch = adw::SwitchRow::new();
+        homes_owner_switch.set_title(&gettext("Only the Owner"));
+        homes_owner_switch.set_subtitle(&gettext(
+            "Nobody else can open a home folder, even by typing its name (valid users = %S)",
+        ));
+        homes_owner_switch.set_active(homes.owner_only);
+        homes_expander.add_row(&homes_owner_switch);
+
+        let homes_browseable_switch = adw::SwitchRow::new();
+        homes_browseable_switch.set_title(&gettext("List a \"homes\" Share"));
+        homes_browseable_switch.set_subtitle(&gettext(
+            "Each person's own folder is listed either way. Usually left off to avoid confusion (browseable).",
+        ));
+        homes_browseable_switch.set_active(homes.browseable);
+        homes_expander.add_row(&homes_browseable_switch);
+
+        homes_group.add(&homes_expander);
+        preferences_page.add(&homes_group);
+
+        // Symlinks Group
+        let symlinks_group = adw::PreferencesGroup::new();
+        symlinks_group.set_title(&gettext("Symbolic Links"));
+
+        let insecure_wide_links_switch = adw::SwitchRow::new();
+        insecure_wide_links_switch.set_title(&gettext("Allow Insecure Wide Links"));
+        insecure_wide_links_switch.set_subtitle(&gettext(
+            "Needed for shares with wide links enabled. Lets clients escape shared folders through symlinks.",
+        ));
+        insecure_wide_links_switch.set_active(global.allow_insecure_wide_links);
+        symlinks_group.add(&insecure_wide_links_switch);
+
+        let insecure_warning_row = adw::ActionRow::new();
+        insecure_warning_row.set_title(&gettext("Insecure wide links are allowed"));
+        insecure_warning_row.set_subtitle(&gettext(
+            "Users of any share with wide links can read files anywhere on this computer that the share's user can access.",
+        ));
+        insecure_warning_row.add_prefix(&gtk4::Image::from_icon_name("dialog-warning-symbolic"));
+        insecure_warning_row.add_css_class("warning");
+        insecure_wide_links_switch
+            .bind_property("active", &insecure_warning_row, "visible")
+            .sync_create()
+            .build();
+        symlinks_group.add(&insecure_warning_row);
+
+        preferences_page.add(&symlinks_group);
+
+        toolbar_view.set_content(Some(&preferences_page));
+
+        // Add action buttons in header
+        let cancel_button = gtk4::Button::with_label(&gettext("Cancel"));
+        header_bar.pack_start(&cancel_button);
+        header_bar.set_show_start_title_buttons(false);
+        header_bar.set_show_end_title_buttons(false);
+
+        let save_button = gtk4::Button::with_label(&gettext("Save Changes"));
+        save_button.add_css_class("suggested-action");
+        header_bar.pack_end(&save_button);
+
+        dialog.set_child(Some(&toast_overlay));
+
+        // Handle cancel button
+        let dialog_clone = dialog.clone();
+        cancel_button.connect_clicked(move |_| {
+            dialog_clone.close();
+        });
+
+        // Handle save button
+        let dialog_clone2 = dialog.clone();
+        let toast_overlay_clone = toast_overlay.clone();
+        save_button.connect_clicked(move |_| {
+            let mut updated = global.clone();
+            updated.smb_encrypt = selected_security_level(&encrypt_combo);
+            updated.server_signing = selected_security_level(&signing_combo);
+            updated.server_min_protocol = selected_protocol(&min_protocol_combo);
+            updated.server_max_protocol = selected_protocol(&max_protocol_combo);
+            updated.allow_insecure_wide_links = insecure_wide_links_switch.is_active();
+            updated.interfaces = interface_checks
+                .iter()
+                .filter(|(_, check)| check.is_active())
+                .map(|(name, _)| name.clone())
+                .collect();
+            updated.bind_interfaces_only = bind_interfaces_switch.is_active();
+            updated.hosts_allow = parse_name_list(&hosts_allow_entry.text());
+
+            let updated_homes = HomesConfig {
+                enabled: homes_switch.is_active(),
+                writable: homes_writable_switch.is_active(),
+                browseable: homes_browseable_switch.is_active(),
+                owner_only: homes_owner_switch.is_active(),
+            };
+
+            // The homes section is only rewritten when it was changed
+            let result = updated.save().and_then(|_| {
+                if updated_homes == homes {
+                    Ok(())
+                } else {
+                    updated_homes.save()
+                }
+            });
+            match result {
+                Ok(_) => {
+                    eprintln!("Global settings updated: {:?} {:?}", updated, updated_homes);
+                    dialog_clone2.close();
+                }
+                Err(e) => {
+                    eprintln!("Failed to update global settings: {}", e);
+                    let error_msg =
+                        format!("{}: {}", gettext("Failed to update server settings"), e);
+                    toast_overlay_clone.add_toast(adw::Toast::new(&error_msg));
+                }
+            }
+        });
+
+        Self { dialog }
+    }
+
+    pub fn present(&self, parent: Option<&impl IsA<gtk4::Widget>>) {
+        self.dialog.present(parent);
+    }
+}
+
+/// Create a combo row listing "System default" followed by `SMB_PROTOCOLS`
+fn protocol_combo(title: &str, subtitle: &str, current: Option<&str>) -> adw::ComboRow {
+    let default_label = gettext("System default");
+    let mut labels = vec![default_label.as_str()];
+    labels.extend(SMB_PROTOCOLS.iter().copied());
+    let list = gtk4::StringList::new(&labels);
+
+    let combo = adw::ComboRow::new();
+    combo.set_title(title);
+    combo.set_subtitle(subtitle);
+    combo.set_model(Some(&list));
+
+    let selected = current
+        .and_then(protocol_rank)
+        .map(|rank| rank as u32 + 1)
+        .unwrap_or(0);
+    combo.set_selected(selected);
+    combo
+}
+
+/// Read the protocol selected in a combo created by `protocol_combo`
+fn selected_protocol(combo: &adw::ComboRow) -> Option<String> {
+    match combo.selected() {
+        0 => None,
+        index => SMB_PROTOCOLS
+            .get(index as usize - 1)
+            .map(|protocol| protocol.to_string()),
+    }
+}
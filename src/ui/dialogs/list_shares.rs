@@ -1,43 +1,113 @@
-use crate::samba::SambaShareConfig;
-use crate::ui::dialogs::EditShareDialog;
+use crate::config::{AppConfig, Favorite};
+use crate::models::app_state::LocalShares;
+use crate::models::AppState;
+use crate::samba::access::{share_access, Accounts, ShareAccess};
+use crate::samba::activity::{share_activity, ShareActivity};
+use crate::samba::disk_space::{disk_space, DiskSpace};
+use crate::samba::drift::ActiveConfig;
+use crate::samba::quota::{quota_usage, QuotaKind};
+use crate::samba::share_backend::is_managed_file;
+use crate::samba::share_config::SambaShareConfig;
+use crate::samba::warnings::share_warnings;
+use crate::ui::dialogs::{confirm_delete, EditShareDialog, LogViewerDialog, ReadOnlyShareDialog, RecycleBinDialog};
+use crate::ui::widgets::{
+    allow_write, appearance_button, favorite_button, lock_when_read_only, locked_badge, locked_row, notes_row,
+    progress_group, share_badges, warning_badge, warning_row, QuotaRow,
+};
+use crate::utils::format::format_size;
 use gettextrs::gettext;
 use gtk4::prelude::*;
+use gtk4::{gio, glib};
 use libadwaita as adw;
 use libadwaita::prelude::*;
+use std::rc::Rc;
+
+/// Seconds during which a deletion can be undone from its toast
+pub(crate) const UNDO_TIMEOUT: u32 = 10;
 
 pub struct ListSharesDialog {
-    window: adw::Window,
+    dialog: adw::Dialog,
     toast_overlay: adw::ToastOverlay,
 }
 
 impl ListSharesDialog {
     pub fn new() -> Self {
-        let window = adw::Window::new();
-        window.set_title(Some(&gettext("Samba Shares")));
-        window.set_default_size(700, 500);
-        window.set_modal(true);
+        let dialog = adw::Dialog::new();
+        dialog.set_title(&gettext("Samba Shares"));
+        dialog.set_content_width(700);
+        dialog.set_content_height(500);
 
         // Create toolbar header
         let toolbar_view = adw::ToolbarView::new();
         let header_bar = adw::HeaderBar::new();
         toolbar_view.add_top_bar(&header_bar);
 
-        // Close button
-        let close_button = gtk4::Button::with_label(&gettext("Close"));
-        header_bar.pack_start(&close_button);
-
         // Create scrolled window for shares list
         let scrolled = gtk4::ScrolledWindow::builder()
             .hexpand(true)
             .vexpand(true)
             .build();
 
-        // Create preferences page
+        // Wrap in toast overlay
+        let toast_overlay = adw::ToastOverlay::new();
+        toast_overlay.set_child(Some(&toolbar_view));
+
+        // Header spinner while the configuration is parsed again
+        let spinner = gtk4::Spinner::new();
+        header_bar.pack_end(&spinner);
+        spinner.start();
+
+        // Show what is already known, the progress of the first load otherwise
+        let state = AppState::get();
+        match state.local_shares() {
+            Some(result) => Self::show_shares(&scrolled, &dialog, &toast_overlay, result),
+            None => {
+                let preferences_page = adw::PreferencesPage::new();
+                preferences_page.add(&progress_group(|| AppState::get().local_progress()));
+                scrolled.set_child(Some(&preferences_page));
+            }
+        }
+
+        // Follow every later change to the shares, wherever it comes from
+        let scrolled_clone = scrolled.clone();
+        let dialog_clone = dialog.clone();
+        let toast_overlay_clone = toast_overlay.clone();
+        let handler = state.connect_local_shares_changed(move |state| {
+            spinner.stop();
+            if let Some(result) = state.local_shares() {
+                Self::show_shares(&scrolled_clone, &dialog_clone, &toast_overlay_clone, result);
+            }
+        });
+        state.disconnect_on_close(&dialog, handler);
+        state.reload_local();
+
+        toolbar_view.set_content(Some(&scrolled));
+
+        dialog.set_child(Some(&toast_overlay));
+
+        Self {
+            dialog,
+            toast_overlay,
+        }
+    }
+
+    /// Replace the list with one group per share, or an empty/error state
+    fn show_shares(
+        scrolled: &gtk4::ScrolledWindow,
+        dialog: &adw::Dialog,
+        toast_overlay: &adw::ToastOverlay,
+        result: LocalShares,
+    ) {
+        // A fresh page, since PreferencesPage has no way to clear its groups
         let preferences_page = adw::PreferencesPage::new();
+        scrolled.set_child(Some(&preferences_page));
 
-        // Load shares from configuration
-        match SambaShareConfig::load_all() {
+        match result {
             Ok(shares) => {
+                // Group memberships and the running configuration are read once for all shares
+                let accounts = Accounts::load();
+                let active_config = ActiveConfig::load();
+
                 if shares.is_empty() {
                     // Show empty state
                     let empty_group = adw::PreferencesGroup::new();
@@ -51,17 +121,63 @@ impl ListSharesDialog {
                     empty_group.add(&empty_box);
                     preferences_page.add(&empty_group);
                 } else {
+                    // Activity rows, filled once the journal and smbstatus answered
+                    let mut activity_rows = Vec::new();
+                    // Storage and size limit rows, filled once df answered
+                    let mut storage_rows = Vec::new();
+                    let audited_shares: Vec<String> =
+                        shares.iter().filter(|share| share.audit).map(|share| share.name.clone()).collect();
+                    let managed_files = AppConfig::new().managed_files();
+
                     // Create a group for each share
                     for share in shares {
                         let group = adw::PreferencesGroup::new();
                         group.set_title(&share.name);
 
+                        let badges = gtk4::Box::new(gtk4::Orientation::Horizontal, 6);
+                        badges.append(&appearance_button(Favorite::Local(share.name.clone()), "folder-symbolic"));
+                        for badge in share_badges(&share, active_config.as_ref()) {
+                            badges.append(&badge);
+                        }
+
+                        // Entries built from Nix expressions are shown but not editable
+                        if let Some(reason) = &share.locked_reason {
+                            badges.append(&locked_badge(reason));
+                            group.add(&locked_row(reason, &share.source_file));
+                        }
+
+                        // Risky settings, each explained with a way to fix it
+                        let warnings = share_warnings(&share);
+                        if !warnings.is_empty() {
+                            badges.append(&warning_badge(&warnings));
+                        }
+                        for warning in &warnings {
+                            group.add(&warning_row(*warning));
+                        }
+
+                        badges.append(&favorite_button(Favorite::Local(share.name.clone())));
+                        group.set_header_suffix(Some(&badges));
+
                         // Path row
                         let path_row = adw::ActionRow::new();
                         path_row.set_title(&gettext("Path"));
                         path_row.set_subtitle(&share.path);
                         group.add(&path_row);
 
+                        // Free note about the share, kept out of the configuration
+                        group.add(&notes_row(Favorite::Local(share.name.clone())));
+
+                        // Filesystem and free space row
+                        let storage_row = adw::ActionRow::new();
+                        storage_row.set_title(&gettext("Storage"));
+                        storage_row.set_subtitle(&gettext("Loading..."));
+                        group.add(&storage_row);
+
+                        // Size limit row, shown on filesystems supporting them
+                        let quota_row = QuotaRow::new(&share.path, toast_overlay);
+                        group.add(quota_row.row());
+                        storage_rows.push((share.path.clone(), storage_row, quota_row));
+
                         // Settings summary
                         let settings = format!(
                             "Browsable: {} • Read Only: {} • Guest OK: {}",
@@ -81,24 +197,194 @@ impl ListSharesDialog {
                         user_group_row.set_subtitle(&user_group_text);
                         group.add(&user_group_row);
 
+                        // Effective access row
+                        let access_row = adw::ActionRow::new();
+                        access_row.set_title(&gettext("Access"));
+                        access_row.set_subtitle(&access_summary(&share_access(&share, &accounts)));
+                        group.add(&access_row);
+
+                        // Usage statistics row
+                        let activity_row = adw::ActionRow::new();
+                        activity_row.set_title(&gettext("Activity"));
+                        activity_row.set_subtitle(&gettext("Loading..."));
+                        group.add(&activity_row);
+                        activity_rows.push((share.name.clone(), activity_row));
+
                         // Edit button
                         let edit_button = gtk4::Button::with_label(&gettext("Edit"));
                         edit_button.set_valign(gtk4::Align::Center);
                         edit_button.add_css_class("flat");
 
+                        edit_button.set_sensitive(share.locked_reason.is_none());
+
+                        // Shares of an imported file the app doesn't manage are only shown
+                        let managed = is_managed_file(&share.source_file, &managed_files);
+                        if managed {
+                            lock_when_read_only(&edit_button);
+                        } else {
+                            edit_button.set_tooltip_text(Some(&gettext(
+                                "Defined in a file the app doesn't edit, opens a read-only view",
+                            )));
+                        }
                         let share_clone = share.clone();
-                        let window_clone_for_edit = window.clone();
-                        edit_button.connect_clicked(move |_| {
+                        let dialog_clone_for_edit = dialog.clone();
+                        edit_button.connect_clicked(move |button| {
+                            if !managed {
+                                read_only_dialog(&share_clone).present(Some(&dialog_clone_for_edit));
+                                return;
+                            }
+                            if !allow_write(button) {
+                                return;
+                            }
                             let edit_dialog = EditShareDialog::new(&share_clone);
-                            edit_dialog.present(Some(&window_clone_for_edit));
+                            edit_dialog.present(Some(&dialog_clone_for_edit));
                         });
 
                         let button_row = adw::ActionRow::new();
+
+                        // Audit log button, only for audited shares
+                        if share.audit {
+                            let log_button = gtk4::Button::with_label(&gettext("View Audit Log"));
+                            log_button.set_valign(gtk4::Align::Center);
+                            log_button.add_css_class("flat");
+
+                            let share_name = share.name.clone();
+                            let dialog_clone_for_log = dialog.clone();
+                            log_button.connect_clicked(move |_| {
+                                let log_dialog = LogViewerDialog::new(Some(&share_name));
+                                log_dialog.present(Some(&dialog_clone_for_log));
+                            });
+                            button_row.add_suffix(&log_button);
+                        }
+
+                        // Recycle bin button, only for shares keeping deleted files
+                        if share.recycle_enabled() {
+                            let recycle_button = gtk4::Button::with_label(&gettext("Recycle Bin"));
+                            recycle_button.set_valign(gtk4::Align::Center);
+                            recycle_button.add_css_class("flat");
+
+                            let share_clone = share.clone();
+                            let dialog_clone_for_recycle = dialog.clone();
+                            recycle_button.connect_clicked(move |_| {
+                                let recycle_dialog = RecycleBinDialog::new(&share_clone);
+                                recycle_dialog.present(Some(&dialog_clone_for_recycle));
+                            });
+                            button_row.add_suffix(&recycle_button);
+                        }
+
+                        // Delete button, undoable from the toast for a few seconds
+                        let delete_button = gtk4::Button::with_label(&gettext("Delete"));
+                        delete_button.set_valign(gtk4::Align::Center);
+                        delete_button.add_css_class("flat");
+                        delete_button.add_css_class("error");
+                        delete_button.set_sensitive(share.locked_reason.is_none() && managed);
+                        lock_when_read_only(&delete_button);
+
+                        let share_clone = share.clone();
+                        let toast_overlay_clone = toast_overlay.clone();
+                        let delete_share = Rc::new(move || match share_clone.delete() {
+                            Ok(change) => {
+                                eprintln!("Share deleted: {}", share_clone.name);
+                                let state = AppState::get();
+                                state.add_pending_change(change.clone());
+                                state.reload_local();
+
+                                let toast = adw::Toast::new(&gettext("Share deleted"));
+                                toast.set_button_label(Some(&gettext("Undo")));
+                                toast.set_timeout(UNDO_TIMEOUT);
+
+                                let toast_overlay = toast_overlay_clone.clone();
+                                toast.connect_button_clicked(move |_| match change.revert() {
+                                    Ok(_) => {
+                                        let state = AppState::get();
+                                        state.remove_pending_change(&change);
+                                        state.reload_local();
+                                    }
+                                    Err(e) => {
+                                        eprintln!("Failed to restore share: {}", e);
+                                        let error_msg =
+                                            format!("{}: {}", gettext("Failed to restore share"), e);
+                                        toast_overlay.add_toast(adw::Toast::new(&error_msg));
+                                    }
+                                });
+                                toast_overlay_clone.add_toast(toast);
+                            }
+                            Err(e) => {
+                                eprintln!("Failed to delete share: {}", e);
+                                let error_msg = format!("{}: {}", gettext("Failed to delete share"), e);
+                                toast_overlay_clone.add_toast(adw::Toast::new(&error_msg));
+                            }
+                        });
+
+                        // Deleting requires typing the share name first
+                        let share_name = share.name.clone();
+                        delete_button.connect_clicked(move |button| {
+                            if !allow_write(button) {
+                                return;
+                            }
+                            let delete_share = delete_share.clone();
+                            confirm_delete(
+                                button,
+                                &share_name,
+                                &gettext("The share is removed from the configuration. Files in the folder are kept."),
+                                move || delete_share(),
+                            );
+                        });
+
                         button_row.add_suffix(&edit_button);
+                        button_row.add_suffix(&delete_button);
                         group.add(&button_row);
 
                         preferences_page.add(&group);
                     }
+
+                    glib::spawn_future_local(async move {
+                        let paths: Vec<String> = storage_rows.iter().map(|(path, _, _)| path.clone()).collect();
+                        let spaces = gio::spawn_blocking(move || {
+                            paths
+                                .iter()
+                                .map(|path| -> Result<_, String> {
+                                    let space = disk_space(path)?;
+                                    let quota = QuotaKind::for_fs_type(&space.fs_type)
+                                        .map(|kind| (kind, quota_usage(path, kind)));
+                                    Ok((space, quota))
+                                })
+                                .collect::<Vec<_>>()
+                        })
+                        .await
+                        .unwrap_or_else(|e| vec![Err(format!("{:?}", e)); storage_rows.len()]);
+                        for ((_, row, quota_row), space) in storage_rows.iter().zip(spaces) {
+                            match space {
+                                Ok((space, quota)) => {
+                                    show_disk_space(row, &space);
+                                    if let Some((kind, usage)) = quota {
+                                        quota_row.show(kind, usage);
+                                    }
+                                }
+                                Err(e) => {
+                                    row.set_subtitle(&gettext("Not available"));
+                                    row.set_tooltip_text(Some(&e));
+                                }
+                            }
+                        }
+                    });
+
+                    glib::spawn_future_local(async move {
+                        let result = gio::spawn_blocking(move || share_activity(&audited_shares))
+                            .await
+                            .unwrap_or_else(|e| Err(format!("{:?}", e)));
+                        for (name, row) in activity_rows {
+                            match &result {
+                                Ok(activity) => row.set_subtitle(&activity_summary(
+                                    &activity.get(&name).cloned().unwrap_or_default(),
+                                )),
+                                Err(e) => {
+                                    row.set_subtitle(&gettext("Not available"));
+                                    row.set_tooltip_text(Some(e));
+                                }
+                            }
+                        }
+                    });
                 }
             }
             Err(e) => {
@@ -115,38 +401,90 @@ impl ListSharesDialog {
                 preferences_page.add(&error_group);
             }
         }
+    }
 
-        scrolled.set_child(Some(&preferences_page));
-        toolbar_view.set_content(Some(&scrolled));
+    pub fn present(&self, parent: Option<&impl IsA<gtk4::Widget>>) {
+        self.dialog.present(parent);
+    }
 
-        // Wrap in toast overlay
-        let toast_overlay = adw::ToastOverlay::new();
-        toast_overlay.set_child(Some(&toolbar_view));
+    pub fn dialog(&self) -> &adw::Dialog {
+        &self.dialog
+    }
+}
 
-        window.set_content(Some(&toast_overlay));
+/// Read-only view of a share defined in a file the app doesn't edit
+fn read_only_dialog(share: &SambaShareConfig) -> ReadOnlyShareDialog {
+    let yes_no = |value: bool| if value { gettext("Yes") } else { gettext("No") };
+    let settings = vec![
+        (gettext("Path"), share.path.clone()),
+        (gettext("Browsable"), yes_no(share.browsable)),
+        (gettext("Read Only"), yes_no(share.read_only)),
+        (gettext("Guest OK"), yes_no(share.guest_ok)),
+        (gettext("Force User"), share.force_user.clone()),
+        (gettext("Force Group"), share.force_group.clone()),
+        (gettext("Valid Users"), share.valid_users.join(", ")),
+        (gettext("Invalid Users"), share.invalid_users.join(", ")),
+    ];
+    ReadOnlyShareDialog::new(&share.name, &settings, &share.source_file)
+}
 
-        // Handle close button
-        let window_clone = window.clone();
-        close_button.connect_clicked(move |_| {
-            window_clone.close();
-        });
+/// "Last access 2024-03-13 19:01 • 3 clients this week" for audited shares,
+/// the clients connected now for the others
+fn activity_summary(activity: &ShareActivity) -> String {
+    let clients = activity.clients.len().to_string();
+    match (activity.from_audit_log, &activity.last_access) {
+        (true, None) => gettext("No access this week"),
+        (false, None) => gettext("No client connected. Enable auditing for weekly statistics."),
+        (true, Some(time)) => gettext("Last access {time} • {count} clients this week")
+            .replace("{time}", time)
+            .replace("{count}", &clients),
+        (false, Some(time)) => gettext("{count} clients connected, since {time}")
+            .replace("{time}", time)
+            .replace("{count}", &clients),
+    }
+}
 
-        Self {
-            window,
-            toast_overlay,
-        }
+/// "ext4 • 120 GB free of 500 GB", flagged as a warning when the space is running out
+fn show_disk_space(row: &adw::ActionRow, space: &DiskSpace) {
+    let summary = gettext("{fs} • {free} free of {total}")
+        .replace("{fs}", &space.fs_type)
+        .replace("{free}", &format_size(space.available))
+        .replace("{total}", &format_size(space.total));
+    if space.is_low() {
+        row.set_subtitle(&format!("{} • {}", summary, gettext("Running out of space")));
+        row.add_prefix(&gtk4::Image::from_icon_name("dialog-warning-symbolic"));
+        row.add_css_class("warning");
+    } else {
+        row.set_subtitle(&summary);
     }
+}
 
-    pub fn present(&self, parent: Option<&impl IsA<gtk4::Widget>>) {
-        if let Some(p) = parent {
-            if let Some(parent_window) = p.dynamic_cast_ref::<gtk4::Window>() {
-                self.window.set_transient_for(Some(parent_window));
-            }
+/// One line describing who can read and write a share
+fn access_summary(access: &ShareAccess) -> String {
+    let mode = |writable: bool| {
+        if writable {
+            gettext("read/write")
+        } else {
+            gettext("read only")
         }
-        self.window.present();
-    }
+    };
 
-    pub fn window(&self) -> &adw::Window {
-        &self.window
+    let mut parts = Vec::new();
+    if !access.restricted {
+        parts.push(format!("{} ({})", gettext("Any Samba user"), mode(access.writable_by_default)));
+    } else if access.users.is_empty() {
+        parts.push(gettext("Nobody, the listed users and groups don't exist"));
+    }
+    for user in &access.users {
+        parts.push(format!("{} ({})", glib::markup_escape_text(&user.name), mode(user.writable)));
+    }
+    for user in &access.denied {
+        parts.push(format!("{} ({})", glib::markup_escape_text(user), gettext("no access")));
+    }
+    match access.guest.as_deref() {
+        Some("") => parts.push(gettext("Guests")),
+        Some(account) => parts.push(format!("{} ({})", gettext("Guests"), glib::markup_escape_text(account))),
+        None => {}
     }
+    parts.join(" • ")
 }
@@ -0,0 +1,89 @@
+use crate::samba::logs::read_audit_log;
+use gettextrs::gettext;
+use gtk4::prelude::*;
+use libadwaita as adw;
+use libadwaita::prelude::*;
+
+/// Number of journal lines fetched when opening the viewer
+const MAX_LINES: usize = 500;
+
+pub struct LogViewerDialog {
+    dialog: adw::Dialog,
+}
+
+impl LogViewerDialog {
+    /// Show the audit log, optionally restricted to a single share
+    pub fn new(share: Option<&str>) -> Self {
+        let dialog = adw::Dialog::new();
+        let title = match share {
+            Some(name) => format!("{} – {}", gettext("Audit Log"), name),
+            None => gettext("Audit Log"),
+        };
+        dialog.set_title(&title);
+        dialog.set_content_width(800);
+        dialog.set_content_height(500);
+
+        // Create toolbar header
+        let toolbar_view = adw::ToolbarView::new();
+        let header_bar = adw::HeaderBar::new();
+        toolbar_view.add_top_bar(&header_bar);
+
+        // Refresh button
+        let refresh_button = gtk4::Button::from_icon_name("view-refresh-symbolic");
+        refresh_button.set_tooltip_text(Some(&gettext("Refresh")));
+        header_bar.pack_end(&refresh_button);
+
+        // Log text view
+        let text_view = gtk4::TextView::new();
+        text_view.set_editable(false);
+        text_view.set_monospace(true);
+        text_view.set_wrap_mode(gtk4::WrapMode::WordChar);
+        text_view.set_left_margin(12);
+        text_view.set_right_margin(12);
+        text_view.set_top_margin(12);
+        text_view.set_bottom_margin(12);
+
+        let scrolled = gtk4::ScrolledWindow::builder()
+            .hexpand(true)
+            .vexpand(true)
+            .child(&text_view)
+            .build();
+        toolbar_view.set_content(Some(&scrolled));
+
+        dialog.set_child(Some(&toolbar_view));
+
+        let share_filter = share.map(|s| s.to_string());
+        Self::load(&text_view, share_filter.clone());
+
+        let text_view_clone = text_view.clone();
+        refresh_button.connect_clicked(move |_| {
+            Self::load(&text_view_clone, share_filter.clone());
+        });
+
+        Self { dialog }
+    }
+
+    fn load(text_view: &gtk4::TextView, share: Option<String>) {
+        text_view.buffer().set_text(&gettext("Loading..."));
+
+        let text_view = text_view.clone();
+        glib::spawn_future_local(async move {
+            let result =
+                gio::spawn_blocking(move || read_audit_log(share.as_deref(), MAX_LINES)).await;
+
+            let text = match result {
+                Ok(Ok(lines)) if lines.is_empty() => gettext(
+                    "No audit entries found. Entries appear once the configuration has been rebuilt and the share is accessed.",
+                ),
+                Ok(Ok(lines)) => lines.join("\n"),
+                Ok(Err(e)) => format!("{}: {}", gettext("Failed to read audit log"), e),
+                Err(e) => format!("{}: {:?}", gettext("Error"), e),
+            };
+            text_view.buffer().set_text(&text);
+        });
+    }
+
+    pub fn present(&self, parent: Option<&impl IsA<gtk4::Widget>>) {
+        self.dialog.present(parent);
+    }
+}
@@ -5,6 +5,26 @@ pub mod list_shares;
 pub mod remote_list_shares;
 pub mod edit_remote_share;
 pub mod add_remote_share;
+pub mod global_settings;
+pub mod log_viewer;
+pub mod change_preview;
+pub mod private_share_wizard;
+pub mod team_share_wizard;
+pub mod confirm_delete;
+pub mod preferences;
+pub mod rebuild_output;
+pub mod share_test;
+pub mod mount_tuning;
+pub mod share_browser;
+pub mod busy_unmount;
+pub mod fstab_import;
+pub mod samba_users;
+pub mod recycle_bin;
+pub mod config_audit;
+pub mod network;
+pub mod mount_credentials;
+pub mod credentials_file;
+pub mod read_only_share;
 
 pub use welcome::WelcomeDialog;
 pub use add_share::AddShareDialog;
@@ -13,4 +33,23 @@ pub use list_shares::ListSharesDialog;
 
 pub use remote_list_shares::RemoteListSharesDialog;
 pub use edit_remote_share::EditRemoteShareDialog;
-pub use add_remote_share::AddRemoteShareDialog;
\ No newline at end of file
+pub use add_remote_share::AddRemoteShareDialog;
+pub use global_settings::GlobalSettingsDialog;
+pub use log_viewer::LogViewerDialog;
+pub use change_preview::ChangePreviewDialog;
+pub use private_share_wizard::PrivateShareWizard;
+pub use team_share_wizard::TeamShareWizard;
+pub use confirm_delete::confirm_delete;
+pub use preferences::PreferencesDialog;
+pub use rebuild_output::RebuildOutputDialog;
+pub use share_test::ShareTestDialog;
+pub use mount_tuning::MountTuningDialog;
+pub use share_browser::ShareBrowserDialog;
+pub use fstab_import::FstabImportDialog;
+pub use samba_users::SambaUsersDialog;
+pub use recycle_bin::RecycleBinDialog;
+pub use config_audit::ConfigAuditDialog;
+pub use network::NetworkDialog;
+pub use mount_credentials::MountCredentialsDialog;
+pub use credentials_file::CredentialsFileDialog;
+pub use read_only_share::ReadOnlyShareDialog;
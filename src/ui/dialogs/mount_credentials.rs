@@ -0,0 +1,280 @@
+use crate::config::AppConfig;
+use crate::models::AppState;
+use crate::samba::neighborhood::allows_guest;
+use crate::samba::secrets;
+use crate::samba::share_browser::split_remote_path;
+use crate::samba::{mount_share, MountCredentials, MountOptions, MountedShare};
+use crate::ui::widgets::browse_file;
+use gettextrs::gettext;
+use gtk4::prelude::*;
+use gtk4::{gio, glib};
+use libadwaita as adw;
+use libadwaita::prelude::*;
+use std::path::PathBuf;
+
+/// Asks for a username and password, or a credentials file, then mounts a
+/// remote share right away until it is unmounted or the computer restarts.
+/// The password can be saved in the keyring, so the next mounts don't ask for it.
+pub struct MountCredentialsDialog {
+    dialog: adw::Dialog,
+}
+
+impl MountCredentialsDialog {
+    /// `toast_overlay` tells the share was mounted once the dialog is closed
+    pub fn new(share: &MountedShare, toast_overlay: &adw::ToastOverlay) -> Self {
+        let dialog = adw::Dialog::new();
+        dialog.set_title(&gettext("Mount Share"));
+        dialog.set_content_width(450);
+
+        // Create toolbar header
+        let toolbar_view = adw::ToolbarView::new();
+        let header_bar = adw::HeaderBar::new();
+        toolbar_view.add_top_bar(&header_bar);
+
+        let dialog_toast_overlay = adw::ToastOverlay::new();
+        let preferences_page = adw::PreferencesPage::new();
+
+        let group = adw::PreferencesGroup::new();
+        group.set_title(&share.source);
+        group.set_description(Some(&format!("{} {}", gettext("Mounted on"), share.target)));
+
+        // Configured entries usually name their credentials file already
+        let configured_file = share
+            .config
+            .as_ref()
+            .map(|config| config.option_credentials.clone())
+            .filter(|path| !path.is_empty());
+
+        let file_switch = adw::SwitchRow::new();
+        file_switch.set_title(&gettext("Use Credentials File"));
+        file_switch.set_subtitle(&gettext("Log in with a file holding username= and password= lines"));
+        file_switch.set_active(configured_file.is_some());
+        group.add(&file_switch);
+
+        let username_entry = adw::EntryRow::new();
+        username_entry.set_title(&gettext("Username"));
+        group.add(&username_entry);
+
+        let password_entry = adw::PasswordEntryRow::new();
+        password_entry.set_title(&gettext("Password"));
+        group.add(&password_entry);
+
+        let remember_switch = adw::SwitchRow::new();
+        remember_switch.set_title(&gettext("Remember Password"));
+        remember_switch.set_subtitle(&gettext("Save it in your keyring to mount this server without asking"));
+        group.add(&remember_switch);
+
+        let file_entry = adw::EntryRow::new();
+        file_entry.set_title(&gettext("Credentials File"));
+        file_entry.set_text(configured_file.as_deref().unwrap_or_default());
+        let browse_button = gtk4::Button::with_label(&gettext("Browse..."));
+        browse_button.set_valign(gtk4::Align::Center);
+        file_entry.add_suffix(&browse_button);
+        group.add(&file_entry);
+
+        let show_fields = {
+            let username_entry = username_entry.clone();
+            let password_entry = password_entry.clone();
+            let remember_switch = remember_switch.clone();
+            let file_entry = file_entry.clone();
+            move |use_file: bool| {
+                username_entry.set_visible(!use_file);
+                password_entry.set_visible(!use_file);
+                remember_switch.set_visible(!use_file);
+                file_entry.set_visible(use_file);
+            }
+        };
+        show_fields(file_switch.is_active());
+        file_switch.connect_active_notify(move |switch| show_fields(switch.is_active()));
+
+        // Filled with the saved password, which a wrong one is changed from
+        let server = share.server().to_string();
+        let username_clone = username_entry.clone();
+        let password_clone = password_entry.clone();
+        let remember_clone = remember_switch.clone();
+        glib::spawn_future_local(async move {
+            let saved = gio::spawn_blocking(move || secrets::lookup(&server))
+                .await
+                .ok()
+                .flatten();
+            if let Some(saved) = saved {
+                if username_clone.text().is_empty() {
+                    username_clone.set_text(&saved.username);
+                    password_clone.set_text(&saved.password);
+                }
+                remember_clone.set_active(true);
+            }
+        });
+
+        preferences_page.add(&group);
+
+        // Actions
+        let actions_group = adw::PreferencesGroup::new();
+        let mount_button = gtk4::Button::with_label(&gettext("Mount"));
+        mount_button.set_halign(gtk4::Align::Center);
+        mount_button.add_css_class("pill");
+        mount_button.add_css_class("suggested-action");
+        actions_group.add(&mount_button);
+        preferences_page.add(&actions_group);
+
+        dialog_toast_overlay.set_child(Some(&preferences_page));
+        toolbar_view.set_content(Some(&dialog_toast_overlay));
+        dialog.set_child(Some(&toolbar_view));
+
+        let dialog_clone = dialog.clone();
+        let file_entry_clone = file_entry.clone();
+        browse_button.connect_clicked(move |_| {
+            browse_file(&dialog_clone, &file_entry_clone, &gettext("Select Credentials File"));
+        });
+
+        let additional_opts = additional_options(share);
+        let server = share.server().to_string();
+        let source = share.source.clone();
+        let target = PathBuf::from(&share.target);
+        let dialog_clone = dialog.clone();
+        let toast_overlay = toast_overlay.clone();
+        mount_button.connect_clicked(move |button| {
+            let credentials = if file_switch.is_active() {
+                let path = file_entry.text().trim().to_string();
+                if path.is_empty() {
+                    dialog_toast_overlay.add_toast(adw::Toast::new(&gettext("Choose a credentials file")));
+                    return;
+                }
+                MountCredentials::File(PathBuf::from(path))
+            } else {
+                let username = username_entry.text().trim().to_string();
+                if username.is_empty() {
+                    dialog_toast_overlay.add_toast(adw::Toast::new(&gettext("Enter a username")));
+                    return;
+                }
+                MountCredentials::Password {
+                    username,
+                    password: password_entry.text().to_string(),
+                }
+            };
+            // Only passwords typed here are saved, credentials files stay where they are
+            let remember = match &credentials {
+                MountCredentials::Password { .. } => Some(remember_switch.is_active()),
+                MountCredentials::File(_) | MountCredentials::Guest => None,
+            };
+            button.set_sensitive(false);
+
+            let options = MountOptions {
+                additional_opts: additional_opts.clone(),
+                retry: AppConfig::new().mount_retry(),
+                ..MountOptions::default()
+            };
+            let server = server.clone();
+            let source = source.clone();
+            let target = target.clone();
+            let button = button.clone();
+            let dialog = dialog_clone.clone();
+            let dialog_toast_overlay = dialog_toast_overlay.clone();
+            let toast_overlay = toast_overlay.clone();
+            glib::spawn_future_local(async move {
+                let result = gio::spawn_blocking(move || -> Result<Result<(), String>, String> {
+                    mount_share(&source, &target, &credentials, options)?;
+                    // The share is mounted whether or not the keyring takes the password
+                    let saved = match (&credentials, remember) {
+                        (MountCredentials::Password { username, password }, Some(true)) => {
+                            secrets::store(&server, username, password)
+                        }
+                        (_, Some(false)) => {
+                            // Without libsecret nothing was saved to begin with
+                            if let Err(e) = secrets::forget(&server) {
+                                eprintln!("Failed to remove the saved password: {}", e);
+                            }
+                            Ok(())
+                        }
+                        _ => Ok(()),
+                    };
+                    Ok(saved)
+                })
+                .await
+                .unwrap_or_else(|e| Err(format!("{:?}", e)));
+
+                button.set_sensitive(true);
+                match result {
+                    Ok(saved) => {
+                        if let Err(e) = saved {
+                            eprintln!("Failed to update the keyring: {}", e);
+                            let error_msg = format!("{}: {}", gettext("Failed to save the password"), e);
+                            toast_overlay.add_toast(adw::Toast::new(&error_msg));
+                        }
+                        toast_overlay.add_toast(adw::Toast::new(&gettext("Share mounted")));
+                        AppState::get().reload_remote();
+                        dialog.close();
+                    }
+                    Err(e) => {
+                        eprintln!("Failed to mount share: {}", e);
+                        let error_msg = format!("{}: {}", gettext("Mount failed"), e);
+                        dialog_toast_overlay.add_toast(adw::Toast::new(&error_msg));
+                    }
+                }
+            });
+        });
+
+        Self { dialog }
+    }
+
+    /// Mount `share` with the password saved for its server, or as guest when there is
+    /// none and the share lets guests in, asking for credentials when that is refused
+    pub fn mount_or_ask(share: &MountedShare, parent: &impl IsA<gtk4::Widget>, toast_overlay: &adw::ToastOverlay) {
+        let share = share.clone();
+        let parent = parent.clone().upcast::<gtk4::Widget>();
+        let toast_overlay = toast_overlay.clone();
+        glib::spawn_future_local(async move {
+            let server = share.server().to_string();
+            let source = share.source.clone();
+            let target = PathBuf::from(&share.target);
+            let options = MountOptions {
+                additional_opts: additional_options(&share),
+                retry: AppConfig::new().mount_retry(),
+                ..MountOptions::default()
+            };
+            let result = gio::spawn_blocking(move || {
+                let credentials = match secrets::lookup(&server) {
+                    Some(saved) => MountCredentials::Password {
+                        username: saved.username,
+                        password: saved.password,
+                    },
+                    None if split_remote_path(&source).is_some_and(|(share, _)| allows_guest(&share)) => {
+                        MountCredentials::Guest
+                    }
+                    None => return Ok(false),
+                };
+                mount_share(&source, &target, &credentials, options).map(|_| true)
+            })
+            .await
+            .unwrap_or_else(|e| Err(format!("{:?}", e)));
+
+            match result {
+                Ok(true) => {
+                    toast_overlay.add_toast(adw::Toast::new(&gettext("Share mounted")));
+                    AppState::get().reload_remote();
+                    return;
+                }
+                Ok(false) => {}
+                Err(e) => {
+                    eprintln!("Failed to mount share without asking for credentials: {}", e);
+                    let error_msg = format!("{}: {}", gettext("Mount failed"), e);
+                    toast_overlay.add_toast(adw::Toast::new(&error_msg));
+                }
+            }
+            Self::new(&share, &toast_overlay).present(Some(&parent));
+        });
+    }
+
+    pub fn present(&self, parent: Option<&impl IsA<gtk4::Widget>>) {
+        self.dialog.present(parent);
+    }
+}
+
+/// Options such as cache= from the configuration entry, systemd ones being kept apart
+fn additional_options(share: &MountedShare) -> Vec<String> {
+    share
+        .config
+        .as_ref()
+        .map(|config| config.extra_options.clone())
+        .unwrap_or_default()
+}
@@ -0,0 +1,190 @@
+use crate::models::AppState;
+use crate::samba::mount_tuning::{apply_preset, benchmark, BenchmarkResult, TuningPreset, PRESETS};
+use crate::samba::remote_share_config::RemoteSambaShareConfig;
+use crate::utils::format::format_rate;
+use gettextrs::gettext;
+use gtk4::prelude::*;
+use gtk4::{gio, glib};
+use libadwaita as adw;
+use libadwaita::prelude::*;
+use std::cell::Cell;
+use std::rc::Rc;
+
+/// Benchmarks a remote share with a few mount option presets and
+/// offers to write the fastest one to its configuration
+pub struct MountTuningDialog {
+    dialog: adw::Dialog,
+}
+
+impl MountTuningDialog {
+    pub fn new(share: &RemoteSambaShareConfig) -> Self {
+        let dialog = adw::Dialog::new();
+        dialog.set_title(&gettext("Tune Mount Options"));
+        dialog.set_content_width(550);
+        dialog.set_content_height(550);
+
+        // Create toolbar header
+        let toolbar_view = adw::ToolbarView::new();
+        let header_bar = adw::HeaderBar::new();
+        toolbar_view.add_top_bar(&header_bar);
+
+        let toast_overlay = adw::ToastOverlay::new();
+        let preferences_page = adw::PreferencesPage::new();
+
+        let group = adw::PreferencesGroup::new();
+        group.set_title(&gettext("Presets"));
+        group.set_description(Some(&gettext(
+            "Each preset mounts the share on a temporary folder, writes and reads a 16 MiB file, then unmounts it",
+        )));
+
+        let rows: Vec<adw::ActionRow> = PRESETS
+            .iter()
+            .map(|preset| {
+                let row = adw::ActionRow::new();
+                row.set_title(&preset_title(preset));
+                row.set_subtitle(&preset.options.join(", "));
+                row.set_use_markup(false);
+                group.add(&row);
+                row
+            })
+            .collect();
+        preferences_page.add(&group);
+
+        // Actions
+        let actions_group = adw::PreferencesGroup::new();
+        let buttons = gtk4::Box::new(gtk4::Orientation::Horizontal, 12);
+        buttons.set_halign(gtk4::Align::Center);
+
+        let start_button = gtk4::Button::with_label(&gettext("Start Benchmark"));
+        start_button.add_css_class("pill");
+        buttons.append(&start_button);
+
+        let apply_button = gtk4::Button::with_label(&gettext("Apply Best"));
+        apply_button.add_css_class("pill");
+        apply_button.add_css_class("suggested-action");
+        apply_button.set_sensitive(false);
+        buttons.append(&apply_button);
+
+        actions_group.add(&buttons);
+        preferences_page.add(&actions_group);
+
+        toast_overlay.set_child(Some(&preferences_page));
+        toolbar_view.set_content(Some(&toast_overlay));
+        dialog.set_child(Some(&toolbar_view));
+
+        // Index of the fastest preset of the last run
+        let best: Rc<Cell<Option<usize>>> = Rc::new(Cell::new(None));
+
+        let share_clone = share.clone();
+        let best_clone = best.clone();
+        let apply_button_clone = apply_button.clone();
+        let toast_overlay_clone = toast_overlay.clone();
+        start_button.connect_clicked(move |button| {
+            button.set_sensitive(false);
+            apply_button_clone.set_sensitive(false);
+            for row in &rows {
+                row.remove_css_class("success");
+                row.remove_css_class("error");
+            }
+
+            let share = share_clone.clone();
+            let rows = rows.clone();
+            let best = best_clone.clone();
+            let button = button.clone();
+            let apply_button = apply_button_clone.clone();
+            let toast_overlay = toast_overlay_clone.clone();
+            glib::spawn_future_local(async move {
+                let mut results: Vec<Option<BenchmarkResult>> = Vec::new();
+                for (preset, row) in PRESETS.iter().zip(&rows) {
+                    row.set_subtitle(&gettext("Running…"));
+
+                    let share = share.clone();
+                    let preset = *preset;
+                    let result = gio::spawn_blocking(move || benchmark(&share, &preset))
+                        .await
+                        .unwrap_or_else(|e| Err(format!("{:?}", e)));
+
+                    match result {
+                        Ok(measured) => {
+                            row.set_subtitle(&format!(
+                                "{}: {} • {}: {}",
+                                gettext("Write"),
+                                format_rate(measured.write_mbps * 1_000_000.0),
+                                gettext("Read"),
+                                format_rate(measured.read_mbps * 1_000_000.0)
+                            ));
+                            results.push(Some(measured));
+                        }
+                        Err(e) => {
+                            eprintln!("Benchmark of {} failed: {}", preset.name, e);
+                            row.set_subtitle(&e);
+                            row.add_css_class("error");
+                            results.push(None);
+                        }
+                    }
+                }
+
+                let fastest = results
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(index, result)| result.map(|result| (index, result.score())))
+                    .max_by(|a, b| a.1.total_cmp(&b.1))
+                    .map(|(index, _)| index);
+                best.set(fastest);
+
+                match fastest {
+                    Some(index) => {
+                        rows[index].add_css_class("success");
+                        apply_button.set_sensitive(true);
+                    }
+                    None => toast_overlay.add_toast(adw::Toast::new(&gettext("Every benchmark failed"))),
+                }
+                button.set_sensitive(true);
+            });
+        });
+
+        // Write the fastest preset's options to the configuration
+        let share_clone = share.clone();
+        let dialog_clone = dialog.clone();
+        let toast_overlay_clone = toast_overlay.clone();
+        apply_button.connect_clicked(move |_| {
+            let Some(index) = best.get() else {
+                return;
+            };
+
+            let mut updated = share_clone.clone();
+            updated.extra_options = apply_preset(&share_clone.extra_options, &PRESETS[index]);
+            match updated.update(&share_clone.name) {
+                Ok(change) => {
+                    let state = AppState::get();
+                    state.add_pending_change(change);
+                    state.reload_remote();
+                    dialog_clone.close();
+                }
+                Err(e) => {
+                    eprintln!("Failed to apply mount options: {}", e);
+                    let error_msg = format!("{}: {}", gettext("Failed to apply mount options"), e);
+                    toast_overlay_clone.add_toast(adw::Toast::new(&error_msg));
+                }
+            }
+        });
+
+        Self { dialog }
+    }
+
+    pub fn present(&self, parent: Option<&impl IsA<gtk4::Widget>>) {
+        self.dialog.present(parent);
+    }
+}
+
+/// Translated name of a preset
+fn preset_title(preset: &TuningPreset) -> String {
+    match preset.name {
+        "Default" => gettext("Default"),
+        "No cache" => gettext("No cache"),
+        "Loose cache" => gettext("Loose cache"),
+        "Small blocks" => gettext("Small blocks"),
+        "Large blocks" => gettext("Large blocks"),
+        other => other.to_string(),
+    }
+}
@@ -0,0 +1,305 @@
+use crate::config::AppConfig;
+use crate::samba::neighborhood::{
+    allows_guest, discover_hosts, group_by_workgroup, list_host_shares, HostShare, NetworkHost,
+};
+use crate::ui::dialogs::AddRemoteShareDialog;
+use crate::ui::widgets::file_picker::parent_window;
+use crate::ui::widgets::{allow_write, lock_when_read_only, skeleton_group};
+use gettextrs::gettext;
+use gtk4::prelude::*;
+use gtk4::{gio, glib};
+use libadwaita as adw;
+use libadwaita::prelude::*;
+use std::cell::Cell;
+use std::rc::Rc;
+
+/// Computers found on the local network grouped by workgroup, each listing its shares
+/// when expanded, from which a share can be added or mounted right away
+pub struct NetworkDialog {
+    dialog: adw::Dialog,
+}
+
+impl NetworkDialog {
+    pub fn new() -> Self {
+        let dialog = adw::Dialog::new();
+        dialog.set_title(&gettext("Network"));
+        dialog.set_content_width(600);
+        dialog.set_content_height(600);
+
+        // Create toolbar header
+        let toolbar_view = adw::ToolbarView::new();
+        let header_bar = adw::HeaderBar::new();
+        let refresh_button = gtk4::Button::from_icon_name("view-refresh-symbolic");
+        refresh_button.set_tooltip_text(Some(&gettext("Refresh")));
+        header_bar.pack_start(&refresh_button);
+        toolbar_view.add_top_bar(&header_bar);
+
+        let scrolled = gtk4::ScrolledWindow::builder()
+            .hexpand(true)
+            .vexpand(true)
+            .build();
+        let toast_overlay = adw::ToastOverlay::new();
+        toast_overlay.set_child(Some(&scrolled));
+        toolbar_view.set_content(Some(&toast_overlay));
+        dialog.set_child(Some(&toolbar_view));
+
+        let dialog_clone = dialog.clone();
+        let scrolled_clone = scrolled.clone();
+        let toast_overlay_clone = toast_overlay.clone();
+        refresh_button.connect_clicked(move |_| {
+            Self::load(&dialog_clone, &scrolled_clone, &toast_overlay_clone);
+        });
+        Self::load(&dialog, &scrolled, &toast_overlay);
+
+        Self { dialog }
+    }
+
+    pub fn present(&self, parent: Option<&impl IsA<gtk4::Widget>>) {
+        self.dialog.present(parent);
+    }
+
+    /// Look for computers again, which takes a few seconds of broadcasts
+    fn load(dialog: &adw::Dialog, scrolled: &gtk4::ScrolledWindow, toast_overlay: &adw::ToastOverlay) {
+        let loading_page = adw::PreferencesPage::new();
+        loading_page.add(&skeleton_group(4));
+        scrolled.set_child(Some(&loading_page));
+
+        let scan = AppConfig::new().discovery_scan();
+        let dialog = dialog.clone();
+        let scrolled = scrolled.clone();
+        let toast_overlay = toast_overlay.clone();
+        glib::spawn_future_local(async move {
+            let result = gio::spawn_blocking(move || discover_hosts(scan))
+                .await
+                .unwrap_or_else(|e| Err(format!("{:?}", e)));
+
+            let hosts = match result {
+                Ok(hosts) => hosts,
+                Err(e) => {
+                    eprintln!("Failed to look for computers: {}", e);
+                    let status = adw::StatusPage::new();
+                    status.set_title(&gettext("Cannot Look for Computers"));
+                    status.set_description(Some(&e));
+                    status.set_icon_name(Some("dialog-error-symbolic"));
+                    scrolled.set_child(Some(&status));
+                    return;
+                }
+            };
+            if hosts.is_empty() {
+                let status = adw::StatusPage::new();
+                status.set_title(&gettext("No Computers Found"));
+                status.set_description(Some(&gettext(
+                    "Computers appear here when they answer NetBIOS, mDNS or WS-Discovery queries. Type the address of a server in Add New Remote Share instead.",
+                )));
+                status.set_icon_name(Some("network-workgroup-symbolic"));
+                scrolled.set_child(Some(&status));
+                return;
+            }
+
+            // NetBIOS answers carry the hardware address, kept to wake the hosts up later
+            let app_config = AppConfig::new();
+            for host in &hosts {
+                if let Some(mac_address) = &host.mac_address {
+                    app_config.set_server_mac_address(&host.name, mac_address);
+                    app_config.set_server_mac_address(&host.address, mac_address);
+                }
+            }
+
+            let preferences_page = adw::PreferencesPage::new();
+            for (workgroup, hosts) in group_by_workgroup(&hosts) {
+                let group = adw::PreferencesGroup::new();
+                group.set_title(&workgroup.unwrap_or_else(|| gettext("Other Computers")));
+                for host in &hosts {
+                    group.add(&host_row(host, &dialog, &toast_overlay));
+                }
+                preferences_page.add(&group);
+            }
+            scrolled.set_child(Some(&preferences_page));
+        });
+    }
+}
+
+/// Expandable row of a computer, listing its shares the first time it is expanded
+fn host_row(host: &NetworkHost, dialog: &adw::Dialog, toast_overlay: &adw::ToastOverlay) -> adw::ExpanderRow {
+    let row = adw::ExpanderRow::new();
+    row.set_title(&host.name);
+    row.set_subtitle(&host.address);
+    row.set_use_markup(false);
+    row.add_prefix(&gtk4::Image::from_icon_name("network-server-symbolic"));
+
+    let loaded = Rc::new(Cell::new(false));
+    let address = host.address.clone();
+    let dialog = dialog.clone();
+    let toast_overlay = toast_overlay.clone();
+    row.connect_expanded_notify(move |row| {
+        if !row.is_expanded() || loaded.replace(true) {
+            return;
+        }
+
+        let loading_row = adw::ActionRow::new();
+        loading_row.set_title(&gettext("Listing shares..."));
+        let spinner = gtk4::Spinner::new();
+        spinner.start();
+        loading_row.add_prefix(&spinner);
+        row.add_row(&loading_row);
+
+        let row = row.clone();
+        let address = address.clone();
+        let dialog = dialog.clone();
+        let toast_overlay = toast_overlay.clone();
+        glib::spawn_future_local(async move {
+            let host = address.clone();
+            let result = gio::spawn_blocking(move || list_host_shares(&host, ""))
+                .await
+                .unwrap_or_else(|e| Err(format!("{:?}", e)));
+            row.remove(&loading_row);
+
+            match result {
+                Ok(shares) if shares.is_empty() => {
+                    let empty_row = adw::ActionRow::new();
+                    empty_row.set_title(&gettext("No shares visible to guests"));
+                    row.add_row(&empty_row);
+                }
+                Ok(shares) => {
+                    for share in &shares {
+                        row.add_row(&share_row(&address, share, &dialog, &toast_overlay));
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Failed to list the shares of {}: {}", address, e);
+                    let error_row = adw::ActionRow::new();
+                    error_row.set_title(&gettext("Cannot list the shares"));
+                    error_row.set_subtitle(&e);
+                    error_row.set_use_markup(false);
+                    error_row.add_prefix(&gtk4::Image::from_icon_name("dialog-warning-symbolic"));
+                    row.add_row(&error_row);
+                }
+            }
+        });
+    });
+    row
+}
+
+/// Row of a share with the actions launched from the network view
+fn share_row(address: &str, share: &HostShare, dialog: &adw::Dialog, toast_overlay: &adw::ToastOverlay) -> adw::ActionRow {
+    let row = adw::ActionRow::new();
+    row.set_title(&share.name);
+    row.set_subtitle(&share.comment);
+    row.set_use_markup(false);
+    row.add_prefix(&gtk4::Image::from_icon_name("folder-remote-symbolic"));
+
+    // Shares open to guests are marked once checked, and mounted without asking
+    let guest = Rc::new(Cell::new(false));
+    let guest_label = gtk4::Label::new(Some(&gettext("Guest Access")));
+    guest_label.add_css_class("caption");
+    guest_label.add_css_class("success");
+    guest_label.set_valign(gtk4::Align::Center);
+    guest_label.set_tooltip_text(Some(&gettext("Anyone on the network can open this share without an account")));
+    guest_label.set_visible(false);
+    row.add_suffix(&guest_label);
+    let share_path = format!("//{}/{}", address, share.name);
+    let guest_clone = guest.clone();
+    glib::spawn_future_local(async move {
+        let allowed = gio::spawn_blocking(move || allows_guest(&share_path))
+            .await
+            .unwrap_or(false);
+        guest_clone.set(allowed);
+        guest_label.set_visible(allowed);
+    });
+
+    // Mounted through GVfs for this session, asking for a password when needed
+    let mount_button = gtk4::Button::with_label(&gettext("Quick Mount"));
+    mount_button.set_valign(gtk4::Align::Center);
+    mount_button.add_css_class("flat");
+    mount_button.set_tooltip_text(Some(&gettext("Open the share in the file manager until you log out")));
+    let uri = format!(
+        "smb://{}/{}",
+        address,
+        glib::Uri::escape_string(&share.name, None, true)
+    );
+    let toast_overlay = toast_overlay.clone();
+    mount_button.connect_clicked(move |button| quick_mount(button, &uri, guest.get(), &toast_overlay));
+    row.add_suffix(&mount_button);
+
+    // Added to the configuration, mounted after the next rebuild
+    let add_button = gtk4::Button::with_label(&gettext("Add as Remote Share"));
+    add_button.set_valign(gtk4::Align::Center);
+    add_button.add_css_class("flat");
+    lock_when_read_only(&add_button);
+    let remote_path = format!("//{}/{}", address, share.name);
+    let dialog = dialog.clone();
+    add_button.connect_clicked(move |button| {
+        if !allow_write(button) {
+            return;
+        }
+        let add_dialog = AddRemoteShareDialog::for_remote_path(&remote_path);
+        add_dialog.present(Some(&dialog));
+    });
+    row.add_suffix(&add_button);
+    row
+}
+
+/// Mount `uri` for the session and open it in the file manager, as guest first when
+/// the share lets guests in and with the password prompt when the server refuses
+fn quick_mount(button: &gtk4::Button, uri: &str, guest: bool, toast_overlay: &adw::ToastOverlay) {
+    let operation = if guest {
+        guest_mount_operation()
+    } else {
+        let window = parent_window(button);
+        gtk4::MountOperation::new(window.as_ref()).upcast()
+    };
+    let file = gio::File::for_uri(uri);
+
+    button.set_sensitive(false);
+    let button = button.clone();
+    let uri = uri.to_string();
+    let toast_overlay = toast_overlay.clone();
+    let file_clone = file.clone();
+    file.mount_enclosing_volume(
+        gio::MountMountFlags::NONE,
+        Some(&operation),
+        None::<&gio::Cancellable>,
+        move |result| {
+            button.set_sensitive(true);
+            match result {
+                Err(e) if guest && !e.matches(gio::IOErrorEnum::AlreadyMounted) => {
+                    eprintln!("Guest access to {} refused, asking for credentials: {}", uri, e);
+                    quick_mount(&button, &uri, false, &toast_overlay);
+                }
+                // Already mounted counts as success, it opens all the same
+                Err(e) if !e.matches(gio::IOErrorEnum::AlreadyMounted) => {
+                    eprintln!("Failed to mount {}: {}", file_clone.uri(), e);
+                    let error_msg = format!("{}: {}", gettext("Mount failed"), e);
+                    toast_overlay.add_toast(adw::Toast::new(&error_msg));
+                }
+                _ => {
+                    gtk4::FileLauncher::new(Some(&file_clone)).launch(
+                        parent_window(&button).as_ref(),
+                        None::<&gio::Cancellable>,
+                        move |result| {
+                            if let Err(e) = result {
+                                eprintln!("Failed to open folder: {}", e);
+                            }
+                        },
+                    );
+                }
+            }
+        },
+    );
+}
+
+/// Mount operation logging in anonymously, without any dialog. A second question
+/// means the server refused, which is answered by giving up.
+fn guest_mount_operation() -> gio::MountOperation {
+    let operation = gio::MountOperation::new();
+    let asked = Cell::new(false);
+    operation.connect_ask_password(move |operation, _, _, _, flags| {
+        if flags.contains(gio::AskPasswordFlags::ANONYMOUS_SUPPORTED) && !asked.replace(true) {
+            operation.set_anonymous(true);
+            operation.reply(gio::MountOperationResult::Handled);
+        } else {
+            operation.reply(gio::MountOperationResult::Aborted);
+        }
+    });
+    operation
+}
@@ -0,0 +1,699 @@
+use crate::config::AppConfig;
+use crate::models::AppState;
+use crate::samba::credentials_file;
+use crate::samba::credentials_migration;
+use crate::samba::mount_defaults::{parse_option_list, MountDefaults, SystemdMount};
+use crate::samba::mount_operations::RetryPolicy;
+use crate::samba::neighborhood::DiscoveryScan;
+use crate::samba::network::invalid_host_entries;
+use crate::samba::nix_imports::config_files;
+use crate::samba::paths::config_path;
+use crate::samba::remote_share_config::RemoteSambaShareConfig;
+use crate::samba::server_template::{is_netbios_name, ServerTemplate};
+use crate::samba::share_backend::{backend, BackendKind};
+use crate::samba::terminal::detect_terminals;
+use crate::ui::background;
+use crate::ui::widgets::{allow_write, idle_timeout_combo, lock_when_read_only, selected_idle_timeout};
+use gettextrs::gettext;
+use gtk4::prelude::*;
+use libadwaita as adw;
+use libadwaita::prelude::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Choices of the share backend row, None detecting it from the system
+const BACKEND_CHOICES: [Option<BackendKind>; 3] = [None, Some(BackendKind::Nixos), Some(BackendKind::SmbConf)];
+
+pub struct PreferencesDialog {
+    dialog: adw::Dialog,
+}
+
+impl PreferencesDialog {
+    pub fn new() -> Self {
+        let dialog = adw::Dialog::new();
+        dialog.set_title(&gettext("Preferences"));
+        dialog.set_content_width(500);
+        dialog.set_content_height(550);
+
+        // Create toolbar header
+        let toolbar_view = adw::ToolbarView::new();
+        let header_bar = adw::HeaderBar::new();
+        toolbar_view.add_top_bar(&header_bar);
+
+        let preferences_page = adw::PreferencesPage::new();
+        let toast_overlay = adw::ToastOverlay::new();
+        toast_overlay.set_child(Some(&preferences_page));
+
+        // Configuration Group
+        let config_group = adw::PreferencesGroup::new();
+        config_group.set_title(&gettext("Configuration"));
+        config_group.set_description(Some(
+            &gettext("Currently editing {file}. Changes to these settings apply the next time the app starts.")
+                .replace("{file}", &backend().description()),
+        ));
+
+        // Same order as BACKEND_CHOICES
+        let backend_combo = adw::ComboRow::new();
+        backend_combo.set_title(&gettext("Save Shares To"));
+        backend_combo.set_model(Some(&gtk4::StringList::new(&[
+            gettext("Automatic").as_str(),
+            gettext("NixOS Configuration").as_str(),
+            "smb.conf",
+        ])));
+        let current_backend = AppConfig::new().share_backend();
+        let selected = BACKEND_CHOICES.iter().position(|choice| *choice == current_backend).unwrap_or(0);
+        backend_combo.set_selected(selected as u32);
+        config_group.add(&backend_combo);
+
+        let config_file_entry = adw::EntryRow::new();
+        config_file_entry.set_title(&gettext("Configuration File"));
+        config_file_entry.set_text(&AppConfig::new().nixos_config_path().unwrap_or_default());
+        config_file_entry.set_tooltip_text(Some(&format!(
+            "{} {}",
+            gettext("File shares are saved to, empty for the default one, e.g."),
+            "/etc/nixos/configuration.nix"
+        )));
+        config_group.add(&config_file_entry);
+
+        // Imported files are only read unless allowed here, the app can't tell which ones
+        // are generated or shared with other machines
+        let managed_files = AppConfig::new().managed_files();
+        let imported_files: Vec<String> = config_files(config_path())
+            .into_iter()
+            .skip(1)
+            .map(|file| file.to_string_lossy().to_string())
+            .collect();
+        let managed_rows: Vec<(String, adw::SwitchRow)> = imported_files
+            .iter()
+            .map(|file| {
+                let row = adw::SwitchRow::new();
+                row.set_use_markup(false);
+                row.set_title(file);
+                row.set_active(managed_files.contains(file));
+                (file.clone(), row)
+            })
+            .collect();
+        if backend().kind() == BackendKind::Nixos && !managed_rows.is_empty() {
+            let imports_row = adw::ExpanderRow::new();
+            imports_row.set_title(&gettext("Editable Imported Files"));
+            imports_row.set_subtitle(&gettext(
+                "Shares defined in the other files are shown read-only, with a button opening the file",
+            ));
+            for (_, row) in &managed_rows {
+                imports_row.add_row(row);
+            }
+            config_group.add(&imports_row);
+        }
+
+        preferences_page.add(&config_group);
+
+        // Rebuild Group
+        let rebuild_group = adw::PreferencesGroup::new();
+        rebuild_group.set_title(&gettext("Rebuild"));
+        rebuild_group.set_description(Some(&gettext(
+            "Terminal opened to run nixos-rebuild after saving",
+        )));
+
+        let terminal_combo = adw::ComboRow::new();
+        terminal_combo.set_title(&gettext("Terminal"));
+        terminal_combo.set_subtitle(&gettext("Automatic tries the known terminals in order"));
+        rebuild_group.add(&terminal_combo);
+
+        let custom_entry = adw::EntryRow::new();
+        custom_entry.set_title(&gettext("Custom Command"));
+        custom_entry.set_tooltip_text(Some(&format!(
+            "{} {}",
+            gettext("Write {script} where the rebuild script goes, e.g."),
+            "foot -e {script}"
+        )));
+        custom_entry.set_visible(false);
+        rebuild_group.add(&custom_entry);
+
+        preferences_page.add(&rebuild_group);
+
+        // Background Group
+        let background_group = adw::PreferencesGroup::new();
+        background_group.set_title(&gettext("Background"));
+
+        let background_row = adw::SwitchRow::new();
+        background_row.set_title(&gettext("Run in Background"));
+        background_row.set_subtitle(&gettext(
+            "Keep watching favorite shares after the window is closed and notify when they are mounted or unmounted",
+        ));
+        background_row.set_active(AppConfig::new().run_in_background());
+        background_group.add(&background_row);
+
+        preferences_page.add(&background_group);
+
+        // New Remote Shares Group
+        let mount_defaults = AppConfig::new().mount_defaults();
+        let mount_group = adw::PreferencesGroup::new();
+        mount_group.set_title(&gettext("New Remote Shares"));
+        mount_group.set_description(Some(&gettext(
+            "Mount options new remote shares start with, shares already added keep theirs",
+        )));
+
+        let automount_row = adw::SwitchRow::new();
+        automount_row.set_title(&gettext("Mount on access"));
+        automount_row.set_subtitle(&gettext(
+            "Mount the share the first time it is opened (x-systemd.automount)",
+        ));
+        automount_row.set_active(mount_defaults.systemd.automount);
+        mount_group.add(&automount_row);
+
+        let noauto_row = adw::SwitchRow::new();
+        noauto_row.set_title(&gettext("Skip at startup"));
+        noauto_row.set_subtitle(&gettext(
+            "Don't mount the share while the computer starts (noauto)",
+        ));
+        noauto_row.set_active(mount_defaults.systemd.noauto);
+        mount_group.add(&noauto_row);
+
+        let device_timeout_row = timeout_row(
+            &gettext("Server Timeout"),
+            &gettext("Seconds to wait for the server, 0 for systemd's default (x-systemd.device-timeout)"),
+            mount_defaults.systemd.device_timeout,
+        );
+        mount_group.add(&device_timeout_row);
+
+        let mount_timeout_row = timeout_row(
+            &gettext("Mount Timeout"),
+            &gettext("Seconds the mount may take, 0 for systemd's default (x-systemd.mount-timeout)"),
+            mount_defaults.systemd.mount_timeout,
+        );
+        mount_group.add(&mount_timeout_row);
+
+        let idle_combo = idle_timeout_combo(mount_defaults.idle_timeout);
+        mount_group.add(&idle_combo);
+
+        let options_entry = adw::EntryRow::new();
+        options_entry.set_title(&gettext("Other Options (comma separated)"));
+        options_entry.set_text(&mount_defaults.extra_options.join(","));
+        options_entry.set_tooltip_text(Some(&format!(
+            "{} {}",
+            gettext("Added to the mount options of new shares, e.g."),
+            "vers=3.1.1,nofail"
+        )));
+        mount_group.add(&options_entry);
+
+        let credentials_folder_entry = adw::EntryRow::new();
+        credentials_folder_entry.set_title(&gettext("Credentials Folder"));
+        credentials_folder_entry.set_text(&AppConfig::new().credentials_folder());
+        credentials_folder_entry.set_tooltip_text(Some(&gettext(
+            "Where the credentials files created for new shares are saved, readable by root only",
+        )));
+        let move_files_button = gtk4::Button::with_label(&gettext("Move Files Here..."));
+        move_files_button.set_valign(gtk4::Align::Center);
+        move_files_button.set_tooltip_text(Some(&gettext(
+            "Move the credentials files of the configured shares to this folder and update their entries",
+        )));
+        lock_when_read_only(&move_files_button);
+        let folder_entry = credentials_folder_entry.clone();
+        let toast_overlay_clone = toast_overlay.clone();
+        move_files_button.connect_clicked(move |button| {
+            if allow_write(button) {
+                move_credentials_files(button, &folder_entry.text(), &toast_overlay_clone);
+            }
+        });
+        credentials_folder_entry.add_suffix(&move_files_button);
+        mount_group.add(&credentials_folder_entry);
+
+        preferences_page.add(&mount_group);
+
+        // Mounting Group
+        let mount_retry = AppConfig::new().mount_retry();
+        let retry_group = adw::PreferencesGroup::new();
+        retry_group.set_title(&gettext("Mounting"));
+        retry_group.set_description(Some(&gettext(
+            "How shares mounted from the app wait for a server that doesn't answer",
+        )));
+
+        let attempts_row = adw::SpinRow::with_range(1.0, 10.0, 1.0);
+        attempts_row.set_title(&gettext("Mount Attempts"));
+        attempts_row.set_subtitle(&gettext("Tries before giving up when the server is unreachable"));
+        attempts_row.set_value(mount_retry.attempts as f64);
+        retry_group.add(&attempts_row);
+
+        let retry_delay_row = timeout_row(
+            &gettext("First Retry Delay"),
+            &gettext("Seconds before trying again, doubled after each failure"),
+            Some(mount_retry.initial_delay),
+        );
+        retry_group.add(&retry_delay_row);
+
+        let attempt_timeout_row = timeout_row(
+            &gettext("Time Limit per Attempt"),
+            &gettext("Seconds a mount may hang before it is stopped, 0 for no limit"),
+            Some(mount_retry.timeout),
+        );
+        retry_group.add(&attempt_timeout_row);
+
+        preferences_page.add(&retry_group);
+
+        // Network Discovery Group
+        let discovery_scan = AppConfig::new().discovery_scan();
+        let discovery_group = adw::PreferencesGroup::new();
+        discovery_group.set_title(&gettext("Network Discovery"));
+        discovery_group.set_description(Some(&gettext(
+            "How servers are looked for besides mDNS, which Samba and macOS servers answer",
+        )));
+
+        let netbios_row = adw::SwitchRow::new();
+        netbios_row.set_title(&gettext("NetBIOS Broadcast"));
+        netbios_row.set_subtitle(&gettext("Find older Windows computers and Samba servers with their workgroup"));
+        netbios_row.set_active(discovery_scan.netbios);
+        discovery_group.add(&netbios_row);
+
+        let ws_discovery_row = adw::SwitchRow::new();
+        ws_discovery_row.set_title(&gettext("WS-Discovery"));
+        ws_discovery_row.set_subtitle(&gettext("Find recent Windows computers, listed by address"));
+        ws_discovery_row.set_active(discovery_scan.ws_discovery);
+        discovery_group.add(&ws_discovery_row);
+
+        preferences_page.add(&discovery_group);
+
+        // New Samba Server Group
+        let server_template = AppConfig::new().server_template();
+        let server_group = adw::PreferencesGroup::new();
+        server_group.set_title(&gettext("New Samba Server"));
+        server_group.set_description(Some(&gettext(
+            "Settings written when the first share creates the Samba configuration. Change an existing configuration in Server Settings.",
+        )));
+
+        let detect_button = gtk4::Button::with_label(&gettext("Detect"));
+        detect_button.add_css_class("flat");
+        detect_button.set_tooltip_text(Some(&gettext(
+            "Use the host name and the networks this computer is connected to",
+        )));
+        server_group.set_header_suffix(Some(&detect_button));
+
+        let workgroup_entry = adw::EntryRow::new();
+        workgroup_entry.set_title(&gettext("Workgroup"));
+        server_group.add(&workgroup_entry);
+
+        let server_name_entry = adw::EntryRow::new();
+        server_name_entry.set_title(&gettext("Server Name"));
+        server_name_entry.set_tooltip_text(Some(&gettext(
+            "Name shown in the network neighborhood, up to 15 letters, digits and dashes",
+        )));
+        server_group.add(&server_name_entry);
+
+        let hosts_entry = adw::EntryRow::new();
+        hosts_entry.set_title(&gettext("Allowed Networks"));
+        hosts_entry.set_tooltip_text(Some(&gettext(
+            "Networks like 192.168.1.0/24 separated by spaces, other hosts are denied. Leave empty to allow every host.",
+        )));
+        server_group.add(&hosts_entry);
+
+        let guest_row = adw::SwitchRow::new();
+        guest_row.set_title(&gettext("Guest Access for Unknown Users"));
+        guest_row.set_subtitle(&gettext(
+            "Let clients logging in with an unknown user name in as guest (map to guest)",
+        ));
+        server_group.add(&guest_row);
+
+        preferences_page.add(&server_group);
+
+        let fill_server_rows = {
+            let workgroup_entry = workgroup_entry.clone();
+            let server_name_entry = server_name_entry.clone();
+            let hosts_entry = hosts_entry.clone();
+            let guest_row = guest_row.clone();
+            move |template: &ServerTemplate| {
+                workgroup_entry.set_text(&template.workgroup);
+                server_name_entry.set_text(&template.server_name);
+                hosts_entry.set_text(&template.hosts_allow.join(" "));
+                guest_row.set_active(template.map_to_guest);
+            }
+        };
+        fill_server_rows(&server_template);
+        detect_button.connect_clicked(move |_| fill_server_rows(&ServerTemplate::detect()));
+
+        // Flag the values Samba wouldn't accept while they are typed
+        workgroup_entry.connect_changed(|entry| {
+            set_error(entry, !is_netbios_name(entry.text().trim()));
+        });
+        server_name_entry.connect_changed(|entry| {
+            set_error(entry, !is_netbios_name(entry.text().trim()));
+        });
+        hosts_entry.connect_changed(|entry| {
+            set_error(entry, !invalid_host_entries(&host_list(&entry.text())).is_empty());
+        });
+
+        // Login Mounts Group
+        let login_group = adw::PreferencesGroup::new();
+        login_group.set_title(&gettext("Mount at Login"));
+        login_group.set_description(Some(&gettext(
+            "Network locations mounted in the file manager when you log in, with the passwords saved in your keyring",
+        )));
+
+        // Selected locations first, then bookmarks and current GVfs mounts
+        let app_config = AppConfig::new();
+        let selected = app_config.login_mounts();
+        let mut candidates = selected.clone();
+        let mounted = gio::VolumeMonitor::get()
+            .mounts()
+            .into_iter()
+            .map(|mount| mount.root().uri().to_string())
+            .filter(|uri| uri.starts_with("smb://"));
+        for uri in app_config.smb_bookmarks().into_iter().chain(mounted) {
+            if !candidates.contains(&uri) {
+                candidates.push(uri);
+            }
+        }
+
+        let login_checks: Rc<RefCell<Vec<(String, gtk4::CheckButton)>>> = Rc::new(RefCell::new(Vec::new()));
+        for uri in &candidates {
+            let (row, check) = login_mount_row(uri, selected.contains(uri));
+            login_group.add(&row);
+            login_checks.borrow_mut().push((uri.clone(), check));
+        }
+
+        let location_entry = adw::EntryRow::new();
+        location_entry.set_title(&gettext("Other Location (smb://server/share)"));
+        location_entry.set_show_apply_button(true);
+        login_group.add(&location_entry);
+
+        // A typed location gets its own row, selected, above the entry
+        let login_group_clone = login_group.clone();
+        let login_checks_clone = login_checks.clone();
+        location_entry.connect_apply(move |entry| {
+            let uri = entry.text().trim().to_string();
+            let known = login_checks_clone.borrow().iter().any(|(existing, _)| *existing == uri);
+            if !uri.starts_with("smb://") || known {
+                entry.add_css_class("error");
+                return;
+            }
+            entry.remove_css_class("error");
+
+            let (row, check) = login_mount_row(&uri, true);
+            login_group_clone.remove(entry);
+            login_group_clone.add(&row);
+            login_group_clone.add(entry);
+            login_checks_clone.borrow_mut().push((uri, check));
+            entry.set_text("");
+        });
+
+        preferences_page.add(&login_group);
+        toolbar_view.set_content(Some(&toast_overlay));
+        dialog.set_child(Some(&toolbar_view));
+
+        // Command templates matching the combo items; None for "Automatic" and "Custom"
+        let templates: Rc<RefCell<Vec<Option<&'static str>>>> = Rc::new(RefCell::new(Vec::new()));
+        let current = AppConfig::new().terminal_command();
+
+        // Detecting installed terminals runs a command per terminal
+        terminal_combo.set_model(Some(&gtk4::StringList::new(&[gettext("Automatic").as_str()])));
+        terminal_combo.set_sensitive(false);
+        let spinner = gtk4::Spinner::new();
+        spinner.start();
+        terminal_combo.add_suffix(&spinner);
+
+        let terminal_combo_clone = terminal_combo.clone();
+        let custom_entry_clone = custom_entry.clone();
+        let templates_clone = templates.clone();
+        glib::spawn_future_local(async move {
+            let detected = gio::spawn_blocking(detect_terminals).await.unwrap_or_else(|e| {
+                eprintln!("Failed to detect terminals: {:?}", e);
+                Vec::new()
+            });
+
+            let automatic = gettext("Automatic");
+            let custom = gettext("Custom");
+            let mut names = vec![automatic.as_str()];
+            let mut items = vec![None];
+            for (name, template) in &detected {
+                names.push(*name);
+                items.push(Some(*template));
+            }
+            names.push(custom.as_str());
+            items.push(None);
+
+            // A configured command that isn't one of the detected terminals is custom
+            let selected = match current.as_deref() {
+                None => 0,
+                Some(command) => items
+                    .iter()
+                    .position(|template| *template == Some(command))
+                    .unwrap_or_else(|| {
+                        custom_entry_clone.set_text(command);
+                        items.len() - 1
+                    }),
+            };
+
+            *templates_clone.borrow_mut() = items;
+            terminal_combo_clone.set_model(Some(&gtk4::StringList::new(&names)));
+            terminal_combo_clone.set_selected(selected as u32);
+            custom_entry_clone.set_visible(selected == names.len() - 1);
+
+            spinner.stop();
+            terminal_combo_clone.remove(&spinner);
+            terminal_combo_clone.set_sensitive(true);
+        });
+
+        // The custom command is only editable with "Custom" selected
+        let custom_entry_clone = custom_entry.clone();
+        terminal_combo.connect_selected_notify(move |combo| {
+            let is_custom = combo
+                .model()
+                .is_some_and(|model| model.n_items() > 1 && combo.selected() == model.n_items() - 1);
+            custom_entry_clone.set_visible(is_custom);
+        });
+
+        // Save when the dialog is closed
+        let terminal_combo_clone = terminal_combo.clone();
+        let custom_entry_clone = custom_entry.clone();
+        dialog.connect_closed(move |_| {
+            let login_mounts: Vec<String> = login_checks
+                .borrow()
+                .iter()
+                .filter(|(_, check)| check.is_active())
+                .map(|(uri, _)| uri.clone())
+                .collect();
+            if let Err(e) = AppConfig::new().set_login_mounts(&login_mounts) {
+                eprintln!("Failed to install the login autostart entry: {}", e);
+            }
+
+            let run_in_background = background_row.is_active();
+            AppConfig::new().set_run_in_background(run_in_background);
+            if let Some(app) = gio::Application::default().and_downcast::<adw::Application>() {
+                background::set_enabled(&app, run_in_background);
+            }
+
+            let spin_seconds = |row: &adw::SpinRow| Some(row.value() as u32).filter(|seconds| *seconds > 0);
+            AppConfig::new().set_mount_defaults(&MountDefaults {
+                systemd: SystemdMount {
+                    automount: automount_row.is_active(),
+                    noauto: noauto_row.is_active(),
+                    device_timeout: spin_seconds(&device_timeout_row),
+                    mount_timeout: spin_seconds(&mount_timeout_row),
+                },
+                idle_timeout: selected_idle_timeout(&idle_combo, mount_defaults.idle_timeout),
+                extra_options: parse_option_list(&options_entry.text()),
+            });
+
+            AppConfig::new().set_mount_retry(&RetryPolicy {
+                attempts: attempts_row.value() as u32,
+                initial_delay: retry_delay_row.value() as u32,
+                timeout: attempt_timeout_row.value() as u32,
+            });
+
+            AppConfig::new().set_discovery_scan(&DiscoveryScan {
+                netbios: netbios_row.is_active(),
+                ws_discovery: ws_discovery_row.is_active(),
+            });
+
+            let backend = BACKEND_CHOICES.get(backend_combo.selected() as usize).copied().flatten();
+            AppConfig::new().set_share_backend(backend);
+
+            // Files no longer imported keep their choice, in case they come back
+            let mut managed: Vec<String> = managed_files
+                .iter()
+                .filter(|file| !imported_files.contains(file))
+                .cloned()
+                .collect();
+            managed.extend(
+                managed_rows
+                    .iter()
+                    .filter(|(_, row)| row.is_active())
+                    .map(|(file, _)| file.clone()),
+            );
+            AppConfig::new().set_managed_files(&managed);
+
+            // Only absolute files, an empty entry going back to the default one
+            let config_file = config_file_entry.text().trim().to_string();
+            if config_file.is_empty() || config_file.starts_with('/') {
+                AppConfig::new().set_nixos_config_path(Some(config_file.as_str()).filter(|path| !path.is_empty()));
+            }
+
+            // Only absolute folders, an empty entry going back to the default one
+            let credentials_folder = credentials_folder_entry.text().trim().to_string();
+            if credentials_folder.is_empty() || credentials_folder.starts_with('/') {
+                AppConfig::new().set_credentials_folder(&credentials_folder);
+            }
+
+            // Keep following the detected settings until they are changed
+            let template = ServerTemplate {
+                workgroup: workgroup_entry.text().trim().to_string(),
+                server_name: server_name_entry.text().trim().to_string(),
+                hosts_allow: host_list(&hosts_entry.text()),
+                map_to_guest: guest_row.is_active(),
+            };
+            if template != server_template {
+                match template.validate() {
+                    Ok(()) => AppConfig::new().set_server_template(&template),
+                    Err(e) => eprintln!("Keeping the previous server settings: {}", e),
+                }
+            }
+
+            // Detection didn't finish, keep the current setting
+            if !terminal_combo_clone.is_sensitive() {
+                return;
+            }
+
+            let app_config = AppConfig::new();
+            if custom_entry_clone.is_visible() {
+                let command = custom_entry_clone.text();
+                let command = command.trim();
+                app_config.set_terminal_command(Some(command).filter(|command| !command.is_empty()));
+            } else {
+                let selected = terminal_combo_clone.selected() as usize;
+                let template = templates.borrow().get(selected).copied().flatten();
+                app_config.set_terminal_command(template);
+            }
+        });
+
+        Self { dialog }
+    }
+
+    pub fn present(&self, parent: Option<&impl IsA<gtk4::Widget>>) {
+        self.dialog.present(parent);
+    }
+}
+
+/// Move the credentials files of the configured shares into `folder` once confirmed,
+/// the entries pointing to them being updated in the same step
+fn move_credentials_files(button: &gtk4::Button, folder: &str, toast_overlay: &adw::ToastOverlay) {
+    let folder = match folder.trim() {
+        "" => credentials_file::DEFAULT_FOLDER.to_string(),
+        folder if folder.starts_with('/') => folder.trim_end_matches('/').to_string(),
+        _ => {
+            toast_overlay.add_toast(adw::Toast::new(&gettext("The credentials folder needs an absolute path")));
+            return;
+        }
+    };
+
+    button.set_sensitive(false);
+    let button = button.clone();
+    let toast_overlay = toast_overlay.clone();
+    glib::spawn_future_local(async move {
+        let planned_folder = folder.clone();
+        let result = gio::spawn_blocking(move || {
+            let shares = RemoteSambaShareConfig::load_all()?;
+            let moves = credentials_migration::plan(&shares, &planned_folder);
+            let changes = credentials_migration::config_changes(&shares, &moves)?;
+            Ok((moves, changes))
+        })
+        .await
+        .unwrap_or_else(|e| Err(format!("{:?}", e)));
+        button.set_sensitive(true);
+
+        let (moves, changes) = match result {
+            Ok(planned) => planned,
+            Err(e) => {
+                eprintln!("Failed to list the credentials files: {}", e);
+                let error_msg = format!("{}: {}", gettext("Failed to move the credentials files"), e);
+                toast_overlay.add_toast(adw::Toast::new(&error_msg));
+                return;
+            }
+        };
+        if moves.is_empty() {
+            toast_overlay.add_toast(adw::Toast::new(&gettext("The credentials files are already in this folder")));
+            return;
+        }
+
+        let list: Vec<String> = moves
+            .iter()
+            .map(|planned| format!("{} → {}", planned.from, planned.to))
+            .collect();
+        let body = format!(
+            "{}\n\n{}",
+            gettext("The old paths become links to the new files, so mounts keep working until the next rebuild."),
+            list.join("\n")
+        );
+        let alert = adw::AlertDialog::new(Some(&gettext("Move Credentials Files?")), Some(&body));
+        alert.add_responses(&[("cancel", &gettext("Cancel")), ("move", &gettext("Move"))]);
+        alert.set_response_appearance("move", adw::ResponseAppearance::Suggested);
+        alert.set_close_response("cancel");
+
+        let toast_overlay_clone = toast_overlay.clone();
+        alert.connect_response(Some("move"), move |_, _| {
+            let folder = folder.clone();
+            let moves = moves.clone();
+            let changes = changes.clone();
+            let toast_overlay = toast_overlay_clone.clone();
+            glib::spawn_future_local(async move {
+                let migrated = folder.clone();
+                let written = changes.clone();
+                let result = gio::spawn_blocking(move || credentials_migration::migrate(&migrated, &moves, &written))
+                    .await
+                    .unwrap_or_else(|e| Err(format!("{:?}", e)));
+
+                match result {
+                    Ok(()) => {
+                        AppConfig::new().set_credentials_folder(&folder);
+                        let state = AppState::get();
+                        for change in changes {
+                            state.add_pending_change(change);
+                        }
+                        state.reload_remote();
+                        toast_overlay.add_toast(adw::Toast::new(&gettext(
+                            "Credentials files moved. Run 'sudo nixos-rebuild switch' to apply changes.",
+                        )));
+                    }
+                    Err(e) => {
+                        eprintln!("Failed to move the credentials files: {}", e);
+                        let error_msg = format!("{}: {}", gettext("Failed to move the credentials files"), e);
+                        toast_overlay.add_toast(adw::Toast::new(&error_msg));
+                    }
+                }
+            });
+        });
+        alert.present(Some(&button));
+    });
+}
+
+/// Row choosing a timeout in seconds, 0 standing for none
+fn timeout_row(title: &str, subtitle: &str, seconds: Option<u32>) -> adw::SpinRow {
+    let row = adw::SpinRow::with_range(0.0, 600.0, 1.0);
+    row.set_title(title);
+    row.set_subtitle(subtitle);
+    row.set_value(seconds.unwrap_or(0) as f64);
+    row
+}
+
+/// Entries of a space separated host list
+fn host_list(text: &str) -> Vec<String> {
+    text.split_whitespace().map(|host| host.to_string()).collect()
+}
+
+fn set_error(entry: &adw::EntryRow, error: bool) {
+    if error {
+        entry.add_css_class("error");
+    } else {
+        entry.remove_css_class("error");
+    }
+}
+
+/// Row selecting a location to mount at login
+fn login_mount_row(uri: &str, active: bool) -> (adw::ActionRow, gtk4::CheckButton) {
+    let check = gtk4::CheckButton::new();
+    check.set_active(active);
+
+    let row = adw::ActionRow::new();
+    row.set_title(uri);
+    row.set_use_markup(false);
+    row.add_prefix(&check);
+    row.set_activatable_widget(Some(&check));
+    (row, check)
+}
@@ -0,0 +1,244 @@
+use crate::config::AppConfig;
+use crate::models::AppState;
+use crate::samba::users::{is_valid_user_name, provision_private_share};
+use crate::samba::{get_system_users, SambaShareConfig};
+use crate::ui::widgets::selected_string;
+use gettextrs::gettext;
+use gtk4::prelude::*;
+use libadwaita as adw;
+use libadwaita::prelude::*;
+
+/// Base folder proposed for new private shares
+const DEFAULT_SHARE_ROOT: &str = "/srv/samba";
+
+/// Guided flow creating a share restricted to one person:
+/// system user, Samba password, owned folder and share with `valid users`
+pub struct PrivateShareWizard {
+    dialog: adw::Dialog,
+}
+
+impl PrivateShareWizard {
+    pub fn new() -> Self {
+        let dialog = adw::Dialog::new();
+        dialog.set_title(&gettext("Private Share for a Person"));
+        dialog.set_content_width(500);
+        dialog.set_content_height(550);
+
+        let navigation_view = adw::NavigationView::new();
+
+        // Wrap navigation in toast overlay for error messages
+        let toast_overlay = adw::ToastOverlay::new();
+        toast_overlay.set_child(Some(&navigation_view));
+        dialog.set_child(Some(&toast_overlay));
+
+        // ============ Step 1: Person ============
+        let person_group = adw::PreferencesGroup::new();
+        person_group.set_title(&gettext("Who is this share for?"));
+        person_group.set_description(Some(&gettext(
+            "Pick an existing account or create one used only for file sharing",
+        )));
+
+        let mut user_labels = vec![gettext("Create a new user")];
+        user_labels.extend(get_system_users());
+        let user_combo = adw::ComboRow::new();
+        user_combo.set_title(&gettext("User"));
+        user_combo.set_model(Some(&gtk4::StringList::new(
+            &user_labels.iter().map(|s| s.as_str()).collect::<Vec<_>>(),
+        )));
+        user_combo.set_selected(0);
+        person_group.add(&user_combo);
+
+        let new_user_entry = adw::EntryRow::new();
+        new_user_entry.set_title(&gettext("New User Name"));
+        person_group.add(&new_user_entry);
+
+        let new_user_entry_clone = new_user_entry.clone();
+        user_combo.connect_selected_notify(move |combo| {
+            new_user_entry_clone.set_visible(combo.selected() == 0);
+        });
+
+        let person_next = gtk4::Button::with_label(&gettext("Next"));
+        person_next.add_css_class("suggested-action");
+        let person_page = wizard_page(&gettext("Person"), &person_group, &person_next);
+        navigation_view.add(&person_page);
+
+        // ============ Step 2: Samba password ============
+        let password_group = adw::PreferencesGroup::new();
+        password_group.set_title(&gettext("Samba Password"));
+        password_group.set_description(Some(&gettext(
+            "Password used to connect to the share from other computers",
+        )));
+
+        let password_entry = adw::PasswordEntryRow::new();
+        password_entry.set_title(&gettext("Password"));
+        password_group.add(&password_entry);
+
+        let confirm_entry = adw::PasswordEntryRow::new();
+        confirm_entry.set_title(&gettext("Confirm Password"));
+        password_group.add(&confirm_entry);
+
+        let password_next = gtk4::Button::with_label(&gettext("Next"));
+        password_next.add_css_class("suggested-action");
+        let password_page = wizard_page(&gettext("Password"), &password_group, &password_next);
+
+        // ============ Step 3: Folder and share ============
+        let folder_group = adw::PreferencesGroup::new();
+        folder_group.set_title(&gettext("Shared Folder"));
+        folder_group.set_description(Some(&gettext(
+            "The folder is created if needed and only this user can access it",
+        )));
+
+        let share_name_entry = adw::EntryRow::new();
+        share_name_entry.set_title(&gettext("Share Name"));
+        folder_group.add(&share_name_entry);
+
+        let folder_entry = adw::EntryRow::new();
+        folder_entry.set_title(&gettext("Path"));
+        folder_group.add(&folder_entry);
+
+        let create_button = gtk4::Button::with_label(&gettext("Create Share"));
+        create_button.add_css_class("suggested-action");
+        let folder_page = wizard_page(&gettext("Folder"), &folder_group, &create_button);
+
+        // Resolve the chosen user name
+        let user_combo_clone = user_combo.clone();
+        let new_user_entry_clone = new_user_entry.clone();
+        let chosen_user = move || -> (String, bool) {
+            if user_combo_clone.selected() == 0 {
+                (new_user_entry_clone.text().trim().to_string(), true)
+            } else {
+                (selected_string(&user_combo_clone), false)
+            }
+        };
+
+        // Step 1 -> 2
+        let navigation_view_clone = navigation_view.clone();
+        let toast_overlay_clone = toast_overlay.clone();
+        let share_name_entry_clone = share_name_entry.clone();
+        let folder_entry_clone = folder_entry.clone();
+        let chosen_user_clone = chosen_user.clone();
+        person_next.connect_clicked(move |_| {
+            let (user, _) = chosen_user_clone();
+            if !is_valid_user_name(&user) {
+                let toast = adw::Toast::new(&gettext(
+                    "User names use lowercase letters, digits, '-' and '_'",
+                ));
+                toast_overlay_clone.add_toast(toast);
+                return;
+            }
+
+            // Suggest a share name and folder for this user
+            if share_name_entry_clone.text().is_empty() {
+                share_name_entry_clone.set_text(&user);
+            }
+            if folder_entry_clone.text().is_empty() {
+                folder_entry_clone.set_text(&format!("{}/{}", DEFAULT_SHARE_ROOT, user));
+            }
+            navigation_view_clone.push(&password_page);
+        });
+
+        // Step 2 -> 3
+        let navigation_view_clone = navigation_view.clone();
+        let toast_overlay_clone = toast_overlay.clone();
+        let password_entry_clone = password_entry.clone();
+        let confirm_entry_clone = confirm_entry.clone();
+        password_next.connect_clicked(move |_| {
+            if password_entry_clone.text().is_empty() {
+                toast_overlay_clone.add_toast(adw::Toast::new(&gettext("Password is required")));
+                return;
+            }
+            if password_entry_clone.text() != confirm_entry_clone.text() {
+                toast_overlay_clone.add_toast(adw::Toast::new(&gettext("Passwords do not match")));
+                return;
+            }
+            navigation_view_clone.push(&folder_page);
+        });
+
+        // Create everything
+        let dialog_clone = dialog.clone();
+        let toast_overlay_clone = toast_overlay.clone();
+        create_button.connect_clicked(move |button| {
+            let (user, create_user) = chosen_user();
+            let password = password_entry.text().to_string();
+            let share_name = share_name_entry.text().trim().to_string();
+            let folder = folder_entry.text().trim().to_string();
+
+            if share_name.is_empty() {
+                toast_overlay_clone.add_toast(adw::Toast::new(&gettext("Share name is required")));
+                return;
+            }
+            if folder.is_empty() {
+                toast_overlay_clone.add_toast(adw::Toast::new(&gettext("Path is required")));
+                return;
+            }
+
+            let mut share = SambaShareConfig::new(
+                share_name,
+                folder.clone(),
+                true,
+                false,
+                false,
+                String::new(),
+                String::new(),
+            );
+            share.valid_users = vec![user.clone()];
+
+            button.set_sensitive(false);
+            let button = button.clone();
+            let dialog = dialog_clone.clone();
+            let toast_overlay = toast_overlay_clone.clone();
+            let template = AppConfig::new().server_template();
+            glib::spawn_future_local(async move {
+                let result = gio::spawn_blocking(move || {
+                    provision_private_share(&user, create_user, &password, &folder)?;
+                    share.write(&template)
+                })
+                .await;
+
+                button.set_sensitive(true);
+                match result {
+                    Ok(Ok(change)) => {
+                        let state = AppState::get();
+                        state.add_pending_change(change);
+                        state.reload_local();
+                        dialog.close();
+                    }
+                    Ok(Err(e)) => {
+                        eprintln!("Failed to create private share: {}", e);
+                        let error_msg =
+                            format!("{}: {}", gettext("Failed to create private share"), e);
+                        toast_overlay.add_toast(adw::Toast::new(&error_msg));
+                    }
+                    Err(e) => {
+                        let error_msg = format!("{}: {:?}", gettext("Error"), e);
+                        toast_overlay.add_toast(adw::Toast::new(&error_msg));
+                    }
+                }
+            });
+        });
+
+        Self { dialog }
+    }
+
+    pub fn present(&self, parent: Option<&impl IsA<gtk4::Widget>>) {
+        self.dialog.present(parent);
+    }
+}
+
+/// Build one wizard step with its own header bar and the action button on the right
+pub(crate) fn wizard_page(
+    title: &str,
+    group: &adw::PreferencesGroup,
+    action_button: &gtk4::Button,
+) -> adw::NavigationPage {
+    let toolbar_view = adw::ToolbarView::new();
+    let header_bar = adw::HeaderBar::new();
+    header_bar.pack_end(action_button);
+    toolbar_view.add_top_bar(&header_bar);
+
+    let preferences_page = adw::PreferencesPage::new();
+    preferences_page.add(group);
+    toolbar_view.set_content(Some(&preferences_page));
+
+    adw::NavigationPage::new(&toolbar_view, title)
+}
@@ -0,0 +1,72 @@
+use crate::ui::widgets::file_picker::parent_window;
+use gettextrs::gettext;
+use gtk4::gio;
+use gtk4::prelude::*;
+use libadwaita as adw;
+use libadwaita::prelude::*;
+
+/// Settings of a share defined in a file the app doesn't edit, opened by Edit instead
+/// of the edit dialog, with a button opening that file
+pub struct ReadOnlyShareDialog {
+    dialog: adw::Dialog,
+}
+
+impl ReadOnlyShareDialog {
+    /// `settings` are shown in order, as title and value rows
+    pub fn new(name: &str, settings: &[(String, String)], source_file: &str) -> Self {
+        let dialog = adw::Dialog::new();
+        dialog.set_title(name);
+        dialog.set_content_width(500);
+        dialog.set_content_height(500);
+
+        // Create toolbar header
+        let toolbar_view = adw::ToolbarView::new();
+        let header_bar = adw::HeaderBar::new();
+        toolbar_view.add_top_bar(&header_bar);
+
+        // File the share comes from, opened in the default editor
+        let banner = adw::Banner::new(&gettext("Defined in {file}, which isn't edited here").replace("{file}", source_file));
+        banner.set_use_markup(false);
+        banner.set_button_label(Some(&gettext("Open Containing File")));
+        banner.set_revealed(true);
+        let file = gio::File::for_path(source_file);
+        banner.connect_button_clicked(move |banner| {
+            gtk4::FileLauncher::new(Some(&file)).launch(
+                parent_window(banner).as_ref(),
+                None::<&gio::Cancellable>,
+                |result| {
+                    if let Err(e) = result {
+                        eprintln!("Failed to open file: {}", e);
+                    }
+                },
+            );
+        });
+        toolbar_view.add_top_bar(&banner);
+
+        let preferences_page = adw::PreferencesPage::new();
+        let settings_group = adw::PreferencesGroup::new();
+        settings_group.set_title(&gettext("Settings"));
+        settings_group.set_description(Some(&gettext(
+            "Change the share in that file, or allow editing the file in the Preferences",
+        )));
+        for (title, value) in settings {
+            let row = adw::ActionRow::new();
+            row.set_use_markup(false);
+            row.set_title(title);
+            row.set_subtitle(value);
+            row.set_subtitle_selectable(true);
+            row.add_css_class("property");
+            settings_group.add(&row);
+        }
+        preferences_page.add(&settings_group);
+
+        toolbar_view.set_content(Some(&preferences_page));
+        dialog.set_child(Some(&toolbar_view));
+
+        Self { dialog }
+    }
+
+    pub fn present(&self, parent: Option<&impl IsA<gtk4::Widget>>) {
+        self.dialog.present(parent);
+    }
+}
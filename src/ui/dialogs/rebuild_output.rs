@@ -0,0 +1,53 @@
+use crate::ui::widgets::source_view;
+use gettextrs::gettext;
+use gtk4::prelude::*;
+use libadwaita as adw;
+use libadwaita::prelude::*;
+
+/// Output captured from the last nixos-rebuild run
+pub struct RebuildOutputDialog {
+    dialog: adw::Dialog,
+}
+
+impl RebuildOutputDialog {
+    pub fn new(output: &str) -> Self {
+        let dialog = adw::Dialog::new();
+        dialog.set_title(&gettext("Rebuild Output"));
+        dialog.set_content_width(800);
+        dialog.set_content_height(500);
+
+        // Create toolbar header
+        let toolbar_view = adw::ToolbarView::new();
+        let header_bar = adw::HeaderBar::new();
+        toolbar_view.add_top_bar(&header_bar);
+
+        let text = if output.is_empty() {
+            gettext("nixos-rebuild didn't print anything")
+        } else {
+            output.to_string()
+        };
+        let view = source_view(&text, "sh");
+        view.set_wrap_mode(gtk4::WrapMode::WordChar);
+
+        let scrolled = gtk4::ScrolledWindow::builder()
+            .hexpand(true)
+            .vexpand(true)
+            .child(&view)
+            .build();
+
+        // Show the end of the output, where errors are
+        let adjustment = scrolled.vadjustment();
+        adjustment.connect_changed(|adjustment| {
+            adjustment.set_value(adjustment.upper() - adjustment.page_size());
+        });
+
+        toolbar_view.set_content(Some(&scrolled));
+        dialog.set_child(Some(&toolbar_view));
+
+        Self { dialog }
+    }
+
+    pub fn present(&self, parent: Option<&impl IsA<gtk4::Widget>>) {
+        self.dialog.present(parent);
+    }
+}
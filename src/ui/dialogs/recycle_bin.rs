@@ -0,0 +1,252 @@
+use crate::samba::recycle::{RecycleBin, RecycledFile};
+use crate::samba::SambaShareConfig;
+use crate::ui::widgets::{allow_write, lock_when_read_only};
+use crate::utils::format::{format_size, format_timestamp};
+use gettextrs::gettext;
+use gtk4::prelude::*;
+use gtk4::{gio, glib};
+use libadwaita as adw;
+use libadwaita::prelude::*;
+use std::cell::RefCell;
+use std::path::Path;
+use std::rc::Rc;
+
+/// Files deleted from a share with the recycle module, which can be put back or removed for good
+pub struct RecycleBinDialog {
+    dialog: adw::Dialog,
+}
+
+/// Widgets and files of the viewer, shared by its callbacks
+struct Viewer {
+    share_path: String,
+    bin: RecycleBin,
+    toast_overlay: adw::ToastOverlay,
+    list: gtk4::ListBox,
+    restore_button: gtk4::Button,
+    purge_button: gtk4::Button,
+    /// Files listed with the check box selecting each
+    files: RefCell<Vec<(RecycledFile, gtk4::CheckButton)>>,
+}
+
+impl RecycleBinDialog {
+    pub fn new(share: &SambaShareConfig) -> Self {
+        let dialog = adw::Dialog::new();
+        dialog.set_title(&format!("{} – {}", gettext("Recycle Bin"), share.name));
+        dialog.set_content_width(600);
+        dialog.set_content_height(500);
+
+        // Create toolbar header
+        let toolbar_view = adw::ToolbarView::new();
+        let header_bar = adw::HeaderBar::new();
+        let refresh_button = gtk4::Button::from_icon_name("view-refresh-symbolic");
+        refresh_button.set_tooltip_text(Some(&gettext("Refresh")));
+        header_bar.pack_start(&refresh_button);
+        toolbar_view.add_top_bar(&header_bar);
+
+        // Actions on the selected files
+        let action_bar = gtk4::ActionBar::new();
+        let purge_button = gtk4::Button::with_label(&gettext("Delete Permanently"));
+        purge_button.add_css_class("destructive-action");
+        lock_when_read_only(&purge_button);
+        action_bar.pack_start(&purge_button);
+        let restore_button = gtk4::Button::with_label(&gettext("Restore"));
+        restore_button.add_css_class("suggested-action");
+        lock_when_read_only(&restore_button);
+        action_bar.pack_end(&restore_button);
+        toolbar_view.add_bottom_bar(&action_bar);
+
+        let list = gtk4::ListBox::new();
+        list.add_css_class("boxed-list");
+        list.set_selection_mode(gtk4::SelectionMode::None);
+        list.set_valign(gtk4::Align::Start);
+        let placeholder = gtk4::Label::new(Some(&gettext("The recycle bin is empty")));
+        placeholder.add_css_class("dim-label");
+        placeholder.set_margin_top(12);
+        placeholder.set_margin_bottom(12);
+        list.set_placeholder(Some(&placeholder));
+
+        let clamp = adw::Clamp::new();
+        clamp.set_child(Some(&list));
+        clamp.set_margin_top(12);
+        clamp.set_margin_bottom(12);
+        clamp.set_margin_start(12);
+        clamp.set_margin_end(12);
+        let scrolled = gtk4::ScrolledWindow::builder()
+            .hexpand(true)
+            .vexpand(true)
+            .child(&clamp)
+            .build();
+
+        let toast_overlay = adw::ToastOverlay::new();
+        toast_overlay.set_child(Some(&scrolled));
+        toolbar_view.set_content(Some(&toast_overlay));
+        dialog.set_child(Some(&toolbar_view));
+
+        let viewer = Rc::new(Viewer {
+            share_path: share.path.clone(),
+            bin: RecycleBin::of(share),
+            toast_overlay,
+            list,
+            restore_button: restore_button.clone(),
+            purge_button: purge_button.clone(),
+            files: RefCell::new(Vec::new()),
+        });
+
+        let viewer_clone = viewer.clone();
+        refresh_button.connect_clicked(move |_| Viewer::load(&viewer_clone));
+
+        let viewer_clone = viewer.clone();
+        restore_button.connect_clicked(move |button| {
+            if !allow_write(button) {
+                return;
+            }
+            Viewer::restore(&viewer_clone);
+        });
+
+        let viewer_clone = viewer.clone();
+        purge_button.connect_clicked(move |button| {
+            if !allow_write(button) {
+                return;
+            }
+            Viewer::confirm_purge(&viewer_clone, button);
+        });
+
+        Viewer::load(&viewer);
+
+        Self { dialog }
+    }
+
+    pub fn present(&self, parent: Option<&impl IsA<gtk4::Widget>>) {
+        self.dialog.present(parent);
+    }
+}
+
+impl Viewer {
+    /// List the bin again, bins are usually private so this asks for authentication
+    fn load(viewer: &Rc<Self>) {
+        while let Some(row) = viewer.list.first_child() {
+            viewer.list.remove(&row);
+        }
+        viewer.files.borrow_mut().clear();
+        viewer.update_buttons();
+
+        let viewer = viewer.clone();
+        let bin = viewer.bin.clone();
+        glib::spawn_future_local(async move {
+            let result = gio::spawn_blocking(move || bin.list())
+                .await
+                .unwrap_or_else(|e| Err(format!("{:?}", e)));
+
+            let files = match result {
+                Ok(files) => files,
+                Err(e) => {
+                    eprintln!("Failed to list the recycle bin: {}", e);
+                    let error_msg = format!("{}: {}", gettext("Failed to list the recycle bin"), e);
+                    viewer.toast_overlay.add_toast(adw::Toast::new(&error_msg));
+                    return;
+                }
+            };
+            for file in files {
+                let row = viewer.file_row(&file);
+                viewer.list.append(&row.0);
+                viewer.files.borrow_mut().push((file, row.1));
+            }
+        });
+    }
+
+    /// Row of a recycled file with the check box selecting it
+    fn file_row(self: &Rc<Self>, file: &RecycledFile) -> (adw::ActionRow, gtk4::CheckButton) {
+        let path = Path::new(&file.path);
+        let row = adw::ActionRow::new();
+        row.set_title(&path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default());
+        row.set_use_markup(false);
+
+        let deleted = format_timestamp(file.deleted);
+        let restored_to = self
+            .bin
+            .original_path(&self.share_path, file)
+            .and_then(|original| original.parent().map(|parent| parent.display().to_string()))
+            .unwrap_or_default();
+        row.set_subtitle(&format!("{} • {} • {}", restored_to, format_size(file.size), deleted));
+
+        let check = gtk4::CheckButton::new();
+        check.set_valign(gtk4::Align::Center);
+        row.add_prefix(&check);
+        row.set_activatable_widget(Some(&check));
+
+        // Weak, the viewer owns the check box
+        let viewer = Rc::downgrade(self);
+        check.connect_toggled(move |_| {
+            if let Some(viewer) = viewer.upgrade() {
+                viewer.update_buttons();
+            }
+        });
+        (row, check)
+    }
+
+    fn selected(&self) -> Vec<RecycledFile> {
+        self.files
+            .borrow()
+            .iter()
+            .filter(|(_, check)| check.is_active())
+            .map(|(file, _)| file.clone())
+            .collect()
+    }
+
+    fn update_buttons(&self) {
+        let any = !self.selected().is_empty();
+        self.restore_button.set_sensitive(any);
+        self.purge_button.set_sensitive(any);
+    }
+
+    /// Put the selected files back, never over an existing file
+    fn restore(viewer: &Rc<Self>) {
+        let files = viewer.selected();
+        let bin = viewer.bin.clone();
+        let share_path = viewer.share_path.clone();
+        Self::run(viewer, move || bin.restore(&share_path, &files), gettext("Files restored"));
+    }
+
+    fn confirm_purge(viewer: &Rc<Self>, parent: &gtk4::Button) {
+        let count = viewer.selected().len();
+        let dialog = adw::AlertDialog::new(
+            Some(&gettext("Delete Permanently?")),
+            Some(&gettext("{count} files will be deleted for good.").replace("{count}", &count.to_string())),
+        );
+        dialog.add_responses(&[("cancel", &gettext("Cancel")), ("delete", &gettext("Delete"))]);
+        dialog.set_response_appearance("delete", adw::ResponseAppearance::Destructive);
+        dialog.set_default_response(Some("cancel"));
+        dialog.set_close_response("cancel");
+
+        let viewer = viewer.clone();
+        dialog.connect_response(Some("delete"), move |_, _| {
+            let files = viewer.selected();
+            let bin = viewer.bin.clone();
+            Self::run(&viewer, move || bin.purge(&files), gettext("Files deleted"));
+        });
+        dialog.present(Some(parent));
+    }
+
+    /// Run a privileged action in the background, then list the bin again
+    fn run(viewer: &Rc<Self>, action: impl FnOnce() -> Result<(), String> + Send + 'static, done_message: String) {
+        viewer.restore_button.set_sensitive(false);
+        viewer.purge_button.set_sensitive(false);
+
+        let viewer = viewer.clone();
+        glib::spawn_future_local(async move {
+            let result = gio::spawn_blocking(action)
+                .await
+                .unwrap_or_else(|e| Err(format!("{:?}", e)));
+
+            let message = match result {
+                Ok(()) => done_message,
+                Err(e) => {
+                    eprintln!("Recycle bin action failed: {}", e);
+                    format!("{}: {}", gettext("Failed to change the recycle bin"), e)
+                }
+            };
+            viewer.toast_overlay.add_toast(adw::Toast::new(&message));
+            Self::load(&viewer);
+        });
+    }
+}
@@ -1,38 +1,60 @@
-use crate::samba::{list_all_shares, mount_share, unmount_share, MountOptions};
+use crate::config::{AppConfig, Favorite};
+use crate::models::app_state::RemoteShares;
+use crate::models::AppState;
+use crate::samba::open_files::blocking_processes;
+use crate::samba::{
+    is_leftover_mount_point, remount_share, remove_mount_point, unmount_share, MountedShare, ShareState,
+};
+use crate::samba::idle_timeout;
 use crate::samba::remote_share_config::RemoteSambaShareConfig;
-use crate::ui::dialogs::{AddRemoteShareDialog, EditRemoteShareDialog};
+use crate::samba::share_backend::is_managed_file;
+use crate::samba::wake_on_lan::{learn_mac_address, wait_until_online, wake};
+use crate::ui::dialogs::busy_unmount::show_blocking_processes;
+use crate::ui::dialogs::list_shares::UNDO_TIMEOUT;
+use crate::ui::dialogs::{
+    confirm_delete, AddRemoteShareDialog, EditRemoteShareDialog, FstabImportDialog, MountCredentialsDialog,
+    MountTuningDialog, ReadOnlyShareDialog, ShareTestDialog,
+};
+use crate::ui::widgets::{
+    allow_write, appearance_button, favorite_button, lock_when_read_only, locked_badge, locked_row,
+    mount_backend_label, notes_row, option_drift_badge, progress_group, remote_share_badge,
+};
+use crate::utils::format::format_timeout;
 use gettextrs::gettext;
 use gtk4::prelude::*;
 use libadwaita as adw;
 use libadwaita::prelude::*;
-use std::path::Path;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 
 pub struct RemoteListSharesDialog {
-    window: adw::Window,
+    dialog: adw::Dialog,
     toast_overlay: adw::ToastOverlay,
 }
 
 impl RemoteListSharesDialog {
     pub fn new() -> Self {
-        let window = adw::Window::new();
-        window.set_title(Some(&gettext("Remote Samba Shares")));
-        window.set_default_size(700, 500);
-        window.set_modal(true);
+        let dialog = adw::Dialog::new();
+        dialog.set_title(&gettext("Remote Samba Shares"));
+        dialog.set_content_width(700);
+        dialog.set_content_height(500);
 
         // Create toolbar header
         let toolbar_view = adw::ToolbarView::new();
         let header_bar = adw::HeaderBar::new();
         toolbar_view.add_top_bar(&header_bar);
 
-        // Close button
-        let close_button = gtk4::Button::with_label(&gettext("Close"));
-        header_bar.pack_start(&close_button);
-
         // Add button
         let add_button = gtk4::Button::from_icon_name("list-add-symbolic");
         add_button.set_tooltip_text(Some(&gettext("Add Remote Share")));
         header_bar.pack_end(&add_button);
 
+        // Import button, for shares an old fstab mounts
+        let import_button = gtk4::Button::from_icon_name("document-open-symbolic");
+        import_button.set_tooltip_text(Some(&gettext("Import from fstab")));
+        header_bar.pack_end(&import_button);
+
         // Refresh button
         let refresh_button = gtk4::Button::from_icon_name("view-refresh-symbolic");
         refresh_button.set_tooltip_text(Some(&gettext("Refresh")));
@@ -44,72 +66,97 @@ impl RemoteListSharesDialog {
             .vexpand(true)
             .build();
 
-        // Create preferences page
-        let preferences_page = adw::PreferencesPage::new();
+        // Spinner shown in the header while shares and mount status load
+        let spinner = gtk4::Spinner::new();
+        header_bar.pack_end(&spinner);
 
         // Wrap in toast overlay
         let toast_overlay = adw::ToastOverlay::new();
         toast_overlay.set_child(Some(&toolbar_view));
 
-        window.set_content(Some(&toast_overlay));
+        toolbar_view.set_content(Some(&scrolled));
+        dialog.set_child(Some(&toast_overlay));
 
-        let dialog = Self {
-            window: window.clone(),
+        let this = Self {
+            dialog: dialog.clone(),
             toast_overlay: toast_overlay.clone(),
         };
 
-        // Load shares
-        dialog.load_shares(&preferences_page);
-
-        scrolled.set_child(Some(&preferences_page));
-        toolbar_view.set_content(Some(&scrolled));
+        // Show what is already known, the progress of the first load otherwise
+        let state = AppState::get();
+        match state.remote_shares() {
+            Some(result) => Self::show_shares(&scrolled, &dialog, &toast_overlay, result),
+            None => {
+                let preferences_page = adw::PreferencesPage::new();
+                preferences_page.add(&progress_group(|| AppState::get().remote_progress()));
+                scrolled.set_child(Some(&preferences_page));
+            }
+        }
 
-        // Handle close button
-        let window_clone = window.clone();
-        close_button.connect_clicked(move |_| {
-            window_clone.close();
+        // Follow every later change to the shares and mounts, wherever it comes from.
+        // Refresh stays disabled until a load finishes.
+        let scrolled_clone = scrolled.clone();
+        let dialog_clone = dialog.clone();
+        let toast_clone = toast_overlay.clone();
+        let spinner_clone = spinner.clone();
+        let refresh_button_clone = refresh_button.clone();
+        let handler = state.connect_remote_shares_changed(move |state| {
+            spinner_clone.stop();
+            refresh_button_clone.set_sensitive(true);
+            if let Some(result) = state.remote_shares() {
+                Self::show_shares(&scrolled_clone, &dialog_clone, &toast_clone, result);
+            }
         });
+        state.disconnect_on_close(&dialog, handler);
+        Self::reload(&spinner, &refresh_button);
 
         // Handle add button
-        let window_for_add = window.clone();
-        add_button.connect_clicked(move |_| {
+        let dialog_for_add = dialog.clone();
+        add_button.connect_clicked(move |button| {
+            if !allow_write(button) {
+                return;
+            }
             let add_dialog = AddRemoteShareDialog::new();
-            add_dialog.present(Some(&window_for_add));
+            add_dialog.present(Some(&dialog_for_add));
+        });
+
+        // Handle import button
+        let dialog_for_import = dialog.clone();
+        import_button.connect_clicked(move |button| {
+            if !allow_write(button) {
+                return;
+            }
+            FstabImportDialog::new().present(Some(&dialog_for_import));
         });
 
         // Handle refresh button
-        let preferences_page_clone = preferences_page.clone();
-        let dialog_clone = dialog.window.clone();
-        let toast_clone = toast_overlay.clone();
-        refresh_button.connect_clicked(move |_| {
-            // Create a new preferences page for reload
-            let new_page = adw::PreferencesPage::new();
-
-            // Reload shares into new page
-            Self::load_shares_static(&new_page, &dialog_clone, &toast_clone);
-
-            // Replace the old page with the new one
-            // Note: GTK4 doesn't have a direct way to clear all children from PreferencesPage
-            // so we would need to recreate the entire view, or iterate through groups
-            // For simplicity in this context, we just show a toast
-            let toast = adw::Toast::new(&gettext("Please close and reopen to refresh"));
-            toast_clone.add_toast(toast);
+        refresh_button.connect_clicked(move |button| {
+            Self::reload(&spinner, button);
         });
 
-        dialog
+        this
     }
 
-    fn load_shares(&self, preferences_page: &adw::PreferencesPage) {
-        Self::load_shares_static(preferences_page, &self.window, &self.toast_overlay);
+    /// Load shares and mount status again; the list follows through the state signal
+    fn reload(spinner: &gtk4::Spinner, refresh_button: &gtk4::Button) {
+        refresh_button.set_sensitive(false);
+        spinner.start();
+        AppState::get().reload_remote();
     }
 
-    fn load_shares_static(
-        preferences_page: &adw::PreferencesPage,
-        window: &adw::Window,
+    /// Replace the list with one group per server, or an empty/error state
+    fn show_shares(
+        scrolled: &gtk4::ScrolledWindow,
+        dialog: &adw::Dialog,
         toast_overlay: &adw::ToastOverlay,
+        result: RemoteShares,
     ) {
-        // Load shares from configuration + mount status
-        match list_all_shares() {
+        // A fresh page, since PreferencesPage has no way to clear its groups
+        let preferences_page = adw::PreferencesPage::new();
+        scrolled.set_child(Some(&preferences_page));
+
+        // Shares from configuration + mount status
+        match result {
             Ok(shares) => {
                 if shares.is_empty() {
                     // Show empty state
@@ -126,183 +173,20 @@ impl RemoteListSharesDialog {
                     empty_group.add(&empty_box);
                     preferences_page.add(&empty_group);
                 } else {
-                    // Create a group for each share
+                    // Group shares by server so long lists stay readable
+                    let mut servers: BTreeMap<String, Vec<MountedShare>> = BTreeMap::new();
                     for share in shares {
-                        let group = adw::PreferencesGroup::new();
-
-                        // Title with mount status indicator
-                        let title = if share.is_mounted {
-                            format!("{} ●", share.target)
-                        } else {
-                            format!("{} ○", share.target)
-                        };
-                        group.set_title(&title);
-
-                        // Description
-                        let desc = if share.is_mounted {
-                            gettext("Mounted")
-                        } else {
-                            gettext("Not mounted")
-                        };
-                        group.set_description(Some(&desc));
-
-                        // Remote path row
-                        let path_row = adw::ActionRow::new();
-                        path_row.set_title(&gettext("Remote Path"));
-                        path_row.set_subtitle(&share.source);
-                        group.add(&path_row);
-
-                        // Mount point row
-                        let mount_row = adw::ActionRow::new();
-                        mount_row.set_title(&gettext("Mount Point"));
-                        mount_row.set_subtitle(&share.target);
-                        group.add(&mount_row);
-
-                        // Filesystem type row
-                        let fs_type_row = adw::ActionRow::new();
-                        fs_type_row.set_title(&gettext("Type"));
-                        fs_type_row.set_subtitle(&share.fstype);
-                        group.add(&fs_type_row);
-
-                        // Options row (truncated if too long)
-                        let options_text = if share.options.len() > 60 {
-                            format!("{}...", &share.options[..60])
-                        } else {
-                            share.options.clone()
-                        };
-                        let options_row = adw::ActionRow::new();
-                        options_row.set_title(&gettext("Options"));
-                        options_row.set_subtitle(&options_text);
-                        group.add(&options_row);
-
-                        // Buttons row
-                        let button_row = adw::ActionRow::new();
-                        let button_box = gtk4::Box::new(gtk4::Orientation::Horizontal, 6);
-
-                        // Edit button (always visible)
-                        let edit_button = gtk4::Button::with_label(&gettext("Edit"));
-                        edit_button.set_valign(gtk4::Align::Center);
-
-                        // Create RemoteSambaShareConfig from share data for editing
-                        let remote_config = RemoteSambaShareConfig::new(
-                            share.target.clone(),
-                            share.source.clone(),
-                            share.fstype.clone(),
-                            // Extract credentials from options
-                            share.options
-                                .split(',')
-                                .find(|opt| opt.contains("credentials="))
-                                .and_then(|opt| opt.split('=').nth(1))
-                                .unwrap_or("")
-                                .to_string(),
-                            // Extract uid from options
-                            share.options
-                                .split(',')
-                                .find(|opt| opt.contains("uid="))
-                                .and_then(|opt| opt.split('=').nth(1))
-                                .unwrap_or("1000")
-                                .to_string(),
-                            // Extract gid from options
-                            share.options
-                                .split(',')
-                                .find(|opt| opt.contains("gid="))
-                                .and_then(|opt| opt.split('=').nth(1))
-                                .unwrap_or("100")
-                                .to_string(),
-                        );
-
-                        let window_for_edit = window.clone();
-                        edit_button.connect_clicked(move |_| {
-                            let edit_dialog = EditRemoteShareDialog::new(&remote_config);
-                            edit_dialog.present(Some(&window_for_edit));
-                        });
+                        servers.entry(share.server().to_string()).or_default().push(share);
+                    }
 
-                        button_box.append(&edit_button);
-
-                        if share.is_mounted {
-                            // Unmount button
-                            let unmount_button = gtk4::Button::with_label(&gettext("Unmount"));
-                            unmount_button.set_valign(gtk4::Align::Center);
-                            unmount_button.add_css_class("destructive-action");
-
-                            let target = share.target.clone();
-                            let toast_clone = toast_overlay.clone();
-                            let window_clone = window.clone();
-                            unmount_button.connect_clicked(move |button| {
-                                button.set_sensitive(false);
-
-                                let target_path = Path::new(&target).to_path_buf();
-                                let toast = toast_clone.clone();
-                                let btn = button.clone();
-
-                                glib::spawn_future_local(async move {
-                                    let result = gio::spawn_blocking(move || {
-                                        unmount_share(&target_path)
-                                    })
-                                    .await;
-
-                                    btn.set_sensitive(true);
-
-                                    match result {
-                                        Ok(Ok(())) => {
-                                            let toast_msg =
-                                                adw::Toast::new(&gettext("Share unmounted successfully"));
-                                            toast.add_toast(toast_msg);
-                                            // Note: Should refresh the list here
-                                        }
-                                        Ok(Err(e)) => {
-                                            let toast_msg = adw::Toast::new(&format!(
-                                                "{}: {}",
-                                                gettext("Unmount failed"),
-                                                e
-                                            ));
-                                            toast.add_toast(toast_msg);
-                                        }
-                                        Err(e) => {
-                                            let toast_msg = adw::Toast::new(&format!(
-                                                "{}: {:?}",
-                                                gettext("Error"),
-                                                e
-                                            ));
-                                            toast.add_toast(toast_msg);
-                                        }
-                                    }
-                                });
-                            });
-
-                            button_box.append(&unmount_button);
-                        } else {
-                            // Mount button
-                            let mount_button = gtk4::Button::with_label(&gettext("Mount"));
-                            mount_button.set_valign(gtk4::Align::Center);
-                            mount_button.add_css_class("suggested-action");
-
-                            let source = share.source.clone();
-                            let target = share.target.clone();
-                            let toast_clone = toast_overlay.clone();
-                            mount_button.connect_clicked(move |button| {
-                                button.set_sensitive(false);
-
-                                // TODO: Get credentials from user input dialog
-                                // For now, show a message that manual mount via CLI is needed
-                                let toast = adw::Toast::new(&gettext(
-                                    "Mount requires credentials. Use 'sudo mount -t cifs ...' or nixos-rebuild.",
-                                ));
-                                toast_clone.add_toast(toast);
-
-                                button.set_sensitive(true);
-
-                                // Future implementation:
-                                // 1. Show credentials dialog
-                                // 2. Get username/password
-                                // 3. Call mount_share()
-                            });
-
-                            button_box.append(&mount_button);
-                        }
+                    for (server, mut shares) in servers {
+                        // Configured shares first, mounts the configuration doesn't know last
+                        shares.sort_by(|a, b| a.state().cmp(&b.state()).then_with(|| a.target.cmp(&b.target)));
+                        let group = Self::server_group(&server, &shares, toast_overlay);
 
-                        button_row.add_suffix(&button_box);
-                        group.add(&button_row);
+                        for share in shares {
+                            Self::add_share_row(&group, &share, dialog, toast_overlay);
+                        }
 
                         preferences_page.add(&group);
                     }
@@ -324,16 +208,740 @@ impl RemoteListSharesDialog {
         }
     }
 
+    /// Group for one server, with a "Mount All" action for its configured shares
+    fn server_group(
+        server: &str,
+        shares: &[MountedShare],
+        toast_overlay: &adw::ToastOverlay,
+    ) -> adw::PreferencesGroup {
+        let group = adw::PreferencesGroup::new();
+        group.set_title(server);
+
+        let mounted = shares.iter().filter(|share| share.is_mounted).count();
+        let unmanaged = shares.iter().filter(|share| share.state() == ShareState::Unmanaged).count();
+        let mut description = format!(
+            "{}: {} • {}: {}",
+            gettext("Shares"),
+            shares.len(),
+            gettext("Mounted"),
+            mounted
+        );
+        if unmanaged > 0 {
+            description.push_str(&format!(" • {}: {}", gettext("Not in Configuration"), unmanaged));
+        }
+        group.set_description(Some(&description));
+
+        // Only configured shares can be mounted without asking for credentials
+        let targets: Vec<PathBuf> = shares
+            .iter()
+            .filter(|share| !share.is_mounted && share.config.is_some())
+            .map(|share| PathBuf::from(&share.target))
+            .collect();
+
+        let wake_targets = targets.clone();
+
+        let mount_all_button = gtk4::Button::with_label(&gettext("Mount All"));
+        mount_all_button.set_valign(gtk4::Align::Center);
+        mount_all_button.add_css_class("flat");
+        mount_all_button.set_sensitive(!targets.is_empty());
+
+        let toast_clone = toast_overlay.clone();
+        mount_all_button.connect_clicked(move |button| {
+            button.set_sensitive(false);
+
+            let targets = targets.clone();
+            let toast = toast_clone.clone();
+            let btn = button.clone();
+            glib::spawn_future_local(async move {
+                let result = gio::spawn_blocking(move || {
+                    targets
+                        .iter()
+                        .filter_map(|target| {
+                            remount_share(target)
+                                .err()
+                                .map(|e| format!("{}: {}", target.display(), e))
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .await;
+
+                btn.set_sensitive(true);
+                AppState::get().reload_remote();
+
+                let message = match result {
+                    Ok(failures) if failures.is_empty() => gettext("All shares mounted"),
+                    Ok(failures) => format!("{}: {}", gettext("Mount failed"), failures.join(", ")),
+                    Err(e) => format!("{}: {:?}", gettext("Error"), e),
+                };
+                toast.add_toast(adw::Toast::new(&message));
+            });
+        });
+
+        // Learn the hardware address while the server is online, to wake it up later
+        if mounted > 0 {
+            let server = server.to_string();
+            glib::spawn_future_local(async move {
+                let server_clone = server.clone();
+                let learned = gio::spawn_blocking(move || learn_mac_address(&server_clone)).await;
+                if let Ok(Some(mac_address)) = learned {
+                    AppConfig::new().set_server_mac_address(&server, &mac_address);
+                }
+            });
+        }
+
+        let buttons = gtk4::Box::new(gtk4::Orientation::Horizontal, 6);
+        if let Some(mac_address) = AppConfig::new().server_mac_address(server) {
+            buttons.append(&Self::wake_button(server, &mac_address, &wake_targets, toast_overlay));
+        }
+        buttons.append(&mount_all_button);
+        group.set_header_suffix(Some(&buttons));
+        group
+    }
+
+    /// Button waking an offline server with a magic packet, then mounting its shares once it answers
+    fn wake_button(
+        server: &str,
+        mac_address: &str,
+        targets: &[PathBuf],
+        toast_overlay: &adw::ToastOverlay,
+    ) -> gtk4::Button {
+        let button = gtk4::Button::with_label(&gettext("Wake Server"));
+        button.set_valign(gtk4::Align::Center);
+        button.add_css_class("flat");
+        button.set_tooltip_text(Some(&format!("{} {}", gettext("Wake-on-LAN to"), mac_address)));
+        button.set_sensitive(!targets.is_empty());
+
+        let server = server.to_string();
+        let mac_address = mac_address.to_string();
+        let targets = targets.to_vec();
+        let toast_overlay = toast_overlay.clone();
+        button.connect_clicked(move |button| {
+            button.set_sensitive(false);
+            toast_overlay.add_toast(adw::Toast::new(&gettext("Waking the server up...")));
+
+            let server = server.clone();
+            let mac_address = mac_address.clone();
+            let targets = targets.clone();
+            let toast_overlay = toast_overlay.clone();
+            let button = button.clone();
+            glib::spawn_future_local(async move {
+                let result = gio::spawn_blocking(move || {
+                    wake(&mac_address)?;
+                    wait_until_online(&server)?;
+                    Ok::<_, String>(
+                        targets
+                            .iter()
+                            .filter_map(|target| {
+                                remount_share(target)
+                                    .err()
+                                    .map(|e| format!("{}: {}", target.display(), e))
+                            })
+                            .collect::<Vec<_>>(),
+                    )
+                })
+                .await
+                .unwrap_or_else(|e| Err(format!("{:?}", e)));
+
+                button.set_sensitive(true);
+                AppState::get().reload_remote();
+
+                let message = match result {
+                    Ok(failures) if failures.is_empty() => gettext("All shares mounted"),
+                    Ok(failures) => format!("{}: {}", gettext("Mount failed"), failures.join(", ")),
+                    Err(e) => {
+                        eprintln!("Failed to wake the server: {}", e);
+                        format!("{}: {}", gettext("Failed to wake the server"), e)
+                    }
+                };
+                toast_overlay.add_toast(adw::Toast::new(&message));
+            });
+        });
+        button
+    }
+
+    /// Collapsible row showing one share's details and actions
+    fn add_share_row(
+        group: &adw::PreferencesGroup,
+        share: &MountedShare,
+        dialog: &adw::Dialog,
+        toast_overlay: &adw::ToastOverlay,
+    ) {
+        let row = adw::ExpanderRow::new();
+
+        // Title with mount status indicator
+        let title = if share.is_mounted {
+            format!("{} ●", share.target)
+        } else {
+            format!("{} ○", share.target)
+        };
+        row.set_title(&title);
+
+        // Description
+        let desc = match share.state() {
+            ShareState::Mounted => gettext("Mounted"),
+            ShareState::NotMounted => gettext("Not mounted"),
+            ShareState::Unmanaged => gettext("Mounted outside the configuration"),
+        };
+        row.set_subtitle(&desc);
+        row.add_suffix(&remote_share_badge(share.state()));
+        row.add_prefix(&appearance_button(
+            Favorite::Remote(share.target.clone()),
+            "folder-remote-symbolic",
+        ));
+
+        // Entries built from Nix expressions are shown but not editable
+        let locked = share
+            .config
+            .as_ref()
+            .and_then(|config| config.locked_reason.as_ref().map(|r| (r, config)));
+        if let Some((reason, config)) = locked {
+            row.add_suffix(&locked_badge(reason));
+            row.add_row(&locked_row(reason, &config.source_file));
+        }
+
+        // Mounted by hand or before the options were changed
+        if let Some(config) = share.config.as_ref().filter(|_| share.is_mounted) {
+            let differences = config.mount_options().live_differences(&share.options);
+            if !differences.is_empty() {
+                row.add_suffix(&option_drift_badge(config, &differences, toast_overlay));
+            }
+        }
+
+        row.add_suffix(&favorite_button(Favorite::Remote(share.target.clone())));
+
+        // Remote path row
+        let path_row = adw::ActionRow::new();
+        path_row.set_title(&gettext("Remote Path"));
+        path_row.set_subtitle(&share.source);
+        row.add_row(&path_row);
+
+        // Mount point row
+        let mount_row = adw::ActionRow::new();
+        mount_row.set_title(&gettext("Mount Point"));
+        mount_row.set_subtitle(&share.target);
+        row.add_row(&mount_row);
+
+        // Free note about the share, kept out of the configuration
+        row.add_row(&notes_row(Favorite::Remote(share.target.clone())));
+
+        // Filesystem type row
+        let fs_type_row = adw::ActionRow::new();
+        fs_type_row.set_title(&gettext("Type"));
+        fs_type_row.set_subtitle(&share.fstype);
+        row.add_row(&fs_type_row);
+
+        // Backend row, for entries of the configuration
+        if let Some(config) = &share.config {
+            let backend_row = adw::ActionRow::new();
+            backend_row.set_title(&gettext("Backend"));
+            backend_row.set_subtitle(&mount_backend_label(config.backend));
+            row.add_row(&backend_row);
+        }
+
+        // Idle unmount row, systemd entries only
+        if let Some(config) = share.config.as_ref().filter(|c| c.backend.uses_systemd()) {
+            let idle_row = adw::ActionRow::new();
+            idle_row.set_title(&gettext("Disconnect After"));
+            idle_row.set_subtitle(&format_timeout(config.idle_timeout));
+            row.add_row(&idle_row);
+
+            // The automount unit keeps its timeout until the next rebuild
+            let configured = config.idle_timeout;
+            let target = share.target.clone();
+            glib::spawn_future_local(async move {
+                let effective = gio::spawn_blocking(move || idle_timeout::effective(&target))
+                    .await
+                    .unwrap_or(None);
+                if let Some(effective) = effective.filter(|effective| *effective != configured) {
+                    idle_row.set_subtitle(
+                        &gettext("{configured}, {current} until the next rebuild")
+                            .replace("{configured}", &format_timeout(configured))
+                            .replace("{current}", &format_timeout(effective)),
+                    );
+                }
+            });
+        }
+
+        // Options row (truncated if too long)
+        let options = share.options.to_string();
+        let options_text = if options.chars().count() > 60 {
+            format!("{}...", options.chars().take(60).collect::<String>())
+        } else {
+            options
+        };
+        let options_row = adw::ActionRow::new();
+        options_row.set_title(&gettext("Options"));
+        options_row.set_subtitle(&options_text);
+        row.add_row(&options_row);
+
+        // Buttons row
+        let button_row = adw::ActionRow::new();
+        let button_box = gtk4::Box::new(gtk4::Orientation::Horizontal, 6);
+
+        // Edit button (always visible)
+        let edit_button = gtk4::Button::with_label(&gettext("Edit"));
+        edit_button.set_valign(gtk4::Align::Center);
+
+        edit_button.set_sensitive(locked.is_none());
+        if share.config.is_none() {
+            edit_button.set_tooltip_text(Some(&gettext(
+                "Start a new configuration entry from this mount, the mount itself is left as it is",
+            )));
+        }
+
+        // Entries of an imported file the app doesn't manage are only shown
+        let read_only_entry = share
+            .config
+            .clone()
+            .filter(|config| !is_managed_file(&config.source_file, &AppConfig::new().managed_files()));
+        if read_only_entry.is_some() {
+            edit_button.set_tooltip_text(Some(&gettext(
+                "Defined in a file the app doesn't edit, opens a read-only view",
+            )));
+        } else {
+            lock_when_read_only(&edit_button);
+        }
+
+        // Use the configuration entry when there is one, so edits go to its file
+        let remote_config = share.config.clone().unwrap_or_else(|| {
+            RemoteSambaShareConfig::new(
+                share.target.clone(),
+                share.source.clone(),
+                share.fstype.clone(),
+                share.options.credentials.clone().unwrap_or_default(),
+                share.options.uid.clone().unwrap_or_else(|| "1000".to_string()),
+                share.options.gid.clone().unwrap_or_else(|| "100".to_string()),
+            )
+        });
+
+        let dialog_for_edit = dialog.clone();
+        let configured = share.config.is_some();
+        let toast_for_edit = toast_overlay.clone();
+        let read_only_for_edit = read_only_entry.clone();
+        edit_button.connect_clicked(move |button| {
+            if let Some(config) = &read_only_for_edit {
+                read_only_dialog(config).present(Some(&dialog_for_edit));
+                return;
+            }
+            if !allow_write(button) {
+                return;
+            }
+            let edit_dialog = EditRemoteShareDialog::new(&remote_config, configured.then_some(&toast_for_edit));
+            edit_dialog.present(Some(&dialog_for_edit));
+        });
+
+        button_box.append(&edit_button);
+
+        // Connectivity test, to diagnose slow or unreachable shares
+        let test_button = gtk4::Button::with_label(&gettext("Test"));
+        test_button.set_valign(gtk4::Align::Center);
+        test_button.add_css_class("flat");
+
+        let share_clone = share.clone();
+        let dialog_for_test = dialog.clone();
+        test_button.connect_clicked(move |_| {
+            let test_dialog = ShareTestDialog::new(&share_clone);
+            test_dialog.present(Some(&dialog_for_test));
+        });
+        button_box.append(&test_button);
+
+        // Mounts made by hand can become entries of the configuration as they are
+        if share.config.is_none() {
+            let adopt_button = gtk4::Button::with_label(&gettext("Adopt into Configuration"));
+            adopt_button.set_valign(gtk4::Align::Center);
+            adopt_button.add_css_class("flat");
+            adopt_button.set_tooltip_text(Some(&gettext(
+                "Add a fileSystems entry mounting the share as it is mounted now",
+            )));
+            lock_when_read_only(&adopt_button);
+
+            let share_clone = share.clone();
+            let toast_clone = toast_overlay.clone();
+            adopt_button.connect_clicked(move |button| {
+                if !allow_write(button) {
+                    return;
+                }
+                confirm_adopt(button, &share_clone, &toast_clone);
+            });
+            button_box.append(&adopt_button);
+        }
+
+        // Option tuning writes to the entry, so it needs an editable one
+        if let Some(config) = share.config.clone().filter(|_| locked.is_none() && read_only_entry.is_none()) {
+            let tune_button = gtk4::Button::with_label(&gettext("Tune"));
+            tune_button.set_valign(gtk4::Align::Center);
+            tune_button.add_css_class("flat");
+            lock_when_read_only(&tune_button);
+
+            let dialog_for_tune = dialog.clone();
+            tune_button.connect_clicked(move |button| {
+                if !allow_write(button) {
+                    return;
+                }
+                let tuning_dialog = MountTuningDialog::new(&config);
+                tuning_dialog.present(Some(&dialog_for_tune));
+            });
+            button_box.append(&tune_button);
+        }
+
+        // Delete button, only for entries of the configuration
+        if let Some(config) = share.config.clone() {
+            let delete_button = gtk4::Button::with_label(&gettext("Delete"));
+            delete_button.set_valign(gtk4::Align::Center);
+            delete_button.add_css_class("flat");
+            delete_button.add_css_class("error");
+            delete_button.set_sensitive(locked.is_none() && read_only_entry.is_none());
+            lock_when_read_only(&delete_button);
+
+            let target = share.target.clone();
+            let is_mounted = share.is_mounted;
+            let toast_clone = toast_overlay.clone();
+            delete_button.connect_clicked(move |button| {
+                if !allow_write(button) {
+                    return;
+                }
+                start_delete(button, config.clone(), target.clone(), is_mounted, toast_clone.clone(), || {});
+            });
+
+            button_box.append(&delete_button);
+        }
+
+        if share.is_mounted {
+            // Unmount button
+            let unmount_button = gtk4::Button::with_label(&gettext("Unmount"));
+            unmount_button.set_valign(gtk4::Align::Center);
+            unmount_button.add_css_class("destructive-action");
+
+            let target = share.target.clone();
+            // Only configured shares can be mounted again without credentials
+            let can_remount = share.config.is_some();
+            let toast_clone = toast_overlay.clone();
+            unmount_button.connect_clicked(move |button| {
+                start_unmount(button, target.clone(), can_remount, toast_clone.clone());
+            });
+
+            button_box.append(&unmount_button);
+        } else {
+            // Mount button
+            let mount_button = gtk4::Button::with_label(&gettext("Mount"));
+            mount_button.set_valign(gtk4::Align::Center);
+            mount_button.add_css_class("suggested-action");
+
+            let share = share.clone();
+            let dialog_clone = dialog.clone();
+            let toast_clone = toast_overlay.clone();
+            mount_button.connect_clicked(move |_| {
+                MountCredentialsDialog::mount_or_ask(&share, &dialog_clone, &toast_clone);
+            });
+
+            button_box.append(&mount_button);
+        }
+
+        button_row.add_suffix(&button_box);
+        row.add_row(&button_row);
+
+        group.add(&row);
+    }
+
     pub fn present(&self, parent: Option<&impl IsA<gtk4::Widget>>) {
-        if let Some(p) = parent {
-            if let Some(parent_window) = p.dynamic_cast_ref::<gtk4::Window>() {
-                self.window.set_transient_for(Some(parent_window));
+        self.dialog.present(parent);
+    }
+
+    pub fn dialog(&self) -> &adw::Dialog {
+        &self.dialog
+    }
+}
+
+/// Delete the configuration entry of a share once its mount point is typed, offering
+/// to unmount it first when it is mounted. `on_deleted` runs once the entry is gone;
+/// the undo toast goes to `toast_overlay`, which must outlive the caller's dialog.
+/// Read-only view of an entry defined in a file the app doesn't edit
+fn read_only_dialog(config: &RemoteSambaShareConfig) -> ReadOnlyShareDialog {
+    let settings = vec![
+        (gettext("Remote Path"), config.remote_path.clone()),
+        (gettext("Type"), config.fs_type.clone()),
+        (gettext("Backend"), mount_backend_label(config.backend)),
+        (gettext("Credentials File"), config.option_credentials.clone()),
+        (gettext("Options"), config.mount_options().to_options().join(", ")),
+    ];
+    ReadOnlyShareDialog::new(&config.name, &settings, &config.source_file)
+}
+
+pub(crate) fn start_delete(
+    parent: &impl IsA<gtk4::Widget>,
+    config: RemoteSambaShareConfig,
+    target: String,
+    is_mounted: bool,
+    toast_overlay: adw::ToastOverlay,
+    on_deleted: impl Fn() + 'static,
+) {
+    let parent = parent.clone().upcast::<gtk4::Widget>();
+    let on_deleted = Rc::new(on_deleted);
+    let name = config.name.clone();
+    let parent_clone = parent.clone();
+    confirm_delete(
+        &parent,
+        &name,
+        &gettext("The fileSystems entry is removed from the configuration. The share will no longer be mounted after the next rebuild."),
+        move || {
+            if !is_mounted {
+                delete_entry(&config, &target, &toast_overlay);
+                on_deleted();
+                return;
             }
+
+            let alert = adw::AlertDialog::new(
+                Some(&gettext("Unmount the Share?")),
+                Some(&gettext("{folder} is still mounted. Unmount it now, or keep it mounted until the next rebuild.")
+                    .replace("{folder}", &target)),
+            );
+            alert.add_responses(&[
+                ("cancel", &gettext("Cancel")),
+                ("keep", &gettext("Keep Mounted")),
+                ("unmount", &gettext("Unmount and Delete")),
+            ]);
+            alert.set_response_appearance("unmount", adw::ResponseAppearance::Destructive);
+            alert.set_default_response(Some("unmount"));
+            alert.set_close_response("cancel");
+
+            let config = config.clone();
+            let target = target.clone();
+            let toast_overlay = toast_overlay.clone();
+            let on_deleted = on_deleted.clone();
+            alert.connect_response(None, move |_, response| {
+                let unmount = match response {
+                    "keep" => false,
+                    "unmount" => true,
+                    _ => return,
+                };
+                let config = config.clone();
+                let target = target.clone();
+                let toast_overlay = toast_overlay.clone();
+                let on_deleted = on_deleted.clone();
+                glib::spawn_future_local(async move {
+                    if unmount {
+                        let target_path = PathBuf::from(&target);
+                        let result = gio::spawn_blocking(move || unmount_share(&target_path))
+                            .await
+                            .unwrap_or_else(|e| Err(format!("{:?}", e)));
+                        if let Err(e) = result {
+                            eprintln!("Failed to unmount {}: {}", target, e);
+                            let error_msg = format!("{}: {}", gettext("Unmount failed"), e);
+                            toast_overlay.add_toast(adw::Toast::new(&error_msg));
+                            return;
+                        }
+                    }
+                    delete_entry(&config, &target, &toast_overlay);
+                    on_deleted();
+                });
+            });
+            alert.present(Some(&parent_clone));
+        },
+    );
+}
+
+/// Remove the entry as a pending change, with a toast to undo it
+fn delete_entry(config: &RemoteSambaShareConfig, target: &str, toast_overlay: &adw::ToastOverlay) {
+    match config.delete(&config.name) {
+        Ok(change) => {
+            eprintln!("Remote share deleted: {}", config.name);
+            let state = AppState::get();
+            state.add_pending_change(change.clone());
+            state.reload_remote();
+
+            let toast = adw::Toast::new(&gettext("Share deleted"));
+            toast.set_button_label(Some(&gettext("Undo")));
+            toast.set_timeout(UNDO_TIMEOUT);
+
+            let toast_overlay_clone = toast_overlay.clone();
+            toast.connect_button_clicked(move |_| match change.revert() {
+                Ok(_) => {
+                    let state = AppState::get();
+                    state.remove_pending_change(&change);
+                    state.reload_remote();
+                }
+                Err(e) => {
+                    eprintln!("Failed to restore share: {}", e);
+                    let toast_msg = adw::Toast::new(&format!(
+                        "{}: {}",
+                        gettext("Failed to restore share"),
+                        e
+                    ));
+                    toast_overlay_clone.add_toast(toast_msg);
+                }
+            });
+            toast_overlay.add_toast(toast);
+            offer_mount_point_cleanup(toast_overlay, target.to_string());
+        }
+        Err(e) => {
+            eprintln!("Failed to delete remote share: {}", e);
+            let toast_msg = adw::Toast::new(&format!(
+                "{}: {}",
+                gettext("Failed to delete share"),
+                e
+            ));
+            toast_overlay.add_toast(toast_msg);
         }
-        self.window.present();
     }
+}
 
-    pub fn window(&self) -> &adw::Window {
-        &self.window
+/// Show the entry `share` would get, then add it to the configuration as a pending change
+fn confirm_adopt(button: &gtk4::Button, share: &MountedShare, toast_overlay: &adw::ToastOverlay) {
+    let config = share.adoption_config(&AppConfig::new().mount_defaults());
+    let mut body = gettext("A fileSystems entry mounts {remote} on {folder} with these options:")
+        .replace("{remote}", &config.remote_path)
+        .replace("{folder}", &config.name);
+    body.push_str("\n\n");
+    body.push_str(&config.mount_options().to_options().join(","));
+    // The mount table never shows the credentials file the share was mounted with
+    if share.options.extras.iter().any(|option| option.starts_with("username=")) {
+        body.push_str("\n\n");
+        body.push_str(&gettext("Add the credentials file with Edit once the entry is added."));
     }
+
+    let alert = adw::AlertDialog::new(Some(&gettext("Adopt the Mount?")), Some(&body));
+    alert.add_responses(&[("cancel", &gettext("Cancel")), ("adopt", &gettext("Add Entry"))]);
+    alert.set_response_appearance("adopt", adw::ResponseAppearance::Suggested);
+    alert.set_default_response(Some("adopt"));
+    alert.set_close_response("cancel");
+
+    let button_clone = button.clone();
+    let toast_overlay = toast_overlay.clone();
+    alert.connect_response(Some("adopt"), move |_, _| {
+        button_clone.set_sensitive(false);
+        let config = config.clone();
+        let button = button_clone.clone();
+        let toast_overlay = toast_overlay.clone();
+        glib::spawn_future_local(async move {
+            let result = gio::spawn_blocking(move || config.write())
+                .await
+                .unwrap_or_else(|e| Err(format!("{:?}", e)));
+
+            button.set_sensitive(true);
+            match result {
+                Ok(change) => {
+                    let state = AppState::get();
+                    state.add_pending_change(change);
+                    state.reload_remote();
+                    toast_overlay.add_toast(adw::Toast::new(&gettext(
+                        "Share added successfully. Run 'sudo nixos-rebuild switch' to apply changes.",
+                    )));
+                }
+                Err(e) => {
+                    eprintln!("Failed to adopt mount: {}", e);
+                    let error_msg = format!("{}: {}", gettext("Failed to add share"), e);
+                    toast_overlay.add_toast(adw::Toast::new(&error_msg));
+                }
+            }
+        });
+    });
+    alert.present(Some(button));
+}
+
+/// Unmount `target` once no application holds files open there, listing the
+/// ones that do so they can be closed before retrying
+fn start_unmount(button: &gtk4::Button, target: String, can_remount: bool, toast: adw::ToastOverlay) {
+    button.set_sensitive(false);
+    let btn = button.clone();
+
+    glib::spawn_future_local(async move {
+        let target_path = PathBuf::from(&target);
+        let blocking = gio::spawn_blocking(move || blocking_processes(&target_path))
+            .await
+            .unwrap_or_default();
+
+        if !blocking.is_empty() {
+            btn.set_sensitive(true);
+            let retry_button = btn.clone();
+            let retry_target = target.clone();
+            show_blocking_processes(&btn, &target, &blocking, move || {
+                start_unmount(&retry_button, retry_target.clone(), can_remount, toast.clone());
+            });
+            return;
+        }
+
+        let target_path = PathBuf::from(&target);
+        let remount_path = target_path.clone();
+        let result = gio::spawn_blocking(move || unmount_share(&target_path)).await;
+
+        btn.set_sensitive(true);
+
+        match result {
+            Ok(Ok(())) => {
+                let toast_msg = adw::Toast::new(&gettext("Share unmounted successfully"));
+                if can_remount {
+                    toast_msg.set_button_label(Some(&gettext("Undo")));
+                    toast_msg.set_timeout(UNDO_TIMEOUT);
+
+                    let toast_overlay = toast.clone();
+                    toast_msg.connect_button_clicked(move |_| {
+                        let remount_path = remount_path.clone();
+                        let toast_overlay = toast_overlay.clone();
+                        glib::spawn_future_local(async move {
+                            let result = gio::spawn_blocking(move || remount_share(&remount_path)).await;
+
+                            AppState::get().reload_remote();
+                            let message = match result {
+                                Ok(Ok(())) => gettext("Share mounted again"),
+                                Ok(Err(e)) => format!("{}: {}", gettext("Mount failed"), e),
+                                Err(e) => format!("{}: {:?}", gettext("Error"), e),
+                            };
+                            toast_overlay.add_toast(adw::Toast::new(&message));
+                        });
+                    });
+                }
+                toast.add_toast(toast_msg);
+                AppState::get().reload_remote();
+                // Without a configuration entry the share won't be mounted there again
+                if !can_remount {
+                    offer_mount_point_cleanup(&toast, target);
+                }
+            }
+            Ok(Err(e)) => {
+                let toast_msg = adw::Toast::new(&format!("{}: {}", gettext("Unmount failed"), e));
+                toast.add_toast(toast_msg);
+            }
+            Err(e) => {
+                let toast_msg = adw::Toast::new(&format!("{}: {:?}", gettext("Error"), e));
+                toast.add_toast(toast_msg);
+            }
+        }
+    });
+}
+
+/// Offer to delete the mount point of a share that is gone, once nothing is
+/// mounted there and the folder is empty
+fn offer_mount_point_cleanup(toast_overlay: &adw::ToastOverlay, target: String) {
+    let toast_overlay = toast_overlay.clone();
+    glib::spawn_future_local(async move {
+        let target_clone = target.clone();
+        let leftover = gio::spawn_blocking(move || is_leftover_mount_point(Path::new(&target_clone)))
+            .await
+            .unwrap_or(false);
+        if !leftover {
+            return;
+        }
+
+        let toast = adw::Toast::new(&gettext("The folder {folder} is now empty").replace("{folder}", &target));
+        toast.set_button_label(Some(&gettext("Remove Folder")));
+        let toast_overlay_clone = toast_overlay.clone();
+        toast.connect_button_clicked(move |_| {
+            let target = target.clone();
+            let toast_overlay = toast_overlay_clone.clone();
+            glib::spawn_future_local(async move {
+                let result = gio::spawn_blocking(move || remove_mount_point(Path::new(&target)))
+                    .await
+                    .unwrap_or_else(|e| Err(format!("{:?}", e)));
+                let message = match result {
+                    Ok(()) => gettext("Folder removed"),
+                    Err(e) => {
+                        eprintln!("Failed to remove mount point: {}", e);
+                        format!("{}: {}", gettext("Failed to remove folder"), e)
+                    }
+                };
+                toast_overlay.add_toast(adw::Toast::new(&message));
+            });
+        });
+        toast_overlay.add_toast(toast);
+    });
 }
@@ -0,0 +1,283 @@
+use crate::models::AppState;
+use crate::samba::access::{set_user_level, user_level, AccessLevel, Accounts};
+use crate::samba::users::{change_password, samba_users};
+use crate::samba::SambaShareConfig;
+use crate::ui::widgets::{allow_write, lock_when_read_only, skeleton_group};
+use gettextrs::gettext;
+use gtk4::prelude::*;
+use gtk4::{gio, glib};
+use libadwaita as adw;
+use libadwaita::prelude::*;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+/// Lists the users of the Samba password database with the shares they can open,
+/// and lets their access and password be changed
+pub struct SambaUsersDialog {
+    dialog: adw::Dialog,
+}
+
+impl SambaUsersDialog {
+    pub fn new() -> Self {
+        let dialog = adw::Dialog::new();
+        dialog.set_title(&gettext("Samba Users"));
+        dialog.set_content_width(500);
+        dialog.set_content_height(450);
+
+        // Create toolbar header
+        let toolbar_view = adw::ToolbarView::new();
+        let header_bar = adw::HeaderBar::new();
+        toolbar_view.add_top_bar(&header_bar);
+
+        let scrolled = gtk4::ScrolledWindow::builder()
+            .hexpand(true)
+            .vexpand(true)
+            .build();
+        let preferences_page = adw::PreferencesPage::new();
+        preferences_page.add(&skeleton_group(3));
+        scrolled.set_child(Some(&preferences_page));
+
+        let toast_overlay = adw::ToastOverlay::new();
+        toast_overlay.set_child(Some(&scrolled));
+        toolbar_view.set_content(Some(&toast_overlay));
+        dialog.set_child(Some(&toolbar_view));
+
+        // The password database is only readable by root
+        glib::spawn_future_local(async move {
+            let result = gio::spawn_blocking(|| {
+                let users = samba_users()?;
+                // Without shares the passwords can still be changed
+                let shares = SambaShareConfig::load_all().unwrap_or_else(|e| {
+                    eprintln!("Failed to load shares: {}", e);
+                    Vec::new()
+                });
+                Ok((users, shares, Accounts::load()))
+            })
+            .await
+            .unwrap_or_else(|e| Err(format!("{:?}", e)));
+            Self::show_users(&scrolled, &toast_overlay, result);
+        });
+
+        Self { dialog }
+    }
+
+    fn show_users(
+        scrolled: &gtk4::ScrolledWindow,
+        toast_overlay: &adw::ToastOverlay,
+        result: Result<(Vec<String>, Vec<SambaShareConfig>, Accounts), String>,
+    ) {
+        let preferences_page = adw::PreferencesPage::new();
+        scrolled.set_child(Some(&preferences_page));
+
+        let matrix = match result {
+            Ok((users, shares, accounts)) if !users.is_empty() => Rc::new(AccessMatrix {
+                users,
+                shares: RefCell::new(shares),
+                accounts,
+                toast_overlay: toast_overlay.clone(),
+            }),
+            Ok(_) => {
+                let status = adw::StatusPage::new();
+                status.set_title(&gettext("No Samba Users"));
+                status.set_description(Some(&gettext(
+                    "People get a Samba password with the private share wizard",
+                )));
+                status.set_icon_name(Some("system-users-symbolic"));
+                scrolled.set_child(Some(&status));
+                return;
+            }
+            Err(e) => {
+                eprintln!("Failed to list Samba users: {}", e);
+                let status = adw::StatusPage::new();
+                status.set_title(&gettext("Cannot List Samba Users"));
+                status.set_description(Some(&e));
+                status.set_icon_name(Some("dialog-error-symbolic"));
+                scrolled.set_child(Some(&status));
+                return;
+            }
+        };
+
+        let group = adw::PreferencesGroup::new();
+        group.set_description(Some(&gettext(
+            "The Samba password is the one people type when connecting to a share, separate from their login password",
+        )));
+        for user in matrix.users.clone() {
+            let row = adw::ExpanderRow::new();
+            row.set_title(&user);
+            row.set_subtitle(&gettext("Shares this person can open"));
+            row.set_use_markup(false);
+            row.add_prefix(&gtk4::Image::from_icon_name("avatar-default-symbolic"));
+            for share in matrix.shares.borrow().iter() {
+                row.add_row(&AccessMatrix::share_row(&matrix, share, &user));
+            }
+
+            let change_button = gtk4::Button::with_label(&gettext("Change Password"));
+            change_button.set_valign(gtk4::Align::Center);
+            lock_when_read_only(&change_button);
+            row.add_suffix(&change_button);
+
+            let toast_overlay = toast_overlay.clone();
+            change_button.connect_clicked(move |button| {
+                if !allow_write(button) {
+                    return;
+                }
+                ask_new_password(button, &user, &toast_overlay);
+            });
+            group.add(&row);
+        }
+        preferences_page.add(&group);
+    }
+
+    pub fn present(&self, parent: Option<&impl IsA<gtk4::Widget>>) {
+        self.dialog.present(parent);
+    }
+}
+
+/// Levels offered for each share, in the order of the drop-downs
+const LEVELS: [AccessLevel; 3] = [AccessLevel::None, AccessLevel::Read, AccessLevel::Write];
+
+/// Which share each Samba user can open, kept up to date as levels are changed
+struct AccessMatrix {
+    users: Vec<String>,
+    shares: RefCell<Vec<SambaShareConfig>>,
+    accounts: Accounts,
+    toast_overlay: adw::ToastOverlay,
+}
+
+impl AccessMatrix {
+    /// Row of a share with the access level of `user`
+    fn share_row(matrix: &Rc<Self>, share: &SambaShareConfig, user: &str) -> adw::ActionRow {
+        let row = adw::ActionRow::new();
+        row.set_title(&share.name);
+        row.set_subtitle(&share.path);
+        row.set_use_markup(false);
+
+        let choices = [gettext("No Access"), gettext("Read Only"), gettext("Read & Write")];
+        let drop_down = gtk4::DropDown::from_strings(&choices.iter().map(String::as_str).collect::<Vec<_>>());
+        drop_down.set_valign(gtk4::Align::Center);
+        drop_down.set_selected(level_position(user_level(share, &matrix.accounts, user)));
+        if let Some(reason) = &share.locked_reason {
+            drop_down.set_sensitive(false);
+            drop_down.set_tooltip_text(Some(reason));
+        }
+        row.add_suffix(&drop_down);
+
+        // Set while the drop-down is put back after a refused change
+        let reverting = Rc::new(Cell::new(false));
+        let matrix = matrix.clone();
+        let share_name = share.name.clone();
+        let user = user.to_string();
+        drop_down.connect_selected_notify(move |drop_down| {
+            if reverting.get() {
+                return;
+            }
+            let level = LEVELS[drop_down.selected() as usize];
+            let result = if allow_write(drop_down) {
+                matrix.set_level(&share_name, &user, level)
+            } else {
+                Err(None)
+            };
+
+            if let Err(e) = result {
+                if let Some(e) = e {
+                    eprintln!("Failed to change the access of {} to {}: {}", user, share_name, e);
+                    let error_msg = format!("{}: {}", gettext("Failed to change access"), e);
+                    matrix.toast_overlay.add_toast(adw::Toast::new(&error_msg));
+                }
+                let current = matrix.level(&share_name, &user);
+                reverting.set(true);
+                drop_down.set_selected(level_position(current));
+                reverting.set(false);
+            }
+        });
+        row
+    }
+
+    fn level(&self, share_name: &str, user: &str) -> AccessLevel {
+        self.shares
+            .borrow()
+            .iter()
+            .find(|share| share.name == share_name)
+            .map(|share| user_level(share, &self.accounts, user))
+            .unwrap_or(AccessLevel::None)
+    }
+
+    /// Rewrite the share's user lists; None as error when nothing was tried
+    fn set_level(&self, share_name: &str, user: &str, level: AccessLevel) -> Result<(), Option<String>> {
+        let mut shares = self.shares.borrow_mut();
+        let Some(share) = shares.iter_mut().find(|share| share.name == share_name) else {
+            return Err(None);
+        };
+        let updated = set_user_level(share, &self.accounts, user, level)?;
+        let change = updated.update(&share.name)?;
+        *share = updated;
+
+        let state = AppState::get();
+        state.add_pending_change(change);
+        state.reload_local();
+        Ok(())
+    }
+}
+
+fn level_position(level: AccessLevel) -> u32 {
+    LEVELS.iter().position(|choice| *choice == level).unwrap_or(0) as u32
+}
+
+/// Ask for the new password twice, then set it
+fn ask_new_password(parent: &gtk4::Button, user: &str, toast_overlay: &adw::ToastOverlay) {
+    let dialog = adw::AlertDialog::new(
+        Some(&gettext("Change Password")),
+        Some(&gettext("New Samba password for {user}").replace("{user}", user)),
+    );
+    dialog.add_responses(&[("cancel", &gettext("Cancel")), ("change", &gettext("Change"))]);
+    dialog.set_response_appearance("change", adw::ResponseAppearance::Suggested);
+    dialog.set_response_enabled("change", false);
+    dialog.set_default_response(Some("change"));
+    dialog.set_close_response("cancel");
+
+    let list = gtk4::ListBox::new();
+    list.add_css_class("boxed-list");
+    list.set_selection_mode(gtk4::SelectionMode::None);
+    let password_entry = adw::PasswordEntryRow::new();
+    password_entry.set_title(&gettext("Password"));
+    list.append(&password_entry);
+    let confirm_entry = adw::PasswordEntryRow::new();
+    confirm_entry.set_title(&gettext("Confirm Password"));
+    list.append(&confirm_entry);
+    dialog.set_extra_child(Some(&list));
+
+    // Only offer the change once both entries match
+    for entry in [&password_entry, &confirm_entry] {
+        let dialog = dialog.clone();
+        let password_entry = password_entry.clone();
+        let confirm_entry = confirm_entry.clone();
+        entry.connect_changed(move |_| {
+            let password = password_entry.text();
+            dialog.set_response_enabled("change", !password.is_empty() && password == confirm_entry.text());
+        });
+    }
+
+    let user = user.to_string();
+    let toast_overlay = toast_overlay.clone();
+    dialog.connect_response(Some("change"), move |_, _| {
+        let user = user.clone();
+        let password = password_entry.text().to_string();
+        let toast_overlay = toast_overlay.clone();
+        glib::spawn_future_local(async move {
+            let user_clone = user.clone();
+            let result = gio::spawn_blocking(move || change_password(&user_clone, &password))
+                .await
+                .unwrap_or_else(|e| Err(format!("{:?}", e)));
+
+            let message = match result {
+                Ok(()) => gettext("Password of {user} changed").replace("{user}", &user),
+                Err(e) => {
+                    eprintln!("Failed to change the Samba password of {}: {}", user, e);
+                    format!("{}: {}", gettext("Failed to change password"), e)
+                }
+            };
+            toast_overlay.add_toast(adw::Toast::new(&message));
+        });
+    });
+    dialog.present(Some(parent));
+}
@@ -0,0 +1,162 @@
+use crate::samba::share_browser::{join_remote_path, list_folders};
+use gettextrs::gettext;
+use gtk4::prelude::*;
+use gtk4::{gio, glib};
+use libadwaita as adw;
+use libadwaita::prelude::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Browses the folders of a remote share to pick the one to mount
+pub struct ShareBrowserDialog {
+    dialog: adw::Dialog,
+}
+
+/// Widgets and location of the browser, shared by its callbacks
+struct Browser {
+    share: String,
+    credentials: String,
+    subpath: RefCell<String>,
+    scrolled: gtk4::ScrolledWindow,
+    up_button: gtk4::Button,
+    select_button: gtk4::Button,
+}
+
+impl ShareBrowserDialog {
+    /// Start browsing `subpath` of `share`; `on_selected` receives the device of the chosen folder
+    pub fn new(share: &str, subpath: &str, credentials: &str, on_selected: impl Fn(String) + 'static) -> Self {
+        let dialog = adw::Dialog::new();
+        dialog.set_title(&gettext("Browse Folders"));
+        dialog.set_content_width(450);
+        dialog.set_content_height(500);
+
+        // Create toolbar header
+        let toolbar_view = adw::ToolbarView::new();
+        let header_bar = adw::HeaderBar::new();
+        header_bar.set_show_end_title_buttons(false);
+
+        let up_button = gtk4::Button::from_icon_name("go-up-symbolic");
+        up_button.set_tooltip_text(Some(&gettext("Parent Folder")));
+        header_bar.pack_start(&up_button);
+
+        let select_button = gtk4::Button::with_label(&gettext("Select"));
+        select_button.add_css_class("suggested-action");
+        header_bar.pack_end(&select_button);
+        toolbar_view.add_top_bar(&header_bar);
+
+        let scrolled = gtk4::ScrolledWindow::builder()
+            .hexpand(true)
+            .vexpand(true)
+            .build();
+        toolbar_view.set_content(Some(&scrolled));
+        dialog.set_child(Some(&toolbar_view));
+
+        let browser = Rc::new(Browser {
+            share: share.to_string(),
+            credentials: credentials.to_string(),
+            subpath: RefCell::new(subpath.to_string()),
+            scrolled,
+            up_button: up_button.clone(),
+            select_button: select_button.clone(),
+        });
+
+        let browser_clone = browser.clone();
+        up_button.connect_clicked(move |_| {
+            let parent = {
+                let subpath = browser_clone.subpath.borrow();
+                subpath.rsplit_once('/').map(|(parent, _)| parent.to_string()).unwrap_or_default()
+            };
+            Browser::open(&browser_clone, parent);
+        });
+
+        let browser_clone = browser.clone();
+        let dialog_clone = dialog.clone();
+        select_button.connect_clicked(move |_| {
+            on_selected(join_remote_path(&browser_clone.share, &browser_clone.subpath.borrow()));
+            dialog_clone.close();
+        });
+
+        Browser::open(&browser, subpath.to_string());
+
+        Self { dialog }
+    }
+
+    pub fn present(&self, parent: Option<&impl IsA<gtk4::Widget>>) {
+        self.dialog.present(parent);
+    }
+}
+
+impl Browser {
+    /// List the folders of `subpath` in the background, then show them
+    fn open(browser: &Rc<Self>, subpath: String) {
+        browser.up_button.set_sensitive(false);
+        browser.select_button.set_sensitive(false);
+        let spinner = gtk4::Spinner::new();
+        spinner.set_size_request(32, 32);
+        spinner.set_valign(gtk4::Align::Center);
+        spinner.start();
+        browser.scrolled.set_child(Some(&spinner));
+
+        let browser = browser.clone();
+        glib::spawn_future_local(async move {
+            let share = browser.share.clone();
+            let credentials = browser.credentials.clone();
+            let subpath_clone = subpath.clone();
+            let result = gio::spawn_blocking(move || list_folders(&share, &subpath_clone, &credentials))
+                .await
+                .unwrap_or_else(|e| Err(format!("{:?}", e)));
+
+            match result {
+                Ok(folders) => {
+                    browser.subpath.replace(subpath);
+                    Self::show_folders(&browser, &folders);
+                }
+                Err(e) => {
+                    eprintln!("Failed to list {}/{}: {}", browser.share, subpath, e);
+                    let status = adw::StatusPage::new();
+                    status.set_title(&gettext("Cannot Browse the Share"));
+                    status.set_description(Some(&e));
+                    status.set_icon_name(Some("network-error-symbolic"));
+                    browser.scrolled.set_child(Some(&status));
+                }
+            }
+            browser.up_button.set_sensitive(!browser.subpath.borrow().is_empty());
+        });
+    }
+
+    /// Replace the list with the folders of the current location
+    fn show_folders(browser: &Rc<Self>, folders: &[String]) {
+        let subpath = browser.subpath.borrow().clone();
+
+        let preferences_page = adw::PreferencesPage::new();
+        let group = adw::PreferencesGroup::new();
+        group.set_title(&join_remote_path(&browser.share, &subpath));
+        if folders.is_empty() {
+            group.set_description(Some(&gettext("No subfolders")));
+        }
+
+        for folder in folders {
+            let row = adw::ActionRow::new();
+            row.set_title(folder);
+            row.set_use_markup(false);
+            row.set_activatable(true);
+            row.add_prefix(&gtk4::Image::from_icon_name("folder-symbolic"));
+            row.add_suffix(&gtk4::Image::from_icon_name("go-next-symbolic"));
+
+            let browser = browser.clone();
+            let child = if subpath.is_empty() {
+                folder.clone()
+            } else {
+                format!("{}/{}", subpath, folder)
+            };
+            row.connect_activated(move |_| {
+                Self::open(&browser, child.clone());
+            });
+            group.add(&row);
+        }
+
+        preferences_page.add(&group);
+        browser.scrolled.set_child(Some(&preferences_page));
+        browser.select_button.set_sensitive(true);
+    }
+}
@@ -0,0 +1,181 @@
+use crate::samba::share_test::{connect, dialect_name, negotiate, read_write_round_trip, resolve, timed};
+use crate::samba::MountedShare;
+use crate::utils::format::{format_milliseconds, format_size};
+use gettextrs::gettext;
+use gtk4::prelude::*;
+use gtk4::{gio, glib};
+use libadwaita as adw;
+use libadwaita::prelude::*;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Timings of each step reaching a remote share, to tell a slow network
+/// from a slow server or a slow disk
+pub struct ShareTestDialog {
+    dialog: adw::Dialog,
+}
+
+/// Rows showing the steps of the test, in the order they run
+#[derive(Clone)]
+struct StepRows {
+    resolve: adw::ActionRow,
+    connect: adw::ActionRow,
+    negotiate: adw::ActionRow,
+    round_trip: adw::ActionRow,
+}
+
+impl ShareTestDialog {
+    pub fn new(share: &MountedShare) -> Self {
+        let dialog = adw::Dialog::new();
+        dialog.set_title(&gettext("Test Share"));
+        dialog.set_content_width(500);
+        dialog.set_content_height(450);
+
+        // Create toolbar header
+        let toolbar_view = adw::ToolbarView::new();
+        let header_bar = adw::HeaderBar::new();
+        let run_button = gtk4::Button::from_icon_name("view-refresh-symbolic");
+        run_button.set_tooltip_text(Some(&gettext("Run Again")));
+        header_bar.pack_start(&run_button);
+        toolbar_view.add_top_bar(&header_bar);
+
+        let preferences_page = adw::PreferencesPage::new();
+        let group = adw::PreferencesGroup::new();
+        group.set_title(&share.source);
+        group.set_description(Some(&share.target));
+
+        let rows = StepRows {
+            resolve: step_row(&gettext("Name Resolution"), "network-server-symbolic"),
+            connect: step_row(&gettext("TCP Connection"), "network-wired-symbolic"),
+            negotiate: step_row(&gettext("SMB Negotiation"), "network-transmit-receive-symbolic"),
+            round_trip: step_row(&gettext("Read/Write Round-Trip"), "drive-harddisk-symbolic"),
+        };
+        for row in [&rows.resolve, &rows.connect, &rows.negotiate, &rows.round_trip] {
+            group.add(row);
+        }
+
+        preferences_page.add(&group);
+        toolbar_view.set_content(Some(&preferences_page));
+        dialog.set_child(Some(&toolbar_view));
+
+        let server = share.server().to_string();
+        // The round-trip writes through the mount, so it needs one
+        let mount_point = share.is_mounted.then(|| PathBuf::from(&share.target));
+
+        let rows_clone = rows.clone();
+        let server_clone = server.clone();
+        let mount_point_clone = mount_point.clone();
+        run_button.connect_clicked(move |button| {
+            Self::run(button, &rows_clone, &server_clone, mount_point_clone.clone());
+        });
+        Self::run(&run_button, &rows, &server, mount_point);
+
+        Self { dialog }
+    }
+
+    /// Run every step in turn. Network steps after a failure are skipped,
+    /// the round-trip only depends on the share being mounted.
+    fn run(button: &gtk4::Button, rows: &StepRows, server: &str, mount_point: Option<PathBuf>) {
+        button.set_sensitive(false);
+        for row in [&rows.resolve, &rows.connect, &rows.negotiate, &rows.round_trip] {
+            row.set_subtitle(&gettext("Waiting…"));
+            row.remove_css_class("error");
+        }
+
+        let button = button.clone();
+        let rows = rows.clone();
+        let server = server.to_string();
+        glib::spawn_future_local(async move {
+            rows.resolve.set_subtitle(&gettext("Running…"));
+            let (duration, addresses) = gio::spawn_blocking(move || timed(|| resolve(&server)))
+                .await
+                .unwrap_or_else(|e| (Duration::ZERO, Err(format!("{:?}", e))));
+            let addresses = show_step(&rows.resolve, duration, addresses, |addresses| {
+                addresses
+                    .iter()
+                    .map(|address| address.ip().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            });
+
+            let stream = match addresses {
+                Some(addresses) => {
+                    rows.connect.set_subtitle(&gettext("Running…"));
+                    let (duration, stream) = gio::spawn_blocking(move || timed(|| connect(&addresses)))
+                        .await
+                        .unwrap_or_else(|e| (Duration::ZERO, Err(format!("{:?}", e))));
+                    show_step(&rows.connect, duration, stream, |stream| {
+                        stream
+                            .peer_addr()
+                            .map(|address| address.to_string())
+                            .unwrap_or_default()
+                    })
+                }
+                None => {
+                    rows.connect.set_subtitle(&gettext("Skipped"));
+                    None
+                }
+            };
+
+            match stream {
+                Some(mut stream) => {
+                    rows.negotiate.set_subtitle(&gettext("Running…"));
+                    let (duration, dialect) = gio::spawn_blocking(move || timed(|| negotiate(&mut stream)))
+                        .await
+                        .unwrap_or_else(|e| (Duration::ZERO, Err(format!("{:?}", e))));
+                    show_step(&rows.negotiate, duration, dialect, |dialect| dialect_name(*dialect));
+                }
+                None => rows.negotiate.set_subtitle(&gettext("Skipped")),
+            }
+
+            match mount_point {
+                Some(mount_point) => {
+                    rows.round_trip.set_subtitle(&gettext("Running…"));
+                    let (duration, size) =
+                        gio::spawn_blocking(move || timed(|| read_write_round_trip(&mount_point)))
+                            .await
+                            .unwrap_or_else(|e| (Duration::ZERO, Err(format!("{:?}", e))));
+                    show_step(&rows.round_trip, duration, size, |size| format_size(*size as u64));
+                }
+                None => rows.round_trip.set_subtitle(&gettext("Skipped, the share isn't mounted")),
+            }
+
+            button.set_sensitive(true);
+        });
+    }
+
+    pub fn present(&self, parent: Option<&impl IsA<gtk4::Widget>>) {
+        self.dialog.present(parent);
+    }
+}
+
+/// Row for one step, its subtitle showing the progress then the result
+fn step_row(title: &str, icon_name: &str) -> adw::ActionRow {
+    let row = adw::ActionRow::new();
+    row.set_title(title);
+    row.set_subtitle_selectable(true);
+    row.set_use_markup(false);
+    row.add_prefix(&gtk4::Image::from_icon_name(icon_name));
+    row
+}
+
+/// Show how long a step took and what it found, or why it failed.
+/// Returns the value for the next step on success.
+fn show_step<T>(
+    row: &adw::ActionRow,
+    duration: Duration,
+    result: Result<T, String>,
+    describe: impl Fn(&T) -> String,
+) -> Option<T> {
+    match result {
+        Ok(value) => {
+            row.set_subtitle(&format!("{} • {}", format_milliseconds(duration), describe(&value)));
+            Some(value)
+        }
+        Err(e) => {
+            row.set_subtitle(&e);
+            row.add_css_class("error");
+            None
+        }
+    }
+}
@@ -0,0 +1,234 @@
+use crate::config::AppConfig;
+use crate::models::AppState;
+use crate::samba::users::{is_valid_user_name, provision_team_share, GroupCreation};
+use crate::samba::{get_system_groups, get_system_users, SambaShareConfig};
+use crate::ui::dialogs::private_share_wizard::wizard_page;
+use crate::ui::widgets::selected_string;
+use gettextrs::gettext;
+use gtk4::prelude::*;
+use libadwaita as adw;
+use libadwaita::prelude::*;
+
+/// Base folder proposed for new team shares
+const DEFAULT_SHARE_ROOT: &str = "/srv/samba";
+
+/// Guided flow creating a share for a group of people:
+/// group and its members, group-owned folder and share with `valid users = @group`
+pub struct TeamShareWizard {
+    dialog: adw::Dialog,
+}
+
+impl TeamShareWizard {
+    pub fn new() -> Self {
+        let dialog = adw::Dialog::new();
+        dialog.set_title(&gettext("Shared Folder for a Team"));
+        dialog.set_content_width(500);
+        dialog.set_content_height(600);
+
+        let navigation_view = adw::NavigationView::new();
+
+        // Wrap navigation in toast overlay for error messages
+        let toast_overlay = adw::ToastOverlay::new();
+        toast_overlay.set_child(Some(&navigation_view));
+        dialog.set_child(Some(&toast_overlay));
+
+        // ============ Step 1: Team ============
+        let team_group = adw::PreferencesGroup::new();
+        team_group.set_title(&gettext("Who is in this team?"));
+        team_group.set_description(Some(&gettext(
+            "Members of the group can read and write every file in the folder",
+        )));
+
+        let mut group_labels = vec![gettext("Create a new group")];
+        group_labels.extend(get_system_groups());
+        let group_combo = adw::ComboRow::new();
+        group_combo.set_title(&gettext("Group"));
+        group_combo.set_model(Some(&gtk4::StringList::new(
+            &group_labels.iter().map(|s| s.as_str()).collect::<Vec<_>>(),
+        )));
+        group_combo.set_selected(0);
+        team_group.add(&group_combo);
+
+        let new_group_entry = adw::EntryRow::new();
+        new_group_entry.set_title(&gettext("New Group Name"));
+        team_group.add(&new_group_entry);
+
+        // Order matches the items of the combo
+        let creation_modes = [GroupCreation::Declarative, GroupCreation::Imperative];
+        let creation_combo = adw::ComboRow::new();
+        creation_combo.set_title(&gettext("Create Group"));
+        creation_combo.set_model(Some(&gtk4::StringList::new(&[
+            gettext("In the NixOS configuration").as_str(),
+            gettext("On this system only").as_str(),
+        ])));
+        creation_combo.set_selected(0);
+        team_group.add(&creation_combo);
+
+        let new_group_entry_clone = new_group_entry.clone();
+        let creation_combo_clone = creation_combo.clone();
+        group_combo.connect_selected_notify(move |combo| {
+            new_group_entry_clone.set_visible(combo.selected() == 0);
+            creation_combo_clone.set_visible(combo.selected() == 0);
+        });
+
+        // One check per account, checked members are added to the group
+        let members_expander = adw::ExpanderRow::new();
+        members_expander.set_title(&gettext("Members"));
+        members_expander.set_subtitle(&gettext("Users added to the group"));
+        let mut member_checks = Vec::new();
+        for user in get_system_users() {
+            let check = gtk4::CheckButton::new();
+            let row = adw::ActionRow::new();
+            row.set_title(&user);
+            row.add_prefix(&check);
+            row.set_activatable_widget(Some(&check));
+            members_expander.add_row(&row);
+            member_checks.push((user, check));
+        }
+        team_group.add(&members_expander);
+
+        let team_next = gtk4::Button::with_label(&gettext("Next"));
+        team_next.add_css_class("suggested-action");
+        let team_page = wizard_page(&gettext("Team"), &team_group, &team_next);
+        navigation_view.add(&team_page);
+
+        // ============ Step 2: Folder and share ============
+        let folder_group = adw::PreferencesGroup::new();
+        folder_group.set_title(&gettext("Shared Folder"));
+        folder_group.set_description(Some(&gettext(
+            "The folder is created if needed and belongs to the group",
+        )));
+
+        let share_name_entry = adw::EntryRow::new();
+        share_name_entry.set_title(&gettext("Share Name"));
+        folder_group.add(&share_name_entry);
+
+        let folder_entry = adw::EntryRow::new();
+        folder_entry.set_title(&gettext("Path"));
+        folder_group.add(&folder_entry);
+
+        let create_button = gtk4::Button::with_label(&gettext("Create Share"));
+        create_button.add_css_class("suggested-action");
+        let folder_page = wizard_page(&gettext("Folder"), &folder_group, &create_button);
+
+        // Resolve the chosen group name and how to obtain it
+        let group_combo_clone = group_combo.clone();
+        let new_group_entry_clone = new_group_entry.clone();
+        let chosen_group = move || -> (String, GroupCreation) {
+            if group_combo_clone.selected() == 0 {
+                let creation = creation_modes
+                    .get(creation_combo.selected() as usize)
+                    .copied()
+                    .unwrap_or(GroupCreation::Declarative);
+                (new_group_entry_clone.text().trim().to_string(), creation)
+            } else {
+                (selected_string(&group_combo_clone), GroupCreation::Existing)
+            }
+        };
+
+        // Step 1 -> 2
+        let navigation_view_clone = navigation_view.clone();
+        let toast_overlay_clone = toast_overlay.clone();
+        let share_name_entry_clone = share_name_entry.clone();
+        let folder_entry_clone = folder_entry.clone();
+        let chosen_group_clone = chosen_group.clone();
+        team_next.connect_clicked(move |_| {
+            let (group, _) = chosen_group_clone();
+            if !is_valid_user_name(&group) {
+                let toast = adw::Toast::new(&gettext(
+                    "Group names use lowercase letters, digits, '-' and '_'",
+                ));
+                toast_overlay_clone.add_toast(toast);
+                return;
+            }
+
+            // Suggest a share name and folder for this group
+            if share_name_entry_clone.text().is_empty() {
+                share_name_entry_clone.set_text(&group);
+            }
+            if folder_entry_clone.text().is_empty() {
+                folder_entry_clone.set_text(&format!("{}/{}", DEFAULT_SHARE_ROOT, group));
+            }
+            navigation_view_clone.push(&folder_page);
+        });
+
+        // Create everything
+        let dialog_clone = dialog.clone();
+        let toast_overlay_clone = toast_overlay.clone();
+        create_button.connect_clicked(move |button| {
+            let (group, creation) = chosen_group();
+            let members: Vec<String> = member_checks
+                .iter()
+                .filter(|(_, check)| check.is_active())
+                .map(|(user, _)| user.clone())
+                .collect();
+            let share_name = share_name_entry.text().trim().to_string();
+            let folder = folder_entry.text().trim().to_string();
+
+            if share_name.is_empty() {
+                toast_overlay_clone.add_toast(adw::Toast::new(&gettext("Share name is required")));
+                return;
+            }
+            if folder.is_empty() {
+                toast_overlay_clone.add_toast(adw::Toast::new(&gettext("Path is required")));
+                return;
+            }
+
+            // Files created through the share stay writable by the whole group
+            let mut share = SambaShareConfig::new(
+                share_name,
+                folder.clone(),
+                true,
+                false,
+                false,
+                String::new(),
+                group.clone(),
+            );
+            share.valid_users = vec![format!("@{}", group)];
+            share.create_mask = "0660".to_string();
+            share.directory_mask = "2770".to_string();
+
+            button.set_sensitive(false);
+            let button = button.clone();
+            let dialog = dialog_clone.clone();
+            let toast_overlay = toast_overlay_clone.clone();
+            let template = AppConfig::new().server_template();
+            glib::spawn_future_local(async move {
+                let result = gio::spawn_blocking(move || {
+                    let declaration = provision_team_share(&group, creation, &members, &folder)?;
+                    let change = share.write(&template)?;
+                    Ok::<_, String>(declaration.into_iter().chain([change]).collect::<Vec<_>>())
+                })
+                .await;
+
+                button.set_sensitive(true);
+                match result {
+                    Ok(Ok(changes)) => {
+                        let state = AppState::get();
+                        for change in changes {
+                            state.add_pending_change(change);
+                        }
+                        state.reload_local();
+                        dialog.close();
+                    }
+                    Ok(Err(e)) => {
+                        eprintln!("Failed to create team share: {}", e);
+                        let error_msg =
+                            format!("{}: {}", gettext("Failed to create team share"), e);
+                        toast_overlay.add_toast(adw::Toast::new(&error_msg));
+                    }
+                    Err(e) => {
+                        let error_msg = format!("{}: {:?}", gettext("Error"), e);
+                        toast_overlay.add_toast(adw::Toast::new(&error_msg));
+                    }
+                }
+            });
+        });
+
+        Self { dialog }
+    }
+
+    pub fn present(&self, parent: Option<&impl IsA<gtk4::Widget>>) {
+        self.dialog.present(parent);
+    }
+}
@@ -6,14 +6,13 @@ use std::cell::RefCell;
 use std::rc::Rc;
 
 pub struct WelcomeDialog {
-    dialog: adw::MessageDialog,
+    dialog: adw::AlertDialog,
     dont_show_again: Rc<RefCell<bool>>,
 }
 
 impl WelcomeDialog {
     pub fn new() -> Self {
-        let dialog = adw::MessageDialog::new(
-            None::<&gtk4::Window>,
+        let dialog = adw::AlertDialog::new(
             Some(&gettext("Welcome to Samba Share Manager")),
             Some(&gettext("This application helps you manage your Samba shares on NixOS")),
         );
@@ -48,19 +47,14 @@ impl WelcomeDialog {
     }
 
     pub fn present(&self, parent: Option<&impl IsA<gtk4::Widget>>) {
-        if let Some(p) = parent {
-            if let Some(window) = p.dynamic_cast_ref::<gtk4::Window>() {
-                self.dialog.set_transient_for(Some(window));
-            }
-        }
-        self.dialog.present();
+        self.dialog.present(parent);
     }
 
     pub fn should_hide_next_time(&self) -> bool {
         *self.dont_show_again.borrow()
     }
 
-    pub fn dialog(&self) -> &adw::MessageDialog {
+    pub fn dialog(&self) -> &adw::AlertDialog {
         &self.dialog
     }
 }
\ No newline at end of file
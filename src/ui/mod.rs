@@ -1,4 +1,5 @@
 pub mod app;
+pub mod background;
 pub mod dialogs;
 pub mod widgets;
 pub mod window;
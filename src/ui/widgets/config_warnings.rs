@@ -0,0 +1,66 @@
+use crate::samba::warnings::ConfigWarning;
+use gettextrs::gettext;
+use gtk4::prelude::*;
+use libadwaita as adw;
+use libadwaita::prelude::*;
+
+/// Small "Insecure" label listing the warnings of an entry in its tooltip
+pub fn warning_badge(warnings: &[ConfigWarning]) -> gtk4::Label {
+    let titles: Vec<String> = warnings.iter().map(|warning| warning_title(*warning)).collect();
+
+    let badge = gtk4::Label::new(Some(&gettext("Insecure")));
+    badge.add_css_class("caption");
+    badge.add_css_class("error");
+    badge.set_valign(gtk4::Align::Center);
+    badge.set_tooltip_text(Some(&titles.join("\n")));
+    badge
+}
+
+/// Row explaining a warning and how to fix it
+pub fn warning_row(warning: ConfigWarning) -> adw::ActionRow {
+    let row = adw::ActionRow::new();
+    row.set_title(&warning_title(warning));
+    row.set_subtitle(&format!("{} {}", warning_explanation(warning), warning_fix(warning)));
+    row.add_prefix(&gtk4::Image::from_icon_name("dialog-warning-symbolic"));
+    row.add_css_class("warning");
+    row
+}
+
+fn warning_title(warning: ConfigWarning) -> String {
+    match warning {
+        ConfigWarning::GuestWritable => gettext("Guests can modify files"),
+        ConfigWarning::WideLinks => gettext("Wide links expose files outside the share"),
+        ConfigWarning::HostsAllowAll => gettext("Every host is allowed"),
+        ConfigWarning::Smb1Enabled => gettext("SMB1 is enabled"),
+    }
+}
+
+fn warning_explanation(warning: ConfigWarning) -> String {
+    match warning {
+        ConfigWarning::GuestWritable => gettext(
+            "Anyone on the network can add, change or delete files without a password.",
+        ),
+        ConfigWarning::WideLinks => gettext(
+            "Any user able to create a symlink can reach the whole filesystem with the share's permissions.",
+        ),
+        ConfigWarning::HostsAllowAll => gettext(
+            "Allowed Hosts matches every address, so the server accepts connections from any network.",
+        ),
+        ConfigWarning::Smb1Enabled => gettext(
+            "SMB1 is obsolete and insecure. Only enable it for legacy devices that cannot use SMB2 or newer.",
+        ),
+    }
+}
+
+fn warning_fix(warning: ConfigWarning) -> String {
+    match warning {
+        ConfigWarning::GuestWritable => {
+            gettext("Turn on Read Only, or turn off Guest OK and use a write list.")
+        }
+        ConfigWarning::WideLinks => gettext("Turn off Wide Links in the Symbolic Links options."),
+        ConfigWarning::HostsAllowAll => {
+            gettext("Replace it with your local networks in Server Settings.")
+        }
+        ConfigWarning::Smb1Enabled => gettext("Set the minimum protocol to SMB2_02 or newer."),
+    }
+}
@@ -0,0 +1,51 @@
+use crate::samba::share_test::{dialect_name, test_connection};
+use gettextrs::gettext;
+use gtk4::prelude::*;
+use gtk4::{gio, glib};
+use libadwaita as adw;
+
+/// "Test Connection" button of a remote share dialog, checking that the server answers
+/// and the credentials file opens the share, without writing anything
+pub fn connection_test_button(
+    remote_path_entry: &adw::EntryRow,
+    credentials_entry: &adw::EntryRow,
+    toast_overlay: &adw::ToastOverlay,
+) -> gtk4::Button {
+    let button = gtk4::Button::with_label(&gettext("Test Connection"));
+    button.set_halign(gtk4::Align::Center);
+    button.add_css_class("pill");
+    button.set_tooltip_text(Some(&gettext(
+        "Check that the server answers and the credentials open the share",
+    )));
+
+    let remote_path_entry = remote_path_entry.clone();
+    let credentials_entry = credentials_entry.clone();
+    let toast_overlay = toast_overlay.clone();
+    button.connect_clicked(move |button| {
+        button.set_sensitive(false);
+        let remote_path = remote_path_entry.text().to_string();
+        let credentials = credentials_entry.text().to_string();
+        let button = button.clone();
+        let toast_overlay = toast_overlay.clone();
+        glib::spawn_future_local(async move {
+            let result = gio::spawn_blocking(move || test_connection(&remote_path, &credentials))
+                .await
+                .unwrap_or_else(|e| Err(format!("{:?}", e)));
+
+            button.set_sensitive(true);
+            match result {
+                Ok(dialect) => {
+                    let message = gettext("Connected to the share with {dialect}")
+                        .replace("{dialect}", &dialect_name(dialect));
+                    toast_overlay.add_toast(adw::Toast::new(&message));
+                }
+                Err(e) => {
+                    eprintln!("Connection test failed: {}", e);
+                    let error_msg = format!("{}: {}", gettext("Connection failed"), e);
+                    toast_overlay.add_toast(adw::Toast::new(&error_msg));
+                }
+            }
+        });
+    });
+    button
+}
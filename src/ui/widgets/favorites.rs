@@ -0,0 +1,242 @@
+use crate::config::{AppConfig, Favorite};
+use crate::models::AppState;
+use crate::samba::{remount_share, unmount_share, MountedShare, SambaShareConfig};
+use crate::ui::widgets::file_picker::parent_window;
+use crate::ui::widgets::share_icon;
+use gettextrs::gettext;
+use gtk4::prelude::*;
+use gtk4::{gio, glib};
+use libadwaita as adw;
+use libadwaita::prelude::*;
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+/// Star toggling whether a share appears among the favorites of the main page
+pub fn favorite_button(favorite: Favorite) -> gtk4::ToggleButton {
+    let button = gtk4::ToggleButton::new();
+    button.set_valign(gtk4::Align::Center);
+    button.add_css_class("flat");
+    button.set_active(AppConfig::new().favorites().contains(&favorite));
+    show_starred(&button);
+
+    button.connect_toggled(move |button| {
+        show_starred(button);
+        AppState::get().set_favorite(&favorite, button.is_active());
+    });
+    button
+}
+
+fn show_starred(button: &gtk4::ToggleButton) {
+    if button.is_active() {
+        button.set_icon_name("starred-symbolic");
+        button.set_tooltip_text(Some(&gettext("Remove from Favorites")));
+    } else {
+        button.set_icon_name("non-starred-symbolic");
+        button.set_tooltip_text(Some(&gettext("Add to Favorites")));
+    }
+}
+
+/// Starred shares on the main page, with a switch to mount remote ones and a button to open them.
+/// Hidden while there are none.
+#[derive(Clone)]
+pub struct FavoritesGroup {
+    group: adw::PreferencesGroup,
+    rows: Rc<RefCell<Vec<adw::ActionRow>>>,
+    toast_overlay: adw::ToastOverlay,
+}
+
+impl FavoritesGroup {
+    pub fn new(toast_overlay: &adw::ToastOverlay) -> Self {
+        let group = adw::PreferencesGroup::new();
+        group.set_title(&gettext("Favorites"));
+        group.set_visible(false);
+
+        let this = Self {
+            group,
+            rows: Rc::new(RefCell::new(Vec::new())),
+            toast_overlay: toast_overlay.clone(),
+        };
+
+        // Rows follow the shared state and the stars and icons set anywhere
+        let state = AppState::get();
+        let this_clone = this.clone();
+        state.connect_local_shares_changed(move |_| this_clone.update());
+        let this_clone = this.clone();
+        state.connect_remote_shares_changed(move |_| this_clone.update());
+        let this_clone = this.clone();
+        state.connect_favorites_changed(move |_| this_clone.update());
+        let this_clone = this.clone();
+        state.connect_appearances_changed(move |_| this_clone.update());
+
+        this.update();
+        this
+    }
+
+    pub fn group(&self) -> &adw::PreferencesGroup {
+        &self.group
+    }
+
+    /// Rebuild the rows from the favorites and the last loaded shares
+    fn update(&self) {
+        for row in self.rows.borrow_mut().drain(..) {
+            self.group.remove(&row);
+        }
+
+        let state = AppState::get();
+        let local = state.local_shares().and_then(Result::ok);
+        let remote = state.remote_shares().and_then(Result::ok);
+
+        let favorites = AppConfig::new().favorites();
+        for favorite in &favorites {
+            let row = match favorite {
+                Favorite::Local(name) => {
+                    let share = local.as_ref().map(|shares| shares.iter().find(|share| &share.name == name));
+                    self.local_row(name, share)
+                }
+                Favorite::Remote(target) => {
+                    let share = remote.as_ref().map(|shares| shares.iter().find(|share| &share.target == target));
+                    self.remote_row(target, share)
+                }
+            };
+            row.add_suffix(&favorite_button(favorite.clone()));
+            self.group.add(&row);
+            self.rows.borrow_mut().push(row);
+        }
+
+        self.group.set_visible(!favorites.is_empty());
+    }
+
+    /// Row for a local share, `share` being `None` until the shares are loaded
+    fn local_row(&self, name: &str, share: Option<Option<&SambaShareConfig>>) -> adw::ActionRow {
+        let row = adw::ActionRow::new();
+        row.set_title(name);
+        row.set_use_markup(false);
+        row.add_prefix(&share_icon(&Favorite::Local(name.to_string()), "folder-symbolic"));
+
+        match share {
+            Some(Some(share)) => {
+                row.set_subtitle(&share.path);
+                row.add_suffix(&self.open_button(&share.path));
+            }
+            Some(None) => row.set_subtitle(&gettext("No longer in the configuration")),
+            None => {}
+        }
+        row
+    }
+
+    /// Row for a remote share, `share` being `None` until the mounts are listed
+    fn remote_row(&self, target: &str, share: Option<Option<&MountedShare>>) -> adw::ActionRow {
+        let row = adw::ActionRow::new();
+        row.set_title(target);
+        row.set_use_markup(false);
+        row.add_prefix(&share_icon(&Favorite::Remote(target.to_string()), "folder-remote-symbolic"));
+
+        match share {
+            Some(Some(share)) => {
+                row.set_subtitle(&share.source);
+                if share.is_mounted {
+                    row.add_suffix(&self.open_button(&share.target));
+                }
+                // Only configured shares can be mounted without asking for credentials
+                if share.is_mounted || share.config.is_some() {
+                    let switch = self.mount_switch(share);
+                    row.add_suffix(&switch);
+                    row.set_activatable_widget(Some(&switch));
+                }
+            }
+            Some(None) => row.set_subtitle(&gettext("No longer in the configuration")),
+            None => {}
+        }
+        row
+    }
+
+    /// Button opening `path` in the file manager
+    fn open_button(&self, path: &str) -> gtk4::Button {
+        let button = gtk4::Button::from_icon_name("folder-open-symbolic");
+        button.set_valign(gtk4::Align::Center);
+        button.set_tooltip_text(Some(&gettext("Open Folder")));
+        button.add_css_class("flat");
+
+        let folder = gio::File::for_path(path);
+        let toast_overlay = self.toast_overlay.clone();
+        button.connect_clicked(move |button| {
+            let toast_overlay = toast_overlay.clone();
+            gtk4::FileLauncher::new(Some(&folder)).launch(
+                parent_window(button).as_ref(),
+                None::<&gio::Cancellable>,
+                move |result| {
+                    if let Err(e) = result {
+                        eprintln!("Failed to open folder: {}", e);
+                        let error_msg = format!("{}: {}", gettext("Failed to open folder"), e);
+                        toast_overlay.add_toast(adw::Toast::new(&error_msg));
+                    }
+                },
+            );
+        });
+        button
+    }
+
+    /// Switch mounting or unmounting the share right away
+    fn mount_switch(&self, share: &MountedShare) -> gtk4::Switch {
+        let switch = gtk4::Switch::new();
+        switch.set_valign(gtk4::Align::Center);
+        switch.set_active(share.is_mounted);
+        switch.set_tooltip_text(Some(&gettext("Mounted")));
+
+        let target = PathBuf::from(&share.target);
+        let toast_overlay = self.toast_overlay.clone();
+        switch.connect_state_set(move |switch, mount| {
+            // Switching back after a failure, nothing to run
+            if mount == switch.state() {
+                return glib::Propagation::Proceed;
+            }
+            switch.set_sensitive(false);
+
+            let target = target.clone();
+            let toast_overlay = toast_overlay.clone();
+            let switch = switch.clone();
+            glib::spawn_future_local(async move {
+                let result = gio::spawn_blocking(move || {
+                    if mount {
+                        remount_share(&target)
+                    } else {
+                        unmount_share(&target)
+                    }
+                })
+                .await;
+
+                switch.set_sensitive(true);
+                let message = match result {
+                    Ok(Ok(())) => {
+                        switch.set_state(mount);
+                        if mount {
+                            gettext("Share mounted")
+                        } else {
+                            gettext("Share unmounted successfully")
+                        }
+                    }
+                    Ok(Err(e)) => {
+                        switch.set_active(switch.state());
+                        let failure = if mount {
+                            gettext("Mount failed")
+                        } else {
+                            gettext("Unmount failed")
+                        };
+                        format!("{}: {}", failure, e)
+                    }
+                    Err(e) => {
+                        switch.set_active(switch.state());
+                        format!("{}: {:?}", gettext("Error"), e)
+                    }
+                };
+                toast_overlay.add_toast(adw::Toast::new(&message));
+                AppState::get().reload_remote();
+            });
+
+            // The state follows once the mount command finished
+            glib::Propagation::Stop
+        });
+        switch
+    }
+}
@@ -0,0 +1,82 @@
+use gtk4::prelude::*;
+use gtk4::{gio, glib};
+use libadwaita as adw;
+use std::path::{Path, PathBuf};
+
+/// Extended attribute set by the document portal on exported files
+const HOST_PATH_ATTRIBUTE: &str = "xattr::document-portal.host-path";
+
+/// Let the user pick a folder through the file chooser portal and write its path in `entry`
+pub fn browse_folder(parent: &impl IsA<gtk4::Widget>, entry: &adw::EntryRow, title: &str) {
+    let dialog = file_dialog(entry, title);
+
+    let entry = entry.clone();
+    dialog.select_folder(parent_window(parent).as_ref(), None::<&gio::Cancellable>, move |result| {
+        set_picked_path(&entry, result);
+    });
+}
+
+/// Let the user pick a file through the file chooser portal and write its path in `entry`
+pub fn browse_file(parent: &impl IsA<gtk4::Widget>, entry: &adw::EntryRow, title: &str) {
+    let dialog = file_dialog(entry, title);
+
+    let entry = entry.clone();
+    dialog.open(parent_window(parent).as_ref(), None::<&gio::Cancellable>, move |result| {
+        set_picked_path(&entry, result);
+    });
+}
+
+/// Window holding `parent`, which is what the portal attaches the chooser to
+pub(crate) fn parent_window(parent: &impl IsA<gtk4::Widget>) -> Option<gtk4::Window> {
+    parent.root().and_downcast::<gtk4::Window>()
+}
+
+/// Modal dialog starting from the path already typed in `entry`
+fn file_dialog(entry: &adw::EntryRow, title: &str) -> gtk4::FileDialog {
+    let dialog = gtk4::FileDialog::new();
+    dialog.set_title(title);
+    dialog.set_modal(true);
+
+    let current = PathBuf::from(entry.text().as_str());
+    if current.is_dir() {
+        dialog.set_initial_folder(Some(&gio::File::for_path(&current)));
+    } else if current.is_file() {
+        dialog.set_initial_file(Some(&gio::File::for_path(&current)));
+    }
+    dialog
+}
+
+fn set_picked_path(entry: &adw::EntryRow, result: Result<gio::File, glib::Error>) {
+    match result {
+        Ok(file) => {
+            if let Some(path) = host_file_path(&file) {
+                entry.set_text(&path.to_string_lossy());
+            }
+        }
+        Err(e) if e.matches(gtk4::DialogError::Dismissed) => {}
+        Err(e) => eprintln!("File chooser failed: {}", e),
+    }
+}
+
+/// Real path of a picked file. Inside a sandbox the portal may hand out a
+/// /run/user/<uid>/doc/... copy, which Samba and mount can't use.
+fn host_file_path(file: &gio::File) -> Option<PathBuf> {
+    let path = file.path()?;
+    if !is_document_portal_path(&path) {
+        return Some(path);
+    }
+
+    file.query_info(HOST_PATH_ATTRIBUTE, gio::FileQueryInfoFlags::NONE, None::<&gio::Cancellable>)
+        .ok()
+        .and_then(|info| info.attribute_as_string(HOST_PATH_ATTRIBUTE))
+        .map(|host_path| PathBuf::from(host_path.as_str()))
+        .or(Some(path))
+}
+
+fn is_document_portal_path(path: &Path) -> bool {
+    // /run/user/<uid>/doc/...
+    path.strip_prefix("/run/user")
+        .ok()
+        .and_then(|rest| rest.components().nth(1))
+        .is_some_and(|component| component.as_os_str() == "doc")
+}
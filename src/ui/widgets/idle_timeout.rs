@@ -0,0 +1,38 @@
+use crate::samba::idle_timeout::CHOICES;
+use crate::utils::format::format_timeout;
+use gettextrs::gettext;
+use libadwaita as adw;
+use libadwaita::prelude::*;
+
+/// Timeouts offered for a share, its current one included when it isn't a usual choice
+fn choices(timeout: Option<u32>) -> Vec<Option<u32>> {
+    let mut choices = CHOICES.to_vec();
+    if !choices.contains(&timeout) {
+        // Keep "Never" last
+        choices.insert(choices.len() - 1, timeout);
+    }
+    choices
+}
+
+/// Combo row choosing when an unused share is unmounted
+pub fn idle_timeout_combo(timeout: Option<u32>) -> adw::ComboRow {
+    let labels: Vec<String> = choices(timeout).into_iter().map(format_timeout).collect();
+    let list = gtk4::StringList::new(&labels.iter().map(|s| s.as_str()).collect::<Vec<_>>());
+
+    let combo = adw::ComboRow::new();
+    combo.set_title(&gettext("Disconnect After"));
+    combo.set_subtitle(&gettext("Unmount the share when it hasn't been used for this long"));
+    combo.set_model(Some(&list));
+    if let Some(pos) = choices(timeout).iter().position(|choice| *choice == timeout) {
+        combo.set_selected(pos as u32);
+    }
+    combo
+}
+
+/// Read the timeout selected in a combo created by `idle_timeout_combo(initial)`
+pub fn selected_idle_timeout(combo: &adw::ComboRow, initial: Option<u32>) -> Option<u32> {
+    choices(initial)
+        .get(combo.selected() as usize)
+        .copied()
+        .unwrap_or(initial)
+}
@@ -0,0 +1,108 @@
+use crate::samba::nix_parse::LoadProgress;
+use crate::ui::widgets::selected_string;
+use gettextrs::gettext;
+use gtk4::prelude::*;
+use libadwaita as adw;
+use libadwaita::prelude::*;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How often the progress of a load is shown again
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Group of dimmed placeholder rows shown while a list is loading
+pub fn skeleton_group(rows: usize) -> adw::PreferencesGroup {
+    let group = adw::PreferencesGroup::new();
+    group.set_title(&gettext("Loading..."));
+
+    for _ in 0..rows {
+        let row = adw::ActionRow::new();
+        row.set_title(" ");
+        row.set_subtitle(" ");
+        row.add_css_class("dim-label");
+        row.set_sensitive(false);
+        group.add(&row);
+    }
+
+    group
+}
+
+/// Group shown while the configuration is parsed, naming the file being read, with a
+/// button stopping the load. `progress` gives the load running when it is called.
+pub fn progress_group(progress: fn() -> Arc<LoadProgress>) -> adw::PreferencesGroup {
+    let group = adw::PreferencesGroup::new();
+    group.set_title(&gettext("Loading..."));
+
+    let row = adw::ActionRow::new();
+    row.set_title(&gettext("Reading the configuration"));
+    row.set_subtitle_lines(1);
+    group.add(&row);
+
+    let cancel_button = gtk4::Button::with_label(&gettext("Cancel"));
+    cancel_button.set_valign(gtk4::Align::Center);
+    cancel_button.add_css_class("flat");
+    cancel_button.connect_clicked(move |button| {
+        progress().cancel();
+        button.set_sensitive(false);
+    });
+    row.add_suffix(&cancel_button);
+
+    let progress_bar = gtk4::ProgressBar::new();
+    progress_bar.set_margin_top(12);
+    group.add(&progress_bar);
+
+    // Weak, so the timer stops once the list replaces the group
+    let row = row.downgrade();
+    let progress_bar = progress_bar.downgrade();
+    glib::timeout_add_local(PROGRESS_INTERVAL, move || {
+        let (Some(row), Some(progress_bar)) = (row.upgrade(), progress_bar.upgrade()) else {
+            return glib::ControlFlow::Break;
+        };
+        let progress = progress();
+        let subtitle = if progress.is_cancelled() {
+            gettext("Stopping after the current file...")
+        } else if let Some(file) = progress.current_file() {
+            file
+        } else {
+            format!("{}: {}", gettext("Files parsed"), progress.files_parsed())
+        };
+        row.set_subtitle(&subtitle);
+        progress_bar.pulse();
+        glib::ControlFlow::Continue
+    });
+
+    group
+}
+
+/// Fill a combo row with names loaded in the background, keeping `current` selected.
+/// Until the list arrives the combo only offers `current` and shows a spinner.
+pub fn load_combo_names(combo: &adw::ComboRow, current: &str, load: fn() -> Vec<String>) {
+    let initial: Vec<&str> = if current.is_empty() { vec![] } else { vec![current] };
+    combo.set_model(Some(&gtk4::StringList::new(&initial)));
+    combo.set_selected(0);
+    combo.set_sensitive(false);
+
+    let spinner = gtk4::Spinner::new();
+    spinner.start();
+    combo.add_suffix(&spinner);
+
+    let combo = combo.clone();
+    glib::spawn_future_local(async move {
+        let names = gio::spawn_blocking(load).await.unwrap_or_else(|e| {
+            eprintln!("Failed to load names: {:?}", e);
+            Vec::new()
+        });
+
+        if !names.is_empty() {
+            let current = selected_string(&combo);
+            let list = gtk4::StringList::new(&names.iter().map(|s| s.as_str()).collect::<Vec<_>>());
+            combo.set_model(Some(&list));
+            let selected = names.iter().position(|name| *name == current).unwrap_or(0);
+            combo.set_selected(selected as u32);
+        }
+
+        spinner.stop();
+        combo.remove(&spinner);
+        combo.set_sensitive(true);
+    });
+}
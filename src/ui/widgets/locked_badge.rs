@@ -0,0 +1,28 @@
+use gettextrs::gettext;
+use gtk4::prelude::*;
+use libadwaita as adw;
+use libadwaita::prelude::*;
+
+/// Small "Read-only" label shown next to entries the app can't edit safely
+pub fn locked_badge(reason: &str) -> gtk4::Label {
+    let badge = gtk4::Label::new(Some(&gettext("Read-only")));
+    badge.add_css_class("caption");
+    badge.add_css_class("warning");
+    badge.set_valign(gtk4::Align::Center);
+    badge.set_tooltip_text(Some(reason));
+    badge
+}
+
+/// Row explaining why an entry is read-only and how to change it
+pub fn locked_row(reason: &str, source_file: &str) -> adw::ActionRow {
+    let row = adw::ActionRow::new();
+    row.set_title(&gettext("Managed by a Nix expression"));
+    row.set_subtitle(&format!(
+        "{}. {}: {}",
+        reason,
+        gettext("Edit it directly in"),
+        source_file
+    ));
+    row.add_prefix(&gtk4::Image::from_icon_name("changes-prevent-symbolic"));
+    row
+}
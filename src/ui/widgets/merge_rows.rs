@@ -0,0 +1,136 @@
+use crate::samba::import_merge::{Field, MergeItem};
+use crate::samba::remote_share_config::RemoteSambaShareConfig;
+use crate::utils::format::format_timeout;
+use gettextrs::gettext;
+use gtk4::prelude::*;
+use libadwaita as adw;
+use libadwaita::prelude::*;
+
+/// What the user decided for an imported share
+#[derive(Clone)]
+pub enum MergeChoice {
+    /// Whether to add a new share
+    Add(gtk4::CheckButton),
+    /// Keep the configured settings, or replace them with the imported ones
+    Resolve(gtk4::DropDown),
+    /// Already configured the same way
+    Nothing,
+}
+
+impl MergeChoice {
+    /// Whether the item gets written
+    pub fn keeps(&self) -> bool {
+        match self {
+            MergeChoice::Add(check) => check.is_active(),
+            MergeChoice::Resolve(drop_down) => drop_down.selected() == 1,
+            MergeChoice::Nothing => false,
+        }
+    }
+
+    pub fn connect_changed(&self, f: impl Fn() + 'static) {
+        match self {
+            MergeChoice::Add(check) => {
+                check.connect_toggled(move |_| f());
+            }
+            MergeChoice::Resolve(drop_down) => {
+                drop_down.connect_selected_notify(move |_| f());
+            }
+            MergeChoice::Nothing => {}
+        }
+    }
+}
+
+/// Row of an imported share: a check box for additions, the differing settings and
+/// a choice between both versions for conflicts. `note` is appended to the subtitle.
+pub fn merge_row(item: &MergeItem, note: Option<&str>) -> (gtk4::Widget, MergeChoice) {
+    let subtitle = |text: String| match note {
+        Some(note) => format!("{} • {}", text, note),
+        None => text,
+    };
+
+    match item {
+        MergeItem::Addition(share) => {
+            let row = adw::ActionRow::new();
+            row.set_title(&share.name);
+            row.set_subtitle(&subtitle(share.remote_path.clone()));
+            row.set_use_markup(false);
+
+            let check = gtk4::CheckButton::new();
+            check.set_valign(gtk4::Align::Center);
+            check.set_active(true);
+            row.add_prefix(&check);
+            row.set_activatable_widget(Some(&check));
+            (row.upcast(), MergeChoice::Add(check))
+        }
+        MergeItem::Conflict {
+            existing,
+            incoming,
+            differences,
+        } => {
+            let row = adw::ExpanderRow::new();
+            row.set_title(&incoming.name);
+            row.set_subtitle(&subtitle(gettext("Configured with other settings")));
+            row.set_use_markup(false);
+
+            // Nothing is replaced unless asked
+            let choices = [gettext("Keep Current"), gettext("Use Imported")];
+            let drop_down = gtk4::DropDown::from_strings(&[choices[0].as_str(), choices[1].as_str()]);
+            drop_down.set_valign(gtk4::Align::Center);
+            row.add_suffix(&drop_down);
+            if let Some(reason) = &existing.locked_reason {
+                drop_down.set_sensitive(false);
+                drop_down.set_tooltip_text(Some(reason));
+            }
+
+            for field in differences {
+                let difference_row = adw::ActionRow::new();
+                difference_row.set_title(&field_label(*field));
+                difference_row.set_subtitle(&format!(
+                    "{} → {}",
+                    field_value(*field, existing),
+                    field_value(*field, incoming)
+                ));
+                difference_row.set_use_markup(false);
+                row.add_row(&difference_row);
+            }
+            (row.upcast(), MergeChoice::Resolve(drop_down))
+        }
+        MergeItem::Unchanged(share) => {
+            let row = adw::ActionRow::new();
+            row.set_title(&share.name);
+            row.set_subtitle(&subtitle(gettext("Already configured")));
+            row.set_use_markup(false);
+            row.set_sensitive(false);
+            (row.upcast(), MergeChoice::Nothing)
+        }
+    }
+}
+
+fn field_label(field: Field) -> String {
+    match field {
+        Field::RemotePath => gettext("Remote Path"),
+        Field::FsType => gettext("Type"),
+        Field::Credentials => gettext("Credentials File"),
+        Field::UserId => gettext("User ID (uid)"),
+        Field::GroupId => gettext("Group ID (gid)"),
+        Field::IdleTimeout => gettext("Disconnect After"),
+        Field::Options => gettext("Options"),
+    }
+}
+
+fn field_value(field: Field, share: &RemoteSambaShareConfig) -> String {
+    let value = match field {
+        Field::RemotePath => share.remote_path.clone(),
+        Field::FsType => share.fs_type.clone(),
+        Field::Credentials => share.option_credentials.clone(),
+        Field::UserId => share.force_user.clone(),
+        Field::GroupId => share.force_group.clone(),
+        Field::IdleTimeout => return format_timeout(share.idle_timeout),
+        Field::Options => share.extra_options.join(", "),
+    };
+    if value.is_empty() {
+        gettext("Not set")
+    } else {
+        value
+    }
+}
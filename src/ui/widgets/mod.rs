@@ -1 +1,44 @@
-// Widgets module - currently empty
\ No newline at end of file
+pub mod config_warnings;
+pub mod connection_test;
+pub mod favorites;
+pub mod file_picker;
+pub mod idle_timeout;
+pub mod loading;
+pub mod locked_badge;
+pub mod merge_rows;
+pub mod mount_backend;
+pub mod option_drift;
+pub mod overview_group;
+pub mod quota_row;
+pub mod remote_path;
+pub mod server_picker;
+pub mod share_advanced_options;
+pub mod share_appearance;
+pub mod share_badges;
+pub mod share_notes;
+pub mod source_view;
+pub mod write_access;
+
+pub use config_warnings::{warning_badge, warning_row};
+pub use connection_test::connection_test_button;
+pub use favorites::{favorite_button, FavoritesGroup};
+pub use file_picker::{browse_file, browse_folder};
+pub use idle_timeout::{idle_timeout_combo, selected_idle_timeout};
+pub use loading::{load_combo_names, progress_group, skeleton_group};
+pub use locked_badge::{locked_badge, locked_row};
+pub use merge_rows::{merge_row, MergeChoice};
+pub use mount_backend::{mount_backend_combo, mount_backend_label, selected_mount_backend};
+pub use option_drift::option_drift_badge;
+pub use overview_group::OverviewGroup;
+pub use quota_row::QuotaRow;
+pub use remote_path::{convert_unc_paths, fill_from_clipboard};
+pub use server_picker::{server_picker, share_picker};
+pub use share_advanced_options::{
+    security_level_combo, select_security_level, select_string, selected_security_level, selected_string,
+    ShareAdvancedOptions,
+};
+pub use share_appearance::{appearance_button, load_share_colors, share_icon};
+pub use share_badges::{remote_share_badge, share_badges};
+pub use share_notes::notes_row;
+pub use source_view::source_view;
+pub use write_access::{allow_write, lock_when_read_only, retry_with_authentication};
@@ -0,0 +1,46 @@
+use crate::samba::remote_share_config::MountBackend;
+use gettextrs::gettext;
+use libadwaita as adw;
+use libadwaita::prelude::*;
+
+/// Short name of a backend, shown in lists
+pub fn mount_backend_label(backend: MountBackend) -> String {
+    match backend {
+        MountBackend::FileSystems => gettext("systemd automount"),
+        MountBackend::Autofs => gettext("autofs"),
+        MountBackend::Fstab => gettext("/etc/fstab"),
+    }
+}
+
+/// Combo row to choose how a remote share is declared
+pub fn mount_backend_combo(backend: MountBackend) -> adw::ComboRow {
+    let backends = MountBackend::available();
+    let labels: Vec<String> = backends
+        .iter()
+        .map(|backend| mount_backend_label(*backend))
+        .collect();
+    let list = gtk4::StringList::new(&labels.iter().map(|s| s.as_str()).collect::<Vec<_>>());
+
+    let combo = adw::ComboRow::new();
+    combo.set_title(&gettext("Backend"));
+    if backends.contains(&MountBackend::Fstab) {
+        combo.set_subtitle(&gettext("A line of /etc/fstab mounted by systemd"));
+    } else {
+        combo.set_subtitle(&gettext("A fileSystems entry mounted by systemd, or a line of an autofs map"));
+    }
+    combo.set_model(Some(&list));
+    if let Some(pos) = backends.iter().position(|b| *b == backend) {
+        combo.set_selected(pos as u32);
+    }
+    combo
+}
+
+/// Read the backend selected in a combo created by `mount_backend_combo`
+pub fn selected_mount_backend(combo: &adw::ComboRow) -> MountBackend {
+    let backends = MountBackend::available();
+    backends
+        .get(combo.selected() as usize)
+        .or(backends.first())
+        .copied()
+        .unwrap_or_default()
+}
@@ -0,0 +1,94 @@
+use crate::models::AppState;
+use crate::samba::mount_option_set::OptionDifference;
+use crate::samba::{remount_with_configured_options, RemoteSambaShareConfig};
+use gettextrs::gettext;
+use gtk4::prelude::*;
+use gtk4::{gio, glib};
+use libadwaita as adw;
+
+/// Badge of a mounted share whose live options differ from its configuration, opening
+/// a popover listing them with an action mounting it again as configured
+pub fn option_drift_badge(
+    config: &RemoteSambaShareConfig,
+    differences: &[OptionDifference],
+    toast_overlay: &adw::ToastOverlay,
+) -> gtk4::MenuButton {
+    let label = gtk4::Label::new(Some(&gettext("Differs from Configuration")));
+    label.add_css_class("caption");
+    label.add_css_class("warning");
+    let button = gtk4::MenuButton::new();
+    button.set_child(Some(&label));
+    button.set_valign(gtk4::Align::Center);
+    button.add_css_class("flat");
+    button.set_tooltip_text(Some(&gettext(
+        "The share is mounted with other options than the configured ones, by hand or before the last change",
+    )));
+
+    let content = gtk4::Box::new(gtk4::Orientation::Vertical, 12);
+    content.set_margin_top(6);
+    content.set_margin_bottom(6);
+    content.set_margin_start(6);
+    content.set_margin_end(6);
+
+    let grid = gtk4::Grid::new();
+    grid.set_row_spacing(6);
+    grid.set_column_spacing(18);
+    let headers = [gettext("Option"), gettext("Configured"), gettext("Mounted")];
+    for (column, header) in headers.iter().enumerate() {
+        let header = gtk4::Label::new(Some(header));
+        header.add_css_class("heading");
+        header.set_halign(gtk4::Align::Start);
+        grid.attach(&header, column as i32, 0, 1, 1);
+    }
+    for (index, difference) in differences.iter().enumerate() {
+        let live = difference.live.clone().unwrap_or_else(|| gettext("Not set"));
+        for (column, text) in [&difference.key, &difference.configured, &live].into_iter().enumerate() {
+            let cell = gtk4::Label::new(Some(text));
+            cell.set_halign(gtk4::Align::Start);
+            cell.set_selectable(true);
+            if column > 0 {
+                cell.add_css_class("monospace");
+            }
+            grid.attach(&cell, column as i32, index as i32 + 1, 1, 1);
+        }
+    }
+    content.append(&grid);
+
+    let remount_button = gtk4::Button::with_label(&gettext("Remount with Configured Options"));
+    remount_button.add_css_class("suggested-action");
+    content.append(&remount_button);
+
+    let popover = gtk4::Popover::new();
+    popover.set_child(Some(&content));
+    button.set_popover(Some(&popover));
+
+    let config = config.clone();
+    let toast_overlay = toast_overlay.clone();
+    remount_button.connect_clicked(move |remount_button| {
+        remount_button.set_sensitive(false);
+        let config = config.clone();
+        let remount_button = remount_button.clone();
+        let popover = popover.clone();
+        let toast_overlay = toast_overlay.clone();
+        glib::spawn_future_local(async move {
+            let result = gio::spawn_blocking(move || remount_with_configured_options(&config))
+                .await
+                .unwrap_or_else(|e| Err(format!("{:?}", e)));
+
+            remount_button.set_sensitive(true);
+            match result {
+                Ok(()) => {
+                    popover.popdown();
+                    toast_overlay.add_toast(adw::Toast::new(&gettext("Share mounted with the configured options")));
+                    AppState::get().reload_remote();
+                }
+                Err(e) => {
+                    eprintln!("Failed to remount share: {}", e);
+                    let error_msg = format!("{}: {}", gettext("Remount failed"), e);
+                    toast_overlay.add_toast(adw::Toast::new(&error_msg));
+                }
+            }
+        });
+    });
+    button
+}
@@ -0,0 +1,170 @@
+use crate::models::AppState;
+use crate::samba::Overview;
+use gettextrs::gettext;
+use gtk4::prelude::*;
+use libadwaita as adw;
+use libadwaita::prelude::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Live counters for the main page, each row leading to the matching page
+#[derive(Clone)]
+pub struct OverviewGroup {
+    group: adw::PreferencesGroup,
+    pub local_row: adw::ActionRow,
+    pub remote_row: adw::ActionRow,
+    pub mounted_row: adw::ActionRow,
+    pub failures_row: adw::ActionRow,
+    pub activity_row: adw::ActionRow,
+    local_count: gtk4::Label,
+    remote_count: gtk4::Label,
+    mounted_count: gtk4::Label,
+    failures_count: gtk4::Label,
+    spinner: gtk4::Spinner,
+    last_activity: Rc<RefCell<Option<String>>>,
+    last: Rc<RefCell<Overview>>,
+}
+
+impl OverviewGroup {
+    pub fn new() -> Self {
+        let group = adw::PreferencesGroup::new();
+        group.set_title(&gettext("Overview"));
+
+        let spinner = gtk4::Spinner::new();
+        group.set_header_suffix(Some(&spinner));
+
+        let (local_row, local_count) = counter_row(&gettext("Local Shares"), "folder-symbolic");
+        group.add(&local_row);
+
+        let (remote_row, remote_count) =
+            counter_row(&gettext("Remote Shares"), "network-server-symbolic");
+        group.add(&remote_row);
+
+        let (mounted_row, mounted_count) =
+            counter_row(&gettext("Mounted"), "drive-harddisk-symbolic");
+        group.add(&mounted_row);
+
+        let (failures_row, failures_count) =
+            counter_row(&gettext("Problems"), "dialog-warning-symbolic");
+        failures_row.add_css_class("warning");
+        failures_row.set_use_markup(false);
+        failures_row.set_visible(false);
+        group.add(&failures_row);
+
+        let activity_row = adw::ActionRow::new();
+        activity_row.set_title(&gettext("Recent Activity"));
+        activity_row.set_subtitle(&gettext("No audited activity yet"));
+        activity_row.set_subtitle_lines(2);
+        activity_row.set_use_markup(false);
+        activity_row.set_activatable(true);
+        activity_row.add_prefix(&gtk4::Image::from_icon_name("document-open-recent-symbolic"));
+        activity_row.add_suffix(&gtk4::Image::from_icon_name("go-next-symbolic"));
+        group.add(&activity_row);
+
+        let this = Self {
+            group,
+            local_row,
+            remote_row,
+            mounted_row,
+            failures_row,
+            activity_row,
+            local_count,
+            remote_count,
+            mounted_count,
+            failures_count,
+            spinner,
+            last_activity: Rc::new(RefCell::new(None)),
+            last: Rc::new(RefCell::new(Overview::default())),
+        };
+
+        // Counters follow the shared state, whoever reloads it
+        let state = AppState::get();
+        let this_clone = this.clone();
+        state.connect_local_shares_changed(move |_| this_clone.update());
+        let this_clone = this.clone();
+        state.connect_remote_shares_changed(move |_| this_clone.update());
+
+        this
+    }
+
+    pub fn group(&self) -> &adw::PreferencesGroup {
+        &self.group
+    }
+
+    /// Counters from the last refresh, used to pick the page a problem links to
+    pub fn overview(&self) -> Overview {
+        self.last.borrow().clone()
+    }
+
+    /// Reload shares, mounts and the last audit line in the background
+    pub fn refresh(&self) {
+        let state = AppState::get();
+        state.reload();
+        if state.is_loading() {
+            self.spinner.start();
+        }
+
+        let this = self.clone();
+        glib::spawn_future_local(async move {
+            let last_activity = gio::spawn_blocking(Overview::load_last_activity)
+                .await
+                .unwrap_or_else(|e| {
+                    eprintln!("Failed to read the audit log: {:?}", e);
+                    None
+                });
+            *this.last_activity.borrow_mut() = last_activity;
+            this.update();
+        });
+    }
+
+    /// Show the counters once both local and remote shares are known
+    fn update(&self) {
+        let state = AppState::get();
+        if !state.is_loading() {
+            self.spinner.stop();
+        }
+
+        let (Some(local), Some(remote)) = (state.local_shares(), state.remote_shares()) else {
+            return;
+        };
+        let overview = Overview::new(&local, &remote, self.last_activity.borrow().clone());
+        self.show(&overview);
+        *self.last.borrow_mut() = overview;
+    }
+
+    fn show(&self, overview: &Overview) {
+        self.local_count.set_label(&overview.local_shares.to_string());
+        self.remote_count.set_label(&overview.remote_shares.to_string());
+        self.mounted_count.set_label(&overview.mounted.to_string());
+
+        let failures = overview.failures();
+        self.failures_count.set_label(&failures.to_string());
+        self.failures_row.set_visible(failures > 0);
+        let errors: Vec<&str> = [&overview.local_error, &overview.remote_error]
+            .into_iter()
+            .flatten()
+            .map(|e| e.as_str())
+            .collect();
+        self.failures_row.set_subtitle(&errors.join("\n"));
+
+        match &overview.last_activity {
+            Some(line) => self.activity_row.set_subtitle(line),
+            None => self.activity_row.set_subtitle(&gettext("No audited activity yet")),
+        }
+    }
+}
+
+/// Activatable row with an icon, a title and a count on the right
+fn counter_row(title: &str, icon_name: &str) -> (adw::ActionRow, gtk4::Label) {
+    let row = adw::ActionRow::new();
+    row.set_title(title);
+    row.set_activatable(true);
+    row.add_prefix(&gtk4::Image::from_icon_name(icon_name));
+
+    let count = gtk4::Label::new(Some("…"));
+    count.add_css_class("title-3");
+    row.add_suffix(&count);
+    row.add_suffix(&gtk4::Image::from_icon_name("go-next-symbolic"));
+
+    (row, count)
+}
@@ -0,0 +1,162 @@
+use crate::samba::quota::{quota_usage, set_quota, QuotaKind, QuotaUsage};
+use crate::ui::widgets::{allow_write, lock_when_read_only};
+use crate::utils::format::format_size;
+use gettextrs::gettext;
+use gtk4::prelude::*;
+use gtk4::{gio, glib};
+use libadwaita as adw;
+use libadwaita::prelude::*;
+use std::cell::Cell;
+use std::rc::Rc;
+
+/// Unit of the limit typed by the user, the one `format_size` displays
+const GB: u64 = 1_000_000_000;
+
+/// Size limit of a local share with a usage bar and a button to change it.
+/// Hidden until the share's filesystem is known to support limits.
+#[derive(Clone)]
+pub struct QuotaRow {
+    row: adw::ActionRow,
+    level_bar: gtk4::LevelBar,
+    path: String,
+    kind: Rc<Cell<Option<QuotaKind>>>,
+    usage: Rc<Cell<Option<QuotaUsage>>>,
+    toast_overlay: adw::ToastOverlay,
+}
+
+impl QuotaRow {
+    pub fn new(path: &str, toast_overlay: &adw::ToastOverlay) -> Self {
+        let row = adw::ActionRow::new();
+        row.set_title(&gettext("Size Limit"));
+        row.set_visible(false);
+
+        let level_bar = gtk4::LevelBar::new();
+        level_bar.set_valign(gtk4::Align::Center);
+        level_bar.set_width_request(120);
+        level_bar.set_visible(false);
+        row.add_suffix(&level_bar);
+
+        let change_button = gtk4::Button::with_label(&gettext("Change"));
+        change_button.set_valign(gtk4::Align::Center);
+        change_button.add_css_class("flat");
+        lock_when_read_only(&change_button);
+        row.add_suffix(&change_button);
+
+        let this = Self {
+            row,
+            level_bar,
+            path: path.to_string(),
+            kind: Rc::new(Cell::new(None)),
+            usage: Rc::new(Cell::new(None)),
+            toast_overlay: toast_overlay.clone(),
+        };
+
+        let this_clone = this.clone();
+        change_button.connect_clicked(move |button| {
+            if !allow_write(button) {
+                return;
+            }
+            this_clone.ask_limit(button);
+        });
+        this
+    }
+
+    pub fn row(&self) -> &adw::ActionRow {
+        &self.row
+    }
+
+    /// Show the limit of a folder on a filesystem supporting them
+    pub fn show(&self, kind: QuotaKind, usage: Result<Option<QuotaUsage>, String>) {
+        self.kind.set(Some(kind));
+        self.row.set_visible(true);
+        self.row.set_tooltip_text(None);
+
+        match usage {
+            Ok(Some(usage)) => {
+                self.usage.set(Some(usage));
+                self.row.set_subtitle(
+                    &gettext("{used} of {limit} used")
+                        .replace("{used}", &format_size(usage.used))
+                        .replace("{limit}", &format_size(usage.limit)),
+                );
+                self.level_bar.set_value(usage.fraction());
+                self.level_bar.set_visible(true);
+            }
+            Ok(None) => {
+                self.usage.set(None);
+                self.row.set_subtitle(&gettext("No limit, the share can fill the disk"));
+                self.level_bar.set_visible(false);
+            }
+            Err(e) => {
+                self.usage.set(None);
+                self.row.set_subtitle(&gettext("Not available"));
+                self.row.set_tooltip_text(Some(&e));
+                self.level_bar.set_visible(false);
+            }
+        }
+    }
+
+    /// Ask for the new limit, then set or remove it
+    fn ask_limit(&self, parent: &gtk4::Button) {
+        let Some(kind) = self.kind.get() else {
+            return;
+        };
+        let current = self.usage.get();
+
+        let body = match kind {
+            QuotaKind::Btrfs => gettext(
+                "Clients can't store more than this in the share. The folder must be a Btrfs subvolume; quotas are enabled on the filesystem if needed.",
+            ),
+            QuotaKind::Xfs => gettext(
+                "Clients can't store more than this in the share. The filesystem must be mounted with the prjquota option.",
+            ),
+        };
+        let dialog = adw::AlertDialog::new(Some(&gettext("Size Limit")), Some(&body));
+        dialog.add_response("cancel", &gettext("Cancel"));
+        if current.is_some() {
+            dialog.add_response("remove", &gettext("Remove Limit"));
+            dialog.set_response_appearance("remove", adw::ResponseAppearance::Destructive);
+        }
+        dialog.add_response("set", &gettext("Set Limit"));
+        dialog.set_response_appearance("set", adw::ResponseAppearance::Suggested);
+        dialog.set_default_response(Some("set"));
+        dialog.set_close_response("cancel");
+
+        let size_row = adw::SpinRow::with_range(1.0, 1_000_000.0, 1.0);
+        size_row.set_title(&gettext("Limit (GB)"));
+        size_row.set_value(current.map(|usage| (usage.limit / GB).max(1)).unwrap_or(100) as f64);
+        let list = gtk4::ListBox::new();
+        list.add_css_class("boxed-list");
+        list.set_selection_mode(gtk4::SelectionMode::None);
+        list.append(&size_row);
+        dialog.set_extra_child(Some(&list));
+
+        let this = self.clone();
+        dialog.connect_response(None, move |_, response| {
+            let limit = match response {
+                "set" => Some(size_row.value() as u64 * GB),
+                "remove" => None,
+                _ => return,
+            };
+            let this = this.clone();
+            let path = this.path.clone();
+            glib::spawn_future_local(async move {
+                let result = gio::spawn_blocking(move || {
+                    set_quota(&path, kind, limit)?;
+                    quota_usage(&path, kind)
+                })
+                .await
+                .unwrap_or_else(|e| Err(format!("{:?}", e)));
+
+                if let Err(e) = &result {
+                    eprintln!("Failed to change the size limit of {}: {}", this.path, e);
+                    let error_msg = format!("{}: {}", gettext("Failed to change the size limit"), e);
+                    this.toast_overlay.add_toast(adw::Toast::new(&error_msg));
+                    return;
+                }
+                this.show(kind, result);
+            });
+        });
+        dialog.present(Some(parent));
+    }
+}
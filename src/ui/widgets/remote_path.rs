@@ -0,0 +1,30 @@
+use crate::samba::share_browser::from_unc_path;
+use gettextrs::gettext;
+use gtk4::gio;
+use gtk4::prelude::*;
+use libadwaita as adw;
+
+/// Rewrite Windows paths typed or pasted in a remote path entry to the `//server/share` form
+pub fn convert_unc_paths(entry: &adw::EntryRow) {
+    entry.connect_changed(|entry| {
+        if let Some(converted) = from_unc_path(&entry.text()) {
+            entry.set_text(&converted);
+            entry.set_position(-1);
+        }
+    });
+}
+
+/// Fill a remote path entry with the Windows path found on the clipboard, if any
+pub fn fill_from_clipboard(entry: &adw::EntryRow, toast_overlay: &adw::ToastOverlay) {
+    let entry_clone = entry.clone();
+    let toast_overlay = toast_overlay.clone();
+    entry
+        .clipboard()
+        .read_text_async(None::<&gio::Cancellable>, move |result| {
+            let Some(converted) = result.ok().flatten().and_then(|text| from_unc_path(&text)) else {
+                return;
+            };
+            entry_clone.set_text(&converted);
+            toast_overlay.add_toast(adw::Toast::new(&gettext("Remote path pasted from the clipboard")));
+        });
+}
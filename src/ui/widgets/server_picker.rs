@@ -0,0 +1,235 @@
+use crate::config::AppConfig;
+use crate::samba::neighborhood::{discover_hosts, list_host_shares, HostShare, NetworkHost};
+use gettextrs::gettext;
+use gtk4::prelude::*;
+use gtk4::{gio, glib};
+use libadwaita as adw;
+use libadwaita::prelude::*;
+
+/// Button of a remote path entry listing the servers found on the network, picking one
+/// replacing the server of the path. The network is searched each time the list opens.
+pub fn server_picker(remote_path_entry: &adw::EntryRow) -> gtk4::MenuButton {
+    let (button, picker) = PickerList::button("network-workgroup-symbolic", &gettext("Servers on the Network"));
+
+    let entry = remote_path_entry.clone();
+    picker.popover.clone().connect_show(move |_| {
+        picker.start_loading();
+
+        let scan = AppConfig::new().discovery_scan();
+        let entry = entry.clone();
+        let picker = picker.clone();
+        glib::spawn_future_local(async move {
+            let result = gio::spawn_blocking(move || discover_hosts(scan))
+                .await
+                .unwrap_or_else(|e| Err(format!("{:?}", e)));
+
+            match result {
+                Ok(hosts) if hosts.is_empty() => {
+                    picker.show_message(&gettext("No servers found, type the address of the server instead"));
+                }
+                Ok(hosts) => {
+                    picker.show_rows(hosts.iter().map(|host| server_row(host, &entry, &picker.popover)));
+                }
+                Err(e) => {
+                    eprintln!("Failed to look for servers: {}", e);
+                    picker.show_message(&format!("{}: {}", gettext("Cannot look for servers"), e));
+                }
+            }
+        });
+    });
+    button
+}
+
+/// Button of a remote path entry listing the shares of the server typed in it, picking
+/// one completing the path. Shares are listed as guest, or with the credentials file of
+/// `credentials_entry` when there is one.
+pub fn share_picker(remote_path_entry: &adw::EntryRow, credentials_entry: &adw::EntryRow) -> gtk4::MenuButton {
+    let (button, picker) = PickerList::button("pan-down-symbolic", &gettext("Shares of the Server"));
+
+    let entry = remote_path_entry.clone();
+    let credentials_entry = credentials_entry.clone();
+    picker.popover.clone().connect_show(move |_| {
+        let server = server_of(&entry.text());
+        if server.is_empty() {
+            picker.show_message(&gettext("Enter or pick the server first"));
+            return;
+        }
+        picker.start_loading();
+
+        let credentials = credentials_entry.text().trim().to_string();
+        let entry = entry.clone();
+        let picker = picker.clone();
+        glib::spawn_future_local(async move {
+            let host = server.clone();
+            let result = gio::spawn_blocking(move || list_host_shares(&host, &credentials))
+                .await
+                .unwrap_or_else(|e| Err(format!("{:?}", e)));
+
+            match result {
+                Ok(shares) if shares.is_empty() => {
+                    picker.show_message(&gettext("The server offers no shares to these credentials"));
+                }
+                Ok(shares) => {
+                    picker.show_rows(shares.iter().map(|share| share_row(&server, share, &entry, &picker.popover)));
+                }
+                Err(e) => {
+                    eprintln!("Failed to list the shares of {}: {}", server, e);
+                    picker.show_message(&format!("{}: {}", gettext("Cannot list the shares"), e));
+                }
+            }
+        });
+    });
+    button
+}
+
+/// Popover of a picker button, showing a spinner while its rows load or a message
+/// when there are none
+#[derive(Clone)]
+struct PickerList {
+    popover: gtk4::Popover,
+    stack: gtk4::Stack,
+    spinner: gtk4::Spinner,
+    message: gtk4::Label,
+    list: gtk4::ListBox,
+}
+
+impl PickerList {
+    fn button(icon_name: &str, tooltip: &str) -> (gtk4::MenuButton, Self) {
+        let button = gtk4::MenuButton::new();
+        button.set_icon_name(icon_name);
+        button.set_valign(gtk4::Align::Center);
+        button.set_tooltip_text(Some(tooltip));
+        button.add_css_class("flat");
+
+        let stack = gtk4::Stack::new();
+        stack.set_vhomogeneous(false);
+        stack.set_size_request(280, -1);
+
+        let spinner = gtk4::Spinner::new();
+        spinner.set_size_request(32, 32);
+        spinner.set_margin_top(12);
+        spinner.set_margin_bottom(12);
+        stack.add_named(&spinner, Some("loading"));
+
+        let message = gtk4::Label::new(None);
+        message.set_wrap(true);
+        message.set_max_width_chars(36);
+        message.set_margin_top(12);
+        message.set_margin_bottom(12);
+        message.add_css_class("dim-label");
+        stack.add_named(&message, Some("message"));
+
+        let list = gtk4::ListBox::new();
+        list.set_selection_mode(gtk4::SelectionMode::None);
+        list.add_css_class("boxed-list");
+        let scrolled = gtk4::ScrolledWindow::builder()
+            .hscrollbar_policy(gtk4::PolicyType::Never)
+            .propagate_natural_height(true)
+            .max_content_height(360)
+            .child(&list)
+            .build();
+        stack.add_named(&scrolled, Some("rows"));
+
+        let popover = gtk4::Popover::new();
+        popover.set_child(Some(&stack));
+        button.set_popover(Some(&popover));
+
+        let picker = Self {
+            popover,
+            stack,
+            spinner,
+            message,
+            list,
+        };
+        (button, picker)
+    }
+
+    fn start_loading(&self) {
+        while let Some(row) = self.list.first_child() {
+            self.list.remove(&row);
+        }
+        self.spinner.start();
+        self.stack.set_visible_child_name("loading");
+    }
+
+    fn show_message(&self, text: &str) {
+        self.spinner.stop();
+        self.message.set_text(text);
+        self.stack.set_visible_child_name("message");
+    }
+
+    fn show_rows(&self, rows: impl Iterator<Item = adw::ActionRow>) {
+        self.spinner.stop();
+        for row in rows {
+            self.list.append(&row);
+        }
+        self.stack.set_visible_child_name("rows");
+    }
+}
+
+/// Row of a server, putting its address in `entry` when activated
+fn server_row(host: &NetworkHost, entry: &adw::EntryRow, popover: &gtk4::Popover) -> adw::ActionRow {
+    let subtitle = match &host.workgroup {
+        Some(workgroup) => format!("{} · {}", host.address, workgroup),
+        None => host.address.clone(),
+    };
+    let address = host.address.clone();
+    let path = move |remote_path: &str| with_server(remote_path, &address);
+    picker_row(&host.name, &subtitle, "network-server-symbolic", path, entry, popover)
+}
+
+/// Row of a share of `server`, putting its path in `entry` when activated
+fn share_row(server: &str, share: &HostShare, entry: &adw::EntryRow, popover: &gtk4::Popover) -> adw::ActionRow {
+    let share_path = format!("//{}/{}", server, share.name);
+    let path = move |_: &str| share_path.clone();
+    picker_row(&share.name, &share.comment, "folder-remote-symbolic", path, entry, popover)
+}
+
+/// Activatable row replacing the text of `entry` with `path` of it
+fn picker_row(
+    title: &str,
+    subtitle: &str,
+    icon_name: &str,
+    path: impl Fn(&str) -> String + 'static,
+    entry: &adw::EntryRow,
+    popover: &gtk4::Popover,
+) -> adw::ActionRow {
+    let row = adw::ActionRow::new();
+    row.set_title(title);
+    row.set_subtitle(subtitle);
+    row.set_use_markup(false);
+    row.add_prefix(&gtk4::Image::from_icon_name(icon_name));
+    row.set_activatable(true);
+
+    let entry = entry.clone();
+    let popover = popover.clone();
+    row.connect_activated(move |_| {
+        entry.set_text(&path(&entry.text()));
+        entry.grab_focus();
+        entry.set_position(-1);
+        popover.popdown();
+    });
+    row
+}
+
+/// Server of a remote path being typed, empty until one is
+fn server_of(remote_path: &str) -> String {
+    remote_path
+        .trim()
+        .trim_start_matches('/')
+        .split('/')
+        .next()
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// `remote_path` on `server`, keeping the share and folders already typed
+fn with_server(remote_path: &str, server: &str) -> String {
+    let rest = remote_path
+        .trim()
+        .trim_start_matches('/')
+        .split_once('/')
+        .map(|(_, rest)| rest)
+        .unwrap_or_default();
+    format!("//{}/{}", server, rest)
+}
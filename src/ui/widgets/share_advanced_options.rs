@@ -0,0 +1,478 @@
+use crate::samba::share_config::parse_name_list;
+use crate::samba::spotlight;
+use crate::samba::{get_system_users, SambaShareConfig, SecurityLevel};
+use gettextrs::gettext;
+use libadwaita as adw;
+use libadwaita::prelude::*;
+
+/// Advanced per-share options, shared by the add and edit share dialogs
+pub struct ShareAdvancedOptions {
+    group: adw::PreferencesGroup,
+    encrypt_combo: adw::ComboRow,
+    audit_switch: adw::SwitchRow,
+    guest_account_combo: adw::ComboRow,
+    guest_accounts: Vec<String>,
+    read_list_entry: adw::EntryRow,
+    write_list_entry: adw::EntryRow,
+    create_mask_entry: adw::EntryRow,
+    directory_mask_entry: adw::EntryRow,
+    force_create_mode_entry: adw::EntryRow,
+    force_directory_mode_entry: adw::EntryRow,
+    inherit_permissions_switch: adw::SwitchRow,
+    inherit_acls_switch: adw::SwitchRow,
+    follow_symlinks_switch: adw::SwitchRow,
+    wide_links_switch: adw::SwitchRow,
+    store_dos_attributes_switch: adw::SwitchRow,
+    map_archive_switch: adw::SwitchRow,
+    map_hidden_switch: adw::SwitchRow,
+    map_system_switch: adw::SwitchRow,
+    oplocks_switch: adw::SwitchRow,
+    level2_oplocks_switch: adw::SwitchRow,
+    kernel_oplocks_switch: adw::SwitchRow,
+    spotlight_switch: adw::SwitchRow,
+}
+
+impl ShareAdvancedOptions {
+    pub fn new() -> Self {
+        let group = adw::PreferencesGroup::new();
+        group.set_title(&gettext("Advanced"));
+
+        // Security expander
+        let security_expander = adw::ExpanderRow::new();
+        security_expander.set_title(&gettext("Security"));
+        security_expander.set_subtitle(&gettext("Encryption requirements for this share"));
+
+        let encrypt_combo = security_level_combo(
+            &gettext("Encryption"),
+            &gettext("Require clients to encrypt traffic (smb encrypt)"),
+        );
+        security_expander.add_row(&encrypt_combo);
+        group.add(&security_expander);
+
+        // Guest account override, first entry keeps the server default
+        let mut guest_accounts = vec![String::new()];
+        guest_accounts.extend(get_system_users());
+        let default_label = gettext("Server default");
+        let guest_labels: Vec<&str> = guest_accounts
+            .iter()
+            .map(|u| if u.is_empty() { default_label.as_str() } else { u.as_str() })
+            .collect();
+        let guest_account_combo = adw::ComboRow::new();
+        guest_account_combo.set_title(&gettext("Guest Account"));
+        guest_account_combo.set_subtitle(&gettext(
+            "System user that guests are mapped to on this share",
+        ));
+        guest_account_combo.set_model(Some(&gtk4::StringList::new(&guest_labels)));
+        guest_account_combo.set_selected(0);
+        guest_account_combo.set_sensitive(false);
+        group.add(&guest_account_combo);
+
+        // Access lists expander
+        let access_expander = adw::ExpanderRow::new();
+        access_expander.set_title(&gettext("Access Lists"));
+        access_expander.set_subtitle(&gettext(
+            "Exceptions to Read Only for specific users or groups",
+        ));
+
+        let read_list_entry = name_list_entry(&gettext("Read List"));
+        read_list_entry.set_tooltip_text(Some(&gettext(
+            "Users and @groups that can only read, even if the share is writable",
+        )));
+        access_expander.add_row(&read_list_entry);
+
+        let write_list_entry = name_list_entry(&gettext("Write List"));
+        write_list_entry.set_tooltip_text(Some(&gettext(
+            "Users and @groups that can write, even if the share is read only",
+        )));
+        access_expander.add_row(&write_list_entry);
+
+        group.add(&access_expander);
+
+        // Advanced permissions expander
+        let permissions_expander = adw::ExpanderRow::new();
+        permissions_expander.set_title(&gettext("Advanced Permissions"));
+        permissions_expander.set_subtitle(&gettext("Unix modes applied to new files and folders"));
+
+        let create_mask_entry = mode_entry(&gettext("Create Mask"), "0744");
+        permissions_expander.add_row(&create_mask_entry);
+        let directory_mask_entry = mode_entry(&gettext("Directory Mask"), "0755");
+        permissions_expander.add_row(&directory_mask_entry);
+        let force_create_mode_entry = mode_entry(&gettext("Force Create Mode"), "0000");
+        permissions_expander.add_row(&force_create_mode_entry);
+        let force_directory_mode_entry = mode_entry(&gettext("Force Directory Mode"), "0000");
+        permissions_expander.add_row(&force_directory_mode_entry);
+
+        let inherit_permissions_switch = adw::SwitchRow::new();
+        inherit_permissions_switch.set_title(&gettext("Inherit Permissions"));
+        inherit_permissions_switch.set_subtitle(&gettext(
+            "New files and folders copy the parent folder's permissions, ignoring the masks",
+        ));
+        permissions_expander.add_row(&inherit_permissions_switch);
+
+        let inherit_acls_switch = adw::SwitchRow::new();
+        inherit_acls_switch.set_title(&gettext("Inherit ACLs"));
+        inherit_acls_switch.set_subtitle(&gettext(
+            "New files and folders copy the parent folder's access control lists",
+        ));
+        permissions_expander.add_row(&inherit_acls_switch);
+
+        group.add(&permissions_expander);
+
+        // Symlinks expander
+        let symlinks_expander = adw::ExpanderRow::new();
+        symlinks_expander.set_title(&gettext("Symbolic Links"));
+        symlinks_expander.set_subtitle(&gettext("How links inside the shared folder are handled"));
+
+        let follow_symlinks_switch = adw::SwitchRow::new();
+        follow_symlinks_switch.set_title(&gettext("Follow Symlinks"));
+        follow_symlinks_switch.set_subtitle(&gettext("Clients can open files through symbolic links"));
+        follow_symlinks_switch.set_active(true);
+        symlinks_expander.add_row(&follow_symlinks_switch);
+
+        let wide_links_switch = adw::SwitchRow::new();
+        wide_links_switch.set_title(&gettext("Wide Links"));
+        wide_links_switch.set_subtitle(&gettext("Allow links pointing outside the shared folder"));
+        symlinks_expander.add_row(&wide_links_switch);
+
+        let wide_links_warning = adw::ActionRow::new();
+        wide_links_warning.set_title(&gettext("Wide links expose files outside the share"));
+        wide_links_warning.set_subtitle(&gettext(
+            "Any user able to create a symlink can reach the whole filesystem with the share's permissions. Samba also ignores this option unless \"Allow Insecure Wide Links\" is enabled in Server Settings.",
+        ));
+        wide_links_warning.add_prefix(&gtk4::Image::from_icon_name("dialog-warning-symbolic"));
+        wide_links_warning.add_css_class("warning");
+        symlinks_expander.add_row(&wide_links_warning);
+
+        // Wide links only make sense while symlinks are followed
+        follow_symlinks_switch
+            .bind_property("active", &wide_links_switch, "sensitive")
+            .sync_create()
+            .build();
+        let wide_links_switch_clone = wide_links_switch.clone();
+        follow_symlinks_switch.connect_active_notify(move |switch| {
+            if !switch.is_active() {
+                wide_links_switch_clone.set_active(false);
+            }
+        });
+        wide_links_switch
+            .bind_property("active", &wide_links_warning, "visible")
+            .sync_create()
+            .build();
+
+        group.add(&symlinks_expander);
+
+        // Windows attributes expander
+        let attributes_expander = adw::ExpanderRow::new();
+        attributes_expander.set_title(&gettext("Windows Attributes"));
+        attributes_expander.set_subtitle(&gettext(
+            "How archive, hidden, system and read-only flags are kept",
+        ));
+
+        let store_dos_attributes_switch = adw::SwitchRow::new();
+        store_dos_attributes_switch.set_title(&gettext("Store DOS Attributes"));
+        store_dos_attributes_switch.set_subtitle(&gettext(
+            "Save Windows attributes in extended attributes (requires xattr support)",
+        ));
+        store_dos_attributes_switch.set_active(true);
+        attributes_expander.add_row(&store_dos_attributes_switch);
+
+        let map_archive_switch = adw::SwitchRow::new();
+        map_archive_switch.set_title(&gettext("Map Archive"));
+        map_archive_switch.set_subtitle(&gettext("Store the archive flag in the owner execute bit"));
+        map_archive_switch.set_active(true);
+        attributes_expander.add_row(&map_archive_switch);
+
+        let map_hidden_switch = adw::SwitchRow::new();
+        map_hidden_switch.set_title(&gettext("Map Hidden"));
+        map_hidden_switch.set_subtitle(&gettext("Store the hidden flag in the world execute bit"));
+        attributes_expander.add_row(&map_hidden_switch);
+
+        let map_system_switch = adw::SwitchRow::new();
+        map_system_switch.set_title(&gettext("Map System"));
+        map_system_switch.set_subtitle(&gettext("Store the system flag in the group execute bit"));
+        attributes_expander.add_row(&map_system_switch);
+
+        // Samba ignores the map options while attributes are stored in xattrs
+        for map_switch in [&map_archive_switch, &map_hidden_switch, &map_system_switch] {
+            store_dos_attributes_switch
+                .bind_property("active", map_switch, "sensitive")
+                .invert_boolean()
+                .sync_create()
+                .build();
+        }
+
+        group.add(&attributes_expander);
+
+        // Locking expander
+        let locking_expander = adw::ExpanderRow::new();
+        locking_expander.set_title(&gettext("Locking"));
+        locking_expander.set_subtitle(&gettext(
+            "Turn off oplocks for databases or applications that corrupt shared files",
+        ));
+
+        let oplocks_switch = adw::SwitchRow::new();
+        oplocks_switch.set_title(&gettext("Oplocks"));
+        oplocks_switch.set_subtitle(&gettext("Let clients cache files locally"));
+        oplocks_switch.set_active(true);
+        locking_expander.add_row(&oplocks_switch);
+
+        let level2_oplocks_switch = adw::SwitchRow::new();
+        level2_oplocks_switch.set_title(&gettext("Level 2 Oplocks"));
+        level2_oplocks_switch.set_subtitle(&gettext(
+            "Let several clients cache a file for reading at the same time",
+        ));
+        level2_oplocks_switch.set_active(true);
+        locking_expander.add_row(&level2_oplocks_switch);
+
+        let kernel_oplocks_switch = adw::SwitchRow::new();
+        kernel_oplocks_switch.set_title(&gettext("Kernel Oplocks"));
+        kernel_oplocks_switch.set_subtitle(&gettext(
+            "Break oplocks when local programs or NFS access the same files",
+        ));
+        locking_expander.add_row(&kernel_oplocks_switch);
+
+        // Level 2 oplocks are only granted while oplocks are enabled
+        oplocks_switch
+            .bind_property("active", &level2_oplocks_switch, "sensitive")
+            .sync_create()
+            .build();
+
+        group.add(&locking_expander);
+
+        // Spotlight search
+        let spotlight_switch = adw::SwitchRow::new();
+        spotlight_switch.set_title(&gettext("Spotlight Search"));
+        spotlight_switch.set_subtitle(&gettext(
+            "Let macOS clients search this share on the server (requires Elasticsearch)",
+        ));
+        group.add(&spotlight_switch);
+
+        let spotlight_status_row = adw::ActionRow::new();
+        spotlight_status_row.set_title(&gettext("Indexing backend"));
+        spotlight_status_row.set_visible(false);
+        group.add(&spotlight_status_row);
+
+        spotlight_switch.connect_active_notify(move |switch| {
+            spotlight_status_row.set_visible(switch.is_active());
+            if switch.is_active() {
+                check_spotlight_backend(&spotlight_status_row);
+            }
+        });
+
+        // Auditing
+        let audit_switch = adw::SwitchRow::new();
+        audit_switch.set_title(&gettext("Audit Access"));
+        audit_switch.set_subtitle(&gettext(
+            "Log who opens, writes, renames and deletes files (full_audit)",
+        ));
+        group.add(&audit_switch);
+
+        Self {
+            group,
+            encrypt_combo,
+            audit_switch,
+            guest_account_combo,
+            guest_accounts,
+            read_list_entry,
+            write_list_entry,
+            create_mask_entry,
+            directory_mask_entry,
+            force_create_mode_entry,
+            force_directory_mode_entry,
+            inherit_permissions_switch,
+            inherit_acls_switch,
+            follow_symlinks_switch,
+            wide_links_switch,
+            store_dos_attributes_switch,
+            map_archive_switch,
+            map_hidden_switch,
+            map_system_switch,
+            oplocks_switch,
+            level2_oplocks_switch,
+            kernel_oplocks_switch,
+            spotlight_switch,
+        }
+    }
+
+    pub fn group(&self) -> &adw::PreferencesGroup {
+        &self.group
+    }
+
+    /// Only allow choosing a guest account while guest access is enabled
+    pub fn follow_guest_switch(&self, guest_ok_switch: &adw::SwitchRow) {
+        guest_ok_switch
+            .bind_property("active", &self.guest_account_combo, "sensitive")
+            .sync_create()
+            .build();
+    }
+
+    /// Fill the widgets from an existing share
+    pub fn load(&self, share: &SambaShareConfig) {
+        select_security_level(&self.encrypt_combo, share.smb_encrypt);
+        self.audit_switch.set_active(share.audit);
+        let guest_pos = self
+            .guest_accounts
+            .iter()
+            .position(|u| u == &share.guest_account)
+            .unwrap_or(0);
+        self.guest_account_combo.set_selected(guest_pos as u32);
+        self.read_list_entry.set_text(&share.read_list.join(" "));
+        self.write_list_entry.set_text(&share.write_list.join(" "));
+        self.create_mask_entry.set_text(&share.create_mask);
+        self.directory_mask_entry.set_text(&share.directory_mask);
+        self.force_create_mode_entry.set_text(&share.force_create_mode);
+        self.force_directory_mode_entry.set_text(&share.force_directory_mode);
+        self.inherit_permissions_switch.set_active(share.inherit_permissions);
+        self.inherit_acls_switch.set_active(share.inherit_acls);
+        self.follow_symlinks_switch.set_active(share.follow_symlinks);
+        self.wide_links_switch.set_active(share.wide_links);
+        self.store_dos_attributes_switch.set_active(share.store_dos_attributes);
+        self.map_archive_switch.set_active(share.map_archive);
+        self.map_hidden_switch.set_active(share.map_hidden);
+        self.map_system_switch.set_active(share.map_system);
+        self.oplocks_switch.set_active(share.oplocks);
+        self.level2_oplocks_switch.set_active(share.level2_oplocks);
+        self.kernel_oplocks_switch.set_active(share.kernel_oplocks);
+        self.spotlight_switch.set_active(share.spotlight);
+    }
+
+    /// Copy the widget values into a share
+    pub fn apply(&self, share: &mut SambaShareConfig) {
+        share.smb_encrypt = selected_security_level(&self.encrypt_combo);
+        share.audit = self.audit_switch.is_active();
+        share.guest_account = self
+            .guest_accounts
+            .get(self.guest_account_combo.selected() as usize)
+            .cloned()
+            .unwrap_or_default();
+        share.read_list = parse_name_list(&self.read_list_entry.text());
+        share.write_list = parse_name_list(&self.write_list_entry.text());
+        share.create_mask = self.create_mask_entry.text().trim().to_string();
+        share.directory_mask = self.directory_mask_entry.text().trim().to_string();
+        share.force_create_mode = self.force_create_mode_entry.text().trim().to_string();
+        share.force_directory_mode = self.force_directory_mode_entry.text().trim().to_string();
+        share.inherit_permissions = self.inherit_permissions_switch.is_active();
+        share.inherit_acls = self.inherit_acls_switch.is_active();
+        share.follow_symlinks = self.follow_symlinks_switch.is_active();
+        share.wide_links = share.follow_symlinks && self.wide_links_switch.is_active();
+        share.store_dos_attributes = self.store_dos_attributes_switch.is_active();
+        share.map_archive = self.map_archive_switch.is_active();
+        share.map_hidden = self.map_hidden_switch.is_active();
+        share.map_system = self.map_system_switch.is_active();
+        share.oplocks = self.oplocks_switch.is_active();
+        share.level2_oplocks = self.level2_oplocks_switch.is_active();
+        share.kernel_oplocks = self.kernel_oplocks_switch.is_active();
+        share.spotlight = self.spotlight_switch.is_active();
+    }
+}
+
+/// Check in the background that Spotlight's indexing backend is running
+fn check_spotlight_backend(status_row: &adw::ActionRow) {
+    status_row.set_subtitle(&gettext("Checking..."));
+
+    let status_row = status_row.clone();
+    glib::spawn_future_local(async move {
+        let result = gio::spawn_blocking(spotlight::check_backend).await;
+
+        match result {
+            Ok(Ok(())) => {
+                status_row.remove_css_class("warning");
+                status_row.set_subtitle(&gettext("Elasticsearch is running"));
+            }
+            Ok(Err(e)) => {
+                eprintln!("Spotlight backend check failed: {}", e);
+                status_row.add_css_class("warning");
+                status_row.set_subtitle(&format!(
+                    "{}: {}",
+                    gettext("Search will not work until the backend is available"),
+                    e
+                ));
+            }
+            Err(e) => {
+                status_row.set_subtitle(&format!("{}: {:?}", gettext("Error"), e));
+            }
+        }
+    });
+}
+
+/// Create a combo row offering the Off/Desired/Required levels
+pub fn security_level_combo(title: &str, subtitle: &str) -> adw::ComboRow {
+    let labels: Vec<String> = SecurityLevel::ALL
+        .iter()
+        .map(|level| security_level_label(*level))
+        .collect();
+    let list = gtk4::StringList::new(&labels.iter().map(|s| s.as_str()).collect::<Vec<_>>());
+
+    let combo = adw::ComboRow::new();
+    combo.set_title(title);
+    combo.set_subtitle(subtitle);
+    combo.set_model(Some(&list));
+    combo.set_selected(0);
+    combo
+}
+
+/// Read the level selected in a combo created by `security_level_combo`
+pub fn selected_security_level(combo: &adw::ComboRow) -> SecurityLevel {
+    SecurityLevel::ALL
+        .get(combo.selected() as usize)
+        .copied()
+        .unwrap_or_default()
+}
+
+/// Select a level in a combo created by `security_level_combo`
+pub fn select_security_level(combo: &adw::ComboRow, level: SecurityLevel) {
+    if let Some(pos) = SecurityLevel::ALL.iter().position(|l| *l == level) {
+        combo.set_selected(pos as u32);
+    }
+}
+
+/// Text of the item selected in a combo row backed by a StringList
+pub fn selected_string(combo: &adw::ComboRow) -> String {
+    combo
+        .model()
+        .and_then(|model| model.downcast::<gtk4::StringList>().ok())
+        .and_then(|list| list.string(combo.selected()))
+        .map(|s| s.to_string())
+        .unwrap_or_default()
+}
+
+/// Select `value` in a combo row backed by a StringList, if it is listed
+pub fn select_string(combo: &adw::ComboRow, value: &str) {
+    let position = combo
+        .model()
+        .and_then(|model| model.downcast::<gtk4::StringList>().ok())
+        .and_then(|list| (0..list.n_items()).find(|&i| list.string(i).as_deref() == Some(value)));
+    if let Some(position) = position {
+        combo.set_selected(position);
+    }
+}
+
+/// Create an entry row for a space-separated list of users and groups
+fn name_list_entry(title: &str) -> adw::EntryRow {
+    let entry = adw::EntryRow::new();
+    entry.set_title(title);
+    entry.set_input_hints(gtk4::InputHints::NO_SPELLCHECK);
+    entry
+}
+
+/// Create an entry row for an octal mode, with Samba's default shown as a tooltip
+fn mode_entry(title: &str, samba_default: &str) -> adw::EntryRow {
+    let entry = adw::EntryRow::new();
+    entry.set_title(title);
+    entry.set_input_purpose(gtk4::InputPurpose::Digits);
+    entry.set_tooltip_text(Some(&format!(
+        "{} {}",
+        gettext("Octal mode, leave empty for Samba's default:"),
+        samba_default
+    )));
+    entry
+}
+
+fn security_level_label(level: SecurityLevel) -> String {
+    match level {
+        SecurityLevel::Default => gettext("System default"),
+        SecurityLevel::Off => gettext("Off"),
+        SecurityLevel::Desired => gettext("Desired"),
+        SecurityLevel::Required => gettext("Required"),
+    }
+}
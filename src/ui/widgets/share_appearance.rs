@@ -0,0 +1,163 @@
+use crate::config::{AppConfig, Favorite, ShareAppearance};
+use crate::models::AppState;
+use gettextrs::gettext;
+use gtk4::gdk;
+use gtk4::prelude::*;
+
+/// Icons offered for shares, after the default one of their kind
+const ICONS: [&str; 9] = [
+    "folder-documents-symbolic",
+    "folder-pictures-symbolic",
+    "folder-music-symbolic",
+    "folder-videos-symbolic",
+    "folder-download-symbolic",
+    "folder-publicshare-symbolic",
+    "user-home-symbolic",
+    "drive-harddisk-symbolic",
+    "network-server-symbolic",
+];
+
+/// Colors offered for shares, named after the GNOME palette
+const COLORS: [&str; 7] = ["blue", "green", "yellow", "orange", "red", "purple", "brown"];
+
+/// Style classes coloring the share icons, one per color of `COLORS`
+const COLOR_CSS: &str = "
+.share-color-blue { color: @blue_3; }
+.share-color-green { color: @green_4; }
+.share-color-yellow { color: @yellow_5; }
+.share-color-orange { color: @orange_3; }
+.share-color-red { color: @red_3; }
+.share-color-purple { color: @purple_3; }
+.share-color-brown { color: @brown_3; }
+";
+
+/// Load the style classes of the share colors, once when the app starts
+pub fn load_share_colors() {
+    let Some(display) = gdk::Display::default() else {
+        return;
+    };
+    let provider = gtk4::CssProvider::new();
+    provider.load_from_data(COLOR_CSS);
+    gtk4::style_context_add_provider_for_display(&display, &provider, gtk4::STYLE_PROVIDER_PRIORITY_APPLICATION);
+}
+
+/// Icon of a share with the icon and color chosen for it, `default_icon` otherwise
+pub fn share_icon(share: &Favorite, default_icon: &str) -> gtk4::Image {
+    let image = gtk4::Image::new();
+    show_appearance(&image, &AppConfig::new().share_appearance(share), default_icon);
+    image
+}
+
+/// Icon of a share opening a popover to pick another icon and color
+pub fn appearance_button(share: Favorite, default_icon: &'static str) -> gtk4::MenuButton {
+    let image = share_icon(&share, default_icon);
+    let button = gtk4::MenuButton::new();
+    button.set_child(Some(&image));
+    button.set_valign(gtk4::Align::Center);
+    button.add_css_class("flat");
+    button.set_tooltip_text(Some(&gettext("Change Icon and Color")));
+
+    let content = gtk4::Box::new(gtk4::Orientation::Vertical, 6);
+    content.set_margin_top(6);
+    content.set_margin_bottom(6);
+    content.set_margin_start(6);
+    content.set_margin_end(6);
+
+    let icon_label = gtk4::Label::new(Some(&gettext("Icon")));
+    icon_label.add_css_class("heading");
+    icon_label.set_halign(gtk4::Align::Start);
+    content.append(&icon_label);
+
+    let icons = gtk4::Grid::new();
+    icons.set_row_spacing(6);
+    icons.set_column_spacing(6);
+    let choices = std::iter::once(None).chain(ICONS.iter().map(|icon| Some(*icon)));
+    for (index, icon) in choices.enumerate() {
+        let choice = gtk4::Button::from_icon_name(icon.unwrap_or(default_icon));
+        choice.add_css_class("flat");
+        if icon.is_none() {
+            choice.set_tooltip_text(Some(&gettext("Default")));
+        }
+
+        let share = share.clone();
+        let image = image.clone();
+        choice.connect_clicked(move |_| {
+            update_appearance(&share, &image, default_icon, |appearance| {
+                appearance.icon = icon.map(|icon| icon.to_string());
+            });
+        });
+        icons.attach(&choice, (index % 5) as i32, (index / 5) as i32, 1, 1);
+    }
+    content.append(&icons);
+
+    let color_label = gtk4::Label::new(Some(&gettext("Color")));
+    color_label.add_css_class("heading");
+    color_label.set_halign(gtk4::Align::Start);
+    content.append(&color_label);
+
+    let colors = gtk4::Box::new(gtk4::Orientation::Horizontal, 6);
+    for color in std::iter::once(None).chain(COLORS.iter().map(|color| Some(*color))) {
+        let swatch = gtk4::Image::from_icon_name("media-record-symbolic");
+        let choice = gtk4::Button::new();
+        choice.set_child(Some(&swatch));
+        choice.add_css_class("flat");
+        match color {
+            Some(color) => {
+                swatch.add_css_class(&format!("share-color-{}", color));
+                choice.set_tooltip_text(Some(&color_name(color)));
+            }
+            None => choice.set_tooltip_text(Some(&gettext("Default"))),
+        }
+
+        let share = share.clone();
+        let image = image.clone();
+        choice.connect_clicked(move |_| {
+            update_appearance(&share, &image, default_icon, |appearance| {
+                appearance.color = color.map(|color| color.to_string());
+            });
+        });
+        colors.append(&choice);
+    }
+    content.append(&colors);
+
+    let popover = gtk4::Popover::new();
+    popover.set_child(Some(&content));
+    button.set_popover(Some(&popover));
+    button
+}
+
+/// Change part of the appearance of a share and show it on `image`
+fn update_appearance(
+    share: &Favorite,
+    image: &gtk4::Image,
+    default_icon: &str,
+    change: impl Fn(&mut ShareAppearance),
+) {
+    let mut appearance = AppConfig::new().share_appearance(share);
+    change(&mut appearance);
+    show_appearance(image, &appearance, default_icon);
+    AppState::get().set_share_appearance(share, &appearance);
+}
+
+fn show_appearance(image: &gtk4::Image, appearance: &ShareAppearance, default_icon: &str) {
+    image.set_icon_name(Some(appearance.icon.as_deref().unwrap_or(default_icon)));
+    for color in COLORS {
+        image.remove_css_class(&format!("share-color-{}", color));
+    }
+    if let Some(color) = &appearance.color {
+        image.add_css_class(&format!("share-color-{}", color));
+    }
+}
+
+fn color_name(color: &str) -> String {
+    match color {
+        "blue" => gettext("Blue"),
+        "green" => gettext("Green"),
+        "yellow" => gettext("Yellow"),
+        "orange" => gettext("Orange"),
+        "red" => gettext("Red"),
+        "purple" => gettext("Purple"),
+        "brown" => gettext("Brown"),
+        _ => color.to_string(),
+    }
+}
@@ -0,0 +1,83 @@
+use crate::samba::drift::{ActiveConfig, Drift};
+use crate::samba::{SambaShareConfig, ShareState};
+use gettextrs::gettext;
+use gtk4::prelude::*;
+
+/// Small labels summing up a share: guest access, read-only, hidden and whether
+/// the running Samba configuration matches it. `active` is None when it can't be read.
+pub fn share_badges(share: &SambaShareConfig, active: Option<&ActiveConfig>) -> Vec<gtk4::Label> {
+    let mut badges = Vec::new();
+
+    if share.guest_ok {
+        badges.push(badge(
+            &gettext("Guest Access"),
+            "accent",
+            &gettext("Anyone on the network can connect without a password"),
+        ));
+    }
+    if share.read_only {
+        badges.push(badge(
+            &gettext("Read Only Share"),
+            "dim-label",
+            &gettext("Clients can open files but not change them"),
+        ));
+    }
+    if !share.browsable {
+        badges.push(badge(
+            &gettext("Hidden"),
+            "dim-label",
+            &gettext("Not listed when browsing the server, clients need its name"),
+        ));
+    }
+
+    match active.and_then(|active| active.drift(share)) {
+        Some(Drift::NotApplied) => badges.push(badge(
+            &gettext("Not Applied"),
+            "warning",
+            &gettext("Samba doesn't serve this share yet, rebuild the system to apply it"),
+        )),
+        Some(Drift::Differs(settings)) => badges.push(badge(
+            &gettext("Out of Sync"),
+            "warning",
+            &format!(
+                "{} {}",
+                gettext("Samba runs with other values for these settings until the next rebuild:"),
+                settings.join(", ")
+            ),
+        )),
+        None => {}
+    }
+
+    badges
+}
+
+/// Label telling whether a remote share comes from the configuration, so editing it
+/// changes an entry, or was only mounted on this computer
+pub fn remote_share_badge(state: ShareState) -> gtk4::Label {
+    match state {
+        ShareState::Mounted => badge(
+            &gettext("In Configuration"),
+            "success",
+            &gettext("Declared in the NixOS configuration and mounted"),
+        ),
+        ShareState::NotMounted => badge(
+            &gettext("In Configuration"),
+            "dim-label",
+            &gettext("Declared in the NixOS configuration, not mounted now"),
+        ),
+        ShareState::Unmanaged => badge(
+            &gettext("Not in Configuration"),
+            "warning",
+            &gettext("Mounted outside the NixOS configuration, it won't be mounted again after a restart"),
+        ),
+    }
+}
+
+fn badge(label: &str, css_class: &str, tooltip: &str) -> gtk4::Label {
+    let badge = gtk4::Label::new(Some(label));
+    badge.add_css_class("caption");
+    badge.add_css_class(css_class);
+    badge.set_valign(gtk4::Align::Center);
+    badge.set_tooltip_text(Some(tooltip));
+    badge
+}
@@ -0,0 +1,15 @@
+use crate::config::{AppConfig, Favorite};
+use gettextrs::gettext;
+use libadwaita as adw;
+use libadwaita::prelude::*;
+
+/// Row holding a free note about a share, saved once applied
+pub fn notes_row(share: Favorite) -> adw::EntryRow {
+    let row = adw::EntryRow::new();
+    row.set_title(&gettext("Notes"));
+    row.set_text(&AppConfig::new().share_note(&share));
+    row.set_show_apply_button(true);
+    row.set_tooltip_text(Some(&gettext("Kept by this app only, the NixOS configuration isn't changed")));
+    row.connect_apply(move |row| AppConfig::new().set_share_note(&share, &row.text()));
+    row
+}
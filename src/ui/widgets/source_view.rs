@@ -0,0 +1,40 @@
+use gtk4::prelude::*;
+use libadwaita as adw;
+use sourceview5::prelude::*;
+
+/// Read-only view of `text` highlighted with a GtkSourceView language
+/// (`"nix"`, `"diff"`, ...). The style scheme follows the light/dark app theme.
+pub fn source_view(text: &str, language: &str) -> sourceview5::View {
+    let buffer = sourceview5::Buffer::new(None);
+    if let Some(lang) = sourceview5::LanguageManager::default().language(language) {
+        buffer.set_language(Some(&lang));
+        buffer.set_highlight_syntax(true);
+    }
+    buffer.set_text(text);
+
+    let style_manager = adw::StyleManager::default();
+    apply_style_scheme(&buffer, style_manager.is_dark());
+    let buffer_weak = buffer.downgrade();
+    style_manager.connect_dark_notify(move |manager| {
+        if let Some(buffer) = buffer_weak.upgrade() {
+            apply_style_scheme(&buffer, manager.is_dark());
+        }
+    });
+
+    let view = sourceview5::View::with_buffer(&buffer);
+    view.set_editable(false);
+    view.set_monospace(true);
+    view.set_show_line_numbers(language != "diff");
+    view.set_left_margin(12);
+    view.set_right_margin(12);
+    view.set_top_margin(12);
+    view.set_bottom_margin(12);
+    view
+}
+
+fn apply_style_scheme(buffer: &sourceview5::Buffer, dark: bool) {
+    let scheme_id = if dark { "Adwaita-dark" } else { "Adwaita" };
+    if let Some(scheme) = sourceview5::StyleSchemeManager::default().scheme(scheme_id) {
+        buffer.set_style_scheme(Some(&scheme));
+    }
+}
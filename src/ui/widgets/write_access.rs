@@ -0,0 +1,78 @@
+use crate::models::AppState;
+use gettextrs::gettext;
+use gtk4::prelude::*;
+use libadwaita as adw;
+use libadwaita::prelude::*;
+
+/// Why nothing can be changed while the app is in read-only mode
+pub fn read_only_explanation() -> String {
+    gettext(
+        "The configuration can be viewed but not changed: no way to gain administrator rights was found. \
+         On NixOS, enable security.polkit.enable = true and rebuild.",
+    )
+}
+
+/// Show a lock on `button` while the app is in read-only mode. The button stays
+/// clickable so `allow_write` can explain why and offer to authenticate.
+pub fn lock_when_read_only(button: &gtk4::Button) {
+    let label = button.label().map(|label| label.to_string()).unwrap_or_default();
+    // Set before the lock, given back once writing is possible
+    let tooltip = button.tooltip_text();
+
+    // Weak, so the button can still be destroyed and disconnect the handler
+    let update = {
+        let button = button.downgrade();
+        move |state: &AppState| {
+            let Some(button) = button.upgrade() else {
+                return;
+            };
+            if state.is_read_only() {
+                let content = adw::ButtonContent::new();
+                content.set_icon_name("changes-prevent-symbolic");
+                content.set_label(&label);
+                button.set_child(Some(&content));
+                button.set_tooltip_text(Some(&read_only_explanation()));
+            } else {
+                button.set_label(&label);
+                button.set_tooltip_text(tooltip.as_deref());
+            }
+        }
+    };
+
+    let state = AppState::get();
+    update(&state);
+    let handler = state.connect_read_only_changed(update);
+    state.disconnect_on_destroy(button, handler);
+}
+
+/// Whether a change can be attempted. In read-only mode, explain why not
+/// and offer to retry with authentication instead.
+pub fn allow_write(parent: &impl IsA<gtk4::Widget>) -> bool {
+    if !AppState::get().is_read_only() {
+        return true;
+    }
+
+    let alert = adw::AlertDialog::new(Some(&gettext("Read-Only Mode")), Some(&read_only_explanation()));
+    alert.add_responses(&[("close", &gettext("Close")), ("retry", &gettext("Retry with Authentication"))]);
+    alert.set_response_appearance("retry", adw::ResponseAppearance::Suggested);
+    alert.set_default_response(Some("retry"));
+    alert.set_close_response("close");
+
+    let parent_clone = parent.upcast_ref::<gtk4::Widget>().clone();
+    alert.connect_response(Some("retry"), move |_, _| retry_with_authentication(&parent_clone));
+    alert.present(Some(parent));
+    false
+}
+
+/// Ask for authentication once, reporting the error in an alert when it fails
+pub fn retry_with_authentication(parent: &impl IsA<gtk4::Widget>) {
+    let parent = parent.upcast_ref::<gtk4::Widget>().clone();
+    AppState::get().retry_write_access(move |result| {
+        if let Err(e) = result {
+            eprintln!("Authentication failed: {}", e);
+            let alert = adw::AlertDialog::new(Some(&gettext("Authentication Failed")), Some(&e));
+            alert.add_response("close", &gettext("Close"));
+            alert.present(Some(&parent));
+        }
+    });
+}
@@ -1,15 +1,29 @@
 use crate::config::AppConfig;
-use crate::ui::dialogs::{AddShareDialog, ListSharesDialog,RemoteListSharesDialog, WelcomeDialog,AddRemoteShareDialog};
+use crate::models::AppState;
+use crate::samba::command_runner::command;
+use crate::samba::demo;
+use crate::samba::diagnostics::build_report;
+use crate::samba::paths::config_path;
+use crate::samba::reapply_mount;
+use crate::samba::rebuild::{terminate, Rebuild, RebuildPhase};
+use crate::samba::report::build_html_report;
+use crate::samba::root::is_root;
+use crate::samba::sandbox::shared_temp_dir;
+use crate::samba::sudo_write::read_file;
+use crate::samba::terminal::open_in_terminal;
+use crate::ui::dialogs::{AddShareDialog, ListSharesDialog,RemoteListSharesDialog, WelcomeDialog,AddRemoteShareDialog,GlobalSettingsDialog,PrivateShareWizard,TeamShareWizard,LogViewerDialog,PreferencesDialog,RebuildOutputDialog,SambaUsersDialog,ConfigAuditDialog,NetworkDialog};
+use crate::ui::widgets::{allow_write, retry_with_authentication, FavoritesGroup, OverviewGroup};
+use crate::utils::format::format_elapsed;
 use gettextrs::gettext;
 use gtk4::prelude::*;
 use gtk4::{gio, glib};
 use libadwaita as adw;
 use libadwaita::prelude::*;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::fs;
-use std::path::PathBuf;
-use std::process::Command;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::time::Instant;
 
 pub struct SambaShareManagerWindow {
     window: adw::ApplicationWindow,
@@ -18,6 +32,7 @@ pub struct SambaShareManagerWindow {
     must_save: Rc<RefCell<bool>>,
     rebuild_banner: adw::Banner,
     rebuild_error_banner: adw::Banner,
+    rebuild_log: Rc<RefCell<String>>,
     toast_overlay: adw::ToastOverlay,
 }
 
@@ -46,19 +61,70 @@ impl SambaShareManagerWindow {
 
         // Create header bar
         let header_bar = adw::HeaderBar::new();
+        let preferences_button = gtk4::Button::from_icon_name("preferences-system-symbolic");
+        preferences_button.set_tooltip_text(Some(&gettext("Preferences")));
+        header_bar.pack_end(&preferences_button);
         toolbar_view.add_top_bar(&header_bar);
 
         // Create banners
         let rebuild_banner = adw::Banner::new(&gettext("Rebuilding NixOS configuration..."));
+        rebuild_banner.set_use_markup(false);
         rebuild_banner.set_revealed(false);
 
         let rebuild_error_banner = adw::Banner::new(&gettext("Failed to rebuild NixOS configuration"));
         rebuild_error_banner.set_revealed(false);
+        rebuild_error_banner.set_use_markup(false);
+        rebuild_error_banner.set_button_label(Some(&gettext("Show Output")));
         rebuild_error_banner.add_css_class("error");
 
+        // Output of the last rebuild, shown from the error banner
+        let rebuild_log = Rc::new(RefCell::new(String::new()));
+        let window_clone_for_output = window.clone();
+        let rebuild_log_clone = rebuild_log.clone();
+        rebuild_error_banner.connect_button_clicked(move |_| {
+            let dialog = RebuildOutputDialog::new(&rebuild_log_clone.borrow());
+            dialog.present(Some(&window_clone_for_output));
+        });
+
         toolbar_view.add_top_bar(&rebuild_banner);
         toolbar_view.add_top_bar(&rebuild_error_banner);
 
+        // Read-only banner, shown when the configuration can't be written
+        let read_only_banner = adw::Banner::new(&gettext("Read-only mode: the configuration can be viewed but not changed"));
+        read_only_banner.set_use_markup(false);
+        read_only_banner.set_button_label(Some(&gettext("Retry with Authentication")));
+        read_only_banner.connect_button_clicked(retry_with_authentication);
+        toolbar_view.add_top_bar(&read_only_banner);
+
+        let state = AppState::get();
+        let read_only_banner_clone = read_only_banner.clone();
+        state.connect_read_only_changed(move |state| {
+            read_only_banner_clone.set_revealed(state.is_read_only());
+        });
+        state.check_write_access();
+
+        // Root banner, since GTK can't reach the session's theme and settings from there
+        if is_root() {
+            let root_banner = adw::Banner::new(&gettext(
+                "Running as root: your theme and desktop settings may not apply. Start the app as a normal user, it asks for authentication when needed.",
+            ));
+            root_banner.set_use_markup(false);
+            root_banner.set_revealed(true);
+            toolbar_view.add_top_bar(&root_banner);
+        }
+
+        // Demo mode banner, so nobody mistakes the fake shares for real ones
+        if demo::is_enabled() {
+            let demo_banner = adw::Banner::new(&format!(
+                "{} {}",
+                gettext("Demo mode: changes are saved to"),
+                config_path()
+            ));
+            demo_banner.set_use_markup(false);
+            demo_banner.set_revealed(true);
+            toolbar_view.add_top_bar(&demo_banner);
+        }
+
         // Create toast overlay for notifications
         let toast_overlay = adw::ToastOverlay::new();
 
@@ -103,20 +169,20 @@ impl SambaShareManagerWindow {
 
         content_box.append(&header_box);
 
+        // ============ Overview Section ============
+        let overview = OverviewGroup::new();
+        content_box.append(overview.group());
+        overview.refresh();
+
+        // ============ Favorites Section ============
+        let favorites = FavoritesGroup::new(&toast_overlay);
+        content_box.append(favorites.group());
+
         // ============ Local Shares Section ============
         let local_group = adw::PreferencesGroup::new();
         local_group.set_title(&gettext("Local Shares"));
         local_group.set_description(Some(&gettext("Share folders from this computer on the network")));
 
-        // List local shares row
-        let list_local_row = adw::ActionRow::new();
-        list_local_row.set_title(&gettext("Manage Local Shares"));
-        list_local_row.set_subtitle(&gettext("View, edit, and delete existing shares"));
-        list_local_row.set_activatable(true);
-        list_local_row.add_prefix(&gtk4::Image::from_icon_name("folder-symbolic"));
-        list_local_row.add_suffix(&gtk4::Image::from_icon_name("go-next-symbolic"));
-        local_group.add(&list_local_row);
-
         // Add local share row
         let add_local_row = adw::ActionRow::new();
         add_local_row.set_title(&gettext("Add New Share"));
@@ -126,6 +192,42 @@ impl SambaShareManagerWindow {
         add_local_row.add_suffix(&gtk4::Image::from_icon_name("go-next-symbolic"));
         local_group.add(&add_local_row);
 
+        // Private share wizard row
+        let private_share_row = adw::ActionRow::new();
+        private_share_row.set_title(&gettext("Private Share for a Person"));
+        private_share_row.set_subtitle(&gettext("Create a user, their Samba password and their own folder"));
+        private_share_row.set_activatable(true);
+        private_share_row.add_prefix(&gtk4::Image::from_icon_name("system-users-symbolic"));
+        private_share_row.add_suffix(&gtk4::Image::from_icon_name("go-next-symbolic"));
+        local_group.add(&private_share_row);
+
+        // Team share wizard row
+        let team_share_row = adw::ActionRow::new();
+        team_share_row.set_title(&gettext("Shared Folder for a Team"));
+        team_share_row.set_subtitle(&gettext("Create a group, add its members and give them a common folder"));
+        team_share_row.set_activatable(true);
+        team_share_row.add_prefix(&gtk4::Image::from_icon_name("folder-publicshare-symbolic"));
+        team_share_row.add_suffix(&gtk4::Image::from_icon_name("go-next-symbolic"));
+        local_group.add(&team_share_row);
+
+        // Samba users row
+        let samba_users_row = adw::ActionRow::new();
+        samba_users_row.set_title(&gettext("Samba Users"));
+        samba_users_row.set_subtitle(&gettext("Change the passwords people use to connect"));
+        samba_users_row.set_activatable(true);
+        samba_users_row.add_prefix(&gtk4::Image::from_icon_name("dialog-password-symbolic"));
+        samba_users_row.add_suffix(&gtk4::Image::from_icon_name("go-next-symbolic"));
+        local_group.add(&samba_users_row);
+
+        // Server settings row
+        let server_settings_row = adw::ActionRow::new();
+        server_settings_row.set_title(&gettext("Server Settings"));
+        server_settings_row.set_subtitle(&gettext("Encryption, signing and other global options"));
+        server_settings_row.set_activatable(true);
+        server_settings_row.add_prefix(&gtk4::Image::from_icon_name("emblem-system-symbolic"));
+        server_settings_row.add_suffix(&gtk4::Image::from_icon_name("go-next-symbolic"));
+        local_group.add(&server_settings_row);
+
         content_box.append(&local_group);
 
         // ============ Remote Shares Section ============
@@ -133,15 +235,6 @@ impl SambaShareManagerWindow {
         remote_group.set_title(&gettext("Remote Shares"));
         remote_group.set_description(Some(&gettext("Connect to shares on other computers")));
 
-        // List remote shares row
-        let list_remote_row = adw::ActionRow::new();
-        list_remote_row.set_title(&gettext("Manage Remote Shares"));
-        list_remote_row.set_subtitle(&gettext("View, edit, mount, and unmount remote shares"));
-        list_remote_row.set_activatable(true);
-        list_remote_row.add_prefix(&gtk4::Image::from_icon_name("network-server-symbolic"));
-        list_remote_row.add_suffix(&gtk4::Image::from_icon_name("go-next-symbolic"));
-        remote_group.add(&list_remote_row);
-
         // Add remote share row
         let add_remote_row = adw::ActionRow::new();
         add_remote_row.set_title(&gettext("Add New Remote Share"));
@@ -151,6 +244,15 @@ impl SambaShareManagerWindow {
         add_remote_row.add_suffix(&gtk4::Image::from_icon_name("go-next-symbolic"));
         remote_group.add(&add_remote_row);
 
+        // Network browsing row
+        let network_row = adw::ActionRow::new();
+        network_row.set_title(&gettext("Browse Network"));
+        network_row.set_subtitle(&gettext("Computers and shares found on the local network"));
+        network_row.set_activatable(true);
+        network_row.add_prefix(&gtk4::Image::from_icon_name("network-workgroup-symbolic"));
+        network_row.add_suffix(&gtk4::Image::from_icon_name("go-next-symbolic"));
+        remote_group.add(&network_row);
+
         content_box.append(&remote_group);
 
         // ============ Info Section ============
@@ -158,11 +260,38 @@ impl SambaShareManagerWindow {
 
         let info_row = adw::ActionRow::new();
         info_row.set_title(&gettext("About NixOS Integration"));
-        info_row.set_subtitle(&gettext("Changes are saved to /etc/nixos/customConfig/default.nix"));
+        info_row.set_subtitle(&gettext("Changes are saved to {file}").replace("{file}", config_path()));
         info_row.add_prefix(&gtk4::Image::from_icon_name("dialog-information-symbolic"));
         info_row.set_activatable(false);
         info_group.add(&info_row);
 
+        // Existing configuration row
+        let audit_row = adw::ActionRow::new();
+        audit_row.set_title(&gettext("Existing Configuration"));
+        audit_row.set_subtitle(&gettext("What this app can and can't change in your configuration"));
+        audit_row.set_activatable(true);
+        audit_row.add_prefix(&gtk4::Image::from_icon_name("system-search-symbolic"));
+        audit_row.add_suffix(&gtk4::Image::from_icon_name("go-next-symbolic"));
+        info_group.add(&audit_row);
+
+        // Diagnostics export row
+        let diagnostics_row = adw::ActionRow::new();
+        diagnostics_row.set_title(&gettext("Export Diagnostics"));
+        diagnostics_row.set_subtitle(&gettext("Save checks, configuration and logs to attach to a bug report"));
+        diagnostics_row.set_activatable(true);
+        diagnostics_row.add_prefix(&gtk4::Image::from_icon_name("document-save-symbolic"));
+        diagnostics_row.add_suffix(&gtk4::Image::from_icon_name("go-next-symbolic"));
+        info_group.add(&diagnostics_row);
+
+        // Report export row
+        let report_row = adw::ActionRow::new();
+        report_row.set_title(&gettext("Export Report"));
+        report_row.set_subtitle(&gettext("Save an HTML summary of all shares for documentation or handover"));
+        report_row.set_activatable(true);
+        report_row.add_prefix(&gtk4::Image::from_icon_name("x-office-document-symbolic"));
+        report_row.add_suffix(&gtk4::Image::from_icon_name("go-next-symbolic"));
+        info_group.add(&report_row);
+
         content_box.append(&info_group);
 
         // Assemble the layout
@@ -172,38 +301,127 @@ impl SambaShareManagerWindow {
         toolbar_view.set_content(Some(&toast_overlay));
 
         // Create references for button handlers
-        let list_shares_button = list_local_row.clone();
         let setup_share_button = add_local_row.clone();
-        let remote_list_shares_button = list_remote_row.clone();
         let remote_setup_share_button = add_remote_row.clone();
 
         // Connect row activated signals
-        // Local shares
+        // Overview counters lead to the page they count
         let window_clone_for_list = window.clone();
-        list_shares_button.connect_activated(move |_| {
+        overview.local_row.connect_activated(move |_| {
             let dialog = ListSharesDialog::new();
             dialog.present(Some(&window_clone_for_list));
         });
 
+        for row in [&overview.remote_row, &overview.mounted_row] {
+            let window_clone_for_remote_list = window.clone();
+            row.connect_activated(move |_| {
+                let dialog = RemoteListSharesDialog::new();
+                dialog.present(Some(&window_clone_for_remote_list));
+            });
+        }
+
+        let window_clone_for_failures = window.clone();
+        let overview_clone = overview.clone();
+        overview.failures_row.connect_activated(move |_| {
+            if overview_clone.overview().local_error.is_some() {
+                let dialog = ListSharesDialog::new();
+                dialog.present(Some(&window_clone_for_failures));
+            } else {
+                let dialog = RemoteListSharesDialog::new();
+                dialog.present(Some(&window_clone_for_failures));
+            }
+        });
+
+        let window_clone_for_activity = window.clone();
+        overview.activity_row.connect_activated(move |_| {
+            let dialog = LogViewerDialog::new(None);
+            dialog.present(Some(&window_clone_for_activity));
+        });
+
+        // Local shares
+
         let window_clone_for_setup = window.clone();
-        setup_share_button.connect_activated(move |_| {
+        setup_share_button.connect_activated(move |row| {
+            if !allow_write(row) {
+                return;
+            }
             let dialog = AddShareDialog::new();
             dialog.present(Some(&window_clone_for_setup));
         });
 
-        // Remote shares
-        let window_clone_for_remote_list = window.clone();
-        remote_list_shares_button.connect_activated(move |_| {
-            let dialog = RemoteListSharesDialog::new();
-            dialog.present(Some(&window_clone_for_remote_list));
+        let window_clone_for_wizard = window.clone();
+        private_share_row.connect_activated(move |row| {
+            if !allow_write(row) {
+                return;
+            }
+            let dialog = PrivateShareWizard::new();
+            dialog.present(Some(&window_clone_for_wizard));
+        });
+
+        let window_clone_for_team = window.clone();
+        team_share_row.connect_activated(move |row| {
+            if !allow_write(row) {
+                return;
+            }
+            let dialog = TeamShareWizard::new();
+            dialog.present(Some(&window_clone_for_team));
         });
 
+        let window_clone_for_users = window.clone();
+        samba_users_row.connect_activated(move |_| {
+            let dialog = SambaUsersDialog::new();
+            dialog.present(Some(&window_clone_for_users));
+        });
+
+        let window_clone_for_settings = window.clone();
+        server_settings_row.connect_activated(move |row| {
+            if !allow_write(row) {
+                return;
+            }
+            let dialog = GlobalSettingsDialog::new();
+            dialog.present(Some(&window_clone_for_settings));
+        });
+
+        let window_clone_for_audit = window.clone();
+        audit_row.connect_activated(move |_| {
+            let dialog = ConfigAuditDialog::new();
+            dialog.present(Some(&window_clone_for_audit));
+        });
+
+        let window_clone_for_diagnostics = window.clone();
+        let toast_overlay_for_diagnostics = toast_overlay.clone();
+        diagnostics_row.connect_activated(move |_| {
+            Self::export_diagnostics(&window_clone_for_diagnostics, &toast_overlay_for_diagnostics);
+        });
+
+        let window_clone_for_report = window.clone();
+        let toast_overlay_for_report = toast_overlay.clone();
+        report_row.connect_activated(move |_| {
+            Self::export_report(&window_clone_for_report, &toast_overlay_for_report);
+        });
+
+        let window_clone_for_preferences = window.clone();
+        preferences_button.connect_clicked(move |_| {
+            let dialog = PreferencesDialog::new();
+            dialog.present(Some(&window_clone_for_preferences));
+        });
+
+        // Remote shares
         let window_clone_for_remote_setup = window.clone();
-        remote_setup_share_button.connect_activated(move |_| {
+        remote_setup_share_button.connect_activated(move |row| {
+            if !allow_write(row) {
+                return;
+            }
             let dialog = AddRemoteShareDialog::new();
             dialog.present(Some(&window_clone_for_remote_setup));
         });
 
+        let window_clone_for_network = window.clone();
+        network_row.connect_activated(move |_| {
+            let dialog = NetworkDialog::new();
+            dialog.present(Some(&window_clone_for_network));
+        });
+
         window.set_content(Some(&toolbar_view));
 
         let window_rc = Rc::new(Self {
@@ -213,17 +431,34 @@ impl SambaShareManagerWindow {
             must_save,
             rebuild_banner,
             rebuild_error_banner,
+            rebuild_log,
             toast_overlay: toast_overlay.clone(),
         });
 
         // Fix minimization bug with pkexec: force redraw when window is shown
         let content_box_clone = content_box.clone();
-        window.connect_is_active_notify(move |_| {
+        window.connect_is_active_notify(move |window| {
             // Force queue a resize and redraw when window becomes active
             content_box_clone.queue_resize();
             content_box_clone.queue_draw();
+
+            // Counters may have changed in a dialog that just closed
+            if window.is_active() {
+                overview.refresh();
+            }
         });
 
+        // On first launch, tell what the app will and won't touch in the existing configuration
+        let show_audit = AppConfig::new().should_show_config_audit();
+        let window_clone_for_first_audit = window.clone();
+        let present_audit = move || {
+            if show_audit {
+                AppConfig::new().set_config_audit_shown();
+                let dialog = ConfigAuditDialog::new();
+                dialog.present(Some(&window_clone_for_first_audit));
+            }
+        };
+
         // Show welcome dialog only if not skipping
         if !skip_welcome {
             let welcome = Rc::new(WelcomeDialog::new());
@@ -235,19 +470,24 @@ impl SambaShareManagerWindow {
                     let app_config = AppConfig::new();
                     app_config.set_hide_welcome(true);
                 }
+                present_audit();
             });
 
             welcome.present(Some(&window));
+        } else {
+            present_audit();
         }
 
         window_rc
     }
 
     fn do_save_config(
+        window: &adw::ApplicationWindow,
         config_file: &PathBuf,
         hardware_config: &Rc<RefCell<String>>,
         rebuild_banner: &adw::Banner,
         rebuild_error_banner: &adw::Banner,
+        rebuild_log: &Rc<RefCell<String>>,
         must_save: &Rc<RefCell<bool>>,
         on_rebuild_complete: Option<Rc<dyn Fn()>>,
     ) {
@@ -265,29 +505,245 @@ impl SambaShareManagerWindow {
 
         eprintln!("File written successfully");
 
+        rebuild_error_banner.set_title(&gettext("Failed to rebuild NixOS configuration"));
         rebuild_error_banner.set_revealed(false);
         rebuild_banner.set_revealed(true);
 
-        // Run nixos-rebuild in background
+        // Run nixos-rebuild in background, following its output
+        let rebuild = match Rebuild::start() {
+            Ok(rebuild) => rebuild,
+            Err(e) => {
+                eprintln!("{}", e);
+                rebuild_banner.set_revealed(false);
+                rebuild_error_banner.set_revealed(true);
+                return;
+            }
+        };
+        rebuild_log.borrow_mut().clear();
+
+        // Show the phase and elapsed time, so a long build doesn't look like a hang
+        let phase = Rc::new(Cell::new(RebuildPhase::Evaluating));
+        let running = Rc::new(Cell::new(true));
+        let started = Instant::now();
+        Self::update_rebuild_banner(rebuild_banner, phase.get(), started);
+
+        let rebuild_banner_clone = rebuild_banner.clone();
+        let phase_clone = phase.clone();
+        let running_clone = running.clone();
+        glib::timeout_add_seconds_local(1, move || {
+            if !running_clone.get() {
+                return glib::ControlFlow::Break;
+            }
+            Self::update_rebuild_banner(&rebuild_banner_clone, phase_clone.get(), started);
+            glib::ControlFlow::Continue
+        });
+
+        // Cancel button, as long as the process group can be signalled
+        let cancelled = Rc::new(Cell::new(false));
+        let pid = rebuild.pid();
+        rebuild_banner.set_button_label(pid.as_ref().map(|_| gettext("Cancel")).as_deref());
+        let cancelled_clone = cancelled.clone();
+        let cancel_handler = rebuild_banner.connect_button_clicked(move |banner| {
+            let Some(pid) = pid.clone() else {
+                return;
+            };
+            // Only ask once
+            banner.set_button_label(None);
+            cancelled_clone.set(true);
+
+            glib::spawn_future_local(async move {
+                match gio::spawn_blocking(move || terminate(&pid)).await {
+                    Ok(Ok(())) => eprintln!("Rebuild cancelled"),
+                    Ok(Err(e)) => eprintln!("Failed to cancel rebuild: {}", e),
+                    Err(e) => eprintln!("Failed to cancel rebuild: {:?}", e),
+                }
+            });
+        });
+
+        let window = window.clone();
         let rebuild_banner = rebuild_banner.clone();
         let rebuild_error_banner = rebuild_error_banner.clone();
         let _must_save = must_save.clone();
         let hardware_config_for_reload = hardware_config.clone();
         let config_file_for_reload = config_file.clone();
+        let rebuild_log = rebuild_log.clone();
 
         glib::spawn_future_local(async move {
             eprintln!("Launching nixos-rebuild switch...");
-            let result = gio::spawn_blocking(|| {
-                // Create a temporary wrapper script for rebuild
-                let timestamp = std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .unwrap()
-                    .as_secs();
-                let wrapper_path = format!("/tmp/samba_share_rebuild_{}.sh", timestamp);
-                let status_file = format!("/tmp/samba_share_rebuild_{}.done", timestamp);
-
-                let script_content = format!(
-                    r#"#!/usr/bin/env bash
+            let rebuild_log_clone = rebuild_log.clone();
+            let rebuild_banner_clone = rebuild_banner.clone();
+            let result = rebuild
+                .supervise(move |line| {
+                    eprintln!("[nixos-rebuild] {}", line);
+                    let mut log = rebuild_log_clone.borrow_mut();
+                    log.push_str(line);
+                    log.push('\n');
+
+                    if let Some(new_phase) = RebuildPhase::from_line(line) {
+                        if new_phase != phase.get() {
+                            phase.set(new_phase);
+                            Self::update_rebuild_banner(&rebuild_banner_clone, new_phase, started);
+                        }
+                        // Stopping halfway through activation could leave services down
+                        if new_phase == RebuildPhase::Activating {
+                            rebuild_banner_clone.set_button_label(None);
+                        }
+                    }
+                })
+                .await;
+
+            running.set(false);
+            rebuild_banner.disconnect(cancel_handler);
+            rebuild_banner.set_button_label(None);
+            rebuild_banner.set_revealed(false);
+            eprintln!("Rebuild took {}", format_elapsed(started.elapsed()));
+
+            match result {
+                Ok(()) => {
+                    eprintln!("Rebuild completed");
+                    AppState::get().clear_pending_changes();
+
+                    // Reload hardware config from file (it was updated by the rebuild)
+                    eprintln!("Reloading config from: {}", config_file_for_reload.display());
+                    let updated_config = read_file(&config_file_for_reload).unwrap_or_else(|e| {
+                        eprintln!("Error reading config: {}", e);
+                        hardware_config_for_reload.borrow().clone()
+                    });
+                    *hardware_config_for_reload.borrow_mut() = updated_config;
+
+                    // Call the refresh callback if provided
+                    if let Some(ref callback) = on_rebuild_complete {
+                        eprintln!("Refreshing interface after rebuild");
+                        callback();
+                    }
+                }
+                Err(_) if cancelled.get() => {
+                    eprintln!("Rebuild cancelled, the configuration is saved but not applied");
+                    rebuild_error_banner.set_title(&gettext("Rebuild cancelled, changes are saved but not applied yet"));
+                    rebuild_error_banner.set_revealed(true);
+                }
+                Err(e) => {
+                    eprintln!("Rebuild failed: {}", e);
+                    rebuild_error_banner.set_title(&format!(
+                        "{}: {}",
+                        gettext("Failed to rebuild NixOS configuration"),
+                        e
+                    ));
+                    rebuild_error_banner.set_revealed(true);
+
+                    // Without a polkit agent, a terminal can still ask for the password
+                    if rebuild.authorization_unavailable() {
+                        Self::offer_terminal_rebuild(&window);
+                    }
+                }
+            }
+        });
+    }
+
+    fn update_rebuild_banner(banner: &adw::Banner, phase: RebuildPhase, started: Instant) {
+        let phase_label = match phase {
+            RebuildPhase::Evaluating => gettext("Evaluating"),
+            RebuildPhase::Downloading => gettext("Downloading"),
+            RebuildPhase::Building => gettext("Building"),
+            RebuildPhase::Activating => gettext("Activating"),
+        };
+        banner.set_title(&format!(
+            "{}: {} ({})",
+            gettext("Rebuilding NixOS configuration"),
+            phase_label,
+            format_elapsed(started.elapsed())
+        ));
+    }
+
+    /// Offer to remount the shares edited while mounted, which keep their previous
+    /// options until they are mounted again
+    fn offer_remount(toast_overlay: &adw::ToastOverlay) {
+        let mount_points = AppState::get().take_pending_remounts();
+        if mount_points.is_empty() {
+            return;
+        }
+
+        let toast = adw::Toast::new(&gettext("Mounted shares still use their previous options"));
+        toast.set_button_label(Some(&gettext("Remount Now")));
+        toast.set_timeout(0);
+        let toast_overlay_clone = toast_overlay.clone();
+        toast.connect_button_clicked(move |_| {
+            let mount_points = mount_points.clone();
+            let toast_overlay = toast_overlay_clone.clone();
+            glib::spawn_future_local(async move {
+                let failures = gio::spawn_blocking(move || {
+                    mount_points
+                        .iter()
+                        .filter_map(|mount_point| {
+                            reapply_mount(Path::new(mount_point))
+                                .err()
+                                .map(|e| format!("{} ({})", mount_point, e))
+                        })
+                        .collect::<Vec<String>>()
+                })
+                .await
+                .unwrap_or_else(|e| vec![format!("{:?}", e)]);
+
+                let message = if failures.is_empty() {
+                    gettext("Shares remounted with their new options")
+                } else {
+                    eprintln!("Failed to remount shares: {}", failures.join(", "));
+                    format!("{}: {}", gettext("Remount failed"), failures.join(", "))
+                };
+                toast_overlay.add_toast(adw::Toast::new(&message));
+                AppState::get().reload_remote();
+            });
+        });
+        toast_overlay.add_toast(toast);
+    }
+
+    /// Offer to run the rebuild in a terminal when no password prompt could be shown
+    fn offer_terminal_rebuild(window: &adw::ApplicationWindow) {
+        let dialog = adw::AlertDialog::new(
+            Some(&gettext("Administrator Rights Unavailable")),
+            Some(&gettext("No authentication agent answered. Run nixos-rebuild in a terminal instead? Its progress won't be followed by the app.")),
+        );
+        dialog.add_responses(&[
+            ("cancel", &gettext("Cancel")),
+            ("terminal", &gettext("Open Terminal")),
+        ]);
+        dialog.set_response_appearance("terminal", adw::ResponseAppearance::Suggested);
+        dialog.set_default_response(Some("terminal"));
+        dialog.set_close_response("cancel");
+
+        let window_clone = window.clone();
+        dialog.connect_response(Some("terminal"), move |_, _| {
+            let window = window_clone.clone();
+            glib::spawn_future_local(async move {
+                let result = gio::spawn_blocking(Self::open_rebuild_terminal)
+                    .await
+                    .unwrap_or_else(|e| {
+                        eprintln!("Error opening terminal: {:?}", e);
+                        Err(Vec::new())
+                    });
+                if let Err(tried) = result {
+                    Self::show_no_terminal_dialog(&window, &tried);
+                }
+            });
+        });
+
+        dialog.present(Some(window));
+    }
+
+    /// Write a rebuild script and open it in the preferred terminal.
+    /// Returns the commands tried when no terminal could be started.
+    fn open_rebuild_terminal() -> Result<(), Vec<String>> {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        // The terminal runs on the host, so the script must be visible there
+        let wrapper_path = shared_temp_dir()
+            .join(format!("samba_share_rebuild_{}.sh", timestamp))
+            .to_string_lossy()
+            .to_string();
+
+        let script_content = r#"#!/usr/bin/env bash
 
 echo "======================================"
 echo "  REBUILDING CONFIGURATION"
@@ -298,139 +754,157 @@ echo ""
 sudo -E nixos-rebuild switch
 EXIT_CODE=$?
 
+echo ""
+echo "======================================"
 if [ $EXIT_CODE -eq 0 ]; then
-    echo ""
-    echo "======================================"
     echo "  ✅ REBUILD COMPLETED SUCCESSFULLY"
-    echo "======================================"
-
-    # Signal completion
-    touch {}
 else
-    echo ""
-    echo "======================================"
     echo "  ❌ REBUILD FAILED"
-    echo "======================================"
 fi
+echo "======================================"
+
+# The script removes itself once the terminal doesn't need it anymore
+rm -f "$0"
 
 echo ""
 echo "Press Enter or close this window..."
 read -t 300 || true
-"#,
-                    status_file
-                );
-
-                if let Err(e) = std::fs::write(&wrapper_path, script_content) {
-                    eprintln!("Error: unable to write rebuild script: {}", e);
-                    return (false, status_file.clone(), wrapper_path.clone());
-                }
+"#;
 
-                if let Err(e) = Command::new("chmod").arg("+x").arg(&wrapper_path).status() {
-                    eprintln!("Error chmod: {}", e);
-                    let _ = std::fs::remove_file(&wrapper_path);
-                    return (false, status_file.clone(), wrapper_path.clone());
-                }
+        if let Err(e) = std::fs::write(&wrapper_path, script_content) {
+            eprintln!("Error: unable to write rebuild script: {}", e);
+            return Err(Vec::new());
+        }
 
-                // Try multiple terminals in order of preference
-                let terminals: Vec<(&str, Vec<&str>)> = vec![
-                    ("kgx", vec!["--", &wrapper_path]), // GNOME Console
-                    ("gnome-terminal", vec!["--", &wrapper_path]),
-                    ("konsole", vec!["-e", &wrapper_path]),
-                    ("xfce4-terminal", vec!["-e", &wrapper_path]),
-                    ("alacritty", vec!["-e", &wrapper_path]),
-                    ("kitty", vec![&wrapper_path]),
-                    ("xterm", vec!["-e", &wrapper_path]),
-                ];
-
-                for (term, args) in terminals {
-                    eprintln!("Trying {}...", term);
-                    if Command::new(term).args(&args).spawn().is_ok() {
-                        eprintln!("Terminal {} opened successfully", term);
-                        return (true, status_file, wrapper_path);
-                    }
-                }
+        if let Err(e) = command("chmod").arg("+x").arg(&wrapper_path).status() {
+            eprintln!("Error chmod: {}", e);
+            let _ = std::fs::remove_file(&wrapper_path);
+            return Err(Vec::new());
+        }
 
+        // Use the terminal from the preferences, or try the known ones in order
+        let terminal = AppConfig::new().terminal_command();
+        match open_in_terminal(terminal.as_deref(), &wrapper_path) {
+            Ok(command_line) => {
+                eprintln!("Terminal opened successfully: {}", command_line);
+                Ok(())
+            }
+            Err(tried) => {
                 eprintln!("No terminal found to execute nixos-rebuild");
                 let _ = std::fs::remove_file(&wrapper_path);
-                (false, status_file, wrapper_path)
-            })
-            .await
-            .unwrap_or((false, String::new(), String::new()));
-
-            let (terminal_opened, status_file_path, script_path) = result;
+                Err(tried)
+            }
+        }
+    }
 
-            if !terminal_opened {
-                rebuild_banner.set_revealed(false);
-                rebuild_error_banner.set_revealed(true);
-            } else {
-                // Start watching for completion
-                let rebuild_banner_watch = rebuild_banner.clone();
-                let rebuild_error_banner_watch = rebuild_error_banner.clone();
-                let hardware_config_watch = hardware_config_for_reload.clone();
-                let on_rebuild_complete_watch = on_rebuild_complete.clone();
-                let config_file_watch = config_file_for_reload.clone();
-                let check_count = Rc::new(RefCell::new(0u32));
-
-                glib::timeout_add_local(std::time::Duration::from_secs(2), move || {
-                    *check_count.borrow_mut() += 1;
-                    let count = *check_count.borrow();
-
-                    // Check if status file exists
-                    if std::path::Path::new(&status_file_path).exists() {
-                        eprintln!("Rebuild completed detected!");
-
-                        // Reload hardware config from file (it was updated by the rebuild)
-                        eprintln!("Reloading config from: {}", config_file_watch.display());
-                        let updated_config = std::fs::read_to_string(&config_file_watch)
-                            .unwrap_or_else(|e| {
-                                eprintln!("Error reading config: {}", e);
-                                hardware_config_watch.borrow().clone()
-                            });
-
-                        // Update the config in memory
-                        *hardware_config_watch.borrow_mut() = updated_config.clone();
-                        eprintln!("Config in memory updated");
-
-                        // Call the refresh callback if provided
-                        if let Some(ref callback) = on_rebuild_complete_watch {
-                            eprintln!("Refreshing interface after rebuild");
-                            callback();
-                        }
+    /// Explain that no terminal could be opened for the rebuild, and offer to pick one
+    fn show_no_terminal_dialog(window: &adw::ApplicationWindow, tried: &[String]) {
+        let dialog = adw::AlertDialog::new(
+            Some(&gettext("No Terminal Found")),
+            Some(&format!(
+                "{}\n\n{}",
+                gettext("The configuration was saved, but no terminal could be opened to run nixos-rebuild. Tried:"),
+                tried.join("\n")
+            )),
+        );
+        dialog.add_responses(&[
+            ("close", &gettext("Close")),
+            ("preferences", &gettext("Choose Terminal")),
+        ]);
+        dialog.set_response_appearance("preferences", adw::ResponseAppearance::Suggested);
+        dialog.set_default_response(Some("preferences"));
+        dialog.set_close_response("close");
+
+        let window_clone = window.clone();
+        dialog.connect_response(Some("preferences"), move |_, _| {
+            let preferences = PreferencesDialog::new();
+            preferences.present(Some(&window_clone));
+        });
 
-                        // Hide banner
-                        rebuild_banner_watch.set_revealed(false);
+        dialog.present(Some(window));
+    }
 
-                        // Clean up
-                        let _ = std::fs::remove_file(&status_file_path);
-                        let _ = std::fs::remove_file(&script_path);
+    /// Ask where to save the diagnostics report, then build and write it in the background
+    fn export_diagnostics(window: &adw::ApplicationWindow, toast_overlay: &adw::ToastOverlay) {
+        Self::export_file(
+            window,
+            toast_overlay,
+            &gettext("Export Diagnostics"),
+            "samba-share-diagnostics.txt",
+            build_report,
+            gettext("Diagnostics saved"),
+            gettext("Failed to export diagnostics"),
+        );
+    }
 
-                        return glib::ControlFlow::Break;
-                    }
+    /// Ask where to save the HTML report of all shares, then build and write it in the background
+    fn export_report(window: &adw::ApplicationWindow, toast_overlay: &adw::ToastOverlay) {
+        Self::export_file(
+            window,
+            toast_overlay,
+            &gettext("Export Report"),
+            "samba-shares-report.html",
+            build_html_report,
+            gettext("Report saved"),
+            gettext("Failed to export the report"),
+        );
+    }
 
-                    // Stop after 10 minutes (300 checks * 2 seconds)
-                    if count > 300 {
-                        eprintln!("Rebuild watcher timeout");
-                        rebuild_banner_watch.set_revealed(false);
-                        let _ = std::fs::remove_file(&script_path);
-                        return glib::ControlFlow::Break;
+    /// Ask where to save a file, then write what `build` returns there in the background
+    fn export_file(
+        window: &adw::ApplicationWindow,
+        toast_overlay: &adw::ToastOverlay,
+        title: &str,
+        initial_name: &str,
+        build: fn() -> String,
+        saved_message: String,
+        failed_message: String,
+    ) {
+        let file_dialog = gtk4::FileDialog::new();
+        file_dialog.set_title(title);
+        file_dialog.set_initial_name(Some(initial_name));
+
+        let toast_overlay = toast_overlay.clone();
+        file_dialog.save(Some(window), None::<&gio::Cancellable>, move |result| {
+            // Cancelled by the user
+            let Some(path) = result.ok().and_then(|file| file.path()) else {
+                return;
+            };
+
+            glib::spawn_future_local(async move {
+                let result = gio::spawn_blocking(move || match fs::write(&path, build()) {
+                    Ok(_) => Ok(path),
+                    Err(e) => Err(format!("Failed to write {}: {}", path.display(), e)),
+                })
+                .await;
+
+                let message = match result {
+                    Ok(Ok(path)) => format!("{}: {}", saved_message, path.display()),
+                    Ok(Err(e)) => {
+                        eprintln!("{}: {}", failed_message, e);
+                        format!("{}: {}", failed_message, e)
                     }
-
-                    glib::ControlFlow::Continue
-                });
-            }
+                    Err(e) => format!("{}: {:?}", gettext("Error"), e),
+                };
+                toast_overlay.add_toast(adw::Toast::new(&message));
+            });
         });
     }
 
     pub fn save_config(&self) {
+        let toast_overlay = self.toast_overlay.clone();
         let refresh_callback = Rc::new(move || {
             eprintln!("Refresh callback called");
+            Self::offer_remount(&toast_overlay);
         });
 
         Self::do_save_config(
+            &self.window,
             &self.config_file,
             &self.hardware_config,
             &self.rebuild_banner,
             &self.rebuild_error_banner,
+            &self.rebuild_log,
             &self.must_save,
             Some(refresh_callback),
         );
@@ -443,4 +917,4 @@ read -t 300 || true
     pub fn gtk_window(&self) -> &adw::ApplicationWindow {
         &self.window
     }
-}
\ No newline at end of file
+}
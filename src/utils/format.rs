@@ -0,0 +1,72 @@
+use gettextrs::gettext;
+use std::time::Duration;
+
+/// "120.0 GB", in decimal units with the number format of the locale
+pub fn format_size(bytes: u64) -> String {
+    glib::format_size(bytes).to_string()
+}
+
+/// "12.5 MB/s"
+pub fn format_rate(bytes_per_second: f64) -> String {
+    gettext("{size}/s").replace("{size}", &format_size(bytes_per_second.max(0.0) as u64))
+}
+
+/// "2 h", "5 min" or "45 s", in the largest unit the duration is a whole number of
+pub fn format_duration(seconds: u32) -> String {
+    if seconds > 0 && seconds.is_multiple_of(3600) {
+        gettext("{count} h").replace("{count}", &(seconds / 3600).to_string())
+    } else if seconds > 0 && seconds.is_multiple_of(60) {
+        gettext("{count} min").replace("{count}", &(seconds / 60).to_string())
+    } else {
+        gettext("{count} s").replace("{count}", &seconds.to_string())
+    }
+}
+
+/// Duration of a timeout such as the idle one, "Never" when there is none
+pub fn format_timeout(timeout: Option<u32>) -> String {
+    match timeout {
+        Some(seconds) => format_duration(seconds),
+        None => gettext("Never"),
+    }
+}
+
+/// Time spent on a running task as m:ss
+pub fn format_elapsed(elapsed: Duration) -> String {
+    let seconds = elapsed.as_secs();
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// "125 ms", for the steps of a check
+pub fn format_milliseconds(duration: Duration) -> String {
+    gettext("{count} ms").replace("{count}", &duration.as_millis().to_string())
+}
+
+/// Local date and time of a Unix timestamp in the format of the locale, empty when out of range
+pub fn format_timestamp(timestamp: i64) -> String {
+    glib::DateTime::from_unix_local(timestamp)
+        .and_then(|time| time.format("%x %X"))
+        .map(|time| time.to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(7200), "2 h");
+        assert_eq!(format_duration(300), "5 min");
+        assert_eq!(format_duration(90), "90 s");
+        assert_eq!(format_duration(0), "0 s");
+        assert_eq!(format_timeout(Some(3600)), "1 h");
+        assert_eq!(format_timeout(None), "Never");
+    }
+
+    #[test]
+    fn test_format_elapsed() {
+        assert_eq!(format_elapsed(Duration::from_secs(187)), "3:07");
+        assert_eq!(format_elapsed(Duration::from_millis(900)), "0:00");
+        assert_eq!(format_milliseconds(Duration::from_micros(125_400)), "125 ms");
+    }
+}
@@ -0,0 +1,78 @@
+use gettextrs::{setlocale, LocaleCategory};
+use once_cell::sync::OnceCell;
+use std::env;
+
+/// Variables selecting the language of messages, highest precedence first
+const LOCALE_VARIABLES: [&str; 3] = ["LC_ALL", "LC_MESSAGES", "LANG"];
+
+/// Locales tried in turn when the requested one isn't installed
+const FALLBACK_LOCALES: [&str; 2] = ["C.UTF-8", "C"];
+
+static ACTIVE_LOCALE: OnceCell<String> = OnceCell::new();
+
+/// Locale requested by the environment, `None` when no variable is set
+pub fn system_locale() -> Option<String> {
+    locale_from(|name| env::var(name).ok())
+}
+
+/// First non-empty locale variable; libc ignores empty ones too
+fn locale_from(var: impl Fn(&str) -> Option<String>) -> Option<String> {
+    LOCALE_VARIABLES
+        .iter()
+        .filter_map(|name| var(name))
+        .find(|value| !value.is_empty())
+}
+
+/// Apply the requested locale to the whole process, or the first fallback that works.
+/// Returns the locale applied.
+pub fn setup_locale() -> String {
+    let requested = system_locale();
+    let candidates = requested.iter().map(String::as_str).chain(FALLBACK_LOCALES);
+
+    let mut applied = FALLBACK_LOCALES[1].to_string();
+    for candidate in candidates {
+        if setlocale(LocaleCategory::LcAll, candidate).is_some() {
+            applied = candidate.to_string();
+            break;
+        }
+        eprintln!("Locale {} isn't available", candidate);
+    }
+
+    let _ = ACTIVE_LOCALE.set(applied.clone());
+    applied
+}
+
+/// Locale applied by `setup_locale`, `None` before it ran
+pub fn active_locale() -> Option<&'static str> {
+    ACTIVE_LOCALE.get().map(|locale| locale.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locale_precedence() {
+        let vars = |set: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                set.iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+
+        assert_eq!(
+            locale_from(vars(&[("LANG", "en_US.UTF-8"), ("LC_ALL", "fr_FR.UTF-8")])),
+            Some("fr_FR.UTF-8".to_string())
+        );
+        assert_eq!(
+            locale_from(vars(&[("LANG", "en_US.UTF-8"), ("LC_MESSAGES", "de_DE.UTF-8")])),
+            Some("de_DE.UTF-8".to_string())
+        );
+        assert_eq!(
+            locale_from(vars(&[("LANG", "en_US.UTF-8"), ("LC_ALL", "")])),
+            Some("en_US.UTF-8".to_string())
+        );
+        assert_eq!(locale_from(vars(&[])), None);
+    }
+}
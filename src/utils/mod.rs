@@ -1 +1,3 @@
-// Utils module - for Samba share utilities
\ No newline at end of file
+// Utils module - for Samba share utilities
+pub mod format;
+pub mod locale;